@@ -0,0 +1,219 @@
+//! Resolving `host:port` strings into socket addresses.
+//!
+//! [`std::net::ToSocketAddrs`] hands name resolution to the system's blocking
+//! `getaddrinfo`, which ignores the servers and options configured for this crate.
+//! [`resolve_socket_addrs`] accepts the same `host:port` syntax but looks the host up
+//! itself, querying AAAA and A records at the same time, and returns the addresses
+//! ready to connect to in the preferred order.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::addrs::{AddressOrder, resolve_socket_addrs};
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::{Ipv4Addr, TcpStream};
+//!
+//! let addrs = resolve_socket_addrs(
+//!     "example.com:443",
+//!     AddressOrder::default(),
+//!     Ipv4Addr::new(8, 8, 8, 8),
+//!     &QueryOptions::default(),
+//! )?;
+//! let stream = TcpStream::connect(&addrs[..])?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+
+use crate::dns::{QueryType, ResponseCode};
+use crate::resolver::{DnsError, QueryOptions, resolve_many};
+
+/// The order in which IPv6 and IPv4 addresses of a host are returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressOrder {
+    /// Alternate between the families, starting with IPv6, as recommended by
+    /// Happy Eyeballs (RFC 8305) so that a broken family costs one attempt at most.
+    #[default]
+    Interleaved,
+    /// All IPv6 addresses, then all IPv4 addresses (the RFC 6724 default policy).
+    Ipv6First,
+    /// All IPv4 addresses, then all IPv6 addresses.
+    Ipv4First,
+    /// Only IPv6 addresses.
+    Ipv6Only,
+    /// Only IPv4 addresses.
+    Ipv4Only,
+}
+
+/// Resolves a `host:port` string into the socket addresses to connect to.
+///
+/// The host may be a name, an IPv4 address, or an IPv6 address in brackets
+/// (`[2001:db8::1]:443`). Addresses are returned without any lookup; names are
+/// resolved by querying AAAA and A records concurrently. Within each family the
+/// server's order is kept.
+///
+/// # Arguments
+///
+/// * `target` - The host and port, separated by a colon
+/// * `order` - How to order the IPv6 and IPv4 addresses of the host
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+/// * `options` - Settings shared by both queries
+///
+/// # Returns
+///
+/// * `Ok(Vec<SocketAddr>)` - At least one address to connect to
+/// * `Err(DnsError)` - If the input is invalid or no address could be found
+///
+/// # Errors
+///
+/// Returns [`DnsError::Io`] with [`io::ErrorKind::InvalidInput`] if `target` has no
+/// valid port, [`DnsError::ServerReturnedError`] if the name does not exist, and
+/// [`DnsError::Io`] with [`io::ErrorKind::NotFound`] if it has no addresses of the
+/// requested families. If both lookups fail for another reason, the error of the
+/// first one is returned.
+pub fn resolve_socket_addrs(
+    target: &str,
+    order: AddressOrder,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<Vec<SocketAddr>, DnsError> {
+    let (host, port) = split_host_port(target)
+        .map_err(|e| DnsError::Io(io::Error::new(io::ErrorKind::InvalidInput, e)))?;
+    if let Ok(addr) = host.parse::<IpAddr>() {
+        return Ok(vec![SocketAddr::new(addr, port)]);
+    }
+
+    let query_types: &[QueryType] = match order {
+        AddressOrder::Ipv6Only => &[QueryType::AAAA],
+        AddressOrder::Ipv4Only => &[QueryType::A],
+        _ => &[QueryType::AAAA, QueryType::A],
+    };
+    let mut ipv6 = Vec::new();
+    let mut ipv4 = Vec::new();
+    let mut first_error = None;
+    for result in resolve_many(host, query_types, dns_server_addr, options) {
+        match result {
+            // Address lookups follow CNAMEs, so only keep the address records.
+            Ok(message) => {
+                for record in &message.answers {
+                    ipv6.extend(record.get_ipv6_address().map(IpAddr::V6));
+                    ipv4.extend(record.get_ipv4_address().map(IpAddr::V4));
+                }
+            }
+            // NXDOMAIN is final and applies to both families.
+            Err(DnsError::ServerReturnedError(ResponseCode::NameError)) => {
+                return Err(DnsError::ServerReturnedError(ResponseCode::NameError));
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+
+    let addrs: Vec<SocketAddr> = order_addresses(ipv6, ipv4, order)
+        .into_iter()
+        .map(|addr| SocketAddr::new(addr, port))
+        .collect();
+    match (addrs.is_empty(), first_error) {
+        (false, _) => Ok(addrs),
+        (true, Some(e)) => Err(e),
+        (true, None) => Err(DnsError::Io(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no addresses found for {}", host),
+        ))),
+    }
+}
+
+/// Splits `host:port` into the host and the port number.
+///
+/// IPv6 addresses must be enclosed in brackets, and the brackets are removed.
+fn split_host_port(target: &str) -> Result<(&str, u16), String> {
+    let (host, port) = target
+        .rsplit_once(':')
+        .ok_or_else(|| format!("'{}' has no port (expected host:port)", target))?;
+    let host = match host.strip_prefix('[') {
+        Some(rest) => rest
+            .strip_suffix(']')
+            .ok_or_else(|| format!("'{}' has an unclosed bracket", target))?,
+        None if host.contains(':') => {
+            return Err(format!(
+                "IPv6 address in '{}' must be enclosed in brackets",
+                target
+            ));
+        }
+        None => host,
+    };
+    if host.is_empty() {
+        return Err(format!("'{}' has no host", target));
+    }
+    let port = port
+        .parse::<u16>()
+        .map_err(|_| format!("Invalid port '{}'", port))?;
+    Ok((host, port))
+}
+
+/// Arranges the addresses of each family according to `order`.
+pub(crate) fn order_addresses(
+    ipv6: Vec<IpAddr>,
+    ipv4: Vec<IpAddr>,
+    order: AddressOrder,
+) -> Vec<IpAddr> {
+    match order {
+        AddressOrder::Interleaved => {
+            let mut ordered = Vec::with_capacity(ipv6.len() + ipv4.len());
+            let mut ipv6 = ipv6.into_iter();
+            let mut ipv4 = ipv4.into_iter();
+            loop {
+                match (ipv6.next(), ipv4.next()) {
+                    (None, None) => break ordered,
+                    (v6, v4) => ordered.extend(v6.into_iter().chain(v4)),
+                }
+            }
+        }
+        AddressOrder::Ipv6First | AddressOrder::Ipv6Only => ipv6.into_iter().chain(ipv4).collect(),
+        AddressOrder::Ipv4First | AddressOrder::Ipv4Only => ipv4.into_iter().chain(ipv6).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_host_port() {
+        assert_eq!(split_host_port("example.com:443"), Ok(("example.com", 443)));
+        assert_eq!(split_host_port("[2001:db8::1]:53"), Ok(("2001:db8::1", 53)));
+        assert!(split_host_port("example.com").is_err());
+        assert!(split_host_port("example.com:https").is_err());
+        assert!(split_host_port("2001:db8::1:53").is_err());
+        assert!(split_host_port(":80").is_err());
+    }
+
+    #[test]
+    fn test_address_ordering() {
+        let v6: Vec<IpAddr> = vec![
+            "2001:db8::1".parse().unwrap(),
+            "2001:db8::2".parse().unwrap(),
+        ];
+        let v4: Vec<IpAddr> = vec!["192.0.2.1".parse().unwrap()];
+
+        let interleaved = order_addresses(v6.clone(), v4.clone(), AddressOrder::Interleaved);
+        assert_eq!(interleaved, vec![v6[0], v4[0], v6[1]]);
+        let ipv4_first = order_addresses(v6.clone(), v4.clone(), AddressOrder::Ipv4First);
+        assert_eq!(ipv4_first, vec![v4[0], v6[0], v6[1]]);
+    }
+
+    #[test]
+    fn test_address_literals_skip_lookup() {
+        // No query is sent, so the unroutable server is never contacted.
+        let addrs = resolve_socket_addrs(
+            "[::1]:8080",
+            AddressOrder::Ipv4Only,
+            Ipv4Addr::UNSPECIFIED,
+            &QueryOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(addrs, vec!["[::1]:8080".parse().unwrap()]);
+    }
+}
@@ -0,0 +1,230 @@
+//! IP-to-ASN lookups through the Team Cymru DNS interface.
+//!
+//! Team Cymru publishes routing origin data as TXT records, which makes origin lookups
+//! a pure-DNS operation. An address is reversed into labels under
+//! `origin.asn.cymru.com` (IPv4) or `origin6.asn.cymru.com` (IPv6), and the answer
+//! is a pipe-separated line describing the originating AS and the announced prefix:
+//!
+//! ```text
+//! 206.187.250.142.origin.asn.cymru.com. TXT "15169 | 142.250.0.0/15 | US | arin | 2012-05-24"
+//! ```
+//!
+//! A second TXT query for `AS<number>.asn.cymru.com` returns the registered AS name:
+//!
+//! ```text
+//! AS15169.asn.cymru.com. TXT "15169 | US | arin | 2000-03-30 | GOOGLE, US"
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::asn::lookup_asn;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! let origins = lookup_asn(
+//!     "142.250.187.206".parse().unwrap(),
+//!     Ipv4Addr::new(8, 8, 8, 8),
+//!     &QueryOptions::default(),
+//! )?;
+//! for origin in origins {
+//!     println!("AS{} {} {:?}", origin.asn, origin.prefix, origin.as_name);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::dns::{QueryType, reverse_address_labels};
+use crate::resolver::{DnsError, QueryOptions, resolve_with_options};
+
+/// Routing origin information for an IP address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsnInfo {
+    /// The number of the autonomous system originating the prefix.
+    pub asn: u32,
+    /// The announced prefix covering the address, in CIDR notation.
+    pub prefix: String,
+    /// The two-letter country code of the allocation.
+    pub country: String,
+    /// The regional internet registry that made the allocation (e.g. `arin`).
+    pub registry: String,
+    /// The allocation date as published, usually `YYYY-MM-DD` (may be empty).
+    pub allocated: String,
+    /// The registered name of the AS, if the name lookup succeeded.
+    pub as_name: Option<String>,
+}
+
+/// Builds the Team Cymru origin query name for an address.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::asn::origin_query_name;
+///
+/// assert_eq!(
+///     origin_query_name("142.250.187.206".parse().unwrap()),
+///     "206.187.250.142.origin.asn.cymru.com"
+/// );
+/// ```
+pub fn origin_query_name(addr: IpAddr) -> String {
+    let suffix = match addr {
+        IpAddr::V4(_) => "origin.asn.cymru.com",
+        IpAddr::V6(_) => "origin6.asn.cymru.com",
+    };
+    format!("{}.{}", reverse_address_labels(&addr), suffix)
+}
+
+/// Parses an origin TXT record into one [`AsnInfo`] per originating AS.
+///
+/// Prefixes announced by more than one AS (multi-origin) list all of them
+/// space-separated in the first field, so a single record may yield several entries.
+/// The `as_name` of every entry is left as `None`.
+///
+/// # Errors
+///
+/// Returns an error if the record does not have the expected five fields or the
+/// AS numbers are not numeric.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::asn::parse_origin_txt;
+///
+/// let origins = parse_origin_txt("15169 | 142.250.0.0/15 | US | arin | 2012-05-24").unwrap();
+/// assert_eq!(origins[0].asn, 15169);
+/// assert_eq!(origins[0].prefix, "142.250.0.0/15");
+/// ```
+pub fn parse_origin_txt(txt: &str) -> Result<Vec<AsnInfo>, String> {
+    let fields: Vec<&str> = txt.split('|').map(str::trim).collect();
+    if fields.len() != 5 {
+        return Err(format!("Unexpected origin record format: \"{}\"", txt));
+    }
+
+    fields[0]
+        .split_whitespace()
+        .map(|asn| {
+            let asn = asn
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid AS number '{}' in \"{}\"", asn, txt))?;
+            Ok(AsnInfo {
+                asn,
+                prefix: fields[1].to_string(),
+                country: fields[2].to_string(),
+                registry: fields[3].to_string(),
+                allocated: fields[4].to_string(),
+                as_name: None,
+            })
+        })
+        .collect()
+}
+
+/// Extracts the AS name from an `AS<number>.asn.cymru.com` TXT record.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::asn::parse_as_name_txt;
+///
+/// assert_eq!(
+///     parse_as_name_txt("15169 | US | arin | 2000-03-30 | GOOGLE, US"),
+///     Some("GOOGLE, US".to_string())
+/// );
+/// ```
+pub fn parse_as_name_txt(txt: &str) -> Option<String> {
+    // The name is the last field and may itself contain commas, but never pipes.
+    let fields: Vec<&str> = txt.split('|').map(str::trim).collect();
+    match fields.as_slice() {
+        [_, _, _, _, name] if !name.is_empty() => Some(name.to_string()),
+        _ => None,
+    }
+}
+
+/// Looks up the originating AS (or ASes) for an IP address, including AS names.
+///
+/// This issues the origin TXT query for `addr`, then one name query per distinct
+/// AS found. A failed name query leaves `as_name` as `None` rather than failing the
+/// whole lookup.
+///
+/// # Arguments
+///
+/// * `addr` - The IPv4 or IPv6 address to look up
+/// * `dns_server_addr` - The recursive resolver to send the queries to
+/// * `options` - Socket options passed through to each query
+///
+/// # Errors
+///
+/// Returns the [`DnsError`] of the origin query, which is
+/// [`DnsError::ServerReturnedError`] with NXDOMAIN when the address is not
+/// routed, or [`DnsError::InvalidResponse`] if the origin record is malformed.
+pub fn lookup_asn(
+    addr: IpAddr,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<Vec<AsnInfo>, DnsError> {
+    let response = resolve_with_options(
+        &origin_query_name(addr),
+        QueryType::TXT,
+        dns_server_addr,
+        options,
+    )?;
+
+    let mut origins = Vec::new();
+    for txt in response.answers.iter().filter_map(|r| r.get_txt_data()) {
+        origins.extend(parse_origin_txt(txt).map_err(DnsError::InvalidResponse)?);
+    }
+
+    for index in 0..origins.len() {
+        // Reuse a name already fetched for the same AS announced elsewhere.
+        let known = origins[..index]
+            .iter()
+            .find(|o| o.asn == origins[index].asn)
+            .map(|o| o.as_name.clone());
+        origins[index].as_name = match known {
+            Some(name) => name,
+            None => lookup_as_name(origins[index].asn, dns_server_addr, options),
+        };
+    }
+
+    Ok(origins)
+}
+
+/// Fetches the registered name of an AS, or `None` if it cannot be determined.
+fn lookup_as_name(asn: u32, dns_server_addr: Ipv4Addr, options: &QueryOptions) -> Option<String> {
+    let name = format!("AS{}.asn.cymru.com", asn);
+    let response = resolve_with_options(&name, QueryType::TXT, dns_server_addr, options).ok()?;
+    response
+        .answers
+        .iter()
+        .filter_map(|r| r.get_txt_data())
+        .find_map(parse_as_name_txt)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_origin_query_name_ipv6() {
+        let name = origin_query_name("2001:db8::1".parse().unwrap());
+        assert!(name.starts_with("1.0.0.0."));
+        assert!(name.ends_with(".8.b.d.0.1.0.0.2.origin6.asn.cymru.com"));
+    }
+
+    #[test]
+    fn test_parse_multi_origin_txt() {
+        let origins = parse_origin_txt("23028 3356 | 216.90.108.0/24 | US | arin | ").unwrap();
+        assert_eq!(origins.len(), 2);
+        assert_eq!(origins[0].asn, 23028);
+        assert_eq!(origins[1].asn, 3356);
+        assert_eq!(origins[1].prefix, "216.90.108.0/24");
+        assert_eq!(origins[1].allocated, "");
+    }
+
+    #[test]
+    fn test_parse_malformed_txt() {
+        assert!(parse_origin_txt("v=spf1 -all").is_err());
+        assert!(parse_origin_txt("AS1 | 192.0.2.0/24 | US | arin | 2000-01-01").is_err());
+        assert_eq!(parse_as_name_txt("15169 | US | arin"), None);
+    }
+}
@@ -0,0 +1,397 @@
+//! Expectations about DNS answers, for checking DNS changes in CI pipelines.
+//!
+//! An [`Assertion`] states something the answer records of a lookup must satisfy:
+//! an exact set of values, a value that must be present, a pattern some value must
+//! match, a record count, or a TTL ceiling. [`Assertion::check`] holds the records
+//! against it and describes any difference as lines of a diff, so a failed
+//! deployment check says what was expected and what was actually served.
+//!
+//! Record values are compared as they are written in zone files without the type
+//! (`203.0.113.10`, `10 mail.example.com`), ignoring case and a trailing dot. TXT
+//! values are the text itself, compared exactly.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::assertion::Assertion;
+//! use dns_resolver::dns::{QueryType, RData, ResourceRecord};
+//! use std::net::Ipv4Addr;
+//!
+//! let record = ResourceRecord {
+//!     name: "example.com".parse().unwrap(),
+//!     rtype: QueryType::A,
+//!     rclass: 1,
+//!     ttl: 600,
+//!     data: RData::A(Ipv4Addr::new(203, 0, 113, 10)),
+//! };
+//!
+//! assert!(Assertion::Equals(vec!["203.0.113.10".to_string()]).check(&[record.clone()]).is_ok());
+//! let diff = Assertion::MaxTtl(300).check(&[record]).unwrap_err();
+//! assert_eq!(diff, vec!["203.0.113.10 has TTL 600"]);
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dns::{RData, ResourceRecord};
+
+/// Something the answer records of a lookup must satisfy.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Assertion {
+    /// The records have exactly these values, in any order (`--equals`, repeatable).
+    Equals(Vec<String>),
+    /// Some record has this value (`--contains`).
+    Contains(String),
+    /// Some record value matches this pattern (`--matches`).
+    Matches(Pattern),
+    /// There are exactly this many records (`--count`).
+    Count(usize),
+    /// No record has a TTL above this many seconds (`--max-ttl`).
+    MaxTtl(u32),
+}
+
+impl Assertion {
+    /// Checks `records` against the assertion.
+    ///
+    /// # Errors
+    ///
+    /// Returns the lines describing how the records differ from the expectation.
+    /// For [`Assertion::Equals`], missing values are prefixed with `-` and
+    /// unexpected ones with `+`.
+    pub fn check(&self, records: &[ResourceRecord]) -> Result<(), Vec<String>> {
+        let values: Vec<String> = records.iter().map(record_value).collect();
+        let diff = match self {
+            Assertion::Equals(expected) => {
+                let missing = expected
+                    .iter()
+                    .filter(|value| !values.iter().any(|actual| same_value(actual, value)))
+                    .map(|value| format!("- {}", value));
+                let unexpected = values
+                    .iter()
+                    .filter(|actual| !expected.iter().any(|value| same_value(actual, value)))
+                    .map(|actual| format!("+ {}", actual));
+                missing.chain(unexpected).collect()
+            }
+            Assertion::Contains(expected) => {
+                if values.iter().any(|actual| same_value(actual, expected)) {
+                    Vec::new()
+                } else {
+                    vec![format!("{} not among [{}]", expected, values.join(", "))]
+                }
+            }
+            Assertion::Matches(pattern) => {
+                if values.iter().any(|actual| pattern.is_match(actual)) {
+                    Vec::new()
+                } else {
+                    vec![format!("no value among [{}] matches", values.join(", "))]
+                }
+            }
+            Assertion::Count(expected) => {
+                if values.len() == *expected {
+                    Vec::new()
+                } else {
+                    vec![format!(
+                        "expected {} records, got {}",
+                        expected,
+                        values.len()
+                    )]
+                }
+            }
+            Assertion::MaxTtl(max) => records
+                .iter()
+                .zip(&values)
+                .filter(|(record, _)| record.ttl > *max)
+                .map(|(record, value)| format!("{} has TTL {}", value, record.ttl))
+                .collect(),
+        };
+        if diff.is_empty() { Ok(()) } else { Err(diff) }
+    }
+}
+
+impl fmt::Display for Assertion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Assertion::Equals(values) => write!(f, "equals {}", values.join(", ")),
+            Assertion::Contains(value) => write!(f, "contains {}", value),
+            Assertion::Matches(pattern) => write!(f, "matches {}", pattern),
+            Assertion::Count(count) => write!(f, "count {}", count),
+            Assertion::MaxTtl(ttl) => write!(f, "max-ttl {}", ttl),
+        }
+    }
+}
+
+/// Returns the value of a record as compared by assertions: its data as written in
+/// a zone file without the type mnemonic, or the bare text of a TXT record.
+pub fn record_value(record: &ResourceRecord) -> String {
+    match &record.data {
+        RData::TXT(text) => text.clone(),
+        data => {
+            let text = data.to_string();
+            match text.split_once(' ') {
+                Some((_, value)) => value.to_string(),
+                None => text,
+            }
+        }
+    }
+}
+
+/// Compares two record values, ignoring case and a trailing dot.
+fn same_value(actual: &str, expected: &str) -> bool {
+    actual
+        .trim_end_matches('.')
+        .eq_ignore_ascii_case(expected.trim_end_matches('.'))
+}
+
+/// A small regular expression for matching record values.
+///
+/// Supports literal characters, `.`, the quantifiers `*`, `+` and `?`, character
+/// classes such as `[0-9]` and `[^.]`, the escapes `\d`, `\w` and `\s`, and the
+/// anchors `^` and `$`. A pattern without anchors matches anywhere in the value.
+/// Groups, alternation and counted repetition are not supported.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::assertion::Pattern;
+///
+/// let pattern: Pattern = r"^203\.0\.113\.\d+$".parse().unwrap();
+/// assert!(pattern.is_match("203.0.113.10"));
+/// assert!(!pattern.is_match("203.0.113.10.in-addr.arpa"));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    source: String,
+    anchored_start: bool,
+    anchored_end: bool,
+    nodes: Vec<Node>,
+}
+
+/// One element of a pattern and how many times in a row it may match.
+#[derive(Debug, Clone, PartialEq)]
+struct Node {
+    atom: Atom,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Atom {
+    Any,
+    Char(char),
+    Class {
+        negated: bool,
+        ranges: Vec<(char, char)>,
+    },
+}
+
+impl Atom {
+    fn matches(&self, c: char) -> bool {
+        match self {
+            Atom::Any => true,
+            Atom::Char(expected) => c == *expected,
+            Atom::Class { negated, ranges } => {
+                ranges.iter().any(|(low, high)| (*low..=*high).contains(&c)) != *negated
+            }
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = String;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let invalid = |why: &str| format!("Invalid pattern '{}': {}", source, why);
+        let (anchored_start, rest) = match source.strip_prefix('^') {
+            Some(rest) => (true, rest),
+            None => (false, source),
+        };
+        let (anchored_end, body) = match rest.strip_suffix('$') {
+            Some(body) if !body.ends_with('\\') => (true, body),
+            _ => (false, rest),
+        };
+
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut chars = body.chars();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '.' => Atom::Any,
+                '\\' => escape(chars.next().ok_or_else(|| invalid("trailing backslash"))?),
+                '[' => {
+                    let negated = chars.as_str().starts_with('^');
+                    if negated {
+                        chars.next();
+                    }
+                    let mut ranges = Vec::new();
+                    loop {
+                        let low = match chars.next() {
+                            None => return Err(invalid("unterminated character class")),
+                            Some(']') if !ranges.is_empty() => break,
+                            Some('\\') => {
+                                chars.next().ok_or_else(|| invalid("trailing backslash"))?
+                            }
+                            Some(low) => low,
+                        };
+                        let rest = chars.as_str();
+                        let high = match rest.strip_prefix('-') {
+                            Some(after) if !after.starts_with(']') && !after.is_empty() => {
+                                chars.next();
+                                chars.next().unwrap_or(low)
+                            }
+                            _ => low,
+                        };
+                        ranges.push((low, high));
+                    }
+                    Atom::Class { negated, ranges }
+                }
+                '*' | '+' | '?' => {
+                    let Some(node) = nodes.last_mut().filter(|n| n.min == 1 && n.max == 1) else {
+                        return Err(invalid("quantifier without something to repeat"));
+                    };
+                    (node.min, node.max) = match c {
+                        '*' => (0, usize::MAX),
+                        '+' => (1, usize::MAX),
+                        _ => (0, 1),
+                    };
+                    continue;
+                }
+                '(' | ')' | '|' | '{' | '}' => {
+                    return Err(invalid(
+                        "groups, alternation and counted repetition are not supported",
+                    ));
+                }
+                c => Atom::Char(c),
+            };
+            nodes.push(Node {
+                atom,
+                min: 1,
+                max: 1,
+            });
+        }
+
+        Ok(Pattern {
+            source: source.to_string(),
+            anchored_start,
+            anchored_end,
+            nodes,
+        })
+    }
+}
+
+/// Returns the atom for the character following a backslash.
+fn escape(c: char) -> Atom {
+    let class = |ranges: &[(char, char)]| Atom::Class {
+        negated: false,
+        ranges: ranges.to_vec(),
+    };
+    match c {
+        'd' => class(&[('0', '9')]),
+        'w' => class(&[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')]),
+        's' => class(&[(' ', ' '), ('\t', '\t'), ('\n', '\n'), ('\r', '\r')]),
+        c => Atom::Char(c),
+    }
+}
+
+impl Pattern {
+    /// Returns `true` if the pattern matches `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        if self.anchored_start {
+            self.match_here(0, &chars, 0)
+        } else {
+            (0..=chars.len()).any(|start| self.match_here(0, &chars, start))
+        }
+    }
+
+    /// Matches the nodes from `node` on against the text from `position` on,
+    /// taking as many repetitions as possible first and backtracking from there.
+    fn match_here(&self, node: usize, chars: &[char], position: usize) -> bool {
+        let Some(current) = self.nodes.get(node) else {
+            return !self.anchored_end || position == chars.len();
+        };
+        let available = chars[position..]
+            .iter()
+            .take(current.max)
+            .take_while(|c| current.atom.matches(**c))
+            .count();
+        (current.min..=available)
+            .rev()
+            .any(|taken| self.match_here(node + 1, chars, position + taken))
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::QueryType;
+    use crate::testutil::a_record;
+
+    #[test]
+    fn test_assertions_report_differences() {
+        let records = [
+            a_record("example.com", [203, 0, 113, 10], 300),
+            a_record("example.com", [203, 0, 113, 11], 3600),
+        ];
+        assert_eq!(
+            Assertion::Equals(vec!["203.0.113.10".to_string(), "203.0.113.12".to_string()])
+                .check(&records),
+            Err(vec![
+                "- 203.0.113.12".to_string(),
+                "+ 203.0.113.11".to_string()
+            ])
+        );
+        assert!(
+            Assertion::Contains("203.0.113.11".to_string())
+                .check(&records)
+                .is_ok()
+        );
+        assert!(Assertion::Count(1).check(&records).is_err());
+        assert!(Assertion::Count(0).check(&[]).is_ok());
+        assert_eq!(
+            Assertion::MaxTtl(300).check(&records),
+            Err(vec!["203.0.113.11 has TTL 3600".to_string()])
+        );
+
+        let mx = ResourceRecord {
+            name: "example.com".parse().unwrap(),
+            rtype: QueryType::MX,
+            rclass: 1,
+            ttl: 300,
+            data: RData::MX {
+                preference: 10,
+                exchange: "Mail.Example.com".to_string(),
+            },
+        };
+        assert!(
+            Assertion::Equals(vec!["10 mail.example.com.".to_string()])
+                .check(&[mx])
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn test_pattern_matching() {
+        let pattern: Pattern = r"^203\.0\.113\.1[0-9]?$".parse().unwrap();
+        assert!(pattern.is_match("203.0.113.1"));
+        assert!(pattern.is_match("203.0.113.15"));
+        assert!(!pattern.is_match("203.0.113.150"));
+        assert!(!pattern.is_match("x203.0.113.1"));
+
+        let pattern: Pattern = "v=spf1 .*-all".parse().unwrap();
+        assert!(pattern.is_match("v=spf1 include:_spf.example.com -all"));
+        assert!(!pattern.is_match("v=spf1 ~all"));
+
+        let pattern: Pattern = r"[^.]+\.example\.net$".parse().unwrap();
+        assert!(pattern.is_match("10 mx1.example.net"));
+        assert!(!pattern.is_match("10 mx1.example.net.evil"));
+
+        assert!("(a|b)".parse::<Pattern>().is_err());
+        assert!("*a".parse::<Pattern>().is_err());
+        assert!("[abc".parse::<Pattern>().is_err());
+    }
+}
@@ -0,0 +1,369 @@
+//! Zone transfers (AXFR, RFC 5936).
+//!
+//! A zone transfer asks an authoritative server for every record of a zone at once.
+//! It only runs over TCP: the client sends a single AXFR query, and the server answers
+//! with a stream of messages, each framed by its length like any other DNS message on
+//! a stream. The records arrive in no particular order, except that the zone's SOA
+//! record comes first and again last, so the client knows the transfer is complete
+//! when it sees the SOA a second time:
+//!
+//! ```text
+//! example.com.      3600 IN SOA   ns1.example.com. hostmaster.example.com. 2024010101 ...
+//! example.com.      3600 IN NS    ns1.example.com.
+//! www.example.com.  300  IN A     192.0.2.10
+//! ...
+//! example.com.      3600 IN SOA   ns1.example.com. hostmaster.example.com. 2024010101 ...
+//! ```
+//!
+//! Most servers only allow transfers to their secondaries, and answer anyone else with
+//! REFUSED, which is reported as [`DnsError::ServerReturnedError`]. Many let in
+//! clients holding a shared key instead: with [`QueryOptions::tsig`] set, the query
+//! is signed and every signed message of the stream is verified (see [`crate::tsig`]).
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::axfr::transfer;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! let zone = transfer("zonetransfer.me", Ipv4Addr::new(81, 4, 108, 41), &QueryOptions::default())?;
+//! println!("{} records in {} messages", zone.records.len(), zone.messages);
+//! std::fs::write("zonetransfer.me.zone", zone.to_zone_file())?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::io::{Read, Write};
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::SystemTime;
+
+use crate::dns::{
+    DnsClass, DnsMessage, DnsName, DnsQuestion, QueryType, RData, ResourceRecord, ResponseCode,
+    canonical_name, display_name,
+};
+use crate::resolver::{
+    DnsError, QueryOptions, connect_tcp, random_query_id, read_framed, write_framed,
+};
+use crate::tsig::TsigSession;
+
+/// The records of a zone, as received in a zone transfer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoneTransfer {
+    /// The name of the zone.
+    pub zone: String,
+    /// Every record of the zone in the order the server sent them, starting with the
+    /// SOA record. The closing copy of the SOA record is not repeated.
+    pub records: Vec<ResourceRecord>,
+    /// How many messages the transfer took.
+    pub messages: usize,
+}
+
+impl ZoneTransfer {
+    /// Returns the zone's SOA record, which is always the first record.
+    pub fn soa(&self) -> Option<&ResourceRecord> {
+        self.records.first()
+    }
+
+    /// Formats the zone as a master file (RFC 1035 section 5), one record per line.
+    ///
+    /// Names are written without a trailing dot, so the file starts with
+    /// `$ORIGIN .` to make every name absolute; the file loads back into a name server
+    /// or a later transfer can be compared to it with `diff`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::axfr::ZoneTransfer;
+    ///
+    /// let zone = ZoneTransfer { zone: "example.com".to_string(), records: Vec::new(), messages: 1 };
+    /// assert!(zone.to_zone_file().contains("$ORIGIN .\n"));
+    /// ```
+    pub fn to_zone_file(&self) -> String {
+        let mut file = format!(
+            "; Zone transfer of {}: {} records in {} messages\n$ORIGIN .\n",
+            display_name(&self.zone),
+            self.records.len(),
+            self.messages
+        );
+        for record in &self.records {
+            // The rdata starts with the type mnemonic, as the type column of a
+            // master file does.
+            file.push_str(&format!(
+                "{} {} {} {}\n",
+                display_name(&record.name),
+                record.ttl,
+                DnsClass::from(record.rclass),
+                record.data
+            ));
+        }
+        file
+    }
+}
+
+/// Transfers a zone from a server over TCP.
+///
+/// The server is reached on [`QueryOptions::port`] in [`QueryOptions::class`], and
+/// each read waits for at most the initial timeout of [`QueryOptions::retry`].
+/// A transfer is never retried, since it is not a small, idempotent exchange.
+///
+/// # Arguments
+///
+/// * `zone` - The name of the zone to transfer
+/// * `server` - The IPv4 address of an authoritative server for the zone
+/// * `options` - The port, class and timeout to use
+///
+/// # Returns
+///
+/// * `Ok(ZoneTransfer)` - Every record of the zone
+/// * `Err(DnsError)` - If the transfer was refused or did not complete
+///
+/// # Errors
+///
+/// Returns [`DnsError::ServerReturnedError`] when the server declines the transfer,
+/// usually with REFUSED or NOTAUTH, [`DnsError::Timeout`] when it stops sending,
+/// [`DnsError::InvalidName`] when `zone` cannot be encoded, [`DnsError::InvalidResponse`] when the stream is not a well-formed transfer or
+/// ends before the closing SOA record, and [`DnsError::Io`] when the connection
+/// fails, or when [`QueryOptions::source_address`] or [`QueryOptions::interface`] is
+/// set on a platform other than Linux, where TCP connections cannot be bound to
+/// either.
+pub fn transfer(
+    zone: &str,
+    server: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<ZoneTransfer, DnsError> {
+    let timeout = options.retry.initial_timeout;
+    let server_address = SocketAddrV4::new(server, options.port.unwrap_or(53));
+    let stream = connect_tcp(server_address, timeout, options)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    transfer_over(stream, zone, options)
+}
+
+/// Sends an AXFR query on a connected stream and reads the transfer that follows.
+fn transfer_over(
+    mut stream: impl Read + Write,
+    zone: &str,
+    options: &QueryOptions,
+) -> Result<ZoneTransfer, DnsError> {
+    // A transfer query asks an authoritative server directly, so RD stays clear.
+    let mut query = DnsMessage::new();
+    query.header.id = random_query_id();
+    query.header.question_count = 1;
+    query.questions.push(DnsQuestion {
+        name: DnsName::new(zone).map_err(DnsError::InvalidName)?,
+        qtype: QueryType::AXFR,
+        qclass: options.class.into(),
+    });
+    let mut query_buffer = Vec::new();
+    query
+        .pack(&mut query_buffer)
+        .map_err(DnsError::InvalidResponse)?;
+    let mut tsig = options
+        .tsig
+        .as_ref()
+        .map(|key| TsigSession::sign_request(key, &mut query_buffer, SystemTime::now()))
+        .transpose()
+        .map_err(DnsError::InvalidResponse)?;
+    write_framed(&mut stream, &query_buffer)?;
+
+    let mut transfer = ZoneTransfer {
+        zone: zone.to_string(),
+        records: Vec::new(),
+        messages: 0,
+    };
+    loop {
+        let response = read_framed(&mut stream).map_err(|e| match e {
+            // The server closed the connection partway through.
+            DnsError::Io(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                DnsError::InvalidResponse(format!(
+                    "zone transfer ended after {} records without the closing SOA record",
+                    transfer.records.len()
+                ))
+            }
+            e => e,
+        })?;
+        if let Some(session) = &mut tsig {
+            session
+                .verify(&response, SystemTime::now())
+                .map_err(|e| tsig_error(transfer.messages, e))?;
+        }
+        let message = DnsMessage::from_bytes(&response)
+            .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+        transfer.messages += 1;
+        if message.header.id != query.header.id {
+            return Err(DnsError::InvalidResponse(
+                "response ID does not match the query".to_string(),
+            ));
+        }
+        match message.header.get_response_code() {
+            ResponseCode::NoError => {}
+            code => return Err(DnsError::ServerReturnedError(code)),
+        }
+        if message.answers.is_empty() {
+            return Err(DnsError::InvalidResponse(
+                "zone transfer message has no records".to_string(),
+            ));
+        }
+
+        for record in message.answers {
+            let is_soa = matches!(record.data, RData::SOA { .. });
+            if transfer.records.is_empty() {
+                // The first record must be the SOA of the zone asked for.
+                if !is_soa || canonical_name(&record.name) != canonical_name(zone) {
+                    return Err(DnsError::InvalidResponse(format!(
+                        "zone transfer starts with {} {} instead of the SOA record of {}",
+                        display_name(&record.name),
+                        record.rtype,
+                        display_name(zone)
+                    )));
+                }
+            } else if is_soa {
+                // The SOA record again closes the transfer, in a message that must
+                // be signed if the others were.
+                if let Some(session) = &tsig
+                    && !session.is_complete()
+                {
+                    return Err(tsig_error(
+                        transfer.messages - 1,
+                        "the last message is not signed".to_string(),
+                    ));
+                }
+                return Ok(transfer);
+            }
+            transfer.records.push(record);
+        }
+    }
+}
+
+/// Reports a TSIG failure in the message with the given zero-based index.
+fn tsig_error(index: usize, reason: String) -> DnsError {
+    DnsError::InvalidResponse(format!(
+        "TSIG verification failed in message {} of the transfer: {}",
+        index + 1,
+        reason
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{StubServer, a_record, record};
+    use crate::transport::{Reply, Transport};
+    use crate::tsig::TsigKey;
+    use std::net::Ipv4Addr;
+
+    fn soa() -> ResourceRecord {
+        record(
+            "example.com",
+            3600,
+            RData::SOA {
+                mname: "ns1.example.com".to_string(),
+                rname: "hostmaster.example.com".to_string(),
+                serial: 2024010101,
+                refresh: 7200,
+                retry: 900,
+                expire: 1209600,
+                minimum: 300,
+            },
+        )
+    }
+
+    fn host(name: &str, last_octet: u8) -> ResourceRecord {
+        a_record(name, [192, 0, 2, last_octet], 3600)
+    }
+
+    fn transfer_from(server: &StubServer) -> Result<ZoneTransfer, DnsError> {
+        transfer("example.com", Ipv4Addr::LOCALHOST, &server.query_options())
+    }
+
+    #[test]
+    fn test_transfer_spanning_several_messages() {
+        let server = StubServer::start(vec![Reply::Transfer(vec![
+            vec![
+                soa(),
+                record("example.com", 3600, RData::NS("ns1.example.com".into())),
+            ],
+            vec![host("www.example.com", 10), host("mail.example.com", 25)],
+            vec![host("ns1.example.com", 53), soa()],
+        ])])
+        .unwrap();
+
+        let zone = transfer_from(&server).unwrap();
+        assert_eq!(zone.messages, 3);
+        assert_eq!(zone.records.len(), 5);
+        assert_eq!(zone.soa(), Some(&soa()));
+        assert_eq!(zone.records[4].name, "ns1.example.com");
+        assert_eq!(server.queries(), vec![Transport::Tcp]);
+
+        let file = zone.to_zone_file();
+        assert!(file.starts_with("; Zone transfer of example.com: 5 records in 3 messages\n"));
+        assert!(file.contains(
+            "\nexample.com 3600 IN SOA ns1.example.com hostmaster.example.com 2024010101 7200 900 1209600 300\n"
+        ));
+        assert!(file.ends_with("\nns1.example.com 3600 IN A 192.0.2.53\n"));
+    }
+
+    #[test]
+    fn test_signed_transfer() {
+        let key: TsigKey = "transfer-key:c2VjcmV0LWtleQ==".parse().unwrap();
+        let records = vec![soa(), host("www.example.com", 10), soa()];
+        let server = StubServer::start(vec![Reply::Signed(
+            key.clone(),
+            Box::new(Reply::Answer(records)),
+        )])
+        .unwrap();
+        let options = QueryOptions {
+            tsig: Some(key),
+            ..server.query_options()
+        };
+        let zone = transfer("example.com", Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(zone.records.len(), 2);
+
+        // A transfer that is not signed at all.
+        let server = StubServer::start(vec![Reply::Transfer(vec![vec![soa(), soa()]])]).unwrap();
+        let options = QueryOptions {
+            tsig: options.tsig,
+            ..server.query_options()
+        };
+        assert!(matches!(
+            transfer("example.com", Ipv4Addr::LOCALHOST, &options),
+            Err(DnsError::InvalidResponse(message)) if message.contains("message 1")
+        ));
+    }
+
+    #[test]
+    fn test_refused_transfer() {
+        let server = StubServer::start(vec![Reply::Code(ResponseCode::Refused)]).unwrap();
+        assert!(matches!(
+            transfer_from(&server),
+            Err(DnsError::ServerReturnedError(ResponseCode::Refused))
+        ));
+    }
+
+    #[test]
+    fn test_incomplete_transfers() {
+        // The connection closes before the closing SOA record.
+        let server = StubServer::start(vec![Reply::Transfer(vec![vec![
+            soa(),
+            host("www.example.com", 10),
+        ]])])
+        .unwrap();
+        match transfer_from(&server) {
+            Err(DnsError::InvalidResponse(message)) => {
+                assert!(message.contains("after 2 records"), "{}", message)
+            }
+            other => panic!("expected an incomplete transfer, got {:?}", other),
+        }
+
+        // The stream does not start with the zone's SOA record.
+        let server = StubServer::start(vec![Reply::Transfer(vec![vec![
+            host("www.example.com", 10),
+            soa(),
+        ]])])
+        .unwrap();
+        assert!(matches!(
+            transfer_from(&server),
+            Err(DnsError::InvalidResponse(_))
+        ));
+    }
+}
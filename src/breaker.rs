@@ -0,0 +1,209 @@
+//! Circuit breaking for upstream servers that keep failing.
+//!
+//! When one of several configured servers is down, every query that reaches it
+//! first waits out a full timeout before failing over. A [`CircuitBreaker`] shared
+//! between queries counts consecutive failures per server. Once a server reaches
+//! [`BreakerConfig::failure_threshold`], its circuit opens and queries go to the
+//! other servers for [`BreakerConfig::open_duration`]. After that, a single query
+//! is let through as a probe: if it succeeds the circuit closes again, and if it
+//! fails the server stays out of rotation for another period.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::breaker::CircuitBreaker;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//! use std::sync::Arc;
+//!
+//! let options = QueryOptions {
+//!     alternate_servers: vec![Ipv4Addr::new(1, 1, 1, 1)],
+//!     circuit_breaker: Some(Arc::new(CircuitBreaker::default())),
+//!     ..QueryOptions::default()
+//! };
+//! ```
+
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// When a circuit opens and how long it stays open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BreakerConfig {
+    /// Consecutive failures (timeouts, network errors or SERVFAIL responses) after
+    /// which a server is taken out of rotation.
+    pub failure_threshold: u32,
+    /// How long a server stays out of rotation before it is probed again.
+    pub open_duration: Duration,
+}
+
+impl Default for BreakerConfig {
+    /// Five failures in a row open the circuit for thirty seconds.
+    fn default() -> Self {
+        BreakerConfig {
+            failure_threshold: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+/// The state of the circuit for one server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Queries flow normally. Holds the number of consecutive failures so far.
+    Closed {
+        /// Failures since the last success.
+        failures: u32,
+    },
+    /// The server is skipped until the given time.
+    Open {
+        /// When the next probe may be sent.
+        until: Instant,
+    },
+    /// A probe has been sent and its outcome is not known yet.
+    HalfOpen,
+}
+
+/// Per-server failure tracking shared by any number of queries and threads.
+#[derive(Debug, Default)]
+pub struct CircuitBreaker {
+    config: BreakerConfig,
+    states: Mutex<HashMap<Ipv4Addr, CircuitState>>,
+}
+
+impl CircuitBreaker {
+    /// Creates a breaker with the given thresholds and every circuit closed.
+    pub fn new(config: BreakerConfig) -> Self {
+        CircuitBreaker {
+            config,
+            states: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the current state of the circuit for `server`.
+    pub fn state(&self, server: Ipv4Addr) -> CircuitState {
+        self.states
+            .lock()
+            .unwrap()
+            .get(&server)
+            .copied()
+            .unwrap_or(CircuitState::Closed { failures: 0 })
+    }
+
+    /// Picks the server for the next attempt, starting at `preferred` and moving on
+    /// through `servers` past any whose circuit is open.
+    ///
+    /// If every circuit is open, the preferred server is used anyway, so a query
+    /// is never refused outright.
+    pub fn choose(&self, servers: &[Ipv4Addr], preferred: usize) -> Ipv4Addr {
+        self.choose_at(servers, preferred, Instant::now())
+    }
+
+    fn choose_at(&self, servers: &[Ipv4Addr], preferred: usize, now: Instant) -> Ipv4Addr {
+        let mut states = self.states.lock().unwrap();
+        (0..servers.len())
+            .map(|offset| servers[(preferred + offset) % servers.len()])
+            .find(|server| match states.get(server).copied() {
+                None | Some(CircuitState::Closed { .. }) => true,
+                Some(CircuitState::Open { until }) if until <= now => {
+                    // Let exactly one query through to find out if it has recovered.
+                    states.insert(*server, CircuitState::HalfOpen);
+                    true
+                }
+                Some(CircuitState::Open { .. }) | Some(CircuitState::HalfOpen) => false,
+            })
+            .unwrap_or(servers[preferred])
+    }
+
+    /// Records a usable response from `server`, closing its circuit.
+    pub fn record_success(&self, server: Ipv4Addr) {
+        self.states
+            .lock()
+            .unwrap()
+            .insert(server, CircuitState::Closed { failures: 0 });
+    }
+
+    /// Records a failed attempt against `server`, opening its circuit once the
+    /// failure threshold is reached or a probe fails.
+    pub fn record_failure(&self, server: Ipv4Addr) {
+        self.record_failure_at(server, Instant::now());
+    }
+
+    fn record_failure_at(&self, server: Ipv4Addr, now: Instant) {
+        let mut states = self.states.lock().unwrap();
+        let open = CircuitState::Open {
+            until: now + self.config.open_duration,
+        };
+        let next = match states.get(&server).copied() {
+            None => CircuitState::Closed { failures: 1 },
+            Some(CircuitState::Closed { failures }) => CircuitState::Closed {
+                failures: failures + 1,
+            },
+            Some(CircuitState::HalfOpen) => open,
+            // A late failure from before the circuit opened changes nothing.
+            Some(state @ CircuitState::Open { .. }) => state,
+        };
+        let next = match next {
+            CircuitState::Closed { failures } if failures >= self.config.failure_threshold => open,
+            next => next,
+        };
+        states.insert(server, next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PRIMARY: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+    const ALTERNATE: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 2);
+
+    #[test]
+    fn test_circuit_opens_after_repeated_failures() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 3,
+            open_duration: Duration::from_secs(10),
+        });
+        let servers = [PRIMARY, ALTERNATE];
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            breaker.record_failure_at(PRIMARY, now);
+        }
+        assert_eq!(breaker.choose_at(&servers, 0, now), PRIMARY);
+
+        // A success resets the count.
+        breaker.record_success(PRIMARY);
+        for _ in 0..3 {
+            breaker.record_failure_at(PRIMARY, now);
+        }
+        assert!(matches!(breaker.state(PRIMARY), CircuitState::Open { .. }));
+        assert_eq!(breaker.choose_at(&servers, 0, now), ALTERNATE);
+        // With nowhere else to go, the open server is still used.
+        assert_eq!(breaker.choose_at(&[PRIMARY], 0, now), PRIMARY);
+    }
+
+    #[test]
+    fn test_single_probe_after_open_period() {
+        let breaker = CircuitBreaker::new(BreakerConfig {
+            failure_threshold: 1,
+            open_duration: Duration::from_secs(10),
+        });
+        let servers = [PRIMARY, ALTERNATE];
+        let now = Instant::now();
+        breaker.record_failure_at(PRIMARY, now);
+
+        let later = now + Duration::from_secs(11);
+        assert_eq!(breaker.choose_at(&servers, 0, later), PRIMARY);
+        assert_eq!(breaker.state(PRIMARY), CircuitState::HalfOpen);
+        // Other queries keep avoiding the server while the probe is out.
+        assert_eq!(breaker.choose_at(&servers, 0, later), ALTERNATE);
+
+        // A failed probe reopens the circuit, a successful one closes it.
+        breaker.record_failure_at(PRIMARY, later);
+        assert_eq!(breaker.choose_at(&servers, 0, later), ALTERNATE);
+        breaker.record_success(PRIMARY);
+        assert_eq!(breaker.choose_at(&servers, 0, later), PRIMARY);
+    }
+}
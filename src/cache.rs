@@ -0,0 +1,1560 @@
+//! TTL-aware caches of whole responses.
+//!
+//! A long-running process that resolves the same names over and over should not
+//! query the network each time. [`ResponseCache`] keeps each response under its
+//! question's name, type and class for as long as the TTLs of its records allow, and
+//! hands out copies whose TTLs have been counted down by the time spent in the
+//! cache, as a caching server does. Names that do not exist and names without
+//! records of the type asked for are remembered too, for the negative TTL of the
+//! zone (RFC 2308).
+//!
+//! Caches implement the [`DnsCache`] trait. A
+//! [`Resolver`](crate::resolver::Resolver) keeps one for
+//! [`Resolver::resolve`](crate::resolver::Resolver::resolve): a [`ResponseCache`]
+//! in memory unless it is built with another one or without one. A [`FileCache`]
+//! also writes its entries to a journal file, so a long-lived process that restarts
+//! starts with the responses it had cached rather than with an empty cache. How long
+//! entries are kept is bounded by the same [`TtlPolicy`] as the host cache's.
+//!
+//! # Serve-Stale
+//!
+//! A cache can keep responses for a while after they expire, and hand them out
+//! when the servers cannot be reached or fail, rather than failing the lookup
+//! (RFC 8767). Such stale responses get a short TTL and carry an Extended DNS Error
+//! "Stale Answer" option (RFC 8914), which [`is_stale_answer`] detects. This is
+//! off by default; see [`ResponseCache::with_serve_stale`].
+//!
+//! # Prefetch
+//!
+//! A response that is asked for often can be refreshed shortly before it expires,
+//! so that lookups of popular names keep being answered from the cache instead of
+//! waiting for the server every time the TTL runs out. A cache set up with
+//! [`ResponseCache::with_prefetch`] hands out one [`DnsCache::claim_prefetch`] per
+//! entry once it has been hit often enough and is in the last tenth of its TTL, and
+//! the resolver then refreshes the entry in the background.
+//!
+//! # Journal Format
+//!
+//! The journal is a text file with one cached response per line, appended as
+//! responses are cached. Lines starting with `#` are comments.
+//!
+//! ```text
+//! <stored_unix_secs> <expires_unix_secs> <class> <type> <name> <response_hex>
+//! ```
+//!
+//! The class and type are numeric, and the name is in canonical form. Later lines
+//! for the same question replace earlier ones. Opening the journal drops expired
+//! and unreadable lines, such as one cut short by a crash, and rewrites it with the
+//! rest.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::Resolver;
+//! use std::net::Ipv4Addr;
+//!
+//! let resolver = Resolver::builder().server(Ipv4Addr::new(8, 8, 8, 8)).build()?;
+//! resolver.resolve("example.com", QueryType::MX)?; // Sent to 8.8.8.8
+//! resolver.resolve("example.com", QueryType::MX)?; // Answered from the cache
+//! let stats = resolver.response_cache().unwrap().stats();
+//! assert_eq!((stats.hits, stats.misses), (1, 1));
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::dns::{
+    DnsClass, DnsMessage, Edns, EdnsOption, QueryType, RData, ResourceRecord, ResponseCode,
+    canonical_name,
+};
+use crate::host_cache::{CacheStats, DEFAULT_NEGATIVE_TTL, TtlPolicy};
+use crate::output::{OutputFormat, csv_field, json_string};
+use crate::transcript::{decode_hex, encode_hex};
+
+/// The EDNS option code of an Extended DNS Error (RFC 8914).
+pub const EDNS_EXTENDED_ERROR: u16 = 15;
+
+/// The Extended DNS Error code for a stale answer (RFC 8914 section 4.4).
+pub const EXTENDED_ERROR_STALE_ANSWER: u16 = 3;
+
+/// The TTL of the records in a stale response, as recommended by RFC 8767
+/// section 4.
+pub const STALE_ANSWER_TTL: u32 = 30;
+
+/// A store of responses keyed by question, as used by
+/// [`Resolver::resolve`](crate::resolver::Resolver::resolve).
+///
+/// Implementations decide where entries live and how they are evicted, but must
+/// hand out a response only while its TTL lasts, with its TTLs counted down by the
+/// time spent in the cache, and must compare names case-insensitively and with or
+/// without a trailing dot.
+pub trait DnsCache: Send + Sync + fmt::Debug {
+    /// Returns the cached response to a question, or `None` if there is none or it
+    /// expired. Every call counts as a hit or a miss in [`DnsCache::stats`].
+    fn get(&self, name: &str, query_type: QueryType, class: DnsClass) -> Option<DnsMessage>;
+
+    /// Returns an expired response to a question that may still be served because
+    /// the servers cannot be reached, marked as stale, or `None` if there is none.
+    ///
+    /// Caches that do not serve stale responses keep the default, which returns
+    /// `None`.
+    fn get_stale(
+        &self,
+        _name: &str,
+        _query_type: QueryType,
+        _class: DnsClass,
+    ) -> Option<DnsMessage> {
+        None
+    }
+
+    /// Checks whether a cached response is popular and close enough to expiring
+    /// that it should be refreshed now, and if so claims the refresh, so that only
+    /// one caller performs it.
+    ///
+    /// Caches that do not prefetch keep the default, which returns `false`.
+    fn claim_prefetch(&self, _name: &str, _query_type: QueryType, _class: DnsClass) -> bool {
+        false
+    }
+
+    /// Caches `message` as the response to a question, if it can be cached.
+    fn insert(&self, name: &str, query_type: QueryType, class: DnsClass, message: &DnsMessage);
+
+    /// Returns the hit and miss counters and the current number of entries.
+    fn stats(&self) -> CacheStats;
+
+    /// Lists the cached responses, including those only kept to be served stale,
+    /// ordered by name, type and class. Listing does not count as hits or misses.
+    fn entries(&self) -> Vec<CachedResponse>;
+
+    /// Removes the cached responses to every question about `name`, of any type
+    /// and class, and returns how many there were.
+    fn remove(&self, name: &str) -> usize;
+
+    /// Removes every cached response. The counters are kept.
+    fn clear(&self);
+}
+
+/// A response held by a cache, as listed by [`DnsCache::entries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedResponse {
+    /// The name asked for, in canonical form.
+    pub name: String,
+    /// The type asked for.
+    pub query_type: QueryType,
+    /// The class asked in.
+    pub class: DnsClass,
+    /// The response, with its TTLs counted down as [`DnsCache::get`] would.
+    pub message: DnsMessage,
+    /// Seconds until the response expires, zero once it has.
+    pub ttl: u32,
+    /// Set when the response has expired and is only kept to be served stale.
+    pub stale: bool,
+}
+
+impl CachedResponse {
+    /// Formats the cached response, one output line per element.
+    ///
+    /// Text output has a line for the question, followed by one indented line per
+    /// answer record. CSV output has one row per answer record, or a single row with
+    /// an empty record for a negative response; the header row is given by
+    /// [`csv_header`].
+    pub fn format_lines(&self, format: OutputFormat) -> Vec<String> {
+        let status = self.message.header.get_response_code().to_string();
+        let answers: Vec<String> = self.message.answers.iter().map(|r| r.to_string()).collect();
+        match format {
+            OutputFormat::Text => {
+                let state = if self.stale {
+                    "stale".to_string()
+                } else {
+                    format!("expires in {}s", self.ttl)
+                };
+                let question = format!(
+                    "{} {} {} {}, {}",
+                    self.name, self.class, self.query_type, status, state
+                );
+                std::iter::once(question)
+                    .chain(answers.iter().map(|answer| format!("  {}", answer)))
+                    .collect()
+            }
+            OutputFormat::Csv => {
+                let row = |record: &str| {
+                    format!(
+                        "{},{},{},{},{},{},{}",
+                        csv_field(&self.name),
+                        self.class,
+                        self.query_type,
+                        status,
+                        self.ttl,
+                        self.stale,
+                        csv_field(record)
+                    )
+                };
+                if answers.is_empty() {
+                    vec![row("")]
+                } else {
+                    answers.iter().map(|answer| row(answer)).collect()
+                }
+            }
+            OutputFormat::Ndjson => {
+                let answers: Vec<String> = answers.iter().map(|a| json_string(a)).collect();
+                vec![format!(
+                    "{{\"name\":{},\"class\":{},\"type\":{},\"status\":{},\"ttl\":{},\"stale\":{},\"answers\":[{}]}}",
+                    json_string(&self.name),
+                    json_string(&self.class.to_string()),
+                    json_string(&self.query_type.to_string()),
+                    json_string(&status),
+                    self.ttl,
+                    self.stale,
+                    answers.join(",")
+                )]
+            }
+        }
+    }
+}
+
+/// Returns the CSV header row matching [`CachedResponse::format_lines`].
+pub fn csv_header() -> &'static str {
+    "name,class,type,status,ttl,stale,record"
+}
+
+/// The question a response is cached under: the name in canonical form, the type
+/// and the class.
+type CacheKey = (String, QueryType, DnsClass);
+
+#[derive(Debug)]
+struct CacheEntry {
+    message: DnsMessage,
+    stored: Instant,
+    expires: Instant,
+    /// The wire size of the message, counted against the memory budget.
+    size: usize,
+    /// When the entry was last stored or handed out, as a position in `recency`.
+    last_used: u64,
+    /// How often the entry was handed out while fresh.
+    hits: u32,
+    /// Set once a refresh of the entry has been claimed.
+    prefetching: bool,
+}
+
+impl CacheEntry {
+    /// Returns a copy of the message with every TTL lowered by the time it has
+    /// spent in the cache by `now`.
+    fn counted_down(&self, now: Instant) -> DnsMessage {
+        let elapsed = now.saturating_duration_since(self.stored).as_secs();
+        let elapsed = u32::try_from(elapsed).unwrap_or(u32::MAX);
+        let mut message = self.message.clone();
+        for record in message
+            .answers
+            .iter_mut()
+            .chain(message.authorities.iter_mut())
+            .chain(message.additionals.iter_mut())
+        {
+            // The TTL field of an OPT record holds EDNS flags, not a TTL.
+            if record.rtype != QueryType::OPT {
+                record.ttl = record.ttl.saturating_sub(elapsed);
+            }
+        }
+        message
+    }
+}
+
+/// The entries of a cache, with the order in which they were last used.
+#[derive(Debug, Default)]
+struct Entries {
+    map: HashMap<CacheKey, CacheEntry>,
+    /// Keys by when they were last used, least recently used first.
+    recency: BTreeMap<u64, CacheKey>,
+    next_use: u64,
+    bytes: usize,
+}
+
+impl Entries {
+    fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Marks an entry as the most recently used one.
+    fn touch(&mut self, key: &CacheKey) {
+        let Some(entry) = self.map.get_mut(key) else {
+            return;
+        };
+        let key = self
+            .recency
+            .remove(&entry.last_used)
+            .unwrap_or_else(|| key.clone());
+        entry.last_used = self.next_use;
+        self.recency.insert(self.next_use, key);
+        self.next_use += 1;
+    }
+
+    fn insert(&mut self, key: CacheKey, mut entry: CacheEntry) {
+        self.remove(&key);
+        entry.last_used = self.next_use;
+        self.bytes += entry.size;
+        self.recency.insert(self.next_use, key.clone());
+        self.map.insert(key, entry);
+        self.next_use += 1;
+    }
+
+    fn remove(&mut self, key: &CacheKey) -> Option<CacheEntry> {
+        let entry = self.map.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        self.bytes -= entry.size;
+        Some(entry)
+    }
+
+    fn remove_least_recently_used(&mut self) -> Option<CacheEntry> {
+        let (_, key) = self.recency.pop_first()?;
+        let entry = self.map.remove(&key)?;
+        self.bytes -= entry.size;
+        Some(entry)
+    }
+
+    fn clear(&mut self) {
+        self.map.clear();
+        self.recency.clear();
+        self.bytes = 0;
+    }
+}
+
+/// A response cache that is safe to share between threads.
+///
+/// Only responses that say something definite about the name are cached: NOERROR,
+/// with or without records, and NXDOMAIN. Error responses such as SERVFAIL or
+/// REFUSED, and truncated responses, are never stored.
+///
+/// By default the cache grows with every question asked. A busy service can bound
+/// it with [`ResponseCache::with_max_entries`] and
+/// [`ResponseCache::with_max_bytes`], in which case the least recently used
+/// responses are evicted to make room, and counted in [`CacheStats::evictions`].
+#[derive(Debug, Default)]
+pub struct ResponseCache {
+    ttl_policy: TtlPolicy,
+    max_entries: Option<usize>,
+    max_bytes: Option<usize>,
+    max_stale: Option<Duration>,
+    prefetch_hits: Option<u32>,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    stale_hits: AtomicU64,
+    prefetches: AtomicU64,
+}
+
+impl ResponseCache {
+    /// Creates an empty cache that keeps responses for the TTL of their records, up
+    /// to [`MAX_CACHE_TTL`](crate::host_cache::MAX_CACHE_TTL).
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty cache that bounds how long responses are kept with
+    /// `ttl_policy`.
+    pub fn with_ttl_policy(ttl_policy: TtlPolicy) -> Self {
+        ResponseCache {
+            ttl_policy,
+            ..Self::default()
+        }
+    }
+
+    /// Limits the cache to `max_entries` responses.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::cache::ResponseCache;
+    ///
+    /// let cache = ResponseCache::new().with_max_entries(10_000).with_max_bytes(8 << 20);
+    /// ```
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Limits the responses in the cache to `max_bytes` in total.
+    ///
+    /// Responses are counted at their size on the wire, so this bounds the memory
+    /// the cache uses only approximately. A response larger than the whole budget
+    /// is not cached.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Keeps responses for up to `max_stale` after they expire, to be served by
+    /// [`DnsCache::get_stale`] when the servers cannot be reached.
+    ///
+    /// RFC 8767 suggests a limit between one and three days. Stale entries still
+    /// count against the limits of the cache until they are evicted or dropped.
+    pub fn with_serve_stale(mut self, max_stale: Duration) -> Self {
+        self.max_stale = Some(max_stale);
+        self
+    }
+
+    /// Has entries that were handed out at least `min_hits` times refreshed in the
+    /// background during the last tenth of their TTL (see [`DnsCache::claim_prefetch`]).
+    pub fn with_prefetch(mut self, min_hits: u32) -> Self {
+        self.prefetch_hits = Some(min_hits);
+        self
+    }
+
+    /// Returns the moment an entry that expires at `expires` is dropped.
+    fn keep_until(&self, expires: Instant) -> Instant {
+        expires + self.max_stale.unwrap_or_default()
+    }
+
+    fn get_at(
+        &self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+        now: Instant,
+    ) -> Option<DnsMessage> {
+        let key = (canonical_name(name), query_type, class);
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.map.get(&key) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+        if entry.expires <= now {
+            // Keep the entry around while it may still be served stale.
+            if self.keep_until(entry.expires) <= now {
+                entries.remove(&key);
+            }
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        if entry.message.answers.is_empty() {
+            self.negative_hits.fetch_add(1, Ordering::Relaxed);
+        }
+        let message = entry.counted_down(now);
+        if let Some(entry) = entries.map.get_mut(&key) {
+            entry.hits = entry.hits.saturating_add(1);
+        }
+        entries.touch(&key);
+        Some(message)
+    }
+
+    fn get_stale_at(
+        &self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+        now: Instant,
+    ) -> Option<DnsMessage> {
+        let key = (canonical_name(name), query_type, class);
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.map.get(&key)?;
+        if entry.expires > now || self.keep_until(entry.expires) <= now {
+            return None;
+        }
+
+        let mut message = entry.message.clone();
+        for record in message
+            .answers
+            .iter_mut()
+            .chain(message.authorities.iter_mut())
+            .chain(message.additionals.iter_mut())
+        {
+            if record.rtype != QueryType::OPT {
+                record.ttl = STALE_ANSWER_TTL;
+            }
+        }
+        mark_stale(&mut message);
+        self.stale_hits.fetch_add(1, Ordering::Relaxed);
+        entries.touch(&key);
+        Some(message)
+    }
+
+    fn claim_prefetch_at(
+        &self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+        now: Instant,
+    ) -> bool {
+        let Some(min_hits) = self.prefetch_hits else {
+            return false;
+        };
+        let key = (canonical_name(name), query_type, class);
+        let mut entries = self.entries.lock().unwrap();
+        let Some(entry) = entries.map.get_mut(&key) else {
+            return false;
+        };
+        let ttl = entry.expires.duration_since(entry.stored);
+        let remaining = entry.expires.saturating_duration_since(now);
+        if entry.prefetching || entry.hits < min_hits || remaining.is_zero() || remaining > ttl / 10
+        {
+            return false;
+        }
+        entry.prefetching = true;
+        self.prefetches.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Caches `message` as of `now` and returns how long it is kept, or `None` if
+    /// it was not cached.
+    fn insert_at(
+        &self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+        message: &DnsMessage,
+        now: Instant,
+    ) -> Option<Duration> {
+        if message.header.truncated() {
+            return None;
+        }
+        let ttl = response_ttl(message)?;
+        let key = canonical_name(name);
+        let ttl = self.ttl_policy.clamp(&key, ttl);
+        if ttl.is_zero() {
+            return None;
+        }
+        self.restore((key, query_type, class), message.clone(), now, now + ttl);
+        Some(ttl)
+    }
+
+    /// Stores an entry whose lifetime has already been worked out, evicting the
+    /// least recently used entries if the cache is over its limits.
+    fn restore(&self, key: CacheKey, message: DnsMessage, stored: Instant, expires: Instant) {
+        let size = wire_size(&message);
+        if self.max_entries == Some(0) || self.max_bytes.is_some_and(|max| size > max) {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(
+            key,
+            CacheEntry {
+                message,
+                stored,
+                expires,
+                size,
+                last_used: 0,
+                hits: 0,
+                prefetching: false,
+            },
+        );
+        while self.max_entries.is_some_and(|max| entries.len() > max)
+            || self.max_bytes.is_some_and(|max| entries.bytes > max)
+        {
+            if entries.remove_least_recently_used().is_none() {
+                break;
+            }
+            self.evictions.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn entries_at(&self, now: Instant) -> Vec<CachedResponse> {
+        let entries = self.entries.lock().unwrap();
+        let mut listed: Vec<CachedResponse> = entries
+            .map
+            .iter()
+            .filter(|(_, entry)| self.keep_until(entry.expires) > now)
+            .map(|((name, query_type, class), entry)| {
+                let ttl = entry.expires.saturating_duration_since(now).as_secs();
+                CachedResponse {
+                    name: name.clone(),
+                    query_type: *query_type,
+                    class: *class,
+                    message: entry.counted_down(now),
+                    ttl: u32::try_from(ttl).unwrap_or(u32::MAX),
+                    stale: entry.expires <= now,
+                }
+            })
+            .collect();
+        listed.sort_by_key(|entry| {
+            let (query_type, class) = (u16::from(entry.query_type), u16::from(entry.class));
+            (entry.name.clone(), query_type, class)
+        });
+        listed
+    }
+
+    /// Returns the entries that are still live, or may still be served stale, at
+    /// `now`.
+    fn live_entries(&self, now: Instant) -> Vec<(CacheKey, DnsMessage, Instant, Instant)> {
+        self.entries
+            .lock()
+            .unwrap()
+            .map
+            .iter()
+            .filter(|(_, entry)| self.keep_until(entry.expires) > now)
+            .map(|(key, entry)| {
+                let message = entry.message.clone();
+                (key.clone(), message, entry.stored, entry.expires)
+            })
+            .collect()
+    }
+}
+
+impl DnsCache for ResponseCache {
+    /// Returns the cached response to a question, with every TTL lowered by the
+    /// time it has spent in the cache, or `None` if there is none or it expired.
+    ///
+    /// Names are compared case-insensitively and with or without a trailing dot.
+    /// Every call counts as a hit or a miss in [`ResponseCache::stats`].
+    fn get(&self, name: &str, query_type: QueryType, class: DnsClass) -> Option<DnsMessage> {
+        self.get_at(name, query_type, class, Instant::now())
+    }
+
+    /// Returns an expired response that is still within the serve-stale window,
+    /// with every TTL set to [`STALE_ANSWER_TTL`] and an Extended DNS Error "Stale
+    /// Answer" option added, or `None` if serving stale is off.
+    fn get_stale(&self, name: &str, query_type: QueryType, class: DnsClass) -> Option<DnsMessage> {
+        self.get_stale_at(name, query_type, class, Instant::now())
+    }
+
+    /// Claims the refresh of an entry that was handed out often enough and is in
+    /// the last tenth of its TTL, once per entry, if prefetching is on.
+    fn claim_prefetch(&self, name: &str, query_type: QueryType, class: DnsClass) -> bool {
+        self.claim_prefetch_at(name, query_type, class, Instant::now())
+    }
+
+    /// Caches `message` as the response to a question, if it can be cached.
+    ///
+    /// Positive responses are kept for the smallest TTL among their answer
+    /// records, negative ones for the negative TTL of the SOA record in the
+    /// authority section, or [`DEFAULT_NEGATIVE_TTL`] without one. Responses whose
+    /// TTL comes to zero are not kept.
+    fn insert(&self, name: &str, query_type: QueryType, class: DnsClass, message: &DnsMessage) {
+        self.insert_at(name, query_type, class, message, Instant::now());
+    }
+
+    /// Returns the hit and miss counters and the current number of entries.
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            stale_hits: self.stale_hits.load(Ordering::Relaxed),
+            prefetches: self.prefetches.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+
+    fn entries(&self) -> Vec<CachedResponse> {
+        self.entries_at(Instant::now())
+    }
+
+    fn remove(&self, name: &str) -> usize {
+        let name = canonical_name(name);
+        let mut entries = self.entries.lock().unwrap();
+        let keys: Vec<CacheKey> = entries
+            .map
+            .keys()
+            .filter(|(cached, _, _)| *cached == name)
+            .cloned()
+            .collect();
+        for key in &keys {
+            entries.remove(key);
+        }
+        keys.len()
+    }
+
+    /// Removes every cached response. The counters are kept.
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// A [`ResponseCache`] that survives restarts by journaling its entries to a file.
+///
+/// Entries are served from memory; the file is only read when the cache is opened.
+/// An entry that cannot be written to the journal is still cached in memory, but
+/// does not survive a restart; [`FileCache::take_write_error`] tells when that
+/// happened.
+#[derive(Debug)]
+pub struct FileCache {
+    memory: ResponseCache,
+    path: PathBuf,
+    /// Held while changing `memory` and writing the change to the journal, so that
+    /// appends and rewrites reach the file in the order the changes were made.
+    journal: Mutex<Journal>,
+}
+
+/// The journal file of a [`FileCache`], opened for appending.
+#[derive(Debug)]
+struct Journal {
+    file: File,
+    /// The first write that failed since the journal last matched the entries.
+    error: Option<io::Error>,
+}
+
+impl Journal {
+    /// Replaces the journal with one holding exactly the live entries of `memory`.
+    fn rewrite(&mut self, path: &Path, memory: &ResponseCache) -> io::Result<()> {
+        self.file = compact(path, memory, Instant::now(), SystemTime::now())?;
+        self.error = None;
+        Ok(())
+    }
+
+    /// Keeps `error` for [`FileCache::take_write_error`], unless an earlier one is
+    /// already kept.
+    fn record(&mut self, error: io::Error) {
+        self.error.get_or_insert(error);
+    }
+}
+
+impl FileCache {
+    /// Opens the cache journaled at `path`, creating the file if it does not exist,
+    /// with the default [`TtlPolicy`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or rewritten.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::open_with_ttl_policy(path, TtlPolicy::default())
+    }
+
+    /// Opens the cache journaled at `path` like [`FileCache::open`], bounding how
+    /// long responses are kept with `ttl_policy`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or rewritten.
+    pub fn open_with_ttl_policy(path: impl AsRef<Path>, ttl_policy: TtlPolicy) -> io::Result<Self> {
+        Self::open_with(path, ResponseCache::with_ttl_policy(ttl_policy))
+    }
+
+    /// Opens the cache journaled at `path` like [`FileCache::open`], serving
+    /// entries from `memory`, which sets the TTL policy and limits.
+    ///
+    /// Entries in the journal are restored in the order they were written, so when
+    /// there are more than the limits allow, the oldest are left out.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or rewritten.
+    pub fn open_with(path: impl AsRef<Path>, memory: ResponseCache) -> io::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e),
+        };
+        let (now, wall_now) = (Instant::now(), SystemTime::now());
+        for line in text.lines() {
+            if let Some((key, message, stored, expires)) = parse_journal_line(line) {
+                // Wall-clock times map onto the monotonic clock of this process.
+                let expires = match expires.duration_since(wall_now) {
+                    Ok(remaining) => now + remaining,
+                    Err(e) => now.checked_sub(e.duration()).unwrap_or(now),
+                };
+                if memory.keep_until(expires) <= now {
+                    continue;
+                }
+                let age = wall_now.duration_since(stored).unwrap_or_default();
+                let stored = now.checked_sub(age).unwrap_or(now);
+                memory.restore(key, message, stored, expires);
+            }
+        }
+
+        let cache = FileCache {
+            journal: Mutex::new(Journal {
+                file: compact(&path, &memory, now, wall_now)?,
+                error: None,
+            }),
+            memory,
+            path,
+        };
+        Ok(cache)
+    }
+
+    /// Returns the path of the journal file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Removes the cached responses about `name`, or every response if `name` is
+    /// `None`, from memory and from the journal, and returns how many there were.
+    ///
+    /// Use this rather than [`DnsCache::remove`] or [`DnsCache::clear`] to learn
+    /// whether the journal could be rewritten; otherwise the removed responses come
+    /// back the next time the cache is opened. Another process that has the same
+    /// journal open keeps its own entries, and its later writes go to the journal
+    /// as it was before this call.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the journal cannot be rewritten.
+    pub fn flush(&self, name: Option<&str>) -> io::Result<usize> {
+        let mut journal = self.journal.lock().unwrap();
+        let removed = self.forget(name);
+        journal.rewrite(&self.path, &self.memory)?;
+        Ok(removed)
+    }
+
+    /// Returns the first failure to write the journal since the last call, or
+    /// `None` if every write succeeded.
+    ///
+    /// [`DnsCache::insert`], [`DnsCache::remove`] and [`DnsCache::clear`] cannot
+    /// return the error themselves, so it is kept here. Responses cached since then
+    /// may be missing from the journal until it is rewritten; a successful
+    /// [`FileCache::flush`] rewrites it and forgets the failure.
+    pub fn take_write_error(&self) -> Option<io::Error> {
+        self.journal.lock().unwrap().error.take()
+    }
+
+    /// Removes responses from memory and from the journal, returning how many
+    /// there were and keeping a failure to rewrite the journal.
+    fn forget_journaled(&self, name: Option<&str>) -> usize {
+        let mut journal = self.journal.lock().unwrap();
+        let removed = self.forget(name);
+        if let Err(e) = journal.rewrite(&self.path, &self.memory) {
+            journal.record(e);
+        }
+        removed
+    }
+
+    /// Removes responses from memory only, returning how many there were.
+    fn forget(&self, name: Option<&str>) -> usize {
+        match name {
+            Some(name) => self.memory.remove(name),
+            None => {
+                let count = self.memory.entries.lock().unwrap().len();
+                self.memory.clear();
+                count
+            }
+        }
+    }
+}
+
+impl DnsCache for FileCache {
+    fn get(&self, name: &str, query_type: QueryType, class: DnsClass) -> Option<DnsMessage> {
+        self.memory.get(name, query_type, class)
+    }
+
+    fn get_stale(&self, name: &str, query_type: QueryType, class: DnsClass) -> Option<DnsMessage> {
+        self.memory.get_stale(name, query_type, class)
+    }
+
+    fn claim_prefetch(&self, name: &str, query_type: QueryType, class: DnsClass) -> bool {
+        self.memory.claim_prefetch(name, query_type, class)
+    }
+
+    fn insert(&self, name: &str, query_type: QueryType, class: DnsClass, message: &DnsMessage) {
+        let mut journal = self.journal.lock().unwrap();
+        let Some(ttl) = self
+            .memory
+            .insert_at(name, query_type, class, message, Instant::now())
+        else {
+            return;
+        };
+        let stored = SystemTime::now();
+        let key = (canonical_name(name), query_type, class);
+        if let Some(line) = journal_line(&key, message, stored, stored + ttl)
+            && let Err(e) = journal.file.write_all(line.as_bytes())
+        {
+            journal.record(e);
+        }
+    }
+
+    fn stats(&self) -> CacheStats {
+        self.memory.stats()
+    }
+
+    fn entries(&self) -> Vec<CachedResponse> {
+        self.memory.entries()
+    }
+
+    fn remove(&self, name: &str) -> usize {
+        self.forget_journaled(Some(name))
+    }
+
+    fn clear(&self) {
+        self.forget_journaled(None);
+    }
+}
+
+/// Rewrites the journal at `path` with the live entries of `memory` and returns it
+/// opened for appending.
+fn compact(
+    path: &Path,
+    memory: &ResponseCache,
+    now: Instant,
+    wall_now: SystemTime,
+) -> io::Result<File> {
+    let mut text = String::from("# dns-resolver cache: stored expires class type name response\n");
+    for (key, message, stored, expires) in memory.live_entries(now) {
+        let stored = wall_now - now.duration_since(stored);
+        let expires = match expires.checked_duration_since(now) {
+            Some(remaining) => wall_now + remaining,
+            None => wall_now - now.duration_since(expires),
+        };
+        text.extend(journal_line(&key, &message, stored, expires));
+    }
+    // Write a new file and move it into place, so a crash leaves the old one whole.
+    let mut temporary = path.as_os_str().to_os_string();
+    temporary.push(".tmp");
+    fs::write(&temporary, text)?;
+    fs::rename(&temporary, path)?;
+    OpenOptions::new().append(true).open(path)
+}
+
+fn journal_line(
+    (name, query_type, class): &CacheKey,
+    message: &DnsMessage,
+    stored: SystemTime,
+    expires: SystemTime,
+) -> Option<String> {
+    // Packing trusts the header counts, which a message built in code may not have
+    // kept up to date.
+    let mut message = message.clone();
+    message.header.question_count = message.questions.len() as u16;
+    message.header.answer_count = message.answers.len() as u16;
+    message.header.authority_count = message.authorities.len() as u16;
+    message.header.additional_count = message.additionals.len() as u16;
+    let mut bytes = Vec::new();
+    message.pack(&mut bytes).ok()?;
+    let unix = |time: SystemTime| time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    Some(format!(
+        "{} {} {} {} {} {}\n",
+        unix(stored),
+        unix(expires),
+        u16::from(*class),
+        u16::from(*query_type),
+        name,
+        encode_hex(&bytes)
+    ))
+}
+
+fn parse_journal_line(line: &str) -> Option<(CacheKey, DnsMessage, SystemTime, SystemTime)> {
+    let line = line.trim();
+    if line.starts_with('#') {
+        return None;
+    }
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    let [stored, expires, class, query_type, name, response] = fields[..] else {
+        return None;
+    };
+    let unix = |field: &str| Some(UNIX_EPOCH + Duration::from_secs(field.parse().ok()?));
+    let class = DnsClass::from(class.parse::<u16>().ok()?);
+    let query_type = QueryType::from(query_type.parse::<u16>().ok()?);
+    let message = DnsMessage::from_bytes(&decode_hex(response)?).ok()?;
+    Some((
+        (name.to_string(), query_type, class),
+        message,
+        unix(stored)?,
+        unix(expires)?,
+    ))
+}
+
+/// Returns how long a response may be cached before the policy applies, or `None`
+/// if it must not be cached at all.
+fn response_ttl(message: &DnsMessage) -> Option<Duration> {
+    let seconds = match message.header.get_response_code() {
+        ResponseCode::NoError if !message.answers.is_empty() => {
+            message.answers.iter().map(|record| record.ttl).min()
+        }
+        ResponseCode::NoError | ResponseCode::NameError => {
+            // The negative TTL is the smaller of the SOA record's own TTL and its
+            // MINIMUM field (RFC 2308 section 5).
+            message.authorities.iter().find_map(negative_ttl)
+        }
+        _ => return None,
+    };
+    Some(seconds.map_or(DEFAULT_NEGATIVE_TTL, |seconds| {
+        Duration::from_secs(seconds.into())
+    }))
+}
+
+/// Checks whether a response was served stale from a cache, as marked by an
+/// Extended DNS Error "Stale Answer" option.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::cache::{ResponseCache, is_stale_answer};
+/// use dns_resolver::dns::QueryType;
+/// use dns_resolver::resolver::Resolver;
+/// use std::net::Ipv4Addr;
+/// use std::time::Duration;
+///
+/// let resolver = Resolver::builder()
+///     .server(Ipv4Addr::new(192, 0, 2, 53))
+///     .serve_stale(Duration::from_secs(86400))
+///     .build()?;
+/// let response = resolver.resolve("example.com", QueryType::A)?;
+/// if is_stale_answer(&response) {
+///     eprintln!("the server is unreachable; serving an expired answer");
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn is_stale_answer(message: &DnsMessage) -> bool {
+    message.edns().is_some_and(|edns| {
+        edns.options.iter().any(|option| {
+            option.code == EDNS_EXTENDED_ERROR
+                && option.data.get(..2) == Some(&EXTENDED_ERROR_STALE_ANSWER.to_be_bytes())
+        })
+    })
+}
+
+/// Adds an Extended DNS Error "Stale Answer" option to a response, and an OPT
+/// record to carry it if the response has none.
+fn mark_stale(message: &mut DnsMessage) {
+    let option = EdnsOption {
+        code: EDNS_EXTENDED_ERROR,
+        data: EXTENDED_ERROR_STALE_ANSWER.to_be_bytes().to_vec(),
+    };
+    let opt = message
+        .additionals
+        .iter_mut()
+        .find(|record| record.rtype == QueryType::OPT);
+    match opt {
+        Some(ResourceRecord {
+            data: RData::OPT(options),
+            ..
+        }) => options.push(option),
+        _ => {
+            let mut edns = Edns::new(512);
+            edns.options.push(option);
+            message.additionals.push(edns.to_record());
+            message.header.additional_count += 1;
+        }
+    }
+}
+
+/// Returns the size of a message on the wire, or an upper bound for a UDP response
+/// if it cannot be encoded.
+fn wire_size(message: &DnsMessage) -> usize {
+    let mut buffer = Vec::new();
+    match message.pack(&mut buffer) {
+        Ok(()) => buffer.len(),
+        Err(_) => 512,
+    }
+}
+
+/// Returns the negative TTL an SOA record sets, or `None` for any other record.
+fn negative_ttl(record: &ResourceRecord) -> Option<u32> {
+    match record.data {
+        RData::SOA { minimum, .. } => Some(record.ttl.min(minimum)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DnsQuestion;
+    use crate::resolver::{DnsError, DnsErrorKind, Resolver};
+    use crate::testutil::{a_record, record};
+    use crate::transport::{MockTransport, Reply};
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    fn soa(ttl: u32, minimum: u32) -> ResourceRecord {
+        record(
+            "example.com",
+            ttl,
+            RData::SOA {
+                mname: "ns1.example.com".to_string(),
+                rname: "hostmaster.example.com".to_string(),
+                serial: 1,
+                refresh: 7200,
+                retry: 900,
+                expire: 1209600,
+                minimum,
+            },
+        )
+    }
+
+    fn response(code: ResponseCode, answers: Vec<ResourceRecord>) -> DnsMessage {
+        let mut message = DnsMessage::new();
+        message.header.set_response(true);
+        message.header.set_response_code(code);
+        message.questions.push(DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::A,
+            qclass: 1,
+        });
+        message.answers = answers;
+        message
+    }
+
+    #[test]
+    fn test_ttls_count_down_until_expiry() {
+        let cache = ResponseCache::new();
+        let message = response(
+            ResponseCode::NoError,
+            vec![
+                a_record("example.com", [192, 0, 2, 1], 300),
+                a_record("example.com", [192, 0, 2, 2], 60),
+            ],
+        );
+        let stored = Instant::now();
+        cache.insert_at("Example.com.", QueryType::A, DnsClass::IN, &message, stored);
+
+        let later = stored + Duration::from_secs(45);
+        let cached = cache
+            .get_at("example.com", QueryType::A, DnsClass::IN, later)
+            .unwrap();
+        let ttls: Vec<u32> = cached.answers.iter().map(|r| r.ttl).collect();
+        assert_eq!(ttls, [255, 15]);
+
+        // Other types and classes are other questions.
+        assert!(
+            cache
+                .get_at("example.com", QueryType::AAAA, DnsClass::IN, later)
+                .is_none()
+        );
+        assert!(
+            cache
+                .get_at("example.com", QueryType::A, DnsClass::CH, later)
+                .is_none()
+        );
+
+        // The smallest TTL decides when the response expires.
+        let expired = stored + Duration::from_secs(60);
+        assert!(
+            cache
+                .get_at("example.com", QueryType::A, DnsClass::IN, expired)
+                .is_none()
+        );
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!((cache.stats().hits, cache.stats().misses), (1, 3));
+    }
+
+    #[test]
+    fn test_negative_and_error_responses() {
+        let cache = ResponseCache::new();
+        let now = Instant::now();
+
+        // NXDOMAIN is kept for the SOA's MINIMUM when that is below its TTL.
+        let mut nxdomain = response(ResponseCode::NameError, Vec::new());
+        nxdomain.authorities.push(soa(3600, 120));
+        cache.insert_at("gone.example", QueryType::A, DnsClass::IN, &nxdomain, now);
+        let in_time = now + Duration::from_secs(119);
+        assert!(
+            cache
+                .get_at("gone.example", QueryType::A, DnsClass::IN, in_time)
+                .is_some()
+        );
+        let too_late = now + Duration::from_secs(120);
+        assert!(
+            cache
+                .get_at("gone.example", QueryType::A, DnsClass::IN, too_late)
+                .is_none()
+        );
+        assert_eq!(cache.stats().negative_hits, 1);
+
+        // Without an SOA record, a default negative TTL applies.
+        let nodata = response(ResponseCode::NoError, Vec::new());
+        assert_eq!(response_ttl(&nodata), Some(DEFAULT_NEGATIVE_TTL));
+
+        // Failures and answers with a TTL of zero are not kept.
+        let servfail = response(ResponseCode::ServerFailure, Vec::new());
+        cache.insert_at("broken.example", QueryType::A, DnsClass::IN, &servfail, now);
+        let volatile = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 0)],
+        );
+        cache.insert_at(
+            "volatile.example",
+            QueryType::A,
+            DnsClass::IN,
+            &volatile,
+            now,
+        );
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_least_recently_used_entries_are_evicted() {
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 300)],
+        );
+        let cache = ResponseCache::new().with_max_entries(2);
+        cache.insert("a.example", QueryType::A, DnsClass::IN, &message);
+        cache.insert("b.example", QueryType::A, DnsClass::IN, &message);
+        // Using a.example makes b.example the one to go.
+        assert!(cache.get("a.example", QueryType::A, DnsClass::IN).is_some());
+        cache.insert("c.example", QueryType::A, DnsClass::IN, &message);
+        assert!(cache.get("b.example", QueryType::A, DnsClass::IN).is_none());
+        assert!(cache.get("a.example", QueryType::A, DnsClass::IN).is_some());
+        assert!(cache.get("c.example", QueryType::A, DnsClass::IN).is_some());
+        assert_eq!((cache.stats().entries, cache.stats().evictions), (2, 1));
+
+        // Replacing an entry does not count as an eviction.
+        cache.insert("c.example", QueryType::A, DnsClass::IN, &message);
+        assert_eq!((cache.stats().entries, cache.stats().evictions), (2, 1));
+
+        // A memory budget of two and a half responses holds two of them, and no
+        // response at all if it is smaller than one.
+        let size = wire_size(&message);
+        let cache = ResponseCache::new().with_max_bytes(size * 5 / 2);
+        for name in ["a.example", "b.example", "c.example"] {
+            cache.insert(name, QueryType::A, DnsClass::IN, &message);
+        }
+        assert_eq!((cache.stats().entries, cache.stats().evictions), (2, 1));
+        let cache = ResponseCache::new().with_max_bytes(size - 1);
+        cache.insert("a.example", QueryType::A, DnsClass::IN, &message);
+        assert_eq!((cache.stats().entries, cache.stats().evictions), (0, 0));
+    }
+
+    #[test]
+    fn test_expired_responses_are_served_stale_within_the_window() {
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 60)],
+        );
+        let cache = ResponseCache::new().with_serve_stale(Duration::from_secs(3600));
+        let stored = Instant::now();
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+
+        // Fresh responses are not served as stale ones.
+        let fresh = stored + Duration::from_secs(30);
+        assert!(
+            cache
+                .get_stale_at("example.com", QueryType::A, DnsClass::IN, fresh)
+                .is_none()
+        );
+
+        // Once expired, the response is a miss but can still be served stale.
+        let expired = stored + Duration::from_secs(600);
+        assert!(
+            cache
+                .get_at("example.com", QueryType::A, DnsClass::IN, expired)
+                .is_none()
+        );
+        let stale = cache
+            .get_stale_at("example.com", QueryType::A, DnsClass::IN, expired)
+            .unwrap();
+        assert_eq!(stale.answers[0].ttl, STALE_ANSWER_TTL);
+        assert!(is_stale_answer(&stale));
+        assert!(!is_stale_answer(&message));
+        assert_eq!(cache.stats().stale_hits, 1);
+
+        // Past the window it is gone.
+        let too_late = stored + Duration::from_secs(60 + 3600);
+        assert!(
+            cache
+                .get_stale_at("example.com", QueryType::A, DnsClass::IN, too_late)
+                .is_none()
+        );
+        cache.get_at("example.com", QueryType::A, DnsClass::IN, too_late);
+        assert_eq!(cache.stats().entries, 0);
+
+        // Without serve-stale, expired responses are dropped right away.
+        let cache = ResponseCache::new();
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+        assert!(
+            cache
+                .get_stale_at("example.com", QueryType::A, DnsClass::IN, expired)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_resolver_serves_stale_when_the_server_fails() {
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 60)],
+        );
+        let cache = ResponseCache::new().with_serve_stale(Duration::from_secs(86400));
+        let long_ago = Instant::now() - Duration::from_secs(300);
+        for name in ["example.com", "example.net", "example.org", "example.edu"] {
+            cache.insert_at(name, QueryType::A, DnsClass::IN, &message, long_ago);
+        }
+        let mock = Arc::new(
+            MockTransport::new()
+                .reply("example.com", QueryType::A, Reply::Drop)
+                .reply(
+                    "example.net",
+                    QueryType::A,
+                    Reply::Code(ResponseCode::ServerFailure),
+                )
+                .reply(
+                    "example.org",
+                    QueryType::A,
+                    Reply::Code(ResponseCode::NameError),
+                ),
+        );
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::new(192, 0, 2, 53))
+            .attempts(1)
+            .mock_transport(mock)
+            .cache(Arc::new(cache))
+            .build()
+            .unwrap();
+
+        for name in ["example.com", "example.net"] {
+            let response = resolver.resolve(name, QueryType::A).unwrap();
+            assert!(is_stale_answer(&response));
+            assert_eq!(response.answers[0].ttl, STALE_ANSWER_TTL);
+        }
+        // A definite answer from the server wins over a stale one.
+        assert!(matches!(
+            resolver.resolve("example.org", QueryType::A),
+            Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+        ));
+        // So does a configuration error: the mock has no reply for this name, and
+        // a stale answer would hide the mistake.
+        match resolver.resolve("example.edu", QueryType::A) {
+            Err(e) => assert_eq!(e.kind(), DnsErrorKind::Configuration),
+            Ok(response) => panic!("expected a configuration error, got {:?}", response),
+        }
+        assert_eq!(resolver.response_cache().unwrap().stats().stale_hits, 2);
+    }
+
+    #[test]
+    fn test_popular_entries_are_claimed_for_prefetch_once() {
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 100)],
+        );
+        let cache = ResponseCache::new().with_prefetch(2);
+        let stored = Instant::now();
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+        let claim = |seconds| {
+            let now = stored + Duration::from_secs(seconds);
+            cache.get_at("example.com", QueryType::A, DnsClass::IN, now);
+            cache.claim_prefetch_at("example.com", QueryType::A, DnsClass::IN, now)
+        };
+
+        // Too early in the TTL, then not yet popular enough.
+        assert!(!claim(50));
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+        assert!(!claim(95));
+        // The second hit in the last tenth of the TTL claims it, and only once.
+        assert!(claim(96));
+        assert!(!claim(97));
+        assert_eq!(cache.stats().prefetches, 1);
+
+        // Without prefetch, nothing is ever claimed.
+        let cache = ResponseCache::new();
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+        let now = stored + Duration::from_secs(95);
+        for _ in 0..3 {
+            cache.get_at("example.com", QueryType::A, DnsClass::IN, now);
+        }
+        assert!(!cache.claim_prefetch_at("example.com", QueryType::A, DnsClass::IN, now));
+    }
+
+    #[test]
+    fn test_resolver_refreshes_popular_entries_in_the_background() {
+        let cache = ResponseCache::new().with_prefetch(1);
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 100)],
+        );
+        let stored = Instant::now() - Duration::from_secs(95);
+        cache.insert_at("example.com", QueryType::A, DnsClass::IN, &message, stored);
+        let mock = Arc::new(MockTransport::new().answer(
+            "example.com",
+            QueryType::A,
+            vec![a_record("example.com", [192, 0, 2, 2], 300)],
+        ));
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::new(192, 0, 2, 53))
+            .mock_transport(Arc::clone(&mock))
+            .cache(Arc::new(cache))
+            .build()
+            .unwrap();
+
+        // The lookup is answered from the cache right away...
+        let response = resolver.resolve("example.com", QueryType::A).unwrap();
+        assert_eq!(response.answers[0].ttl, 5);
+
+        // ...and the refreshed response replaces it shortly after.
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            let response = resolver.resolve("example.com", QueryType::A).unwrap();
+            if response.answers[0].ttl > 100 {
+                break;
+            }
+            assert!(Instant::now() < deadline, "the entry was not refreshed");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(mock.queries().len(), 1);
+        let stats = resolver.response_cache().unwrap().stats();
+        assert_eq!((stats.misses, stats.prefetches), (0, 1));
+    }
+
+    #[test]
+    fn test_resolver_answers_repeated_lookups_from_the_cache() {
+        let mock = Arc::new(
+            MockTransport::new()
+                .answer(
+                    "example.com",
+                    QueryType::A,
+                    vec![a_record("example.com", [192, 0, 2, 1], 300)],
+                )
+                .reply(
+                    "gone.example",
+                    QueryType::A,
+                    Reply::Code(ResponseCode::NameError),
+                ),
+        );
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::new(192, 0, 2, 53))
+            .mock_transport(Arc::clone(&mock))
+            .build()
+            .unwrap();
+
+        for _ in 0..3 {
+            let response = resolver.resolve("example.com", QueryType::A).unwrap();
+            assert_eq!(response.answers.len(), 1);
+            assert!(matches!(
+                resolver.resolve("gone.example", QueryType::A),
+                Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+            ));
+        }
+        assert_eq!(mock.queries().len(), 2);
+        let stats = resolver.response_cache().unwrap().stats();
+        assert_eq!((stats.hits, stats.negative_hits, stats.misses), (4, 2, 2));
+
+        // Without a cache, every lookup goes out.
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::new(192, 0, 2, 53))
+            .mock_transport(Arc::clone(&mock))
+            .response_cache(false)
+            .build()
+            .unwrap();
+        resolver.resolve("example.com", QueryType::A).unwrap();
+        resolver.resolve("example.com", QueryType::A).unwrap();
+        assert!(resolver.response_cache().is_none());
+        assert_eq!(mock.queries().len(), 4);
+    }
+
+    #[test]
+    fn test_file_cache_survives_reopening() {
+        let path =
+            std::env::temp_dir().join(format!("dns-resolver-cache-{}.journal", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 300)],
+        );
+
+        let cache = FileCache::open(&path).unwrap();
+        cache.insert("Example.com.", QueryType::A, DnsClass::IN, &message);
+        drop(cache);
+
+        // An expired entry and a line cut short by a crash are dropped on opening.
+        let mut journal = OpenOptions::new().append(true).open(&path).unwrap();
+        writeln!(journal, "1 2 1 1 expired.example 0000").unwrap();
+        write!(journal, "1 4102444800 1 1 torn.exa").unwrap();
+        drop(journal);
+
+        let cache = FileCache::open(&path).unwrap();
+        assert_eq!(cache.stats().entries, 1);
+        let cached = cache
+            .get("example.com", QueryType::A, DnsClass::IN)
+            .unwrap();
+        assert_eq!(cached.answers[0].data, message.answers[0].data);
+        // The journal keeps whole seconds.
+        assert!((299..=300).contains(&cached.answers[0].ttl));
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().filter(|l| !l.starts_with('#')).count(), 1);
+
+        // Flushing a name removes it from the journal too.
+        cache.insert("example.net", QueryType::MX, DnsClass::IN, &message);
+        assert_eq!(cache.flush(Some("EXAMPLE.com.")).unwrap(), 1);
+        drop(cache);
+        let cache = FileCache::open(&path).unwrap();
+        let names: Vec<String> = cache.entries().into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["example.net"]);
+
+        // So does clearing the cache.
+        cache.clear();
+        drop(cache);
+        let cache = FileCache::open(&path).unwrap();
+        assert_eq!(cache.stats().entries, 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_file_cache_reports_journal_write_failures() {
+        let path = std::env::temp_dir().join(format!(
+            "dns-resolver-cache-failing-{}.journal",
+            std::process::id()
+        ));
+        let mut temporary = path.as_os_str().to_os_string();
+        temporary.push(".tmp");
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 300)],
+        );
+
+        let cache = FileCache::open(&path).unwrap();
+        cache.insert("example.com", QueryType::A, DnsClass::IN, &message);
+        assert!(cache.take_write_error().is_none());
+
+        // A directory where the rewritten journal goes makes every rewrite fail.
+        fs::create_dir(&temporary).unwrap();
+        cache.clear();
+        assert_eq!(cache.stats().entries, 0);
+        assert!(cache.take_write_error().is_some());
+        assert!(cache.take_write_error().is_none());
+        assert!(cache.flush(None).is_err());
+
+        fs::remove_dir(&temporary).unwrap();
+        cache.remove("example.com");
+        assert!(cache.take_write_error().is_none());
+        assert_eq!(cache.flush(None).unwrap(), 0);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_entries_are_listed_and_removed_by_name() {
+        let message = response(
+            ResponseCode::NoError,
+            vec![a_record("example.com", [192, 0, 2, 1], 300)],
+        );
+        let cache = ResponseCache::new().with_serve_stale(Duration::from_secs(3600));
+        let stored = Instant::now();
+        cache.insert_at("b.example", QueryType::A, DnsClass::IN, &message, stored);
+        cache.insert_at("a.example", QueryType::MX, DnsClass::IN, &message, stored);
+        cache.insert_at("a.example", QueryType::A, DnsClass::IN, &message, stored);
+
+        let entries = cache.entries_at(stored + Duration::from_secs(100));
+        let listed: Vec<(&str, QueryType, u32, bool)> = entries
+            .iter()
+            .map(|e| (e.name.as_str(), e.query_type, e.ttl, e.stale))
+            .collect();
+        assert_eq!(
+            listed,
+            [
+                ("a.example", QueryType::A, 200, false),
+                ("a.example", QueryType::MX, 200, false),
+                ("b.example", QueryType::A, 200, false),
+            ]
+        );
+        assert_eq!(entries[0].message.answers[0].ttl, 200);
+        let stale = cache.entries_at(stored + Duration::from_secs(400));
+        assert!(stale.iter().all(|e| e.stale && e.ttl == 0));
+        // Listing is not a lookup.
+        assert_eq!((cache.stats().hits, cache.stats().misses), (0, 0));
+
+        let answer = entries[2].message.answers[0].to_string();
+        assert_eq!(
+            entries[2].format_lines(OutputFormat::Text),
+            [
+                "b.example IN A NOERROR, expires in 200s".to_string(),
+                format!("  {}", answer),
+            ]
+        );
+        assert_eq!(
+            entries[2].format_lines(OutputFormat::Csv),
+            [format!("b.example,IN,A,NOERROR,200,false,{}", answer)]
+        );
+
+        assert_eq!(cache.remove("A.Example."), 2);
+        assert_eq!(cache.remove("a.example"), 0);
+        assert_eq!(cache.stats().entries, 1);
+    }
+}
@@ -0,0 +1,213 @@
+//! CNAME chain reconstruction.
+//!
+//! A recursive resolver that follows aliases returns every CNAME it passed through in
+//! the answer section, but not necessarily in order. This module rebuilds the chain
+//! from the queried name to the final target so it can be shown hop by hop with each
+//! hop's TTL, and flags chains that loop back on themselves or run longer than any
+//! sane configuration needs.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::chain::follow_cname_chain;
+//!
+//! # let answers = Vec::new();
+//! let chain = follow_cname_chain("www.example.com", &answers);
+//! for hop in &chain.hops {
+//!     println!("{} -> {} (TTL {})", hop.alias, hop.target, hop.ttl);
+//! }
+//! ```
+
+use std::fmt;
+
+use crate::dns::{RData, ResourceRecord};
+use crate::validation::names_equal;
+
+/// The number of hops after which a chain is reported as too deep.
+///
+/// Common resolvers give up somewhere between 8 and 16 aliases, so a longer chain
+/// is fragile even where it still resolves.
+pub const MAX_CHAIN_DEPTH: usize = 8;
+
+/// One alias in a CNAME chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CnameHop {
+    /// The owner name of the CNAME record, as spelled in the response.
+    pub alias: String,
+    /// The canonical name it points to.
+    pub target: String,
+    /// The TTL of the CNAME record.
+    pub ttl: u32,
+}
+
+/// A problem found while following a chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChainProblem {
+    /// The chain points back to a name already on it.
+    Loop {
+        /// The name that was reached a second time.
+        name: String,
+    },
+    /// The chain has more than [`MAX_CHAIN_DEPTH`] hops.
+    TooDeep {
+        /// The number of hops followed before giving up.
+        hops: usize,
+    },
+}
+
+impl fmt::Display for ChainProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ChainProblem::Loop { name } => {
+                write!(f, "CNAME loop: the chain points back to {}", name)
+            }
+            ChainProblem::TooDeep { hops } => write!(
+                f,
+                "CNAME chain is longer than {} hops (stopped after {})",
+                MAX_CHAIN_DEPTH, hops
+            ),
+        }
+    }
+}
+
+/// A CNAME chain as reconstructed from an answer section.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CnameChain {
+    /// The name the chain starts from.
+    pub start: String,
+    /// The aliases followed, in order from the queried name.
+    pub hops: Vec<CnameHop>,
+    /// Set if the chain loops or is too deep.
+    pub problem: Option<ChainProblem>,
+}
+
+impl CnameChain {
+    /// Returns the name at the end of the chain, where the answer records live.
+    pub fn final_target(&self) -> &str {
+        self.hops
+            .last()
+            .map_or(self.start.as_str(), |hop| hop.target.as_str())
+    }
+}
+
+/// Follows the CNAME records in `answers`, starting from `qname`.
+///
+/// Records that are not part of the chain are ignored. When an alias has several
+/// CNAME records, which is itself a protocol violation, the first one is followed.
+///
+/// # Arguments
+///
+/// * `qname` - The name that was queried
+/// * `answers` - The answer section of the response
+pub fn follow_cname_chain(qname: &str, answers: &[ResourceRecord]) -> CnameChain {
+    let mut hops: Vec<CnameHop> = Vec::new();
+    let mut current = qname.to_string();
+    let mut problem = None;
+
+    while let Some((record, target)) = answers.iter().find_map(|record| match &record.data {
+        RData::CNAME(target) if names_equal(&record.name, &current) => Some((record, target)),
+        _ => None,
+    }) {
+        if hops.len() == MAX_CHAIN_DEPTH {
+            problem = Some(ChainProblem::TooDeep { hops: hops.len() });
+            break;
+        }
+        // Every name reached so far is the query name or the target of an earlier hop.
+        let seen =
+            names_equal(target, qname) || hops.iter().any(|hop| names_equal(&hop.target, target));
+        hops.push(CnameHop {
+            alias: record.name.to_string(),
+            target: target.clone(),
+            ttl: record.ttl,
+        });
+        if seen {
+            problem = Some(ChainProblem::Loop {
+                name: target.clone(),
+            });
+            break;
+        }
+        current = target.clone();
+    }
+
+    CnameChain {
+        start: qname.to_string(),
+        hops,
+        problem,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::QueryType;
+    use crate::testutil::a_record;
+
+    fn cname(alias: &str, target: &str, ttl: u32) -> ResourceRecord {
+        ResourceRecord {
+            name: alias.parse().unwrap(),
+            rtype: QueryType::CNAME,
+            rclass: 1,
+            ttl,
+            data: RData::CNAME(target.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_chain_is_ordered_from_the_query_name() {
+        let answers = vec![
+            a_record("edge.example.org", [192, 0, 2, 1], 20),
+            cname("cdn.example.net", "edge.example.org", 60),
+            cname("WWW.example.com.", "cdn.example.net", 300),
+        ];
+        let chain = follow_cname_chain("www.example.com", &answers);
+        assert_eq!(chain.problem, None);
+        assert_eq!(chain.hops.len(), 2);
+        assert_eq!(chain.hops[0].ttl, 300);
+        assert_eq!(chain.hops[1].alias, "cdn.example.net");
+        assert_eq!(chain.final_target(), "edge.example.org");
+    }
+
+    #[test]
+    fn test_loop_is_detected() {
+        let answers = vec![
+            cname("a.example.com", "b.example.com", 60),
+            cname("b.example.com", "c.example.com", 60),
+            cname("c.example.com", "b.example.com", 60),
+        ];
+        let chain = follow_cname_chain("a.example.com", &answers);
+        assert_eq!(chain.hops.len(), 3);
+        assert_eq!(
+            chain.problem,
+            Some(ChainProblem::Loop {
+                name: "b.example.com".to_string()
+            })
+        );
+
+        let self_loop = follow_cname_chain(
+            "a.example.com",
+            &[cname("a.example.com", "a.example.com", 1)],
+        );
+        assert!(matches!(self_loop.problem, Some(ChainProblem::Loop { .. })));
+    }
+
+    #[test]
+    fn test_excessive_depth_is_flagged() {
+        let answers: Vec<ResourceRecord> = (0..12)
+            .map(|i| {
+                cname(
+                    &format!("n{}.example.com", i),
+                    &format!("n{}.example.com", i + 1),
+                    60,
+                )
+            })
+            .collect();
+        let chain = follow_cname_chain("n0.example.com", &answers);
+        assert_eq!(chain.hops.len(), MAX_CHAIN_DEPTH);
+        assert_eq!(
+            chain.problem,
+            Some(ChainProblem::TooDeep {
+                hops: MAX_CHAIN_DEPTH
+            })
+        );
+    }
+}
@@ -0,0 +1,1316 @@
+//! Command-line argument parsing.
+//!
+//! This module turns the raw process arguments into a [`CliArgs`] structure that the
+//! entry point in `main.rs` can act on. Positional arguments select the [`Command`]:
+//! by default a domain name and optional record type to look up, or a subcommand name
+//! followed by its operands. `--flag value` options and bare `--switch` flags adjust
+//! how queries are sent and apply to every command.
+//!
+//! # Examples
+//!
+//! ```bash
+//! dns-resolver google.com AAAA --source 192.0.2.10
+//! dns-resolver internal.example MX --interface eth1
+//! dns-resolver example.com A --duplicate-window 500
+//! dns-resolver example.com A --strict
+//! dns-resolver example.com TXT --tcp
+//! dns-resolver example.com TXT --edns 1232
+//! dns-resolver example.com A --ad --require-ad
+//! dns-resolver example.com A --validate
+//! dns-resolver example.com DNSKEY +dnssec +cd
+//! dns-resolver example.com --all
+//! dns-resolver -x 2001:db8::1
+//! dns-resolver bücher.example A --idn-out unicode
+//! dns-resolver example.com MX --human-ttl --ttl-expiry
+//! dns-resolver example.com A --consensus 1.1.1.1,8.8.8.8,9.9.9.9 --quorum 2
+//! dns-resolver example.com A --record bug.transcript
+//! dns-resolver example.com A --replay bug.transcript
+//! dns-resolver build.corp.example --hosts-file ./test-hosts
+//! dns-resolver asn 142.250.187.206
+//! dns-resolver report example.com --format json
+//! dns-resolver assert example.com A --equals 203.0.113.10 --max-ttl 300
+//! dns-resolver sweep 192.0.2.0/24 --concurrency 4 --rate 10 --format csv
+//! dns-resolver open-resolvers 192.168.1.0/24 --probe-name example.org
+//! dns-resolver axfr example.com --server 192.0.2.53 --output example.com.zone
+//! dns-resolver axfr example.com --server 192.0.2.53 --tsig-keyfile transfer.key
+//! dns-resolver example.com SOA --server 192.0.2.53 --tsig hmac-sha256:transfer-key:c2VjcmV0
+//! dns-resolver notify example.com 2024010102 --server 192.0.2.54
+//! dns-resolver notify-listen 0.0.0.0:5353
+//! ```
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use dns_resolver::assertion::Assertion;
+use dns_resolver::dns::{DnsClass, EdnsOption, QueryType, reverse_lookup_name};
+use dns_resolver::hosts::HOSTS_PATH;
+use dns_resolver::idn::IdnOutput;
+use dns_resolver::output::{OutputFormat, TtlFormat};
+use dns_resolver::sweep::Cidr;
+use dns_resolver::tsig::TsigKey;
+
+/// The operation selected on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Resolve a name (`<domain_name> [record_type]`). This is the default.
+    Lookup {
+        /// The domain name to resolve.
+        domain_name: String,
+        /// The record type to query, defaulting to [`QueryType::A`].
+        query_type: QueryType,
+    },
+    /// Resolve every commonly used record type of a name (`<domain_name> --all`).
+    LookupAll {
+        /// The domain name to resolve.
+        domain_name: String,
+    },
+    /// Find the originating AS of an address (`asn <address>`).
+    Asn {
+        /// The IPv4 or IPv6 address to look up.
+        address: IpAddr,
+    },
+    /// Summarize a domain's records, DNSSEC status and mail posture
+    /// (`report <domain_name>`).
+    Report {
+        /// The domain to report on.
+        domain_name: String,
+    },
+    /// Check the answer to a lookup against [`CliArgs::assertions`]
+    /// (`assert <domain_name> [record_type]`).
+    Assert {
+        /// The domain name to resolve.
+        domain_name: String,
+        /// The record type to query, defaulting to [`QueryType::A`].
+        query_type: QueryType,
+    },
+    /// Look up the PTR records of every address in a prefix (`sweep <cidr>`).
+    Sweep {
+        /// The prefix to sweep.
+        cidr: Cidr,
+    },
+    /// Find hosts in a prefix that answer recursive queries for anyone
+    /// (`open-resolvers <cidr>`).
+    OpenResolvers {
+        /// The IPv4 prefix to scan.
+        targets: Cidr,
+    },
+    /// Transfer a whole zone from an authoritative server over TCP (`axfr <zone>`,
+    /// or `<zone> AXFR`).
+    Axfr {
+        /// The name of the zone to transfer.
+        zone: String,
+    },
+    /// Tell a server that a zone changed (`notify <zone> [serial]`).
+    Notify {
+        /// The name of the zone that changed.
+        zone: String,
+        /// The zone's new serial, sent along as a hint.
+        serial: Option<u32>,
+    },
+    /// Acknowledge and print the zone change notifications sent to an address
+    /// (`notify-listen <address:port>`).
+    NotifyListen {
+        /// The local address and port to receive notifications on.
+        address: SocketAddr,
+    },
+    /// List the responses in the persistent cache given with
+    /// [`CliArgs::cache_file`] (`cache dump`).
+    CacheDump,
+    /// Remove the responses about a name, or all of them, from the persistent
+    /// cache (`cache flush [name]`).
+    CacheFlush {
+        /// The name whose responses to remove, of any type; all if `None`.
+        name: Option<String>,
+    },
+}
+
+/// Parsed command-line arguments for a single resolver invocation.
+#[derive(Debug, Clone)]
+pub struct CliArgs {
+    /// The operation to perform.
+    pub command: Command,
+    /// Local address to bind outgoing sockets to (`--source`).
+    pub source_address: Option<Ipv4Addr>,
+    /// Network interface to bind outgoing sockets to (`--interface`).
+    pub interface: Option<String>,
+    /// How long to keep listening for duplicate responses (`--duplicate-window <ms>`).
+    pub duplicate_window: Option<Duration>,
+    /// Reject responses with any protocol irregularity (`--strict`).
+    pub strict: bool,
+    /// Set the DNSSEC OK bit in an EDNS(0) OPT record (`--dnssec` or `+dnssec`).
+    pub dnssec_ok: bool,
+    /// Set the Checking Disabled bit on the query (`--cd`, `+cd` or `+cdflag`).
+    pub checking_disabled: bool,
+    /// Set the Authentic Data bit on the query (`--ad`, `+ad` or `+adflag`).
+    pub authentic_data: bool,
+    /// Fail unless the response has the Authentic Data bit set (`--require-ad`).
+    pub require_authentic_data: bool,
+    /// Validate the response with DNSSEC from the root trust anchor down (`--validate`).
+    pub validate: bool,
+    /// Validate from the root keys in this file instead of the built-in ones
+    /// (`--trust-anchors <file>`).
+    pub trust_anchors_file: Option<PathBuf>,
+    /// Maximum number of lookups in flight during a sweep (`--concurrency <n>`).
+    pub concurrency: Option<usize>,
+    /// Maximum number of lookups per second during a sweep (`--rate <n>`, 0 for no limit).
+    pub rate_limit: Option<u32>,
+    /// How to print results of multi-result commands (`--format text|csv|ndjson`).
+    pub output_format: OutputFormat,
+    /// Sign queries and verify responses with this TSIG key
+    /// (`--tsig [algorithm:]name:secret`).
+    pub tsig_key: Option<TsigKey>,
+    /// Read the TSIG key from this BIND key file (`--tsig-keyfile <file>`).
+    pub tsig_key_file: Option<PathBuf>,
+    /// Save a transferred zone to this file instead of printing it (`--output <file>`).
+    pub zone_file: Option<PathBuf>,
+    /// The third-party name open-resolver probes ask for (`--probe-name <name>`).
+    pub probe_name: Option<String>,
+    /// Permit open-resolver scans of public address space (`--allow-public`).
+    pub allow_public: bool,
+    /// The class to query in (`--class IN|CH|HS|CLASS<n>|<n>`, default IN).
+    pub class: DnsClass,
+    /// Ask both systemd-resolved and its upstream server and compare the answers
+    /// (`--compare-resolved`).
+    pub compare_resolved: bool,
+    /// The DNS server to query (`--server <address>`), if not the default.
+    pub server: Option<Ipv4Addr>,
+    /// A Unix domain socket to send queries over instead (`--server unix:<path>`).
+    pub unix_socket: Option<PathBuf>,
+    /// Send every query over TCP instead of UDP (`--tcp`).
+    pub tcp: bool,
+    /// Advertise this UDP payload size with EDNS(0) (`--edns <bytes>`).
+    pub edns: Option<u16>,
+    /// Raw options to send in the OPT record (`--edns-opt <code>[:<hex>]`).
+    pub edns_options: Vec<EdnsOption>,
+    /// How to render TTLs (`--human-ttl`, `--ttl-expiry`).
+    pub ttl_format: TtlFormat,
+    /// Show wire sizes and section statistics of the response (`--verbose`).
+    pub verbose: bool,
+    /// Record every exchange to this transcript file (`--record <file>`).
+    pub record_transcript: Option<PathBuf>,
+    /// Answer every query from this transcript file instead of the network
+    /// (`--replay <file>`).
+    pub replay_transcript: Option<PathBuf>,
+    /// What the answer of an `assert` command must satisfy (`--equals <value>`,
+    /// `--contains <value>`, `--matches <pattern>`, `--count <n>`, `--max-ttl <seconds>`).
+    /// Values given with `--equals` are gathered into a single assertion.
+    pub assertions: Vec<Assertion>,
+    /// Ask each of these servers and keep only the records enough of them agree on
+    /// (`--consensus <address>,<address>,...`).
+    pub consensus_servers: Vec<Ipv4Addr>,
+    /// How many consensus servers must return a record (`--quorum <n>`), by default
+    /// a majority.
+    pub quorum: Option<usize>,
+    /// The journal of the persistent cache the cache commands work on
+    /// (`--cache-file <file>`).
+    pub cache_file: Option<PathBuf>,
+    /// Answer A, AAAA and PTR queries for the names and addresses this hosts file
+    /// lists from it (`--hosts` for `/etc/hosts`, `--hosts-file <file>`).
+    pub hosts_file: Option<PathBuf>,
+    /// Whether names in responses are shown as A-labels or U-labels
+    /// (`--idn-out ascii|unicode`).
+    pub idn_output: IdnOutput,
+}
+
+/// Parses the process arguments (including the program name at index 0).
+///
+/// # Arguments
+///
+/// * `args` - The full argument vector as returned by [`std::env::args`]
+///
+/// # Returns
+///
+/// * `Ok(CliArgs)` - The parsed arguments
+/// * `Err(String)` - A message describing the first invalid or missing argument
+///
+/// # Examples
+///
+/// ```rust,ignore
+/// use crate::cli::{parse_args, Command};
+///
+/// let args: Vec<String> = ["dns-resolver", "example.com", "MX", "--source", "192.0.2.10"]
+///     .iter()
+///     .map(|s| s.to_string())
+///     .collect();
+///
+/// let cli = parse_args(&args).unwrap();
+/// assert!(matches!(cli.command, Command::Lookup { ref domain_name, .. } if domain_name == "example.com"));
+/// assert_eq!(cli.source_address, Some("192.0.2.10".parse().unwrap()));
+/// ```
+pub fn parse_args(args: &[String]) -> Result<CliArgs, String> {
+    let mut positional = Vec::new();
+    let mut source_address = None;
+    let mut interface = None;
+    let mut duplicate_window = None;
+    let mut strict = false;
+    let mut dnssec_ok = false;
+    let mut checking_disabled = false;
+    let mut authentic_data = false;
+    let mut require_authentic_data = false;
+    let mut validate = false;
+    let mut trust_anchors_file = None;
+    let mut concurrency = None;
+    let mut rate_limit = None;
+    let mut output_format = OutputFormat::default();
+    let mut tsig_key = None;
+    let mut tsig_key_file = None;
+    let mut zone_file = None;
+    let mut probe_name = None;
+    let mut allow_public = false;
+    let mut class = DnsClass::default();
+    let mut compare_resolved = false;
+    let mut server = None;
+    let mut unix_socket = None;
+    let mut tcp = false;
+    let mut edns = None;
+    let mut edns_options = Vec::new();
+    let mut ttl_format = TtlFormat::default();
+    let mut verbose = false;
+    let mut record_transcript = None;
+    let mut replay_transcript = None;
+    let mut assertions = Vec::new();
+    let mut equals = Vec::new();
+    let mut consensus_servers = Vec::new();
+    let mut quorum = None;
+    let mut cache_file = None;
+    let mut hosts_file = None;
+    let mut idn_output = IdnOutput::default();
+    let mut all = false;
+    let mut reverse = None;
+
+    let mut iter = args.iter().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--source" => {
+                let value = flag_value(&mut iter, arg)?;
+                let addr = value
+                    .parse::<Ipv4Addr>()
+                    .map_err(|_| format!("Invalid source address '{}'", value))?;
+                source_address = Some(addr);
+            }
+            "--server" => {
+                let value = flag_value(&mut iter, arg)?;
+                match value.strip_prefix("unix:") {
+                    Some("") => return Err("--server unix: needs a socket path".to_string()),
+                    Some(path) => unix_socket = Some(PathBuf::from(path)),
+                    None => {
+                        let addr = value
+                            .parse::<Ipv4Addr>()
+                            .map_err(|_| format!("Invalid server '{}'", value))?;
+                        server = Some(addr);
+                    }
+                }
+            }
+            "--interface" => {
+                interface = Some(flag_value(&mut iter, arg)?.to_string());
+            }
+            "--duplicate-window" => {
+                let value = flag_value(&mut iter, arg)?;
+                let millis = value
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid duplicate window '{}' (milliseconds)", value))?;
+                duplicate_window = Some(Duration::from_millis(millis));
+            }
+            "-x" | "--reverse" => {
+                let value = flag_value(&mut iter, arg)?;
+                let address = value
+                    .parse::<IpAddr>()
+                    .map_err(|_| format!("Invalid IP address '{}'", value))?;
+                reverse = Some(address);
+            }
+            "--edns" => {
+                let value = flag_value(&mut iter, arg)?;
+                let size = value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|size| *size >= 512)
+                    .ok_or_else(|| {
+                        format!("Invalid EDNS payload size '{}' (512 to 65535 bytes)", value)
+                    })?;
+                edns = Some(size);
+            }
+            "--edns-opt" => edns_options.push(flag_value(&mut iter, arg)?.parse()?),
+            "--concurrency" => {
+                let value = flag_value(&mut iter, arg)?;
+                let workers = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("Invalid concurrency '{}'", value))?;
+                concurrency = Some(workers);
+            }
+            "--rate" => {
+                let value = flag_value(&mut iter, arg)?;
+                let per_second = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid rate '{}' (queries per second)", value))?;
+                rate_limit = Some(per_second);
+            }
+            "--format" => {
+                let value = flag_value(&mut iter, arg)?;
+                output_format = value.parse().map_err(|_| {
+                    format!(
+                        "Invalid output format '{}'. Supported formats are text, csv, ndjson.",
+                        value
+                    )
+                })?;
+            }
+            "--record" => {
+                record_transcript = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--replay" => {
+                replay_transcript = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--cache-file" => {
+                cache_file = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--hosts" => hosts_file = Some(PathBuf::from(HOSTS_PATH)),
+            "--hosts-file" => {
+                hosts_file = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--idn-out" => {
+                let value = flag_value(&mut iter, arg)?;
+                idn_output = value.parse().map_err(|_| {
+                    format!(
+                        "Invalid --idn-out value '{}'. Supported values are ascii, unicode.",
+                        value
+                    )
+                })?;
+            }
+            "--tsig" => tsig_key = Some(flag_value(&mut iter, arg)?.parse::<TsigKey>()?),
+            "--tsig-keyfile" => {
+                tsig_key_file = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--output" => {
+                zone_file = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            "--equals" => equals.push(flag_value(&mut iter, arg)?.to_string()),
+            "--contains" => {
+                assertions.push(Assertion::Contains(flag_value(&mut iter, arg)?.to_string()));
+            }
+            "--matches" => {
+                assertions.push(Assertion::Matches(flag_value(&mut iter, arg)?.parse()?));
+            }
+            "--count" => {
+                let value = flag_value(&mut iter, arg)?;
+                let count = value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid record count '{}'", value))?;
+                assertions.push(Assertion::Count(count));
+            }
+            "--max-ttl" => {
+                let value = flag_value(&mut iter, arg)?;
+                let ttl = value
+                    .parse::<u32>()
+                    .map_err(|_| format!("Invalid TTL '{}' (seconds)", value))?;
+                assertions.push(Assertion::MaxTtl(ttl));
+            }
+            "--consensus" => {
+                for value in flag_value(&mut iter, arg)?.split(',') {
+                    let addr = value
+                        .trim()
+                        .parse::<Ipv4Addr>()
+                        .map_err(|_| format!("Invalid consensus server '{}'", value))?;
+                    consensus_servers.push(addr);
+                }
+            }
+            "--quorum" => {
+                let value = flag_value(&mut iter, arg)?;
+                let servers = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .ok_or_else(|| format!("Invalid quorum '{}'", value))?;
+                quorum = Some(servers);
+            }
+            "--probe-name" => {
+                probe_name = Some(flag_value(&mut iter, arg)?.to_string());
+            }
+            "--class" => {
+                let value = flag_value(&mut iter, arg)?;
+                class = value.parse().map_err(|_| {
+                    format!(
+                        "Invalid class '{}'. Supported classes are IN, CH, HS, NONE, ANY, CLASS<number> or a number.",
+                        value
+                    )
+                })?;
+            }
+            "--allow-public" => allow_public = true,
+            "--compare-resolved" => compare_resolved = true,
+            "--human-ttl" => ttl_format.human = true,
+            "--ttl-expiry" => ttl_format.expiry = true,
+            "--verbose" => verbose = true,
+            "--all" => all = true,
+            "--strict" => strict = true,
+            "--tcp" => tcp = true,
+            // The DNSSEC bits also take the names dig gives them, so that muscle
+            // memory carries over.
+            "--dnssec" | "+dnssec" => dnssec_ok = true,
+            "--cd" | "+cd" | "+cdflag" => checking_disabled = true,
+            "--ad" | "+ad" | "+adflag" => authentic_data = true,
+            "--require-ad" => require_authentic_data = true,
+            "--validate" => validate = true,
+            "--trust-anchors" => {
+                trust_anchors_file = Some(PathBuf::from(flag_value(&mut iter, arg)?));
+            }
+            flag if flag.starts_with("--") || flag.starts_with('+') => {
+                return Err(format!("Unknown option '{}'", flag));
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+
+    // A reverse lookup is a PTR lookup of the address's name under in-addr.arpa
+    // or ip6.arpa, so it goes through the regular lookup path.
+    let command = match reverse {
+        Some(address) => {
+            if !positional.is_empty() || all {
+                return Err("-x takes the place of the domain name and record type".to_string());
+            }
+            Command::Lookup {
+                domain_name: reverse_lookup_name(&address),
+                query_type: QueryType::PTR,
+            }
+        }
+        None => match (parse_command(&positional)?, all) {
+            (Command::Lookup { domain_name, .. }, true) if positional.len() == 1 => {
+                Command::LookupAll { domain_name }
+            }
+            (_, true) => {
+                return Err("--all takes a domain name and no record type".to_string());
+            }
+            (command, false) => command,
+        },
+    };
+    if compare_resolved && !matches!(command, Command::Lookup { .. }) {
+        return Err("--compare-resolved applies to single-type lookups only".to_string());
+    }
+    // These commands choose their own servers.
+    if (server.is_some() || unix_socket.is_some())
+        && (compare_resolved || matches!(command, Command::OpenResolvers { .. }))
+    {
+        return Err(
+            "--server cannot be combined with open-resolvers or --compare-resolved".to_string(),
+        );
+    }
+
+    if !equals.is_empty() {
+        assertions.insert(0, Assertion::Equals(equals));
+    }
+    match (&command, assertions.is_empty()) {
+        (Command::Assert { .. }, true) => {
+            return Err(
+                "The assert command needs at least one of --equals, --contains, --matches, --count or --max-ttl"
+                    .to_string(),
+            );
+        }
+        (Command::Assert { .. }, false) | (_, true) => {}
+        (_, false) => {
+            return Err("--equals, --contains, --matches, --count and --max-ttl apply to the assert command only".to_string());
+        }
+    }
+
+    if !consensus_servers.is_empty() {
+        if !matches!(command, Command::Lookup { .. }) || compare_resolved {
+            return Err("--consensus applies to single-type lookups only".to_string());
+        }
+        if server.is_some() || unix_socket.is_some() {
+            return Err(
+                "--consensus chooses its own servers and cannot be combined with --server"
+                    .to_string(),
+            );
+        }
+        if consensus_servers.len() < 2 {
+            return Err("--consensus needs at least two servers".to_string());
+        }
+    }
+    if trust_anchors_file.is_some() && !validate {
+        return Err("--trust-anchors applies to --validate only".to_string());
+    }
+    match quorum {
+        Some(_) if consensus_servers.is_empty() => {
+            return Err("--quorum applies to --consensus only".to_string());
+        }
+        Some(n) if n > consensus_servers.len() => {
+            return Err(format!(
+                "--quorum {} is more than the {} consensus servers",
+                n,
+                consensus_servers.len()
+            ));
+        }
+        _ => {}
+    }
+
+    if zone_file.is_some() && !matches!(command, Command::Axfr { .. }) {
+        return Err("--output applies to the axfr command only".to_string());
+    }
+    // Zone transfers are streamed over their own TCP connection.
+    if matches!(command, Command::Axfr { .. })
+        && (unix_socket.is_some()
+            || record_transcript.is_some()
+            || replay_transcript.is_some()
+            || source_address.is_some()
+            || interface.is_some())
+    {
+        return Err(
+            "axfr needs a TCP server and cannot be combined with a unix: server, --record, --replay, --source or --interface"
+                .to_string(),
+        );
+    }
+
+    // Notifications are sent over their own socket and cannot be recorded.
+    if matches!(command, Command::Notify { .. })
+        && (unix_socket.is_some() || record_transcript.is_some() || replay_transcript.is_some())
+    {
+        return Err(
+            "notify cannot be combined with a unix: server, --record or --replay".to_string(),
+        );
+    }
+
+    // The cache commands only read and rewrite a journal; they send no queries.
+    match (&command, &cache_file) {
+        (Command::CacheDump | Command::CacheFlush { .. }, None) => {
+            return Err("The cache commands need --cache-file <file>".to_string());
+        }
+        (Command::CacheDump | Command::CacheFlush { .. }, Some(_)) | (_, None) => {}
+        (_, Some(_)) => {
+            return Err("--cache-file applies to the cache commands only".to_string());
+        }
+    }
+
+    if tsig_key.is_some() && tsig_key_file.is_some() {
+        return Err("--tsig and --tsig-keyfile cannot be combined".to_string());
+    }
+    // A signature covers the time it was made, so no two runs send the same query.
+    if (tsig_key.is_some() || tsig_key_file.is_some()) && replay_transcript.is_some() {
+        return Err("TSIG-signed queries cannot be answered from --replay".to_string());
+    }
+
+    if record_transcript.is_some() && replay_transcript.is_some() {
+        return Err("--record and --replay cannot be combined".to_string());
+    }
+    // Only Linux can bind a TCP connection to a source address or interface.
+    if cfg!(not(target_os = "linux")) && tcp && (source_address.is_some() || interface.is_some()) {
+        return Err("--tcp cannot be combined with --source or --interface".to_string());
+    }
+    if tcp && unix_socket.is_some() {
+        return Err("--tcp cannot be combined with a unix: server".to_string());
+    }
+
+    Ok(CliArgs {
+        command,
+        source_address,
+        interface,
+        duplicate_window,
+        strict,
+        dnssec_ok,
+        checking_disabled,
+        authentic_data,
+        require_authentic_data,
+        validate,
+        trust_anchors_file,
+        concurrency,
+        rate_limit,
+        output_format,
+        tsig_key,
+        tsig_key_file,
+        zone_file,
+        probe_name,
+        allow_public,
+        class,
+        compare_resolved,
+        server,
+        unix_socket,
+        tcp,
+        edns,
+        edns_options,
+        ttl_format,
+        verbose,
+        record_transcript,
+        replay_transcript,
+        assertions,
+        consensus_servers,
+        quorum,
+        cache_file,
+        hosts_file,
+        idn_output,
+    })
+}
+
+/// Determines the command from the positional arguments.
+fn parse_command(positional: &[&str]) -> Result<Command, String> {
+    match positional {
+        ["asn", address] => {
+            let address = address
+                .parse::<IpAddr>()
+                .map_err(|_| format!("Invalid IP address '{}'", address))?;
+            Ok(Command::Asn { address })
+        }
+        ["asn", ..] => Err("The asn command expects exactly one IP address".to_string()),
+        ["report", domain_name] => Ok(Command::Report {
+            domain_name: domain_name.to_string(),
+        }),
+        ["report", ..] => Err("The report command expects exactly one domain name".to_string()),
+        ["assert", domain_name] | ["assert", domain_name, _] => Ok(Command::Assert {
+            domain_name: domain_name.to_string(),
+            query_type: parse_query_type(positional.get(2).copied().unwrap_or("A"))?,
+        }),
+        ["assert", ..] => {
+            Err("The assert command expects a domain name and an optional record type".to_string())
+        }
+        ["sweep", cidr] => Ok(Command::Sweep {
+            cidr: cidr.parse()?,
+        }),
+        ["sweep", ..] => Err("The sweep command expects exactly one prefix".to_string()),
+        ["open-resolvers", targets] => Ok(Command::OpenResolvers {
+            targets: targets.parse()?,
+        }),
+        ["open-resolvers", ..] => {
+            Err("The open-resolvers command expects exactly one prefix".to_string())
+        }
+        ["axfr", zone] => Ok(Command::Axfr {
+            zone: zone.to_string(),
+        }),
+        ["axfr", ..] => Err("The axfr command expects exactly one zone name".to_string()),
+        ["notify", zone] | ["notify", zone, _] => Ok(Command::Notify {
+            zone: zone.to_string(),
+            serial: positional
+                .get(2)
+                .map(|serial| {
+                    serial
+                        .parse::<u32>()
+                        .map_err(|_| format!("Invalid serial '{}'", serial))
+                })
+                .transpose()?,
+        }),
+        ["notify", ..] => {
+            Err("The notify command expects a zone name and an optional serial".to_string())
+        }
+        ["notify-listen", address] => Ok(Command::NotifyListen {
+            address: address
+                .parse()
+                .map_err(|_| format!("Invalid listen address '{}' (address:port)", address))?,
+        }),
+        ["notify-listen", ..] => {
+            Err("The notify-listen command expects exactly one address:port".to_string())
+        }
+        ["cache", "dump"] => Ok(Command::CacheDump),
+        ["cache", "flush"] => Ok(Command::CacheFlush { name: None }),
+        ["cache", "flush", name] => Ok(Command::CacheFlush {
+            name: Some(name.to_string()),
+        }),
+        ["cache", ..] => {
+            Err("The cache command expects dump, or flush and an optional name".to_string())
+        }
+        // A transfer cannot be a plain lookup, so asking for type AXFR as dig does
+        // runs the axfr command.
+        [zone, record_type] if parse_query_type(record_type) == Ok(QueryType::AXFR) => {
+            Ok(Command::Axfr {
+                zone: zone.to_string(),
+            })
+        }
+        [domain_name] | [domain_name, _] => {
+            // If no record type is specified, default to 'A' (IPv4 address records).
+            let query_type = parse_query_type(positional.get(1).copied().unwrap_or("A"))?;
+            Ok(Command::Lookup {
+                domain_name: domain_name.to_string(),
+                query_type,
+            })
+        }
+        _ => Err("Expected a domain name and an optional record type".to_string()),
+    }
+}
+
+/// Parses a record type operand, naming the supported types if it is invalid.
+fn parse_query_type(value: &str) -> Result<QueryType, String> {
+    QueryType::from_str(value).map_err(|_| {
+        format!(
+            "Invalid record type '{}'. Supported types are A, AAAA, CNAME, MX, TXT, PTR, NS, SOA, HINFO, SRV, NAPTR, SSHFP, SVCB, HTTPS, DS, DNSKEY, RRSIG, NSEC, NSEC3, ANY, or TYPE<number>.",
+            value
+        )
+    })
+}
+
+/// Takes the value following a `--flag`, failing if the arguments end early.
+fn flag_value<'a>(
+    iter: &mut impl Iterator<Item = &'a String>,
+    flag: &str,
+) -> Result<&'a str, String> {
+    iter.next()
+        .map(|s| s.as_str())
+        .ok_or_else(|| format!("Option '{}' requires a value", flag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        std::iter::once("dns-resolver")
+            .chain(list.iter().copied())
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_defaults_to_a_record() {
+        let cli = parse_args(&args(&["example.com"])).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Lookup {
+                domain_name: "example.com".to_string(),
+                query_type: QueryType::A,
+            }
+        );
+        assert!(cli.source_address.is_none());
+        assert!(cli.interface.is_none());
+        assert!(cli.duplicate_window.is_none());
+        assert!(!cli.strict);
+        assert!(!cli.checking_disabled && !cli.authentic_data && !cli.require_authentic_data);
+    }
+
+    #[test]
+    fn test_parse_dnssec_bits() {
+        let cli = parse_args(&args(&["example.com", "--cd", "--ad", "--require-ad"])).unwrap();
+        assert!(cli.checking_disabled);
+        assert!(cli.authentic_data);
+        assert!(cli.require_authentic_data);
+        assert!(!cli.validate && !cli.dnssec_ok);
+        assert!(
+            parse_args(&args(&["example.com", "--validate"]))
+                .unwrap()
+                .validate
+        );
+        let cli = parse_args(&args(&[
+            "example.com",
+            "--validate",
+            "--trust-anchors",
+            "root.key",
+        ]))
+        .unwrap();
+        assert_eq!(cli.trust_anchors_file, Some(PathBuf::from("root.key")));
+        assert!(parse_args(&args(&["example.com", "--trust-anchors", "root.key"])).is_err());
+
+        // dig's spellings work too.
+        let cli = parse_args(&args(&[
+            "example.com",
+            "DNSKEY",
+            "+dnssec",
+            "+cd",
+            "+adflag",
+        ]))
+        .unwrap();
+        assert!(cli.dnssec_ok && cli.checking_disabled && cli.authentic_data);
+        assert!(
+            parse_args(&args(&["example.com", "--dnssec"]))
+                .unwrap()
+                .dnssec_ok
+        );
+        assert!(parse_args(&args(&["example.com", "+short"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_strict_switch() {
+        let cli = parse_args(&args(&["--strict", "example.com", "MX"])).unwrap();
+        assert!(cli.strict);
+        assert!(matches!(
+            cli.command,
+            Command::Lookup {
+                query_type: QueryType::MX,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_duplicate_window() {
+        let cli = parse_args(&args(&["example.com", "--duplicate-window", "250"])).unwrap();
+        assert_eq!(cli.duplicate_window, Some(Duration::from_millis(250)));
+        assert!(parse_args(&args(&["example.com", "--duplicate-window", "soon"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_source_and_interface() {
+        let cli = parse_args(&args(&[
+            "--interface",
+            "eth1",
+            "example.com",
+            "aaaa",
+            "--source",
+            "192.0.2.10",
+        ]))
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Lookup {
+                query_type: QueryType::AAAA,
+                ..
+            }
+        ));
+        assert_eq!(cli.source_address, Some(Ipv4Addr::new(192, 0, 2, 10)));
+        assert_eq!(cli.interface.as_deref(), Some("eth1"));
+    }
+
+    #[test]
+    fn test_parse_asn_command() {
+        let cli = parse_args(&args(&["asn", "2001:db8::1", "--source", "192.0.2.10"])).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Asn {
+                address: "2001:db8::1".parse().unwrap()
+            }
+        );
+        assert!(parse_args(&args(&["asn", "example.com"])).is_err());
+        assert!(parse_args(&args(&["asn"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_sweep_command() {
+        let cli = parse_args(&args(&[
+            "sweep",
+            "192.0.2.0/24",
+            "--concurrency",
+            "4",
+            "--rate",
+            "0",
+            "--format",
+            "ndjson",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Sweep {
+                cidr: "192.0.2.0/24".parse().unwrap()
+            }
+        );
+        assert_eq!(cli.concurrency, Some(4));
+        assert_eq!(cli.rate_limit, Some(0));
+        assert_eq!(cli.output_format, OutputFormat::Ndjson);
+        assert!(parse_args(&args(&["sweep", "192.0.2.0/40"])).is_err());
+        assert!(parse_args(&args(&["sweep", "192.0.2.0/24", "--concurrency", "0"])).is_err());
+        assert!(parse_args(&args(&["sweep", "192.0.2.0/24", "--format", "xml"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_open_resolvers_command() {
+        let cli = parse_args(&args(&[
+            "open-resolvers",
+            "10.0.0.0/24",
+            "--probe-name",
+            "example.org",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::OpenResolvers {
+                targets: "10.0.0.0/24".parse().unwrap()
+            }
+        );
+        assert_eq!(cli.probe_name.as_deref(), Some("example.org"));
+        assert!(!cli.allow_public);
+        assert!(parse_args(&args(&["open-resolvers"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_class() {
+        let cli = parse_args(&args(&["version.bind", "TXT", "--class", "CH"])).unwrap();
+        assert_eq!(cli.class, DnsClass::CH);
+        let cli = parse_args(&args(&["example.com", "--class", "CLASS254"])).unwrap();
+        assert_eq!(cli.class, DnsClass::NONE);
+        let cli = parse_args(&args(&["example.com", "--class", "42"])).unwrap();
+        assert_eq!(cli.class, DnsClass::Unknown(42));
+        assert_eq!(
+            parse_args(&args(&["example.com"])).unwrap().class,
+            DnsClass::IN
+        );
+        assert!(parse_args(&args(&["example.com", "--class", "CLASS70000"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_ttl_flags() {
+        let cli = parse_args(&args(&["report", "example.com", "--human-ttl"])).unwrap();
+        assert_eq!(
+            cli.ttl_format,
+            TtlFormat {
+                human: true,
+                expiry: false
+            }
+        );
+        let cli = parse_args(&args(&["example.com", "--ttl-expiry"])).unwrap();
+        assert!(cli.ttl_format.expiry && !cli.ttl_format.human);
+    }
+
+    #[test]
+    fn test_parse_transcript_flags() {
+        let cli = parse_args(&args(&["example.com", "--record", "run.transcript"])).unwrap();
+        assert_eq!(cli.record_transcript, Some(PathBuf::from("run.transcript")));
+        assert_eq!(cli.replay_transcript, None);
+        assert!(parse_args(&args(&["example.com", "--record", "a", "--replay", "b"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--replay"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_hosts_flags() {
+        assert_eq!(
+            parse_args(&args(&["example.com"])).unwrap().hosts_file,
+            None
+        );
+        let cli = parse_args(&args(&["localhost", "--hosts"])).unwrap();
+        assert_eq!(cli.hosts_file, Some(PathBuf::from("/etc/hosts")));
+        let cli = parse_args(&args(&["-x", "192.0.2.10", "--hosts-file", "test-hosts"])).unwrap();
+        assert_eq!(cli.hosts_file, Some(PathBuf::from("test-hosts")));
+        assert!(parse_args(&args(&["localhost", "--hosts-file"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_idn_output() {
+        let cli = parse_args(&args(&["bücher.example"])).unwrap();
+        assert_eq!(cli.idn_output, IdnOutput::Ascii);
+        let cli = parse_args(&args(&["bücher.example", "--idn-out", "Unicode"])).unwrap();
+        assert_eq!(cli.idn_output, IdnOutput::Unicode);
+        assert!(parse_args(&args(&["bücher.example", "--idn-out", "utf8"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_verbose() {
+        assert!(
+            parse_args(&args(&["example.com", "--verbose"]))
+                .unwrap()
+                .verbose
+        );
+        assert!(!parse_args(&args(&["example.com"])).unwrap().verbose);
+    }
+
+    #[test]
+    fn test_parse_compare_resolved() {
+        let cli = parse_args(&args(&["example.com", "AAAA", "--compare-resolved"])).unwrap();
+        assert!(cli.compare_resolved);
+        assert!(parse_args(&args(&["example.com", "--all", "--compare-resolved"])).is_err());
+        assert!(parse_args(&args(&["report", "example.com", "--compare-resolved"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_tcp_switch() {
+        assert!(!parse_args(&args(&["example.com"])).unwrap().tcp);
+        let cli = parse_args(&args(&["example.com", "TXT", "--tcp"])).unwrap();
+        assert!(cli.tcp);
+
+        let bound = parse_args(&args(&["example.com", "--tcp", "--source", "192.0.2.10"]));
+        assert_eq!(bound.is_ok(), cfg!(target_os = "linux"));
+        let bound = parse_args(&args(&["example.com", "--tcp", "--interface", "eth1"]));
+        assert_eq!(bound.is_ok(), cfg!(target_os = "linux"));
+        assert!(
+            parse_args(&args(&[
+                "example.com",
+                "--tcp",
+                "--server",
+                "unix:/run/dns.sock"
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_edns() {
+        assert_eq!(parse_args(&args(&["example.com"])).unwrap().edns, None);
+        let cli = parse_args(&args(&["example.com", "--edns", "4096"])).unwrap();
+        assert_eq!(cli.edns, Some(4096));
+        assert!(parse_args(&args(&["example.com", "--edns", "511"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--edns", "65536"])).is_err());
+
+        let cli = parse_args(&args(&[
+            "example.com",
+            "--edns-opt",
+            "nsid",
+            "--edns-opt",
+            "65001:beef",
+        ]))
+        .unwrap();
+        let options: Vec<(u16, Vec<u8>)> = cli
+            .edns_options
+            .into_iter()
+            .map(|option| (option.code, option.data))
+            .collect();
+        assert_eq!(options, [(3, vec![]), (65001, vec![0xbe, 0xef])]);
+        assert!(parse_args(&args(&["example.com", "--edns-opt", "10:abc"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_reverse_lookup() {
+        let cli = parse_args(&args(&["-x", "192.0.2.1"])).unwrap();
+        match cli.command {
+            Command::Lookup {
+                domain_name,
+                query_type,
+            } => {
+                assert_eq!(domain_name, "1.2.0.192.in-addr.arpa");
+                assert_eq!(query_type, QueryType::PTR);
+            }
+            other => panic!("unexpected command {:?}", other),
+        }
+        let cli = parse_args(&args(&[
+            "--reverse",
+            "2001:db8::1",
+            "--server",
+            "192.0.2.53",
+        ]))
+        .unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Lookup { ref domain_name, .. } if domain_name.ends_with(".ip6.arpa")
+        ));
+
+        assert!(parse_args(&args(&["-x", "example.com"])).is_err());
+        assert!(parse_args(&args(&["-x", "192.0.2.1", "example.com"])).is_err());
+        assert!(parse_args(&args(&["-x", "192.0.2.1", "--all"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_server() {
+        let cli = parse_args(&args(&["example.com", "--server", "192.0.2.53"])).unwrap();
+        assert_eq!(cli.server, Some(Ipv4Addr::new(192, 0, 2, 53)));
+        assert_eq!(cli.unix_socket, None);
+
+        let cli = parse_args(&args(&["example.com", "--server", "unix:/run/mydns.sock"])).unwrap();
+        assert_eq!(cli.server, None);
+        assert_eq!(cli.unix_socket, Some(PathBuf::from("/run/mydns.sock")));
+
+        assert!(parse_args(&args(&["example.com", "--server", "unix:"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--server", "dns.example"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "open-resolvers",
+                "10.0.0.0/24",
+                "--server",
+                "192.0.2.53"
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_all_switch() {
+        let cli = parse_args(&args(&["example.com", "--all"])).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::LookupAll {
+                domain_name: "example.com".to_string()
+            }
+        );
+        assert!(parse_args(&args(&["example.com", "MX", "--all"])).is_err());
+        assert!(parse_args(&args(&["asn", "192.0.2.1", "--all"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_report_command() {
+        let cli = parse_args(&args(&["report", "example.com", "--format", "json"])).unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Report {
+                domain_name: "example.com".to_string()
+            }
+        );
+        assert_eq!(cli.output_format, OutputFormat::Ndjson);
+    }
+
+    #[test]
+    fn test_parse_axfr_command() {
+        let cli = parse_args(&args(&["axfr", "example.com", "--output", "example.zone"])).unwrap();
+        let axfr = Command::Axfr {
+            zone: "example.com".to_string(),
+        };
+        assert_eq!(cli.command, axfr);
+        assert_eq!(cli.zone_file, Some(PathBuf::from("example.zone")));
+        assert_eq!(
+            parse_args(&args(&["example.com", "axfr"])).unwrap().command,
+            axfr
+        );
+
+        assert!(parse_args(&args(&["axfr"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--output", "example.zone"])).is_err());
+        assert!(parse_args(&args(&["axfr", "example.com", "--server", "unix:/run/dns"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_notify_commands() {
+        assert_eq!(
+            parse_args(&args(&["notify", "example.com", "2024010102"]))
+                .unwrap()
+                .command,
+            Command::Notify {
+                zone: "example.com".to_string(),
+                serial: Some(2024010102),
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&["notify", "example.com"]))
+                .unwrap()
+                .command,
+            Command::Notify {
+                zone: "example.com".to_string(),
+                serial: None,
+            }
+        );
+        assert_eq!(
+            parse_args(&args(&["notify-listen", "127.0.0.1:5353"]))
+                .unwrap()
+                .command,
+            Command::NotifyListen {
+                address: "127.0.0.1:5353".parse().unwrap(),
+            }
+        );
+
+        assert!(parse_args(&args(&["notify", "example.com", "tomorrow"])).is_err());
+        assert!(parse_args(&args(&["notify-listen", "127.0.0.1"])).is_err());
+        assert!(parse_args(&args(&["notify", "example.com", "--replay", "t"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_cache_commands() {
+        let cli = parse_args(&args(&["cache", "dump", "--cache-file", "dns.journal"])).unwrap();
+        assert_eq!(cli.command, Command::CacheDump);
+        assert_eq!(cli.cache_file, Some(PathBuf::from("dns.journal")));
+        assert_eq!(
+            parse_args(&args(&["cache", "flush", "--cache-file", "dns.journal"]))
+                .unwrap()
+                .command,
+            Command::CacheFlush { name: None }
+        );
+        assert_eq!(
+            parse_args(&args(&[
+                "cache",
+                "flush",
+                "example.com",
+                "--cache-file",
+                "dns.journal"
+            ]))
+            .unwrap()
+            .command,
+            Command::CacheFlush {
+                name: Some("example.com".to_string()),
+            }
+        );
+
+        assert!(parse_args(&args(&["cache", "dump"])).is_err());
+        assert!(parse_args(&args(&["cache", "list", "--cache-file", "dns.journal"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--cache-file", "dns.journal"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_tsig_key() {
+        let cli = parse_args(&args(&[
+            "axfr",
+            "example.com",
+            "--tsig",
+            "hmac-sha512:transfer-key:c2VjcmV0",
+        ]))
+        .unwrap();
+        let key = cli.tsig_key.unwrap();
+        assert_eq!(key.name, "transfer-key");
+        assert_eq!(key.secret, b"secret");
+
+        let cli = parse_args(&args(&["example.com", "--tsig-keyfile", "transfer.key"])).unwrap();
+        assert_eq!(cli.tsig_key_file, Some(PathBuf::from("transfer.key")));
+
+        assert!(parse_args(&args(&["example.com", "--tsig", "transfer-key"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "example.com",
+                "--tsig",
+                "k:c2VjcmV0",
+                "--tsig-keyfile",
+                "transfer.key"
+            ]))
+            .is_err()
+        );
+        assert!(
+            parse_args(&args(&[
+                "example.com",
+                "--tsig",
+                "k:c2VjcmV0",
+                "--replay",
+                "bug.transcript"
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_assert_command() {
+        let cli = parse_args(&args(&[
+            "assert",
+            "example.com",
+            "A",
+            "--max-ttl",
+            "300",
+            "--equals",
+            "203.0.113.10",
+            "--equals",
+            "203.0.113.11",
+        ]))
+        .unwrap();
+        assert_eq!(
+            cli.command,
+            Command::Assert {
+                domain_name: "example.com".to_string(),
+                query_type: QueryType::A,
+            }
+        );
+        assert_eq!(
+            cli.assertions,
+            vec![
+                Assertion::Equals(vec!["203.0.113.10".to_string(), "203.0.113.11".to_string()]),
+                Assertion::MaxTtl(300),
+            ]
+        );
+        assert!(parse_args(&args(&["assert", "example.com"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--count", "1"])).is_err());
+        assert!(parse_args(&args(&["assert", "example.com", "--matches", "(a|b)"])).is_err());
+    }
+
+    #[test]
+    fn test_parse_consensus() {
+        let cli = parse_args(&args(&[
+            "example.com",
+            "--consensus",
+            "1.1.1.1,8.8.8.8, 9.9.9.9",
+            "--quorum",
+            "3",
+        ]))
+        .unwrap();
+        assert_eq!(cli.consensus_servers.len(), 3);
+        assert_eq!(cli.quorum, Some(3));
+        assert!(parse_args(&args(&["example.com", "--consensus", "1.1.1.1"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "example.com",
+                "--consensus",
+                "1.1.1.1,8.8.8.8",
+                "--quorum",
+                "3"
+            ]))
+            .is_err()
+        );
+        assert!(parse_args(&args(&["example.com", "--quorum", "2"])).is_err());
+        assert!(
+            parse_args(&args(&[
+                "report",
+                "example.com",
+                "--consensus",
+                "1.1.1.1,8.8.8.8"
+            ]))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_arguments() {
+        assert!(parse_args(&args(&[])).is_err());
+        assert!(parse_args(&args(&["example.com", "BOGUS"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--source"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--source", "not-an-ip"])).is_err());
+        assert!(parse_args(&args(&["example.com", "--quiet"])).is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Answers agreed on by several independent resolvers.
+//!
+//! On an untrusted network a single upstream resolver can lie, and nothing in a
+//! plain DNS response gives the lie away. Asking several independent resolvers and
+//! keeping only what enough of them agree on makes a lie visible: a tampered answer
+//! has to come from a quorum of resolvers to be believed. [`query_consensus`] sends
+//! the same query to every server at once and [`Consensus::tally`] counts, for each
+//! record, how many servers returned it.
+//!
+//! Records are compared by owner name (ignoring case), type, class and data. TTLs
+//! are ignored, since each resolver serves from its own cache.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::consensus::{majority, query_consensus};
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! let servers = [
+//!     Ipv4Addr::new(1, 1, 1, 1),
+//!     Ipv4Addr::new(8, 8, 8, 8),
+//!     Ipv4Addr::new(9, 9, 9, 9),
+//! ];
+//! let consensus = query_consensus("example.com", QueryType::A, &servers, majority(servers.len()), &QueryOptions::default());
+//! for outlier in &consensus.outliers {
+//!     println!("{} disagrees with the others", outlier.server);
+//! }
+//! ```
+
+use std::net::Ipv4Addr;
+
+use crate::dns::{QueryType, ResourceRecord, ResponseCode};
+use crate::resolver::{DnsError, QueryOptions, query};
+
+/// What one server answered.
+#[derive(Debug, Clone)]
+pub struct ServerAnswer {
+    /// The server that was asked.
+    pub server: Ipv4Addr,
+    /// The answer records, or why there are none. A name that does not exist is an
+    /// empty answer, not an error.
+    pub records: Result<Vec<ResourceRecord>, String>,
+}
+
+/// A server whose answer differs from the agreed one.
+#[derive(Debug, Clone)]
+pub struct Outlier {
+    /// The server that disagreed.
+    pub server: Ipv4Addr,
+    /// Records it returned that too few servers confirmed.
+    pub unconfirmed: Vec<ResourceRecord>,
+    /// Agreed records it did not return.
+    pub missing: Vec<ResourceRecord>,
+}
+
+/// The outcome of asking several servers the same question.
+#[derive(Debug, Clone)]
+pub struct Consensus {
+    /// How many servers must return a record for it to be agreed on.
+    pub quorum: usize,
+    /// The records returned by at least [`Consensus::quorum`] servers.
+    pub agreed: Vec<ResourceRecord>,
+    /// Every server's answer, in the order the servers were given.
+    pub answers: Vec<ServerAnswer>,
+    /// The servers that answered, but with a different set of records than the
+    /// agreed one.
+    pub outliers: Vec<Outlier>,
+}
+
+impl Consensus {
+    /// Counts which records enough servers agree on and which servers disagree.
+    ///
+    /// # Arguments
+    ///
+    /// * `answers` - One answer per server
+    /// * `quorum` - How many servers must return a record for it to be agreed on
+    pub fn tally(answers: Vec<ServerAnswer>, quorum: usize) -> Self {
+        let mut counted: Vec<(ResourceRecord, usize)> = Vec::new();
+        for records in answers
+            .iter()
+            .filter_map(|answer| answer.records.as_ref().ok())
+        {
+            // A server listing a record twice still casts one vote for it.
+            let mut voted: Vec<&ResourceRecord> = Vec::new();
+            for record in records {
+                if voted.iter().any(|other| same_record(record, other)) {
+                    continue;
+                }
+                voted.push(record);
+                match counted
+                    .iter_mut()
+                    .find(|(other, _)| same_record(record, other))
+                {
+                    Some((_, votes)) => *votes += 1,
+                    None => counted.push((record.clone(), 1)),
+                }
+            }
+        }
+        let agreed: Vec<ResourceRecord> = counted
+            .into_iter()
+            .filter(|(_, votes)| *votes >= quorum)
+            .map(|(record, _)| record)
+            .collect();
+
+        let outliers = answers
+            .iter()
+            .filter_map(|answer| {
+                let records = answer.records.as_ref().ok()?;
+                let unconfirmed: Vec<ResourceRecord> = records
+                    .iter()
+                    .filter(|record| !agreed.iter().any(|other| same_record(record, other)))
+                    .cloned()
+                    .collect();
+                let missing: Vec<ResourceRecord> = agreed
+                    .iter()
+                    .filter(|record| !records.iter().any(|other| same_record(record, other)))
+                    .cloned()
+                    .collect();
+                (!unconfirmed.is_empty() || !missing.is_empty()).then_some(Outlier {
+                    server: answer.server,
+                    unconfirmed,
+                    missing,
+                })
+            })
+            .collect();
+
+        Consensus {
+            quorum,
+            agreed,
+            answers,
+            outliers,
+        }
+    }
+
+    /// Returns whether at least [`Consensus::quorum`] servers returned exactly the
+    /// agreed records. When they did not, too many servers failed or disagreed for
+    /// the agreed records to be the whole answer.
+    pub fn reached(&self) -> bool {
+        let matching = self
+            .answers
+            .iter()
+            .filter(|answer| answer.records.is_ok())
+            .filter(|answer| {
+                !self
+                    .outliers
+                    .iter()
+                    .any(|outlier| outlier.server == answer.server)
+            })
+            .count();
+        matching >= self.quorum
+    }
+}
+
+/// Returns the smallest number of servers that is more than half of `servers`.
+pub fn majority(servers: usize) -> usize {
+    servers / 2 + 1
+}
+
+/// Sends the same query to every server concurrently and tallies the answers.
+///
+/// Each server is queried on its own, without failing over to
+/// [`QueryOptions::alternate_servers`], so every answer can be attributed to the
+/// server that gave it.
+///
+/// # Arguments
+///
+/// * `domain_name` - The name to resolve
+/// * `query_type` - The record type to request
+/// * `servers` - The independent resolvers to ask
+/// * `quorum` - How many servers must return a record for it to be agreed on
+/// * `options` - Settings shared by every query
+pub fn query_consensus(
+    domain_name: &str,
+    query_type: QueryType,
+    servers: &[Ipv4Addr],
+    quorum: usize,
+    options: &QueryOptions,
+) -> Consensus {
+    let options = QueryOptions {
+        alternate_servers: Vec::new(),
+        ..options.clone()
+    };
+    let answers = std::thread::scope(|scope| {
+        let handles: Vec<_> = servers
+            .iter()
+            .map(|server| {
+                let options = &options;
+                scope.spawn(move || ServerAnswer {
+                    server: *server,
+                    records: server_records(domain_name, query_type, *server, options),
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("query thread panicked"))
+            .collect()
+    });
+    Consensus::tally(answers, quorum)
+}
+
+/// Queries one server, treating NXDOMAIN as an empty answer.
+fn server_records(
+    domain_name: &str,
+    query_type: QueryType,
+    server: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<Vec<ResourceRecord>, String> {
+    let result = query(domain_name, query_type, server, options).map_err(|e| e.to_string())?;
+    match result.message.header.get_response_code() {
+        ResponseCode::NoError | ResponseCode::NameError => Ok(result.message.answers),
+        code => Err(DnsError::ServerReturnedError(code).to_string()),
+    }
+}
+
+/// Compares two records by owner name (ignoring case), type, class and data.
+fn same_record(a: &ResourceRecord, b: &ResourceRecord) -> bool {
+    a.name.eq_ignore_ascii_case(&b.name)
+        && a.rtype == b.rtype
+        && a.rclass == b.rclass
+        && a.data == b.data
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::a_record;
+
+    fn answer(server: u8, records: Result<Vec<ResourceRecord>, &str>) -> ServerAnswer {
+        ServerAnswer {
+            server: Ipv4Addr::new(198, 51, 100, server),
+            records: records.map_err(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_outlier_is_flagged() {
+        let consensus = Consensus::tally(
+            vec![
+                answer(
+                    1,
+                    Ok(vec![
+                        a_record("example.com", [192, 0, 2, 1], 300),
+                        a_record("example.com", [192, 0, 2, 2], 300),
+                    ]),
+                ),
+                // TTLs differ between caches and do not count as disagreement.
+                answer(
+                    2,
+                    Ok(vec![
+                        a_record("example.com", [192, 0, 2, 2], 120),
+                        a_record("example.com", [192, 0, 2, 1], 120),
+                    ]),
+                ),
+                answer(3, Ok(vec![a_record("example.com", [192, 0, 2, 66], 300)])),
+            ],
+            majority(3),
+        );
+        assert_eq!(consensus.agreed.len(), 2);
+        assert!(consensus.reached());
+        assert_eq!(consensus.outliers.len(), 1);
+        let outlier = &consensus.outliers[0];
+        assert_eq!(outlier.server, Ipv4Addr::new(198, 51, 100, 3));
+        assert_eq!(
+            outlier.unconfirmed,
+            vec![a_record("example.com", [192, 0, 2, 66], 300)]
+        );
+        assert_eq!(outlier.missing.len(), 2);
+    }
+
+    #[test]
+    fn test_no_quorum_without_enough_answers() {
+        let consensus = Consensus::tally(
+            vec![
+                answer(1, Ok(vec![a_record("example.com", [192, 0, 2, 1], 300)])),
+                answer(2, Err("Request timed out")),
+                answer(3, Ok(vec![a_record("example.com", [192, 0, 2, 2], 300)])),
+            ],
+            2,
+        );
+        assert!(consensus.agreed.is_empty());
+        assert!(!consensus.reached());
+        assert_eq!(consensus.outliers.len(), 2);
+    }
+}
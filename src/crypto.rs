@@ -0,0 +1,1174 @@
+//! Hashes and signature verification for DNSSEC, and HMACs for TSIG.
+//!
+//! DNSSEC relies on a handful of algorithms: SHA-1 to hash names in NSEC3 records
+//! (RFC 5155), SHA-256 and SHA-384 for DS digests (RFC 4509, RFC 6605), and RSA
+//! (RFC 5702) and ECDSA P-256 (RFC 6605) signatures. TSIG (RFC 8945) authenticates
+//! messages with an HMAC (RFC 2104) of the same hashes. This module implements exactly
+//! those, on the standard library alone.
+//!
+//! Only signature verification is provided. It works on public data, so the arithmetic
+//! makes no attempt to run in constant time and favours clarity over speed. HMACs
+//! involve a shared secret, so [`macs_equal`] compares them in constant time.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::crypto::HashAlgorithm;
+//!
+//! let digest = HashAlgorithm::Sha256.digest(b"abc");
+//! assert_eq!(digest[..4], [0xba, 0x78, 0x16, 0xbf]);
+//! ```
+
+use std::cmp::Ordering;
+
+/// SHA-256 round constants (FIPS 180-4 section 4.2.2).
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+/// SHA-256 initial hash value (FIPS 180-4 section 5.3.3).
+const SHA256_INITIAL: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+/// SHA-384 and SHA-512 round constants (FIPS 180-4 section 4.2.3).
+const SHA512_K: [u64; 80] = [
+    0x428a2f98d728ae22,
+    0x7137449123ef65cd,
+    0xb5c0fbcfec4d3b2f,
+    0xe9b5dba58189dbbc,
+    0x3956c25bf348b538,
+    0x59f111f1b605d019,
+    0x923f82a4af194f9b,
+    0xab1c5ed5da6d8118,
+    0xd807aa98a3030242,
+    0x12835b0145706fbe,
+    0x243185be4ee4b28c,
+    0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f,
+    0x80deb1fe3b1696b1,
+    0x9bdc06a725c71235,
+    0xc19bf174cf692694,
+    0xe49b69c19ef14ad2,
+    0xefbe4786384f25e3,
+    0x0fc19dc68b8cd5b5,
+    0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275,
+    0x4a7484aa6ea6e483,
+    0x5cb0a9dcbd41fbd4,
+    0x76f988da831153b5,
+    0x983e5152ee66dfab,
+    0xa831c66d2db43210,
+    0xb00327c898fb213f,
+    0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2,
+    0xd5a79147930aa725,
+    0x06ca6351e003826f,
+    0x142929670a0e6e70,
+    0x27b70a8546d22ffc,
+    0x2e1b21385c26c926,
+    0x4d2c6dfc5ac42aed,
+    0x53380d139d95b3df,
+    0x650a73548baf63de,
+    0x766a0abb3c77b2a8,
+    0x81c2c92e47edaee6,
+    0x92722c851482353b,
+    0xa2bfe8a14cf10364,
+    0xa81a664bbc423001,
+    0xc24b8b70d0f89791,
+    0xc76c51a30654be30,
+    0xd192e819d6ef5218,
+    0xd69906245565a910,
+    0xf40e35855771202a,
+    0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8,
+    0x1e376c085141ab53,
+    0x2748774cdf8eeb99,
+    0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63,
+    0x4ed8aa4ae3418acb,
+    0x5b9cca4f7763e373,
+    0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc,
+    0x78a5636f43172f60,
+    0x84c87814a1f0ab72,
+    0x8cc702081a6439ec,
+    0x90befffa23631e28,
+    0xa4506cebde82bde9,
+    0xbef9a3f7b2c67915,
+    0xc67178f2e372532b,
+    0xca273eceea26619c,
+    0xd186b8c721c0c207,
+    0xeada7dd6cde0eb1e,
+    0xf57d4f7fee6ed178,
+    0x06f067aa72176fba,
+    0x0a637dc5a2c898a6,
+    0x113f9804bef90dae,
+    0x1b710b35131c471b,
+    0x28db77f523047d84,
+    0x32caab7b40c72493,
+    0x3c9ebe0a15c9bebc,
+    0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6,
+    0x597f299cfc657e2a,
+    0x5fcb6fab3ad6faec,
+    0x6c44198c4a475817,
+];
+
+/// SHA-384 initial hash value (FIPS 180-4 section 5.3.4).
+const SHA384_INITIAL: [u64; 8] = [
+    0xcbbb9d5dc1059ed8,
+    0x629a292a367cd507,
+    0x9159015a3070dd17,
+    0x152fecd8f70e5939,
+    0x67332667ffc00b31,
+    0x8eb44a8768581511,
+    0xdb0c2e0d64f98fa7,
+    0x47b5481dbefa4fa4,
+];
+
+/// SHA-512 initial hash value (FIPS 180-4 section 5.3.5).
+const SHA512_INITIAL: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+/// The largest RSA modulus a DNSKEY may carry, in bits (RFC 3110 section 2).
+const MAX_RSA_MODULUS_BITS: usize = 4096;
+
+// The NIST P-256 curve y² = x³ - 3x + b over the integers modulo P256_P, with base
+// point (P256_GX, P256_GY) of prime order P256_N (FIPS 186-4 appendix D.1.2.3).
+const P256_P: &str = "ffffffff00000001000000000000000000000000ffffffffffffffffffffffff";
+const P256_N: &str = "ffffffff00000000ffffffffffffffffbce6faada7179e84f3b9cac2fc632551";
+const P256_B: &str = "5ac635d8aa3a93e7b3ebbd55769886bc651d06b0cc53b0f63bce3c3e27d2604b";
+const P256_GX: &str = "6b17d1f2e12c4247f8bce6e563a440f277037d812deb33a0f4a13945d898c296";
+const P256_GY: &str = "4fe342e2fe1a7f9b8ee7eb4a7c0f9e162bce33576b315ececbb6406837bf51f5";
+
+/// A hash function used by DNSSEC and TSIG.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    /// SHA-1, which NSEC3 hashes names with.
+    Sha1,
+    /// SHA-256.
+    Sha256,
+    /// SHA-384.
+    Sha384,
+    /// SHA-512.
+    Sha512,
+}
+
+impl HashAlgorithm {
+    /// Returns the digest of `data`.
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            HashAlgorithm::Sha1 => sha1(data),
+            HashAlgorithm::Sha256 => sha256(data),
+            HashAlgorithm::Sha384 => sha512_with(SHA384_INITIAL, data)[..48].to_vec(),
+            HashAlgorithm::Sha512 => sha512_with(SHA512_INITIAL, data),
+        }
+    }
+
+    /// The size of the blocks the hash function works on, in bytes.
+    fn block_size(self) -> usize {
+        match self {
+            HashAlgorithm::Sha1 | HashAlgorithm::Sha256 => 64,
+            HashAlgorithm::Sha384 | HashAlgorithm::Sha512 => 128,
+        }
+    }
+
+    /// The DER encoding of the DigestInfo that PKCS #1 v1.5 signatures wrap a
+    /// digest of this kind in, up to the digest itself (RFC 8017 section 9.2).
+    fn digest_info_prefix(self) -> &'static [u8] {
+        match self {
+            HashAlgorithm::Sha1 => &[
+                0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+                0x14,
+            ],
+            HashAlgorithm::Sha256 => &[
+                0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x01, 0x05, 0x00, 0x04, 0x20,
+            ],
+            HashAlgorithm::Sha384 => &[
+                0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x02, 0x05, 0x00, 0x04, 0x30,
+            ],
+            HashAlgorithm::Sha512 => &[
+                0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02,
+                0x03, 0x05, 0x00, 0x04, 0x40,
+            ],
+        }
+    }
+}
+
+/// Computes the HMAC of `message` under `key` with the given hash (RFC 2104).
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::crypto::{HashAlgorithm, hmac};
+///
+/// let mac = hmac(HashAlgorithm::Sha256, b"Jefe", b"what do ya want for nothing?");
+/// assert_eq!(mac[..4], [0x5b, 0xdc, 0xc1, 0x46]);
+/// ```
+pub fn hmac(hash: HashAlgorithm, key: &[u8], message: &[u8]) -> Vec<u8> {
+    // Keys longer than a block are hashed first; shorter ones are padded with zeros.
+    let mut block = if key.len() > hash.block_size() {
+        hash.digest(key)
+    } else {
+        key.to_vec()
+    };
+    block.resize(hash.block_size(), 0);
+
+    let mut inner: Vec<u8> = block.iter().map(|byte| byte ^ 0x36).collect();
+    inner.extend_from_slice(message);
+    let mut outer: Vec<u8> = block.iter().map(|byte| byte ^ 0x5c).collect();
+    outer.extend_from_slice(&hash.digest(&inner));
+    hash.digest(&outer)
+}
+
+/// Compares two MACs in time that depends only on their lengths, so that a forger
+/// cannot learn how much of a guess was right.
+pub fn macs_equal(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Pads a message as SHA-1 and SHA-2 do: a 1 bit, zeros, and the message length
+/// in bits, filling a whole number of blocks. The length takes an eighth of a block.
+fn pad(data: &[u8], block_size: usize) -> Vec<u8> {
+    let length_size = block_size / 8;
+    let mut padded = data.to_vec();
+    padded.push(0x80);
+    while !(padded.len() + length_size).is_multiple_of(block_size) {
+        padded.push(0);
+    }
+    let bits = data.len() as u128 * 8;
+    padded.extend_from_slice(&bits.to_be_bytes()[16 - length_size..]);
+    padded
+}
+
+/// SHA-1 (FIPS 180-4 section 6.1).
+fn sha1(data: &[u8]) -> Vec<u8> {
+    let mut state: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    for block in pad(data, 64).chunks(64) {
+        let mut schedule = [0u32; 80];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            schedule[i] = (schedule[i - 3] ^ schedule[i - 8] ^ schedule[i - 14] ^ schedule[i - 16])
+                .rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, word) in schedule.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (word, value) in state.iter_mut().zip([a, b, c, d, e]) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    state.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+/// SHA-256 (FIPS 180-4 section 6.2).
+fn sha256(data: &[u8]) -> Vec<u8> {
+    let mut state = SHA256_INITIAL;
+    for block in pad(data, 64).chunks(64) {
+        let mut schedule = [0u32; 64];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..64 {
+            let s0 = schedule[i - 15].rotate_right(7)
+                ^ schedule[i - 15].rotate_right(18)
+                ^ (schedule[i - 15] >> 3);
+            let s1 = schedule[i - 2].rotate_right(17)
+                ^ schedule[i - 2].rotate_right(19)
+                ^ (schedule[i - 2] >> 10);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+        let mut working = state;
+        for (k, word) in SHA256_K.iter().zip(schedule) {
+            let [a, b, c, d, e, f, g, h] = working;
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            working = [
+                temp1.wrapping_add(temp2),
+                a,
+                b,
+                c,
+                d.wrapping_add(temp1),
+                e,
+                f,
+                g,
+            ];
+        }
+        for (word, value) in state.iter_mut().zip(working) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    state.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+/// SHA-512 from the given initial hash value, which is also SHA-384 before
+/// truncation (FIPS 180-4 sections 6.4 and 6.5).
+fn sha512_with(initial: [u64; 8], data: &[u8]) -> Vec<u8> {
+    let mut state = initial;
+    for block in pad(data, 128).chunks(128) {
+        let mut schedule = [0u64; 80];
+        for (word, bytes) in schedule.iter_mut().zip(block.chunks(8)) {
+            *word = u64::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            let s0 = schedule[i - 15].rotate_right(1)
+                ^ schedule[i - 15].rotate_right(8)
+                ^ (schedule[i - 15] >> 7);
+            let s1 = schedule[i - 2].rotate_right(19)
+                ^ schedule[i - 2].rotate_right(61)
+                ^ (schedule[i - 2] >> 6);
+            schedule[i] = schedule[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(schedule[i - 7])
+                .wrapping_add(s1);
+        }
+        let mut working = state;
+        for (k, word) in SHA512_K.iter().zip(schedule) {
+            let [a, b, c, d, e, f, g, h] = working;
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let choice = (e & f) ^ (!e & g);
+            let temp1 = h
+                .wrapping_add(s1)
+                .wrapping_add(choice)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let majority = (a & b) ^ (a & c) ^ (b & c);
+            let temp2 = s0.wrapping_add(majority);
+            working = [
+                temp1.wrapping_add(temp2),
+                a,
+                b,
+                c,
+                d.wrapping_add(temp1),
+                e,
+                f,
+                g,
+            ];
+        }
+        for (word, value) in state.iter_mut().zip(working) {
+            *word = word.wrapping_add(value);
+        }
+    }
+    state.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+/// Verifies an RSA signature with PKCS #1 v1.5 padding, the scheme of RSA/SHA-256
+/// and RSA/SHA-512 DNSKEYs (RFC 5702).
+///
+/// # Arguments
+///
+/// * `public_key` - The key as DNSKEY records carry it (RFC 3110 section 2): the
+///   exponent length in one octet, or in two after a zero octet, then the exponent
+///   and the modulus
+/// * `hash` - The hash the signature was made over
+/// * `message` - The signed data
+/// * `signature` - The signature to check
+///
+/// Keys RFC 3110 does not allow, with a modulus over 4096 bits or an exponent
+/// longer than the modulus, never verify, so a hostile DNSKEY cannot make the
+/// check arbitrarily slow.
+pub fn verify_rsa(
+    public_key: &[u8],
+    hash: HashAlgorithm,
+    message: &[u8],
+    signature: &[u8],
+) -> bool {
+    let (exponent_length, rest) = match public_key {
+        [0, high, low, rest @ ..] => (usize::from(u16::from_be_bytes([*high, *low])), rest),
+        [length, rest @ ..] => (usize::from(*length), rest),
+        [] => return false,
+    };
+    if exponent_length == 0 || rest.len() <= exponent_length {
+        return false;
+    }
+    let (exponent, modulus) = rest.split_at(exponent_length);
+    let significant = |bytes: &[u8]| bytes.iter().skip_while(|&&byte| byte == 0).count();
+    if significant(modulus) * 8 > MAX_RSA_MODULUS_BITS
+        || significant(exponent) > significant(modulus)
+    {
+        return false;
+    }
+    let exponent = BigUint::from_be_bytes(exponent);
+    let modulus = BigUint::from_be_bytes(modulus);
+    let signature = BigUint::from_be_bytes(signature);
+    if modulus.is_zero() || signature >= modulus {
+        return false;
+    }
+
+    // EMSA-PKCS1-v1_5 (RFC 8017 section 9.2): 0x00 0x01, at least eight 0xff
+    // octets, 0x00, then the DigestInfo, filling the length of the modulus.
+    let length = modulus.bits().div_ceil(8);
+    let digest_info = [hash.digest_info_prefix(), &hash.digest(message)].concat();
+    let Some(padding) = length.checked_sub(digest_info.len() + 3) else {
+        return false;
+    };
+    if padding < 8 {
+        return false;
+    }
+    let mut expected = vec![0x00, 0x01];
+    expected.resize(2 + padding, 0xff);
+    expected.push(0x00);
+    expected.extend_from_slice(&digest_info);
+
+    signature.mod_pow(&exponent, &modulus).to_be_bytes(length) == expected
+}
+
+/// The modulus of a 1024-bit RSA key made with `openssl genrsa`, whose public
+/// exponent is 65537. Tests sign with it; it is far too short for real use.
+#[cfg(test)]
+pub const TEST_RSA_MODULUS: &str = concat!(
+    "c74a94c20f87abfe34708c081f9c21823ccc6b9f4ce506e7d0b0e1ba8a294e5737f2bb792fb7b244",
+    "e1ea3fb80326eaea4a6c9ea53030827c25669762d67b970f784720c3a2ff2f36941d28d4d1e10b7f",
+    "192769040c5538e07f6a0bd0deff551c0f3c34469215f9baf8629e61a266e2b0509178e4b8763e3e",
+    "7ddff8688e18ac29"
+);
+
+/// The private exponent of [`TEST_RSA_MODULUS`].
+#[cfg(test)]
+pub const TEST_RSA_PRIVATE_EXPONENT: &str = concat!(
+    "09d403c20e42029966bca62b4063d2c3397c5b525fd86b9ec30bf615dcfa6c82a09fb7df221b4d0f",
+    "5894d4cf9e582cfda0edfa64eb1b7953e6044932f6a0e1b22b541716b3af3d53a44e1eaf3522ff26",
+    "02b8d07acea47a01030972533fcc4348b7765ab76dd95771baa14e749f3535a98c9ec31057c5752f",
+    "324b5055400f7481"
+);
+
+/// Signs `message` with an RSA private key and PKCS #1 v1.5 padding, so tests can
+/// build signed zones.
+#[cfg(test)]
+pub fn sign_rsa(
+    modulus: &[u8],
+    private_exponent: &[u8],
+    hash: HashAlgorithm,
+    message: &[u8],
+) -> Vec<u8> {
+    let modulus = BigUint::from_be_bytes(modulus);
+    let length = modulus.bits().div_ceil(8);
+    let digest_info = [hash.digest_info_prefix(), &hash.digest(message)].concat();
+    let mut encoded = vec![0x00, 0x01];
+    encoded.resize(length - digest_info.len() - 1, 0xff);
+    encoded.push(0x00);
+    encoded.extend_from_slice(&digest_info);
+    BigUint::from_be_bytes(&encoded)
+        .mod_pow(&BigUint::from_be_bytes(private_exponent), &modulus)
+        .to_be_bytes(length)
+}
+
+/// Verifies an ECDSA P-256 signature over the SHA-256 digest of `message`, the
+/// scheme of ECDSAP256SHA256 DNSKEYs (RFC 6605).
+///
+/// # Arguments
+///
+/// * `public_key` - The curve point as DNSKEY records carry it: its x and y
+///   coordinates, 32 octets each
+/// * `message` - The signed data
+/// * `signature` - The values r and s, 32 octets each
+pub fn verify_ecdsa_p256(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    if public_key.len() != 64 || signature.len() != 64 {
+        return false;
+    }
+    let curve = Curve::p256();
+    let n = BigUint::from_hex(P256_N);
+    let key = Point::affine(
+        BigUint::from_be_bytes(&public_key[..32]),
+        BigUint::from_be_bytes(&public_key[32..]),
+    );
+    if key.x >= curve.p || key.y >= curve.p || !curve.contains(&key) {
+        return false;
+    }
+    let r = BigUint::from_be_bytes(&signature[..32]);
+    let s = BigUint::from_be_bytes(&signature[32..]);
+    if r.is_zero() || s.is_zero() || r >= n || s >= n {
+        return false;
+    }
+
+    // SEC 1 section 4.1.4: with w = s⁻¹, the point (e·w)·G + (r·w)·Q must have
+    // r as its x coordinate, modulo n.
+    let e = BigUint::from_be_bytes(&sha256(message)).rem(&n);
+    let w = s.mod_pow(&n.sub(&BigUint::from_u32(2)), &n);
+    let u1 = e.mul(&w).rem(&n);
+    let u2 = r.mul(&w).rem(&n);
+    let point = curve.multiply_sum(&u1, &curve.generator, &u2, &key);
+    match curve.affine_x(&point) {
+        Some(x) => x.rem(&n) == r,
+        None => false,
+    }
+}
+
+/// A point on an elliptic curve in Jacobian coordinates, standing for the affine
+/// point (x/z², y/z³). A z of zero is the point at infinity.
+#[derive(Debug, Clone)]
+struct Point {
+    x: BigUint,
+    y: BigUint,
+    z: BigUint,
+}
+
+impl Point {
+    fn affine(x: BigUint, y: BigUint) -> Self {
+        Point {
+            x,
+            y,
+            z: BigUint::from_u32(1),
+        }
+    }
+
+    fn infinity() -> Self {
+        Point {
+            x: BigUint::from_u32(1),
+            y: BigUint::from_u32(1),
+            z: BigUint::zero(),
+        }
+    }
+
+    fn is_infinity(&self) -> bool {
+        self.z.is_zero()
+    }
+}
+
+/// A short Weierstrass curve y² = x³ - 3x + b over the integers modulo a prime.
+struct Curve {
+    p: BigUint,
+    b: BigUint,
+    generator: Point,
+}
+
+impl Curve {
+    fn p256() -> Self {
+        Curve {
+            p: BigUint::from_hex(P256_P),
+            b: BigUint::from_hex(P256_B),
+            generator: Point::affine(BigUint::from_hex(P256_GX), BigUint::from_hex(P256_GY)),
+        }
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        let sum = a.add(b);
+        if sum >= self.p { sum.sub(&self.p) } else { sum }
+    }
+
+    fn sub(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        if a >= b {
+            a.sub(b)
+        } else {
+            a.add(&self.p).sub(b)
+        }
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a.mul(b).rem(&self.p)
+    }
+
+    /// Returns `a` added to itself `times` times.
+    fn times(&self, a: &BigUint, times: u32) -> BigUint {
+        (1..times).fold(a.clone(), |sum, _| self.add(&sum, a))
+    }
+
+    /// Returns whether an affine point satisfies the curve equation.
+    fn contains(&self, point: &Point) -> bool {
+        let x = &point.x;
+        let y_squared = self.mul(&point.y, &point.y);
+        let x_cubed = self.mul(&self.mul(x, x), x);
+        y_squared == self.add(&self.sub(&x_cubed, &self.times(x, 3)), &self.b)
+    }
+
+    /// Doubles a point ("dbl-2001-b", for curves with a = -3).
+    fn double(&self, point: &Point) -> Point {
+        if point.is_infinity() {
+            return point.clone();
+        }
+        let delta = self.mul(&point.z, &point.z);
+        let gamma = self.mul(&point.y, &point.y);
+        let beta = self.mul(&point.x, &gamma);
+        let alpha = self.times(
+            &self.mul(&self.sub(&point.x, &delta), &self.add(&point.x, &delta)),
+            3,
+        );
+        let x = self.sub(&self.mul(&alpha, &alpha), &self.times(&beta, 8));
+        let y_plus_z = self.add(&point.y, &point.z);
+        let z = self.sub(&self.sub(&self.mul(&y_plus_z, &y_plus_z), &gamma), &delta);
+        let y = self.sub(
+            &self.mul(&alpha, &self.sub(&self.times(&beta, 4), &x)),
+            &self.times(&self.mul(&gamma, &gamma), 8),
+        );
+        Point { x, y, z }
+    }
+
+    /// Adds two points ("add-2007-bl").
+    fn add_points(&self, a: &Point, b: &Point) -> Point {
+        if a.is_infinity() {
+            return b.clone();
+        }
+        if b.is_infinity() {
+            return a.clone();
+        }
+        let z1z1 = self.mul(&a.z, &a.z);
+        let z2z2 = self.mul(&b.z, &b.z);
+        let u1 = self.mul(&a.x, &z2z2);
+        let u2 = self.mul(&b.x, &z1z1);
+        let s1 = self.mul(&self.mul(&a.y, &b.z), &z2z2);
+        let s2 = self.mul(&self.mul(&b.y, &a.z), &z1z1);
+        let h = self.sub(&u2, &u1);
+        let r = self.times(&self.sub(&s2, &s1), 2);
+        if h.is_zero() {
+            // The same x: either the same point, or a point and its negation.
+            return if r.is_zero() {
+                self.double(a)
+            } else {
+                Point::infinity()
+            };
+        }
+        let two_h = self.times(&h, 2);
+        let i = self.mul(&two_h, &two_h);
+        let j = self.mul(&h, &i);
+        let v = self.mul(&u1, &i);
+        let x = self.sub(&self.sub(&self.mul(&r, &r), &j), &self.times(&v, 2));
+        let y = self.sub(
+            &self.mul(&r, &self.sub(&v, &x)),
+            &self.times(&self.mul(&s1, &j), 2),
+        );
+        let z1_plus_z2 = self.add(&a.z, &b.z);
+        let z = self.mul(
+            &self.sub(&self.sub(&self.mul(&z1_plus_z2, &z1_plus_z2), &z1z1), &z2z2),
+            &h,
+        );
+        Point { x, y, z }
+    }
+
+    /// Computes `k1·a + k2·b` with one pass over the bits of both scalars.
+    fn multiply_sum(&self, k1: &BigUint, a: &Point, k2: &BigUint, b: &Point) -> Point {
+        let both = self.add_points(a, b);
+        let mut result = Point::infinity();
+        for bit in (0..k1.bits().max(k2.bits())).rev() {
+            result = self.double(&result);
+            match (k1.bit(bit), k2.bit(bit)) {
+                (true, true) => result = self.add_points(&result, &both),
+                (true, false) => result = self.add_points(&result, a),
+                (false, true) => result = self.add_points(&result, b),
+                (false, false) => {}
+            }
+        }
+        result
+    }
+
+    /// Returns the affine x coordinate of a point, or `None` for the point at infinity.
+    fn affine_x(&self, point: &Point) -> Option<BigUint> {
+        if point.is_infinity() {
+            return None;
+        }
+        let z_inverse = point.z.mod_pow(&self.p.sub(&BigUint::from_u32(2)), &self.p);
+        Some(self.mul(&point.x, &self.mul(&z_inverse, &z_inverse)))
+    }
+}
+
+/// An unsigned integer of any size, as little-endian 32-bit limbs without leading
+/// zero limbs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BigUint(Vec<u32>);
+
+impl BigUint {
+    fn zero() -> Self {
+        BigUint(Vec::new())
+    }
+
+    fn from_u32(value: u32) -> Self {
+        BigUint(vec![value]).normalized()
+    }
+
+    fn from_be_bytes(bytes: &[u8]) -> Self {
+        let limbs = bytes
+            .rchunks(4)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0u32, |limb, byte| limb << 8 | u32::from(*byte))
+            })
+            .collect();
+        BigUint(limbs).normalized()
+    }
+
+    fn from_hex(hex: &str) -> Self {
+        let bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).expect("valid hex constant"))
+            .collect();
+        BigUint::from_be_bytes(&bytes)
+    }
+
+    /// Returns the number as big-endian bytes, padded with leading zeros to `length`.
+    fn to_be_bytes(&self, length: usize) -> Vec<u8> {
+        let mut bytes: Vec<u8> = self
+            .0
+            .iter()
+            .rev()
+            .flat_map(|limb| limb.to_be_bytes())
+            .skip_while(|byte| *byte == 0)
+            .collect();
+        if bytes.len() < length {
+            bytes.splice(0..0, std::iter::repeat_n(0, length - bytes.len()));
+        }
+        bytes
+    }
+
+    fn normalized(mut self) -> Self {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+        self
+    }
+
+    fn is_zero(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Returns the number of significant bits.
+    fn bits(&self) -> usize {
+        match self.0.last() {
+            Some(top) => self.0.len() * 32 - top.leading_zeros() as usize,
+            None => 0,
+        }
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.0
+            .get(index / 32)
+            .is_some_and(|limb| limb >> (index % 32) & 1 == 1)
+    }
+
+    fn add(&self, other: &BigUint) -> BigUint {
+        let (long, short) = if self.0.len() >= other.0.len() {
+            (&self.0, &other.0)
+        } else {
+            (&other.0, &self.0)
+        };
+        let mut limbs = Vec::with_capacity(long.len() + 1);
+        let mut carry = 0u64;
+        for (i, limb) in long.iter().enumerate() {
+            let sum = u64::from(*limb) + u64::from(short.get(i).copied().unwrap_or(0)) + carry;
+            limbs.push(sum as u32);
+            carry = sum >> 32;
+        }
+        limbs.push(carry as u32);
+        BigUint(limbs).normalized()
+    }
+
+    /// Subtracts `other`, which must not be larger.
+    fn sub(&self, other: &BigUint) -> BigUint {
+        debug_assert!(*self >= *other);
+        let mut limbs = Vec::with_capacity(self.0.len());
+        let mut borrow = 0i64;
+        for (i, limb) in self.0.iter().enumerate() {
+            let difference =
+                i64::from(*limb) - i64::from(other.0.get(i).copied().unwrap_or(0)) - borrow;
+            limbs.push(difference as u32);
+            borrow = i64::from(difference < 0);
+        }
+        BigUint(limbs).normalized()
+    }
+
+    fn mul(&self, other: &BigUint) -> BigUint {
+        let mut limbs = vec![0u32; self.0.len() + other.0.len()];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0u64;
+            for (j, b) in other.0.iter().enumerate() {
+                let product = u64::from(*a) * u64::from(*b) + u64::from(limbs[i + j]) + carry;
+                limbs[i + j] = product as u32;
+                carry = product >> 32;
+            }
+            limbs[i + other.0.len()] = carry as u32;
+        }
+        BigUint(limbs).normalized()
+    }
+
+    /// Returns the remainder of division by `modulus`, which must not be zero.
+    ///
+    /// This is Knuth's Algorithm D (The Art of Computer Programming, volume 2,
+    /// section 4.3.1) as laid out in Hacker's Delight, keeping only the remainder.
+    fn rem(&self, modulus: &BigUint) -> BigUint {
+        assert!(!modulus.is_zero(), "division by zero");
+        if self < modulus {
+            return self.clone();
+        }
+        let n = modulus.0.len();
+        if n == 1 {
+            let divisor = u64::from(modulus.0[0]);
+            let remainder = self.0.iter().rev().fold(0u64, |remainder, limb| {
+                (remainder << 32 | u64::from(*limb)) % divisor
+            });
+            return BigUint(vec![remainder as u32]).normalized();
+        }
+
+        // Shift both numbers left until the divisor's top bit is set, which keeps
+        // each estimated quotient digit at most two too large.
+        let shift = modulus.0[n - 1].leading_zeros();
+        let shifted = |limbs: &[u32]| -> Vec<u32> {
+            let mut out = Vec::with_capacity(limbs.len() + 1);
+            let mut carry = 0u32;
+            for limb in limbs {
+                out.push(limb << shift | carry);
+                carry = if shift == 0 { 0 } else { limb >> (32 - shift) };
+            }
+            out.push(carry);
+            out
+        };
+        let mut divisor = shifted(&modulus.0);
+        divisor.pop();
+        let mut remainder = shifted(&self.0);
+        let top = u64::from(divisor[n - 1]);
+        let next = u64::from(divisor[n - 2]);
+
+        for j in (0..=self.0.len() - n).rev() {
+            // Estimate the quotient digit from the top two limbs, then correct it.
+            let numerator = u64::from(remainder[j + n]) << 32 | u64::from(remainder[j + n - 1]);
+            let mut digit = numerator / top;
+            let mut rest = numerator % top;
+            while digit > u64::from(u32::MAX)
+                || digit * next > (rest << 32 | u64::from(remainder[j + n - 2]))
+            {
+                digit -= 1;
+                rest += top;
+                if rest > u64::from(u32::MAX) {
+                    break;
+                }
+            }
+
+            // Subtract digit × divisor from the current window.
+            let mut borrow = 0i64;
+            for (i, limb) in divisor.iter().enumerate() {
+                let product = digit * u64::from(*limb);
+                let difference =
+                    i64::from(remainder[i + j]) - borrow - (product & 0xffff_ffff) as i64;
+                remainder[i + j] = difference as u32;
+                borrow = (product >> 32) as i64 - (difference >> 32);
+            }
+            let difference = i64::from(remainder[j + n]) - borrow;
+            remainder[j + n] = difference as u32;
+
+            // The digit was one too large: add the divisor back.
+            if difference < 0 {
+                let mut carry = 0u64;
+                for (i, limb) in divisor.iter().enumerate() {
+                    let sum = u64::from(remainder[i + j]) + u64::from(*limb) + carry;
+                    remainder[i + j] = sum as u32;
+                    carry = sum >> 32;
+                }
+                remainder[j + n] = remainder[j + n].wrapping_add(carry as u32);
+            }
+        }
+
+        // Undo the shift.
+        let limbs = (0..n)
+            .map(|i| {
+                if shift == 0 {
+                    remainder[i]
+                } else {
+                    remainder[i] >> shift | remainder[i + 1] << (32 - shift)
+                }
+            })
+            .collect();
+        BigUint(limbs).normalized()
+    }
+
+    /// Returns `self` raised to `exponent`, modulo `modulus`.
+    fn mod_pow(&self, exponent: &BigUint, modulus: &BigUint) -> BigUint {
+        let base = self.rem(modulus);
+        let mut result = BigUint::from_u32(1).rem(modulus);
+        for bit in (0..exponent.bits()).rev() {
+            result = result.mul(&result).rem(modulus);
+            if exponent.bit(bit) {
+                result = result.mul(&base).rem(modulus);
+            }
+        }
+        result
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.iter().rev().cmp(other.0.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transcript::decode_hex;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+
+    #[test]
+    fn test_hashes() {
+        let messages: [&[u8]; 3] = [
+            b"abc",
+            b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq",
+            &[b'a'; 1000],
+        ];
+        let expected = [
+            [
+                "a9993e364706816aba3e25717850c26c9cd0d89d",
+                "84983e441c3bd26ebaae4aa1f95129e5e54670f1",
+                "291e9a6c66994949b57ba5e650361e98fc36b1ba",
+            ],
+            [
+                "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1",
+                "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3",
+            ],
+            [
+                concat!(
+                    "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded163",
+                    "1a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+                ),
+                concat!(
+                    "3391fdddfc8dc7393707a65b1b4709397cf8b1d162af05ab",
+                    "fe8f450de5f36bc6b0455a8520bc4e6f5fe95b1fe3c8452b"
+                ),
+                concat!(
+                    "f54480689c6b0b11d0303285d9a81b21a93bca6ba5a1b447",
+                    "2765dca4da45ee328082d469c650cd3b61b16d3266ab8ced"
+                ),
+            ],
+            [
+                concat!(
+                    "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a",
+                    "2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+                ),
+                concat!(
+                    "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c335",
+                    "96fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445"
+                ),
+                concat!(
+                    "67ba5535a46e3f86dbfbed8cbbaf0125c76ed549ff8b0b9e03e0c88cf90fa634",
+                    "fa7b12b47d77b694de488ace8d9a65967dc96df599727d3292a8d9d447709c97"
+                ),
+            ],
+        ];
+        let algorithms = [
+            HashAlgorithm::Sha1,
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Sha384,
+            HashAlgorithm::Sha512,
+        ];
+        for (algorithm, digests) in algorithms.iter().zip(expected) {
+            for (message, digest) in messages.iter().zip(digests) {
+                assert_eq!(hex(&algorithm.digest(message)), digest, "{:?}", algorithm);
+            }
+        }
+    }
+
+    #[test]
+    fn test_hmac() {
+        // RFC 2202 and RFC 4231 test case 2, and RFC 4231 test case 6 for a key
+        // longer than the block.
+        let message = b"what do ya want for nothing?";
+        assert_eq!(
+            hex(&hmac(HashAlgorithm::Sha1, b"Jefe", message)),
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79"
+        );
+        assert_eq!(
+            hex(&hmac(HashAlgorithm::Sha256, b"Jefe", message)),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+        assert_eq!(
+            hex(&hmac(HashAlgorithm::Sha384, b"Jefe", message)),
+            concat!(
+                "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47",
+                "e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649"
+            )
+        );
+        assert_eq!(
+            hex(&hmac(
+                HashAlgorithm::Sha512,
+                &[0xaa; 131],
+                b"Test Using Larger Than Block-Size Key - Hash Key First"
+            )),
+            concat!(
+                "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352",
+                "6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598"
+            )
+        );
+
+        assert!(macs_equal(b"abc", b"abc"));
+        assert!(!macs_equal(b"abc", b"abd"));
+        assert!(!macs_equal(b"abc", b"ab"));
+    }
+
+    #[test]
+    fn test_big_integer_arithmetic() {
+        let a = BigUint::from_hex(
+            "c74a94c20f87abfe34708c081f9c21823ccc6b9f4ce506e7d0b0e1ba8a294e5737f2bb792fb7b244e1ea3fb80326ea",
+        );
+        let p = BigUint::from_hex(P256_P);
+        assert_eq!(
+            a.mul(&a).rem(&p),
+            BigUint::from_hex("d5284390907d3016910a8a7ce175bba9161e5ba45896484b2199e0724286be05")
+        );
+        assert_eq!(
+            a.rem(&BigUint::from_u32(0x1234567)),
+            BigUint::from_u32(0x29159b)
+        );
+        assert_eq!(
+            a.mod_pow(&BigUint::from_u32(65537), &p),
+            BigUint::from_hex("246c6840b4960ab6524e58773464e4a8f9b7e89d5c6358bf48f9379f326184d9")
+        );
+        assert_eq!(a.add(&p).sub(&p), a);
+        assert_eq!(BigUint::from_u32(1).to_be_bytes(3), [0, 0, 1]);
+    }
+
+    #[test]
+    fn test_rsa_signatures() {
+        let mut public_key = vec![3, 0x01, 0x00, 0x01];
+        public_key.extend(decode_hex(TEST_RSA_MODULUS).unwrap());
+        let sha256_signature = decode_hex(concat!(
+            "379810fe9f490e016d7750d94e97877e598bce341083c13d3bc6875be085bea347a6e94a344d5924",
+            "539a7d05c9d38e64f56c1517f2466da2d6581fae2c40b03f8550ff0896dfcd1fa4871be83812d353",
+            "206b3ab9dadfa6b9d7e0fa191e4fdbd292f5f0bfe2d44bff521428a015b4af20c21a46a1a0f5e3ec",
+            "57632f41704bca68"
+        ))
+        .unwrap();
+        let sha512_signature = decode_hex(concat!(
+            "2d3b928c08141f7a2298383d240c32ad74c938c40bf12d8717c3a42ba3d792d393de637c1ccd8f72",
+            "6ad72bfda7de1139888c44363409cee85ffe75dd1043e573d72d85f4eb2c18cc2e852afba3ac75c4",
+            "3386090dfddd1ace4b3fb8344d123b7800f674ce869d139f0f076b8b773c0c7ad258eb563ad27a2e",
+            "58a3ab34ef5d4489"
+        ))
+        .unwrap();
+        // Signatures made with `openssl dgst -sign`.
+        assert!(verify_rsa(
+            &public_key,
+            HashAlgorithm::Sha256,
+            b"dnssec",
+            &sha256_signature
+        ));
+        assert!(verify_rsa(
+            &public_key,
+            HashAlgorithm::Sha512,
+            b"dnssec",
+            &sha512_signature
+        ));
+        assert!(!verify_rsa(
+            &public_key,
+            HashAlgorithm::Sha256,
+            b"dnssed",
+            &sha256_signature
+        ));
+        assert!(!verify_rsa(
+            &public_key,
+            HashAlgorithm::Sha512,
+            b"dnssec",
+            &sha256_signature
+        ));
+        assert!(!verify_rsa(&[], HashAlgorithm::Sha256, b"dnssec", &[]));
+
+        // Oversized keys are refused before any arithmetic on them.
+        let mut huge_modulus = vec![3, 0x01, 0x00, 0x01];
+        huge_modulus.extend(vec![0xff; 513]);
+        assert!(!verify_rsa(
+            &huge_modulus,
+            HashAlgorithm::Sha256,
+            b"dnssec",
+            &sha256_signature
+        ));
+        let mut huge_exponent = vec![0, 0x01, 0x01];
+        huge_exponent.extend(vec![0xff; 257]);
+        huge_exponent.extend(decode_hex(TEST_RSA_MODULUS).unwrap());
+        assert!(!verify_rsa(
+            &huge_exponent,
+            HashAlgorithm::Sha256,
+            b"dnssec",
+            &sha256_signature
+        ));
+
+        // PKCS #1 v1.5 signatures are deterministic, so signing reproduces them.
+        let signed = sign_rsa(
+            &decode_hex(TEST_RSA_MODULUS).unwrap(),
+            &decode_hex(TEST_RSA_PRIVATE_EXPONENT).unwrap(),
+            HashAlgorithm::Sha256,
+            b"dnssec",
+        );
+        assert_eq!(signed, sha256_signature);
+    }
+
+    #[test]
+    fn test_ecdsa_p256_signatures() {
+        let public_key = decode_hex(concat!(
+            "3211d64b3607e151878c7a33b30bd84bbdd7a790a3ba212dbcab7f7ede00feda",
+            "1465634ab4c8231b547750a0bd487dba7b4803879f0b7c1d393be8ff14798f5b"
+        ))
+        .unwrap();
+        // A signature made with `openssl dgst -sign`, converted from DER to r and s.
+        let signature = decode_hex(concat!(
+            "484684b2317c0496588497067522cdb1bc9781cd3ea24c6baf815f105bd22c1f",
+            "1b85c41144d5d977ecb088112db7a7e4982f33a458446d5917a269631b1059dd"
+        ))
+        .unwrap();
+        assert!(verify_ecdsa_p256(&public_key, b"dnssec", &signature));
+        assert!(!verify_ecdsa_p256(&public_key, b"dnssed", &signature));
+
+        let mut off_curve = public_key.clone();
+        off_curve[63] ^= 1;
+        assert!(!verify_ecdsa_p256(&off_curve, b"dnssec", &signature));
+        let mut tampered = signature.clone();
+        tampered[0] ^= 1;
+        assert!(!verify_ecdsa_p256(&public_key, b"dnssec", &tampered));
+    }
+}
@@ -9,9 +9,17 @@
 //!
 //! # Core Types
 //!
-//! - [`QueryType`] - Enumeration of supported DNS record types (A, AAAA, CNAME, MX, TXT)
+//! - [`QueryType`] - Enumeration of supported DNS record types (A, AAAA, CNAME, MX, TXT, PTR, NS,
+//!   SOA, HINFO, SRV, NAPTR, SSHFP, SVCB, HTTPS, and the DNSSEC types DS, DNSKEY, RRSIG, NSEC
+//!   and NSEC3), the ANY and AXFR query types and the EDNS(0) OPT and TSIG
+//!   pseudo-records
 //! - [`DnsHeader`] - Represents the 12-byte DNS message header
 //! - [`ResponseCode`] - DNS response codes indicating query success or failure types
+//! - [`Edns`] - The EDNS(0) parameters carried in an OPT pseudo-record (RFC 6891)
+//!
+//! Canonical name order and the canonical form of records and RRsets (RFC 4034
+//! section 6) are available through [`compare_canonical_names`],
+//! [`ResourceRecord::to_canonical`] and [`pack_canonical_rrset`].
 //!
 //! # Examples
 //!
@@ -23,7 +31,7 @@
 //!
 //! // Parse a query type from string
 //! let query_type = QueryType::from_str("A").unwrap();
-//! assert_eq!(query_type as u16, 1);
+//! assert_eq!(u16::from(query_type), 1);
 //!
 //! // Create and serialize a DNS header
 //! let mut header = DnsHeader::new();
@@ -43,16 +51,24 @@
 
 use core::fmt;
 use std::{
+    borrow::Cow,
+    cmp::Ordering,
+    collections::HashMap,
     io::{Cursor, Read},
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     str::FromStr,
+    time::{Duration, UNIX_EPOCH},
 };
 
+use crate::idn::to_ascii_label;
+use crate::output::format_timestamp;
+
 /// Represents the type of a DNS query according to RFC 1035 and subsequent RFCs.
 ///
 /// This enum maps DNS query types to their standard numeric codes as defined in the DNS
-/// specification. It supports the most commonly used DNS record types for basic DNS
-/// resolution operations.
+/// specification. It names the most commonly used DNS record types for basic DNS
+/// resolution operations, and carries any other type code in [`QueryType::Unknown`],
+/// written `TYPE<number>` as in RFC 3597.
 ///
 /// # Examples
 ///
@@ -65,22 +81,86 @@ use std::{
 /// assert_eq!(query_type, QueryType::A);
 ///
 /// // Convert to numeric code
-/// let code = query_type as u16;
+/// let code = u16::from(query_type);
 /// assert_eq!(code, 1);
+///
+/// // Any other type can be named by number
+/// assert_eq!(QueryType::from_str("TYPE65280").unwrap(), QueryType::Unknown(65280));
 /// ```
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[repr(u16)]
+#[allow(clippy::upper_case_acronyms)] // Record type mnemonics as spelled in the RFCs
 pub enum QueryType {
-    /// IPv4 address record (RFC 1035).
-    A = 1,
-    /// IPv6 address record (RFC 3596).
-    AAAA = 28,
-    /// Canonical name record (RFC 1035).
-    CNAME = 5,
-    /// Mail exchange record (RFC 1035).
-    MX = 15,
-    /// Text record (RFC 1035).
-    TXT = 16,
+    /// IPv4 address record, type 1 (RFC 1035).
+    A,
+    /// IPv6 address record, type 28 (RFC 3596).
+    AAAA,
+    /// Canonical name record, type 5 (RFC 1035).
+    CNAME,
+    /// Mail exchange record, type 15 (RFC 1035).
+    MX,
+    /// Text record, type 16 (RFC 1035).
+    TXT,
+    /// Domain name pointer record, type 12, used for reverse lookups (RFC 1035).
+    PTR,
+    /// Authoritative name server record, type 2 (RFC 1035).
+    NS,
+    /// Start of authority record, type 6, describing a zone (RFC 1035).
+    SOA,
+    /// Host information record, type 13 (RFC 1035). Servers that refuse ANY
+    /// queries answer with a synthesized one (RFC 8482).
+    HINFO,
+    /// Service locator record, type 33 (RFC 2782).
+    SRV,
+    /// Naming authority pointer record, type 35, used by ENUM and SIP (RFC 3403).
+    NAPTR,
+    /// EDNS(0) OPT pseudo-record, type 41 (RFC 6891). It only ever appears in the
+    /// additional section and carries [`Edns`] parameters rather than data.
+    OPT,
+    /// Delegation signer record, type 43 (RFC 4034).
+    DS,
+    /// SSH host key fingerprint record, type 44 (RFC 4255).
+    SSHFP,
+    /// DNSSEC signature record, type 46 (RFC 4034).
+    RRSIG,
+    /// Next secure record, type 47, proving that a name or type does not exist
+    /// (RFC 4034).
+    NSEC,
+    /// DNSSEC public key record, type 48 (RFC 4034).
+    DNSKEY,
+    /// Hashed next secure record, type 50 (RFC 5155).
+    NSEC3,
+    /// Service binding record, type 64 (RFC 9460).
+    SVCB,
+    /// Service binding record for HTTPS origins, type 65 (RFC 9460).
+    HTTPS,
+    /// Transaction signature pseudo-record, type 250 (RFC 8945). It only ever appears
+    /// last in the additional section and authenticates the message it ends.
+    TSIG,
+    /// Zone transfer, type 252 (RFC 5936). Only valid in questions, and only over
+    /// TCP, where the answer is the whole zone in a stream of messages.
+    AXFR,
+    /// Query for every record of a name, type 255 (RFC 1035). Only valid in
+    /// questions; many servers answer it with a single record instead (RFC 8482).
+    ANY,
+    /// Any other record type, by its numeric code (RFC 3597).
+    ///
+    /// Codes of the named variants are always converted to those variants, so
+    /// `Unknown` never holds, for example, 1 in place of [`QueryType::A`].
+    Unknown(u16),
+}
+
+impl QueryType {
+    /// The record types that together give an overview of a domain, in display order.
+    ///
+    /// This is the set queried by `--all` and included in domain reports.
+    pub const COMMON: [QueryType; 6] = [
+        QueryType::A,
+        QueryType::AAAA,
+        QueryType::CNAME,
+        QueryType::MX,
+        QueryType::TXT,
+        QueryType::NS,
+    ];
 }
 
 impl FromStr for QueryType {
@@ -99,11 +179,16 @@ impl FromStr for QueryType {
     /// assert_eq!(QueryType::from_str("A").unwrap(), QueryType::A);
     /// assert_eq!(QueryType::from_str("aaaa").unwrap(), QueryType::AAAA);
     /// assert_eq!(QueryType::from_str("CnAmE").unwrap(), QueryType::CNAME);
+    ///
+    /// // RFC 3597 generic names, which map to named types where one exists
+    /// assert_eq!(QueryType::from_str("type65280").unwrap(), QueryType::Unknown(65280));
+    /// assert_eq!(QueryType::from_str("TYPE1").unwrap(), QueryType::A);
     /// ```
     ///
     /// # Errors
     ///
-    /// Returns an error if the input string does not match any known DNS record type.
+    /// Returns an error if the input string is neither a known DNS record type nor
+    /// `TYPE` followed by a number from 0 to 65535.
     ///
     /// ```rust
     /// use dns_resolver::dns::QueryType;
@@ -119,7 +204,29 @@ impl FromStr for QueryType {
             "CNAME" => Ok(QueryType::CNAME),
             "MX" => Ok(QueryType::MX),
             "TXT" => Ok(QueryType::TXT),
-            _ => Err(format!("Unknown query type: {}", s)),
+            "PTR" => Ok(QueryType::PTR),
+            "NS" => Ok(QueryType::NS),
+            "SOA" => Ok(QueryType::SOA),
+            "HINFO" => Ok(QueryType::HINFO),
+            "SRV" => Ok(QueryType::SRV),
+            "NAPTR" => Ok(QueryType::NAPTR),
+            "OPT" => Ok(QueryType::OPT),
+            "DS" => Ok(QueryType::DS),
+            "SSHFP" => Ok(QueryType::SSHFP),
+            "RRSIG" => Ok(QueryType::RRSIG),
+            "NSEC" => Ok(QueryType::NSEC),
+            "DNSKEY" => Ok(QueryType::DNSKEY),
+            "NSEC3" => Ok(QueryType::NSEC3),
+            "SVCB" => Ok(QueryType::SVCB),
+            "HTTPS" => Ok(QueryType::HTTPS),
+            "TSIG" => Ok(QueryType::TSIG),
+            "AXFR" => Ok(QueryType::AXFR),
+            "ANY" => Ok(QueryType::ANY),
+            upper => upper
+                .strip_prefix("TYPE")
+                .and_then(|code| code.parse::<u16>().ok())
+                .map(QueryType::from)
+                .ok_or_else(|| format!("Unknown query type: {}", s)),
         }
     }
 }
@@ -143,46 +250,213 @@ impl fmt::Display for QueryType {
             QueryType::CNAME => write!(f, "CNAME"),
             QueryType::MX => write!(f, "MX"),
             QueryType::TXT => write!(f, "TXT"),
+            QueryType::PTR => write!(f, "PTR"),
+            QueryType::NS => write!(f, "NS"),
+            QueryType::SOA => write!(f, "SOA"),
+            QueryType::HINFO => write!(f, "HINFO"),
+            QueryType::SRV => write!(f, "SRV"),
+            QueryType::NAPTR => write!(f, "NAPTR"),
+            QueryType::OPT => write!(f, "OPT"),
+            QueryType::DS => write!(f, "DS"),
+            QueryType::SSHFP => write!(f, "SSHFP"),
+            QueryType::RRSIG => write!(f, "RRSIG"),
+            QueryType::NSEC => write!(f, "NSEC"),
+            QueryType::DNSKEY => write!(f, "DNSKEY"),
+            QueryType::NSEC3 => write!(f, "NSEC3"),
+            QueryType::SVCB => write!(f, "SVCB"),
+            QueryType::HTTPS => write!(f, "HTTPS"),
+            QueryType::TSIG => write!(f, "TSIG"),
+            QueryType::AXFR => write!(f, "AXFR"),
+            QueryType::ANY => write!(f, "ANY"),
+            QueryType::Unknown(code) => write!(f, "TYPE{}", code),
         }
     }
 }
 
-impl TryFrom<u16> for QueryType {
-    type Error = String;
-
+impl From<u16> for QueryType {
     /// Converts a numeric DNS record type code into a [`QueryType`].
     ///
     /// This is useful when parsing DNS packets where record types are represented
-    /// as numeric codes according to the DNS specification.
+    /// as numeric codes according to the DNS specification. Codes without a named
+    /// variant become [`QueryType::Unknown`].
     ///
     /// # Examples
     ///
     /// ```rust
     /// use dns_resolver::dns::QueryType;
     ///
-    /// assert_eq!(QueryType::try_from(1).unwrap(), QueryType::A);
-    /// assert_eq!(QueryType::try_from(28).unwrap(), QueryType::AAAA);
-    /// assert_eq!(QueryType::try_from(5).unwrap(), QueryType::CNAME);
+    /// assert_eq!(QueryType::from(1), QueryType::A);
+    /// assert_eq!(QueryType::from(28), QueryType::AAAA);
+    /// assert_eq!(QueryType::from(999), QueryType::Unknown(999));
     /// ```
+    fn from(value: u16) -> Self {
+        match value {
+            1 => QueryType::A,
+            28 => QueryType::AAAA,
+            5 => QueryType::CNAME,
+            15 => QueryType::MX,
+            16 => QueryType::TXT,
+            12 => QueryType::PTR,
+            2 => QueryType::NS,
+            6 => QueryType::SOA,
+            13 => QueryType::HINFO,
+            33 => QueryType::SRV,
+            35 => QueryType::NAPTR,
+            41 => QueryType::OPT,
+            43 => QueryType::DS,
+            44 => QueryType::SSHFP,
+            46 => QueryType::RRSIG,
+            47 => QueryType::NSEC,
+            48 => QueryType::DNSKEY,
+            50 => QueryType::NSEC3,
+            64 => QueryType::SVCB,
+            65 => QueryType::HTTPS,
+            250 => QueryType::TSIG,
+            252 => QueryType::AXFR,
+            255 => QueryType::ANY,
+            code => QueryType::Unknown(code),
+        }
+    }
+}
+
+impl From<QueryType> for u16 {
+    /// Converts a [`QueryType`] into its numeric code for the wire format.
     ///
-    /// # Errors
-    ///
-    /// Returns an error if the numeric code does not correspond to a supported DNS record type.
+    /// # Examples
     ///
     /// ```rust
     /// use dns_resolver::dns::QueryType;
     ///
-    /// let result = QueryType::try_from(999);
-    /// assert!(result.is_err());
+    /// assert_eq!(u16::from(QueryType::MX), 15);
+    /// assert_eq!(u16::from(QueryType::Unknown(65280)), 65280);
     /// ```
-    fn try_from(value: u16) -> Result<Self, Self::Error> {
+    fn from(query_type: QueryType) -> Self {
+        match query_type {
+            QueryType::A => 1,
+            QueryType::AAAA => 28,
+            QueryType::CNAME => 5,
+            QueryType::MX => 15,
+            QueryType::TXT => 16,
+            QueryType::PTR => 12,
+            QueryType::NS => 2,
+            QueryType::SOA => 6,
+            QueryType::HINFO => 13,
+            QueryType::SRV => 33,
+            QueryType::NAPTR => 35,
+            QueryType::OPT => 41,
+            QueryType::DS => 43,
+            QueryType::SSHFP => 44,
+            QueryType::RRSIG => 46,
+            QueryType::NSEC => 47,
+            QueryType::DNSKEY => 48,
+            QueryType::NSEC3 => 50,
+            QueryType::SVCB => 64,
+            QueryType::HTTPS => 65,
+            QueryType::TSIG => 250,
+            QueryType::AXFR => 252,
+            QueryType::ANY => 255,
+            QueryType::Unknown(code) => code,
+        }
+    }
+}
+
+/// Represents the class of a DNS query or record (RFC 1035 section 3.2.4).
+///
+/// Nearly all DNS data is in the Internet class, but the Chaos class is still used
+/// for server identification queries such as `version.bind`, and the query-only
+/// classes NONE and ANY appear in dynamic updates. Any other class is carried in
+/// [`DnsClass::Unknown`] and written `CLASS<number>` as in RFC 3597.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::DnsClass;
+/// use std::str::FromStr;
+///
+/// assert_eq!(DnsClass::from_str("ch").unwrap(), DnsClass::CH);
+/// assert_eq!(DnsClass::from_str("CLASS3").unwrap(), DnsClass::CH);
+/// assert_eq!(DnsClass::from_str("42").unwrap(), DnsClass::Unknown(42));
+/// assert_eq!(DnsClass::from(42).to_string(), "CLASS42");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[allow(clippy::upper_case_acronyms)] // Class mnemonics as spelled in the RFCs
+pub enum DnsClass {
+    /// The Internet class, code 1.
+    #[default]
+    IN,
+    /// The Chaos class, code 3.
+    CH,
+    /// The Hesiod class, code 4.
+    HS,
+    /// The query class NONE, code 254 (RFC 2136).
+    NONE,
+    /// The query class ANY (`*`), code 255.
+    ANY,
+    /// Any other class, by its numeric code (RFC 3597).
+    Unknown(u16),
+}
+
+impl FromStr for DnsClass {
+    type Err = String;
+
+    /// Parses a class mnemonic, `CLASS<number>`, or a bare number.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the input is none of those, or the number is out of range.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.to_uppercase();
+        match upper.as_str() {
+            "IN" => Ok(DnsClass::IN),
+            "CH" => Ok(DnsClass::CH),
+            "HS" => Ok(DnsClass::HS),
+            "NONE" => Ok(DnsClass::NONE),
+            "ANY" => Ok(DnsClass::ANY),
+            _ => upper
+                .strip_prefix("CLASS")
+                .unwrap_or(&upper)
+                .parse::<u16>()
+                .map(DnsClass::from)
+                .map_err(|_| format!("Unknown class: {}", s)),
+        }
+    }
+}
+
+impl fmt::Display for DnsClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DnsClass::IN => write!(f, "IN"),
+            DnsClass::CH => write!(f, "CH"),
+            DnsClass::HS => write!(f, "HS"),
+            DnsClass::NONE => write!(f, "NONE"),
+            DnsClass::ANY => write!(f, "ANY"),
+            DnsClass::Unknown(code) => write!(f, "CLASS{}", code),
+        }
+    }
+}
+
+impl From<u16> for DnsClass {
+    fn from(value: u16) -> Self {
         match value {
-            1 => Ok(QueryType::A),
-            28 => Ok(QueryType::AAAA),
-            5 => Ok(QueryType::CNAME),
-            15 => Ok(QueryType::MX),
-            16 => Ok(QueryType::TXT),
-            _ => Err(format!("Unknown query type code: {}", value)),
+            1 => DnsClass::IN,
+            3 => DnsClass::CH,
+            4 => DnsClass::HS,
+            254 => DnsClass::NONE,
+            255 => DnsClass::ANY,
+            code => DnsClass::Unknown(code),
+        }
+    }
+}
+
+impl From<DnsClass> for u16 {
+    fn from(class: DnsClass) -> Self {
+        match class {
+            DnsClass::IN => 1,
+            DnsClass::CH => 3,
+            DnsClass::HS => 4,
+            DnsClass::NONE => 254,
+            DnsClass::ANY => 255,
+            DnsClass::Unknown(code) => code,
         }
     }
 }
@@ -219,7 +493,7 @@ impl TryFrom<u16> for QueryType {
 /// header.pack(&mut buffer);
 /// assert_eq!(buffer.len(), 12); // DNS header is always 12 bytes
 /// ```
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct DnsHeader {
     /// Packet identifier used to match queries with responses.
     ///
@@ -322,6 +596,94 @@ pub enum ResponseCode {
     Refused = 5,
 }
 
+impl fmt::Display for ResponseCode {
+    /// Formats the response code by its mnemonic, as dig prints it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ResponseCode::NoError => "NOERROR",
+            ResponseCode::FormatError => "FORMERR",
+            ResponseCode::ServerFailure => "SERVFAIL",
+            ResponseCode::NameError => "NXDOMAIN",
+            ResponseCode::NotImplemented => "NOTIMP",
+            ResponseCode::Refused => "REFUSED",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Represents the 4-bit operation code (OPCODE) field in the DNS header.
+///
+/// The opcode says what kind of request a message is, and is copied into the
+/// response. It's stored in bits 11 to 14 of the flags field, right after the QR bit.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{DnsHeader, Opcode};
+///
+/// let mut header = DnsHeader::new();
+/// header.set_opcode(Opcode::Notify);
+/// assert_eq!(header.flags, 0x2000);
+/// assert_eq!(header.opcode(), Opcode::Notify);
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Opcode {
+    /// A standard query (OPCODE = 0, RFC 1035).
+    Query,
+    /// A server status request (OPCODE = 2, RFC 1035).
+    Status,
+    /// A zone change notification from a primary to its secondaries
+    /// (OPCODE = 4, RFC 1996).
+    Notify,
+    /// A dynamic update (OPCODE = 5, RFC 2136).
+    Update,
+    /// Any other opcode, including the obsolete inverse query (OPCODE = 1).
+    Other(u8),
+}
+
+impl From<u8> for Opcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Opcode::Query,
+            2 => Opcode::Status,
+            4 => Opcode::Notify,
+            5 => Opcode::Update,
+            other => Opcode::Other(other),
+        }
+    }
+}
+
+impl From<Opcode> for u8 {
+    fn from(opcode: Opcode) -> Self {
+        match opcode {
+            Opcode::Query => 0,
+            Opcode::Status => 2,
+            Opcode::Notify => 4,
+            Opcode::Update => 5,
+            Opcode::Other(value) => value,
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    /// Formats the opcode by its mnemonic, as dig prints it.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opcode::Query => write!(f, "QUERY"),
+            Opcode::Status => write!(f, "STATUS"),
+            Opcode::Notify => write!(f, "NOTIFY"),
+            Opcode::Update => write!(f, "UPDATE"),
+            Opcode::Other(value) => write!(f, "OPCODE{}", value),
+        }
+    }
+}
+
+impl Default for DnsHeader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DnsHeader {
     /// Creates a new `DnsHeader` with all fields initialized to zero.
     ///
@@ -490,171 +852,635 @@ impl DnsHeader {
             _ => ResponseCode::ServerFailure, // Default to ServerFailure for unknown codes
         }
     }
-}
-
-/// Represents a DNS question section entry in a DNS message.
-///
-/// The question section of a DNS message contains queries that specify what
-/// the client is asking the DNS server. Each question consists of a domain name,
-/// query type, and query class according to RFC 1035.
-///
-/// # Examples
-///
-/// ```rust
-/// use dns_resolver::dns::{DnsQuestion, QueryType};
-///
-/// let question = DnsQuestion {
-///     name: "www.example.com".to_string(),
-///     qtype: QueryType::A,
-///     qclass: 1, // IN (Internet) class
-/// };
-/// ```
-#[derive(Debug, Clone)]
-pub struct DnsQuestion {
-    /// The domain name being queried (e.g., "www.example.com").
-    pub name: String,
-    /// The type of DNS record being requested (A, AAAA, CNAME, etc.).
-    pub qtype: QueryType,
-    /// The query class, typically 1 for Internet (IN) class.
-    pub qclass: u16,
-}
 
-impl DnsQuestion {
-    /// Serializes the DNS question into bytes and appends them to the provided buffer.
+    /// Returns the [`Opcode`] from the header flags.
     ///
-    /// The question is packed according to DNS wire format: domain name in label format,
-    /// followed by query type and query class in network byte order.
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{DnsHeader, Opcode};
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0xA400; // NOTIFY response with AA set
+    /// assert_eq!(header.opcode(), Opcode::Notify);
+    /// ```
+    pub fn opcode(&self) -> Opcode {
+        Opcode::from(((self.flags >> 11) & 0x000F) as u8)
+    }
+
+    /// Sets the opcode bits of the header flags, leaving the other flags as they are.
     ///
     /// # Arguments
     ///
-    /// * `buffer` - A mutable reference to a `Vec<u8>` where the serialized question will be appended
+    /// * `opcode` - The opcode to set; only its lower 4 bits are used
+    pub fn set_opcode(&mut self, opcode: Opcode) {
+        self.flags = (self.flags & !0x7800) | ((u8::from(opcode) as u16 & 0x000F) << 11);
+    }
+
+    /// Returns whether the Recursion Desired (RD) bit is set.
     ///
-    /// # Returns
+    /// Clients set RD to ask the server to resolve the query on their behalf
+    /// rather than returning a referral.
     ///
-    /// * `Ok(())` - Successfully serialized the question
-    /// * `Err(String)` - If domain name encoding fails (e.g., label too long)
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsHeader;
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x0100; // Standard query with RD set
+    /// assert!(header.recursion_desired());
+    /// ```
+    pub fn recursion_desired(&self) -> bool {
+        self.flags & 0x0100 != 0
+    }
+
+    /// Returns whether the Truncation (TC) bit is set.
+    ///
+    /// A server sets TC when the full response does not fit in a UDP datagram, and
+    /// the client is expected to repeat the query over TCP to get all of it.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use dns_resolver::dns::{DnsQuestion, QueryType};
+    /// use dns_resolver::dns::DnsHeader;
     ///
-    /// let question = DnsQuestion {
-    ///     name: "example.com".to_string(),
-    ///     qtype: QueryType::A,
-    ///     qclass: 1,
-    /// };
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x8380; // Response with TC, RD and RA set
+    /// assert!(header.truncated());
+    /// ```
+    pub fn truncated(&self) -> bool {
+        self.flags & 0x0200 != 0
+    }
+
+    /// Returns whether the Recursion Available (RA) bit is set.
     ///
-    /// let mut buffer = Vec::new();
-    /// question.pack(&mut buffer).unwrap();
-    /// // Buffer now contains the packed question
+    /// Servers set RA in responses when they offer recursive resolution. A response
+    /// to a recursive query with RA clear usually comes from an authoritative-only
+    /// server and may contain a referral instead of an answer.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsHeader;
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x8180; // Response with RD and RA set
+    /// assert!(header.recursion_available());
+    ///
+    /// header.flags = 0x8500; // Authoritative response with RD set, RA clear
+    /// assert!(!header.recursion_available());
     /// ```
-    pub fn pack(&self, buffer: &mut Vec<u8>) -> Result<(), String> {
-        pack_domain_name(buffer, &self.name)?;
-        buffer.extend_from_slice(&(self.qtype as u16).to_be_bytes());
-        buffer.extend_from_slice(&self.qclass.to_be_bytes());
-        Ok(())
+    pub fn recursion_available(&self) -> bool {
+        self.flags & 0x0080 != 0
     }
 
-    /// Deserializes a DNS question from a byte cursor.
+    /// Returns whether the Authentic Data (AD) bit is set (RFC 4035).
     ///
-    /// Reads a DNS question from the cursor in wire format: domain name (with potential
-    /// compression), followed by query type and query class in network byte order.
+    /// A validating resolver sets AD in a response when it has verified every record
+    /// in the answer and authority sections with DNSSEC. Clients can set it in a
+    /// query to signal that they understand the bit (RFC 6840 section 5.7).
     ///
-    /// # Arguments
+    /// # Examples
     ///
-    /// * `cursor` - A mutable reference to a `Cursor<&[u8]>` positioned at the start of the question
+    /// ```rust
+    /// use dns_resolver::dns::DnsHeader;
     ///
-    /// # Returns
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x81A0; // Response with RD, RA and AD set
+    /// assert!(header.authentic_data());
+    /// ```
+    pub fn authentic_data(&self) -> bool {
+        self.flags & 0x0020 != 0
+    }
+
+    /// Returns whether the Checking Disabled (CD) bit is set (RFC 4035).
     ///
-    /// * `Ok(DnsQuestion)` - Successfully parsed DNS question
-    /// * `Err(std::io::Error)` - If parsing fails due to insufficient data or invalid format
+    /// A query with CD set asks a validating resolver to return data even if it
+    /// fails DNSSEC validation, leaving the checking to the client.
     ///
     /// # Examples
     ///
     /// ```rust
-    /// use dns_resolver::dns::DnsQuestion;
-    /// use std::io::Cursor;
+    /// use dns_resolver::dns::DnsHeader;
     ///
-    /// let data = [
-    ///     7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', // "example"
-    ///     3, b'c', b'o', b'm', // "com"
-    ///     0, // null terminator
-    ///     0, 1, // Type A
-    ///     0, 1, // Class IN
-    /// ];
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x0110; // Query with RD and CD set
+    /// assert!(header.checking_disabled());
+    /// ```
+    pub fn checking_disabled(&self) -> bool {
+        self.flags & 0x0010 != 0
+    }
+
+    /// Returns whether the Query/Response (QR) bit is set, marking the message as a
+    /// response rather than a query.
     ///
-    /// let mut cursor = Cursor::new(&data[..]);
-    /// let question = DnsQuestion::from_bytes(&mut cursor).unwrap();
-    /// assert_eq!(question.name, "example.com");
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsHeader;
+    ///
+    /// let mut header = DnsHeader::new();
+    /// assert!(!header.is_response());
+    /// header.set_response(true);
+    /// assert_eq!(header.flags, 0x8000);
     /// ```
+    pub fn is_response(&self) -> bool {
+        self.flags & 0x8000 != 0
+    }
+
+    /// Returns whether the Authoritative Answer (AA) bit is set.
     ///
-    /// # Errors
+    /// A server sets AA when it answers from a zone it is authoritative for, rather
+    /// than from its cache. Primaries also set it in NOTIFY requests (RFC 1996).
     ///
-    /// This function will return an error if:
-    /// - The cursor doesn't contain enough data to read a complete question
-    /// - The domain name format is invalid or contains compression pointer errors
-    /// - The query type is not recognized
-    pub fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
-        let name = unpack_domain_name(cursor)?;
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsHeader;
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.flags = 0x8500; // Authoritative response with RD set
+    /// assert!(header.authoritative());
+    /// ```
+    pub fn authoritative(&self) -> bool {
+        self.flags & 0x0400 != 0
+    }
 
-        let mut buf = [0u8; 2];
-        cursor.read_exact(&mut buf)?;
-        let qtype_val = u16::from_be_bytes(buf);
-        let qtype = QueryType::try_from(qtype_val)
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    /// Returns whether the reserved Z bit is set. It must be zero in queries and
+    /// responses alike (RFC 1035 section 4.1.1).
+    pub fn z(&self) -> bool {
+        self.flags & 0x0040 != 0
+    }
 
-        cursor.read_exact(&mut buf)?;
-        let qclass = u16::from_be_bytes(buf);
+    /// Sets or clears the Query/Response (QR) bit.
+    pub fn set_response(&mut self, value: bool) {
+        self.set_flag(0x8000, value);
+    }
 
-        Ok(DnsQuestion {
-            name,
-            qtype,
-            qclass,
-        })
+    /// Sets or clears the Authoritative Answer (AA) bit.
+    pub fn set_authoritative(&mut self, value: bool) {
+        self.set_flag(0x0400, value);
     }
-}
 
-/// Represents MX record data.
-///
-/// Contains the preference value and exchange server hostname from an MX record.
-#[derive(Debug, Clone)]
-#[allow(dead_code)] // This is part of the public API
-pub struct MxData {
-    /// Priority/preference value - lower numbers have higher priority.
-    pub preference: u16,
-    /// The hostname of the mail server.
-    pub exchange: String,
-}
+    /// Sets or clears the Truncation (TC) bit.
+    pub fn set_truncated(&mut self, value: bool) {
+        self.set_flag(0x0200, value);
+    }
 
-/// Represents a DNS resource record in the answer, authority, or additional sections.
-///
-/// Resource records contain the actual data returned by DNS servers in response to queries.
-/// Each record includes the domain name, record type, class, time-to-live, and the actual data.
-/// This structure follows the DNS wire format as defined in RFC 1035.
-///
-/// # Examples
-///
-/// ```rust
-/// use dns_resolver::dns::{ResourceRecord, QueryType, RData};
-/// use std::net::Ipv4Addr;
-///
-/// let record = ResourceRecord {
-///     name: "example.com".to_string(),
-///     rtype: QueryType::A,
-///     rclass: 1, // IN class
-///     ttl: 300,  // 5 minutes
-///     data: RData::A(Ipv4Addr::new(93, 184, 216, 34)),
-/// };
-/// ```
+    /// Sets or clears the Recursion Desired (RD) bit.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use dns_resolver::dns::DnsHeader;
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.set_recursion_desired(true);
+    /// assert_eq!(header.flags, 0x0100); // Standard query (RD=1)
+    /// ```
+    pub fn set_recursion_desired(&mut self, value: bool) {
+        self.set_flag(0x0100, value);
+    }
+
+    /// Sets or clears the Recursion Available (RA) bit.
+    pub fn set_recursion_available(&mut self, value: bool) {
+        self.set_flag(0x0080, value);
+    }
+
+    /// Sets or clears the reserved Z bit. Only useful to test how peers react to it.
+    pub fn set_z(&mut self, value: bool) {
+        self.set_flag(0x0040, value);
+    }
+
+    /// Sets or clears the Authentic Data (AD) bit.
+    pub fn set_authentic_data(&mut self, value: bool) {
+        self.set_flag(0x0020, value);
+    }
+
+    /// Sets or clears the Checking Disabled (CD) bit.
+    pub fn set_checking_disabled(&mut self, value: bool) {
+        self.set_flag(0x0010, value);
+    }
+
+    /// Sets the response code bits of the header flags, leaving the other flags as
+    /// they are.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{DnsHeader, ResponseCode};
+    ///
+    /// let mut header = DnsHeader::new();
+    /// header.set_response(true);
+    /// header.set_response_code(ResponseCode::NameError);
+    /// assert_eq!(header.flags, 0x8003);
+    /// ```
+    pub fn set_response_code(&mut self, code: ResponseCode) {
+        self.flags = (self.flags & !0x000F) | code as u16;
+    }
+
+    /// Sets the bits of `mask` in the flags when `value` is true, or clears them.
+    fn set_flag(&mut self, mask: u16, value: bool) {
+        if value {
+            self.flags |= mask;
+        } else {
+            self.flags &= !mask;
+        }
+    }
+}
+
+impl fmt::Display for DnsHeader {
+    /// Formats the header with its flags decoded, in the style of dig:
+    ///
+    /// ```text
+    /// id 48211, opcode QUERY, status NOERROR, flags: qr rd ra; QUERY: 1, ANSWER: 2, AUTHORITY: 0, ADDITIONAL: 0
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [
+            (self.is_response(), "qr"),
+            (self.authoritative(), "aa"),
+            (self.truncated(), "tc"),
+            (self.recursion_desired(), "rd"),
+            (self.recursion_available(), "ra"),
+            (self.z(), "z"),
+            (self.authentic_data(), "ad"),
+            (self.checking_disabled(), "cd"),
+        ];
+        let set: Vec<&str> = flags
+            .iter()
+            .filter_map(|&(on, name)| on.then_some(name))
+            .collect();
+        write!(
+            f,
+            "id {}, opcode {}, status {}, flags: {}; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.id,
+            self.opcode(),
+            self.get_response_code(),
+            set.join(" "),
+            self.question_count,
+            self.answer_count,
+            self.authority_count,
+            self.additional_count
+        )
+    }
+}
+
+/// The longest name that can be encoded, in octets on the wire (RFC 1035,
+/// section 2.3.4).
+pub const MAX_NAME_LENGTH: usize = 255;
+
+/// The longest label that can be encoded, in octets (RFC 1035, section 2.3.4).
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// A domain name, such as the owner of a record or the name a question asks about.
+///
+/// A `DnsName` can always be encoded: [`DnsName::new`] refuses names with empty
+/// labels, labels longer than [`MAX_LABEL_LENGTH`] octets, or more than
+/// [`MAX_NAME_LENGTH`] octets in all, counting Unicode labels by the A-labels they
+/// are sent as (see [`crate::idn`]). The name keeps the spelling it was given, for
+/// display, but compares and hashes as DNS compares names: ignoring ASCII case and
+/// a trailing dot. It dereferences to the name as a string, so it can be passed
+/// wherever a `&str` is expected.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::DnsName;
+///
+/// let name: DnsName = "WWW.Example.com.".parse()?;
+/// assert_eq!(name, "www.example.com");
+/// assert_eq!(name.to_string(), "WWW.Example.com.");
+/// assert_eq!(name.labels().collect::<Vec<_>>(), ["WWW", "Example", "com"]);
+/// assert!(name.is_subdomain_of(&"example.COM".parse()?));
+///
+/// assert!(DnsName::new("a..example").is_err());
+/// assert!(DnsName::new(format!("{}.example", "a".repeat(64))).is_err());
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct DnsName(String);
+
+impl DnsName {
+    /// Checks that `name` can be encoded and wraps it. The root name is written
+    /// as `""` or `"."`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a label is empty, too long or an invalid Unicode label,
+    /// or if the whole name is longer than [`MAX_NAME_LENGTH`] octets on the wire.
+    pub fn new(name: impl Into<String>) -> Result<Self, String> {
+        let name = name.into();
+        let length = wire_length(&name)?;
+        if length > MAX_NAME_LENGTH {
+            return Err(format!(
+                "Domain name '{}' is {} octets long, more than the maximum of {}",
+                name, length, MAX_NAME_LENGTH
+            ));
+        }
+        Ok(DnsName(name))
+    }
+
+    /// Wraps a name without checking it, for names read from the wire, which fit in
+    /// a message by construction, and names derived from them. Packing still
+    /// rejects names that cannot be encoded.
+    pub(crate) fn new_unchecked(name: String) -> Self {
+        DnsName(name)
+    }
+
+    /// Returns the root name, written as `""`.
+    pub fn root() -> Self {
+        DnsName(String::new())
+    }
+
+    /// Checks whether this is the root name.
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    /// Returns the name as it was given.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns the labels of the name from left to right, without the empty label
+    /// of the root.
+    pub fn labels(&self) -> impl DoubleEndedIterator<Item = &str> {
+        let name = self.0.strip_suffix('.').unwrap_or(&self.0);
+        name.split('.').filter(|label| !label.is_empty())
+    }
+
+    /// Returns the number of octets the name takes on the wire, uncompressed.
+    pub fn wire_length(&self) -> usize {
+        wire_length(&self.0).unwrap_or(self.0.len() + 2)
+    }
+
+    /// Returns the name in canonical form: lowercase, with no trailing dot (see
+    /// [`canonical_name`]).
+    pub fn to_canonical(&self) -> DnsName {
+        DnsName(canonical_name(&self.0))
+    }
+
+    /// Checks whether the name equals `zone` or lies below it, comparing whole
+    /// labels without regard to ASCII case.
+    pub fn is_subdomain_of(&self, zone: &DnsName) -> bool {
+        let mut labels = self.labels().rev();
+        zone.labels().rev().all(|zone_label| {
+            labels
+                .next()
+                .is_some_and(|label| label.eq_ignore_ascii_case(zone_label))
+        })
+    }
+}
+
+/// Returns the number of octets `name` takes on the wire, uncompressed.
+fn wire_length(name: &str) -> Result<usize, String> {
+    Ok(name_labels(name)?
+        .iter()
+        .map(|label| label.len() + 1)
+        .sum::<usize>()
+        + 1)
+}
+
+impl FromStr for DnsName {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DnsName::new(s)
+    }
+}
+
+impl fmt::Display for DnsName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::ops::Deref for DnsName {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for DnsName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<DnsName> for String {
+    fn from(name: DnsName) -> String {
+        name.0
+    }
+}
+
+impl PartialEq for DnsName {
+    fn eq(&self, other: &DnsName) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl Eq for DnsName {}
+
+impl PartialEq<str> for DnsName {
+    fn eq(&self, other: &str) -> bool {
+        let other = other.strip_suffix('.').unwrap_or(other);
+        self.0
+            .strip_suffix('.')
+            .unwrap_or(&self.0)
+            .eq_ignore_ascii_case(other)
+    }
+}
+
+impl PartialEq<&str> for DnsName {
+    fn eq(&self, other: &&str) -> bool {
+        self == *other
+    }
+}
+
+impl PartialEq<String> for DnsName {
+    fn eq(&self, other: &String) -> bool {
+        self == other.as_str()
+    }
+}
+
+impl std::hash::Hash for DnsName {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        canonical_name(&self.0).hash(state);
+    }
+}
+
+/// Represents a DNS question section entry in a DNS message.
+///
+/// The question section of a DNS message contains queries that specify what
+/// the client is asking the DNS server. Each question consists of a domain name,
+/// query type, and query class according to RFC 1035.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{DnsQuestion, QueryType};
+///
+/// let question = DnsQuestion {
+///     name: "www.example.com".parse().unwrap(),
+///     qtype: QueryType::A,
+///     qclass: 1, // IN (Internet) class
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuestion {
+    /// The domain name being queried (e.g., "www.example.com").
+    pub name: DnsName,
+    /// The type of DNS record being requested (A, AAAA, CNAME, etc.).
+    pub qtype: QueryType,
+    /// The query class, typically 1 for Internet (IN) class.
+    pub qclass: u16,
+}
+
+impl DnsQuestion {
+    /// Serializes the DNS question into bytes and appends them to the provided buffer.
+    ///
+    /// The question is packed according to DNS wire format: domain name in label format,
+    /// followed by query type and query class in network byte order.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - A mutable reference to a `Vec<u8>` where the serialized question will be appended
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Successfully serialized the question
+    /// * `Err(String)` - If domain name encoding fails (e.g., label too long)
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{DnsQuestion, QueryType};
+    ///
+    /// let question = DnsQuestion {
+    ///     name: "example.com".parse().unwrap(),
+    ///     qtype: QueryType::A,
+    ///     qclass: 1,
+    /// };
+    ///
+    /// let mut buffer = Vec::new();
+    /// question.pack(&mut buffer).unwrap();
+    /// // Buffer now contains the packed question
+    /// ```
+    pub fn pack(&self, buffer: &mut Vec<u8>) -> Result<(), String> {
+        self.pack_with(buffer, &mut None)
+    }
+
+    /// Serializes the question, compressing its name against earlier names in the
+    /// message when a compressor is given.
+    fn pack_with(
+        &self,
+        buffer: &mut Vec<u8>,
+        compressor: &mut Option<NameCompressor>,
+    ) -> Result<(), String> {
+        pack_name(buffer, &self.name, compressor)?;
+        buffer.extend_from_slice(&u16::from(self.qtype).to_be_bytes());
+        buffer.extend_from_slice(&self.qclass.to_be_bytes());
+        Ok(())
+    }
+
+    /// Deserializes a DNS question from a byte cursor.
+    ///
+    /// Reads a DNS question from the cursor in wire format: domain name (with potential
+    /// compression), followed by query type and query class in network byte order.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - A mutable reference to a `Cursor<&[u8]>` positioned at the start of the question
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(DnsQuestion)` - Successfully parsed DNS question
+    /// * `Err(std::io::Error)` - If parsing fails due to insufficient data or invalid format
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsQuestion;
+    /// use std::io::Cursor;
+    ///
+    /// let data = [
+    ///     7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', // "example"
+    ///     3, b'c', b'o', b'm', // "com"
+    ///     0, // null terminator
+    ///     0, 1, // Type A
+    ///     0, 1, // Class IN
+    /// ];
+    ///
+    /// let mut cursor = Cursor::new(&data[..]);
+    /// let question = DnsQuestion::from_bytes(&mut cursor).unwrap();
+    /// assert_eq!(question.name, "example.com");
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if:
+    /// - The cursor doesn't contain enough data to read a complete question
+    /// - The domain name format is invalid or contains compression pointer errors
+    pub fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let name = unpack_domain_name(cursor)?;
+
+        let mut buf = [0u8; 2];
+        cursor.read_exact(&mut buf)?;
+        let qtype_val = u16::from_be_bytes(buf);
+        let qtype = QueryType::from(qtype_val);
+
+        cursor.read_exact(&mut buf)?;
+        let qclass = u16::from_be_bytes(buf);
+
+        Ok(DnsQuestion {
+            name: DnsName::new_unchecked(name),
+            qtype,
+            qclass,
+        })
+    }
+}
+
+/// Represents MX record data.
+///
+/// Contains the preference value and exchange server hostname from an MX record.
 #[derive(Debug, Clone)]
+pub struct MxData {
+    /// Priority/preference value - lower numbers have higher priority.
+    pub preference: u16,
+    /// The hostname of the mail server.
+    pub exchange: String,
+}
+
+/// Represents a DNS resource record in the answer, authority, or additional sections.
+///
+/// Resource records contain the actual data returned by DNS servers in response to queries.
+/// Each record includes the domain name, record type, class, time-to-live, and the actual data.
+/// This structure follows the DNS wire format as defined in RFC 1035.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{ResourceRecord, QueryType, RData};
+/// use std::net::Ipv4Addr;
+///
+/// let record = ResourceRecord {
+///     name: "example.com".parse().unwrap(),
+///     rtype: QueryType::A,
+///     rclass: 1, // IN class
+///     ttl: 300,  // 5 minutes
+///     data: RData::A(Ipv4Addr::new(93, 184, 216, 34)),
+/// };
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ResourceRecord {
     /// The domain name this record refers to (e.g., "www.example.com").
-    pub name: String,
+    pub name: DnsName,
     /// The type of this resource record (A, AAAA, CNAME, etc.).
     pub rtype: QueryType,
     /// The record class, typically 1 for Internet (IN) class.
-    #[allow(dead_code)] // Used by get_class() method
     pub rclass: u16,
     /// Time-to-live in seconds - how long this record can be cached.
     pub ttl: u32,
@@ -686,7 +1512,8 @@ pub struct ResourceRecord {
 /// // Text record
 /// let txt_record = RData::TXT("v=spf1 include:_spf.google.com ~all".to_string());
 /// ```
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)] // Record type mnemonics as spelled in the RFCs
 pub enum RData {
     /// IPv4 address record data (A record).
     A(Ipv4Addr),
@@ -703,12 +1530,207 @@ pub enum RData {
     },
     /// Text record data (TXT record) containing arbitrary text.
     TXT(String),
+    /// Domain name pointer data (PTR record) - the name an address maps back to.
+    PTR(String),
+    /// Name server data (NS record) - the host name of an authoritative server.
+    NS(String),
+    /// Start of authority data (SOA record) - the parameters of a zone. The SOA
+    /// record of the zone also appears in the authority section of negative
+    /// responses, where its TTL and `minimum` bound negative caching (RFC 2308).
+    SOA {
+        /// The host name of the zone's primary name server.
+        mname: String,
+        /// The mailbox of the person responsible for the zone, with the first `.`
+        /// standing for `@` (so `hostmaster.example.com` is `hostmaster@example.com`).
+        rname: String,
+        /// The version number of the zone, which secondaries compare to decide
+        /// whether to transfer it.
+        serial: u32,
+        /// How often secondaries check the serial, in seconds.
+        refresh: u32,
+        /// How long secondaries wait before retrying a failed refresh, in seconds.
+        retry: u32,
+        /// How long secondaries keep serving the zone without a successful
+        /// refresh, in seconds.
+        expire: u32,
+        /// The TTL of negative answers from the zone, in seconds (RFC 2308).
+        minimum: u32,
+    },
+    /// Host information data (HINFO record). Rarely published any more, but a server
+    /// that refuses ANY queries answers with one whose `cpu` is `RFC8482`.
+    HINFO {
+        /// The host's hardware, e.g. `INTEL-386`.
+        cpu: String,
+        /// The host's operating system, e.g. `UNIX`.
+        os: String,
+    },
+    /// Service locator data (SRV record), owned by a name like
+    /// `_ldap._tcp.example.com`.
+    SRV {
+        /// Clients use the targets with the lowest priority first.
+        priority: u16,
+        /// Among targets of equal priority, the relative share of clients each
+        /// should get.
+        weight: u16,
+        /// The port the service listens on.
+        port: u16,
+        /// The host name of the server; `.` means the service is not available.
+        target: String,
+    },
+    /// Naming authority pointer data (NAPTR record), one rule for rewriting a name
+    /// such as an ENUM telephone number into a URI or another name.
+    NAPTR {
+        /// Rules are applied in increasing order.
+        order: u16,
+        /// Among rules of equal order, the ones with lower preference are tried first.
+        preference: u16,
+        /// How to continue after this rule: `U` for a terminal URI, `S` for an SRV
+        /// lookup, `A` for an address lookup, or empty to apply the rules of the
+        /// replacement name.
+        flags: String,
+        /// The service and protocol the rule leads to, e.g. `E2U+sip`.
+        services: String,
+        /// A substitution expression applied to the original name, e.g.
+        /// `!^.*$!sip:info@example.com!`.
+        regexp: String,
+        /// The next name to look up, used when `regexp` is empty; the root otherwise.
+        replacement: String,
+    },
+    /// EDNS(0) options (OPT pseudo-record). The rest of the EDNS parameters live in
+    /// the record's class and TTL fields; see [`Edns`].
+    OPT(Vec<EdnsOption>),
+    /// Delegation signer data (DS record), published by a parent zone to vouch for a
+    /// key of the child zone and so extend the chain of trust to it.
+    DS {
+        /// The key tag of the DNSKEY record the digest is of.
+        key_tag: u16,
+        /// The algorithm of that key, numbered as for [`RData::DNSKEY`].
+        algorithm: u8,
+        /// The digest algorithm: 1 for SHA-1, 2 for SHA-256 and 4 for SHA-384.
+        digest_type: u8,
+        /// The digest of the key's owner name and data.
+        digest: Vec<u8>,
+    },
+    /// DNSSEC public key data (DNSKEY record).
+    DNSKEY {
+        /// Key flags: 256 marks a zone key and 1 a secure entry point, so key-signing
+        /// keys usually have flags 257 and zone-signing keys 256. 128 marks a
+        /// revoked key (RFC 5011).
+        flags: u16,
+        /// Always 3.
+        protocol: u8,
+        /// The signing algorithm, e.g. 8 for RSA/SHA-256, 13 for ECDSA P-256 with
+        /// SHA-256 and 15 for Ed25519.
+        algorithm: u8,
+        /// The public key, in the algorithm's own format.
+        public_key: Vec<u8>,
+    },
+    /// DNSSEC signature data (RRSIG record), signing the RRset of one type at the
+    /// owner name.
+    RRSIG {
+        /// The type of the RRset the signature covers.
+        type_covered: QueryType,
+        /// The algorithm of the signing key, numbered as for [`RData::DNSKEY`].
+        algorithm: u8,
+        /// The number of labels in the signed owner name, not counting the root or a
+        /// leading `*` wildcard label.
+        labels: u8,
+        /// The TTL of the RRset as signed.
+        original_ttl: u32,
+        /// When the signature stops being valid, in seconds since the epoch (modulo
+        /// 2^32, RFC 4034 section 3.1.5).
+        expiration: u32,
+        /// When the signature starts being valid, in seconds since the epoch (modulo
+        /// 2^32).
+        inception: u32,
+        /// The key tag of the DNSKEY record that made the signature.
+        key_tag: u16,
+        /// The zone the signing key belongs to.
+        signer_name: String,
+        /// The signature, in the algorithm's own format.
+        signature: Vec<u8>,
+    },
+    /// Next secure data (NSEC record): the next name in the zone in canonical order,
+    /// proving no name exists in between, and the types that exist at the owner name.
+    NSEC {
+        /// The next owner name in the zone, or the zone's apex for its last name.
+        next_domain: String,
+        /// The types present at the owner name.
+        types: Vec<QueryType>,
+    },
+    /// Hashed next secure data (NSEC3 record), like [`RData::NSEC`] but chaining the
+    /// hashes of the names so the zone's contents cannot be walked.
+    NSEC3 {
+        /// The hash algorithm: 1 for SHA-1.
+        hash_algorithm: u8,
+        /// Flag 1 is opt-out: unsigned delegations may lie between this name and
+        /// the next.
+        flags: u8,
+        /// How many additional times the name is hashed.
+        iterations: u16,
+        /// The salt appended to the name before each hash.
+        salt: Vec<u8>,
+        /// The hash of the next owner name in hash order.
+        next_hashed_owner: Vec<u8>,
+        /// The types present at the original owner name.
+        types: Vec<QueryType>,
+    },
+    /// SSH host key fingerprint data (SSHFP record), which lets an SSH client check
+    /// a server's host key against DNS instead of asking the user.
+    SSHFP {
+        /// The host key algorithm: 1 for RSA, 2 for DSA, 3 for ECDSA, 4 for Ed25519
+        /// and 6 for Ed448.
+        algorithm: u8,
+        /// The hash the fingerprint was made with: 1 for SHA-1 and 2 for SHA-256.
+        fingerprint_type: u8,
+        /// The hash of the host's public key.
+        fingerprint: Vec<u8>,
+    },
+    /// Service binding data (SVCB record): where and how to reach a service, owned
+    /// by a name like `_dns.example.com` or `_8443._foo.example.com`.
+    SVCB {
+        /// 0 for an alias to `target`; otherwise clients try the bindings with the
+        /// lowest priority first.
+        priority: u16,
+        /// The host name offering the service; `.` means the owner name itself.
+        target: String,
+        /// The parameters of the service, in increasing key order.
+        params: Vec<SvcParam>,
+    },
+    /// Service binding data for an HTTPS origin (HTTPS record), laid out like
+    /// [`RData::SVCB`] and owned by the origin's host name itself.
+    HTTPS {
+        /// 0 for an alias to `target`; otherwise clients try the bindings with the
+        /// lowest priority first.
+        priority: u16,
+        /// The host name offering the service; `.` means the owner name itself.
+        target: String,
+        /// The parameters of the service, in increasing key order.
+        params: Vec<SvcParam>,
+    },
+    /// Transaction signature data (TSIG pseudo-record): a MAC over the message under a
+    /// key shared with the server, named by the record's owner. See [`crate::tsig`].
+    TSIG {
+        /// The MAC algorithm, e.g. `hmac-sha256`.
+        algorithm: String,
+        /// When the message was signed, in seconds since the Unix epoch (48 bits).
+        time_signed: u64,
+        /// How many seconds `time_signed` may differ from the receiver's clock.
+        fudge: u16,
+        /// The message authentication code.
+        mac: Vec<u8>,
+        /// The ID of the message as first signed, in case a forwarder changed it.
+        original_id: u16,
+        /// 0, or the TSIG error a server reports, such as 16 for BADSIG.
+        error: u16,
+        /// Extra data; the server's clock, with a BADTIME error.
+        other: Vec<u8>,
+    },
     /// Raw data for unsupported record types, preserving the original type code and data.
     Other {
         /// The numeric DNS record type code.
         rtype: u16,
         /// The raw record data as received from the server.
-        #[allow(dead_code)] // Used by get_raw_data() method
         data: Vec<u8>,
     },
 }
@@ -717,12 +1739,63 @@ impl fmt::Display for ResourceRecord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "{:<30} {:<10} {:<10} {}",
-            self.name, self.ttl, self.rtype, self.data
+            "{:<30} {:<10} {:<8} {:<10} {}",
+            self.name,
+            self.ttl,
+            DnsClass::from(self.rclass),
+            self.rtype,
+            self.data
         )
     }
 }
 
+impl RData {
+    /// Returns the key tag of DNSKEY data (RFC 4034 appendix B), the number DS and
+    /// RRSIG records use to refer to the key, or `None` for any other data.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::RData;
+    ///
+    /// let key = RData::DNSKEY {
+    ///     flags: 257,
+    ///     protocol: 3,
+    ///     algorithm: 13,
+    ///     public_key: vec![0; 64],
+    /// };
+    /// assert_eq!(key.key_tag(), Some(1038));
+    /// ```
+    pub fn key_tag(&self) -> Option<u16> {
+        let RData::DNSKEY {
+            flags,
+            protocol,
+            algorithm,
+            public_key,
+        } = self
+        else {
+            return None;
+        };
+        // RSA/MD5 keys use the 16 bits before the last octet of the modulus instead.
+        if *algorithm == 1 {
+            let end = public_key.len().checked_sub(1)?;
+            let start = end.checked_sub(2)?;
+            return Some(u16::from_be_bytes([
+                public_key[start],
+                public_key[start + 1],
+            ]));
+        }
+        let mut data = flags.to_be_bytes().to_vec();
+        data.extend_from_slice(&[*protocol, *algorithm]);
+        data.extend_from_slice(public_key);
+        // The data summed as 16-bit words, with the carry folded back in once.
+        let sum = data.chunks(2).fold(0u32, |sum, word| {
+            sum + (u32::from(word[0]) << 8) + u32::from(word.get(1).copied().unwrap_or(0))
+        });
+        Some((sum + (sum >> 16)) as u16)
+    }
+}
+
 impl fmt::Display for RData {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -734,7 +1807,215 @@ impl fmt::Display for RData {
                 exchange,
             } => write!(f, "MX {} {}", preference, exchange),
             RData::TXT(text) => write!(f, "TXT \"{}\"", text),
-            RData::Other { rtype, .. } => write!(f, "TYPE={} (Unsupported)", rtype),
+            RData::PTR(name) => write!(f, "PTR {}", name),
+            RData::NS(name) => write!(f, "NS {}", name),
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => write!(
+                f,
+                "SOA {} {} {} {} {} {} {}",
+                mname, rname, serial, refresh, retry, expire, minimum
+            ),
+            RData::HINFO { cpu, os } => write!(f, "HINFO {} {}", quoted(cpu), quoted(os)),
+            RData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => write!(
+                f,
+                "SRV {} {} {} {}",
+                priority,
+                weight,
+                port,
+                display_name(target)
+            ),
+            RData::NAPTR {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => write!(
+                f,
+                "NAPTR {} {} {} {} {} {}",
+                order,
+                preference,
+                quoted(flags),
+                quoted(services),
+                quoted(regexp),
+                display_name(replacement)
+            ),
+            RData::OPT(options) => {
+                write!(f, "OPT")?;
+                for option in options {
+                    write!(f, " {}", option)?;
+                }
+                Ok(())
+            }
+            RData::SSHFP {
+                algorithm,
+                fingerprint_type,
+                fingerprint,
+            } => {
+                // The fingerprint in hex, as `ssh-keygen -r` prints it.
+                write!(
+                    f,
+                    "SSHFP {} {} {}",
+                    algorithm,
+                    fingerprint_type,
+                    hex(fingerprint)
+                )
+            }
+            RData::DS {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            } => write!(
+                f,
+                "DS {} {} {} {}",
+                key_tag,
+                algorithm,
+                digest_type,
+                hex(digest)
+            ),
+            RData::DNSKEY {
+                flags,
+                protocol,
+                algorithm,
+                public_key,
+            } => write!(
+                f,
+                "DNSKEY {} {} {} {}",
+                flags,
+                protocol,
+                algorithm,
+                base64(public_key)
+            ),
+            RData::RRSIG {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+            } => write!(
+                f,
+                "RRSIG {} {} {} {} {} {} {} {} {}",
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                signature_time(*expiration),
+                signature_time(*inception),
+                key_tag,
+                display_name(signer_name),
+                base64(signature)
+            ),
+            RData::NSEC { next_domain, types } => {
+                write!(f, "NSEC {}", display_name(next_domain))?;
+                for rtype in types {
+                    write!(f, " {}", rtype)?;
+                }
+                Ok(())
+            }
+            RData::NSEC3 {
+                hash_algorithm,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } => {
+                // An empty salt is written as `-` (RFC 5155 section 3.3).
+                let salt = if salt.is_empty() {
+                    "-".to_string()
+                } else {
+                    hex(salt)
+                };
+                write!(
+                    f,
+                    "NSEC3 {} {} {} {} {}",
+                    hash_algorithm,
+                    flags,
+                    iterations,
+                    salt,
+                    base32hex(next_hashed_owner)
+                )?;
+                for rtype in types {
+                    write!(f, " {}", rtype)?;
+                }
+                Ok(())
+            }
+            RData::SVCB {
+                priority,
+                target,
+                params,
+            }
+            | RData::HTTPS {
+                priority,
+                target,
+                params,
+            } => {
+                let rtype = if matches!(self, RData::SVCB { .. }) {
+                    "SVCB"
+                } else {
+                    "HTTPS"
+                };
+                write!(f, "{} {} {}", rtype, priority, display_name(target))?;
+                for param in params {
+                    write!(f, " {}", param)?;
+                }
+                Ok(())
+            }
+            RData::TSIG {
+                algorithm,
+                time_signed,
+                fudge,
+                mac,
+                original_id,
+                error,
+                other,
+            } => {
+                write!(
+                    f,
+                    "TSIG {} {} {} {} {} {} {} {}",
+                    display_name(algorithm),
+                    time_signed,
+                    fudge,
+                    mac.len(),
+                    base64(mac),
+                    original_id,
+                    tsig_error_name(*error),
+                    other.len()
+                )?;
+                if !other.is_empty() {
+                    write!(f, " {}", hex(other))?;
+                }
+                Ok(())
+            }
+            // RFC 3597 generic encoding, which zone files accept for any type.
+            RData::Other { rtype, data } => {
+                write!(f, "TYPE{} \\# {}", rtype, data.len())?;
+                if !data.is_empty() {
+                    write!(f, " ")?;
+                    for byte in data {
+                        write!(f, "{:02x}", byte)?;
+                    }
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -754,7 +2035,7 @@ impl ResourceRecord {
     /// use std::net::Ipv4Addr;
     ///
     /// let record = ResourceRecord {
-    ///     name: "example.com".to_string(),
+    ///     name: "example.com".parse().unwrap(),
     ///     rtype: QueryType::A,
     ///     rclass: 1,
     ///     ttl: 300,
@@ -765,7 +2046,6 @@ impl ResourceRecord {
     ///     println!("IP address: {}", ip);
     /// }
     /// ```
-    #[allow(dead_code)] // Public API method
     pub fn get_ipv4_address(&self) -> Option<std::net::Ipv4Addr> {
         match &self.data {
             RData::A(addr) => Some(*addr),
@@ -779,7 +2059,6 @@ impl ResourceRecord {
     ///
     /// * `Some(Ipv6Addr)` - The IPv6 address if this is an AAAA record
     /// * `None` - If this is not an AAAA record
-    #[allow(dead_code)] // Public API method
     pub fn get_ipv6_address(&self) -> Option<std::net::Ipv6Addr> {
         match &self.data {
             RData::AAAA(addr) => Some(*addr),
@@ -800,7 +2079,7 @@ impl ResourceRecord {
     /// use dns_resolver::dns::{ResourceRecord, QueryType, RData};
     ///
     /// let record = ResourceRecord {
-    ///     name: "example.com".to_string(),
+    ///     name: "example.com".parse().unwrap(),
     ///     rtype: QueryType::MX,
     ///     rclass: 1,
     ///     ttl: 3600,
@@ -814,7 +2093,6 @@ impl ResourceRecord {
     ///     println!("Mail server: {} (priority: {})", mx_data.exchange, mx_data.preference);
     /// }
     /// ```
-    #[allow(dead_code)] // Public API method
     pub fn get_mx_data(&self) -> Option<MxData> {
         match &self.data {
             RData::MX {
@@ -830,14 +2108,42 @@ impl ResourceRecord {
 
     /// Gets the CNAME target from a CNAME record.
     ///
-    /// # Returns
+    /// # Returns
+    ///
+    /// * `Some(String)` - The canonical name if this is a CNAME record
+    /// * `None` - If this is not a CNAME record
+    pub fn get_cname(&self) -> Option<&str> {
+        match &self.data {
+            RData::CNAME(name) => Some(name),
+            _ => None,
+        }
+    }
+
+    /// Gets the target name from a PTR record.
+    ///
+    /// # Returns
+    ///
+    /// * `Some(&str)` - The name the address maps to if this is a PTR record
+    /// * `None` - If this is not a PTR record
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{ResourceRecord, QueryType, RData};
+    ///
+    /// let record = ResourceRecord {
+    ///     name: "1.2.0.192.in-addr.arpa".parse().unwrap(),
+    ///     rtype: QueryType::PTR,
+    ///     rclass: 1,
+    ///     ttl: 3600,
+    ///     data: RData::PTR("host.example.com".to_string()),
+    /// };
     ///
-    /// * `Some(String)` - The canonical name if this is a CNAME record
-    /// * `None` - If this is not a CNAME record
-    #[allow(dead_code)] // Public API method
-    pub fn get_cname(&self) -> Option<&str> {
+    /// assert_eq!(record.get_ptr(), Some("host.example.com"));
+    /// ```
+    pub fn get_ptr(&self) -> Option<&str> {
         match &self.data {
-            RData::CNAME(name) => Some(name),
+            RData::PTR(name) => Some(name),
             _ => None,
         }
     }
@@ -848,7 +2154,6 @@ impl ResourceRecord {
     ///
     /// * `Some(String)` - The text content if this is a TXT record
     /// * `None` - If this is not a TXT record
-    #[allow(dead_code)] // Public API method
     pub fn get_txt_data(&self) -> Option<&str> {
         match &self.data {
             RData::TXT(text) => Some(text),
@@ -869,7 +2174,7 @@ impl ResourceRecord {
     /// use std::net::Ipv4Addr;
     ///
     /// let record = ResourceRecord {
-    ///     name: "example.com".to_string(),
+    ///     name: "example.com".parse().unwrap(),
     ///     rtype: QueryType::A,
     ///     rclass: 1, // IN class
     ///     ttl: 300,
@@ -878,7 +2183,6 @@ impl ResourceRecord {
     ///
     /// assert_eq!(record.get_class(), 1); // Internet class
     /// ```
-    #[allow(dead_code)] // Public API method
     pub fn get_class(&self) -> u16 {
         self.rclass
     }
@@ -889,7 +2193,6 @@ impl ResourceRecord {
     ///
     /// * `Some((rtype, data))` - The record type code and raw data if this is an unsupported record type
     /// * `None` - If this is a supported record type
-    #[allow(dead_code)] // Public API method
     pub fn get_raw_data(&self) -> Option<(u16, &[u8])> {
         match &self.data {
             RData::Other { rtype, data } => Some((*rtype, data)),
@@ -897,6 +2200,434 @@ impl ResourceRecord {
         }
     }
 
+    /// Serializes the resource record into DNS wire format and appends it to the buffer.
+    ///
+    /// Names are written uncompressed, since a lone record has no message to point
+    /// into; [`DnsMessage::pack`] compresses them. TXT text is split into
+    /// `<character-string>`s of at most 255 bytes, and [`RData::Other`] is written
+    /// as its raw bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffer` - A mutable reference to a `Vec<u8>` where the serialized record will be appended
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - Successfully serialized the record
+    /// * `Err(String)` - If a name cannot be encoded or the record data exceeds 65535 bytes
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{QueryType, RData, ResourceRecord};
+    /// use std::io::Cursor;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let record = ResourceRecord {
+    ///     name: "example.com".parse().unwrap(),
+    ///     rtype: QueryType::A,
+    ///     rclass: 1,
+    ///     ttl: 300,
+    ///     data: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+    /// };
+    ///
+    /// let mut buffer = Vec::new();
+    /// record.pack(&mut buffer).unwrap();
+    /// let parsed = ResourceRecord::from_bytes(&mut Cursor::new(&buffer[..])).unwrap();
+    /// assert_eq!(parsed, record);
+    /// ```
+    pub fn pack(&self, buffer: &mut Vec<u8>) -> Result<(), String> {
+        self.pack_with(buffer, &mut None)
+    }
+
+    /// Returns the record in canonical form (RFC 4034 section 6.2).
+    ///
+    /// The owner name and the names embedded in NS, CNAME, PTR, MX, SOA, SRV, NAPTR,
+    /// SVCB, HTTPS and RRSIG data are lowercased and lose any trailing dot. Other data
+    /// is left as it is: TXT text is case-sensitive, the next name of NSEC data keeps
+    /// its case (RFC 6840 section 5.1), and the layout of unknown types is not known
+    /// (RFC 3597 section 7). Canonical records are always packed uncompressed.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{QueryType, RData, ResourceRecord};
+    ///
+    /// let record = ResourceRecord {
+    ///     name: "WWW.Example.COM.".parse().unwrap(),
+    ///     rtype: QueryType::CNAME,
+    ///     rclass: 1,
+    ///     ttl: 300,
+    ///     data: RData::CNAME("Web.Example.NET".to_string()),
+    /// };
+    ///
+    /// let canonical = record.to_canonical();
+    /// assert_eq!(canonical.name.as_str(), "www.example.com");
+    /// assert_eq!(canonical.data, RData::CNAME("web.example.net".to_string()));
+    /// ```
+    pub fn to_canonical(&self) -> ResourceRecord {
+        self.map_names(canonical_name)
+    }
+
+    /// Returns the record with `map` applied to the owner name and to the names
+    /// embedded in its data: NS, CNAME, PTR, MX, SOA, SRV, NAPTR, SVCB, HTTPS and
+    /// RRSIG data, as in [`ResourceRecord::to_canonical`]. Everything else is kept.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::{QueryType, RData, ResourceRecord};
+    ///
+    /// let record = ResourceRecord {
+    ///     name: "www.example.com".parse().unwrap(),
+    ///     rtype: QueryType::NS,
+    ///     rclass: 1,
+    ///     ttl: 300,
+    ///     data: RData::NS("ns1.example.net".to_string()),
+    /// };
+    ///
+    /// let absolute = record.map_names(|name| format!("{}.", name));
+    /// assert_eq!(absolute.name, "www.example.com.");
+    /// assert_eq!(absolute.data, RData::NS("ns1.example.net.".to_string()));
+    /// ```
+    pub fn map_names(&self, map: impl Fn(&str) -> String) -> ResourceRecord {
+        let data = match &self.data {
+            RData::CNAME(name) => RData::CNAME(map(name)),
+            RData::PTR(name) => RData::PTR(map(name)),
+            RData::NS(name) => RData::NS(map(name)),
+            RData::MX {
+                preference,
+                exchange,
+            } => RData::MX {
+                preference: *preference,
+                exchange: map(exchange),
+            },
+            RData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => RData::SRV {
+                priority: *priority,
+                weight: *weight,
+                port: *port,
+                target: map(target),
+            },
+            RData::NAPTR {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => RData::NAPTR {
+                order: *order,
+                preference: *preference,
+                flags: flags.clone(),
+                services: services.clone(),
+                regexp: regexp.clone(),
+                replacement: map(replacement),
+            },
+            RData::RRSIG {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+            } => RData::RRSIG {
+                type_covered: *type_covered,
+                algorithm: *algorithm,
+                labels: *labels,
+                original_ttl: *original_ttl,
+                expiration: *expiration,
+                inception: *inception,
+                key_tag: *key_tag,
+                signer_name: map(signer_name),
+                signature: signature.clone(),
+            },
+            RData::SVCB {
+                priority,
+                target,
+                params,
+            } => RData::SVCB {
+                priority: *priority,
+                target: map(target),
+                params: params.clone(),
+            },
+            RData::HTTPS {
+                priority,
+                target,
+                params,
+            } => RData::HTTPS {
+                priority: *priority,
+                target: map(target),
+                params: params.clone(),
+            },
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => RData::SOA {
+                mname: map(mname),
+                rname: map(rname),
+                serial: *serial,
+                refresh: *refresh,
+                retry: *retry,
+                expire: *expire,
+                minimum: *minimum,
+            },
+            data => data.clone(),
+        };
+        ResourceRecord {
+            name: DnsName::new_unchecked(map(&self.name)),
+            data,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the record data of the canonical form of the record in wire format,
+    /// which is what RRsets are ordered by (RFC 4034 section 6.3).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a name cannot be encoded or the data exceeds 65535 bytes.
+    pub fn canonical_rdata(&self) -> Result<Vec<u8>, String> {
+        let mut buffer = Vec::new();
+        let canonical = self.to_canonical();
+        canonical.pack(&mut buffer)?;
+        // Skip the owner name, TYPE, CLASS, TTL and RDLENGTH.
+        let mut name_length = Vec::new();
+        pack_domain_name(&mut name_length, &canonical.name)?;
+        Ok(buffer.split_off(name_length.len() + 10))
+    }
+
+    /// Serializes the record, compressing names against earlier names in the message
+    /// when a compressor is given.
+    fn pack_with(
+        &self,
+        buffer: &mut Vec<u8>,
+        compressor: &mut Option<NameCompressor>,
+    ) -> Result<(), String> {
+        pack_name(buffer, &self.name, compressor)?;
+        buffer.extend_from_slice(&u16::from(self.rtype).to_be_bytes());
+        buffer.extend_from_slice(&self.rclass.to_be_bytes());
+        buffer.extend_from_slice(&self.ttl.to_be_bytes());
+
+        // Reserve the RDLENGTH field and fill it in once the data is written.
+        let length_pos = buffer.len();
+        buffer.extend_from_slice(&[0, 0]);
+        match &self.data {
+            RData::A(addr) => buffer.extend_from_slice(&addr.octets()),
+            RData::AAAA(addr) => buffer.extend_from_slice(&addr.octets()),
+            RData::CNAME(name) | RData::PTR(name) | RData::NS(name) => {
+                pack_name(buffer, name, compressor)?
+            }
+            RData::MX {
+                preference,
+                exchange,
+            } => {
+                buffer.extend_from_slice(&preference.to_be_bytes());
+                pack_name(buffer, exchange, compressor)?;
+            }
+            RData::SOA {
+                mname,
+                rname,
+                serial,
+                refresh,
+                retry,
+                expire,
+                minimum,
+            } => {
+                pack_name(buffer, mname, compressor)?;
+                pack_name(buffer, rname, compressor)?;
+                for value in [serial, refresh, retry, expire, minimum] {
+                    buffer.extend_from_slice(&value.to_be_bytes());
+                }
+            }
+            RData::HINFO { cpu, os } => {
+                pack_character_string(buffer, cpu)?;
+                pack_character_string(buffer, os)?;
+            }
+            RData::TXT(text) => {
+                // Every TXT record has at least one, possibly empty, character-string.
+                let bytes = text.as_bytes();
+                if bytes.is_empty() {
+                    buffer.push(0);
+                }
+                for chunk in bytes.chunks(255) {
+                    buffer.push(chunk.len() as u8);
+                    buffer.extend_from_slice(chunk);
+                }
+            }
+            RData::SRV {
+                priority,
+                weight,
+                port,
+                target,
+            } => {
+                for value in [priority, weight, port] {
+                    buffer.extend_from_slice(&value.to_be_bytes());
+                }
+                // The target must not be compressed (RFC 2782).
+                pack_domain_name(buffer, target)?;
+            }
+            RData::NAPTR {
+                order,
+                preference,
+                flags,
+                services,
+                regexp,
+                replacement,
+            } => {
+                buffer.extend_from_slice(&order.to_be_bytes());
+                buffer.extend_from_slice(&preference.to_be_bytes());
+                for text in [flags, services, regexp] {
+                    pack_character_string(buffer, text)?;
+                }
+                // The replacement must not be compressed (RFC 3403).
+                pack_domain_name(buffer, replacement)?;
+            }
+            RData::OPT(options) => {
+                for option in options {
+                    let length = u16::try_from(option.data.len())
+                        .map_err(|_| format!("EDNS option {} exceeds 65535 bytes", option.code))?;
+                    buffer.extend_from_slice(&option.code.to_be_bytes());
+                    buffer.extend_from_slice(&length.to_be_bytes());
+                    buffer.extend_from_slice(&option.data);
+                }
+            }
+            RData::SSHFP {
+                algorithm,
+                fingerprint_type,
+                fingerprint,
+            } => {
+                buffer.push(*algorithm);
+                buffer.push(*fingerprint_type);
+                buffer.extend_from_slice(fingerprint);
+            }
+            RData::DS {
+                key_tag,
+                algorithm,
+                digest_type,
+                digest,
+            } => {
+                buffer.extend_from_slice(&key_tag.to_be_bytes());
+                buffer.extend_from_slice(&[*algorithm, *digest_type]);
+                buffer.extend_from_slice(digest);
+            }
+            RData::DNSKEY {
+                flags,
+                protocol,
+                algorithm,
+                public_key,
+            } => {
+                buffer.extend_from_slice(&flags.to_be_bytes());
+                buffer.extend_from_slice(&[*protocol, *algorithm]);
+                buffer.extend_from_slice(public_key);
+            }
+            RData::RRSIG {
+                type_covered,
+                algorithm,
+                labels,
+                original_ttl,
+                expiration,
+                inception,
+                key_tag,
+                signer_name,
+                signature,
+            } => {
+                buffer.extend_from_slice(&u16::from(*type_covered).to_be_bytes());
+                buffer.extend_from_slice(&[*algorithm, *labels]);
+                for value in [original_ttl, expiration, inception] {
+                    buffer.extend_from_slice(&value.to_be_bytes());
+                }
+                buffer.extend_from_slice(&key_tag.to_be_bytes());
+                // The signer's name must not be compressed (RFC 4034 section 3.1.7).
+                pack_domain_name(buffer, signer_name)?;
+                buffer.extend_from_slice(signature);
+            }
+            RData::NSEC { next_domain, types } => {
+                // The next name must not be compressed (RFC 4034 section 4.1.1).
+                pack_domain_name(buffer, next_domain)?;
+                pack_type_bitmaps(buffer, types);
+            }
+            RData::NSEC3 {
+                hash_algorithm,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } => {
+                buffer.extend_from_slice(&[*hash_algorithm, *flags]);
+                buffer.extend_from_slice(&iterations.to_be_bytes());
+                for field in [salt, next_hashed_owner] {
+                    let length = u8::try_from(field.len())
+                        .map_err(|_| "NSEC3 salt or hash exceeds 255 bytes".to_string())?;
+                    buffer.push(length);
+                    buffer.extend_from_slice(field);
+                }
+                pack_type_bitmaps(buffer, types);
+            }
+            RData::SVCB {
+                priority,
+                target,
+                params,
+            }
+            | RData::HTTPS {
+                priority,
+                target,
+                params,
+            } => {
+                buffer.extend_from_slice(&priority.to_be_bytes());
+                // The target must not be compressed (RFC 9460 section 2.2).
+                pack_domain_name(buffer, target)?;
+                for param in params {
+                    param.pack(buffer)?;
+                }
+            }
+            RData::TSIG {
+                algorithm,
+                time_signed,
+                fudge,
+                mac,
+                original_id,
+                error,
+                other,
+            } => {
+                // The algorithm name must not be compressed (RFC 8945 section 4.2).
+                pack_domain_name(buffer, algorithm)?;
+                buffer.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+                buffer.extend_from_slice(&fudge.to_be_bytes());
+                // The MAC and the other data each come with a two-byte length.
+                let field_length = |field: &[u8]| {
+                    u16::try_from(field.len())
+                        .map(u16::to_be_bytes)
+                        .map_err(|_| "TSIG field exceeds 65535 bytes".to_string())
+                };
+                buffer.extend_from_slice(&field_length(mac)?);
+                buffer.extend_from_slice(mac);
+                buffer.extend_from_slice(&original_id.to_be_bytes());
+                buffer.extend_from_slice(&error.to_be_bytes());
+                buffer.extend_from_slice(&field_length(other)?);
+                buffer.extend_from_slice(other);
+            }
+            RData::Other { data, .. } => buffer.extend_from_slice(data),
+        }
+        let length = u16::try_from(buffer.len() - length_pos - 2)
+            .map_err(|_| format!("Record data for {} exceeds 65535 bytes", self.name))?;
+        buffer[length_pos..length_pos + 2].copy_from_slice(&length.to_be_bytes());
+        Ok(())
+    }
+
     /// Deserializes a DNS resource record from a byte cursor.
     ///
     /// Reads a complete resource record from the cursor in DNS wire format, including
@@ -918,14 +2649,20 @@ impl ResourceRecord {
     /// use dns_resolver::dns::ResourceRecord;
     /// use std::io::Cursor;
     ///
-    /// // Example A record data (simplified - real data would include proper domain name encoding)
+    /// // An A record for example.com with a TTL of 300 seconds
     /// let data = [
-    ///     // Domain name, type, class, TTL, data length, and IP address would be here
-    ///     // This is a conceptual example - actual usage requires proper DNS packet data
+    ///     7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0, // name
+    ///     0x00, 0x01, // type A
+    ///     0x00, 0x01, // class IN
+    ///     0x00, 0x00, 0x01, 0x2C, // TTL
+    ///     0x00, 0x04, // data length
+    ///     192, 0, 2, 1, // address
     /// ];
     ///
-    /// // let mut cursor = Cursor::new(&data[..]);
-    /// // let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+    /// let mut cursor = Cursor::new(&data[..]);
+    /// let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+    /// assert_eq!((record.name.as_str(), record.ttl), ("example.com", 300));
+    /// assert_eq!(record.data.to_string(), "A 192.0.2.1");
     /// ```
     ///
     /// # Errors
@@ -943,6 +2680,19 @@ impl ResourceRecord {
     /// - **CNAME records**: Parsed into [`RData::CNAME`] with target domain name
     /// - **MX records**: Parsed into [`RData::MX`] with preference and exchange server
     /// - **TXT records**: Parsed into [`RData::TXT`] with text content
+    /// - **PTR records**: Parsed into [`RData::PTR`] with the target domain name
+    /// - **NS records**: Parsed into [`RData::NS`] with the name server's host name
+    /// - **SOA records**: Parsed into [`RData::SOA`] with the zone's parameters
+    /// - **HINFO records**: Parsed into [`RData::HINFO`] with the CPU and OS strings
+    /// - **SRV records**: Parsed into [`RData::SRV`] with the service's location
+    /// - **NAPTR records**: Parsed into [`RData::NAPTR`] with the rewrite rule
+    /// - **OPT pseudo-records**: Parsed into [`RData::OPT`] with the EDNS options
+    /// - **SSHFP records**: Parsed into [`RData::SSHFP`] with the host key fingerprint
+    /// - **DS, DNSKEY, RRSIG, NSEC and NSEC3 records**: Parsed into the [`RData`]
+    ///   variant of the same name with every DNSSEC field decoded
+    /// - **SVCB and HTTPS records**: Parsed into [`RData::SVCB`] and [`RData::HTTPS`]
+    ///   with the service's parameters
+    /// - **TSIG pseudo-records**: Parsed into [`RData::TSIG`] with the MAC and its timers
     /// - **Other types**: Stored as [`RData::Other`] with raw data for forward compatibility
     pub fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
         let name = unpack_domain_name(cursor)?;
@@ -952,7 +2702,7 @@ impl ResourceRecord {
 
         cursor.read_exact(&mut u16_buf)?;
         let rtype_val = u16::from_be_bytes(u16_buf);
-        let rtype = QueryType::try_from(rtype_val);
+        let rtype = QueryType::from(rtype_val);
 
         cursor.read_exact(&mut u16_buf)?;
         let rclass = u16::from_be_bytes(u16_buf);
@@ -966,70 +2716,629 @@ impl ResourceRecord {
         let data_start_pos = cursor.position() as usize;
         let data_end_pos = data_start_pos + data_len;
 
-        let rdata = match rtype {
-            Ok(QueryType::A) => {
-                cursor.read_exact(&mut u32_buf)?;
-                RData::A(Ipv4Addr::from(u32_buf))
+        let rdata = match rtype {
+            QueryType::A => {
+                cursor.read_exact(&mut u32_buf)?;
+                RData::A(Ipv4Addr::from(u32_buf))
+            }
+            QueryType::AAAA => {
+                let mut ipv6_buf = [0u8; 16];
+                cursor.read_exact(&mut ipv6_buf)?;
+                RData::AAAA(Ipv6Addr::from(ipv6_buf))
+            }
+            QueryType::CNAME => {
+                let cname = unpack_domain_name(cursor)?;
+                RData::CNAME(cname)
+            }
+            QueryType::MX => {
+                cursor.read_exact(&mut u16_buf)?;
+                let preference = u16::from_be_bytes(u16_buf);
+                let exchange = unpack_domain_name(cursor)?;
+                RData::MX {
+                    preference,
+                    exchange,
+                }
+            }
+            QueryType::PTR => {
+                let ptr = unpack_domain_name(cursor)?;
+                RData::PTR(ptr)
+            }
+            QueryType::NS => {
+                let ns = unpack_domain_name(cursor)?;
+                RData::NS(ns)
+            }
+            QueryType::SOA => {
+                let mname = unpack_domain_name(cursor)?;
+                let rname = unpack_domain_name(cursor)?;
+                // SERIAL, REFRESH, RETRY, EXPIRE and MINIMUM, in that order.
+                let mut values = [0u32; 5];
+                for value in &mut values {
+                    cursor.read_exact(&mut u32_buf)?;
+                    *value = u32::from_be_bytes(u32_buf);
+                }
+                let [serial, refresh, retry, expire, minimum] = values;
+                RData::SOA {
+                    mname,
+                    rname,
+                    serial,
+                    refresh,
+                    retry,
+                    expire,
+                    minimum,
+                }
+            }
+            QueryType::HINFO => RData::HINFO {
+                cpu: read_character_string(cursor)?,
+                os: read_character_string(cursor)?,
+            },
+            QueryType::SRV => {
+                // PRIORITY, WEIGHT and PORT, then the target.
+                let mut values = [0u16; 3];
+                for value in &mut values {
+                    cursor.read_exact(&mut u16_buf)?;
+                    *value = u16::from_be_bytes(u16_buf);
+                }
+                let [priority, weight, port] = values;
+                RData::SRV {
+                    priority,
+                    weight,
+                    port,
+                    target: unpack_domain_name(cursor)?,
+                }
+            }
+            QueryType::NAPTR => {
+                cursor.read_exact(&mut u16_buf)?;
+                let order = u16::from_be_bytes(u16_buf);
+                cursor.read_exact(&mut u16_buf)?;
+                let preference = u16::from_be_bytes(u16_buf);
+                RData::NAPTR {
+                    order,
+                    preference,
+                    flags: read_character_string(cursor)?,
+                    services: read_character_string(cursor)?,
+                    regexp: read_character_string(cursor)?,
+                    replacement: unpack_domain_name(cursor)?,
+                }
+            }
+            QueryType::OPT => {
+                // OPT data is a sequence of options, each a code, a length and
+                // that many bytes of data (RFC 6891 section 6.1.2).
+                let mut options = Vec::new();
+                while cursor.position() < data_end_pos as u64 {
+                    cursor.read_exact(&mut u16_buf)?;
+                    let code = u16::from_be_bytes(u16_buf);
+                    cursor.read_exact(&mut u16_buf)?;
+                    let mut data = vec![0; u16::from_be_bytes(u16_buf) as usize];
+                    cursor.read_exact(&mut data)?;
+                    options.push(EdnsOption { code, data });
+                }
+                RData::OPT(options)
+            }
+            QueryType::SSHFP => {
+                // The algorithm and fingerprint type, then the fingerprint itself.
+                let mut fields = [0u8; 2];
+                cursor.read_exact(&mut fields)?;
+                RData::SSHFP {
+                    algorithm: fields[0],
+                    fingerprint_type: fields[1],
+                    fingerprint: read_to_end(cursor, data_end_pos)?,
+                }
+            }
+            QueryType::DS => {
+                cursor.read_exact(&mut u16_buf)?;
+                let mut fields = [0u8; 2];
+                cursor.read_exact(&mut fields)?;
+                RData::DS {
+                    key_tag: u16::from_be_bytes(u16_buf),
+                    algorithm: fields[0],
+                    digest_type: fields[1],
+                    digest: read_to_end(cursor, data_end_pos)?,
+                }
+            }
+            QueryType::DNSKEY => {
+                cursor.read_exact(&mut u16_buf)?;
+                let mut fields = [0u8; 2];
+                cursor.read_exact(&mut fields)?;
+                RData::DNSKEY {
+                    flags: u16::from_be_bytes(u16_buf),
+                    protocol: fields[0],
+                    algorithm: fields[1],
+                    public_key: read_to_end(cursor, data_end_pos)?,
+                }
+            }
+            QueryType::RRSIG => {
+                // TYPE COVERED, ALGORITHM and LABELS, then ORIGINAL TTL, SIGNATURE
+                // EXPIRATION and SIGNATURE INCEPTION, then KEY TAG.
+                cursor.read_exact(&mut u16_buf)?;
+                let type_covered = QueryType::from(u16::from_be_bytes(u16_buf));
+                let mut fields = [0u8; 2];
+                cursor.read_exact(&mut fields)?;
+                let mut values = [0u32; 3];
+                for value in &mut values {
+                    cursor.read_exact(&mut u32_buf)?;
+                    *value = u32::from_be_bytes(u32_buf);
+                }
+                let [original_ttl, expiration, inception] = values;
+                cursor.read_exact(&mut u16_buf)?;
+                RData::RRSIG {
+                    type_covered,
+                    algorithm: fields[0],
+                    labels: fields[1],
+                    original_ttl,
+                    expiration,
+                    inception,
+                    key_tag: u16::from_be_bytes(u16_buf),
+                    signer_name: unpack_domain_name(cursor)?,
+                    signature: read_to_end(cursor, data_end_pos)?,
+                }
+            }
+            QueryType::NSEC => RData::NSEC {
+                next_domain: unpack_domain_name(cursor)?,
+                types: read_type_bitmaps(cursor, data_end_pos)?,
+            },
+            QueryType::NSEC3 => {
+                let mut fields = [0u8; 2];
+                cursor.read_exact(&mut fields)?;
+                cursor.read_exact(&mut u16_buf)?;
+                // The salt and the next hash each come with a length octet.
+                let read_field = |cursor: &mut Cursor<&[u8]>| {
+                    let mut length = [0u8; 1];
+                    cursor.read_exact(&mut length)?;
+                    let mut field = vec![0; length[0] as usize];
+                    cursor.read_exact(&mut field)?;
+                    Ok::<_, std::io::Error>(field)
+                };
+                RData::NSEC3 {
+                    hash_algorithm: fields[0],
+                    flags: fields[1],
+                    iterations: u16::from_be_bytes(u16_buf),
+                    salt: read_field(cursor)?,
+                    next_hashed_owner: read_field(cursor)?,
+                    types: read_type_bitmaps(cursor, data_end_pos)?,
+                }
+            }
+            QueryType::TSIG => {
+                let algorithm = unpack_domain_name(cursor)?;
+                // TIME SIGNED is 48 bits, then FUDGE.
+                let mut time = [0u8; 8];
+                cursor.read_exact(&mut time[2..])?;
+                cursor.read_exact(&mut u16_buf)?;
+                let fudge = u16::from_be_bytes(u16_buf);
+                // The MAC and the other data each come with a two-byte length.
+                let read_field = |cursor: &mut Cursor<&[u8]>| {
+                    let mut length = [0u8; 2];
+                    cursor.read_exact(&mut length)?;
+                    let mut field = vec![0; u16::from_be_bytes(length) as usize];
+                    cursor.read_exact(&mut field)?;
+                    Ok::<_, std::io::Error>(field)
+                };
+                let mac = read_field(cursor)?;
+                cursor.read_exact(&mut u16_buf)?;
+                let original_id = u16::from_be_bytes(u16_buf);
+                cursor.read_exact(&mut u16_buf)?;
+                RData::TSIG {
+                    algorithm,
+                    time_signed: u64::from_be_bytes(time),
+                    fudge,
+                    mac,
+                    original_id,
+                    error: u16::from_be_bytes(u16_buf),
+                    other: read_field(cursor)?,
+                }
+            }
+            QueryType::SVCB | QueryType::HTTPS => {
+                cursor.read_exact(&mut u16_buf)?;
+                let priority = u16::from_be_bytes(u16_buf);
+                let target = unpack_domain_name(cursor)?;
+                let mut params = Vec::new();
+                while cursor.position() < data_end_pos as u64 {
+                    params.push(SvcParam::from_bytes(cursor)?);
+                }
+                if rtype == QueryType::SVCB {
+                    RData::SVCB {
+                        priority,
+                        target,
+                        params,
+                    }
+                } else {
+                    RData::HTTPS {
+                        priority,
+                        target,
+                        params,
+                    }
+                }
+            }
+            QueryType::TXT => {
+                // TXT records have one or more <character-string>s. A <character-string>
+                // is a length octet followed by that number of characters.
+                let mut text_data = Vec::new();
+                let mut current_pos = cursor.position();
+                while current_pos < data_end_pos as u64 {
+                    let mut len_buf = [0u8; 1];
+                    cursor.read_exact(&mut len_buf)?;
+                    let len = len_buf[0] as usize;
+
+                    let mut str_buf = vec![0u8; len];
+                    cursor.read_exact(&mut str_buf)?;
+                    text_data.extend_from_slice(&str_buf);
+                    current_pos = cursor.position();
+                }
+                RData::TXT(String::from_utf8_lossy(&text_data).to_string())
+            }
+            _ => {
+                // Unsupported type
+                let mut other_data = vec![0; data_len];
+                cursor.read_exact(&mut other_data)?;
+                RData::Other {
+                    rtype: rtype_val,
+                    data: other_data,
+                }
+            }
+        };
+
+        // Ensure cursor is at the end of the RDATA section
+        cursor.set_position(data_end_pos as u64);
+
+        Ok(ResourceRecord {
+            name: DnsName::new_unchecked(name),
+            rtype,
+            rclass,
+            ttl,
+            data: rdata,
+        })
+    }
+}
+
+/// One option in the data of an OPT pseudo-record (RFC 6891 section 6.1.2), such as
+/// a client subnet or a cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EdnsOption {
+    /// The option code assigned by IANA.
+    pub code: u16,
+    /// The option data, uninterpreted.
+    pub data: Vec<u8>,
+}
+
+/// The option codes with a name, from the IANA EDNS0 Option Codes registry.
+const EDNS_OPTION_NAMES: &[(u16, &str)] = &[
+    (3, "NSID"),
+    (5, "DAU"),
+    (6, "DHU"),
+    (7, "N3U"),
+    (8, "CLIENT-SUBNET"),
+    (9, "EXPIRE"),
+    (10, "COOKIE"),
+    (11, "TCP-KEEPALIVE"),
+    (12, "PADDING"),
+    (13, "CHAIN"),
+    (14, "KEY-TAG"),
+    (15, "EDE"),
+    (18, "REPORT-CHANNEL"),
+    (19, "ZONEVERSION"),
+];
+
+impl EdnsOption {
+    /// Returns the registered name of the option code, such as `COOKIE` for 10,
+    /// or `None` for a code without one.
+    pub fn name(&self) -> Option<&'static str> {
+        EDNS_OPTION_NAMES
+            .iter()
+            .find(|(code, _)| *code == self.code)
+            .map(|(_, name)| *name)
+    }
+}
+
+impl fmt::Display for EdnsOption {
+    /// Formats the option as its name, or its code when it has none, and hex data,
+    /// e.g. `COOKIE:0123456789abcdef` or `65001:beef`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.name() {
+            Some(name) => write!(f, "{}:", name)?,
+            None => write!(f, "{}:", self.code)?,
+        }
+        for byte in &self.data {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for EdnsOption {
+    type Err = String;
+
+    /// Parses an option in the form it is displayed in: a code or registered name,
+    /// optionally followed by a colon and the data in hex.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::EdnsOption;
+    ///
+    /// let option: EdnsOption = "65001:beef".parse().unwrap();
+    /// assert_eq!((option.code, option.data), (65001, vec![0xbe, 0xef]));
+    /// let option: EdnsOption = "nsid".parse().unwrap();
+    /// assert_eq!((option.code, option.data), (3, Vec::new()));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code is neither a number from 0 to 65535 nor a
+    /// registered name, or the data is not an even number of hex digits.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (code, data) = s.split_once(':').unwrap_or((s, ""));
+        let code = match EDNS_OPTION_NAMES
+            .iter()
+            .find(|(_, name)| name.eq_ignore_ascii_case(code))
+        {
+            Some((code, _)) => *code,
+            None => code
+                .parse::<u16>()
+                .map_err(|_| format!("Unknown EDNS option code: {}", code))?,
+        };
+        let data = crate::transcript::decode_hex(data)
+            .ok_or_else(|| format!("Invalid EDNS option data: {}", data))?;
+        Ok(EdnsOption { code, data })
+    }
+}
+
+/// One parameter of an SVCB or HTTPS record (RFC 9460 section 7), in the form
+/// `key=value` when displayed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SvcParam {
+    /// `alpn` (key 1): the protocols the service supports, such as `h2` and `h3`.
+    Alpn(Vec<String>),
+    /// `port` (key 3): the port to connect to instead of the scheme's default.
+    Port(u16),
+    /// `ipv4hint` (key 4): addresses clients may use before resolving the target.
+    Ipv4Hint(Vec<Ipv4Addr>),
+    /// `ipv6hint` (key 6): addresses clients may use before resolving the target.
+    Ipv6Hint(Vec<Ipv6Addr>),
+    /// Any other parameter, such as `mandatory`, `no-default-alpn` or `ech`,
+    /// displayed in the generic `key<number>="value"` form.
+    Other {
+        /// The parameter key assigned by IANA.
+        key: u16,
+        /// The parameter value, uninterpreted.
+        value: Vec<u8>,
+    },
+}
+
+impl SvcParam {
+    /// Returns the parameter's key.
+    pub fn key(&self) -> u16 {
+        match self {
+            SvcParam::Alpn(_) => 1,
+            SvcParam::Port(_) => 3,
+            SvcParam::Ipv4Hint(_) => 4,
+            SvcParam::Ipv6Hint(_) => 6,
+            SvcParam::Other { key, .. } => *key,
+        }
+    }
+
+    /// Reads one parameter: a key, a length and that many bytes of value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::ErrorKind::InvalidData`] error if a known parameter's
+    /// value does not have the layout RFC 9460 gives it.
+    fn from_bytes(cursor: &mut Cursor<&[u8]>) -> Result<Self, std::io::Error> {
+        let mut u16_buf = [0u8; 2];
+        cursor.read_exact(&mut u16_buf)?;
+        let key = u16::from_be_bytes(u16_buf);
+        cursor.read_exact(&mut u16_buf)?;
+        let mut value = vec![0; u16::from_be_bytes(u16_buf) as usize];
+        cursor.read_exact(&mut value)?;
+
+        let invalid = || {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Malformed value for SvcParam key{}", key),
+            )
+        };
+        let param = match key {
+            1 => {
+                // A non-empty sequence of non-empty <character-string>s.
+                let mut protocols = Vec::new();
+                let mut value_cursor = Cursor::new(&value[..]);
+                while value_cursor.position() < value.len() as u64 {
+                    let protocol =
+                        read_character_string(&mut value_cursor).map_err(|_| invalid())?;
+                    if protocol.is_empty() {
+                        return Err(invalid());
+                    }
+                    protocols.push(protocol);
+                }
+                if protocols.is_empty() {
+                    return Err(invalid());
+                }
+                SvcParam::Alpn(protocols)
             }
-            Ok(QueryType::AAAA) => {
-                let mut ipv6_buf = [0u8; 16];
-                cursor.read_exact(&mut ipv6_buf)?;
-                RData::AAAA(Ipv6Addr::from(ipv6_buf))
+            3 => SvcParam::Port(u16::from_be_bytes(
+                value.as_slice().try_into().map_err(|_| invalid())?,
+            )),
+            4 if !value.is_empty() && value.len().is_multiple_of(4) => SvcParam::Ipv4Hint(
+                value
+                    .chunks(4)
+                    .map(|octets| Ipv4Addr::from(<[u8; 4]>::try_from(octets).unwrap()))
+                    .collect(),
+            ),
+            6 if !value.is_empty() && value.len().is_multiple_of(16) => SvcParam::Ipv6Hint(
+                value
+                    .chunks(16)
+                    .map(|octets| Ipv6Addr::from(<[u8; 16]>::try_from(octets).unwrap()))
+                    .collect(),
+            ),
+            4 | 6 => return Err(invalid()),
+            key => SvcParam::Other { key, value },
+        };
+        Ok(param)
+    }
+
+    /// Writes the parameter's key, length and value.
+    fn pack(&self, buffer: &mut Vec<u8>) -> Result<(), String> {
+        let mut value = Vec::new();
+        match self {
+            SvcParam::Alpn(protocols) => {
+                for protocol in protocols {
+                    pack_character_string(&mut value, protocol)?;
+                }
             }
-            Ok(QueryType::CNAME) => {
-                let cname = unpack_domain_name(cursor)?;
-                RData::CNAME(cname)
+            SvcParam::Port(port) => value.extend_from_slice(&port.to_be_bytes()),
+            SvcParam::Ipv4Hint(addresses) => {
+                for address in addresses {
+                    value.extend_from_slice(&address.octets());
+                }
             }
-            Ok(QueryType::MX) => {
-                cursor.read_exact(&mut u16_buf)?;
-                let preference = u16::from_be_bytes(u16_buf);
-                let exchange = unpack_domain_name(cursor)?;
-                RData::MX {
-                    preference,
-                    exchange,
+            SvcParam::Ipv6Hint(addresses) => {
+                for address in addresses {
+                    value.extend_from_slice(&address.octets());
                 }
             }
-            Ok(QueryType::TXT) => {
-                // TXT records have one or more <character-string>s. A <character-string>
-                // is a length octet followed by that number of characters.
-                let mut text_data = Vec::new();
-                let mut current_pos = cursor.position();
-                while current_pos < data_end_pos as u64 {
-                    let mut len_buf = [0u8; 1];
-                    cursor.read_exact(&mut len_buf)?;
-                    let len = len_buf[0] as usize;
+            SvcParam::Other { value: data, .. } => value.extend_from_slice(data),
+        }
+        let length = u16::try_from(value.len())
+            .map_err(|_| format!("SvcParam key{} exceeds 65535 bytes", self.key()))?;
+        buffer.extend_from_slice(&self.key().to_be_bytes());
+        buffer.extend_from_slice(&length.to_be_bytes());
+        buffer.extend_from_slice(&value);
+        Ok(())
+    }
+}
 
-                    let mut str_buf = vec![0u8; len];
-                    cursor.read_exact(&mut str_buf)?;
-                    text_data.extend_from_slice(&str_buf);
-                    current_pos = cursor.position();
+impl fmt::Display for SvcParam {
+    /// Formats the parameter in presentation format, e.g. `alpn=h2,h3` or
+    /// `ipv4hint=192.0.2.1,192.0.2.2`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn join<T: fmt::Display>(items: &[T]) -> String {
+            items.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+        }
+        match self {
+            SvcParam::Alpn(protocols) => write!(f, "alpn={}", join(protocols)),
+            SvcParam::Port(port) => write!(f, "port={}", port),
+            SvcParam::Ipv4Hint(addresses) => write!(f, "ipv4hint={}", join(addresses)),
+            SvcParam::Ipv6Hint(addresses) => write!(f, "ipv6hint={}", join(addresses)),
+            SvcParam::Other { key, value } => {
+                write!(f, "key{}", key)?;
+                if value.is_empty() {
+                    return Ok(());
                 }
-                RData::TXT(String::from_utf8_lossy(&text_data).to_string())
-            }
-            _ => {
-                // Unsupported type
-                let mut other_data = vec![0; data_len];
-                cursor.read_exact(&mut other_data)?;
-                RData::Other {
-                    rtype: rtype_val,
-                    data: other_data,
+                // Printable characters as they are, anything else as \DDD.
+                write!(f, "=\"")?;
+                for &byte in value {
+                    match byte {
+                        b'"' | b'\\' => write!(f, "\\{}", byte as char)?,
+                        0x21..=0x7e => write!(f, "{}", byte as char)?,
+                        _ => write!(f, "\\{:03}", byte)?,
+                    }
                 }
+                write!(f, "\"")
             }
-        };
+        }
+    }
+}
 
-        // Ensure cursor is at the end of the RDATA section
-        cursor.set_position(data_end_pos as u64);
+/// The EDNS(0) parameters of a message (RFC 6891).
+///
+/// EDNS travels in an OPT pseudo-record in the additional section. The record
+/// reuses the fixed fields of a resource record for its own purposes: the class
+/// holds the largest UDP payload the sender can reassemble, and the TTL holds the
+/// upper bits of the response code, the EDNS version and flags such as DO.
+/// [`Edns::to_record`] and [`Edns::from_record`] convert between the two.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{DnsMessage, Edns};
+///
+/// // Advertise that responses of up to 1232 bytes can be received over UDP.
+/// let mut message = DnsMessage::new();
+/// message.additionals.push(Edns::new(1232).to_record());
+/// message.header.additional_count = 1;
+///
+/// assert_eq!(message.edns().unwrap().udp_payload_size, 1232);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Edns {
+    /// The largest UDP payload, in bytes, the sender can receive.
+    pub udp_payload_size: u16,
+    /// The upper eight bits of the 12-bit extended response code.
+    pub extended_rcode: u8,
+    /// The EDNS version; only version 0 is defined.
+    pub version: u8,
+    /// The DNSSEC OK (DO) flag: the sender wants DNSSEC records (RFC 3225).
+    pub dnssec_ok: bool,
+    /// The options carried in the record data.
+    pub options: Vec<EdnsOption>,
+}
 
-        Ok(ResourceRecord {
-            name,
-            rtype: rtype.unwrap_or(QueryType::A), // Default for display, data is in RData::Other
-            rclass,
+/// The DO bit within the TTL field of an OPT record.
+const EDNS_DO_FLAG: u32 = 0x8000;
+
+impl Edns {
+    /// Creates EDNS version 0 parameters advertising `udp_payload_size`, with no
+    /// flags or options.
+    ///
+    /// Payload sizes below 512 bytes are treated as 512 by receivers (RFC 6891
+    /// section 6.2.3).
+    pub fn new(udp_payload_size: u16) -> Self {
+        Edns {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Builds the OPT pseudo-record that carries these parameters, owned by the root.
+    pub fn to_record(&self) -> ResourceRecord {
+        let mut ttl = (self.extended_rcode as u32) << 24 | (self.version as u32) << 16;
+        if self.dnssec_ok {
+            ttl |= EDNS_DO_FLAG;
+        }
+        ResourceRecord {
+            name: DnsName::root(),
+            rtype: QueryType::OPT,
+            rclass: self.udp_payload_size,
             ttl,
-            data: rdata,
+            data: RData::OPT(self.options.clone()),
+        }
+    }
+
+    /// Reads EDNS parameters from an OPT pseudo-record.
+    ///
+    /// # Returns
+    ///
+    /// The parameters, or `None` if `record` is not an OPT record.
+    pub fn from_record(record: &ResourceRecord) -> Option<Self> {
+        let RData::OPT(options) = &record.data else {
+            return None;
+        };
+        Some(Edns {
+            udp_payload_size: record.rclass,
+            extended_rcode: (record.ttl >> 24) as u8,
+            version: (record.ttl >> 16) as u8,
+            dnssec_ok: record.ttl & EDNS_DO_FLAG != 0,
+            options: options.clone(),
         })
     }
 }
 
+impl fmt::Display for Edns {
+    /// Formats the parameters in the style of `dig`, e.g. `version: 0, flags: do; udp: 1232`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "version: {}, flags:", self.version)?;
+        if self.dnssec_ok {
+            write!(f, " do")?;
+        }
+        write!(f, "; udp: {}", self.udp_payload_size)?;
+        for option in &self.options {
+            write!(f, "; option {}", option)?;
+        }
+        Ok(())
+    }
+}
+
 /// Represents a complete DNS message containing header and all sections.
 ///
 /// A DNS message consists of a header followed by four sections: questions, answers,
@@ -1046,13 +3355,13 @@ impl ResourceRecord {
 /// message.header.question_count = 1;
 ///
 /// let question = DnsQuestion {
-///     name: "example.com".to_string(),
+///     name: "example.com".parse().unwrap(),
 ///     qtype: QueryType::A,
 ///     qclass: 1,
 /// };
 /// message.questions.push(question);
 /// ```
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DnsMessage {
     /// The DNS message header containing IDs, flags, and section counts.
     pub header: DnsHeader,
@@ -1066,6 +3375,12 @@ pub struct DnsMessage {
     pub additionals: Vec<ResourceRecord>,
 }
 
+impl Default for DnsMessage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl DnsMessage {
     /// Creates a new empty DNS message with default header values.
     ///
@@ -1094,9 +3409,10 @@ impl DnsMessage {
 
     /// Serializes the DNS message into bytes and appends them to the provided buffer.
     ///
-    /// Packs the complete DNS message including header and questions into DNS wire format.
-    /// Currently only questions are serialized - resource record serialization is not
-    /// implemented as this library primarily sends queries rather than responses.
+    /// Packs the complete DNS message into DNS wire format: the header, then every
+    /// question and resource record. Names that repeat a name or suffix written
+    /// earlier in the message are compressed into pointers (RFC 1035 section 4.1.4).
+    /// The header is written as is, so its section counts must match the sections.
     ///
     /// # Arguments
     ///
@@ -1105,7 +3421,7 @@ impl DnsMessage {
     /// # Returns
     ///
     /// * `Ok(())` - Successfully serialized the message
-    /// * `Err(String)` - If question serialization fails (e.g., invalid domain name)
+    /// * `Err(String)` - If serialization fails (e.g., invalid domain name or oversized record data)
     ///
     /// # Examples
     ///
@@ -1117,7 +3433,7 @@ impl DnsMessage {
     /// message.header.question_count = 1;
     ///
     /// let question = DnsQuestion {
-    ///     name: "example.com".to_string(),
+    ///     name: "example.com".parse().unwrap(),
     ///     qtype: QueryType::A,
     ///     qclass: 1,
     /// };
@@ -1127,20 +3443,49 @@ impl DnsMessage {
     /// message.pack(&mut buffer).unwrap();
     /// // Buffer now contains the complete DNS query packet
     /// ```
-    ///
-    /// # Note
-    ///
-    /// Resource records (answers, authorities, additionals) are not serialized by this
-    /// method as they are typically only present in DNS responses, not queries.
     pub fn pack(&self, buffer: &mut Vec<u8>) -> Result<(), String> {
+        let mut compressor = Some(NameCompressor::new(buffer.len()));
         self.header.pack(buffer);
         for question in &self.questions {
-            question.pack(buffer)?;
+            question.pack_with(buffer, &mut compressor)?;
+        }
+        for record in self
+            .answers
+            .iter()
+            .chain(&self.authorities)
+            .chain(&self.additionals)
+        {
+            record.pack_with(buffer, &mut compressor)?;
         }
-        // Packing resource records is not implemented as we only send queries.
         Ok(())
     }
 
+    /// Returns the EDNS parameters of the message, if its additional section has an
+    /// OPT pseudo-record.
+    pub fn edns(&self) -> Option<Edns> {
+        self.additionals.iter().find_map(Edns::from_record)
+    }
+
+    /// Checks whether the message is a server declining an ANY query (RFC 8482).
+    ///
+    /// Rather than returning every record of a name, such servers answer an ANY
+    /// query with a single synthesized HINFO record whose CPU field is `RFC8482`.
+    ///
+    /// # Returns
+    ///
+    /// * `true` - If the question is for type ANY and the only answers are such
+    ///   HINFO records
+    /// * `false` - Otherwise, including for ANY queries answered in full
+    pub fn is_minimal_any_response(&self) -> bool {
+        self.questions
+            .iter()
+            .any(|question| question.qtype == QueryType::ANY)
+            && !self.answers.is_empty()
+            && self.answers.iter().all(|record| {
+                matches!(&record.data, RData::HINFO { cpu, .. } if cpu.eq_ignore_ascii_case("RFC8482"))
+            })
+    }
+
     /// Deserializes a complete DNS message from a byte slice.
     ///
     /// Parses a full DNS packet including header and all sections (questions, answers,
@@ -1193,6 +3538,39 @@ impl DnsMessage {
     /// The function pre-allocates vectors based on the counts in the DNS header
     /// to minimize memory allocations during parsing.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self, std::io::Error> {
+        Self::parse_prefix(bytes).map(|(message, _)| message)
+    }
+
+    /// Deserializes a DNS message from the start of a byte slice, reporting how
+    /// many bytes it occupied.
+    ///
+    /// This behaves like [`DnsMessage::from_bytes`], but also returns the offset just
+    /// past the last record described by the header counts. A value smaller than
+    /// `bytes.len()` means the datagram carries trailing data that is not part of any
+    /// section, which well-behaved servers never send.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - A byte slice starting with a DNS message in wire format
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((DnsMessage, usize))` - The parsed message and the number of bytes consumed
+    /// * `Err(std::io::Error)` - Under the same conditions as [`DnsMessage::from_bytes`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::dns::DnsMessage;
+    ///
+    /// // A bare header followed by two stray bytes.
+    /// let data = [0x12, 0x34, 0x81, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0xDE, 0xAD];
+    ///
+    /// let (message, consumed) = DnsMessage::parse_prefix(&data).unwrap();
+    /// assert_eq!(message.header.id, 0x1234);
+    /// assert_eq!(consumed, 12);
+    /// ```
+    pub fn parse_prefix(bytes: &[u8]) -> Result<(Self, usize), std::io::Error> {
         let mut cursor = Cursor::new(bytes);
         let header = DnsHeader::from_bytes(&mut cursor)?;
 
@@ -1216,13 +3594,17 @@ impl DnsMessage {
             additionals.push(ResourceRecord::from_bytes(&mut cursor)?);
         }
 
-        Ok(DnsMessage {
-            header,
-            questions,
-            answers,
-            authorities,
-            additionals,
-        })
+        let consumed = cursor.position() as usize;
+        Ok((
+            DnsMessage {
+                header,
+                questions,
+                answers,
+                authorities,
+                additionals,
+            },
+            consumed,
+        ))
     }
 }
 
@@ -1230,7 +3612,8 @@ impl DnsMessage {
 ///
 /// Converts a human-readable domain name like "www.google.com" into the DNS wire format
 /// where each label is prefixed by its length: `\x03www\x06google\x03com\x00`.
-/// The encoded name is terminated with a null byte (0x00).
+/// The encoded name is terminated with a null byte (0x00). Labels with non-ASCII
+/// characters, such as `bücher`, are written as their A-labels (`xn--bcher-kva`).
 ///
 /// # Arguments
 ///
@@ -1240,7 +3623,8 @@ impl DnsMessage {
 /// # Returns
 ///
 /// * `Ok(())` - Successfully encoded the domain name
-/// * `Err(String)` - If any label exceeds the maximum length of 63 characters
+/// * `Err(String)` - If any label is empty, exceeds the maximum length of 63 characters
+///   or cannot be encoded as an A-label
 ///
 /// # Examples
 ///
@@ -1278,27 +3662,447 @@ impl DnsMessage {
 ///
 /// # Domain Name Format
 ///
-/// The DNS label format stores each label (domain component) as:
-/// 1. A length byte (0-63) indicating how many characters follow
-/// 2. The label characters in ASCII
-/// 3. Repeat for each label
-/// 4. A null byte (0x00) to terminate the name
-pub fn pack_domain_name(buffer: &mut Vec<u8>, domain: &str) -> Result<(), String> {
-    for label in domain.split('.') {
-        let len = label.len();
-        if len > 63 {
-            return Err(format!(
-                "Label '{}' exceeds maximum length of 63 characters",
-                label
-            ));
+/// The DNS label format stores each label (domain component) as:
+/// 1. A length byte (0-63) indicating how many characters follow
+/// 2. The label characters in ASCII
+/// 3. Repeat for each label
+/// 4. A null byte (0x00) to terminate the name
+pub fn pack_domain_name(buffer: &mut Vec<u8>, domain: &str) -> Result<(), String> {
+    for label in name_labels(domain)? {
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label.as_bytes());
+    }
+
+    buffer.push(0);
+    Ok(())
+}
+
+/// Splits a domain name into its labels, checking that each can be encoded.
+///
+/// A trailing dot is accepted, and the root name (`""` or `"."`) has no labels.
+fn name_labels(domain: &str) -> Result<Vec<Cow<'_, str>>, String> {
+    let domain = domain.strip_suffix('.').unwrap_or(domain);
+    if domain.is_empty() {
+        return Ok(Vec::new());
+    }
+    domain
+        .split('.')
+        // Labels with non-ASCII characters travel as A-labels (RFC 5890).
+        .map(|label| match to_ascii_label(label)? {
+            label if label.is_empty() => {
+                Err(format!("Domain name '{}' contains an empty label", domain))
+            }
+            label if label.len() > MAX_LABEL_LENGTH => Err(format!(
+                "Label '{}' exceeds maximum length of {} characters",
+                label, MAX_LABEL_LENGTH
+            )),
+            label => Ok(label),
+        })
+        .collect()
+}
+
+/// Remembers where names were written in a message so that later occurrences of
+/// the same name or suffix can be replaced by a compression pointer.
+///
+/// Names are matched exactly rather than case-insensitively, so that unpacking a
+/// compressed message reproduces every name as it was spelled.
+struct NameCompressor {
+    /// The position of the start of the message in the buffer.
+    start: usize,
+    /// The message offset of each name suffix written so far.
+    offsets: HashMap<String, u16>,
+}
+
+impl NameCompressor {
+    /// Creates a compressor for a message starting at `start` in the buffer.
+    fn new(start: usize) -> Self {
+        NameCompressor {
+            start,
+            offsets: HashMap::new(),
+        }
+    }
+
+    /// Writes `domain`, pointing to an earlier copy of its longest known suffix.
+    fn pack(&mut self, buffer: &mut Vec<u8>, domain: &str) -> Result<(), String> {
+        let labels = name_labels(domain)?;
+        for i in 0..labels.len() {
+            let suffix = labels[i..].join(".");
+            if let Some(offset) = self.offsets.get(&suffix) {
+                buffer.extend_from_slice(&(0xC000 | offset).to_be_bytes());
+                return Ok(());
+            }
+            // Pointers have 14 bits, so only the start of a message can be pointed to.
+            let offset = buffer.len() - self.start;
+            if offset < 0x4000 {
+                self.offsets.insert(suffix, offset as u16);
+            }
+            buffer.push(labels[i].len() as u8);
+            buffer.extend_from_slice(labels[i].as_bytes());
+        }
+        buffer.push(0);
+        Ok(())
+    }
+}
+
+/// Returns the canonical form of a name (RFC 4034 section 6.2): lowercase, with no
+/// trailing dot. The root name is the empty string.
+///
+/// Only ASCII letters are lowercased, as DNS compares names case-insensitively in
+/// ASCII only.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::canonical_name;
+///
+/// assert_eq!(canonical_name("WWW.Example.COM."), "www.example.com");
+/// ```
+pub fn canonical_name(name: &str) -> String {
+    name.strip_suffix('.').unwrap_or(name).to_ascii_lowercase()
+}
+
+/// Returns `name` for display, writing the root name as `.`.
+pub(crate) fn display_name(name: &str) -> &str {
+    if name.is_empty() { "." } else { name }
+}
+
+/// Quotes a character-string for display as in a zone file, escaping `"` and `\`.
+fn quoted(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Returns the mnemonic of a TSIG error code (RFC 8945 section 3), or the number
+/// for codes without one.
+pub(crate) fn tsig_error_name(code: u16) -> String {
+    match code {
+        0 => "NOERROR".to_string(),
+        16 => "BADSIG".to_string(),
+        17 => "BADKEY".to_string(),
+        18 => "BADTIME".to_string(),
+        22 => "BADTRUNC".to_string(),
+        code => code.to_string(),
+    }
+}
+
+/// Formats bytes as lowercase hex.
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Formats bytes in padded base64 (RFC 4648 section 4), as zone files write keys
+/// and signatures.
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0u32, |group, (i, byte)| {
+            group | u32::from(*byte) << (16 - 8 * i)
+        });
+        // n bytes fill n + 1 characters; the rest of the four are padding.
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(group >> (18 - 6 * i)) as usize & 63] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Decodes padded base64 (RFC 4648 section 4), ignoring whitespace, as in zone files
+/// and key files.
+pub(crate) fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let symbols: Vec<u8> = text
+        .bytes()
+        .filter(|byte| !byte.is_ascii_whitespace())
+        .collect();
+    if !symbols.len().is_multiple_of(4) {
+        return Err(format!(
+            "Invalid base64 '{}': length is not a multiple of 4",
+            text
+        ));
+    }
+    let mut decoded = Vec::with_capacity(symbols.len() / 4 * 3);
+    for (index, chunk) in symbols.chunks(4).enumerate() {
+        let padding = chunk.iter().rev().take_while(|byte| **byte == b'=').count();
+        // Padding may only end the last group, and fills at most two characters.
+        if padding > 2 || (padding > 0 && index + 1 < symbols.len() / 4) {
+            return Err(format!("Invalid base64 '{}': misplaced padding", text));
+        }
+        let mut group = 0u32;
+        for byte in &chunk[..4 - padding] {
+            let value = match byte {
+                b'A'..=b'Z' => byte - b'A',
+                b'a'..=b'z' => byte - b'a' + 26,
+                b'0'..=b'9' => byte - b'0' + 52,
+                b'+' => 62,
+                b'/' => 63,
+                _ => {
+                    return Err(format!(
+                        "Invalid base64 '{}': unexpected '{}'",
+                        text, *byte as char
+                    ));
+                }
+            };
+            group = group << 6 | u32::from(value);
+        }
+        group <<= 6 * padding;
+        decoded.extend_from_slice(&group.to_be_bytes()[1..4 - padding]);
+    }
+    Ok(decoded)
+}
+
+/// Formats bytes in lowercase base32 with the extended hex alphabet and no
+/// padding (RFC 4648 section 7), as NSEC3 hashes are written (RFC 5155 section 3.3).
+pub(crate) fn base32hex(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 32] = b"0123456789abcdefghijklmnopqrstuv";
+    let mut encoded = String::new();
+    let mut bits = 0u32;
+    let mut count = 0;
+    for byte in bytes {
+        bits = (bits << 8 | u32::from(*byte)) & 0xffff;
+        count += 8;
+        while count >= 5 {
+            count -= 5;
+            encoded.push(ALPHABET[(bits >> count) as usize & 31] as char);
+        }
+    }
+    if count > 0 {
+        encoded.push(ALPHABET[(bits << (5 - count)) as usize & 31] as char);
+    }
+    encoded
+}
+
+/// Formats an RRSIG inception or expiration time as `YYYYMMDDHHmmSS` in UTC
+/// (RFC 4034 section 3.2).
+fn signature_time(seconds: u32) -> String {
+    format_timestamp(UNIX_EPOCH + Duration::from_secs(seconds.into()))
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect()
+}
+
+/// Reads the rest of a record's data, which ends at `end`.
+fn read_to_end(cursor: &mut Cursor<&[u8]>, end: usize) -> Result<Vec<u8>, std::io::Error> {
+    let length = end.checked_sub(cursor.position() as usize).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            "Record data is longer than its RDLENGTH",
+        )
+    })?;
+    let mut data = vec![0; length];
+    cursor.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// Reads the type bitmaps of NSEC or NSEC3 data (RFC 4034 section 4.1.2), which
+/// run to `end`.
+///
+/// Each window covers 256 type codes: a window number, a bitmap length of 1 to 32
+/// octets, and the bitmap, whose bits stand for the codes of the window in order.
+fn read_type_bitmaps(
+    cursor: &mut Cursor<&[u8]>,
+    end: usize,
+) -> Result<Vec<QueryType>, std::io::Error> {
+    let mut types = Vec::new();
+    while (cursor.position() as usize) < end {
+        let mut header = [0u8; 2];
+        cursor.read_exact(&mut header)?;
+        let [window, length] = header;
+        if !(1..=32).contains(&length) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("Type bitmap of window {} has length {}", window, length),
+            ));
+        }
+        let mut bitmap = vec![0u8; length as usize];
+        cursor.read_exact(&mut bitmap)?;
+        for (index, octet) in bitmap.iter().enumerate() {
+            for bit in 0..8 {
+                if octet & (0x80 >> bit) != 0 {
+                    let code = u16::from(window) << 8 | (index * 8 + bit) as u16;
+                    types.push(QueryType::from(code));
+                }
+            }
+        }
+    }
+    Ok(types)
+}
+
+/// Writes `types` as NSEC or NSEC3 type bitmaps, in increasing order of code.
+fn pack_type_bitmaps(buffer: &mut Vec<u8>, types: &[QueryType]) {
+    let mut codes: Vec<u16> = types.iter().map(|rtype| u16::from(*rtype)).collect();
+    codes.sort_unstable();
+    codes.dedup();
+    for window in codes.chunk_by(|a, b| a >> 8 == b >> 8) {
+        let mut bitmap = [0u8; 32];
+        for code in window {
+            let low = usize::from(code & 0xff);
+            bitmap[low / 8] |= 0x80 >> (low % 8);
+        }
+        // Trailing octets with no bits set are left out.
+        let length = usize::from(window[window.len() - 1] & 0xff) / 8 + 1;
+        buffer.push((window[0] >> 8) as u8);
+        buffer.push(length as u8);
+        buffer.extend_from_slice(&bitmap[..length]);
+    }
+}
+
+/// Reads a <character-string>: a length octet followed by that many bytes
+/// (RFC 1035 section 3.3).
+fn read_character_string(cursor: &mut Cursor<&[u8]>) -> Result<String, std::io::Error> {
+    let mut length = [0u8; 1];
+    cursor.read_exact(&mut length)?;
+    let mut bytes = vec![0u8; length[0] as usize];
+    cursor.read_exact(&mut bytes)?;
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// Writes `text` as a <character-string>, which holds at most 255 bytes.
+fn pack_character_string(buffer: &mut Vec<u8>, text: &str) -> Result<(), String> {
+    let length = u8::try_from(text.len())
+        .map_err(|_| format!("Character string '{}' exceeds 255 bytes", text))?;
+    buffer.push(length);
+    buffer.extend_from_slice(text.as_bytes());
+    Ok(())
+}
+
+/// Compares two names in canonical DNS name order (RFC 4034 section 6.1).
+///
+/// Names are compared label by label starting from the rightmost label, ignoring
+/// ASCII case, and a name sorts before every name below it. This is the order of
+/// names in a zone file sorted for DNSSEC signing and of NSEC chains.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::compare_canonical_names;
+/// use std::cmp::Ordering;
+///
+/// assert_eq!(compare_canonical_names("example", "a.example"), Ordering::Less);
+/// assert_eq!(compare_canonical_names("Z.a.example", "zABC.a.EXAMPLE"), Ordering::Less);
+/// assert_eq!(compare_canonical_names("EXAMPLE.", "example"), Ordering::Equal);
+/// ```
+pub fn compare_canonical_names(a: &str, b: &str) -> Ordering {
+    let a = canonical_name(a);
+    let b = canonical_name(b);
+    let labels = |name: &str| -> Vec<Vec<u8>> {
+        if name.is_empty() {
+            return Vec::new();
+        }
+        name.rsplit('.')
+            .map(|label| label.as_bytes().to_vec())
+            .collect()
+    };
+    labels(&a).cmp(&labels(&b))
+}
+
+/// Puts an RRset in canonical order (RFC 4034 section 6.3), removing duplicates.
+///
+/// Records are ordered by their canonical record data as unsigned octet strings.
+/// Records whose canonical data is identical are duplicates and only the first is
+/// kept, whatever their TTLs.
+///
+/// # Errors
+///
+/// Returns an error if the data of a record cannot be encoded.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{QueryType, RData, ResourceRecord, sort_rrset_canonical};
+/// use std::net::Ipv4Addr;
+///
+/// let record = |last_octet| ResourceRecord {
+///     name: "example.com".parse().unwrap(),
+///     rtype: QueryType::A,
+///     rclass: 1,
+///     ttl: 300,
+///     data: RData::A(Ipv4Addr::new(192, 0, 2, last_octet)),
+/// };
+///
+/// let mut rrset = vec![record(20), record(3), record(20)];
+/// sort_rrset_canonical(&mut rrset).unwrap();
+/// assert_eq!(rrset, vec![record(3), record(20)]);
+/// ```
+pub fn sort_rrset_canonical(records: &mut Vec<ResourceRecord>) -> Result<(), String> {
+    let mut keyed = records
+        .drain(..)
+        .map(|record| Ok((record.canonical_rdata()?, record)))
+        .collect::<Result<Vec<_>, String>>()?;
+    keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+    keyed.dedup_by(|(a, _), (b, _)| a == b);
+    records.extend(keyed.into_iter().map(|(_, record)| record));
+    Ok(())
+}
+
+/// Serializes an RRset in canonical wire format (RFC 4034 sections 6.2 and 6.3):
+/// every record in canonical form, uncompressed, in canonical order, without
+/// duplicates.
+///
+/// This is the byte sequence a DNSSEC signature covers and a zone digest hashes.
+/// Signatures are computed over the TTL the RRSIG record carries as the original
+/// TTL rather than the decremented TTL a cache hands out, so that TTL can be given
+/// in `original_ttl` to replace the TTL of every record.
+///
+/// # Arguments
+///
+/// * `records` - The records of one RRset, in any order
+/// * `original_ttl` - The TTL to write for every record, or `None` to keep each
+///   record's own TTL
+///
+/// # Returns
+///
+/// * `Ok(Vec<u8>)` - The canonical wire form of the RRset
+/// * `Err(String)` - If a record cannot be encoded
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{QueryType, RData, ResourceRecord, pack_canonical_rrset};
+/// use std::net::Ipv4Addr;
+///
+/// let record = |name: &str, ttl| ResourceRecord {
+///     name: name.parse().unwrap(),
+///     rtype: QueryType::A,
+///     rclass: 1,
+///     ttl,
+///     data: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+/// };
+///
+/// // Spelling and remaining TTL do not change the canonical form.
+/// let from_cache = pack_canonical_rrset(&[record("Example.COM.", 17)], Some(3600)).unwrap();
+/// let from_zone = pack_canonical_rrset(&[record("example.com", 3600)], None).unwrap();
+/// assert_eq!(from_cache, from_zone);
+/// ```
+pub fn pack_canonical_rrset(
+    records: &[ResourceRecord],
+    original_ttl: Option<u32>,
+) -> Result<Vec<u8>, String> {
+    let mut records = records.to_vec();
+    sort_rrset_canonical(&mut records)?;
+    let mut buffer = Vec::new();
+    for record in records {
+        ResourceRecord {
+            ttl: original_ttl.unwrap_or(record.ttl),
+            ..record.to_canonical()
         }
-
-        buffer.push(len as u8);
-        buffer.extend_from_slice(label.as_bytes());
+        .pack(&mut buffer)?;
     }
+    Ok(buffer)
+}
 
-    buffer.push(0);
-    Ok(())
+/// Writes a name through the compressor if there is one, or uncompressed otherwise.
+fn pack_name(
+    buffer: &mut Vec<u8>,
+    domain: &str,
+    compressor: &mut Option<NameCompressor>,
+) -> Result<(), String> {
+    match compressor {
+        Some(compressor) => compressor.pack(buffer, domain),
+        None => pack_domain_name(buffer, domain),
+    }
 }
 
 /// Decodes a domain name from DNS wire format, handling compression pointers.
@@ -1375,7 +4179,7 @@ pub fn pack_domain_name(buffer: &mut Vec<u8>, domain: &str) -> Result<(), String
 /// ];
 ///
 /// let mut cursor = Cursor::new(&data[..]);
-/// cursor.set_position(29); // Position at "www" + pointer
+/// cursor.set_position(25); // Position at "www" + pointer
 /// let domain = unpack_domain_name(&mut cursor).unwrap();
 /// assert_eq!(domain, "www.example.com");
 /// ```
@@ -1438,9 +4242,107 @@ pub fn unpack_domain_name(cursor: &mut Cursor<&[u8]>) -> Result<String, std::io:
     Ok(parts.join("."))
 }
 
+/// Returns the labels of an IP address in reverse order, as used by reverse-mapping zones.
+///
+/// IPv4 addresses are split into their four octets and IPv6 addresses into their 32
+/// nibbles, least significant first. The result has no zone suffix, so callers can
+/// append `in-addr.arpa`, `ip6.arpa`, or the suffix of any other service that indexes
+/// data by address (such as DNS-based blocklists or IP-to-ASN mappings).
+///
+/// # Arguments
+///
+/// * `addr` - The IPv4 or IPv6 address to reverse
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::reverse_address_labels;
+/// use std::net::IpAddr;
+///
+/// let v4: IpAddr = "192.0.2.1".parse().unwrap();
+/// assert_eq!(reverse_address_labels(&v4), "1.2.0.192");
+///
+/// let v6: IpAddr = "2001:db8::1".parse().unwrap();
+/// assert!(reverse_address_labels(&v6).starts_with("1.0.0.0."));
+/// assert!(reverse_address_labels(&v6).ends_with(".8.b.d.0.1.0.0.2"));
+/// ```
+pub fn reverse_address_labels(addr: &IpAddr) -> String {
+    match addr {
+        IpAddr::V4(v4) => {
+            let octets = v4.octets();
+            format!("{}.{}.{}.{}", octets[3], octets[2], octets[1], octets[0])
+        }
+        IpAddr::V6(v6) => v6
+            .octets()
+            .iter()
+            .rev()
+            .flat_map(|byte| [byte & 0x0F, byte >> 4])
+            .map(|nibble| format!("{:x}", nibble))
+            .collect::<Vec<_>>()
+            .join("."),
+    }
+}
+
+/// Returns the reverse-mapping name to query for the PTR record of an address.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::reverse_lookup_name;
+/// use std::net::IpAddr;
+///
+/// let v4: IpAddr = "192.0.2.1".parse().unwrap();
+/// assert_eq!(reverse_lookup_name(&v4), "1.2.0.192.in-addr.arpa");
+///
+/// let v6: IpAddr = "2001:db8::1".parse().unwrap();
+/// assert!(reverse_lookup_name(&v6).ends_with(".8.b.d.0.1.0.0.2.ip6.arpa"));
+/// ```
+pub fn reverse_lookup_name(addr: &IpAddr) -> String {
+    let zone = match addr {
+        IpAddr::V4(_) => "in-addr.arpa",
+        IpAddr::V6(_) => "ip6.arpa",
+    };
+    format!("{}.{}", reverse_address_labels(addr), zone)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testutil::a_record;
+
+    #[test]
+    fn test_header_flags() {
+        let mut header = DnsHeader::new();
+        header.set_recursion_desired(true);
+        header.set_checking_disabled(true);
+        assert_eq!(header.flags, 0x0110);
+
+        header.set_response(true);
+        header.set_recursion_available(true);
+        header.set_authentic_data(true);
+        header.set_response_code(ResponseCode::Refused);
+        assert_eq!(header.flags, 0x81B5);
+        assert!(header.is_response() && header.authentic_data());
+        assert!(!header.authoritative() && !header.truncated() && !header.z());
+        assert_eq!(header.get_response_code(), ResponseCode::Refused);
+
+        // Each setter only touches its own bits.
+        header.set_checking_disabled(false);
+        header.set_opcode(Opcode::Update);
+        header.set_response_code(ResponseCode::NoError);
+        assert_eq!(header.flags, 0xA9A0);
+        assert_eq!(header.opcode(), Opcode::Update);
+        assert_eq!(u8::from(Opcode::from(9)), 9);
+
+        header.id = 48211;
+        header.question_count = 1;
+        header.answer_count = 2;
+        assert_eq!(
+            header.to_string(),
+            "id 48211, opcode UPDATE, status NOERROR, flags: qr rd ra ad; \
+             QUERY: 1, ANSWER: 2, AUTHORITY: 0, ADDITIONAL: 0"
+        );
+    }
 
     #[test]
     fn test_pack_domain_name() {
@@ -1454,6 +4356,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_dns_name_limits() {
+        // 4 labels of 61 octets take 4 * 62 + 1 = 249 octets; one more label of
+        // up to 5 octets still fits in 255.
+        let long = vec!["a".repeat(61); 4].join(".");
+        assert_eq!(
+            DnsName::new(format!("abcde.{}", long))
+                .unwrap()
+                .wire_length(),
+            MAX_NAME_LENGTH
+        );
+        assert!(DnsName::new(format!("abcdef.{}", long)).is_err());
+        assert!(DnsName::new("a".repeat(MAX_LABEL_LENGTH)).is_ok());
+        assert!(DnsName::new("mail..example").is_err());
+        // Unicode labels count at the length of their A-labels.
+        assert_eq!(DnsName::new("bücher.example").unwrap().wire_length(), 23);
+
+        let root: DnsName = ".".parse().unwrap();
+        assert!(root.is_root());
+        assert_eq!(root, DnsName::root());
+        assert_eq!(root.wire_length(), 1);
+
+        let name: DnsName = "Mail.Example.COM.".parse().unwrap();
+        assert_eq!(name, DnsName::new("mail.example.com").unwrap());
+        assert_eq!(name.to_canonical().as_str(), "mail.example.com");
+        assert_eq!(name.labels().next_back(), Some("COM"));
+        assert!(name.is_subdomain_of(&root));
+        assert!(!name.is_subdomain_of(&"ample.com".parse().unwrap()));
+        let names: std::collections::HashSet<DnsName> =
+            [name, "mail.example.com".parse().unwrap()].into();
+        assert_eq!(names.len(), 1);
+    }
+
     #[test]
     fn test_unpack_simple_domain_name() {
         let data = vec![
@@ -1542,4 +4477,858 @@ mod tests {
             _ => panic!("Expected A record"),
         }
     }
+
+    #[test]
+    fn test_reverse_address_labels() {
+        let v4: IpAddr = "142.250.187.206".parse().unwrap();
+        assert_eq!(reverse_address_labels(&v4), "206.187.250.142");
+
+        let v6: IpAddr = "2001:db8::567:89ab".parse().unwrap();
+        assert_eq!(
+            reverse_address_labels(&v6),
+            "b.a.9.8.7.6.5.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2"
+        );
+    }
+
+    #[test]
+    fn test_parse_ptr_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "1.2.0.192.in-addr.arpa").unwrap();
+        packet.extend_from_slice(&[0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x12]);
+        pack_domain_name(&mut packet, "host.example.com").unwrap();
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::PTR);
+        assert_eq!(record.ttl, 3600);
+        assert_eq!(record.get_ptr(), Some("host.example.com"));
+        assert_eq!(cursor.position() as usize, packet.len());
+    }
+
+    #[test]
+    fn test_parse_ns_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x02, 0x00, 0x01, 0x00, 0x01, 0x51, 0x80, 0x00, 0x11]);
+        pack_domain_name(&mut packet, "ns1.example.com").unwrap();
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::NS);
+        assert!(matches!(record.data, RData::NS(ref name) if name == "ns1.example.com"));
+        assert_eq!(
+            record.to_string().split_whitespace().last(),
+            Some("ns1.example.com")
+        );
+    }
+
+    #[test]
+    fn test_parse_soa_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x06, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x3d]);
+        pack_domain_name(&mut packet, "ns1.example.com").unwrap();
+        pack_domain_name(&mut packet, "hostmaster.example.com").unwrap();
+        for value in [2024010101u32, 7200, 3600, 1209600, 300] {
+            packet.extend_from_slice(&value.to_be_bytes());
+        }
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::SOA);
+        assert!(matches!(
+            record.data,
+            RData::SOA { ref rname, serial: 2024010101, minimum: 300, .. }
+                if rname == "hostmaster.example.com"
+        ));
+        assert_eq!(
+            record.data.to_string(),
+            "SOA ns1.example.com hostmaster.example.com 2024010101 7200 3600 1209600 300"
+        );
+        assert_eq!(cursor.position() as usize, packet.len());
+    }
+
+    #[test]
+    fn test_parse_srv_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "_ldap._tcp.example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x21, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x17]);
+        packet.extend_from_slice(&[0x00, 0x0a, 0x00, 0x3c, 0x01, 0x85]);
+        pack_domain_name(&mut packet, "dc1.example.com").unwrap();
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::SRV);
+        assert_eq!(record.data.to_string(), "SRV 10 60 389 dc1.example.com");
+        assert_eq!(cursor.position() as usize, packet.len());
+
+        // The target is written out in full even when it repeats an earlier name.
+        let mut message = DnsMessage::new();
+        message.header.answer_count = 2;
+        message
+            .answers
+            .push(a_record("dc1.example.com", [192, 0, 2, 1], 60));
+        message.answers.push(record.clone());
+        let mut buffer = Vec::new();
+        message.pack(&mut buffer).unwrap();
+        assert!(buffer.ends_with(&packet[packet.len() - 17..]));
+        assert_eq!(DnsMessage::from_bytes(&buffer).unwrap().answers[1], record);
+    }
+
+    #[test]
+    fn test_parse_naptr_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "4.3.2.1.5.5.5.0.0.8.1.e164.arpa").unwrap();
+        packet.extend_from_slice(&[0x00, 0x23, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x2b]);
+        packet.extend_from_slice(&[0x00, 0x64, 0x00, 0x0a]);
+        for text in ["U", "E2U+sip", "!^.*$!sip:info@example.com!"] {
+            packet.push(text.len() as u8);
+            packet.extend_from_slice(text.as_bytes());
+        }
+        packet.push(0); // The root as replacement
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::NAPTR);
+        assert_eq!(
+            record.data.to_string(),
+            r#"NAPTR 100 10 "U" "E2U+sip" "!^.*$!sip:info@example.com!" ."#
+        );
+        assert_eq!(cursor.position() as usize, packet.len());
+
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+    }
+
+    #[test]
+    fn test_parse_sshfp_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "host.example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x2c, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x22]);
+        packet.extend_from_slice(&[0x04, 0x02]); // Ed25519, SHA-256
+        packet.extend((0..32).map(|byte| byte * 8));
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::SSHFP);
+        assert_eq!(
+            record.data.to_string(),
+            "SSHFP 4 2 0008101820283038404850586068707880889098a0a8b0b8c0c8d0d8e0e8f0f8"
+        );
+        assert_eq!(cursor.position() as usize, packet.len());
+
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+    }
+
+    #[test]
+    fn test_parse_dnssec_records() {
+        // The DNSKEY record of RFC 4034 section 5.4.
+        let public_key: Vec<u8> = concat!(
+            "01039e8a247418e318903b215a848acfd5f37f026bd4062db26c774c690968d5",
+            "d56df8bfda91e6f36d9a279888f41333357c5e6029990d10fdf5663062a51276",
+            "3326980a615ddbf17a05ddfcce7e5fb3abcca05a31b0957452d4521e83870789",
+            "063115bf97f6c308ccf57cdc9ce7fe10f6ed1bd0cc0660038c50dcdb0feb963c",
+            "2f17"
+        )
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+        .collect();
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "dskey.example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x30, 0x00, 0x01, 0x00, 0x01, 0x51, 0x80, 0x00, 0x86]);
+        packet.extend_from_slice(&[0x01, 0x00, 0x03, 0x05]);
+        packet.extend_from_slice(&public_key);
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(
+            record.data.to_string(),
+            concat!(
+                "DNSKEY 256 3 5 AQOeiiR0GOMYkDshWoSKz9XzfwJr1AYtsmx3TGkJaNXVbfi/2pHm822aJ5iI9BMzNXxe",
+                "YCmZDRD99WYwYqUSdjMmmAphXdvxegXd/M5+X7OrzKBaMbCVdFLUUh6DhweJBjEVv5f2wwjM9XzcnOf+",
+                "EPbtG9DMBmADjFDc2w/rljwvFw=="
+            )
+        );
+        assert_eq!(record.data.key_tag(), Some(60485));
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+
+        // The NSEC record of RFC 4034 section 4.3, with a type in a second window.
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "alfa.example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x2f, 0x00, 0x01, 0x00, 0x01, 0x51, 0x80, 0x00, 0x37]);
+        pack_domain_name(&mut packet, "host.example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x06, 0x40, 0x01, 0x00, 0x00, 0x00, 0x03]);
+        packet.extend_from_slice(&[0x04, 0x1b]);
+        packet.extend_from_slice(&[0; 26]);
+        packet.push(0x20);
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(
+            record.data.to_string(),
+            "NSEC host.example.com A MX RRSIG NSEC TYPE1234"
+        );
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+
+        // A window with an empty bitmap is malformed.
+        let first_window_length = packet.len() - 36;
+        packet[first_window_length] = 0;
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+        assert!(ResourceRecord::from_bytes(&mut cursor).is_err());
+
+        // Validity times from the RRSIG example of RFC 4034 section 3.3.
+        let rrsig = RData::RRSIG {
+            type_covered: QueryType::A,
+            algorithm: 5,
+            labels: 3,
+            original_ttl: 86400,
+            expiration: 1048354263,
+            inception: 1045762263,
+            key_tag: 2642,
+            signer_name: "example.com".to_string(),
+            signature: vec![0x01, 0x02, 0x03],
+        };
+        assert_eq!(
+            rrsig.to_string(),
+            "RRSIG A 5 3 86400 20030322173103 20030220173103 2642 example.com AQID"
+        );
+
+        // An NSEC3 record from RFC 5155 appendix A, with its types in code order.
+        let nsec3 = ResourceRecord {
+            name: "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom.example".parse().unwrap(),
+            rtype: QueryType::NSEC3,
+            rclass: 1,
+            ttl: 3600,
+            data: RData::NSEC3 {
+                hash_algorithm: 1,
+                flags: 1,
+                iterations: 12,
+                salt: vec![0xaa, 0xbb, 0xcc, 0xdd],
+                next_hashed_owner: vec![
+                    0x17, 0x4e, 0xb2, 0x40, 0x9f, 0xe2, 0x8b, 0xcb, 0x48, 0x87, 0xa1, 0x83, 0x6f,
+                    0x95, 0x7f, 0x0a, 0x84, 0x25, 0xe2, 0x7b,
+                ],
+                types: [2, 6, 15, 46, 48, 51].map(QueryType::from).to_vec(),
+            },
+        };
+        assert_eq!(
+            nsec3.data.to_string(),
+            "NSEC3 1 1 12 aabbccdd 2t7b4g4vsa5smi47k61mv5bv1a22bojr NS SOA MX RRSIG DNSKEY TYPE51"
+        );
+        let mut buffer = vec![0; 12];
+        nsec3.pack(&mut buffer).unwrap();
+        let mut cursor = Cursor::new(&buffer[..]);
+        cursor.set_position(12);
+        assert_eq!(ResourceRecord::from_bytes(&mut cursor).unwrap(), nsec3);
+    }
+
+    #[test]
+    fn test_binary_encodings() {
+        // The test vectors of RFC 4648 section 10.
+        let vectors = ["", "f", "fo", "foo", "foob", "fooba", "foobar"];
+        let encoded: Vec<String> = vectors.iter().map(|v| base64(v.as_bytes())).collect();
+        assert_eq!(
+            encoded,
+            [
+                "", "Zg==", "Zm8=", "Zm9v", "Zm9vYg==", "Zm9vYmE=", "Zm9vYmFy"
+            ]
+        );
+        for (vector, encoded) in vectors.iter().zip(&encoded) {
+            assert_eq!(decode_base64(encoded).unwrap(), vector.as_bytes());
+        }
+        assert_eq!(decode_base64("Zm9v\n YmFy").unwrap(), b"foobar");
+        for invalid in ["Zg=", "Zg=a", "Z===", "Zg==Zm9v", "Zm9v!A=="] {
+            assert!(decode_base64(invalid).is_err(), "{}", invalid);
+        }
+        let encoded: Vec<String> = vectors.iter().map(|v| base32hex(v.as_bytes())).collect();
+        assert_eq!(
+            encoded,
+            [
+                "",
+                "co",
+                "cpng",
+                "cpnmu",
+                "cpnmuog",
+                "cpnmuoj1",
+                "cpnmuoj1e8"
+            ]
+        );
+        assert_eq!(hex(&[0x00, 0xab, 0x10]), "00ab10");
+    }
+
+    #[test]
+    fn test_parse_https_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x41, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x35]);
+        packet.extend_from_slice(&[0x00, 0x01, 0x00]); // Priority 1, the owner as target
+        packet.extend_from_slice(&[0x00, 0x01, 0x00, 0x06, 0x02, b'h', b'2', 0x02, b'h', b'3']);
+        packet.extend_from_slice(&[0x00, 0x03, 0x00, 0x02, 0x20, 0xfb]);
+        packet.extend_from_slice(&[0x00, 0x04, 0x00, 0x04, 192, 0, 2, 1]);
+        packet.extend_from_slice(&[0x00, 0x05, 0x00, 0x02, b'"', 0x00]);
+        packet.extend_from_slice(&[0x00, 0x06, 0x00, 0x10]);
+        packet.extend_from_slice(&"2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::HTTPS);
+        assert_eq!(
+            record.data.to_string(),
+            r#"HTTPS 1 . alpn=h2,h3 port=8443 ipv4hint=192.0.2.1 key5="\"\000" ipv6hint=2001:db8::1"#
+        );
+        assert_eq!(cursor.position() as usize, packet.len());
+
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+
+        // An IPv4 hint must hold whole addresses.
+        let mut malformed = vec![0; 12];
+        pack_domain_name(&mut malformed, "example.com").unwrap();
+        malformed.extend_from_slice(&[0x00, 0x41, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x0a]);
+        malformed.extend_from_slice(&[0x00, 0x01, 0x00, 0x00, 0x04, 0x00, 0x03, 192, 0, 2]);
+        let mut cursor = Cursor::new(&malformed[..]);
+        cursor.set_position(12);
+        assert!(ResourceRecord::from_bytes(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_unknown_type_uses_generic_encoding() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "example.com").unwrap();
+        packet.extend_from_slice(&[0xff, 0x00, 0x00, 0x01, 0x00, 0x00, 0x01, 0x2c, 0x00, 0x03]);
+        packet.extend_from_slice(&[0x00, 0x01, 0xff]);
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::Unknown(65280));
+        assert_eq!(
+            record.get_raw_data(),
+            Some((65280, &[0x00, 0x01, 0xff][..]))
+        );
+        assert_eq!(record.data.to_string(), "TYPE65280 \\# 3 0001ff");
+        assert_eq!(record.rtype.to_string(), "TYPE65280");
+    }
+
+    #[test]
+    fn test_edns_round_trip() {
+        let edns = Edns {
+            udp_payload_size: 4096,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            options: vec![EdnsOption {
+                code: 10,
+                data: vec![0x01, 0x23],
+            }],
+        };
+        let mut message = DnsMessage::new();
+        message.header.additional_count = 1;
+        message.additionals.push(edns.to_record());
+        let mut buffer = Vec::new();
+        message.pack(&mut buffer).unwrap();
+        // Root owner, TYPE 41, CLASS 4096, TTL with the extended RCODE and DO bit.
+        assert_eq!(&buffer[12..23], &[0, 0, 41, 0x10, 0, 1, 0, 0x80, 0, 0, 6]);
+
+        let parsed = DnsMessage::from_bytes(&buffer).unwrap();
+        assert_eq!(parsed.additionals[0].rtype, QueryType::OPT);
+        assert_eq!(parsed.edns(), Some(edns));
+        assert_eq!(
+            parsed.edns().unwrap().to_string(),
+            "version: 0, flags: do; udp: 4096; option COOKIE:0123"
+        );
+        assert_eq!(DnsMessage::new().edns(), None);
+    }
+
+    #[test]
+    fn test_edns_options_display_and_parse() {
+        let unknown = EdnsOption {
+            code: 65001,
+            data: vec![0xbe, 0xef],
+        };
+        assert_eq!(unknown.name(), None);
+        assert_eq!(unknown.to_string(), "65001:beef");
+        let nsid = EdnsOption {
+            code: 3,
+            data: Vec::new(),
+        };
+        assert_eq!(nsid.to_string(), "NSID:");
+        for option in [unknown, nsid] {
+            assert_eq!(option.to_string().parse::<EdnsOption>(), Ok(option));
+        }
+        assert_eq!("client-subnet".parse::<EdnsOption>().unwrap().code, 8);
+        assert!("65536".parse::<EdnsOption>().is_err());
+        assert!("BOGUS:00".parse::<EdnsOption>().is_err());
+        assert!("10:abc".parse::<EdnsOption>().is_err());
+    }
+
+    #[test]
+    fn test_parse_hinfo_record() {
+        let mut packet = vec![0; 12];
+        pack_domain_name(&mut packet, "example.com").unwrap();
+        packet.extend_from_slice(&[0x00, 0x0d, 0x00, 0x01, 0x00, 0x00, 0x0e, 0x10, 0x00, 0x09]);
+        packet.extend_from_slice(b"\x07RFC8482\x00");
+
+        let mut cursor = Cursor::new(&packet[..]);
+        cursor.set_position(12);
+
+        let record = ResourceRecord::from_bytes(&mut cursor).unwrap();
+        assert_eq!(record.rtype, QueryType::HINFO);
+        assert_eq!(record.data.to_string(), "HINFO \"RFC8482\" \"\"");
+        assert_eq!(cursor.position() as usize, packet.len());
+
+        let mut buffer = Vec::new();
+        record.pack(&mut buffer).unwrap();
+        assert_eq!(buffer, packet[12..]);
+    }
+
+    #[test]
+    fn test_minimal_any_response() {
+        let hinfo = |cpu: &str| ResourceRecord {
+            name: "example.com".parse().unwrap(),
+            rtype: QueryType::HINFO,
+            rclass: 1,
+            ttl: 3789,
+            data: RData::HINFO {
+                cpu: cpu.to_string(),
+                os: String::new(),
+            },
+        };
+        let mut message = DnsMessage::new();
+        message.questions.push(DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::ANY,
+            qclass: 1,
+        });
+        assert!(!message.is_minimal_any_response());
+
+        message.answers.push(hinfo("RFC8482"));
+        assert!(message.is_minimal_any_response());
+
+        // A real HINFO record is part of a full answer.
+        message.answers.push(hinfo("INTEL-386"));
+        assert!(!message.is_minimal_any_response());
+
+        message.answers.truncate(1);
+        message.questions[0].qtype = QueryType::HINFO;
+        assert!(!message.is_minimal_any_response());
+    }
+
+    #[test]
+    fn test_numeric_query_type_round_trip() {
+        assert_eq!(QueryType::from_str("TYPE15").unwrap(), QueryType::MX);
+        assert_eq!(
+            QueryType::from_str("TYPE65535").unwrap(),
+            QueryType::Unknown(65535)
+        );
+        assert!(QueryType::from_str("TYPE65536").is_err());
+        assert!(QueryType::from_str("TYPE").is_err());
+
+        let question = DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::Unknown(65280),
+            qclass: 1,
+        };
+        let mut buffer = Vec::new();
+        question.pack(&mut buffer).unwrap();
+        let mut cursor = Cursor::new(&buffer[..]);
+        assert_eq!(
+            DnsQuestion::from_bytes(&mut cursor).unwrap().qtype,
+            QueryType::Unknown(65280)
+        );
+    }
+
+    #[test]
+    fn test_class_parse_and_display() {
+        assert_eq!(DnsClass::from_str("in").unwrap(), DnsClass::IN);
+        assert_eq!(DnsClass::from_str("CLASS3").unwrap(), DnsClass::CH);
+        assert_eq!(DnsClass::from_str("254").unwrap(), DnsClass::NONE);
+        assert_eq!(
+            DnsClass::from_str("CLASS42").unwrap(),
+            DnsClass::Unknown(42)
+        );
+        assert!(DnsClass::from_str("CLASS").is_err());
+        assert!(DnsClass::from_str("XX").is_err());
+        assert_eq!(DnsClass::Unknown(42).to_string(), "CLASS42");
+        assert_eq!(u16::from(DnsClass::from(3)), 3);
+
+        let record = ResourceRecord {
+            name: "version.bind".parse().unwrap(),
+            rtype: QueryType::TXT,
+            rclass: 3,
+            ttl: 0,
+            data: RData::TXT("9.18.0".to_string()),
+        };
+        let columns: Vec<String> = record
+            .to_string()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        assert_eq!(columns[2], "CH");
+    }
+
+    /// A small deterministic xorshift generator, so that failures are reproducible.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: u64) -> u64 {
+            self.next() % n
+        }
+
+        fn name(&mut self) -> String {
+            // Draw labels from a few fixed suffixes so that compression kicks in.
+            const SUFFIXES: [&str; 4] = ["", "example.com", "Example.COM", "a.b.c.d"];
+            let mut labels: Vec<String> = (0..self.below(4))
+                .map(|_| {
+                    (0..1 + self.below(63))
+                        .map(|_| b"abcxyzXZ09-_"[self.below(12) as usize] as char)
+                        .collect()
+                })
+                .collect();
+            let suffix = SUFFIXES[self.below(4) as usize];
+            if !suffix.is_empty() {
+                labels.push(suffix.to_string());
+            }
+            labels.join(".")
+        }
+
+        fn record(&mut self) -> ResourceRecord {
+            let data = match self.below(16) {
+                0 => RData::A(Ipv4Addr::from(self.next() as u32)),
+                1 => RData::AAAA(Ipv6Addr::from(
+                    ((self.next() as u128) << 64) | self.next() as u128,
+                )),
+                2 => RData::CNAME(self.name()),
+                3 => RData::PTR(self.name()),
+                4 => RData::NS(self.name()),
+                5 => RData::MX {
+                    preference: self.next() as u16,
+                    exchange: self.name(),
+                },
+                6 => RData::TXT(
+                    (0..self.below(600))
+                        .map(|_| ['a', 'é', ' ', '"', '\\'][self.below(5) as usize])
+                        .collect(),
+                ),
+                7 => RData::SOA {
+                    mname: self.name(),
+                    rname: self.name(),
+                    serial: self.next() as u32,
+                    refresh: self.next() as u32,
+                    retry: self.next() as u32,
+                    expire: self.next() as u32,
+                    minimum: self.next() as u32,
+                },
+                8 => RData::SRV {
+                    priority: self.next() as u16,
+                    weight: self.next() as u16,
+                    port: self.next() as u16,
+                    target: self.name(),
+                },
+                9 => RData::NAPTR {
+                    order: self.next() as u16,
+                    preference: self.next() as u16,
+                    flags: ["", "U", "S"][self.below(3) as usize].to_string(),
+                    services: "E2U+sip".to_string(),
+                    regexp: ["", "!^.*$!sip:a\\\\b@example.com!"][self.below(2) as usize]
+                        .to_string(),
+                    replacement: self.name(),
+                },
+                10 => RData::SSHFP {
+                    algorithm: self.below(7) as u8,
+                    fingerprint_type: self.below(3) as u8,
+                    fingerprint: (0..self.below(33)).map(|_| self.next() as u8).collect(),
+                },
+                11 => {
+                    let priority = self.next() as u16;
+                    let target = self.name();
+                    let params = vec![
+                        SvcParam::Alpn(vec!["h2".to_string(), "h3".to_string()]),
+                        SvcParam::Port(self.next() as u16),
+                        SvcParam::Ipv4Hint(vec![Ipv4Addr::from(self.next() as u32)]),
+                        SvcParam::Other {
+                            key: 5,
+                            value: vec![self.next() as u8],
+                        },
+                        SvcParam::Ipv6Hint(vec![Ipv6Addr::from(self.next() as u128)]),
+                    ];
+                    if self.below(2) == 0 {
+                        RData::SVCB {
+                            priority,
+                            target,
+                            params,
+                        }
+                    } else {
+                        RData::HTTPS {
+                            priority,
+                            target,
+                            params,
+                        }
+                    }
+                }
+                12 => {
+                    // A type bitmap drawn from a fixed, increasing set of types.
+                    let types: Vec<QueryType> = [1, 2, 6, 15, 28, 46, 47, 48, 257, 1234]
+                        .into_iter()
+                        .filter(|_| self.below(2) == 0)
+                        .map(QueryType::from)
+                        .collect();
+                    match self.below(5) {
+                        0 => RData::DS {
+                            key_tag: self.next() as u16,
+                            algorithm: self.next() as u8,
+                            digest_type: self.next() as u8,
+                            digest: (0..32).map(|_| self.next() as u8).collect(),
+                        },
+                        1 => RData::DNSKEY {
+                            flags: self.next() as u16,
+                            protocol: 3,
+                            algorithm: self.next() as u8,
+                            public_key: (0..self.below(100)).map(|_| self.next() as u8).collect(),
+                        },
+                        2 => RData::RRSIG {
+                            type_covered: QueryType::from(self.next() as u16),
+                            algorithm: self.next() as u8,
+                            labels: self.next() as u8,
+                            original_ttl: self.next() as u32,
+                            expiration: self.next() as u32,
+                            inception: self.next() as u32,
+                            key_tag: self.next() as u16,
+                            signer_name: self.name(),
+                            signature: (0..self.below(100)).map(|_| self.next() as u8).collect(),
+                        },
+                        3 => RData::NSEC {
+                            next_domain: self.name(),
+                            types,
+                        },
+                        _ => RData::NSEC3 {
+                            hash_algorithm: 1,
+                            flags: self.below(2) as u8,
+                            iterations: self.next() as u16,
+                            salt: (0..self.below(9)).map(|_| self.next() as u8).collect(),
+                            next_hashed_owner: (0..20).map(|_| self.next() as u8).collect(),
+                            types,
+                        },
+                    }
+                }
+                13 => RData::HINFO {
+                    cpu: ["RFC8482", "INTEL-386", "ARM \"64\""][self.below(3) as usize].to_string(),
+                    os: ["", "UNIX", "Linux"][self.below(3) as usize].to_string(),
+                },
+                14 => RData::TSIG {
+                    algorithm: "hmac-sha256".to_string(),
+                    time_signed: self.next() >> 16,
+                    fudge: self.next() as u16,
+                    mac: (0..self.below(65)).map(|_| self.next() as u8).collect(),
+                    original_id: self.next() as u16,
+                    error: [0, 16, 18][self.below(3) as usize],
+                    other: (0..self.below(7)).map(|_| self.next() as u8).collect(),
+                },
+                _ => {
+                    let rtype = loop {
+                        if let QueryType::Unknown(code) = QueryType::from(self.next() as u16) {
+                            break code;
+                        }
+                    };
+                    RData::Other {
+                        rtype,
+                        data: (0..self.below(40)).map(|_| self.next() as u8).collect(),
+                    }
+                }
+            };
+            let rtype = match &data {
+                RData::A(_) => QueryType::A,
+                RData::AAAA(_) => QueryType::AAAA,
+                RData::CNAME(_) => QueryType::CNAME,
+                RData::PTR(_) => QueryType::PTR,
+                RData::NS(_) => QueryType::NS,
+                RData::SOA { .. } => QueryType::SOA,
+                RData::HINFO { .. } => QueryType::HINFO,
+                RData::TSIG { .. } => QueryType::TSIG,
+                RData::SRV { .. } => QueryType::SRV,
+                RData::NAPTR { .. } => QueryType::NAPTR,
+                RData::OPT(_) => QueryType::OPT,
+                RData::SSHFP { .. } => QueryType::SSHFP,
+                RData::DS { .. } => QueryType::DS,
+                RData::DNSKEY { .. } => QueryType::DNSKEY,
+                RData::RRSIG { .. } => QueryType::RRSIG,
+                RData::NSEC { .. } => QueryType::NSEC,
+                RData::NSEC3 { .. } => QueryType::NSEC3,
+                RData::SVCB { .. } => QueryType::SVCB,
+                RData::HTTPS { .. } => QueryType::HTTPS,
+                RData::MX { .. } => QueryType::MX,
+                RData::TXT(_) => QueryType::TXT,
+                RData::Other { rtype, .. } => QueryType::Unknown(*rtype),
+            };
+            ResourceRecord {
+                name: DnsName::new_unchecked(self.name()),
+                rtype,
+                rclass: self.next() as u16,
+                ttl: self.next() as u32,
+                data,
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack_then_parse_is_identity() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+        for case in 0..500 {
+            let mut message = DnsMessage::new();
+            message.header.id = rng.next() as u16;
+            message.header.flags = rng.next() as u16;
+            for _ in 0..rng.below(3) {
+                message.questions.push(DnsQuestion {
+                    name: DnsName::new_unchecked(rng.name()),
+                    qtype: QueryType::from(rng.next() as u16),
+                    qclass: rng.next() as u16,
+                });
+            }
+            for section in [
+                &mut message.answers,
+                &mut message.authorities,
+                &mut message.additionals,
+            ] {
+                for _ in 0..rng.below(5) {
+                    section.push(rng.record());
+                }
+            }
+            message.header.question_count = message.questions.len() as u16;
+            message.header.answer_count = message.answers.len() as u16;
+            message.header.authority_count = message.authorities.len() as u16;
+            message.header.additional_count = message.additionals.len() as u16;
+
+            let mut buffer = Vec::new();
+            message.pack(&mut buffer).unwrap();
+            let (parsed, consumed) = DnsMessage::parse_prefix(&buffer).unwrap();
+            assert_eq!(parsed, message, "case {} did not round-trip", case);
+            // Names compare without regard to case, so check their spelling too.
+            let mut repacked = Vec::new();
+            parsed.pack(&mut repacked).unwrap();
+            assert_eq!(repacked, buffer, "case {} changed a name", case);
+            assert_eq!(consumed, buffer.len(), "case {} left trailing bytes", case);
+
+            for record in &message.answers {
+                let mut buffer = Vec::new();
+                record.pack(&mut buffer).unwrap();
+                let mut cursor = Cursor::new(&buffer[..]);
+                assert_eq!(&ResourceRecord::from_bytes(&mut cursor).unwrap(), record);
+            }
+        }
+    }
+
+    #[test]
+    fn test_repeated_names_are_compressed() {
+        let record = |name: &str| ResourceRecord {
+            name: name.parse().unwrap(),
+            rtype: QueryType::CNAME,
+            rclass: 1,
+            ttl: 60,
+            data: RData::CNAME("target.example.com".to_string()),
+        };
+        let mut message = DnsMessage::new();
+        message.header.answer_count = 2;
+        message.answers = vec![record("www.example.com"), record("www.example.com")];
+
+        let mut buffer = Vec::new();
+        message.pack(&mut buffer).unwrap();
+        // The second record is a pointer to the first owner name (offset 12) and its
+        // target is a pointer to the first target.
+        let second = &buffer[buffer.len() - 14..];
+        assert_eq!(&second[..2], &[0xC0, 0x0C]);
+        assert_eq!(&second[10..12], &[0x00, 0x02]);
+        assert_eq!(DnsMessage::from_bytes(&buffer).unwrap(), message);
+
+        assert!(pack_domain_name(&mut Vec::new(), "a..example.com").is_err());
+        let mut root = Vec::new();
+        pack_domain_name(&mut root, ".").unwrap();
+        assert_eq!(root, vec![0]);
+    }
+
+    #[test]
+    fn test_canonical_name_order() {
+        // The example ordering from RFC 4034 section 6.1, without the names that
+        // contain non-printable octets.
+        let expected = [
+            "example",
+            "a.example",
+            "yljkjljk.a.example",
+            "Z.a.example",
+            "zABC.a.EXAMPLE",
+            "z.example",
+            "*.z.example",
+        ];
+        let mut names = expected.to_vec();
+        names.reverse();
+        names.swap(0, 4);
+        names.sort_by(|a, b| compare_canonical_names(a, b));
+        assert_eq!(names, expected);
+    }
+
+    #[test]
+    fn test_canonical_rrset() {
+        let record = |name: &str, ttl, exchange: &str| ResourceRecord {
+            name: name.parse().unwrap(),
+            rtype: QueryType::MX,
+            rclass: 1,
+            ttl,
+            data: RData::MX {
+                preference: 10,
+                exchange: exchange.to_string(),
+            },
+        };
+        let rrset = vec![
+            record("Example.com", 60, "MX2.example.com."),
+            record("example.com", 300, "mx1.example.com"),
+            record("EXAMPLE.COM.", 10, "mx2.EXAMPLE.com"),
+        ];
+
+        let mut sorted = rrset.clone();
+        sort_rrset_canonical(&mut sorted).unwrap();
+        assert_eq!(sorted, vec![rrset[1].clone(), rrset[0].clone()]);
+
+        let wire = pack_canonical_rrset(&rrset, Some(3600)).unwrap();
+        let mut expected = Vec::new();
+        record("example.com", 3600, "mx1.example.com")
+            .pack(&mut expected)
+            .unwrap();
+        record("example.com", 3600, "mx2.example.com")
+            .pack(&mut expected)
+            .unwrap();
+        assert_eq!(wire, expected);
+    }
 }
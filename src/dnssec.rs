@@ -0,0 +1,1625 @@
+//! DNSSEC validation (RFC 4033, RFC 4034 and RFC 4035).
+//!
+//! Unsigned DNS data is only as trustworthy as the path it took. DNSSEC lets a
+//! client check the data itself: every signed zone publishes its keys in DNSKEY
+//! records, the parent zone vouches for those keys with DS records signed by its own
+//! keys, and the keys of the root zone are known in advance as the trust anchor.
+//! This module follows that chain from the root down to the records of a response
+//! and reports one of the outcomes of RFC 4033 section 5:
+//!
+//! * secure - every record is covered by a valid signature chained to the trust
+//!   anchor, and every claim that something does not exist is backed by signed
+//!   NSEC or NSEC3 records
+//! * insecure - the records belong to a zone that is provably unsigned, because
+//!   its parent delegates to it without DS records, or only with DS records for
+//!   algorithms this module does not implement
+//! * bogus - a signature is missing, expired or wrong, or the chain is broken
+//!
+//! The chain starts from the root keys IANA publishes, which are built in
+//! ([`root_trust_anchors`]). [`load_trust_anchors`] reads others from a file, for
+//! a root key rollover or a test root, and [`QueryOptions::trust_anchors`] puts
+//! them to use.
+//!
+//! RSA/SHA-256 (8), RSA/SHA-512 (10) and ECDSA P-256 with SHA-256 (13) signatures
+//! are verified, which covers nearly every signed zone; zones signed only with
+//! other algorithms are insecure as far as this module is concerned (RFC 4035
+//! section 5.2).
+//!
+//! The keys and delegation records are fetched with the DO and CD bits set, so that
+//! a validating upstream resolver hands over the signatures and leaves judging them
+//! to us.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::{QueryOptions, query};
+//! use std::net::Ipv4Addr;
+//!
+//! let options = QueryOptions {
+//!     validate: true,
+//!     ..QueryOptions::default()
+//! };
+//! let result = query("example.com", QueryType::A, Ipv4Addr::new(8, 8, 8, 8), &options)?;
+//! println!("DNSSEC: {}", result.security.unwrap());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crypto::{HashAlgorithm, verify_ecdsa_p256, verify_rsa};
+use crate::dns::{
+    DnsMessage, DnsName, QueryType, RData, ResourceRecord, ResponseCode, base32hex, canonical_name,
+    compare_canonical_names, decode_base64, display_name, pack_canonical_rrset, pack_domain_name,
+};
+use crate::resolver::{QueryOptions, query};
+use crate::transcript::decode_hex;
+use crate::validation::is_subdomain;
+
+/// The UDP payload size advertised when DNSSEC records are requested, which lets
+/// most signed responses arrive without falling back to TCP.
+pub const DNSSEC_UDP_PAYLOAD_SIZE: u16 = 1232;
+
+/// The key tags and SHA-256 digests of the root zone's key-signing keys, KSK-2017
+/// and KSK-2024, both RSA/SHA-256, as IANA publishes them at
+/// <https://data.iana.org/root-anchors/root-anchors.xml>.
+const ROOT_ANCHORS: [(u16, &str); 2] = [
+    (
+        20326,
+        "e06d44b80b8f1d39a95c0b0d7c65d08458e880409bbc683457104237c7f8ec8d",
+    ),
+    (
+        38696,
+        "683d2d0acb8c9b712a1948b27f741219298d0a450d612c483af444a4c0fb2b16",
+    ),
+];
+
+/// The DNSKEY flag marking a zone key, the only kind allowed to sign zone data.
+const ZONE_KEY_FLAG: u16 = 0x0100;
+
+/// The NSEC3 flag marking a range that may hide unsigned delegations.
+const OPT_OUT_FLAG: u8 = 0x01;
+
+/// The most NSEC3 iterations a denial of existence is accepted with; zones that
+/// use more are treated as insecure (RFC 9276 section 3.2).
+const MAX_NSEC3_ITERATIONS: u16 = 150;
+
+/// The outcome of validating a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecurityStatus {
+    /// Every record is authenticated by a chain of signatures from the trust anchor.
+    Secure,
+    /// The records come from a zone that is provably unsigned, for the reason given.
+    Insecure(String),
+    /// The records should be signed but failed validation, for the reason given.
+    Bogus(String),
+}
+
+impl fmt::Display for SecurityStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecurityStatus::Secure => write!(f, "secure"),
+            SecurityStatus::Insecure(reason) => write!(f, "insecure ({})", reason),
+            SecurityStatus::Bogus(reason) => write!(f, "bogus ({})", reason),
+        }
+    }
+}
+
+/// Returns the trust anchors for the root zone built into the resolver, as DS
+/// records.
+pub fn root_trust_anchors() -> Vec<ResourceRecord> {
+    ROOT_ANCHORS
+        .iter()
+        .map(|(key_tag, digest)| ResourceRecord {
+            name: DnsName::root(),
+            rtype: QueryType::DS,
+            rclass: 1,
+            ttl: 0,
+            data: RData::DS {
+                key_tag: *key_tag,
+                algorithm: 8,
+                digest_type: 2,
+                digest: (0..digest.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&digest[i..i + 2], 16).unwrap())
+                    .collect(),
+            },
+        })
+        .collect()
+}
+
+/// Reads trust anchors for the root zone from a file, to validate against instead
+/// of the built-in [`root_trust_anchors`].
+///
+/// The file holds DS or DNSKEY records in presentation format, one per line, as
+/// `unbound-anchor` writes them and IANA publishes them. Comments start with `;`:
+///
+/// ```text
+/// ; KSK-2024
+/// .  IN  DS  38696 8 2 683D2D0ACB8C9B712A1948B27F741219298D0A450D612C483AF444A4C0FB2B16
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or holds anything
+/// [`parse_trust_anchors`] rejects.
+pub fn load_trust_anchors(path: impl AsRef<Path>) -> Result<Vec<ResourceRecord>, String> {
+    let path = path.as_ref();
+    let text =
+        fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_trust_anchors(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Parses trust anchors in the format [`load_trust_anchors`] reads.
+///
+/// # Returns
+///
+/// The anchors as DS records. A DNSKEY record is turned into the DS record that
+/// vouches for it, with a SHA-256 digest.
+///
+/// # Errors
+///
+/// Returns an error naming the line of the first record that is malformed, is not
+/// a DS or DNSKEY record, or is not owned by the root, or if there is no record.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dnssec::{parse_trust_anchors, root_trust_anchors};
+///
+/// let anchors = parse_trust_anchors(
+///     ". 86400 IN DS 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D",
+/// )?;
+/// assert_eq!(anchors[0], root_trust_anchors()[0]);
+/// # Ok::<(), String>(())
+/// ```
+pub fn parse_trust_anchors(text: &str) -> Result<Vec<ResourceRecord>, String> {
+    let mut anchors = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split(';').next().unwrap_or_default();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.is_empty() {
+            continue;
+        }
+        let anchor =
+            parse_trust_anchor(&fields).map_err(|e| format!("line {}: {}", index + 1, e))?;
+        anchors.push(anchor);
+    }
+    if anchors.is_empty() {
+        return Err("no trust anchors found".to_string());
+    }
+    Ok(anchors)
+}
+
+/// Parses the fields of one trust anchor line into a DS record.
+fn parse_trust_anchor(fields: &[&str]) -> Result<ResourceRecord, String> {
+    let (owner, mut rest) = fields
+        .split_first()
+        .ok_or_else(|| "empty record".to_string())?;
+    if !canonical_name(owner).is_empty() {
+        return Err(format!(
+            "{} is not the root; only root anchors are used",
+            owner
+        ));
+    }
+    // The TTL and class are optional, and mean nothing for a trust anchor.
+    while let Some((field, tail)) = rest.split_first()
+        && (field.parse::<u32>().is_ok() || field.eq_ignore_ascii_case("IN"))
+    {
+        rest = tail;
+    }
+    let (rtype, rdata) = rest
+        .split_first()
+        .ok_or_else(|| "missing record type".to_string())?;
+    let data = match rtype.to_ascii_uppercase().as_str() {
+        "DS" => RData::DS {
+            key_tag: rdata_field(rdata, 0, "key tag")?,
+            algorithm: rdata_field(rdata, 1, "algorithm")?,
+            digest_type: rdata_field(rdata, 2, "digest type")?,
+            digest: decode_hex(&rdata.get(3..).unwrap_or_default().concat())
+                .filter(|digest| !digest.is_empty())
+                .ok_or_else(|| "invalid DS digest".to_string())?,
+        },
+        "DNSKEY" => {
+            let flags: u16 = rdata_field(rdata, 0, "flags")?;
+            if flags & ZONE_KEY_FLAG == 0 {
+                return Err("the DNSKEY record is not a zone key".to_string());
+            }
+            let algorithm = rdata_field(rdata, 2, "algorithm")?;
+            let key = ResourceRecord {
+                name: DnsName::root(),
+                rtype: QueryType::DNSKEY,
+                rclass: 1,
+                ttl: 0,
+                data: RData::DNSKEY {
+                    flags,
+                    protocol: rdata_field(rdata, 1, "protocol")?,
+                    algorithm,
+                    public_key: decode_base64(&rdata.get(3..).unwrap_or_default().concat())?,
+                },
+            };
+            RData::DS {
+                key_tag: key.data.key_tag().unwrap_or_default(),
+                algorithm,
+                digest_type: 2,
+                digest: key_digest("", &key, HashAlgorithm::Sha256)
+                    .ok_or_else(|| "the DNSKEY record cannot be digested".to_string())?,
+            }
+        }
+        other => return Err(format!("{} records cannot be trust anchors", other)),
+    };
+    Ok(ResourceRecord {
+        name: DnsName::root(),
+        rtype: QueryType::DS,
+        rclass: 1,
+        ttl: 0,
+        data,
+    })
+}
+
+/// Parses the field of a record's data at `index`.
+fn rdata_field<T: FromStr>(rdata: &[&str], index: usize, what: &str) -> Result<T, String> {
+    let field = rdata
+        .get(index)
+        .ok_or_else(|| format!("missing {}", what))?;
+    field
+        .parse()
+        .map_err(|_| format!("invalid {} '{}'", what, field))
+}
+
+/// Validates a response against [`QueryOptions::trust_anchors`], or the built-in
+/// root trust anchors when it is unset, fetching the keys and delegation records
+/// the chain of trust needs from a DNS server.
+///
+/// # Arguments
+///
+/// * `message` - The response to validate, which should have been requested with
+///   the DO bit so that it carries its signatures
+/// * `dns_server_addr` - The server to fetch keys and delegation records from,
+///   normally the one that sent `message`
+/// * `options` - How to send those queries; DO and CD are set on every one
+///
+/// # Returns
+///
+/// The security status of the response. Keys and delegation records that cannot
+/// be fetched make the response bogus, since its status cannot be established.
+pub fn validate(
+    message: &DnsMessage,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> SecurityStatus {
+    let trust_anchors = match &options.trust_anchors {
+        Some(trust_anchors) => trust_anchors.to_vec(),
+        None => root_trust_anchors(),
+    };
+    let options = QueryOptions {
+        validate: false,
+        dnssec_ok: true,
+        checking_disabled: true,
+        edns: Some(options.edns.unwrap_or(0).max(DNSSEC_UDP_PAYLOAD_SIZE)),
+        duplicate_window: None,
+        ..options.clone()
+    };
+    let mut fetch = |name: &str, query_type| {
+        query(name, query_type, dns_server_addr, &options)
+            .map(|result| result.message)
+            .map_err(|e| e.to_string())
+    };
+    validate_with(message, &trust_anchors, SystemTime::now(), &mut fetch)
+}
+
+/// Validates a response against the given trust anchors, fetching the records the
+/// chain of trust needs through a callback.
+///
+/// The validator walks down from the root towards every name in the response,
+/// asking for the DS records of each name on the way to find the zone cuts, and
+/// for the DNSKEY records of every signed zone it finds. Each answer is fetched
+/// once per call.
+///
+/// # Arguments
+///
+/// * `message` - The response to validate
+/// * `trust_anchors` - DS records for the root zone's keys, such as
+///   [`root_trust_anchors`]
+/// * `now` - The time signature validity periods are checked against
+/// * `fetch` - Answers a query for a name and type, with DNSSEC records included;
+///   the root is passed as an empty name
+///
+/// # Returns
+///
+/// The security status of the response.
+pub fn validate_with(
+    message: &DnsMessage,
+    trust_anchors: &[ResourceRecord],
+    now: SystemTime,
+    fetch: &mut dyn FnMut(&str, QueryType) -> Result<DnsMessage, String>,
+) -> SecurityStatus {
+    let mut validator = Validator {
+        trust_anchors,
+        // Signature times are 32-bit serial numbers, so the seconds wrap on purpose.
+        now: now
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs() as u32),
+        fetch,
+        zones: HashMap::new(),
+    };
+    match validator.check(message) {
+        Ok(()) => SecurityStatus::Secure,
+        Err(status) => status,
+    }
+}
+
+/// A signed zone whose keys have been authenticated.
+#[derive(Debug, Clone)]
+struct Zone {
+    /// The canonical name of the zone apex.
+    apex: String,
+    /// The zone's DNSKEY records.
+    keys: Vec<ResourceRecord>,
+}
+
+/// The state of one validation. Checks fail with the insecure or bogus status they
+/// establish, so that `?` ends a check as soon as its outcome is known.
+struct Validator<'a> {
+    trust_anchors: &'a [ResourceRecord],
+    now: u32,
+    fetch: &'a mut dyn FnMut(&str, QueryType) -> Result<DnsMessage, String>,
+    /// The zone of every name walked so far, or why it cannot be trusted.
+    zones: HashMap<String, Result<Zone, SecurityStatus>>,
+}
+
+impl Validator<'_> {
+    /// Checks the answer section of a response, and the denial of existence in the
+    /// authority section when the answer does not hold the records asked for.
+    fn check(&mut self, message: &DnsMessage) -> Result<(), SecurityStatus> {
+        let question = message
+            .questions
+            .first()
+            .ok_or_else(|| bogus("the response has no question"))?;
+        let response_code = message.header.get_response_code();
+        if !matches!(
+            response_code,
+            ResponseCode::NoError | ResponseCode::NameError
+        ) {
+            return Err(bogus(format!(
+                "the response code {:?} carries nothing to validate",
+                response_code
+            )));
+        }
+
+        // A bogus RRset spoils the whole response, while an insecure one only
+        // matters if nothing turns out to be bogus.
+        let mut insecure = None;
+
+        // Every RRset in the answer section must be signed by the zone it is in.
+        for (owner, rtype, records) in rrsets(&message.answers) {
+            let result = self.check_rrset(&owner, rtype, &records, message);
+            note(&mut insecure, result)?;
+        }
+
+        // Follow any aliases to the name the answer is about. Without records of
+        // the queried type there, the response must prove that none exist.
+        let target = alias_target(&question.name, question.qtype, &message.answers);
+        let answered = message
+            .answers
+            .iter()
+            .any(|record| record.rtype == question.qtype && canonical_name(&record.name) == target);
+        if !answered {
+            let nxdomain = response_code == ResponseCode::NameError;
+            let result = self.check_denial(&target, question.qtype, nxdomain, &message.authorities);
+            note(&mut insecure, result)?;
+        }
+
+        insecure.map_or(Ok(()), Err)
+    }
+
+    /// Checks the signature of an answer RRset, and that a wildcard it was
+    /// synthesized from was allowed to apply.
+    fn check_rrset(
+        &mut self,
+        owner: &str,
+        rtype: QueryType,
+        records: &[ResourceRecord],
+        message: &DnsMessage,
+    ) -> Result<(), SecurityStatus> {
+        let zone = self.zone_for(owner, rtype)?;
+        let signatures = signatures_for(&message.answers, owner, rtype);
+        let labels = self.verify_rrset(owner, rtype, records, &signatures, &zone)?;
+
+        // A signature over fewer labels than the owner name has means the records
+        // were expanded from a wildcard, which only applies to names that do not
+        // exist (RFC 4035 section 5.3.4).
+        if labels < label_count(owner) {
+            let (nsecs, nsec3s) = self.denial_records(&zone, &message.authorities)?;
+            let next_closer = suffix(owner, usize::from(labels) + 1);
+            let proven = nsecs.iter().any(|nsec| nsec.covers(owner))
+                || nsec3s.is_some_and(|chain| chain.covering(&next_closer).is_some());
+            if !proven {
+                return Err(bogus(format!(
+                    "{} {} was expanded from a wildcard without proof that the name does not exist",
+                    display_name(owner),
+                    rtype
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks that the authority section proves that `name` has no records of
+    /// type `rtype`, or does not exist at all when `nxdomain` is set.
+    fn check_denial(
+        &mut self,
+        name: &str,
+        rtype: QueryType,
+        nxdomain: bool,
+        authorities: &[ResourceRecord],
+    ) -> Result<(), SecurityStatus> {
+        let zone = self.zone_for(name, rtype)?;
+        let (nsecs, nsec3s) = self.denial_records(&zone, authorities)?;
+        let claim = if nxdomain {
+            format!("{} does not exist", display_name(name))
+        } else {
+            format!("{} has no {} records", display_name(name), rtype)
+        };
+        if !nsecs.is_empty() {
+            return if nsec_denies(&nsecs, name, rtype, nxdomain) {
+                Ok(())
+            } else {
+                Err(bogus(format!(
+                    "the NSEC records do not prove that {}",
+                    claim
+                )))
+            };
+        }
+        match nsec3s {
+            Some(chain) => chain.denies(name, rtype, nxdomain, &claim),
+            None => Err(bogus(format!(
+                "no NSEC or NSEC3 records prove that {}",
+                claim
+            ))),
+        }
+    }
+
+    /// Returns the zone that holds the records of `name` with type `rtype`. DS
+    /// records live on the parent side of a zone cut, every other type below it.
+    fn zone_for(&mut self, name: &str, rtype: QueryType) -> Result<Zone, SecurityStatus> {
+        match parent(name) {
+            Some(parent) if rtype == QueryType::DS => self.zone_of(&parent),
+            _ => self.zone_of(name),
+        }
+    }
+
+    /// Returns the signed zone `name` belongs to, walking down from the root.
+    fn zone_of(&mut self, name: &str) -> Result<Zone, SecurityStatus> {
+        if let Some(zone) = self.zones.get(name) {
+            return zone.clone();
+        }
+        let zone = match parent(name) {
+            None => {
+                let trust_anchors = self.trust_anchors;
+                self.authenticate_keys("", trust_anchors).map(|keys| Zone {
+                    apex: String::new(),
+                    keys,
+                })
+            }
+            Some(parent) => self
+                .zone_of(&parent)
+                .and_then(|zone| self.find_cut(name, zone)),
+        };
+        self.zones.insert(name.to_string(), zone.clone());
+        zone
+    }
+
+    /// Looks for a zone cut at `name`, which lies in `zone`, by asking for its DS
+    /// records, and returns the zone `name` belongs to.
+    fn find_cut(&mut self, name: &str, zone: Zone) -> Result<Zone, SecurityStatus> {
+        let response = self.fetch(name, QueryType::DS)?;
+        let ds = records_of(&response.answers, name, QueryType::DS);
+        if !ds.is_empty() {
+            let signatures = signatures_for(&response.answers, name, QueryType::DS);
+            self.verify_rrset(name, QueryType::DS, &ds, &signatures, &zone)?;
+            let keys = self.authenticate_keys(name, &ds)?;
+            return Ok(Zone {
+                apex: name.to_string(),
+                keys,
+            });
+        }
+
+        // Without DS records the name is either inside the zone or the apex of an
+        // unsigned child zone. Only the latter needs proof: wrongly taking a cut
+        // for the former merely makes the child's records fail validation.
+        match response.header.get_response_code() {
+            ResponseCode::NoError => {}
+            ResponseCode::NameError => return Ok(zone),
+            code => {
+                return Err(bogus(format!(
+                    "the DS query for {} failed with {:?}",
+                    display_name(name),
+                    code
+                )));
+            }
+        }
+        let (nsecs, nsec3s) = self.denial_records(&zone, &response.authorities)?;
+        let types = match nsecs.iter().find(|nsec| nsec.owner == name) {
+            Some(nsec) => Some(nsec.types.as_slice()),
+            None => match &nsec3s {
+                Some(chain) => match chain.matching(name) {
+                    Some(entry) => Some(entry.types.as_slice()),
+                    // An opt-out range may hide unsigned delegations (RFC 5155 section 6).
+                    None if chain.covering(name).is_some_and(|entry| entry.opt_out) => {
+                        return Err(insecure(format!(
+                            "{} lies in an NSEC3 opt-out range",
+                            display_name(name)
+                        )));
+                    }
+                    None => None,
+                },
+                None => None,
+            },
+        };
+        if let Some(types) = types
+            && types.contains(&QueryType::NS)
+            && !types.contains(&QueryType::SOA)
+        {
+            return Err(insecure(format!(
+                "{} is delegated without a DS record",
+                display_name(name)
+            )));
+        }
+        Ok(zone)
+    }
+
+    /// Fetches the DNSKEY records of `apex` and returns them if one of the keys
+    /// matches a DS record and signs them all.
+    fn authenticate_keys(
+        &mut self,
+        apex: &str,
+        ds_records: &[ResourceRecord],
+    ) -> Result<Vec<ResourceRecord>, SecurityStatus> {
+        let ds_records: Vec<&ResourceRecord> = ds_records
+            .iter()
+            .filter(|ds| {
+                matches!(&ds.data, RData::DS { algorithm, digest_type, .. }
+                    if supported_algorithm(*algorithm) && digest_algorithm(*digest_type).is_some())
+            })
+            .collect();
+        if ds_records.is_empty() {
+            return Err(insecure(format!(
+                "no DS record for {} uses a supported algorithm",
+                display_name(apex)
+            )));
+        }
+
+        let response = self.fetch(apex, QueryType::DNSKEY)?;
+        let keys = records_of(&response.answers, apex, QueryType::DNSKEY);
+        let entry_points: Vec<ResourceRecord> = keys
+            .iter()
+            .filter(|key| ds_records.iter().any(|ds| ds_matches(ds, apex, key)))
+            .cloned()
+            .collect();
+        if entry_points.is_empty() {
+            return Err(bogus(format!(
+                "no DNSKEY record of {} matches its DS records",
+                display_name(apex)
+            )));
+        }
+        let signatures = signatures_for(&response.answers, apex, QueryType::DNSKEY);
+        let zone = Zone {
+            apex: apex.to_string(),
+            keys: entry_points,
+        };
+        self.verify_rrset(apex, QueryType::DNSKEY, &keys, &signatures, &zone)?;
+        Ok(keys)
+    }
+
+    /// Returns the NSEC and NSEC3 records of `zone` in an authority section, once
+    /// their signatures have been verified.
+    fn denial_records(
+        &mut self,
+        zone: &Zone,
+        authorities: &[ResourceRecord],
+    ) -> Result<(Vec<NsecEntry>, Option<Nsec3Chain>), SecurityStatus> {
+        let mut nsecs = Vec::new();
+        let mut nsec3s = Vec::new();
+        for (owner, rtype, records) in rrsets(authorities) {
+            if !matches!(rtype, QueryType::NSEC | QueryType::NSEC3)
+                || !is_subdomain(&owner, &zone.apex)
+            {
+                continue;
+            }
+            let signatures = signatures_for(authorities, &owner, rtype);
+            self.verify_rrset(&owner, rtype, &records, &signatures, zone)?;
+            for record in records {
+                match record.data {
+                    RData::NSEC { next_domain, types } => nsecs.push(NsecEntry {
+                        owner: owner.clone(),
+                        next: canonical_name(&next_domain),
+                        types,
+                    }),
+                    RData::NSEC3 { .. } => nsec3s.push(record),
+                    _ => {}
+                }
+            }
+        }
+        let chain = Nsec3Chain::new(&zone.apex, &nsec3s)?;
+        Ok((nsecs, chain))
+    }
+
+    /// Verifies that at least one of `signatures` is a valid signature over an
+    /// RRset by a key of `zone`.
+    ///
+    /// # Returns
+    ///
+    /// The label count of the signature that verified, which tells whether the
+    /// records were expanded from a wildcard.
+    fn verify_rrset(
+        &self,
+        owner: &str,
+        rtype: QueryType,
+        records: &[ResourceRecord],
+        signatures: &[ResourceRecord],
+        zone: &Zone,
+    ) -> Result<u8, SecurityStatus> {
+        let mut reason = String::from("it is not signed");
+        for signature in signatures {
+            match self.verify_signature(owner, records, signature, zone) {
+                Ok(labels) => return Ok(labels),
+                Err(why) => reason = why,
+            }
+        }
+        Err(bogus(format!(
+            "{} {}: {}",
+            display_name(owner),
+            rtype,
+            reason
+        )))
+    }
+
+    /// Checks one RRSIG record over an RRset, following RFC 4035 section 5.3.
+    fn verify_signature(
+        &self,
+        owner: &str,
+        records: &[ResourceRecord],
+        signature: &ResourceRecord,
+        zone: &Zone,
+    ) -> Result<u8, String> {
+        let RData::RRSIG {
+            algorithm,
+            labels,
+            expiration,
+            inception,
+            key_tag,
+            signer_name,
+            signature: signature_bytes,
+            ..
+        } = &signature.data
+        else {
+            return Err("the signature is not an RRSIG record".to_string());
+        };
+        if canonical_name(signer_name) != zone.apex {
+            return Err(format!(
+                "signed by {} rather than by the zone {}",
+                display_name(signer_name),
+                display_name(&zone.apex)
+            ));
+        }
+        if *labels > label_count(owner) {
+            return Err("the signature has more labels than the owner name".to_string());
+        }
+        // Compare times as serial numbers (RFC 4034 section 3.1.5), so that the
+        // 32-bit fields keep working past 2106.
+        if (self.now.wrapping_sub(*inception) as i32) < 0 {
+            return Err("the signature is not valid yet".to_string());
+        }
+        if (expiration.wrapping_sub(self.now) as i32) < 0 {
+            return Err("the signature has expired".to_string());
+        }
+        if !supported_algorithm(*algorithm) {
+            return Err(format!("algorithm {} is not supported", algorithm));
+        }
+
+        let data = signed_data(owner, records, signature)?;
+        let verified = zone.keys.iter().any(|key| match &key.data {
+            RData::DNSKEY {
+                flags,
+                protocol: 3,
+                algorithm: key_algorithm,
+                public_key,
+            } if flags & ZONE_KEY_FLAG != 0
+                && key_algorithm == algorithm
+                && key.data.key_tag() == Some(*key_tag) =>
+            {
+                verify(*algorithm, public_key, &data, signature_bytes)
+            }
+            _ => false,
+        });
+        if verified {
+            Ok(*labels)
+        } else {
+            Err(format!(
+                "no key with tag {} and algorithm {} verifies the signature",
+                key_tag, algorithm
+            ))
+        }
+    }
+
+    /// Fetches `name` and `rtype`, treating any failure as bogus.
+    fn fetch(&mut self, name: &str, rtype: QueryType) -> Result<DnsMessage, SecurityStatus> {
+        (self.fetch)(name, rtype).map_err(|e| {
+            bogus(format!(
+                "cannot fetch the {} records of {}: {}",
+                rtype,
+                display_name(name),
+                e
+            ))
+        })
+    }
+}
+
+/// An NSEC record: the names of a zone between its owner and its next name do not
+/// exist, and its owner has exactly the listed types.
+struct NsecEntry {
+    owner: String,
+    next: String,
+    types: Vec<QueryType>,
+}
+
+impl NsecEntry {
+    /// Whether `name` falls strictly between the owner and the next name. The last
+    /// record of a zone points back to the apex, so its range wraps around.
+    fn covers(&self, name: &str) -> bool {
+        let after_owner = compare_canonical_names(&self.owner, name) == Ordering::Less;
+        let before_next = compare_canonical_names(name, &self.next) == Ordering::Less;
+        if compare_canonical_names(&self.owner, &self.next) == Ordering::Less {
+            after_owner && before_next
+        } else {
+            after_owner || before_next
+        }
+    }
+}
+
+/// An NSEC3 record, with its owner and next owner hashes in base32hex.
+struct Nsec3Entry {
+    owner: String,
+    next: String,
+    opt_out: bool,
+    types: Vec<QueryType>,
+}
+
+/// The NSEC3 records of one zone that share its hash parameters.
+struct Nsec3Chain {
+    apex: String,
+    salt: Vec<u8>,
+    iterations: u16,
+    entries: Vec<Nsec3Entry>,
+}
+
+impl Nsec3Chain {
+    /// Collects NSEC3 records of the zone at `apex` into a chain, or returns
+    /// `None` when there are none that use SHA-1, the only defined hash.
+    fn new(apex: &str, records: &[ResourceRecord]) -> Result<Option<Self>, SecurityStatus> {
+        let mut chain: Option<Nsec3Chain> = None;
+        for record in records {
+            let RData::NSEC3 {
+                hash_algorithm: 1,
+                flags,
+                iterations,
+                salt,
+                next_hashed_owner,
+                types,
+            } = &record.data
+            else {
+                continue;
+            };
+            let owner = canonical_name(&record.name);
+            let (hash, zone) = owner.split_once('.').unwrap_or((&owner, ""));
+            if zone != apex {
+                continue;
+            }
+            if *iterations > MAX_NSEC3_ITERATIONS {
+                return Err(insecure(format!(
+                    "NSEC3 records of {} use {} iterations, more than {}",
+                    display_name(apex),
+                    iterations,
+                    MAX_NSEC3_ITERATIONS
+                )));
+            }
+            let chain = chain.get_or_insert_with(|| Nsec3Chain {
+                apex: apex.to_string(),
+                salt: salt.clone(),
+                iterations: *iterations,
+                entries: Vec::new(),
+            });
+            if chain.salt == *salt && chain.iterations == *iterations {
+                chain.entries.push(Nsec3Entry {
+                    owner: hash.to_string(),
+                    next: base32hex(next_hashed_owner),
+                    opt_out: flags & OPT_OUT_FLAG != 0,
+                    types: types.clone(),
+                });
+            }
+        }
+        Ok(chain)
+    }
+
+    fn hash(&self, name: &str) -> String {
+        base32hex(&nsec3_hash(name, &self.salt, self.iterations))
+    }
+
+    /// Returns the record whose owner is the hash of `name`.
+    fn matching(&self, name: &str) -> Option<&Nsec3Entry> {
+        let hash = self.hash(name);
+        self.entries.iter().find(|entry| entry.owner == hash)
+    }
+
+    /// Returns the record whose range holds the hash of `name`. Base32hex keeps the
+    /// order of the hashes, so the encoded forms are compared.
+    fn covering(&self, name: &str) -> Option<&Nsec3Entry> {
+        let hash = self.hash(name);
+        self.entries.iter().find(|entry| {
+            if entry.owner < entry.next {
+                entry.owner < hash && hash < entry.next
+            } else {
+                entry.owner < hash || hash < entry.next
+            }
+        })
+    }
+
+    /// Checks the NSEC3 proof that `name` has no `rtype` records, or does not
+    /// exist when `nxdomain` is set (RFC 5155 sections 8.4 to 8.7).
+    fn denies(
+        &self,
+        name: &str,
+        rtype: QueryType,
+        nxdomain: bool,
+        claim: &str,
+    ) -> Result<(), SecurityStatus> {
+        let fails = || bogus(format!("the NSEC3 records do not prove that {}", claim));
+        if !nxdomain && let Some(entry) = self.matching(name) {
+            return lacks(&entry.types, rtype).then_some(()).ok_or_else(fails);
+        }
+
+        // The closest encloser proof: the nearest ancestor that exists has a
+        // matching record, and the next name below it towards `name` is covered.
+        let mut encloser = parent(name);
+        while let Some(candidate) = encloser.take() {
+            if !is_subdomain(&candidate, &self.apex) {
+                break;
+            }
+            if self.matching(&candidate).is_some() {
+                encloser = Some(candidate);
+                break;
+            }
+            encloser = parent(&candidate);
+        }
+        let encloser = encloser.ok_or_else(fails)?;
+        let next_closer = suffix(name, label_count(&encloser) as usize + 1);
+        let cover = self.covering(&next_closer).ok_or_else(fails)?;
+        if cover.opt_out {
+            return Err(insecure(format!(
+                "{} lies in an NSEC3 opt-out range",
+                display_name(&next_closer)
+            )));
+        }
+
+        // Nor may a wildcard at the closest encloser have stood in for the name,
+        // unless it lacks the type too.
+        let wildcard = wildcard_of(&encloser);
+        let proven = if nxdomain {
+            self.covering(&wildcard).is_some()
+        } else {
+            self.matching(&wildcard)
+                .is_some_and(|entry| lacks(&entry.types, rtype))
+        };
+        proven.then_some(()).ok_or_else(fails)
+    }
+}
+
+/// Checks the NSEC proof that `name` has no `rtype` records, or does not exist when
+/// `nxdomain` is set (RFC 4035 section 5.4).
+fn nsec_denies(nsecs: &[NsecEntry], name: &str, rtype: QueryType, nxdomain: bool) -> bool {
+    if !nxdomain {
+        // The name exists, so its own record must leave out the type.
+        if let Some(nsec) = nsecs.iter().find(|nsec| nsec.owner == name) {
+            return lacks(&nsec.types, rtype);
+        }
+        // An empty non-terminal has no record of its own; the record before it
+        // points to a name below it.
+        if nsecs
+            .iter()
+            .any(|nsec| nsec.covers(name) && is_subdomain(&nsec.next, name))
+        {
+            return true;
+        }
+    }
+
+    // Otherwise the name must not exist, and a wildcard at its closest encloser,
+    // the longest ancestor it shares with the names around it, must not have
+    // stood in for it unless that lacks the type too.
+    let Some(cover) = nsecs.iter().find(|nsec| nsec.covers(name)) else {
+        return false;
+    };
+    let owner_side = common_ancestor(name, &cover.owner);
+    let next_side = common_ancestor(name, &cover.next);
+    let encloser = if label_count(&owner_side) >= label_count(&next_side) {
+        owner_side
+    } else {
+        next_side
+    };
+    let wildcard = wildcard_of(&encloser);
+    if nxdomain {
+        nsecs.iter().any(|nsec| nsec.covers(&wildcard))
+    } else {
+        nsecs
+            .iter()
+            .any(|nsec| nsec.owner == wildcard && lacks(&nsec.types, rtype))
+    }
+}
+
+/// Whether a name with the listed types has no records of type `rtype`, counting
+/// a CNAME, which would have been returned instead.
+fn lacks(types: &[QueryType], rtype: QueryType) -> bool {
+    !types.contains(&rtype) && !types.contains(&QueryType::CNAME)
+}
+
+/// Returns the data an RRSIG record signs (RFC 4034 section 3.1.8.1): its own
+/// data up to the signature, then the RRset in canonical form with the original TTL,
+/// under the wildcard name when the records were expanded from one.
+fn signed_data(
+    owner: &str,
+    records: &[ResourceRecord],
+    signature: &ResourceRecord,
+) -> Result<Vec<u8>, String> {
+    let RData::RRSIG {
+        labels,
+        original_ttl,
+        signature: signature_bytes,
+        ..
+    } = &signature.data
+    else {
+        return Err("the signature is not an RRSIG record".to_string());
+    };
+    let mut data = signature.canonical_rdata()?;
+    data.truncate(data.len() - signature_bytes.len());
+    let name = if *labels < label_count(owner) {
+        wildcard_of(&suffix(owner, usize::from(*labels)))
+    } else {
+        owner.to_string()
+    };
+    let records: Vec<ResourceRecord> = records
+        .iter()
+        .map(|record| ResourceRecord {
+            name: DnsName::new_unchecked(name.clone()),
+            ..record.clone()
+        })
+        .collect();
+    data.extend(pack_canonical_rrset(&records, Some(*original_ttl))?);
+    Ok(data)
+}
+
+/// Verifies a signature with a DNSKEY public key of the given algorithm.
+fn verify(algorithm: u8, public_key: &[u8], data: &[u8], signature: &[u8]) -> bool {
+    match algorithm {
+        8 => verify_rsa(public_key, HashAlgorithm::Sha256, data, signature),
+        10 => verify_rsa(public_key, HashAlgorithm::Sha512, data, signature),
+        13 => verify_ecdsa_p256(public_key, data, signature),
+        _ => false,
+    }
+}
+
+/// Whether signatures of a DNSSEC algorithm can be verified.
+fn supported_algorithm(algorithm: u8) -> bool {
+    matches!(algorithm, 8 | 10 | 13)
+}
+
+/// Returns the hash of a DS digest type, if it is supported.
+fn digest_algorithm(digest_type: u8) -> Option<HashAlgorithm> {
+    match digest_type {
+        2 => Some(HashAlgorithm::Sha256),
+        4 => Some(HashAlgorithm::Sha384),
+        _ => None,
+    }
+}
+
+/// Whether a DS record of the zone at `apex` refers to a DNSKEY record: the
+/// digest covers the owner name and the key data (RFC 4034 section 5.1.4).
+fn ds_matches(ds: &ResourceRecord, apex: &str, key: &ResourceRecord) -> bool {
+    let (
+        RData::DS {
+            key_tag,
+            algorithm,
+            digest_type,
+            digest,
+        },
+        RData::DNSKEY {
+            algorithm: key_algorithm,
+            ..
+        },
+    ) = (&ds.data, &key.data)
+    else {
+        return false;
+    };
+    let Some(hash) = digest_algorithm(*digest_type) else {
+        return false;
+    };
+    if algorithm != key_algorithm || key.data.key_tag() != Some(*key_tag) {
+        return false;
+    }
+    key_digest(apex, key, hash).is_some_and(|key_digest| key_digest == *digest)
+}
+
+/// Computes the digest a DS record holds for the DNSKEY record `key` of `apex`: a
+/// hash of the owner name and the key's data (RFC 4034 section 5.1.4).
+fn key_digest(apex: &str, key: &ResourceRecord, hash: HashAlgorithm) -> Option<Vec<u8>> {
+    let mut data = Vec::new();
+    pack_domain_name(&mut data, apex).ok()?;
+    data.extend(key.canonical_rdata().ok()?);
+    Some(hash.digest(&data))
+}
+
+/// Hashes a name as NSEC3 does (RFC 5155 section 5): SHA-1 over the canonical
+/// wire form and the salt, then again over each digest and the salt.
+fn nsec3_hash(name: &str, salt: &[u8], iterations: u16) -> Vec<u8> {
+    let mut digest = Vec::new();
+    // Names reaching here have already been packed once, so encoding succeeds.
+    let _ = pack_domain_name(&mut digest, &canonical_name(name));
+    for _ in 0..=iterations {
+        digest.extend_from_slice(salt);
+        digest = HashAlgorithm::Sha1.digest(&digest);
+    }
+    digest
+}
+
+/// Groups records into RRsets by canonical owner name and type, leaving out
+/// signatures and OPT records.
+fn rrsets(records: &[ResourceRecord]) -> Vec<(String, QueryType, Vec<ResourceRecord>)> {
+    let mut sets: Vec<(String, QueryType, Vec<ResourceRecord>)> = Vec::new();
+    for record in records {
+        if matches!(record.rtype, QueryType::RRSIG | QueryType::OPT) {
+            continue;
+        }
+        let owner = canonical_name(&record.name);
+        match sets
+            .iter_mut()
+            .find(|(name, rtype, _)| *name == owner && *rtype == record.rtype)
+        {
+            Some((_, _, set)) => set.push(record.clone()),
+            None => sets.push((owner, record.rtype, vec![record.clone()])),
+        }
+    }
+    sets
+}
+
+/// Returns the records of one RRset.
+fn records_of(records: &[ResourceRecord], owner: &str, rtype: QueryType) -> Vec<ResourceRecord> {
+    records
+        .iter()
+        .filter(|record| record.rtype == rtype && canonical_name(&record.name) == owner)
+        .cloned()
+        .collect()
+}
+
+/// Returns the RRSIG records over one RRset.
+fn signatures_for(
+    records: &[ResourceRecord],
+    owner: &str,
+    rtype: QueryType,
+) -> Vec<ResourceRecord> {
+    records
+        .iter()
+        .filter(|record| {
+            matches!(&record.data, RData::RRSIG { type_covered, .. } if *type_covered == rtype)
+                && canonical_name(&record.name) == owner
+        })
+        .cloned()
+        .collect()
+}
+
+/// Follows CNAME records from `name` to the name the answer is about, unless the
+/// CNAME itself was asked for.
+fn alias_target(name: &str, qtype: QueryType, answers: &[ResourceRecord]) -> String {
+    let mut target = canonical_name(name);
+    if qtype == QueryType::CNAME {
+        return target;
+    }
+    // Every hop uses up a record, which also ends alias loops.
+    for _ in 0..answers.len() {
+        let next = answers.iter().find_map(|record| match &record.data {
+            RData::CNAME(next) if canonical_name(&record.name) == target => {
+                Some(canonical_name(next))
+            }
+            _ => None,
+        });
+        match next {
+            Some(next) => target = next,
+            None => break,
+        }
+    }
+    target
+}
+
+/// Folds the outcome of one check into the outcome of the response: insecure
+/// outcomes are kept for the end, anything bogus ends validation at once.
+fn note(
+    insecure: &mut Option<SecurityStatus>,
+    result: Result<(), SecurityStatus>,
+) -> Result<(), SecurityStatus> {
+    match result {
+        Err(status @ SecurityStatus::Insecure(_)) => {
+            insecure.get_or_insert(status);
+            Ok(())
+        }
+        other => other,
+    }
+}
+
+fn bogus(reason: impl Into<String>) -> SecurityStatus {
+    SecurityStatus::Bogus(reason.into())
+}
+
+fn insecure(reason: impl Into<String>) -> SecurityStatus {
+    SecurityStatus::Insecure(reason.into())
+}
+
+/// Returns the parent of a canonical name, or `None` for the root.
+fn parent(name: &str) -> Option<String> {
+    if name.is_empty() {
+        return None;
+    }
+    Some(
+        name.split_once('.')
+            .map_or("", |(_, rest)| rest)
+            .to_string(),
+    )
+}
+
+/// Counts the labels of a canonical name as the RRSIG labels field does, leaving
+/// out a leading wildcard label.
+fn label_count(name: &str) -> u8 {
+    if name.is_empty() {
+        return 0;
+    }
+    let labels = name.split('.').count() - usize::from(name.starts_with("*."));
+    labels as u8
+}
+
+/// Returns the last `count` labels of a canonical name.
+fn suffix(name: &str, count: usize) -> String {
+    let labels: Vec<&str> = name.split('.').filter(|label| !label.is_empty()).collect();
+    labels[labels.len().saturating_sub(count)..].join(".")
+}
+
+/// Returns the wildcard name directly below `name`.
+fn wildcard_of(name: &str) -> String {
+    if name.is_empty() {
+        "*".to_string()
+    } else {
+        format!("*.{}", name)
+    }
+}
+
+/// Returns the longest common ancestor of two canonical names.
+fn common_ancestor(a: &str, b: &str) -> String {
+    let common: Vec<&str> = a
+        .rsplit('.')
+        .zip(b.rsplit('.'))
+        .take_while(|(a, b)| a == b && !a.is_empty())
+        .map(|(label, _)| label)
+        .collect();
+    common.into_iter().rev().collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{TEST_RSA_MODULUS, TEST_RSA_PRIVATE_EXPONENT, sign_rsa};
+    use crate::testutil::{a_record, record};
+    use crate::transcript::decode_hex;
+    use std::time::Duration;
+
+    /// The time validation runs at; test signatures are valid for a day either side.
+    const NOW: u32 = 1_760_000_000;
+
+    fn address(name: &str, last_octet: u8) -> ResourceRecord {
+        a_record(name, [192, 0, 2, last_octet], 3600)
+    }
+
+    /// Every test zone uses the same RSA/SHA-256 key.
+    fn dnskey(zone: &str) -> ResourceRecord {
+        let mut public_key = vec![3, 0x01, 0x00, 0x01];
+        public_key.extend(decode_hex(TEST_RSA_MODULUS).unwrap());
+        record(
+            zone,
+            3600,
+            RData::DNSKEY {
+                flags: 257,
+                protocol: 3,
+                algorithm: 8,
+                public_key,
+            },
+        )
+    }
+
+    fn ds(zone: &str) -> ResourceRecord {
+        let key = dnskey(zone);
+        let mut data = Vec::new();
+        pack_domain_name(&mut data, zone).unwrap();
+        data.extend(key.canonical_rdata().unwrap());
+        record(
+            zone,
+            3600,
+            RData::DS {
+                key_tag: key.data.key_tag().unwrap(),
+                algorithm: 8,
+                digest_type: 2,
+                digest: HashAlgorithm::Sha256.digest(&data),
+            },
+        )
+    }
+
+    fn nsec(name: &str, next: &str, types: &[QueryType]) -> ResourceRecord {
+        record(
+            name,
+            3600,
+            RData::NSEC {
+                next_domain: next.to_string(),
+                types: types.to_vec(),
+            },
+        )
+    }
+
+    /// Signs an RRset with the key of `zone`, returning it with its signature.
+    fn signed(zone: &str, records: Vec<ResourceRecord>) -> Vec<ResourceRecord> {
+        let owner = canonical_name(&records[0].name);
+        let mut signature = ResourceRecord {
+            name: records[0].name.clone(),
+            rtype: QueryType::RRSIG,
+            rclass: 1,
+            ttl: 3600,
+            data: RData::RRSIG {
+                type_covered: records[0].rtype,
+                algorithm: 8,
+                labels: label_count(&owner),
+                original_ttl: 3600,
+                expiration: NOW + 86400,
+                inception: NOW - 86400,
+                key_tag: dnskey(zone).data.key_tag().unwrap(),
+                signer_name: zone.to_string(),
+                signature: Vec::new(),
+            },
+        };
+        let data = signed_data(&owner, &records, &signature).unwrap();
+        if let RData::RRSIG { signature, .. } = &mut signature.data {
+            *signature = sign_rsa(
+                &decode_hex(TEST_RSA_MODULUS).unwrap(),
+                &decode_hex(TEST_RSA_PRIVATE_EXPONENT).unwrap(),
+                HashAlgorithm::Sha256,
+                &data,
+            );
+        }
+        let mut records = records;
+        records.push(signature);
+        records
+    }
+
+    fn message(
+        name: &str,
+        qtype: QueryType,
+        response_code: ResponseCode,
+        answers: Vec<ResourceRecord>,
+        authorities: Vec<ResourceRecord>,
+    ) -> DnsMessage {
+        let mut message = DnsMessage::new();
+        message.header.flags = 0x8180 | response_code as u16;
+        message.header.question_count = 1;
+        message.header.answer_count = answers.len() as u16;
+        message.header.authority_count = authorities.len() as u16;
+        message.questions.push(crate::dns::DnsQuestion {
+            name: name.parse().unwrap(),
+            qtype,
+            qclass: 1,
+        });
+        message.answers = answers;
+        message.authorities = authorities;
+        message
+    }
+
+    /// Signed test zones, answering the queries of a validator from a table of
+    /// responses in wire format.
+    struct Zones(HashMap<(String, QueryType), Vec<u8>>);
+
+    impl Zones {
+        /// The root zone with a secure delegation to com.
+        fn new() -> Self {
+            let mut zones = Zones(HashMap::new());
+            zones.add_keys("");
+            zones.delegate("", "com");
+            zones
+        }
+
+        fn insert(&mut self, name: &str, qtype: QueryType, response: DnsMessage) {
+            let mut buffer = Vec::new();
+            response.pack(&mut buffer).unwrap();
+            self.0.insert((name.to_string(), qtype), buffer);
+        }
+
+        fn add_keys(&mut self, zone: &str) {
+            let answers = signed(zone, vec![dnskey(zone)]);
+            let response = message(
+                zone,
+                QueryType::DNSKEY,
+                ResponseCode::NoError,
+                answers,
+                vec![],
+            );
+            self.insert(zone, QueryType::DNSKEY, response);
+        }
+
+        fn delegate(&mut self, parent: &str, child: &str) {
+            let answers = signed(parent, vec![ds(child)]);
+            let response = message(child, QueryType::DS, ResponseCode::NoError, answers, vec![]);
+            self.insert(child, QueryType::DS, response);
+            self.add_keys(child);
+        }
+
+        /// Answers the DS query for `name` in `zone` with no data, proven by an
+        /// NSEC record listing `types`.
+        fn no_ds(&mut self, zone: &str, name: &str, types: &[QueryType]) {
+            let proof = signed(zone, vec![nsec(name, zone, types)]);
+            let response = message(name, QueryType::DS, ResponseCode::NoError, vec![], proof);
+            self.insert(name, QueryType::DS, response);
+        }
+
+        fn nxdomain(&mut self, name: &str) {
+            let response = message(name, QueryType::DS, ResponseCode::NameError, vec![], vec![]);
+            self.insert(name, QueryType::DS, response);
+        }
+
+        fn check_at(&self, message: &DnsMessage, now: u32) -> SecurityStatus {
+            let now = UNIX_EPOCH + Duration::from_secs(u64::from(now));
+            validate_with(message, &[ds("")], now, &mut |name, qtype| {
+                let response = self
+                    .0
+                    .get(&(name.to_string(), qtype))
+                    .ok_or_else(|| format!("no test data for {} {}", name, qtype))?;
+                DnsMessage::from_bytes(response).map_err(|e| e.to_string())
+            })
+        }
+
+        fn check(&self, message: &DnsMessage) -> SecurityStatus {
+            self.check_at(message, NOW)
+        }
+    }
+
+    fn is_bogus(status: SecurityStatus) -> bool {
+        matches!(status, SecurityStatus::Bogus(_))
+    }
+
+    #[test]
+    fn test_validate_signed_answer() {
+        let mut zones = Zones::new();
+        zones.delegate("com", "example.com");
+        zones.no_ds(
+            "example.com",
+            "www.example.com",
+            &[QueryType::A, QueryType::RRSIG, QueryType::NSEC],
+        );
+        let answers = signed("example.com", vec![address("www.example.com", 1)]);
+        let response = |answers: Vec<ResourceRecord>| {
+            message(
+                "www.example.com",
+                QueryType::A,
+                ResponseCode::NoError,
+                answers,
+                vec![],
+            )
+        };
+        assert_eq!(
+            zones.check(&response(answers.clone())),
+            SecurityStatus::Secure
+        );
+
+        // Any change to the records breaks the signature.
+        let mut tampered = answers.clone();
+        tampered[0] = address("www.example.com", 2);
+        assert!(is_bogus(zones.check(&response(tampered))));
+
+        // Records from a signed zone must carry a signature.
+        assert!(is_bogus(zones.check(&response(answers[..1].to_vec()))));
+
+        // Signatures are only valid within their validity period.
+        assert!(is_bogus(
+            zones.check_at(&response(answers.clone()), NOW + 2 * 86400)
+        ));
+
+        // Keys that the parent's DS records do not vouch for are not trusted.
+        let mut wrong_ds = ds("example.org");
+        wrong_ds.name = "example.com".parse().unwrap();
+        zones.insert(
+            "example.com",
+            QueryType::DS,
+            message(
+                "example.com",
+                QueryType::DS,
+                ResponseCode::NoError,
+                signed("com", vec![wrong_ds]),
+                vec![],
+            ),
+        );
+        assert!(is_bogus(zones.check(&response(answers))));
+    }
+
+    #[test]
+    fn test_validate_insecure_delegation() {
+        let mut zones = Zones::new();
+        zones.no_ds(
+            "com",
+            "example.com",
+            &[QueryType::NS, QueryType::RRSIG, QueryType::NSEC],
+        );
+        let response = message(
+            "www.example.com",
+            QueryType::A,
+            ResponseCode::NoError,
+            vec![address("www.example.com", 1)],
+            vec![],
+        );
+        assert!(matches!(
+            zones.check(&response),
+            SecurityStatus::Insecure(reason) if reason.contains("example.com")
+        ));
+    }
+
+    #[test]
+    fn test_validate_denial_of_existence() {
+        let mut zones = Zones::new();
+        zones.delegate("com", "example.com");
+        zones.no_ds(
+            "example.com",
+            "www.example.com",
+            &[QueryType::A, QueryType::RRSIG, QueryType::NSEC],
+        );
+        zones.nxdomain("nope.example.com");
+
+        // The NSEC record from the apex to www covers both the name and the
+        // wildcard that could have matched it.
+        let apex = signed(
+            "example.com",
+            vec![nsec("example.com", "www.example.com", &[QueryType::NS])],
+        );
+        let nxdomain = |authorities| {
+            message(
+                "nope.example.com",
+                QueryType::A,
+                ResponseCode::NameError,
+                vec![],
+                authorities,
+            )
+        };
+        assert_eq!(zones.check(&nxdomain(apex)), SecurityStatus::Secure);
+        assert!(is_bogus(zones.check(&nxdomain(vec![]))));
+
+        // The NSEC record of a name must leave out the type asked for.
+        let nodata = |types: &[QueryType]| {
+            message(
+                "www.example.com",
+                QueryType::AAAA,
+                ResponseCode::NoError,
+                vec![],
+                signed(
+                    "example.com",
+                    vec![nsec("www.example.com", "example.com", types)],
+                ),
+            )
+        };
+        assert_eq!(
+            zones.check(&nodata(&[QueryType::A, QueryType::NSEC])),
+            SecurityStatus::Secure
+        );
+        assert!(is_bogus(zones.check(&nodata(&[QueryType::AAAA]))));
+    }
+
+    #[test]
+    fn test_validate_nsec3_denial() {
+        let mut zones = Zones::new();
+        zones.delegate("com", "example.com");
+        zones.nxdomain("nope.example.com");
+
+        // A single NSEC3 record matches the apex and covers every other hash.
+        let salt = vec![0xaa, 0xbb];
+        let apex_hash = base32hex(&nsec3_hash("example.com", &salt, 1));
+        let proof = |flags| {
+            signed(
+                "example.com",
+                vec![record(
+                    &format!("{}.example.com", apex_hash),
+                    3600,
+                    RData::NSEC3 {
+                        hash_algorithm: 1,
+                        flags,
+                        iterations: 1,
+                        salt: salt.clone(),
+                        next_hashed_owner: nsec3_hash("example.com", &salt, 1),
+                        types: vec![QueryType::NS, QueryType::SOA],
+                    },
+                )],
+            )
+        };
+        let nxdomain = |authorities| {
+            message(
+                "nope.example.com",
+                QueryType::A,
+                ResponseCode::NameError,
+                vec![],
+                authorities,
+            )
+        };
+        assert_eq!(zones.check(&nxdomain(proof(0))), SecurityStatus::Secure);
+        assert!(matches!(
+            zones.check(&nxdomain(proof(OPT_OUT_FLAG))),
+            SecurityStatus::Insecure(_)
+        ));
+    }
+
+    #[test]
+    fn test_nsec3_hash() {
+        // From RFC 5155 appendix A.
+        let salt = [0xaa, 0xbb, 0xcc, 0xdd];
+        assert_eq!(
+            base32hex(&nsec3_hash("example", &salt, 12)),
+            "0p9mhaveqvm6t7vbl5lop2u3t2rp3tom"
+        );
+        assert_eq!(
+            base32hex(&nsec3_hash("A.Example.", &salt, 12)),
+            "35mthgpgcu1qg68fab165klnsnk3dpvl"
+        );
+    }
+
+    #[test]
+    fn test_parse_trust_anchors() {
+        let text = "; The root keys, as IANA publishes them\n\
+            . IN DS 20326 8 2 E06D44B80B8F1D39A95C0B0D7C65D08458E880409BBC683457104237C7F8EC8D\n\
+            \n\
+            . 86400 IN DS 38696 8 2 683D2D0ACB8C9B712A1948B27F741219 \
+            298D0A450D612C483AF444A4C0FB2B16 ; KSK-2024\n";
+        assert_eq!(parse_trust_anchors(text).unwrap(), root_trust_anchors());
+
+        // A key stands for the DS record that vouches for it.
+        let key = format!(". 3600 IN {}", dnskey("").data);
+        let anchors = parse_trust_anchors(&key).unwrap();
+        assert_eq!(anchors[0].data, ds("").data);
+
+        for bad in [
+            "",
+            "com. IN DS 20326 8 2 E06D44B8",
+            ". IN MX 10 mail.example.",
+            ". IN DS 20326 8 2 E06D44B",
+            ". IN DS 20326 8",
+            ". IN DNSKEY 1 3 8 AwEAAQ==",
+        ] {
+            assert!(parse_trust_anchors(bad).is_err(), "{}", bad);
+        }
+        assert!(
+            parse_trust_anchors("\n. IN DS x 8 2 00")
+                .unwrap_err()
+                .starts_with("line 2:")
+        );
+    }
+
+    #[test]
+    fn test_root_trust_anchors() {
+        let anchors = root_trust_anchors();
+        assert_eq!(anchors.len(), 2);
+        assert!(anchors.iter().all(|anchor| {
+            matches!(&anchor.data, RData::DS { algorithm: 8, digest_type: 2, digest, .. }
+                if digest.len() == 32)
+        }));
+    }
+}
@@ -0,0 +1,577 @@
+//! A caching replacement for repeated host lookups.
+//!
+//! Long-running programs that call [`std::net::ToSocketAddrs`] for the same few hosts
+//! over and over pay for a full lookup each time, unless the system happens to run a
+//! caching daemon. [`HostCache::lookup_host`] returns the addresses of a name and
+//! remembers them for as long as their TTL allows. Names that do not exist or have no
+//! addresses are remembered too (negative caching, RFC 2308), so a misconfigured
+//! host name does not turn into a query storm. Hit and miss counts are kept so the
+//! cache's effectiveness can be monitored.
+//!
+//! How long entries are kept can be bounded with a [`TtlPolicy`]: a minimum stops
+//! zones with very short TTLs from defeating the cache, and a maximum keeps a
+//! failover from being hidden behind a week-long TTL. Either bound can be set
+//! differently for individual zones.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::host_cache::HostCache;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! let cache = HostCache::new(Ipv4Addr::new(8, 8, 8, 8), QueryOptions::default());
+//! for _ in 0..3 {
+//!     let addrs = cache.lookup_host("example.com")?;
+//!     println!("{:?}", addrs);
+//! }
+//! println!("hit rate: {:.0}%", cache.stats().hit_rate() * 100.0);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::addrs::{AddressOrder, order_addresses};
+use crate::dns::{QueryType, ResponseCode};
+use crate::resolver::{DnsError, QueryOptions, QueryResult, query};
+
+/// The longest time any answer is cached by default, however large its TTL.
+pub const MAX_CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long a negative answer is cached when the response carries no SOA record
+/// to take the negative TTL from.
+pub const DEFAULT_NEGATIVE_TTL: Duration = Duration::from_secs(30);
+
+/// Bounds on how long answers are cached, applied when they are stored.
+///
+/// The TTL of each answer is raised to `min_ttl` and lowered to `max_ttl`, or to the
+/// bounds of the most specific matching [`ZoneTtlOverride`]. The bounds apply to
+/// negative answers as well. A raised minimum also caches answers with a TTL of zero,
+/// which are otherwise not cached at all.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TtlPolicy {
+    /// The shortest time any answer is cached.
+    pub min_ttl: Duration,
+    /// The longest time any answer is cached.
+    pub max_ttl: Duration,
+    /// Different bounds for names in particular zones.
+    pub zone_overrides: Vec<ZoneTtlOverride>,
+}
+
+impl Default for TtlPolicy {
+    /// Cache answers for their own TTL, up to [`MAX_CACHE_TTL`].
+    fn default() -> Self {
+        TtlPolicy {
+            min_ttl: Duration::ZERO,
+            max_ttl: MAX_CACHE_TTL,
+            zone_overrides: Vec::new(),
+        }
+    }
+}
+
+/// TTL bounds for the names in one zone, replacing those of the [`TtlPolicy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneTtlOverride {
+    /// The zone, matching the name itself and every name below it.
+    pub zone: String,
+    /// The shortest time to cache answers in the zone, or `None` for the policy's.
+    pub min_ttl: Option<Duration>,
+    /// The longest time to cache answers in the zone, or `None` for the policy's.
+    pub max_ttl: Option<Duration>,
+}
+
+impl TtlPolicy {
+    /// Returns how long to cache an answer for `name` that came with `ttl`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::host_cache::{TtlPolicy, ZoneTtlOverride};
+    /// use std::time::Duration;
+    ///
+    /// let policy = TtlPolicy {
+    ///     min_ttl: Duration::from_secs(60),
+    ///     zone_overrides: vec![ZoneTtlOverride {
+    ///         zone: "failover.example".to_string(),
+    ///         min_ttl: Some(Duration::ZERO),
+    ///         max_ttl: Some(Duration::from_secs(30)),
+    ///     }],
+    ///     ..TtlPolicy::default()
+    /// };
+    ///
+    /// assert_eq!(policy.clamp("cdn.example", Duration::from_secs(5)), Duration::from_secs(60));
+    /// assert_eq!(policy.clamp("db.failover.example", Duration::from_secs(600)), Duration::from_secs(30));
+    /// ```
+    pub fn clamp(&self, name: &str, ttl: Duration) -> Duration {
+        let name = name.trim_end_matches('.');
+        let zone_override = self
+            .zone_overrides
+            .iter()
+            .filter(|o| in_zone(name, &o.zone))
+            .max_by_key(|o| o.zone.trim_end_matches('.').len());
+        let min_ttl = zone_override
+            .and_then(|o| o.min_ttl)
+            .unwrap_or(self.min_ttl);
+        let max_ttl = zone_override
+            .and_then(|o| o.max_ttl)
+            .unwrap_or(self.max_ttl);
+        // A minimum above the maximum is a misconfiguration; the maximum wins.
+        ttl.max(min_ttl).min(max_ttl)
+    }
+}
+
+/// Returns whether `name` is `zone` or a name below it, ignoring ASCII case.
+fn in_zone(name: &str, zone: &str) -> bool {
+    let zone = zone.trim_end_matches('.');
+    if zone.is_empty() {
+        return true;
+    }
+    let Some(prefix_len) = name.len().checked_sub(zone.len()) else {
+        return false;
+    };
+    name.is_char_boundary(prefix_len)
+        && name[prefix_len..].eq_ignore_ascii_case(zone)
+        && (prefix_len == 0 || name[..prefix_len].ends_with('.'))
+}
+
+/// A cached lookup outcome.
+#[derive(Debug, Clone)]
+enum Cached {
+    /// The addresses of the name, empty if it exists but has none.
+    Addresses(Vec<IpAddr>),
+    /// The name does not exist (NXDOMAIN).
+    NameError,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    outcome: Cached,
+    expires: Instant,
+}
+
+/// Counters describing how well a [`HostCache`] or a
+/// [`DnsCache`](crate::cache::DnsCache) is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheStats {
+    /// Lookups answered from the cache, including negative answers.
+    pub hits: u64,
+    /// Of the hits, those that returned a cached negative answer.
+    pub negative_hits: u64,
+    /// Lookups that had to query the server.
+    pub misses: u64,
+    /// Entries dropped before they expired to keep the cache within its limits.
+    pub evictions: u64,
+    /// Expired responses served because the servers could not be reached
+    /// (RFC 8767). These are not counted as hits.
+    pub stale_hits: u64,
+    /// Refreshes started in the background for popular entries about to expire.
+    pub prefetches: u64,
+    /// The number of names currently cached, including expired entries that have
+    /// not been looked up since.
+    pub entries: usize,
+}
+
+impl CacheStats {
+    /// Returns the fraction of lookups answered from the cache, between 0 and 1.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+/// A host lookup cache that is safe to share between threads.
+///
+/// Each cache owns its own entries; share one instance (for example in an `Arc`)
+/// to share its contents. Failures that say nothing about the name, such as
+/// timeouts or SERVFAIL, are never cached.
+#[derive(Debug)]
+pub struct HostCache {
+    dns_server_addr: Ipv4Addr,
+    options: QueryOptions,
+    ttl_policy: TtlPolicy,
+    entries: Mutex<HashMap<String, CacheEntry>>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl HostCache {
+    /// Creates an empty cache that resolves names through `dns_server_addr`.
+    ///
+    /// # Arguments
+    ///
+    /// * `dns_server_addr` - The IPv4 address of the DNS server to query
+    /// * `options` - Settings for every query the cache sends
+    pub fn new(dns_server_addr: Ipv4Addr, options: QueryOptions) -> Self {
+        Self::with_ttl_policy(dns_server_addr, options, TtlPolicy::default())
+    }
+
+    /// Creates an empty cache like [`HostCache::new`] that bounds how long answers
+    /// are kept with `ttl_policy`.
+    pub fn with_ttl_policy(
+        dns_server_addr: Ipv4Addr,
+        options: QueryOptions,
+        ttl_policy: TtlPolicy,
+    ) -> Self {
+        HostCache {
+            dns_server_addr,
+            options,
+            ttl_policy,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the IPv6 and IPv4 addresses of `name`, from the cache if possible.
+    ///
+    /// Addresses are returned with the families interleaved, IPv6 first. Names are
+    /// compared case-insensitively and with or without a trailing dot.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<IpAddr>)` - The addresses of the name, empty if it has none
+    /// * `Err(DnsError)` - If the name does not exist or could not be resolved
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DnsError::ServerReturnedError`] with [`ResponseCode::NameError`] if the
+    /// name does not exist (possibly from the cache), and any other error of
+    /// [`query`] if both address lookups failed.
+    pub fn lookup_host(&self, name: &str) -> Result<Vec<IpAddr>, DnsError> {
+        let key = name.trim_end_matches('.').to_lowercase();
+        if let Some(outcome) = self.cached(&key, Instant::now()) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return match outcome {
+                Cached::Addresses(addrs) => {
+                    if addrs.is_empty() {
+                        self.negative_hits.fetch_add(1, Ordering::Relaxed);
+                    }
+                    Ok(addrs)
+                }
+                Cached::NameError => {
+                    self.negative_hits.fetch_add(1, Ordering::Relaxed);
+                    Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+                }
+            };
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let name = key.as_str();
+        let results: Vec<Result<QueryResult, DnsError>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = [QueryType::AAAA, QueryType::A]
+                .into_iter()
+                .map(|query_type| {
+                    scope
+                        .spawn(move || query(name, query_type, self.dns_server_addr, &self.options))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("query thread panicked"))
+                .collect()
+        });
+
+        let (outcome, ttl) = combine_responses(results)?;
+        self.store(key, outcome.clone(), ttl, Instant::now());
+        match outcome {
+            Cached::Addresses(addrs) => Ok(addrs),
+            Cached::NameError => Err(DnsError::ServerReturnedError(ResponseCode::NameError)),
+        }
+    }
+
+    /// Returns the hit and miss counters and the current number of entries.
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            // Host entries are only ever dropped when they expire.
+            evictions: 0,
+            stale_hits: 0,
+            prefetches: 0,
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+
+    /// Removes every cached entry. The counters are kept.
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// Returns the cached outcome for `key` if it has not expired at `now`,
+    /// dropping the entry if it has.
+    fn cached(&self, key: &str, now: Instant) -> Option<Cached> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires > now => Some(entry.outcome.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Caches `outcome` for `key` for `ttl` from `now`, within the TTL policy.
+    fn store(&self, key: String, outcome: Cached, ttl: Duration, now: Instant) {
+        let ttl = self.ttl_policy.clamp(&key, ttl);
+        if ttl.is_zero() {
+            return;
+        }
+        let expires = now + ttl;
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key, CacheEntry { outcome, expires });
+    }
+}
+
+/// Combines the AAAA and A responses into one cacheable outcome and its TTL.
+///
+/// The addresses are cached for the smallest TTL among the records that led to
+/// them, including any CNAMEs. A negative answer is cached for the TTL of the SOA
+/// record in the authority section (RFC 2308), or [`DEFAULT_NEGATIVE_TTL`].
+fn combine_responses(
+    results: Vec<Result<QueryResult, DnsError>>,
+) -> Result<(Cached, Duration), DnsError> {
+    let mut ipv6 = Vec::new();
+    let mut ipv4 = Vec::new();
+    let mut positive_ttl: Option<u32> = None;
+    let mut negative_ttl: Option<u32> = None;
+    let mut name_error = false;
+    let mut answered = false;
+    let mut first_error = None;
+
+    for result in results {
+        let message = match result {
+            Ok(result) => result.message,
+            Err(e) => {
+                first_error.get_or_insert(e);
+                continue;
+            }
+        };
+        match message.header.get_response_code() {
+            ResponseCode::NoError => answered = true,
+            ResponseCode::NameError => name_error = true,
+            code => {
+                first_error.get_or_insert(DnsError::ServerReturnedError(code));
+                continue;
+            }
+        }
+        for record in &message.answers {
+            ipv6.extend(record.get_ipv6_address().map(IpAddr::V6));
+            ipv4.extend(record.get_ipv4_address().map(IpAddr::V4));
+            positive_ttl = Some(positive_ttl.map_or(record.ttl, |ttl| ttl.min(record.ttl)));
+        }
+        if message.answers.is_empty() {
+            for record in message
+                .authorities
+                .iter()
+                .filter(|r| r.rtype == QueryType::SOA)
+            {
+                negative_ttl = Some(negative_ttl.map_or(record.ttl, |ttl| ttl.min(record.ttl)));
+            }
+        }
+    }
+
+    let negative_ttl = negative_ttl
+        .map(|ttl| Duration::from_secs(ttl.into()))
+        .unwrap_or(DEFAULT_NEGATIVE_TTL);
+    if name_error && !answered {
+        return Ok((Cached::NameError, negative_ttl));
+    }
+    if !ipv6.is_empty() || !ipv4.is_empty() {
+        let ttl = Duration::from_secs(positive_ttl.unwrap_or(0).into());
+        let addrs = order_addresses(ipv6, ipv4, AddressOrder::Interleaved);
+        return Ok((Cached::Addresses(addrs), ttl));
+    }
+    match first_error {
+        // Without a definitive answer for both families there is nothing to cache.
+        Some(e) => Err(e),
+        None => Ok((Cached::Addresses(Vec::new()), negative_ttl)),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::dns::{DnsMessage, RData, ResourceRecord};
+    use std::io::{Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Serves `www.example.com` (A and AAAA, TTL 300), `v4.example.com` (A only) and
+    /// NXDOMAIN for anything else over a Unix socket, counting the queries received.
+    fn start_server(tag: &str) -> (QueryOptions, Arc<AtomicUsize>) {
+        let path = std::env::temp_dir().join(format!(
+            "dns-resolver-cache-{}-{}.sock",
+            tag,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let queries = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&queries);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut length = [0; 2];
+                stream.read_exact(&mut length).unwrap();
+                let mut request = vec![0; u16::from_be_bytes(length) as usize];
+                stream.read_exact(&mut request).unwrap();
+
+                let mut response = DnsMessage::from_bytes(&request).unwrap();
+                let question = response.questions[0].clone();
+                let record = |data| ResourceRecord {
+                    name: question.name.clone(),
+                    rtype: question.qtype,
+                    rclass: 1,
+                    ttl: 300,
+                    data,
+                };
+                match (question.name.as_str(), question.qtype) {
+                    ("www.example.com", QueryType::A) | ("v4.example.com", QueryType::A) => {
+                        response
+                            .answers
+                            .push(record(RData::A(Ipv4Addr::new(192, 0, 2, 1))))
+                    }
+                    ("www.example.com", QueryType::AAAA) => response
+                        .answers
+                        .push(record(RData::AAAA("2001:db8::1".parse().unwrap()))),
+                    ("v4.example.com", _) => {}
+                    _ => response.header.set_response_code(ResponseCode::NameError),
+                }
+                if response.answers.is_empty() {
+                    response.authorities.push(ResourceRecord {
+                        name: "example.com".parse().unwrap(),
+                        rtype: QueryType::SOA,
+                        rclass: 1,
+                        ttl: 45,
+                        data: RData::SOA {
+                            mname: "ns1.example.com".to_string(),
+                            rname: "hostmaster.example.com".to_string(),
+                            serial: 1,
+                            refresh: 7200,
+                            retry: 3600,
+                            expire: 1209600,
+                            minimum: 300,
+                        },
+                    });
+                }
+                response.header.set_response(true);
+                response.header.set_recursion_available(true);
+                response.header.answer_count = response.answers.len() as u16;
+                response.header.authority_count = response.authorities.len() as u16;
+
+                let mut buffer = Vec::new();
+                response.pack(&mut buffer).unwrap();
+                stream
+                    .write_all(&(buffer.len() as u16).to_be_bytes())
+                    .unwrap();
+                stream.write_all(&buffer).unwrap();
+            }
+        });
+        let options = QueryOptions {
+            unix_socket: Some(path),
+            ..QueryOptions::default()
+        };
+        (options, queries)
+    }
+
+    #[test]
+    fn test_answers_are_cached() {
+        let (options, queries) = start_server("positive");
+        let cache = HostCache::new(Ipv4Addr::LOCALHOST, options);
+
+        let first = cache.lookup_host("www.example.com").unwrap();
+        assert_eq!(
+            first,
+            vec![
+                "2001:db8::1".parse::<IpAddr>().unwrap(),
+                "192.0.2.1".parse().unwrap()
+            ]
+        );
+        assert_eq!(cache.lookup_host("WWW.Example.com.").unwrap(), first);
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+
+        // A name with only an A record is still a positive answer.
+        assert_eq!(cache.lookup_host("v4.example.com").unwrap().len(), 1);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 2));
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_negative_answers_are_cached_for_the_soa_ttl() {
+        let (options, queries) = start_server("negative");
+        let cache = HostCache::new(Ipv4Addr::LOCALHOST, options);
+
+        for _ in 0..3 {
+            assert!(matches!(
+                cache.lookup_host("missing.example.com"),
+                Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+            ));
+        }
+        assert_eq!(queries.load(Ordering::SeqCst), 2);
+        assert_eq!(cache.stats().negative_hits, 2);
+
+        // The entry lapses after the SOA TTL of 45 seconds.
+        let now = Instant::now();
+        assert!(cache.cached("missing.example.com", now).is_some());
+        assert!(
+            cache
+                .cached("missing.example.com", now + Duration::from_secs(46))
+                .is_none()
+        );
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn test_ttl_policy_bounds_cache_lifetime() {
+        let policy = TtlPolicy {
+            min_ttl: Duration::from_secs(600),
+            max_ttl: Duration::from_secs(3600),
+            zone_overrides: vec![ZoneTtlOverride {
+                zone: "Example.com.".to_string(),
+                min_ttl: None,
+                max_ttl: Some(Duration::from_secs(10)),
+            }],
+        };
+        assert_eq!(
+            policy.clamp("other.test", Duration::ZERO),
+            Duration::from_secs(600)
+        );
+        assert_eq!(
+            policy.clamp("other.test", Duration::from_secs(86400)),
+            Duration::from_secs(3600)
+        );
+        // "notexample.com" is not in the example.com zone.
+        assert_eq!(
+            policy.clamp("notexample.com", Duration::from_secs(5)),
+            Duration::from_secs(600)
+        );
+
+        // Answers from the server below are 300s positive and 45s negative.
+        let (options, _) = start_server("policy");
+        let cache = HostCache::with_ttl_policy(Ipv4Addr::LOCALHOST, options, policy);
+        cache.lookup_host("www.example.com").unwrap();
+        let now = Instant::now();
+        assert!(cache.cached("www.example.com", now).is_some());
+        assert!(
+            cache
+                .cached("www.example.com", now + Duration::from_secs(11))
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,324 @@
+//! Answering lookups from a hosts file before going to the network.
+//!
+//! Programs that resolve names through the C library see the entries of
+//! `/etc/hosts` before anything DNS returns, which is how local overrides, test
+//! setups and blocklists work. A [`HostsFile`] set as [`QueryOptions::hosts`]
+//! answers A, AAAA and PTR queries for the names and addresses it lists in the same
+//! way, with a response synthesized in process. Everything else, including names it
+//! lists only with addresses of the other family, still goes to the server.
+//!
+//! Hosts answers cannot be signed, so queries that are TSIG-signed or validated
+//! with DNSSEC skip the hosts file.
+//!
+//! # File Format
+//!
+//! One address per line, followed by its canonical name and any aliases, separated
+//! by whitespace. Text from a `#` to the end of the line is a comment.
+//!
+//! ```text
+//! 127.0.0.1   localhost
+//! 192.0.2.10  build.corp.example build
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::hosts::HostsFile;
+//! use dns_resolver::resolver::Resolver;
+//! use std::net::Ipv4Addr;
+//! use std::sync::Arc;
+//!
+//! let resolver = Resolver::builder()
+//!     .server(Ipv4Addr::new(192, 0, 2, 53))
+//!     .hosts(Arc::new(HostsFile::load()?))
+//!     .build()?;
+//! let response = resolver.resolve("localhost", QueryType::A)?; // Not sent anywhere
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! [`QueryOptions::hosts`]: crate::resolver::QueryOptions::hosts
+
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+
+use crate::dns::{
+    DnsClass, DnsMessage, DnsName, QueryType, RData, ResourceRecord, canonical_name,
+    reverse_lookup_name,
+};
+use crate::idn::to_ascii;
+
+/// Where the system hosts file lives.
+pub const HOSTS_PATH: &str = "/etc/hosts";
+
+/// The TTL of records answered from a hosts file. Zero keeps them out of caches, so
+/// the file stays the only source of truth for them.
+pub const HOSTS_TTL: u32 = 0;
+
+/// The entries of a hosts file, indexed for lookups by name and by address.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HostsFile {
+    /// The addresses of each name, in canonical form, in file order.
+    addresses: HashMap<String, Vec<IpAddr>>,
+    /// The canonical name of each address, keyed by its reverse-mapping name.
+    names: HashMap<String, String>,
+}
+
+impl HostsFile {
+    /// Reads [`HOSTS_PATH`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(HOSTS_PATH)
+    }
+
+    /// Reads a file in hosts format from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))
+    }
+
+    /// Parses the text of a hosts file, skipping lines that do not start with an
+    /// address, as the C library does.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::hosts::HostsFile;
+    /// use std::net::IpAddr;
+    ///
+    /// let hosts = HostsFile::parse("192.0.2.10 build.corp.example build # CI\n");
+    /// let address: IpAddr = "192.0.2.10".parse().unwrap();
+    /// assert_eq!(hosts.addresses("BUILD."), [address]);
+    /// assert_eq!(hosts.name(&address), Some("build.corp.example"));
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut hosts = HostsFile::default();
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let mut fields = line.split_whitespace();
+            // Scoped IPv6 addresses carry their interface after a `%`.
+            let Some(address) = fields
+                .next()
+                .and_then(|address| address.split('%').next())
+                .and_then(|address| address.parse::<IpAddr>().ok())
+            else {
+                continue;
+            };
+            let names: Vec<String> = fields.map(host_key).collect();
+            let Some(canonical) = names.first() else {
+                continue;
+            };
+            // The first line that lists an address names it.
+            hosts
+                .names
+                .entry(reverse_lookup_name(&address))
+                .or_insert_with(|| canonical.clone());
+            for name in names {
+                let addresses = hosts.addresses.entry(name).or_default();
+                if !addresses.contains(&address) {
+                    addresses.push(address);
+                }
+            }
+        }
+        hosts
+    }
+
+    /// Returns the addresses listed for `name`, compared case-insensitively and with
+    /// or without a trailing dot. Unicode names match their A-labels.
+    pub fn addresses(&self, name: &str) -> &[IpAddr] {
+        self.addresses
+            .get(&host_key(name))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the canonical name of `address`: the first name on the first line
+    /// that lists it.
+    pub fn name(&self, address: &IpAddr) -> Option<&str> {
+        self.names
+            .get(&reverse_lookup_name(address))
+            .map(String::as_str)
+    }
+
+    /// Returns the records that answer a question, or `None` if the file has none
+    /// and the question should go to the server.
+    pub fn records(
+        &self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+    ) -> Option<Vec<ResourceRecord>> {
+        if class != DnsClass::IN {
+            return None;
+        }
+        let record = |data| ResourceRecord {
+            name: DnsName::new_unchecked(name.to_string()),
+            rtype: query_type,
+            rclass: u16::from(DnsClass::IN),
+            ttl: HOSTS_TTL,
+            data,
+        };
+        let records: Vec<ResourceRecord> = match query_type {
+            QueryType::A => self
+                .addresses(name)
+                .iter()
+                .filter_map(|address| match address {
+                    IpAddr::V4(address) => Some(record(RData::A(*address))),
+                    IpAddr::V6(_) => None,
+                })
+                .collect(),
+            QueryType::AAAA => self
+                .addresses(name)
+                .iter()
+                .filter_map(|address| match address {
+                    IpAddr::V6(address) => Some(record(RData::AAAA(*address))),
+                    IpAddr::V4(_) => None,
+                })
+                .collect(),
+            QueryType::PTR => self
+                .names
+                .get(&canonical_name(name))
+                .map(|host| record(RData::PTR(host.clone())))
+                .into_iter()
+                .collect(),
+            _ => Vec::new(),
+        };
+        (!records.is_empty()).then_some(records)
+    }
+
+    /// Answers a query in wire format from the file, or returns `None` if the
+    /// question should go to the server.
+    pub(crate) fn respond(&self, query: &[u8]) -> Option<Vec<u8>> {
+        let query = DnsMessage::from_bytes(query).ok()?;
+        let question = query.questions.first()?;
+        let answers = self.records(
+            &question.name,
+            question.qtype,
+            DnsClass::from(question.qclass),
+        )?;
+
+        let mut response = DnsMessage::new();
+        response.header.id = query.header.id;
+        response.header.set_response(true);
+        response.header.set_opcode(query.header.opcode());
+        response
+            .header
+            .set_recursion_desired(query.header.recursion_desired());
+        response.header.set_recursion_available(true);
+        response.header.question_count = 1;
+        response.header.answer_count = answers.len() as u16;
+        response.questions.push(question.clone());
+        response.answers = answers;
+        // Echo EDNS support, as a server would.
+        if let Some(edns) = query.edns() {
+            response.additionals.push(edns.to_record());
+            response.header.additional_count = 1;
+        }
+        let mut buffer = Vec::new();
+        response.pack(&mut buffer).ok()?;
+        Some(buffer)
+    }
+}
+
+/// Returns the form of `name` entries are indexed by: canonical, with A-labels for
+/// Unicode labels, so names match however they are written.
+fn host_key(name: &str) -> String {
+    canonical_name(&to_ascii(name).unwrap_or_else(|_| name.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{QueryOptions, query};
+    use crate::transport::MockTransport;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::sync::Arc;
+
+    const HOSTS: &str = "# Static table lookup for hostnames.\n\
+                         127.0.0.1   localhost\n\
+                         ::1         localhost ip6-localhost\n\
+                         192.0.2.10  build.corp.example build   # CI\n\
+                         192.0.2.11  Build.Corp.Example\n\
+                         not-an-address ignored.example\n\
+                         192.0.2.12\n\
+                         192.0.2.13  bücher.example\n";
+
+    #[test]
+    fn test_parse_hosts_file() {
+        let hosts = HostsFile::parse(HOSTS);
+        assert_eq!(
+            hosts.addresses("localhost"),
+            [
+                IpAddr::V4(Ipv4Addr::LOCALHOST),
+                IpAddr::V6(Ipv6Addr::LOCALHOST)
+            ]
+        );
+        assert_eq!(hosts.addresses("build.corp.example.").len(), 2);
+        assert!(hosts.addresses("ignored.example").is_empty());
+        assert_eq!(hosts.addresses("xn--bcher-kva.example").len(), 1);
+        let address = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 11));
+        assert_eq!(hosts.name(&address), Some("build.corp.example"));
+        assert_eq!(hosts.name(&IpAddr::V4(Ipv4Addr::new(192, 0, 2, 12))), None);
+
+        // Only A, AAAA and PTR in class IN are answered.
+        let ptr = hosts
+            .records("1.0.0.127.in-addr.arpa", QueryType::PTR, DnsClass::IN)
+            .unwrap();
+        assert_eq!(ptr[0].data, RData::PTR("localhost".to_string()));
+        assert!(
+            hosts
+                .records("localhost", QueryType::MX, DnsClass::IN)
+                .is_none()
+        );
+        assert!(
+            hosts
+                .records("localhost", QueryType::A, DnsClass::CH)
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_hosts_answer_before_the_server() {
+        let hosts = HostsFile::parse(HOSTS);
+        let mock = Arc::new(MockTransport::new().answer(
+            "build.corp.example",
+            QueryType::AAAA,
+            vec![ResourceRecord {
+                name: "build.corp.example".parse().unwrap(),
+                rtype: QueryType::AAAA,
+                rclass: 1,
+                ttl: 300,
+                data: RData::AAAA("2001:db8::10".parse().unwrap()),
+            }],
+        ));
+        let options = QueryOptions {
+            hosts: Some(Arc::new(hosts)),
+            mock_transport: Some(Arc::clone(&mock)),
+            edns: Some(1232),
+            ..QueryOptions::default()
+        };
+        let server = Ipv4Addr::new(192, 0, 2, 53);
+
+        let result = query("Build", QueryType::A, server, &options).unwrap();
+        let addresses: Vec<&RData> = result.message.answers.iter().map(|r| &r.data).collect();
+        assert_eq!(addresses, [&RData::A(Ipv4Addr::new(192, 0, 2, 10))]);
+        assert_eq!(result.message.answers[0].ttl, HOSTS_TTL);
+        assert!(!result.recursion_unavailable);
+        assert!(mock.queries().is_empty());
+
+        // The file lists no IPv6 address for the name, so the server is asked.
+        let result = query("build.corp.example", QueryType::AAAA, server, &options).unwrap();
+        assert_eq!(result.message.answers[0].ttl, 300);
+        assert_eq!(mock.queries().len(), 1);
+    }
+}
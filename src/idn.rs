@@ -0,0 +1,960 @@
+//! Internationalized domain names: converting between A-labels and U-labels, and
+//! spotting homographs.
+//!
+//! On the wire, a label with non-ASCII characters travels as an A-label: `xn--`
+//! followed by the Punycode encoding of the Unicode label (RFC 3492). [`to_ascii`]
+//! turns the U-labels people type into A-labels, which every name packed into a
+//! message goes through, so `bücher.example` can be queried as it is written.
+//! [`to_unicode`] and [`to_unicode_message`] turn A-labels back for display.
+//!
+//! Not every Unicode string may be a label. IDNA2008 (RFC 5891 to 5893) disallows
+//! most symbols, punctuation and code points that change under case folding,
+//! allows joiners and a few punctuation marks only in context, and requires labels
+//! written right to left to follow the Bidi Rule. [`validate_label`] and
+//! [`validate_name`] check these rules, and names that break them are neither
+//! encoded nor decoded. The standard library has no Unicode normalization or
+//! general category tables, so the rules are approximated from the character
+//! properties it does have plus the exceptions listed in RFC 5892, and labels are
+//! not checked for Normalization Form C.
+//!
+//! Decoding gives back the U-label people see, which is where homograph attacks
+//! live: a name like `аpple.com` with a Cyrillic `а` looks exactly like `apple.com`
+//! in most fonts.
+//!
+//! [`check_homographs`] decodes each label of a name and flags the ones that mix
+//! scripts (apart from the Han, Kana and Hangul mixes Chinese, Japanese and Korean
+//! names legitimately use), and the ones written entirely in letters of another
+//! script that look like Latin letters. Where the look-alike can be spelled in
+//! Latin, it is reported too.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::idn::check_homographs;
+//!
+//! let suspicious = check_homographs("xn--80ak6aa92e.com");
+//! assert_eq!(suspicious[0].label, "аррӏе");
+//! assert_eq!(suspicious[0].lookalike.as_deref(), Some("apple"));
+//! assert!(check_homographs("bücher.example").is_empty());
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dns::{DnsMessage, DnsName};
+
+/// The prefix that marks a label as Punycode-encoded.
+const ACE_PREFIX: &str = "xn--";
+
+// Punycode parameters (RFC 3492, section 5).
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 0x80;
+
+/// Letters of other scripts that are commonly mistaken for Latin letters, with the
+/// Latin letter they pass for.
+const CONFUSABLES: &[(char, char)] = &[
+    // Cyrillic
+    ('а', 'a'),
+    ('с', 'c'),
+    ('ԁ', 'd'),
+    ('е', 'e'),
+    ('һ', 'h'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ӏ', 'l'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('ԛ', 'q'),
+    ('ѕ', 's'),
+    ('у', 'y'),
+    ('ԝ', 'w'),
+    ('х', 'x'),
+    // Greek
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('υ', 'u'),
+    ('χ', 'x'),
+];
+
+/// The writing system a letter belongs to, as far as homograph checks care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Script {
+    /// Latin letters, including accented ones.
+    Latin,
+    /// Greek letters.
+    Greek,
+    /// Cyrillic letters.
+    Cyrillic,
+    /// Armenian letters.
+    Armenian,
+    /// Hebrew letters.
+    Hebrew,
+    /// Arabic letters.
+    Arabic,
+    /// Chinese characters, also used in Japanese and Korean.
+    Han,
+    /// Japanese Hiragana and Katakana.
+    Kana,
+    /// Korean Hangul.
+    Hangul,
+    /// Any other script.
+    Other,
+}
+
+impl Script {
+    /// Returns the script of `c`, or `None` for characters shared by every script,
+    /// such as digits and the hyphen.
+    pub fn of(c: char) -> Option<Script> {
+        let script = match c as u32 {
+            0x30..=0x39 | 0x2D | 0x5F => return None,
+            0x41..=0x5A | 0x61..=0x7A | 0xC0..=0x24F | 0x1E00..=0x1EFF => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F => Script::Cyrillic,
+            0x530..=0x58F => Script::Armenian,
+            0x590..=0x5FF => Script::Hebrew,
+            0x600..=0x6FF => Script::Arabic,
+            0x3040..=0x30FF => Script::Kana,
+            0x3400..=0x4DBF | 0x4E00..=0x9FFF => Script::Han,
+            0x1100..=0x11FF | 0xAC00..=0xD7AF => Script::Hangul,
+            _ => Script::Other,
+        };
+        Some(script)
+    }
+}
+
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+/// A label that may be impersonating another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SuspiciousLabel {
+    /// The label as people see it, decoded from its A-label if it was one.
+    pub label: String,
+    /// Why the label is suspicious.
+    pub reason: String,
+    /// The Latin spelling the label passes for, if every letter has a look-alike.
+    pub lookalike: Option<String>,
+}
+
+impl fmt::Display for SuspiciousLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "'{}' {}", self.label, self.reason)?;
+        if let Some(lookalike) = &self.lookalike {
+            write!(f, " and looks like '{}'", lookalike)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks every label of `name`, given as A-labels, U-labels or a mix, for signs of
+/// a homograph attack.
+///
+/// # Returns
+///
+/// The suspicious labels, in order. Pure ASCII labels are never suspicious, and
+/// neither are A-labels that fail to decode, which no registry would accept.
+pub fn check_homographs(name: &str) -> Vec<SuspiciousLabel> {
+    name.trim_end_matches('.')
+        .split('.')
+        .filter_map(|label| check_label(&to_unicode_label(label)))
+        .collect()
+}
+
+fn check_label(label: &str) -> Option<SuspiciousLabel> {
+    if label.is_ascii() {
+        return None;
+    }
+    let label = label.to_lowercase();
+    let mut scripts: Vec<Script> = Vec::new();
+    for script in label.chars().filter_map(Script::of) {
+        if !scripts.contains(&script) {
+            scripts.push(script);
+        }
+    }
+    // Chinese, Japanese and Korean names mix these scripts as a matter of course.
+    let cjk = scripts
+        .iter()
+        .all(|script| matches!(script, Script::Han | Script::Kana | Script::Hangul));
+
+    let skeleton: String = label.chars().map(latin_lookalike).collect();
+    let lookalike = skeleton.is_ascii().then_some(skeleton);
+    let reason = match scripts.as_slice() {
+        [_, _, ..] if !cjk => format!(
+            "mixes {} letters",
+            scripts
+                .iter()
+                .map(Script::to_string)
+                .collect::<Vec<_>>()
+                .join(" and ")
+        ),
+        [script] if *script != Script::Latin && lookalike.is_some() => {
+            format!("is written in {} letters that look like Latin ones", script)
+        }
+        _ => return None,
+    };
+    Some(SuspiciousLabel {
+        label,
+        reason,
+        lookalike,
+    })
+}
+
+/// Returns the Latin letter `c` passes for, or `c` itself.
+fn latin_lookalike(c: char) -> char {
+    CONFUSABLES
+        .iter()
+        .find(|(confusable, _)| *confusable == c)
+        .map_or(c, |(_, latin)| *latin)
+}
+
+/// Converts each U-label of `name` to its A-label, leaving ASCII labels as they are.
+///
+/// Non-ASCII labels are lowercased before they are encoded, as registries only
+/// accept lowercase U-labels. The ideographic full stops some input methods produce
+/// (`。`, `．` and `｡`) separate labels like `.`. Labels already written as
+/// A-labels are kept as they are.
+///
+/// # Errors
+///
+/// Returns an error if a non-ASCII label breaks the IDNA2008 rules (see
+/// [`validate_label`]), if the name has right-to-left labels and any label breaks
+/// the Bidi Rule, or if a label is too long to be encoded.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::idn::to_ascii;
+///
+/// assert_eq!(to_ascii("www.Bücher.example.").unwrap(), "www.xn--bcher-kva.example.");
+/// assert_eq!(to_ascii("例え。テスト").unwrap(), "xn--r8jz45g.xn--zckzah");
+/// assert!(to_ascii("☃.example").is_err());
+/// ```
+pub fn to_ascii(name: &str) -> Result<String, String> {
+    let labels: Vec<&str> = name
+        .split(['.', '\u{3002}', '\u{ff0e}', '\u{ff61}'])
+        .collect();
+    let unicode: Vec<String> = labels
+        .iter()
+        .map(|label| decode_a_label(label).unwrap_or_else(|| label.to_lowercase()))
+        .collect();
+    check_bidi_name(&unicode)?;
+    labels
+        .iter()
+        .map(|label| to_ascii_label(label).map(Cow::into_owned))
+        .collect::<Result<Vec<_>, _>>()
+        .map(|labels| labels.join("."))
+}
+
+/// Encodes `label` as an A-label if it has non-ASCII characters, and returns it
+/// unchanged otherwise.
+///
+/// # Errors
+///
+/// Returns an error if the label breaks the IDNA2008 rules for a single label (see
+/// [`validate_label`]) or is too long to be encoded.
+pub(crate) fn to_ascii_label(label: &str) -> Result<Cow<'_, str>, String> {
+    if label.is_ascii() {
+        return Ok(Cow::Borrowed(label));
+    }
+    let label = label.to_lowercase();
+    validate_label(&label)
+        .map_err(|e| format!("Label '{}' is not a valid IDNA2008 label: {}", label, e))?;
+    encode_punycode(&label)
+        .map(|encoded| Cow::Owned(format!("{}{}", ACE_PREFIX, encoded)))
+        .ok_or_else(|| format!("Label '{}' cannot be encoded as an A-label", label))
+}
+
+/// Checks a U-label against the IDNA2008 rules for a single label.
+///
+/// The label must not start or end with a hyphen, have hyphens in its third and
+/// fourth positions, or start with a combining mark (RFC 5891, section 4.2.3).
+/// Every code point must be valid on its own, or in its context (RFC 5892): lowercase
+/// letters, digits, combining marks and the hyphen are, while symbols, punctuation,
+/// spaces, uppercase letters and compatibility forms such as fullwidth letters or
+/// superscript digits are not. A label with right-to-left characters must also
+/// follow the Bidi Rule (RFC 5893).
+///
+/// # Errors
+///
+/// Returns a description of the first rule the label breaks.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::idn::validate_label;
+///
+/// assert!(validate_label("bücher").is_ok());
+/// assert!(validate_label("l·l").is_ok()); // Catalan middle dot, between two l's
+/// assert!(validate_label("a·b").is_err());
+/// assert!(validate_label("Bücher").is_err()); // Lowercase it first
+/// assert!(validate_label("i❤you").is_err());
+/// ```
+pub fn validate_label(label: &str) -> Result<(), String> {
+    let chars: Vec<char> = label.chars().collect();
+    let Some(&first) = chars.first() else {
+        return Err("the label is empty".to_string());
+    };
+    if label.starts_with('-') || label.ends_with('-') {
+        return Err("the label starts or ends with a hyphen".to_string());
+    }
+    if chars.get(2..4) == Some(&['-', '-']) {
+        return Err("the label has hyphens in its third and fourth positions".to_string());
+    }
+    if is_combining_mark(first) {
+        return Err("the label starts with a combining mark".to_string());
+    }
+    if let Some(c) = (0..chars.len())
+        .filter(|&i| !is_valid_code_point(&chars, i))
+        .map(|i| chars[i])
+        .next()
+    {
+        return Err(format!("U+{:04X} ({}) is not allowed", u32::from(c), c));
+    }
+    let arabic_indic = chars.iter().any(|c| ('\u{660}'..='\u{669}').contains(c));
+    let extended = chars.iter().any(|c| ('\u{6F0}'..='\u{6F9}').contains(c));
+    if arabic_indic && extended {
+        return Err("the label mixes Arabic-Indic and extended Arabic-Indic digits".to_string());
+    }
+    if is_right_to_left(label) {
+        check_bidi_rule(label)?;
+    }
+    Ok(())
+}
+
+/// Checks every label of `name`, given as A-labels, U-labels or a mix, against the
+/// IDNA2008 rules.
+///
+/// A-labels must decode to a valid U-label that encodes back to the same A-label,
+/// and U-labels must be valid (see [`validate_label`]). Plain ASCII labels are not
+/// checked, since host names are not the only names in the DNS. If any label is
+/// written right to left, every label must follow the Bidi Rule (RFC 5893).
+///
+/// # Errors
+///
+/// Returns a description of the first rule the name breaks.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::idn::validate_name;
+///
+/// assert!(validate_name("www.xn--bcher-kva.example.").is_ok());
+/// assert!(validate_name("xn--bcher-kva1.example").is_err()); // Not valid Punycode
+/// assert!(validate_name("שלום.3com").is_err()); // A digit first in a bidi name
+/// ```
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return Ok(());
+    }
+    let mut labels = Vec::new();
+    for label in name.split('.') {
+        let unicode = if is_a_label(label) {
+            decode_a_label(label)
+                .ok_or_else(|| format!("Label '{}' is not a valid A-label", label))?
+        } else if label.is_ascii() {
+            label.to_string()
+        } else {
+            validate_label(label)
+                .map_err(|e| format!("Label '{}' is not a valid IDNA2008 label: {}", label, e))?;
+            label.to_string()
+        };
+        labels.push(unicode);
+    }
+    check_bidi_name(&labels)
+}
+
+/// Code points that are letters or digits, but are still disallowed: the
+/// exceptions of RFC 5892 (section 2.6) and compatibility forms, which only exist
+/// to be mapped to other characters, such as superscript digits, fractions, Roman
+/// numerals, circled numbers, ligatures and fullwidth letters.
+const DISALLOWED: &[(char, char)] = &[
+    ('\u{AA}', '\u{AA}'),
+    ('\u{B2}', '\u{B3}'),
+    ('\u{B9}', '\u{BA}'),
+    ('\u{BC}', '\u{BE}'),
+    ('\u{640}', '\u{640}'),
+    ('\u{7FA}', '\u{7FA}'),
+    ('\u{2070}', '\u{209F}'),
+    ('\u{2150}', '\u{218F}'),
+    ('\u{2460}', '\u{24FF}'),
+    ('\u{2776}', '\u{2793}'),
+    ('\u{302E}', '\u{302F}'),
+    ('\u{3031}', '\u{3035}'),
+    ('\u{303B}', '\u{303B}'),
+    ('\u{3131}', '\u{318E}'),
+    ('\u{FB00}', '\u{FB06}'),
+    ('\u{FF00}', '\u{FFEF}'),
+];
+
+/// Checks whether the code point at `i` may appear in a label where it is.
+fn is_valid_code_point(chars: &[char], i: usize) -> bool {
+    let c = chars[i];
+    let before = i.checked_sub(1).map(|i| chars[i]);
+    let after = chars.get(i + 1).copied();
+    match c {
+        'a'..='z' | '0'..='9' | '-' => true,
+        _ if c.is_ascii() => false,
+        // The joiners are only valid after a virama, and the zero width non-joiner
+        // also between two joining letters (RFC 5892, appendix A.1 and A.2).
+        '\u{200C}' => {
+            before.is_some_and(is_virama)
+                || (before.map(bidi_class) == Some(BidiClass::AL)
+                    && after.map(bidi_class) == Some(BidiClass::AL))
+        }
+        '\u{200D}' => before.is_some_and(is_virama),
+        // Punctuation valid in context (RFC 5892, appendix A.3 to A.7).
+        '\u{B7}' => before == Some('l') && after == Some('l'),
+        '\u{375}' => after.and_then(Script::of) == Some(Script::Greek),
+        '\u{5F3}' | '\u{5F4}' => before.and_then(Script::of) == Some(Script::Hebrew),
+        '\u{30FB}' => chars
+            .iter()
+            .any(|&c| c != '\u{30FB}' && matches!(Script::of(c), Some(Script::Han | Script::Kana))),
+        _ if DISALLOWED
+            .iter()
+            .any(|&(low, high)| (low..=high).contains(&c)) =>
+        {
+            false
+        }
+        // Letters and digits are valid if case folding leaves them alone.
+        _ => (c.is_alphanumeric() || is_combining_mark(c)) && c.to_lowercase().eq([c]),
+    }
+}
+
+/// Checks whether `c` is a combining mark from the blocks of combining marks, from
+/// the Hebrew and Arabic blocks, or a virama.
+fn is_combining_mark(c: char) -> bool {
+    is_virama(c)
+        || matches!(c,
+        '\u{300}'..='\u{36F}'
+        | '\u{483}'..='\u{489}'
+        | '\u{591}'..='\u{5BD}'
+        | '\u{5BF}'
+        | '\u{5C1}'..='\u{5C2}'
+        | '\u{5C4}'..='\u{5C5}'
+        | '\u{5C7}'
+        | '\u{610}'..='\u{61A}'
+        | '\u{64B}'..='\u{65F}'
+        | '\u{670}'
+        | '\u{6D6}'..='\u{6DC}'
+        | '\u{6DF}'..='\u{6E4}'
+        | '\u{6E7}'..='\u{6E8}'
+        | '\u{6EA}'..='\u{6ED}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+/// Checks whether `c` is the virama of one of the Brahmic scripts of India and
+/// South-East Asia, which a joiner may follow.
+fn is_virama(c: char) -> bool {
+    matches!(
+        c,
+        '\u{94D}'
+            | '\u{9CD}'
+            | '\u{A4D}'
+            | '\u{ACD}'
+            | '\u{B4D}'
+            | '\u{BCD}'
+            | '\u{C4D}'
+            | '\u{CCD}'
+            | '\u{D4D}'
+            | '\u{DCA}'
+            | '\u{E3A}'
+            | '\u{F84}'
+            | '\u{1039}'
+            | '\u{17D2}'
+    )
+}
+
+/// The bidirectional character types the Bidi Rule tells apart (RFC 5893, section
+/// 2). Other letters count as left to right.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)] // Bidi class names as spelled in the RFCs
+enum BidiClass {
+    /// Left-to-right letters.
+    L,
+    /// Right-to-left letters, such as Hebrew.
+    R,
+    /// Arabic letters.
+    AL,
+    /// Arabic-Indic digits.
+    AN,
+    /// European digits, including the extended Arabic-Indic ones.
+    EN,
+    /// The hyphen, a European separator.
+    ES,
+    /// Combining marks, which take the direction of the character before them.
+    NSM,
+}
+
+/// Returns the bidirectional character type of `c`, telling the right-to-left
+/// scripts apart by their blocks.
+fn bidi_class(c: char) -> BidiClass {
+    match c {
+        '0'..='9' | '\u{6F0}'..='\u{6F9}' => BidiClass::EN,
+        '-' => BidiClass::ES,
+        '\u{660}'..='\u{669}' | '\u{66B}'..='\u{66C}' => BidiClass::AN,
+        c if is_combining_mark(c) => BidiClass::NSM,
+        '\u{590}'..='\u{5FF}'
+        | '\u{7C0}'..='\u{85F}'
+        | '\u{FB1D}'..='\u{FB4F}'
+        | '\u{10800}'..='\u{10FFF}'
+        | '\u{1E800}'..='\u{1EFFF}' => BidiClass::R,
+        '\u{600}'..='\u{7BF}'
+        | '\u{860}'..='\u{8FF}'
+        | '\u{FB50}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}' => BidiClass::AL,
+        _ => BidiClass::L,
+    }
+}
+
+/// Checks whether `label` has right-to-left characters, which makes any name it
+/// is part of a bidi domain name.
+fn is_right_to_left(label: &str) -> bool {
+    label
+        .chars()
+        .any(|c| matches!(bidi_class(c), BidiClass::R | BidiClass::AL | BidiClass::AN))
+}
+
+/// Checks a label of a bidi domain name against the Bidi Rule (RFC 5893,
+/// section 2).
+fn check_bidi_rule(label: &str) -> Result<(), String> {
+    let classes: Vec<BidiClass> = label.chars().map(bidi_class).collect();
+    let last = classes.iter().rev().find(|&&class| class != BidiClass::NSM);
+    let has = |class| classes.contains(&class);
+    if is_right_to_left(label) {
+        if !matches!(classes.first(), Some(BidiClass::R | BidiClass::AL)) {
+            return Err("a right-to-left label must start with a right-to-left letter".to_string());
+        }
+        if has(BidiClass::L) {
+            return Err("the label mixes left-to-right and right-to-left letters".to_string());
+        }
+        if !matches!(
+            last,
+            Some(BidiClass::R | BidiClass::AL | BidiClass::EN | BidiClass::AN)
+        ) {
+            return Err(
+                "a right-to-left label must end with a right-to-left letter or a digit".to_string(),
+            );
+        }
+        if has(BidiClass::EN) && has(BidiClass::AN) {
+            return Err("the label mixes European and Arabic-Indic digits".to_string());
+        }
+    } else if classes.first() != Some(&BidiClass::L)
+        || !matches!(last, Some(BidiClass::L | BidiClass::EN))
+    {
+        return Err(
+            "in a name with right-to-left labels, the other labels must start with a letter and end with a letter or digit"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Checks every label of a name against the Bidi Rule if any of them is written
+/// right to left. The labels are given as U-labels.
+fn check_bidi_name(labels: &[String]) -> Result<(), String> {
+    if !labels.iter().any(|label| is_right_to_left(label)) {
+        return Ok(());
+    }
+    for label in labels.iter().filter(|label| !label.is_empty()) {
+        check_bidi_rule(label)
+            .map_err(|e| format!("Label '{}' breaks the Bidi Rule: {}", label, e))?;
+    }
+    Ok(())
+}
+
+/// Converts each valid A-label of `name` to its U-label, leaving other labels,
+/// including A-labels that break the IDNA2008 rules, as they are.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::idn::to_unicode;
+///
+/// assert_eq!(to_unicode("www.xn--bcher-kva.example."), "www.bücher.example.");
+/// assert_eq!(to_unicode("xn--ls8h.example"), "xn--ls8h.example"); // An emoji
+/// ```
+pub fn to_unicode(name: &str) -> String {
+    name.split('.')
+        .map(|label| decode_a_label(label).unwrap_or_else(|| label.to_string()))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// How internationalized names in responses are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdnOutput {
+    /// As A-labels (`xn--bcher-kva.example`), exactly as they travel on the wire.
+    #[default]
+    Ascii,
+    /// As U-labels (`bücher.example`), decoded from every valid A-label.
+    Unicode,
+}
+
+impl IdnOutput {
+    /// Returns `name` in this form.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::idn::IdnOutput;
+    ///
+    /// assert_eq!(IdnOutput::Ascii.name("Bücher.example"), "xn--bcher-kva.example");
+    /// assert_eq!(IdnOutput::Unicode.name("xn--bcher-kva.example"), "bücher.example");
+    /// ```
+    pub fn name(self, name: &str) -> String {
+        let ascii = to_ascii(name).unwrap_or_else(|_| name.to_string());
+        match self {
+            IdnOutput::Ascii => ascii,
+            IdnOutput::Unicode => to_unicode(&ascii),
+        }
+    }
+
+    /// Returns `message` with its names in this form (see [`to_unicode_message`]).
+    pub fn message(self, message: DnsMessage) -> DnsMessage {
+        match self {
+            IdnOutput::Ascii => message,
+            IdnOutput::Unicode => to_unicode_message(&message),
+        }
+    }
+}
+
+impl FromStr for IdnOutput {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii" | "a-label" | "punycode" => Ok(IdnOutput::Ascii),
+            "unicode" | "u-label" => Ok(IdnOutput::Unicode),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for IdnOutput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            IdnOutput::Ascii => write!(f, "ascii"),
+            IdnOutput::Unicode => write!(f, "unicode"),
+        }
+    }
+}
+
+/// Returns a copy of `message` with the A-labels of every name in its questions and
+/// records converted to U-labels, for display. See
+/// [`ResourceRecord::map_names`](crate::dns::ResourceRecord::map_names) for the
+/// names converted in record data.
+pub fn to_unicode_message(message: &DnsMessage) -> DnsMessage {
+    let mut message = message.clone();
+    for question in &mut message.questions {
+        question.name = DnsName::new_unchecked(to_unicode(&question.name));
+    }
+    for records in [
+        &mut message.answers,
+        &mut message.authorities,
+        &mut message.additionals,
+    ] {
+        for record in records.iter_mut() {
+            *record = record.map_names(to_unicode);
+        }
+    }
+    message
+}
+
+/// Checks whether `label` starts with the `xn--` prefix of A-labels.
+fn is_a_label(label: &str) -> bool {
+    label
+        .get(..ACE_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(ACE_PREFIX))
+}
+
+/// Decodes `label` if it is an A-label of a valid U-label: one that decodes, is
+/// not plain ASCII, encodes back to the same A-label and follows the IDNA2008
+/// rules. Returns `None` otherwise.
+fn decode_a_label(label: &str) -> Option<String> {
+    if !is_a_label(label) {
+        return None;
+    }
+    let encoded = label[ACE_PREFIX.len()..].to_ascii_lowercase();
+    let decoded = decode_punycode(&encoded)?;
+    (!decoded.is_ascii()
+        && encode_punycode(&decoded).as_deref() == Some(encoded.as_str())
+        && validate_label(&decoded).is_ok())
+    .then_some(decoded)
+}
+
+/// Decodes `label` if it is an A-label whose Punycode decodes, and returns it
+/// unchanged otherwise. Labels that break the IDNA2008 rules are decoded too, since
+/// a homograph is no less deceptive for being invalid.
+fn to_unicode_label(label: &str) -> String {
+    is_a_label(label)
+        .then(|| decode_punycode(&label[ACE_PREFIX.len()..]))
+        .flatten()
+        .unwrap_or_else(|| label.to_string())
+}
+
+/// Decodes a Punycode string (without the `xn--` prefix), as specified in RFC 3492.
+///
+/// # Returns
+///
+/// The decoded string, or `None` if the input is not valid Punycode.
+pub fn decode_punycode(input: &str) -> Option<String> {
+    // Basic code points come first, up to the last delimiter.
+    let (basic, extended) = match input.rfind('-') {
+        Some(delimiter) => (&input[..delimiter], &input[delimiter + 1..]),
+        None => ("", input),
+    };
+    if !basic.is_ascii() {
+        return None;
+    }
+    let mut output: Vec<char> = basic.chars().collect();
+
+    let mut n = INITIAL_N;
+    let mut i: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut digits = extended.bytes().peekable();
+    while digits.peek().is_some() {
+        let old_i = i;
+        let mut weight: u32 = 1;
+        let mut k = BASE;
+        loop {
+            let digit = match digits.next()? {
+                byte @ b'a'..=b'z' => u32::from(byte - b'a'),
+                byte @ b'A'..=b'Z' => u32::from(byte - b'A'),
+                byte @ b'0'..=b'9' => u32::from(byte - b'0') + 26,
+                _ => return None,
+            };
+            i = i.checked_add(digit.checked_mul(weight)?)?;
+            let threshold = threshold(k, bias);
+            if digit < threshold {
+                break;
+            }
+            weight = weight.checked_mul(BASE - threshold)?;
+            k += BASE;
+        }
+        let length = output.len() as u32 + 1;
+        bias = adapt(i - old_i, length, old_i == 0);
+        n = n.checked_add(i / length)?;
+        i %= length;
+        output.insert(i as usize, char::from_u32(n)?);
+        i += 1;
+    }
+    Some(output.into_iter().collect())
+}
+
+/// Encodes a string as Punycode (without the `xn--` prefix), as specified in
+/// RFC 3492.
+///
+/// # Returns
+///
+/// The encoded string, or `None` if the input is too long to encode.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::idn::encode_punycode;
+///
+/// assert_eq!(encode_punycode("bücher").as_deref(), Some("bcher-kva"));
+/// ```
+pub fn encode_punycode(input: &str) -> Option<String> {
+    let code_points: Vec<u32> = input.chars().map(u32::from).collect();
+    // Basic code points are copied first, followed by a delimiter if there are any.
+    let mut output: String = input.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    let mut handled = basic;
+    while (handled as usize) < code_points.len() {
+        let next = code_points.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((next - n).checked_mul(handled + 1)?)?;
+        n = next;
+        for &c in &code_points {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let threshold = threshold(k, bias);
+                    if q < threshold {
+                        break;
+                    }
+                    output.push(punycode_digit(
+                        threshold + (q - threshold) % (BASE - threshold),
+                    ));
+                    q = (q - threshold) / (BASE - threshold);
+                    k += BASE;
+                }
+                output.push(punycode_digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n += 1;
+    }
+    Some(output)
+}
+
+/// Returns the character that stands for a Punycode digit (0-35).
+fn punycode_digit(digit: u32) -> char {
+    match digit {
+        0..=25 => char::from(b'a' + digit as u8),
+        _ => char::from(b'0' + (digit - 26) as u8),
+    }
+}
+
+/// The threshold of a digit at position `k` (RFC 3492, section 6.2).
+fn threshold(k: u32, bias: u32) -> u32 {
+    if k <= bias {
+        T_MIN
+    } else if k >= bias + T_MAX {
+        T_MAX
+    } else {
+        k - bias
+    }
+}
+
+/// The bias adaptation function of RFC 3492, section 6.1.
+fn adapt(delta: u32, points: u32, first: bool) -> u32 {
+    let mut delta = if first { delta / DAMP } else { delta / 2 };
+    delta += delta / points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_punycode() {
+        assert_eq!(decode_punycode("bcher-kva").as_deref(), Some("bücher"));
+        assert_eq!(decode_punycode("80ak6aa92e").as_deref(), Some("аррӏе"));
+        // RFC 3492 sample (A): Arabic (Egyptian).
+        assert_eq!(
+            decode_punycode("egbpdaj6bu4bxfgehfvwxn").as_deref(),
+            Some("ليهمابتكلموشعربي؟")
+        );
+        assert_eq!(decode_punycode("abc-").as_deref(), Some("abc"));
+        assert_eq!(decode_punycode("b\u{fc}cher-kva"), None);
+        assert_eq!(decode_punycode("99999999999"), None);
+        assert_eq!(to_unicode("xn--bcher-kva.XN--80AK6AA92E"), "bücher.аррӏе");
+    }
+
+    #[test]
+    fn test_encode_punycode() {
+        assert_eq!(encode_punycode("аррӏе").as_deref(), Some("80ak6aa92e"));
+        assert_eq!(
+            encode_punycode("ليهمابتكلموشعربي؟").as_deref(),
+            Some("egbpdaj6bu4bxfgehfvwxn")
+        );
+        assert_eq!(encode_punycode("abc").as_deref(), Some("abc-"));
+        for label in ["bücher", "пример", "例え", "münchen-straße"] {
+            let encoded = encode_punycode(label).unwrap();
+            assert_eq!(decode_punycode(&encoded).as_deref(), Some(label));
+        }
+
+        assert_eq!(to_ascii("MÜNCHEN.de").unwrap(), "xn--mnchen-3ya.de");
+        assert_eq!(to_ascii("example.com").unwrap(), "example.com");
+        assert!(to_ascii_label("example").is_ok_and(|label| matches!(label, Cow::Borrowed(_))));
+    }
+
+    #[test]
+    fn test_idna2008_label_rules() {
+        for label in [
+            "bücher",
+            "straße",
+            "пример",
+            "例え",
+            "한국",
+            "שלום",
+            "مثال",
+            "क्\u{200D}ष",
+        ] {
+            assert_eq!(validate_label(label), Ok(()), "{}", label);
+        }
+        for label in [
+            "-bücher",
+            "bü--cher",
+            "\u{301}bücher",
+            "Bücher",
+            "ab\u{2603}",
+            "ｂücher",
+            "x²",
+            "a\u{200D}b",
+            "ab\u{640}c",
+            "a\u{375}b",
+            "a\u{30FB}b",
+            "\u{661}\u{6F1}",
+        ] {
+            assert!(validate_label(label).is_err(), "{}", label);
+        }
+
+        // Bidi Rule: right-to-left labels stay right to left, and in a bidi name
+        // every other label starts with a letter.
+        assert!(validate_label("שלוםabc").is_err());
+        assert!(validate_label("1שלום").is_err());
+        assert!(validate_label("שלום1").is_ok());
+        assert!(validate_name("xn--4dbrk0ce.example").is_ok());
+        assert!(validate_name("xn--4dbrk0ce.3com").is_err());
+        assert!(to_ascii("שלום.3com").is_err());
+
+        // A-labels must decode to a valid U-label and encode back to themselves.
+        assert!(validate_name("xn--ls8h.example").is_err());
+        assert!(validate_name("xn--abc-.example").is_err());
+        assert_eq!(
+            to_unicode("xn--ls8h.XN--BCHER-KVA.example"),
+            "xn--ls8h.bücher.example"
+        );
+        assert_eq!(to_ascii("xn--ls8h.example").unwrap(), "xn--ls8h.example");
+        assert!(to_ascii("i\u{2764}you.example").is_err());
+    }
+
+    #[test]
+    fn test_homograph_checks() {
+        // Cyrillic 'а' in an otherwise Latin label.
+        let found = check_homographs("www.\u{430}pple.com");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].reason, "mixes Cyrillic and Latin letters");
+        assert_eq!(found[0].lookalike.as_deref(), Some("apple"));
+
+        // All-Cyrillic labels pass only if they cannot be read as Latin.
+        assert!(check_homographs("пример.рф").is_empty());
+        assert_eq!(
+            check_homographs("xn--80ak6aa92e.com")[0].reason,
+            "is written in Cyrillic letters that look like Latin ones"
+        );
+
+        assert!(check_homographs("例え.テスト").is_empty());
+        assert!(check_homographs("xn--ab-!.example").is_empty());
+        assert!(check_homographs("bücher.example").is_empty());
+    }
+}
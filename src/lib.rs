@@ -0,0 +1,87 @@
+//! A DNS client library: message encoding and decoding, a resolver with retries,
+//! caching and DNSSEC validation, and the building blocks of the `dns-resolver`
+//! command-line tool.
+//!
+//! Everything a service needs to resolve names without shelling out to the binary is
+//! re-exported at the crate root. The modules hold the rest: the wire format in
+//! [`dns`], the query functions and their options in [`resolver`], and zone
+//! transfers, NOTIFY, TSIG, DNSSEC and the diagnostics the tool is built on in the
+//! others.
+//!
+//! # Examples
+//!
+//! A [`Resolver`] bundles servers, their settings and a host cache into one handle
+//! that can be cloned cheaply and shared between threads:
+//!
+//! ```rust,no_run
+//! use dns_resolver::{QueryType, Resolver};
+//! use std::net::Ipv4Addr;
+//! use std::time::Duration;
+//!
+//! let resolver = Resolver::builder()
+//!     .server(Ipv4Addr::new(8, 8, 8, 8))
+//!     .timeout(Duration::from_secs(2))
+//!     .attempts(3)
+//!     .build()?;
+//! let response = resolver.resolve("example.com", QueryType::MX)?;
+//! for answer in &response.answers {
+//!     println!("{}", answer);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! Single queries need no handle:
+//!
+//! ```rust,no_run
+//! use dns_resolver::{QueryType, resolve};
+//! use std::net::Ipv4Addr;
+//!
+//! let response = resolve("example.com", QueryType::AAAA, Ipv4Addr::new(1, 1, 1, 1))?;
+//! println!("{} answers", response.answers.len());
+//! # Ok::<(), dns_resolver::DnsError>(())
+//! ```
+
+pub mod addrs;
+pub mod asn;
+pub mod assertion;
+pub mod axfr;
+pub mod breaker;
+pub mod cache;
+pub mod chain;
+pub mod consensus;
+pub mod crypto;
+pub mod dns;
+pub mod dnssec;
+pub mod host_cache;
+pub mod hosts;
+pub mod idn;
+pub mod lint;
+pub mod notify;
+pub mod output;
+pub mod report;
+pub mod resolv_conf;
+pub mod resolved;
+pub mod resolver;
+pub mod retry;
+pub mod scan;
+pub mod stats;
+pub mod sweep;
+pub mod system_config;
+#[cfg(any(test, feature = "testutil"))]
+pub mod testutil;
+pub mod transcript;
+pub mod transport;
+pub mod tsig;
+pub mod validation;
+pub mod watch;
+
+pub use cache::{CachedResponse, DnsCache, FileCache, ResponseCache, is_stale_answer};
+pub use dns::{
+    DnsClass, DnsHeader, DnsMessage, DnsName, DnsQuestion, Opcode, QueryType, RData,
+    ResourceRecord, ResponseCode,
+};
+pub use resolver::{
+    DnsError, DnsErrorKind, QueryOptions, QueryResult, Resolver, ResolverBuilder, query, resolve,
+    resolve_with_options, reverse_lookup,
+};
+pub use retry::RetryPolicy;
@@ -0,0 +1,411 @@
+//! Best-practice checks on DNS data.
+//!
+//! A response can be perfectly valid on the wire and still describe a setup that
+//! breaks in practice: an alias at the zone apex, mail exchanges that are aliases,
+//! SPF policies that receivers give up on, or nameservers that do not resolve. The
+//! functions here look for such problems and describe each one as a
+//! [`Finding`], the same structure domain reports use.
+//!
+//! The checks come in three scopes:
+//!
+//! * [`lint_response`] looks at a single response, without further queries
+//! * [`lint_records`] looks at the records gathered for a name across several types
+//! * [`lint_domain`] adds the checks that need follow-up queries (MX targets, SPF
+//!   includes and nameserver addresses)
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::lint::{LintOptions, lint_response};
+//! use dns_resolver::resolver::{QueryOptions, query};
+//! use std::net::Ipv4Addr;
+//!
+//! let result = query("example.com", QueryType::A, Ipv4Addr::new(8, 8, 8, 8), &QueryOptions::default())?;
+//! for finding in lint_response("example.com", &result.message, &LintOptions::default()) {
+//!     println!("[{}] {}", finding.severity, finding.message);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::net::Ipv4Addr;
+
+use crate::dns::{DnsMessage, QueryType, RData, ResourceRecord};
+use crate::report::{Finding, Severity, is_policy};
+use crate::resolver::{QueryOptions, resolve_many, resolve_with_options};
+use crate::validation::names_equal;
+
+/// The number of DNS-querying SPF terms a policy may use before receivers must
+/// treat it as a permanent error (RFC 7208, section 4.6.4).
+pub const SPF_LOOKUP_LIMIT: usize = 10;
+
+/// Thresholds used by the checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LintOptions {
+    /// Record sets with a TTL below this many seconds are flagged, since they
+    /// defeat caching and multiply the load on the authoritative servers.
+    pub min_ttl: u32,
+}
+
+impl Default for LintOptions {
+    fn default() -> Self {
+        LintOptions { min_ttl: 60 }
+    }
+}
+
+/// Checks a single response: TTLs of the answer records, and a CNAME at the
+/// queried name when the response also shows that name to be a zone apex.
+///
+/// Checks that need records of several types, like a missing AAAA, are left to
+/// [`lint_records`].
+///
+/// # Arguments
+///
+/// * `name` - The name that was queried
+/// * `message` - The response to check
+/// * `options` - The thresholds to apply
+pub fn lint_response(name: &str, message: &DnsMessage, options: &LintOptions) -> Vec<Finding> {
+    let mut findings = low_ttls(&message.answers, options);
+    let records: Vec<ResourceRecord> = message
+        .answers
+        .iter()
+        .chain(&message.authorities)
+        .cloned()
+        .collect();
+    findings.extend(apex_cname(name, &records));
+    findings
+}
+
+/// Checks the records gathered for `name` across several record types.
+///
+/// `records` should hold the answers to at least the A and AAAA queries, since a
+/// missing AAAA is flagged whenever A records are present and AAAA records are not.
+/// NS records owned by `name` mark it as a zone apex for the CNAME check.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{QueryType, RData, ResourceRecord};
+/// use dns_resolver::lint::{LintOptions, lint_records};
+/// use std::net::Ipv4Addr;
+///
+/// let a = ResourceRecord {
+///     name: "example.com".parse().unwrap(),
+///     rtype: QueryType::A,
+///     rclass: 1,
+///     ttl: 3600,
+///     data: RData::A(Ipv4Addr::new(192, 0, 2, 1)),
+/// };
+/// let findings = lint_records("example.com", &[a], &LintOptions::default());
+/// assert!(findings[0].message.contains("no AAAA"));
+/// ```
+pub fn lint_records(name: &str, records: &[ResourceRecord], options: &LintOptions) -> Vec<Finding> {
+    let mut findings = low_ttls(records, options);
+    findings.extend(apex_cname(name, records));
+
+    let has_type = |rtype: QueryType| records.iter().any(|record| record.rtype == rtype);
+    if has_type(QueryType::A) && !has_type(QueryType::AAAA) {
+        findings.push(Finding::new(
+            Severity::Info,
+            format!(
+                "{} has A records but no AAAA records, so it is unreachable over IPv6-only networks",
+                name
+            ),
+        ));
+    }
+    findings
+}
+
+/// Runs every check on `name`, making follow-up queries where needed.
+///
+/// Besides the checks of [`lint_records`], this resolves each MX exchange to see
+/// whether it is an alias, counts the DNS lookups of the SPF policy across its
+/// includes and redirects, and resolves each nameserver of the name. Follow-up
+/// queries that fail are not reported as findings themselves.
+///
+/// # Arguments
+///
+/// * `name` - The name to check
+/// * `records` - The records already gathered for `name`, as for [`lint_records`]
+/// * `dns_server_addr` - The recursive resolver to use for follow-up queries
+/// * `options` - Settings shared by every follow-up query
+/// * `lint_options` - The thresholds to apply
+pub fn lint_domain(
+    name: &str,
+    records: &[ResourceRecord],
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+    lint_options: &LintOptions,
+) -> Vec<Finding> {
+    let mut findings = lint_records(name, records, lint_options);
+
+    // RFC 2181, section 10.3: an MX exchange must be a name with address records,
+    // not an alias. Many mail servers cope, but some refuse to deliver.
+    for record in records {
+        let RData::MX { exchange, .. } = &record.data else {
+            continue;
+        };
+        if exchange.trim_end_matches('.').is_empty() {
+            continue;
+        }
+        let aliased = resolve_with_options(exchange, QueryType::A, dns_server_addr, options)
+            .is_ok_and(|message| {
+                message.answers.iter().any(|answer| {
+                    answer.rtype == QueryType::CNAME && names_equal(&answer.name, exchange)
+                })
+            });
+        if aliased {
+            findings.push(Finding::new(
+                Severity::Warning,
+                format!(
+                    "MX exchange {} is an alias (CNAME); mail exchanges must have address records of their own",
+                    exchange
+                ),
+            ));
+        }
+    }
+
+    let mut fetch = |domain: &str| -> Vec<String> {
+        resolve_with_options(domain, QueryType::TXT, dns_server_addr, options)
+            .map(|message| {
+                message
+                    .answers
+                    .iter()
+                    .filter_map(|record| record.get_txt_data())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    for policy in records
+        .iter()
+        .filter(|record| names_equal(&record.name, name))
+        .filter_map(|record| record.get_txt_data())
+        .filter(|txt| is_policy(txt, "v=spf1"))
+    {
+        let lookups = spf_lookups(policy, &mut fetch);
+        if lookups > SPF_LOOKUP_LIMIT {
+            findings.push(Finding::new(
+                Severity::Error,
+                format!(
+                    "SPF policy needs {} DNS lookups, more than the limit of {}; receivers treat it as a permanent error",
+                    lookups, SPF_LOOKUP_LIMIT
+                ),
+            ));
+        }
+    }
+
+    for record in records {
+        let RData::NS(host) = &record.data else {
+            continue;
+        };
+        if !names_equal(&record.name, name) {
+            continue;
+        }
+        let resolvable = resolve_many(
+            host,
+            &[QueryType::A, QueryType::AAAA],
+            dns_server_addr,
+            options,
+        )
+        .into_iter()
+        .flatten()
+        .any(|message| {
+            message
+                .answers
+                .iter()
+                .any(|answer| matches!(answer.data, RData::A(_) | RData::AAAA(_)))
+        });
+        if !resolvable {
+            findings.push(Finding::new(
+                Severity::Error,
+                format!("Nameserver {} does not resolve to any address", host),
+            ));
+        }
+    }
+
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+    findings
+}
+
+/// Counts the DNS lookups an SPF policy causes, following `include` and `redirect`
+/// terms into the policies they name.
+///
+/// Every `include`, `redirect`, `a`, `mx`, `ptr` and `exists` term counts as one
+/// lookup (RFC 7208, section 4.6.4). `fetch` returns the TXT strings of a name; a
+/// name that has no SPF policy, or that was already visited, adds nothing beyond
+/// the term that named it. Counting stops following references once the limit is
+/// exceeded, so the result is exact up to [`SPF_LOOKUP_LIMIT`] + 1.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::lint::spf_lookups;
+///
+/// let mut fetch = |name: &str| match name {
+///     "_spf.example.com" => vec!["v=spf1 ip4:192.0.2.0/24 a mx -all".to_string()],
+///     _ => Vec::new(),
+/// };
+/// assert_eq!(spf_lookups("v=spf1 include:_spf.example.com -all", &mut fetch), 3);
+/// ```
+pub fn spf_lookups(policy: &str, fetch: &mut dyn FnMut(&str) -> Vec<String>) -> usize {
+    count_spf_lookups(policy, fetch, &mut Vec::new())
+}
+
+fn count_spf_lookups(
+    policy: &str,
+    fetch: &mut dyn FnMut(&str) -> Vec<String>,
+    visited: &mut Vec<String>,
+) -> usize {
+    let mut lookups = 0;
+    for term in policy.split_whitespace().skip(1) {
+        let term = term.trim_start_matches(['+', '-', '~', '?']);
+        let (mechanism, target) = match term.find([':', '=', '/']) {
+            Some(at) => (&term[..at], Some(&term[at + 1..])),
+            None => (term, None),
+        };
+        match mechanism.to_ascii_lowercase().as_str() {
+            "include" | "redirect" => {
+                lookups += 1;
+                // Every followed reference was counted above, so once there are more
+                // than the limit the policy has failed and the rest does not matter.
+                let Some(target) = target else { continue };
+                let target = target.trim_end_matches('.').to_ascii_lowercase();
+                if visited.len() > SPF_LOOKUP_LIMIT || visited.contains(&target) {
+                    continue;
+                }
+                visited.push(target.clone());
+                if let Some(nested) = fetch(&target).iter().find(|txt| is_policy(txt, "v=spf1")) {
+                    lookups += count_spf_lookups(nested, fetch, visited);
+                }
+            }
+            "a" | "mx" | "ptr" | "exists" => lookups += 1,
+            _ => {}
+        }
+    }
+    lookups
+}
+
+/// Flags each record set in `records` whose TTL is below the threshold, once per
+/// owner and type.
+fn low_ttls(records: &[ResourceRecord], options: &LintOptions) -> Vec<Finding> {
+    let mut flagged: Vec<(String, QueryType)> = Vec::new();
+    let mut findings = Vec::new();
+    for record in records {
+        if record.ttl >= options.min_ttl {
+            continue;
+        }
+        let key = (record.name.to_ascii_lowercase(), record.rtype);
+        if flagged.contains(&key) {
+            continue;
+        }
+        flagged.push(key);
+        findings.push(Finding::new(
+            Severity::Warning,
+            format!(
+                "{} records of {} have a TTL of {}s, below {}s; they will barely be cached",
+                record.rtype, record.name, record.ttl, options.min_ttl
+            ),
+        ));
+    }
+    findings
+}
+
+/// Flags a CNAME owned by `name` when `records` also hold NS or SOA records owned
+/// by it. Those only exist at a zone apex, where an alias cannot coexist with them
+/// (RFC 1034, section 3.6.2).
+fn apex_cname(name: &str, records: &[ResourceRecord]) -> Option<Finding> {
+    let owned = |rtype: QueryType| {
+        records
+            .iter()
+            .any(|record| record.rtype == rtype && names_equal(&record.name, name))
+    };
+    (owned(QueryType::CNAME) && (owned(QueryType::NS) || owned(QueryType::SOA))).then(|| {
+        Finding::new(
+            Severity::Error,
+            format!(
+                "{} is a zone apex but has a CNAME record; the alias hides the zone's NS and SOA records",
+                name
+            ),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testutil::{a_record, record};
+
+    #[test]
+    fn test_record_checks() {
+        let records = vec![
+            a_record("example.com", [192, 0, 2, 1], 30),
+            a_record("example.com", [192, 0, 2, 2], 30),
+            record(
+                "example.com",
+                3600,
+                RData::NS("ns1.example.net".to_string()),
+            ),
+            record(
+                "Example.com.",
+                3600,
+                RData::CNAME("target.example.net".to_string()),
+            ),
+        ];
+        let findings = lint_records("example.com", &records, &LintOptions::default());
+        // One low-TTL finding for the A set, not one per record.
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.message.contains("TTL of 30s"))
+                .count(),
+            1
+        );
+        assert!(findings.iter().any(|f| f.severity == Severity::Error));
+        assert!(findings.iter().any(|f| f.message.contains("no AAAA")));
+
+        let healthy = vec![
+            a_record("example.com", [192, 0, 2, 1], 300),
+            record(
+                "example.com",
+                300,
+                RData::AAAA("2001:db8::1".parse().unwrap()),
+            ),
+        ];
+        assert!(lint_records("example.com", &healthy, &LintOptions::default()).is_empty());
+    }
+
+    #[test]
+    fn test_spf_lookup_counting() {
+        let mut fetch = |name: &str| -> Vec<String> {
+            match name {
+                "a.example.com" => vec!["v=spf1 include:b.example.com mx ptr -all".to_string()],
+                // A reference loop must not be followed twice.
+                "b.example.com" => {
+                    vec!["v=spf1 include:a.example.com exists:%{i}.x -all".to_string()]
+                }
+                _ => vec!["unrelated text".to_string()],
+            }
+        };
+        assert_eq!(spf_lookups("v=spf1 ip4:192.0.2.0/24 -all", &mut fetch), 0);
+        assert_eq!(
+            spf_lookups("v=spf1 a/24 mx:mail.example.com -all", &mut fetch),
+            2
+        );
+        // include:a (1) + include:b, mx, ptr (3) + include:a, exists (2).
+        assert_eq!(
+            spf_lookups("v=spf1 include:a.example.com ~all", &mut fetch),
+            6
+        );
+
+        let mut chain = |name: &str| -> Vec<String> {
+            let next: u32 = name.trim_start_matches('n').parse::<u32>().unwrap() + 1;
+            vec![format!("v=spf1 include:n{} -all", next)]
+        };
+        assert_eq!(
+            spf_lookups("v=spf1 include:n0 -all", &mut chain),
+            SPF_LOOKUP_LIMIT + 2
+        );
+    }
+}
@@ -1,11 +1,13 @@
 //! Command-line DNS resolver application.
 //!
 //! This module provides a command-line interface for performing DNS queries against
-//! public DNS servers. It supports common DNS record types (A, AAAA, CNAME, MX, TXT)
-//! and displays comprehensive information about DNS responses including headers,
-//! questions, answers, authority records, and additional records.
+//! public DNS servers. It supports common DNS record types (A, AAAA, CNAME, MX, TXT, PTR, NS,
+//! SOA, HINFO, SRV, NAPTR, SSHFP, SVCB, HTTPS, DS, DNSKEY, RRSIG, NSEC, NSEC3, ANY) and displays
+//! comprehensive information about DNS responses including headers, questions, answers,
+//! authority records, and additional records.
 //!
-//! The application uses Google's public DNS server (8.8.8.8) by default and implements
+//! The application queries the nameservers the operating system is configured with by
+//! default (`/etc/resolv.conf` on Unix-like systems), falling back to Google's public DNS server (8.8.8.8), and implements
 //! proper error handling for various failure scenarios including network timeouts,
 //! invalid domains, and unsupported record types.
 //!
@@ -23,6 +25,98 @@
 //!
 //! # Query AAAA (IPv6) records
 //! dns-resolver google.com AAAA
+//!
+//! # Query a type by number (RFC 3597), here CAA
+//! dns-resolver example.com TYPE257
+//!
+//! # Ask a server to identify itself with a Chaos class query
+//! dns-resolver version.bind TXT --class CH
+//!
+//! # Query every common record type at once, grouped by type
+//! dns-resolver example.com --all
+//!
+//! # Ask a different server, or a local daemon listening on a Unix domain socket
+//! dns-resolver example.com A --server 1.1.1.1
+//! dns-resolver example.com A --server unix:/run/mydns.sock
+//!
+//! # Send the query from a specific local address or interface
+//! dns-resolver internal.example A --source 192.0.2.10
+//! dns-resolver internal.example A --interface eth1
+//!
+//! # Watch for spoofed duplicate responses for half a second after the answer
+//! dns-resolver example.com A --duplicate-window 500
+//!
+//! # Record a run to a transcript, and reproduce it later without the network
+//! dns-resolver example.com A --record bug.transcript
+//! dns-resolver example.com A --replay bug.transcript
+//!
+//! # Answer names listed in /etc/hosts from the file, as other programs on the host do
+//! dns-resolver build.corp.example A --hosts
+//! dns-resolver -x 192.0.2.10 --hosts-file ./test-hosts
+//!
+//! # Reject and explain any protocol irregularity in the response
+//! dns-resolver example.com A --strict
+//!
+//! # Look up an internationalized name, which is sent as xn--bcher-kva.example, and
+//! # show the names in the response decoded
+//! dns-resolver bücher.example A --idn-out unicode
+//!
+//! # Find the host names an address maps back to (PTR lookup)
+//! dns-resolver -x 192.0.2.1
+//!
+//! # Query over TCP on a network that blocks UDP port 53
+//! dns-resolver example.com A --tcp
+//!
+//! # Transfer a zone from a server that allows it, and save it as a zone file
+//! dns-resolver axfr zonetransfer.me --server 81.4.108.41 --output zonetransfer.me.zone
+//!
+//! # Authenticate a transfer with a TSIG key from a BIND key file
+//! dns-resolver axfr example.com --server 192.0.2.53 --tsig-keyfile transfer.key
+//!
+//! # Tell a secondary that a zone changed so it transfers the new serial right away
+//! dns-resolver notify example.com 2024010102 --server 192.0.2.54
+//!
+//! # Print the zone change notifications a primary sends to this host
+//! dns-resolver notify-listen 0.0.0.0:5353
+//!
+//! # List what a long-running process has cached, and purge a poisoned name from it
+//! dns-resolver cache dump --cache-file /var/cache/app/dns.journal
+//! dns-resolver cache flush example.com --cache-file /var/cache/app/dns.journal
+//!
+//! # Ask for responses of up to 1232 bytes over UDP with EDNS(0)
+//! dns-resolver example.com TXT --edns 1232
+//!
+//! # Assert that a validating upstream authenticated the answer
+//! dns-resolver example.com A --require-ad
+//!
+//! # Ask for the signatures too, dig style, without upstream validation
+//! dns-resolver example.com DNSKEY +dnssec +cd
+//!
+//! # Validate the answer with DNSSEC from the root trust anchor down
+//! dns-resolver example.com A --validate
+//!
+//! # Only trust records that at least two of three independent resolvers agree on
+//! dns-resolver example.com A --consensus 1.1.1.1,8.8.8.8,9.9.9.9 --quorum 2
+//!
+//! # Compare what systemd-resolved answers with what its upstream server says
+//! dns-resolver example.com A --compare-resolved
+//!
+//! # Summarize records, DNSSEC status and mail posture of a domain (or as JSON)
+//! dns-resolver report example.com
+//! dns-resolver report example.com --format json
+//!
+//! # Verify a DNS change in a deployment pipeline (exits non-zero with a diff)
+//! dns-resolver assert example.com A --equals 203.0.113.10 --max-ttl 300
+//! dns-resolver assert example.com MX --matches 'mx[0-9]+\.example\.net$'
+//!
+//! # Find the origin AS of an address (via Team Cymru's DNS interface)
+//! dns-resolver asn 142.250.187.206
+//!
+//! # List the reverse DNS names of a prefix as CSV, 4 lookups at a time, 10 per second
+//! dns-resolver sweep 192.0.2.0/24 --concurrency 4 --rate 10 --format csv
+//!
+//! # Check your own network for accidentally open recursive resolvers
+//! dns-resolver open-resolvers 192.168.1.0/24
 //! ```
 //!
 //! # Supported Record Types
@@ -32,6 +126,21 @@
 //! - **CNAME**: Canonical name (alias) records
 //! - **MX**: Mail exchange records
 //! - **TXT**: Text records
+//! - **PTR**: Domain name pointer (reverse DNS) records
+//! - **NS**: Authoritative name server records
+//! - **SOA**: Start of authority records, with the zone's serial and timers
+//! - **HINFO**: Host information records
+//! - **SRV**: Service locator records
+//! - **NAPTR**: Naming authority pointer records, used by ENUM and SIP
+//! - **SSHFP**: SSH host key fingerprints, for checking a server's host key
+//! - **SVCB** and **HTTPS**: Service binding records, with their parameters (ALPN
+//!   protocols, port and address hints)
+//! - **DS**, **DNSKEY**, **RRSIG**, **NSEC** and **NSEC3**: DNSSEC records, shown
+//!   with their keys and signatures in base64 and their validity times in UTC
+//! - **ANY**: Every record of a name. Many servers refuse these queries and answer
+//!   with a single synthesized HINFO record instead (RFC 8482); the output says so
+//! - **TYPE<number>**: Any other type by its numeric code (RFC 3597), shown in the
+//!   generic `\# <length> <hex>` encoding
 //!
 //! # Examples
 //!
@@ -40,10 +149,10 @@
 //! $ dns-resolver google.com
 //! Querying 8.8.8.8 for A records of google.com...
 //! ------------------------------------
-//! Header: DnsHeader { id: 1234, flags: 33152, ... }
+//! Header: id 48211, opcode QUERY, status NOERROR, flags: qr rd ra; QUERY: 1, ANSWER: 2, AUTHORITY: 0, ADDITIONAL: 0
 //!
 //! Question Section:
-//!   - QNAME: google.com, QTYPE: A
+//!   - QNAME: google.com, QTYPE: A, QCLASS: IN
 //!
 //! Answer Section:
 //!   - google.com 300 IN A 142.250.187.206
@@ -62,28 +171,141 @@
 //! ```
 
 use std::env;
-use std::net::Ipv4Addr;
-use std::str::FromStr;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::SystemTime;
 
-// Import modules from the current crate.
-mod dns;
-mod resolver;
+// The command-line parsing is specific to the binary; everything else comes from
+// the library.
+mod cli;
 
-use dns::QueryType;
-use resolver::resolve;
+use cli::{CliArgs, Command, parse_args};
+use dns_resolver::asn::{lookup_asn, origin_query_name};
+use dns_resolver::assertion::Assertion;
+use dns_resolver::axfr::transfer;
+use dns_resolver::cache::{self, DnsCache, FileCache};
+use dns_resolver::chain::follow_cname_chain;
+use dns_resolver::consensus::{majority, query_consensus};
+use dns_resolver::dns::{DnsClass, QueryType, RData, ResponseCode};
+use dns_resolver::dnssec::{SecurityStatus, load_trust_anchors};
+use dns_resolver::hosts::HostsFile;
+use dns_resolver::idn::{IdnOutput, check_homographs};
+use dns_resolver::lint::{LintOptions, lint_response};
+use dns_resolver::notify::{self, Notify, listen};
+use dns_resolver::output::{OutputFormat, TtlFormat};
+use dns_resolver::report::{Severity, build_report};
+use dns_resolver::resolved::{ResolvedConfig, STUB_ADDRESS};
+use dns_resolver::resolver::{
+    DnsError, DuplicateResponse, QueryOptions, QueryResult, query, resolve_many,
+};
+use dns_resolver::scan::{self, ResolverStatus, ScanOptions, scan_open_resolvers};
+use dns_resolver::sweep::{Cidr, SweepOptions, csv_header, sweep};
+use dns_resolver::system_config::system_config;
+use dns_resolver::transcript::Transcript;
+use dns_resolver::tsig::TsigKey;
 
 /// Entry point for the DNS resolver command-line application.
 ///
 /// This function orchestrates the complete DNS resolution process:
 /// 1. Parses command-line arguments for domain name and record type
 /// 2. Validates the provided arguments and record type
-/// 3. Performs the DNS query using Google's public DNS server
+/// 3. Performs the DNS query using the system's nameservers
 /// 4. Displays comprehensive results or error information
 ///
 /// # Command-line Arguments
 ///
-/// * `domain_name` - The fully qualified domain name to resolve (required)
+/// * `domain_name` - The fully qualified domain name to resolve (required, unless `-x` is given)
+/// * `-x <address>`, `--reverse <address>` - Look up the PTR records of an IPv4 or IPv6
+///   address, in place of a domain name and record type
 /// * `record_type` - The DNS record type to query (optional, defaults to "A")
+/// * `--server <address>` - The IPv4 DNS server to query (default: the system's
+///   nameservers, or 8.8.8.8 if there are none usable), or
+///   `unix:<path>` to send length-prefixed messages over a Unix domain socket
+/// * `--source <address>` - Local IPv4 address to send the query from (optional)
+/// * `--interface <name>` - Network interface to send the query through (optional)
+/// * `--duplicate-window <ms>` - Keep listening for duplicate (possibly spoofed)
+///   responses for this many milliseconds after the answer arrives (optional)
+/// * `--strict` - Reject responses with any protocol irregularity and report each one
+/// * `--tcp` - Send queries over TCP instead of UDP, for networks that block UDP port 53
+///   (cannot be combined with `--source` or `--interface` outside Linux)
+/// * `--edns <bytes>` - Advertise this UDP payload size (512 to 65535) in an EDNS(0) OPT
+///   record, so larger responses arrive without falling back to TCP
+/// * `--edns-opt <code>[:<hex>]` - Send a raw option in the OPT record, by code or name
+///   (such as `NSID`), with its data in hex; may be repeated
+/// * `--dnssec` - Set the DNSSEC OK (DO) bit in an EDNS(0) OPT record, so the server
+///   includes RRSIG, NSEC and NSEC3 records. Advertises 1232 bytes unless `--edns` is given
+/// * `--cd` - Set the Checking Disabled bit so a validating upstream returns unvalidated data
+/// * `--ad` - Set the Authentic Data bit to ask the upstream to report validation status
+/// * `+dnssec`, `+cd`/`+cdflag`, `+ad`/`+adflag` - The same switches, spelled as in `dig`
+/// * `--require-ad` - Exit with a failure status unless the response has AD set (implies `--ad`)
+/// * `--validate` - Validate the response with DNSSEC, following the chain of trust from
+///   the root trust anchor, and show whether it is secure, insecure or bogus. A bogus
+///   response exits with a failure status
+/// * `--trust-anchors <file>` - Validate from the root keys in this file, DS or DNSKEY
+///   records in presentation format, instead of the built-in ones (with `--validate`)
+/// * `--class <class>` - Query class: IN (default), CH, HS, NONE, ANY, or any other
+///   class as `CLASS<number>` or a plain number (RFC 3597)
+/// * `--compare-resolved` - Query both the systemd-resolved stub (127.0.0.53) and the
+///   upstream server resolved routes the name to, and show the answers side by side
+/// * `--all` - Query all common record types concurrently instead of a single type
+/// * `--consensus <address>,<address>,...` - Ask each of several independent
+///   resolvers and show only the records enough of them agree on, flagging the
+///   servers that answered differently. `--quorum <n>` sets how many must agree
+///   (default: a majority)
+/// * `--record <file>` - Write every query and response, as wire bytes with timing,
+///   to a transcript file
+/// * `--replay <file>` - Answer every query from a transcript recorded with `--record`
+///   instead of the network, to reproduce a run offline
+/// * `--hosts` - Answer A, AAAA and PTR queries for the names and addresses listed in
+///   `/etc/hosts` from the file, without sending them, as the C library does
+/// * `--hosts-file <file>` - The same, with a hosts file other than `/etc/hosts`
+/// * `--idn-out <ascii|unicode>` - Show internationalized names in responses as the
+///   A-labels sent on the wire (`xn--bcher-kva`, the default) or decoded to U-labels
+///   (`bücher`). Only A-labels that are valid under IDNA2008 are decoded
+/// * `--verbose` - Also show the size of the query and response on the wire, the
+///   records present in each section against the header counts, whether the
+///   response used name compression, and best-practice warnings about the answer
+/// * `--tsig [algorithm:]name:secret` - Sign queries with a TSIG key (RFC 8945) shared
+///   with the server, given as in `dig -y` with a base64 secret and hmac-sha256 by
+///   default, and accept only responses signed with it. Works for lookups and `axfr`
+/// * `--tsig-keyfile <file>` - Read the TSIG key from a BIND key file, as written by
+///   `tsig-keygen`
+/// * `--human-ttl` - Show TTLs as durations such as `2h 30m` instead of seconds
+/// * `--ttl-expiry` - Also show when each record expires, as a UTC timestamp. JSON
+///   output keeps the numeric `ttl` and adds `ttl_human` and `expires` fields
+///
+/// # Subcommands
+///
+/// * `asn <ip_address>` - Look up the originating AS, prefix and AS name of an address
+///   through the Team Cymru DNS interface
+/// * `report <domain_name>` - Summarize the common records, DNSSEC status and mail
+///   posture (MX, SPF, DMARC) of a domain with graded findings, including
+///   best-practice checks of its TTLs, MX targets, SPF lookup count and nameservers
+///   and look-alike (homograph) labels, as text or, with `--format json`, as a single
+///   JSON object
+/// * `assert <domain_name> [record_type]` - Check the answer against expectations and
+///   exit with a failure status and a diff if any is not met, for CI pipelines:
+///   `--equals <value>` (repeatable, the exact set of values), `--contains <value>`,
+///   `--matches <pattern>`, `--count <n>` and `--max-ttl <seconds>`
+/// * `sweep <cidr>` - Look up the PTR records of every address in a prefix, printing
+///   the hostnames found. Tuned with `--concurrency <n>` (default 8), `--rate <n>`
+///   queries per second (default 20, 0 for no limit) and `--format text|csv|ndjson`
+/// * `open-resolvers <cidr>` - Probe an IPv4 prefix for hosts that resolve a third-party
+///   name (`--probe-name`, default `example.com`) for anyone. Only private, shared,
+///   loopback and link-local targets are allowed without `--allow-public`. Uses the
+///   same `--concurrency`, `--rate` (default 10) and `--format` options as `sweep`
+/// * `axfr <zone>` (or `<zone> AXFR`) - Transfer a whole zone over TCP from the server
+///   given with `--server`, which must allow it, and print every record, or save them
+///   as a zone file with `--output <file>`
+/// * `cache dump|flush [name]` - List the responses in the persistent cache given
+///   with `--cache-file <file>`, as text or with `--format csv|ndjson`, or remove
+///   the responses about a name, or all of them
+/// * `notify <zone> [serial]` - Tell the server given with `--server` that a zone
+///   changed (RFC 1996), with its new serial as an optional hint, and wait for the
+///   acknowledgement. The notification is signed when `--tsig` is given
+/// * `notify-listen <address:port>` - Acknowledge and print the zone change
+///   notifications sent to an address, until interrupted
 ///
 /// # Exit Behavior
 ///
@@ -113,120 +335,1361 @@ use resolver::resolve;
 /// # Query mail exchange records
 /// ./dns-resolver example.com MX
 /// ```
-fn main() {
+fn main() -> ExitCode {
     // Collect command-line arguments into a vector for processing.
     // The first argument (index 0) is always the program name/path.
     let args: Vec<String> = env::args().collect();
 
-    // Validate command-line argument count and provide usage information.
-    // We expect 1-2 arguments beyond the program name:
-    // - Required: domain name to resolve
-    // - Optional: DNS record type (defaults to 'A' if not specified)
-    if args.len() < 2 || args.len() > 3 {
-        eprintln!("Usage: {} <domain_name> [record_type]", args[0]);
-        eprintln!("Example: {} google.com A", args[0]);
-        eprintln!("Supported record types: A, AAAA, CNAME, MX, TXT");
-        return;
+    // Parse and validate the command-line arguments and provide usage information.
+    // We expect a domain name to resolve and an optional DNS record type
+    // (defaulting to 'A') or a subcommand, plus any of the supported options.
+    let cli_args = match parse_args(&args) {
+        Ok(cli_args) => cli_args,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            eprintln!(
+                "Usage: {} <domain_name> [record_type] [--server <address>|unix:<path>] [--source <address>] [--interface <name>] [--duplicate-window <ms>] [--strict] [--tcp] [--edns <bytes>] [--edns-opt <code>[:<hex>]] [--dnssec] [--cd] [--ad] [--require-ad] [--validate [--trust-anchors <file>]] [--class <class>] [--compare-resolved] [--consensus <address>,... [--quorum <n>]] [--all] [--human-ttl] [--ttl-expiry] [--verbose] [--record <file>|--replay <file>] [--tsig [algorithm:]name:secret|--tsig-keyfile <file>] [--hosts|--hosts-file <file>] [--idn-out <ascii|unicode>]",
+                args[0]
+            );
+            eprintln!("       {} -x <ip_address> [options]", args[0]);
+            eprintln!("       {} asn <ip_address> [options]", args[0]);
+            eprintln!(
+                "       {} report <domain_name> [--format text|json] [options]",
+                args[0]
+            );
+            eprintln!(
+                "       {} assert <domain_name> [record_type] [--equals <value>]... [--contains <value>] [--matches <pattern>] [--count <n>] [--max-ttl <seconds>] [options]",
+                args[0]
+            );
+            eprintln!(
+                "       {} sweep <cidr> [--concurrency <n>] [--rate <n>] [--format text|csv|ndjson] [options]",
+                args[0]
+            );
+            eprintln!(
+                "       {} open-resolvers <cidr> [--probe-name <name>] [--allow-public] [--concurrency <n>] [--rate <n>] [--format text|csv|ndjson] [options]",
+                args[0]
+            );
+            eprintln!(
+                "       {} axfr <zone> --server <address> [--output <file>] [options]",
+                args[0]
+            );
+            eprintln!(
+                "       {} notify <zone> [serial] --server <address> [options]",
+                args[0]
+            );
+            eprintln!("       {} notify-listen <address:port>", args[0]);
+            eprintln!(
+                "       {} cache dump|flush [name] --cache-file <file> [--format text|csv|ndjson]",
+                args[0]
+            );
+            eprintln!("Example: {} google.com A", args[0]);
+            eprintln!(
+                "Supported record types: A, AAAA, CNAME, MX, TXT, PTR, NS, SOA, HINFO, SRV, NAPTR, SSHFP, SVCB, HTTPS, DS, DNSKEY, RRSIG, NSEC, NSEC3, ANY, or TYPE<number>"
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Open the transcript to record to or replay from, if any, before any query
+    // is sent.
+    let transcript = match (&cli_args.record_transcript, &cli_args.replay_transcript) {
+        (Some(path), _) => Transcript::record(path).map(Some),
+        (_, Some(path)) => Transcript::replay(path).map(Some),
+        (None, None) => Ok(None),
+    };
+    let transcript = match transcript {
+        Ok(transcript) => transcript.map(Arc::new),
+        Err(e) => {
+            eprintln!("Error: cannot open the transcript: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Load the hosts file to answer from, if any.
+    let hosts = match &cli_args.hosts_file {
+        Some(path) => match HostsFile::load_from(path) {
+            Ok(hosts) => Some(Arc::new(hosts)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    // Load the trust anchors to validate from, if they were given.
+    let trust_anchors = match &cli_args.trust_anchors_file {
+        Some(path) => match load_trust_anchors(path) {
+            Ok(anchors) => Some(Arc::new(anchors)),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    // Load the TSIG key from its file, if it was given as one.
+    let tsig = match (&cli_args.tsig_key, &cli_args.tsig_key_file) {
+        (_, Some(path)) => match std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| TsigKey::from_key_file(&text))
+        {
+            Ok(key) => Some(key),
+            Err(e) => {
+                eprintln!(
+                    "Error: cannot read the TSIG key from {}: {}",
+                    path.display(),
+                    e
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        (key, None) => key.clone(),
+    };
+
+    // Carry the socket-level settings over to the resolver.
+    let mut options = QueryOptions {
+        source_address: cli_args.source_address,
+        interface: cli_args.interface.clone(),
+        duplicate_window: cli_args.duplicate_window,
+        strict: cli_args.strict,
+        dnssec_ok: cli_args.dnssec_ok,
+        checking_disabled: cli_args.checking_disabled,
+        // Asking for AD to be enforced only makes sense if the server reports it.
+        authentic_data: cli_args.authentic_data || cli_args.require_authentic_data,
+        validate: cli_args.validate,
+        trust_anchors,
+        class: cli_args.class,
+        unix_socket: cli_args.unix_socket.clone(),
+        tcp: cli_args.tcp,
+        edns: cli_args.edns,
+        edns_options: cli_args.edns_options.clone(),
+        transcript,
+        tsig,
+        hosts,
+        ..QueryOptions::default()
+    };
+
+    // Configure the DNS server to use for resolution, unless one was given with --server.
+    // Like every other program on the host, ask the system's nameservers, which may
+    // answer internal names that public resolvers do not know.
+    let dns_server_addr = match cli_args.server {
+        Some(server) => server,
+        None if uses_system_nameservers(&cli_args) => system_nameservers(&mut options),
+        None => FALLBACK_SERVER,
+    };
+
+    match &cli_args.command {
+        Command::Lookup {
+            domain_name,
+            query_type,
+        } if !cli_args.consensus_servers.is_empty() => run_consensus(
+            domain_name,
+            *query_type,
+            &cli_args.consensus_servers,
+            cli_args.quorum,
+            &options,
+        ),
+        Command::Lookup {
+            domain_name,
+            query_type,
+        } if cli_args.compare_resolved => {
+            run_resolved_comparison(domain_name, *query_type, cli_args.ttl_format, &options)
+        }
+        Command::Lookup {
+            domain_name,
+            query_type,
+        } => run_lookup(
+            &cli_args,
+            domain_name,
+            *query_type,
+            dns_server_addr,
+            &options,
+        ),
+        Command::LookupAll { domain_name } => run_lookup_all(
+            domain_name,
+            cli_args.ttl_format,
+            cli_args.idn_output,
+            dns_server_addr,
+            &options,
+        ),
+        Command::Report { domain_name } => run_report(
+            domain_name,
+            cli_args.output_format,
+            cli_args.ttl_format,
+            dns_server_addr,
+            &options,
+        ),
+        Command::Assert {
+            domain_name,
+            query_type,
+        } => run_assert(
+            domain_name,
+            *query_type,
+            &cli_args.assertions,
+            dns_server_addr,
+            &options,
+        ),
+        Command::Asn { address } => run_asn(*address, dns_server_addr, &options),
+        Command::Sweep { cidr } => run_sweep(&cli_args, cidr, dns_server_addr, &options),
+        Command::OpenResolvers { targets } => run_open_resolver_scan(&cli_args, targets, &options),
+        Command::Axfr { zone } => run_axfr(&cli_args, zone, dns_server_addr, &options),
+        Command::Notify { zone, serial } => run_notify(zone, *serial, dns_server_addr, &options),
+        Command::NotifyListen { address } => run_notify_listen(*address),
+        Command::CacheDump => run_cache_dump(&cli_args),
+        Command::CacheFlush { name } => run_cache_flush(&cli_args, name.as_deref()),
     }
+}
 
-    // Extract the domain name from the first argument.
-    // This should be a fully qualified domain name (e.g., "www.example.com").
-    let domain_name = &args[1];
+/// The server queried when the system's nameservers cannot be used. Google's public
+/// DNS is chosen for its reliability and global availability.
+const FALLBACK_SERVER: Ipv4Addr = Ipv4Addr::new(8, 8, 8, 8);
 
-    // Extract and validate the DNS record type from the optional second argument.
-    // If no record type is specified, default to 'A' (IPv4 address records).
-    let record_type_str = args.get(2).map_or("A", |s| s.as_str());
-    let query_type = match QueryType::from_str(record_type_str) {
-        Ok(qt) => qt,
-        Err(_) => {
+/// Checks whether a command sends its queries to the default server. Consensus and
+/// resolved comparisons pick their own servers, and some commands send none.
+fn uses_system_nameservers(cli_args: &CliArgs) -> bool {
+    cli_args.unix_socket.is_none()
+        && cli_args.consensus_servers.is_empty()
+        && !cli_args.compare_resolved
+        && !matches!(
+            cli_args.command,
+            Command::OpenResolvers { .. }
+                | Command::NotifyListen { .. }
+                | Command::CacheDump
+                | Command::CacheFlush { .. }
+        )
+}
+
+/// Points `options` at the nameservers the operating system uses, with their timeout,
+/// attempts and rotation, and returns the first one. Falls back to
+/// [`FALLBACK_SERVER`] with a warning if the configuration cannot be read or lists
+/// no IPv4 server.
+fn system_nameservers(options: &mut QueryOptions) -> Ipv4Addr {
+    match system_config().and_then(|conf| conf.apply(options)) {
+        Ok(server) => server,
+        Err(e) => {
             eprintln!(
-                "Error: Invalid record type '{}'. Supported types are A, AAAA, CNAME, MX, TXT.",
-                record_type_str
+                "Warning: not using the system nameservers ({}); querying {} instead.",
+                e, FALLBACK_SERVER
             );
-            return;
+            FALLBACK_SERVER
         }
-    };
+    }
+}
 
-    // Configure the DNS server to use for resolution.
-    // Google's public DNS (8.8.8.8) is chosen for its reliability and global availability.
-    // Alternative options include Cloudflare (1.1.1.1) or OpenDNS (208.67.222.222).
-    let dns_server_addr = "8.8.8.8".parse::<Ipv4Addr>().unwrap();
+/// Describes where queries go: the server address, or the Unix socket when one is
+/// configured.
+fn describe_server(dns_server_addr: Ipv4Addr, options: &QueryOptions) -> String {
+    match &options.unix_socket {
+        Some(path) => format!("unix:{}", path.display()),
+        None => dns_server_addr.to_string(),
+    }
+}
 
+/// Prints a prominent warning for each label that may make a name a homograph of
+/// another, checking every distinct name once.
+fn warn_homographs<'a>(names: impl IntoIterator<Item = &'a str>) {
+    let mut checked: Vec<String> = Vec::new();
+    for name in names {
+        let key = name.trim_end_matches('.').to_lowercase();
+        if checked.contains(&key) {
+            continue;
+        }
+        checked.push(key);
+        for label in check_homographs(name) {
+            eprintln!("WARNING: possible homograph in {}: label {}", name, label);
+        }
+    }
+}
+
+/// Resolves a single name and prints the full response.
+fn run_lookup(
+    cli_args: &CliArgs,
+    domain_name: &str,
+    query_type: QueryType,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
     // Display query information to the user before initiating the DNS request.
     // This provides immediate feedback about what operation is being performed.
     println!(
         "Querying {} for {} records of {}...",
-        dns_server_addr,
+        describe_server(dns_server_addr, options),
         query_type.to_string().to_uppercase(),
         domain_name
     );
     println!("------------------------------------");
 
+    // Flag look-alike names up front: whatever the answer, an analyst should know
+    // the name may be impersonating another one.
+    warn_homographs([domain_name]);
+
     // Perform the DNS resolution and handle the result.
-    // The resolve function performs the complete DNS query lifecycle and returns
-    // either a successful DNS message or a detailed error description.
-    match resolve(domain_name, query_type, dns_server_addr) {
-        Ok(dns_message) => {
+    // The query function performs the complete DNS query lifecycle and returns
+    // either the server's response (with any duplicates seen) or a detailed error description.
+    match query(domain_name, query_type, dns_server_addr, options) {
+        Ok(result) => {
+            // Record expiry (--ttl-expiry) is counted from when the response arrived.
+            let received = SystemTime::now();
+            let ttl_format = cli_args.ttl_format;
+
+            // Report duplicate responses first: a conflicting duplicate means the
+            // answer printed below may itself be the spoofed one.
+            print_duplicates(&result.duplicates, cli_args.duplicate_window.is_some());
+
+            // Warn when the server would not recurse for us: whatever follows is only
+            // what it knows authoritatively, often just a referral to other servers.
+            if result.recursion_unavailable {
+                eprintln!(
+                    "Warning: recursion was requested but {} does not offer it (RA=0). \
+                     It may be an authoritative-only server, and the response may be a referral \
+                     rather than an answer.",
+                    describe_server(result.server, options)
+                );
+                eprintln!();
+            }
+
+            // A truncated response only comes back when TCP could not be bound to
+            // --source or --interface on this platform.
+            if result.message.header.truncated() {
+                eprintln!(
+                    "Warning: the response from {} is truncated and could not be repeated over \
+                     TCP from the requested source address or interface, so records may be missing.",
+                    describe_server(result.server, options)
+                );
+                eprintln!();
+            }
+
+            // A well-formed response can still carry an error code like NXDOMAIN.
+            let response_code = result.message.header.get_response_code();
+            if response_code != ResponseCode::NoError {
+                eprintln!(
+                    "Error resolving {}: {}",
+                    domain_name,
+                    DnsError::ServerReturnedError(response_code)
+                );
+                return ExitCode::FAILURE;
+            }
+            // Show names as A-labels or U-labels, as asked (--idn-out), and match
+            // the queried name against them in the same form.
+            let dns_message = cli_args.idn_output.message(result.message);
+            let query_name = cli_args.idn_output.name(domain_name);
+
             // --- Display DNS Response Information ---
 
             // Print the DNS header containing metadata about the response.
             // The header includes information such as response codes, flags,
-            // and counts for each section of the DNS message.
-            println!("Header: {:?}", dns_message.header);
+            // and counts for each section of the DNS message, decoded as dig does.
+            println!("Header: {}", dns_message.header);
+
+            // In verbose mode, describe the exchange as it looked on the wire. Header
+            // counts that disagree with the sections point at a middlebox that edited
+            // the response without fixing it up.
+            if cli_args.verbose {
+                println!("Query Size: {} bytes", result.request_size);
+                println!("Response: {}", result.response_stats);
+                for mismatch in result.response_stats.mismatches() {
+                    println!("  ! {}", mismatch);
+                    eprintln!("Warning: {}", mismatch);
+                }
+            }
+
+            // Show the DNSSEC validation status reported by the upstream. AD is only
+            // meaningful when the upstream is a validating resolver we trust.
+            let authenticated = dns_message.header.authentic_data();
+            println!(
+                "Authenticated Data (AD): {}{}",
+                if authenticated { "yes" } else { "no" },
+                if dns_message.header.checking_disabled() {
+                    " (Checking Disabled)"
+                } else {
+                    ""
+                }
+            );
+            // A server that ignores EDNS, or strips the DO bit, will not send the
+            // signatures, which explains a response without any RRSIG records.
+            if options.dnssec_ok {
+                let echoed = dns_message.edns().is_some_and(|edns| edns.dnssec_ok);
+                println!(
+                    "DNSSEC OK (DO): {}",
+                    if echoed {
+                        "yes"
+                    } else {
+                        "no (not echoed by the server, so DNSSEC records may be missing)"
+                    }
+                );
+            }
+
+            // Our own verdict, from the signatures themselves rather than the upstream.
+            if let Some(security) = &result.security {
+                println!("DNSSEC Validation: {}", security);
+            }
+            // The query would have failed had the signature not checked out.
+            if let Some(key) = &options.tsig {
+                println!("TSIG: verified (key {}, {})", key.name, key.algorithm);
+            }
             println!();
 
             // Display the question section showing what was asked.
             // This confirms the query that was sent to the DNS server and
             // helps verify that the response matches the request.
             println!("Question Section:");
-            for question in dns_message.questions {
-                println!("  - QNAME: {}, QTYPE: {}", question.name, question.qtype);
+            for question in &dns_message.questions {
+                println!(
+                    "  - QNAME: {}, QTYPE: {}, QCLASS: {}",
+                    question.name,
+                    question.qtype,
+                    DnsClass::from(question.qclass)
+                );
             }
             println!();
 
+            // Reconstruct any alias chain from the queried name, since servers may
+            // return the CNAME records in any order, and show it hop by hop.
+            let chain = follow_cname_chain(&query_name, &dns_message.answers);
+            if !chain.hops.is_empty() {
+                println!("CNAME Chain:");
+                println!("  {}", chain.start);
+                for hop in &chain.hops {
+                    println!(
+                        "    -> {} (TTL {})",
+                        hop.target,
+                        ttl_format.render(hop.ttl, received)
+                    );
+                }
+                match &chain.problem {
+                    Some(problem) => {
+                        println!("  ! {}", problem);
+                        eprintln!("Warning: {}", problem);
+                    }
+                    None => println!("  Final target: {}", chain.final_target()),
+                }
+                println!();
+            }
+
             // Display answer records if any were returned.
             // Answer records contain the direct responses to the DNS query
             // (e.g., IP addresses for A records, mail servers for MX records).
+            // The chain's CNAME records come first, in chain order.
             if !dns_message.answers.is_empty() {
                 println!("Answer Section:");
-                for record in dns_message.answers {
-                    println!("  - {}", record);
+                let (mut chained, rest): (Vec<_>, Vec<_>) =
+                    dns_message.answers.iter().partition(|record| {
+                        chain.hops.iter().any(|hop| {
+                            record.get_cname() == Some(hop.target.as_str())
+                                && record.name == hop.alias
+                        })
+                    });
+                chained.sort_by_key(|record| {
+                    chain.hops.iter().position(|hop| record.name == hop.alias)
+                });
+                for record in chained.into_iter().chain(rest) {
+                    println!("  - {}", ttl_format.record(record, received));
                 }
             } else {
                 println!("Answer Section: No records found.");
             }
+            // The HINFO record above is not really the name's: the server declined
+            // to list everything and synthesized it instead (RFC 8482).
+            if dns_message.is_minimal_any_response() {
+                println!(
+                    "  Note: {} declined the ANY query (RFC 8482) and answered with a \
+                     placeholder HINFO record. Query specific types instead, or use --all.",
+                    describe_server(dns_server_addr, options)
+                );
+            }
             println!();
 
+            // Aliases and targets in the answer can be look-alikes too.
+            warn_homographs(dns_message.answers.iter().flat_map(|record| {
+                let target = match &record.data {
+                    RData::CNAME(name) | RData::NS(name) | RData::PTR(name) => Some(name.as_str()),
+                    RData::MX { exchange, .. } => Some(exchange.as_str()),
+                    RData::SRV { target, .. }
+                    | RData::SVCB { target, .. }
+                    | RData::HTTPS { target, .. } => Some(target.as_str()),
+                    _ => None,
+                };
+                std::iter::once(record.name.as_str()).chain(target)
+            }));
+
             // Display authority records if present.
             // Authority records identify authoritative name servers for the domain
             // and are particularly useful when no direct answers are available.
             if !dns_message.authorities.is_empty() {
                 println!("Authority Section:");
-                for record in dns_message.authorities {
-                    println!("  - {}", record);
+                for record in &dns_message.authorities {
+                    println!("  - {}", ttl_format.record(record, received));
                 }
             } else {
                 println!("Authority Section: No records found.");
             }
             println!();
 
+            // In verbose mode, point out answers that work but go against common
+            // practice, such as TTLs too short to cache or an alias at a zone apex.
+            if cli_args.verbose {
+                let findings = lint_response(&query_name, &dns_message, &LintOptions::default());
+                if !findings.is_empty() {
+                    println!("Lint:");
+                    for finding in findings {
+                        println!("  [{}] {}", finding.severity, finding.message);
+                    }
+                    println!();
+                }
+            }
+
             // Display additional records if present.
             // Additional records provide supplementary information that may be
             // useful but wasn't directly requested (e.g., A records for MX targets).
-            if !dns_message.additionals.is_empty() {
+            // The OPT pseudo-record is shown as the EDNS parameters it carries, and
+            // the TSIG pseudo-record as the verification result above.
+            let additionals: Vec<_> = dns_message
+                .additionals
+                .iter()
+                .filter(|record| !matches!(record.rtype, QueryType::OPT | QueryType::TSIG))
+                .collect();
+            if !additionals.is_empty() {
                 println!("Additional Section:");
-                for record in dns_message.additionals {
-                    println!("  - {}", record);
+                for record in additionals {
+                    println!("  - {}", ttl_format.record(record, received));
                 }
             } else {
                 println!("Additional Section: No records found.");
             }
+            if let Some(edns) = dns_message.edns() {
+                println!("EDNS: {}", edns);
+            }
+
+            // Enforce the AD requirement and the validation verdict last so the
+            // response is still shown.
+            if let Some(SecurityStatus::Bogus(reason)) = &result.security {
+                eprintln!();
+                eprintln!("Error: the response failed DNSSEC validation: {}.", reason);
+                return ExitCode::FAILURE;
+            }
+            if cli_args.require_authentic_data && !authenticated {
+                eprintln!();
+                eprintln!(
+                    "Error: the response from {} is not marked as authenticated (AD=0).",
+                    describe_server(dns_server_addr, options)
+                );
+                return ExitCode::FAILURE;
+            }
+            ExitCode::SUCCESS
+        }
+        Err(DnsError::ProtocolViolations(violations)) => {
+            // List each strict-mode finding on its own line so it can be read at a glance.
+            eprintln!(
+                "Error resolving {}: response failed strict validation:",
+                domain_name
+            );
+            for violation in violations {
+                eprintln!("  - {}", violation);
+            }
+            ExitCode::FAILURE
         }
         Err(e) => {
             // Handle DNS resolution errors with descriptive error messages.
             // This covers various failure scenarios including network issues,
             // DNS server errors, timeouts, and protocol violations.
             eprintln!("Error resolving {}: {}", domain_name, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Resolves a name through the systemd-resolved stub and directly against the
+/// upstream server resolved routes it to, and prints both answers side by side.
+///
+/// Succeeds if both answered with the same records. TTLs are not compared, since
+/// resolved serves cached records with decreasing TTLs.
+fn run_resolved_comparison(
+    domain_name: &str,
+    query_type: QueryType,
+    ttl_format: TtlFormat,
+    options: &QueryOptions,
+) -> ExitCode {
+    // Find out where resolved would send the query. Without that we can still show
+    // what resolved itself answers.
+    let upstream = match ResolvedConfig::load() {
+        Ok(config) => match config.route(domain_name) {
+            Some(link) => {
+                let server = link.servers.iter().find_map(|server| match server {
+                    IpAddr::V4(addr) => Some(*addr),
+                    IpAddr::V6(_) => None,
+                });
+                if server.is_none() {
+                    eprintln!(
+                        "Warning: link {} only has IPv6 DNS servers, which are not supported yet.",
+                        link.name
+                    );
+                }
+                server.map(|server| (server, link.name.clone()))
+            }
+            None => {
+                eprintln!("Warning: systemd-resolved has no DNS servers configured.");
+                None
+            }
+        },
+        Err(e) => {
+            eprintln!("Warning: {}", e);
+            None
+        }
+    };
+
+    println!(
+        "Comparing {} records of {} via systemd-resolved and its upstream...",
+        query_type, domain_name
+    );
+    println!("------------------------------------");
+
+    let stub_result = query(domain_name, query_type, STUB_ADDRESS, options);
+    let stub_answers = print_comparison_side(
+        &format!("systemd-resolved ({})", STUB_ADDRESS),
+        &stub_result,
+        ttl_format,
+    );
+
+    let Some((server, link)) = upstream else {
+        return ExitCode::FAILURE;
+    };
+    let upstream_result = query(domain_name, query_type, server, options);
+    let upstream_answers = print_comparison_side(
+        &format!("Upstream {} (link {})", server, link),
+        &upstream_result,
+        ttl_format,
+    );
+
+    match (stub_answers, upstream_answers) {
+        (Some(stub), Some(upstream)) if stub == upstream => {
+            println!("Result: resolved and the upstream agree.");
+            ExitCode::SUCCESS
+        }
+        (Some(_), Some(_)) => {
+            println!(
+                "Result: the answers differ. resolved may be serving a stale cache entry, \
+                 applying DNSSEC or split-DNS policy, or answering from /etc/hosts."
+            );
+            ExitCode::FAILURE
+        }
+        _ => {
+            println!("Result: at least one side failed to answer.");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Prints one side of a resolved comparison and returns its answer section as a
+/// sorted list of records without TTLs, with the response code first.
+fn print_comparison_side(
+    label: &str,
+    result: &Result<QueryResult, DnsError>,
+    ttl_format: TtlFormat,
+) -> Option<Vec<String>> {
+    println!("{}:", label);
+    let answers = match result {
+        Ok(result) => {
+            let received = SystemTime::now();
+            let header = &result.message.header;
+            println!(
+                "  Response code: {:?}, AD: {}",
+                header.get_response_code(),
+                if header.authentic_data() { "yes" } else { "no" }
+            );
+            let mut answers: Vec<String> = result
+                .message
+                .answers
+                .iter()
+                .map(|record| {
+                    println!("  - {}", ttl_format.record(record, received));
+                    format!("{} {}", record.name.to_lowercase(), record.data)
+                })
+                .collect();
+            if answers.is_empty() {
+                println!("  No records found.");
+            }
+            answers.sort();
+            answers.insert(0, format!("{:?}", header.get_response_code()));
+            Some(answers)
+        }
+        Err(e) => {
+            println!("  Error: {}", e);
+            None
+        }
+    };
+    println!();
+    answers
+}
+
+/// Resolves all common record types of a name at once and prints the answers grouped
+/// by type.
+///
+/// Succeeds if at least one type could be resolved, since most names only have a
+/// few of the record types asked for.
+fn run_lookup_all(
+    domain_name: &str,
+    ttl_format: TtlFormat,
+    idn_output: IdnOutput,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    println!(
+        "Querying {} for all common records of {}...",
+        describe_server(dns_server_addr, options),
+        domain_name
+    );
+    println!("------------------------------------");
+
+    let results = resolve_many(domain_name, &QueryType::COMMON, dns_server_addr, options);
+    let received = SystemTime::now();
+
+    // NXDOMAIN applies to the name as a whole, so report it once rather than per type.
+    if results.iter().all(|r| {
+        matches!(
+            r,
+            Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+        )
+    }) {
+        eprintln!(
+            "Error resolving {}: {}",
+            domain_name,
+            DnsError::ServerReturnedError(ResponseCode::NameError)
+        );
+        return ExitCode::FAILURE;
+    }
+
+    let mut resolved = false;
+    for (query_type, result) in QueryType::COMMON.iter().zip(results) {
+        match result {
+            Ok(message) => {
+                resolved = true;
+                let message = idn_output.message(message);
+                // Answers for one type can include other types, such as the CNAME an
+                // address lookup followed, so only list the records of the type asked
+                // for; the CNAME group shows the aliases.
+                let records: Vec<_> = message
+                    .answers
+                    .iter()
+                    .filter(|record| record.rtype == *query_type)
+                    .collect();
+                // Mark the types a validating upstream vouched for.
+                let authenticated = if message.header.authentic_data() {
+                    " (authenticated)"
+                } else {
+                    ""
+                };
+                if records.is_empty() {
+                    println!("{}{}: No records found.", query_type, authenticated);
+                } else {
+                    println!("{}{}:", query_type, authenticated);
+                    for record in records {
+                        println!("  - {}", ttl_format.record(record, received));
+                    }
+                }
+            }
+            Err(e) => println!("{}: Error: {}", query_type, e),
+        }
+        println!();
+    }
+
+    if resolved {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+/// Builds and prints a report card for a domain.
+///
+/// Exits with a failure status if the report contains any error-level finding.
+fn run_report(
+    domain_name: &str,
+    format: OutputFormat,
+    ttl_format: TtlFormat,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    if format == OutputFormat::Csv {
+        eprintln!("Error: reports can be printed as text or json, not csv");
+        return ExitCode::FAILURE;
+    }
+
+    eprintln!(
+        "Building report for {} via {}...",
+        domain_name,
+        describe_server(dns_server_addr, options)
+    );
+    let mut report = build_report(domain_name, dns_server_addr, options);
+    report.ttl_format = ttl_format;
+    match format {
+        OutputFormat::Ndjson => println!("{}", report.to_json()),
+        _ => print!("{}", report),
+    }
+
+    if report
+        .findings
+        .iter()
+        .any(|f| f.severity == Severity::Error)
+    {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Asks every consensus server the same question and prints the records a quorum
+/// agreed on, followed by the servers that disagreed or failed.
+///
+/// Succeeds if at least a quorum of servers returned exactly the agreed records.
+fn run_consensus(
+    domain_name: &str,
+    query_type: QueryType,
+    servers: &[Ipv4Addr],
+    quorum: Option<usize>,
+    options: &QueryOptions,
+) -> ExitCode {
+    let quorum = quorum.unwrap_or_else(|| majority(servers.len()));
+    println!(
+        "Querying {} servers for {} records of {} (quorum {})...",
+        servers.len(),
+        query_type.to_string().to_uppercase(),
+        domain_name,
+        quorum
+    );
+    println!("------------------------------------");
+
+    let consensus = query_consensus(domain_name, query_type, servers, quorum, options);
+
+    if consensus.agreed.is_empty() {
+        println!("Agreed Answer: No records.");
+    } else {
+        println!("Agreed Answer:");
+        for record in &consensus.agreed {
+            println!("  - {}", record);
+        }
+    }
+    println!();
+
+    // A server that disagrees with the quorum is either stale or lying; show
+    // exactly what it added and left out.
+    for outlier in &consensus.outliers {
+        println!("Outlier {}:", outlier.server);
+        for record in &outlier.unconfirmed {
+            println!("  + {}", record);
+        }
+        for record in &outlier.missing {
+            println!("  - {}", record);
+        }
+        eprintln!(
+            "Warning: {} answered differently from the quorum",
+            outlier.server
+        );
+    }
+    for answer in &consensus.answers {
+        if let Err(e) = &answer.records {
+            println!("Failed {}: {}", answer.server, e);
+        }
+    }
+
+    if consensus.reached() {
+        ExitCode::SUCCESS
+    } else {
+        eprintln!(
+            "Error: fewer than {} of {} servers agreed on the answer",
+            quorum,
+            servers.len()
+        );
+        ExitCode::FAILURE
+    }
+}
+
+/// Resolves a name and checks the answer against each assertion, printing a line per
+/// assertion and a diff for those that fail.
+///
+/// A name that does not exist is checked as an empty answer, so `--count 0` can
+/// assert that a record was removed. Succeeds only if every assertion holds.
+fn run_assert(
+    domain_name: &str,
+    query_type: QueryType,
+    assertions: &[Assertion],
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    let result = match query(domain_name, query_type, dns_server_addr, options) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error resolving {}: {}", domain_name, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    let response_code = result.message.header.get_response_code();
+    if !matches!(
+        response_code,
+        ResponseCode::NoError | ResponseCode::NameError
+    ) {
+        eprintln!(
+            "Error resolving {}: {}",
+            domain_name,
+            DnsError::ServerReturnedError(response_code)
+        );
+        return ExitCode::FAILURE;
+    }
+
+    // Only records of the asserted type count; aliases on the way are not the answer.
+    let records: Vec<_> = result
+        .message
+        .answers
+        .into_iter()
+        .filter(|record| record.rtype == query_type)
+        .collect();
+    let mut failed = false;
+    for assertion in assertions {
+        match assertion.check(&records) {
+            Ok(()) => println!("PASS {} {} {}", domain_name, query_type, assertion),
+            Err(diff) => {
+                failed = true;
+                println!("FAIL {} {} {}", domain_name, query_type, assertion);
+                for line in diff {
+                    println!("    {}", line);
+                }
+            }
+        }
+    }
+
+    if failed {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Transfers a zone over TCP and prints its records, or saves them as a zone file
+/// with `--output`.
+fn run_axfr(
+    cli_args: &CliArgs,
+    zone: &str,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    println!(
+        "Transferring {} from {} over TCP...",
+        zone,
+        describe_server(dns_server_addr, options)
+    );
+    println!("------------------------------------");
+
+    if let Some(key) = &options.tsig {
+        println!("Signing with TSIG key {} ({})", key.name, key.algorithm);
+    }
+    let transfer = match transfer(zone, dns_server_addr, options) {
+        Ok(transfer) => transfer,
+        Err(DnsError::ServerReturnedError(code)) => {
+            // Servers refuse transfers to anyone but their secondaries as a rule.
+            eprintln!(
+                "{} declined the transfer of {} ({:?}). Servers usually only allow \
+                 transfers to their secondaries; try one of the zone's NS servers.",
+                dns_server_addr, zone, code
+            );
+            return ExitCode::FAILURE;
+        }
+        Err(e) => {
+            eprintln!("Error transferring {}: {}", zone, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    match &cli_args.zone_file {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, transfer.to_zone_file()) {
+                eprintln!("Error: cannot write {}: {}", path.display(), e);
+                return ExitCode::FAILURE;
+            }
+            println!(
+                "Saved {} records of {} to {}.",
+                transfer.records.len(),
+                zone,
+                path.display()
+            );
+        }
+        None => {
+            let received = SystemTime::now();
+            for record in &transfer.records {
+                println!("{}", cli_args.ttl_format.record(record, received));
+            }
+            println!();
+            println!(
+                "{} records in {} messages.",
+                transfer.records.len(),
+                transfer.messages
+            );
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+/// Sends a NOTIFY for a zone and reports whether the server acknowledged it.
+fn run_notify(
+    zone: &str,
+    serial: Option<u32>,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    match serial {
+        Some(serial) => println!(
+            "Notifying {} that {} changed (serial {})...",
+            dns_server_addr, zone, serial
+        ),
+        None => println!("Notifying {} that {} changed...", dns_server_addr, zone),
+    }
+    println!("------------------------------------");
+
+    if let Some(key) = &options.tsig {
+        println!("Signing with TSIG key {} ({})", key.name, key.algorithm);
+    }
+    match notify::send(zone, serial, dns_server_addr, options) {
+        Ok(ack) => {
+            println!(
+                "{} acknowledged the notification in {} ms after {} attempt(s).",
+                dns_server_addr,
+                ack.round_trip.as_millis(),
+                ack.attempts
+            );
+            ExitCode::SUCCESS
+        }
+        Err(DnsError::ServerReturnedError(code)) => {
+            // Servers only accept notifications for zones they are secondaries of,
+            // and often only from their configured primaries.
+            eprintln!(
+                "{} declined the notification for {} ({:?}). Check that it is a \
+                 secondary for the zone and accepts notifications from this host.",
+                dns_server_addr, zone, code
+            );
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error notifying {}: {}", dns_server_addr, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Acknowledges and prints every zone change notification sent to `address`, until
+/// interrupted.
+fn run_notify_listen(address: SocketAddr) -> ExitCode {
+    let socket = match UdpSocket::bind(address) {
+        Ok(socket) => socket,
+        Err(e) => {
+            eprintln!("Error: cannot listen on {}: {}", address, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("Listening for NOTIFY messages on {}...", address);
+    println!("------------------------------------");
+
+    let result = listen(&socket, |notify: &Notify, sender| {
+        match notify.serial {
+            Some(serial) => println!(
+                "{} NOTIFY from {}: {} {} serial {}",
+                notify.id, sender, notify.zone, notify.class, serial
+            ),
+            None => println!(
+                "{} NOTIFY from {}: {} {}",
+                notify.id, sender, notify.zone, notify.class
+            ),
+        }
+        true
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error receiving notifications on {}: {}", address, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Looks up and prints the originating AS of an address via Team Cymru.
+fn run_asn(address: IpAddr, dns_server_addr: Ipv4Addr, options: &QueryOptions) -> ExitCode {
+    println!(
+        "Querying {} for the origin AS of {} ({})...",
+        describe_server(dns_server_addr, options),
+        address,
+        origin_query_name(address)
+    );
+    println!("------------------------------------");
+
+    match lookup_asn(address, dns_server_addr, options) {
+        Ok(origins) if origins.is_empty() => {
+            println!("No origin AS found for {}.", address);
+            ExitCode::FAILURE
+        }
+        Ok(origins) => {
+            for origin in origins {
+                println!(
+                    "AS{:<10} {:<20} {:<3} {:<9} {:<11} {}",
+                    origin.asn,
+                    origin.prefix,
+                    origin.country,
+                    origin.registry,
+                    origin.allocated,
+                    origin.as_name.as_deref().unwrap_or("(name unavailable)")
+                );
+            }
+            ExitCode::SUCCESS
+        }
+        Err(DnsError::ServerReturnedError(ResponseCode::NameError)) => {
+            // Team Cymru answers NXDOMAIN for addresses that are not announced.
+            eprintln!("{} is not covered by any announced prefix.", address);
+            ExitCode::FAILURE
+        }
+        Err(e) => {
+            eprintln!("Error looking up the origin AS of {}: {}", address, e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Sweeps a prefix for PTR records, printing hostnames as they are found.
+///
+/// Only the results go to stdout, so CSV and NDJSON output can be redirected
+/// straight into a file; progress, errors and the summary go to stderr.
+fn run_sweep(
+    cli_args: &CliArgs,
+    cidr: &Cidr,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> ExitCode {
+    let sweep_options = sweep_options(cli_args, SweepOptions::default());
+
+    eprintln!(
+        "Sweeping {} ({} addresses) via {}...",
+        cidr,
+        cidr.len(),
+        describe_server(dns_server_addr, options)
+    );
+    if cli_args.output_format == OutputFormat::Csv {
+        println!("{}", csv_header());
+    }
+
+    let (mut found, mut failed) = (0usize, 0usize);
+    let outcome = sweep(cidr, dns_server_addr, options, &sweep_options, |result| {
+        match &result.outcome {
+            Ok(hostnames) if !hostnames.is_empty() => found += 1,
+            Ok(_) => {}
+            Err(e) => {
+                failed += 1;
+                eprintln!("Error looking up {}: {}", result.address, e);
+            }
+        }
+        for line in result.format_lines(cli_args.output_format) {
+            println!("{}", line);
+        }
+    });
+
+    match outcome {
+        Ok(()) => {
+            eprintln!(
+                "Swept {} addresses: {} with PTR records, {} failed.",
+                cidr.len(),
+                found,
+                failed
+            );
+            if failed > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Scans a prefix for open resolvers, printing every host that answered.
+///
+/// Exits with a failure status if any open resolver was found, so the scan can
+/// gate a deployment or monitoring check.
+fn run_open_resolver_scan(cli_args: &CliArgs, targets: &Cidr, options: &QueryOptions) -> ExitCode {
+    let defaults = ScanOptions::default();
+    let scan_options = ScanOptions {
+        probe_name: cli_args.probe_name.clone().unwrap_or(defaults.probe_name),
+        allow_public: cli_args.allow_public,
+        sweep: sweep_options(cli_args, defaults.sweep),
+    };
+
+    eprintln!(
+        "Probing {} ({} addresses) for open resolvers with a recursive query for {}...",
+        targets,
+        targets.len(),
+        scan_options.probe_name
+    );
+    if cli_args.output_format == OutputFormat::Csv {
+        println!("address,status");
+    }
+
+    // Hosts that never answered are the bulk of most ranges, so only list responders.
+    let (mut responded, mut open) = (0usize, 0usize);
+    let outcome = scan_open_resolvers(targets, &scan_options, options, |address, status| {
+        if !status.responded() {
+            return;
+        }
+        responded += 1;
+        if status == ResolverStatus::Open {
+            open += 1;
+        }
+        println!(
+            "{}",
+            scan::format_line(address, &status, cli_args.output_format)
+        );
+    });
+
+    match outcome {
+        Ok(()) => {
+            eprintln!(
+                "Probed {} addresses: {} responded, {} open resolvers.",
+                targets.len(),
+                responded,
+                open
+            );
+            if open > 0 {
+                ExitCode::FAILURE
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// Applies the `--concurrency` and `--rate` options on top of a command's defaults.
+fn sweep_options(cli_args: &CliArgs, defaults: SweepOptions) -> SweepOptions {
+    SweepOptions {
+        concurrency: cli_args.concurrency.unwrap_or(defaults.concurrency),
+        // An explicit rate of zero lifts the limit entirely.
+        rate_limit: match cli_args.rate_limit {
+            Some(0) => None,
+            Some(rate) => Some(rate),
+            None => defaults.rate_limit,
+        },
+    }
+}
+
+/// Prints the duplicate responses collected during the duplicate-detection window.
+///
+/// Conflicting duplicates are printed to stderr as warnings, together with the
+/// records that differ, since they are evidence of a spoofing attempt. Nothing is
+/// printed when duplicate detection was not requested.
+fn print_duplicates(duplicates: &[DuplicateResponse], detection_enabled: bool) {
+    if !detection_enabled {
+        return;
+    }
+
+    if duplicates.is_empty() {
+        println!("Duplicate Responses: none received.");
+        println!();
+        return;
+    }
+
+    println!("Duplicate Responses:");
+    for duplicate in duplicates {
+        let delay_ms = duplicate.delay.as_secs_f64() * 1000.0;
+        if !duplicate.conflicting {
+            println!(
+                "  - from {} after +{:.1}ms: identical to the accepted response",
+                duplicate.source, delay_ms
+            );
+            continue;
+        }
+
+        println!(
+            "  - from {} after +{:.1}ms: CONFLICTING ({:?})",
+            duplicate.source, delay_ms, duplicate.response_code
+        );
+        for record in &duplicate.only_in_accepted {
+            println!("      only in accepted:  {}", record);
+        }
+        for record in &duplicate.only_in_duplicate {
+            println!("      only in duplicate: {}", record);
+        }
+        eprintln!(
+            "Warning: conflicting response from {} arrived {:.1}ms after the accepted one; the answer may be spoofed.",
+            duplicate.source, delay_ms
+        );
+    }
+    println!();
+}
+
+/// Opens the persistent cache named with `--cache-file`, reporting any failure.
+fn open_cache_file(cli_args: &CliArgs) -> Option<FileCache> {
+    let path = cli_args.cache_file.as_ref()?;
+    // Opening creates a missing journal, which would hide a mistyped path.
+    if !path.exists() {
+        eprintln!("Error: there is no cache at {}", path.display());
+        return None;
+    }
+    match FileCache::open(path) {
+        Ok(cache) => Some(cache),
+        Err(e) => {
+            eprintln!("Error: cannot open the cache {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Prints every response in the persistent cache, fresh or kept to be served stale.
+fn run_cache_dump(cli_args: &CliArgs) -> ExitCode {
+    let Some(cache) = open_cache_file(cli_args) else {
+        return ExitCode::FAILURE;
+    };
+    let entries = cache.entries();
+    if cli_args.output_format == OutputFormat::Csv {
+        println!("{}", cache::csv_header());
+    }
+    for entry in &entries {
+        for line in entry.format_lines(cli_args.output_format) {
+            println!("{}", line);
+        }
+    }
+    let stale = entries.iter().filter(|entry| entry.stale).count();
+    eprintln!(
+        "{} cached responses in {} ({} stale).",
+        entries.len(),
+        cache.path().display(),
+        stale
+    );
+    ExitCode::SUCCESS
+}
+
+/// Removes the responses about a name, or all responses, from the persistent cache.
+fn run_cache_flush(cli_args: &CliArgs, name: Option<&str>) -> ExitCode {
+    let Some(cache) = open_cache_file(cli_args) else {
+        return ExitCode::FAILURE;
+    };
+    match cache.flush(name) {
+        Ok(removed) => {
+            println!(
+                "Removed {} cached responses{} from {}.",
+                removed,
+                name.map(|name| format!(" about {}", name))
+                    .unwrap_or_default(),
+                cache.path().display()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!(
+                "Error: cannot rewrite the cache {}: {}",
+                cache.path().display(),
+                e
+            );
+            ExitCode::FAILURE
         }
     }
 }
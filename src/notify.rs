@@ -0,0 +1,438 @@
+//! Zone change notifications (NOTIFY, RFC 1996).
+//!
+//! Secondaries poll their primary for zone changes every SOA refresh interval, which
+//! can leave them hours behind. A primary cuts that short by sending each secondary a
+//! NOTIFY message as soon as the zone changes: a message with opcode NOTIFY, the AA
+//! bit set and a question for the zone's SOA record, optionally with the new SOA
+//! record in the answer section as a hint. The secondary acknowledges it with a
+//! response that repeats the ID, opcode and question, then checks the serial on the
+//! primary and starts a zone transfer if it went up.
+//!
+//! [`send`] plays the primary, to make a secondary pick up a change right away, and
+//! [`listen`] plays the secondary, to watch which zones a primary announces. Both sides
+//! build their messages with [`build_notify`] and [`build_notify_response`] and read
+//! them with [`parse_notify`].
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::notify::send;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! // Tell a secondary that example.com is now at serial 2024010102.
+//! let ack = send("example.com", Some(2024010102), Ipv4Addr::new(192, 0, 2, 53), &QueryOptions::default())?;
+//! println!("acknowledged after {} attempts", ack.attempts);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::dns::{
+    DnsClass, DnsMessage, DnsName, DnsQuestion, Opcode, QueryType, RData, ResourceRecord,
+    ResponseCode, canonical_name,
+};
+use crate::resolver::{
+    DnsError, DnsErrorKind, QueryOptions, exchange, exchange_tcp, random_query_id,
+};
+use crate::tsig::TsigSession;
+
+/// A NOTIFY request or response, as read from the wire.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notify {
+    /// The transaction ID, which the response repeats.
+    pub id: u16,
+    /// Whether the message is the acknowledgement rather than the notification.
+    pub response: bool,
+    /// The name of the zone that changed.
+    pub zone: String,
+    /// The class of the zone.
+    pub class: DnsClass,
+    /// The serial of the zone's new SOA record, if the sender included it as a hint.
+    pub serial: Option<u32>,
+}
+
+/// The outcome of a notification that a server acknowledged.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotifyAck {
+    /// How many times the notification was sent before it was acknowledged.
+    pub attempts: u32,
+    /// How long the acknowledged attempt took.
+    pub round_trip: Duration,
+}
+
+/// Builds a NOTIFY request for a zone.
+///
+/// The message has a random ID, opcode NOTIFY, the AA bit set and an SOA question for
+/// the zone. With a serial, the answer section carries an SOA record with that serial
+/// as a hint; the receiver only looks at the serial, so the record's other fields are
+/// left empty.
+///
+/// # Arguments
+///
+/// * `zone` - The name of the zone that changed
+/// * `serial` - The zone's new serial, if it should be sent along
+/// * `class` - The class of the zone, usually [`DnsClass::IN`]
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{DnsClass, Opcode};
+/// use dns_resolver::notify::build_notify;
+///
+/// let message = build_notify("example.com", Some(2024010102), DnsClass::IN);
+/// assert_eq!(message.header.opcode(), Opcode::Notify);
+/// assert_eq!(message.answers.len(), 1);
+/// ```
+pub fn build_notify(zone: &str, serial: Option<u32>, class: DnsClass) -> DnsMessage {
+    let mut message = DnsMessage::new();
+    message.header.id = random_query_id();
+    message.header.set_opcode(Opcode::Notify);
+    message.header.set_authoritative(true); // The sender speaks for the zone
+    message.header.question_count = 1;
+    message.questions.push(DnsQuestion {
+        name: DnsName::new_unchecked(zone.to_string()),
+        qtype: QueryType::SOA,
+        qclass: class.into(),
+    });
+    if let Some(serial) = serial {
+        message.answers.push(ResourceRecord {
+            name: DnsName::new_unchecked(zone.to_string()),
+            rtype: QueryType::SOA,
+            rclass: class.into(),
+            ttl: 0,
+            data: RData::SOA {
+                mname: String::new(),
+                rname: String::new(),
+                serial,
+                refresh: 0,
+                retry: 0,
+                expire: 0,
+                minimum: 0,
+            },
+        });
+        message.header.answer_count = 1;
+    }
+    message
+}
+
+/// Builds the acknowledgement of a NOTIFY request.
+///
+/// The response repeats the request's ID, opcode and question, and sets the QR and
+/// AA bits. The answer section stays empty (RFC 1996 section 4.7).
+///
+/// # Arguments
+///
+/// * `request` - The NOTIFY request to acknowledge
+pub fn build_notify_response(request: &DnsMessage) -> DnsMessage {
+    let mut message = DnsMessage::new();
+    message.header.id = request.header.id;
+    message.header.set_opcode(Opcode::Notify);
+    message.header.set_response(true);
+    message.header.set_authoritative(true);
+    message.header.question_count = request.questions.len() as u16;
+    message.questions = request.questions.clone();
+    message
+}
+
+/// Reads a NOTIFY request or response from its wire format.
+///
+/// # Arguments
+///
+/// * `bytes` - The message as received
+///
+/// # Returns
+///
+/// * `Ok(Notify)` - The zone, class and serial hint of the notification
+/// * `Err(String)` - If the message is malformed or is not a NOTIFY message
+///
+/// # Errors
+///
+/// Returns an error when the message cannot be parsed, when its opcode is not NOTIFY,
+/// or when it does not have exactly one SOA question.
+pub fn parse_notify(bytes: &[u8]) -> Result<Notify, String> {
+    let message = DnsMessage::from_bytes(bytes).map_err(|e| e.to_string())?;
+    if message.header.opcode() != Opcode::Notify {
+        return Err(format!(
+            "opcode is {} instead of NOTIFY",
+            message.header.opcode()
+        ));
+    }
+    let question = match message.questions.as_slice() {
+        [question] if question.qtype == QueryType::SOA => question,
+        [question] => {
+            return Err(format!(
+                "NOTIFY question has type {} instead of SOA",
+                question.qtype
+            ));
+        }
+        questions => {
+            return Err(format!(
+                "NOTIFY has {} questions instead of one",
+                questions.len()
+            ));
+        }
+    };
+    // Only an SOA record of the zone itself counts as a serial hint.
+    let serial = message.answers.iter().find_map(|record| match record.data {
+        RData::SOA { serial, .. } if record.name == question.name => Some(serial),
+        _ => None,
+    });
+    Ok(Notify {
+        id: message.header.id,
+        response: message.header.is_response(),
+        zone: question.name.to_string(),
+        class: DnsClass::from(question.qclass),
+        serial,
+    })
+}
+
+/// Notifies a server that a zone changed, and waits for its acknowledgement.
+///
+/// The notification goes over UDP, or TCP with [`QueryOptions::tcp`], to
+/// [`QueryOptions::port`] in [`QueryOptions::class`], and is resent as
+/// [`QueryOptions::retry`] allows until the server acknowledges it, as a primary
+/// does (RFC 1996 section 3.6). With [`QueryOptions::tsig`] set, the notification is
+/// signed and the acknowledgement verified; secondaries often ignore unsigned
+/// notifications.
+///
+/// # Arguments
+///
+/// * `zone` - The name of the zone that changed
+/// * `serial` - The zone's new serial, sent along as a hint if given
+/// * `server` - The IPv4 address of the server to notify, usually a secondary
+/// * `options` - The transport, port, class, retry policy and key to use
+///
+/// # Returns
+///
+/// * `Ok(NotifyAck)` - The server acknowledged the notification
+/// * `Err(DnsError)` - If it declined, never answered, or answered with something else
+///
+/// # Errors
+///
+/// Returns [`DnsError::ServerReturnedError`] when the server answers with an error
+/// code, usually NOTAUTH or REFUSED from a server that is not a secondary for the
+/// zone, [`DnsError::Timeout`] when no attempt is acknowledged, and
+/// [`DnsError::InvalidResponse`] when the answer is not a NOTIFY response or fails
+/// TSIG verification.
+pub fn send(
+    zone: &str,
+    serial: Option<u32>,
+    server: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<NotifyAck, DnsError> {
+    let message = build_notify(zone, serial, options.class);
+    let mut request = Vec::new();
+    message
+        .pack(&mut request)
+        .map_err(DnsError::InvalidResponse)?;
+    let mut tsig = options
+        .tsig
+        .as_ref()
+        .map(|key| TsigSession::sign_request(key, &mut request, SystemTime::now()))
+        .transpose()
+        .map_err(DnsError::InvalidResponse)?;
+
+    // Every attempt sends the same bytes, so an acknowledgement of an earlier attempt
+    // that arrives late still matches.
+    let mut attempt = 0;
+    let (response, round_trip) = loop {
+        let timeout = options.retry.timeout(attempt);
+        let sent = Instant::now();
+        let outcome = if options.tcp {
+            exchange_tcp(&request, server, timeout, options)
+        } else {
+            exchange(&request, server, timeout, options).map(|(_, response)| response)
+        };
+        match outcome {
+            Ok(response) => break (response, sent.elapsed()),
+            Err(e)
+                if e.kind() == DnsErrorKind::Transient
+                    && attempt + 1 < options.retry.attempts() =>
+            {
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    if let Some(session) = &mut tsig {
+        session
+            .verify(&response, SystemTime::now())
+            .map_err(|e| DnsError::InvalidResponse(format!("TSIG verification failed: {}", e)))?;
+    }
+    // Servers that do not implement NOTIFY answer NOTIMP, often without echoing the
+    // opcode, so the response code is checked first.
+    let code = DnsMessage::from_bytes(&response)
+        .map_err(|e| DnsError::InvalidResponse(e.to_string()))?
+        .header
+        .get_response_code();
+    if code != ResponseCode::NoError {
+        return Err(DnsError::ServerReturnedError(code));
+    }
+    let ack = parse_notify(&response).map_err(DnsError::InvalidResponse)?;
+    if !ack.response {
+        return Err(DnsError::InvalidResponse(
+            "the server sent a NOTIFY request instead of a response".to_string(),
+        ));
+    }
+    if canonical_name(&ack.zone) != canonical_name(zone) {
+        return Err(DnsError::InvalidResponse(format!(
+            "the server acknowledged {} instead of {}",
+            ack.zone, zone
+        )));
+    }
+    Ok(NotifyAck {
+        attempts: attempt + 1,
+        round_trip,
+    })
+}
+
+/// Receives NOTIFY requests on a bound socket and acknowledges each one.
+///
+/// Every NOTIFY request is acknowledged first, so the sender stops resending it, and
+/// then handed to `on_notify` along with the address it came from. Datagrams that are
+/// not NOTIFY requests are ignored. Signed requests are acknowledged without checking
+/// or adding a signature.
+///
+/// # Arguments
+///
+/// * `socket` - The UDP socket to receive on, bound to the address primaries notify
+/// * `on_notify` - Called with each notification; returns whether to keep listening
+///
+/// # Errors
+///
+/// Returns [`DnsError::Io`] when receiving or sending fails, including when a read
+/// timeout set on the socket runs out.
+pub fn listen(
+    socket: &UdpSocket,
+    mut on_notify: impl FnMut(&Notify, SocketAddr) -> bool,
+) -> Result<(), DnsError> {
+    let mut buffer = vec![0; 65535];
+    loop {
+        let (size, sender) = socket.recv_from(&mut buffer)?;
+        let Ok(notify) = parse_notify(&buffer[..size]) else {
+            continue;
+        };
+        if notify.response {
+            continue;
+        }
+        // The request parsed as a NOTIFY, so it parses as a message too.
+        let request = DnsMessage::from_bytes(&buffer[..size])
+            .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+        let mut response = Vec::new();
+        build_notify_response(&request)
+            .pack(&mut response)
+            .map_err(DnsError::InvalidResponse)?;
+        socket.send_to(&response, sender)?;
+        if !on_notify(&notify, sender) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::retry::RetryPolicy;
+    use crate::testutil::StubServer;
+    use crate::transport::Reply;
+    use crate::tsig::TsigKey;
+    use std::thread;
+
+    #[test]
+    fn test_build_and_parse_notify() {
+        let message = build_notify("example.com", Some(2024010102), DnsClass::IN);
+        assert_eq!(message.header.flags, 0x2400);
+        let mut bytes = Vec::new();
+        message.pack(&mut bytes).unwrap();
+
+        let notify = parse_notify(&bytes).unwrap();
+        assert_eq!(notify.id, message.header.id);
+        assert!(!notify.response);
+        assert_eq!(notify.zone, "example.com");
+        assert_eq!(notify.class, DnsClass::IN);
+        assert_eq!(notify.serial, Some(2024010102));
+
+        let mut response = Vec::new();
+        build_notify_response(&message).pack(&mut response).unwrap();
+        let ack = parse_notify(&response).unwrap();
+        assert!(ack.response);
+        assert_eq!(
+            (ack.id, ack.zone.as_str(), ack.serial),
+            (notify.id, "example.com", None)
+        );
+
+        // Without a hint, there is no answer section at all.
+        let message = build_notify("example.com", None, DnsClass::IN);
+        assert!(message.answers.is_empty());
+
+        // A standard query is not a notification.
+        let mut query = message;
+        query.header.set_opcode(Opcode::Query);
+        let mut bytes = Vec::new();
+        query.pack(&mut bytes).unwrap();
+        assert!(parse_notify(&bytes).unwrap_err().contains("QUERY"));
+    }
+
+    #[test]
+    fn test_send_notify() {
+        let server = StubServer::start(vec![Reply::Drop, Reply::Answer(Vec::new())]).unwrap();
+        let mut options = server.query_options();
+        options.retry = RetryPolicy {
+            initial_timeout: Duration::from_millis(100),
+            max_attempts: 3,
+            ..RetryPolicy::default()
+        };
+        let ack = send("example.com", Some(7), Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(ack.attempts, 2);
+
+        // A server that is not a secondary for the zone declines.
+        let server = StubServer::start(vec![Reply::Code(ResponseCode::Refused)]).unwrap();
+        assert!(matches!(
+            send(
+                "example.com",
+                None,
+                Ipv4Addr::LOCALHOST,
+                &server.query_options()
+            ),
+            Err(DnsError::ServerReturnedError(ResponseCode::Refused))
+        ));
+
+        // Signed notifications need signed acknowledgements.
+        let key: TsigKey = "notify-key:c2VjcmV0".parse().unwrap();
+        let server = StubServer::start(vec![Reply::Signed(
+            key.clone(),
+            Box::new(Reply::Answer(Vec::new())),
+        )])
+        .unwrap();
+        let mut options = server.query_options();
+        options.tsig = Some(key);
+        send("example.com", None, Ipv4Addr::LOCALHOST, &options).unwrap();
+    }
+
+    #[test]
+    fn test_listen_acknowledges_notifications() {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let listener = thread::spawn(move || {
+            let mut received = Vec::new();
+            listen(&socket, |notify, _| {
+                received.push(notify.clone());
+                false
+            })
+            .map(|()| received)
+        });
+
+        let options = QueryOptions {
+            port: Some(port),
+            ..QueryOptions::default()
+        };
+        send("example.com", Some(42), Ipv4Addr::LOCALHOST, &options).unwrap();
+        let received = listener.join().unwrap().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].zone, "example.com");
+        assert_eq!(received[0].serial, Some(42));
+    }
+}
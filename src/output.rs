@@ -0,0 +1,262 @@
+//! Machine-readable output formats.
+//!
+//! Commands that produce many results at once (such as a reverse-DNS sweep) can
+//! print them as plain text for people, or as CSV or newline-delimited JSON for
+//! spreadsheets and log pipelines. This module holds the format selector and the
+//! escaping rules shared by those commands, along with [`TtlFormat`], which controls
+//! how TTLs are shown in every format.
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::dns::{DnsClass, ResourceRecord};
+
+/// How a command should print its results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputFormat {
+    /// Aligned columns meant to be read in a terminal.
+    #[default]
+    Text,
+    /// Comma-separated values with a header row (RFC 4180 quoting).
+    Csv,
+    /// One JSON object per line (`ndjson`, `jsonl` or `json`). Commands that
+    /// produce a single result print exactly one JSON document.
+    Ndjson,
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" | "jsonl" | "json" => Ok(OutputFormat::Ndjson),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputFormat::Text => write!(f, "text"),
+            OutputFormat::Csv => write!(f, "csv"),
+            OutputFormat::Ndjson => write!(f, "ndjson"),
+        }
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::output::csv_field;
+///
+/// assert_eq!(csv_field("host.example.com"), "host.example.com");
+/// assert_eq!(csv_field("a,b"), "\"a,b\"");
+/// ```
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders a string as a quoted JSON string literal.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::output::json_string;
+///
+/// assert_eq!(json_string("say \"hi\""), "\"say \\\"hi\\\"\"");
+/// ```
+pub fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// How TTLs are rendered for people (`--human-ttl`, `--ttl-expiry`).
+///
+/// By default a TTL is shown as the raw number of seconds the server sent.
+/// Machine-readable output always keeps that number and adds the chosen renderings
+/// as extra fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TtlFormat {
+    /// Show TTLs as durations such as `2h 30m` instead of seconds.
+    pub human: bool,
+    /// Also show when each record expires, counted from when it was received.
+    pub expiry: bool,
+}
+
+impl TtlFormat {
+    /// Renders `ttl` for a record received at `received`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::output::TtlFormat;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let format = TtlFormat { human: true, expiry: true };
+    /// let received = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    /// assert_eq!(format.render(9000, received), "2h 30m (expires 2023-11-15T00:43:20Z)");
+    /// ```
+    pub fn render(&self, ttl: u32, received: SystemTime) -> String {
+        let mut text = if self.human {
+            humanize_duration(ttl)
+        } else {
+            ttl.to_string()
+        };
+        if self.expiry {
+            text.push_str(&format!(" (expires {})", expiry_timestamp(ttl, received)));
+        }
+        text
+    }
+
+    /// Renders a record in the same columns as its `Display` implementation, with the
+    /// TTL rendered by [`TtlFormat::render`].
+    pub fn record(&self, record: &ResourceRecord, received: SystemTime) -> String {
+        format!(
+            "{:<30} {:<10} {:<8} {:<10} {}",
+            record.name,
+            self.render(record.ttl, received),
+            DnsClass::from(record.rclass),
+            record.rtype,
+            record.data
+        )
+    }
+}
+
+/// Renders a number of seconds as days, hours, minutes and seconds, leaving out the
+/// units that are zero.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::output::humanize_duration;
+///
+/// assert_eq!(humanize_duration(9000), "2h 30m");
+/// assert_eq!(humanize_duration(0), "0s");
+/// ```
+pub fn humanize_duration(seconds: u32) -> String {
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds % 86400 / 3600, "h"),
+        (seconds % 3600 / 60, "m"),
+        (seconds % 60, "s"),
+    ];
+    let parts: Vec<String> = units
+        .iter()
+        .filter(|(value, _)| *value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect();
+    if parts.is_empty() {
+        "0s".to_string()
+    } else {
+        parts.join(" ")
+    }
+}
+
+/// Returns when a record with `ttl` received at `received` expires, as an
+/// RFC 3339 UTC timestamp.
+pub fn expiry_timestamp(ttl: u32, received: SystemTime) -> String {
+    format_timestamp(received + Duration::from_secs(ttl.into()))
+}
+
+/// Renders a point in time as an RFC 3339 UTC timestamp with second precision.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::output::format_timestamp;
+/// use std::time::UNIX_EPOCH;
+///
+/// assert_eq!(format_timestamp(UNIX_EPOCH), "1970-01-01T00:00:00Z");
+/// ```
+pub fn format_timestamp(time: SystemTime) -> String {
+    // Times before the epoch do not occur for DNS data and are shown as the epoch.
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let (year, month, day) = civil_from_days(seconds / 86400);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds % 86400 / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// Converts a day count since 1970-01-01 into a proleptic Gregorian date.
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm, restricted to dates after
+/// the epoch.
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months are counted from March so that the leap day falls at the end of the year.
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_output_format() {
+        assert_eq!("CSV".parse(), Ok(OutputFormat::Csv));
+        assert_eq!("jsonl".parse(), Ok(OutputFormat::Ndjson));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+
+    #[test]
+    fn test_escaping() {
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(json_string("a\\b\u{1}"), "\"a\\\\b\\u0001\"");
+    }
+
+    #[test]
+    fn test_ttl_rendering() {
+        assert_eq!(humanize_duration(86400 + 61), "1d 1m 1s");
+        assert_eq!(humanize_duration(300), "5m");
+        let leap_day = UNIX_EPOCH + Duration::from_secs(951_782_400);
+        assert_eq!(format_timestamp(leap_day), "2000-02-29T00:00:00Z");
+        assert_eq!(expiry_timestamp(86400, leap_day), "2000-03-01T00:00:00Z");
+        let plain = TtlFormat::default();
+        assert_eq!(plain.render(300, leap_day), "300");
+    }
+}
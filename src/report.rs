@@ -0,0 +1,677 @@
+//! One-page overview reports for a domain.
+//!
+//! A [`DomainReport`] gathers what is usually checked when taking over or onboarding a
+//! domain: its common records, whether a validating resolver considers it
+//! DNSSEC-authenticated, and its mail posture (MX, SPF and DMARC). Problems found
+//! along the way, including the best-practice checks of [`crate::lint`], are listed as
+//! [`Finding`]s with a [`Severity`], and the report can be rendered as text for people
+//! or as a single JSON object for tooling.
+//!
+//! Delegation health and CAA are not part of the report yet: they need NS/SOA
+//! consistency checks and CAA record decoding, which the resolver does not have. The
+//! report lists them under [`DomainReport::not_checked`] so their absence is explicit.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::report::build_report;
+//! use dns_resolver::resolver::QueryOptions;
+//! use std::net::Ipv4Addr;
+//!
+//! let report = build_report("example.com", Ipv4Addr::new(8, 8, 8, 8), &QueryOptions::default());
+//! println!("{}", report);
+//! println!("{}", report.to_json());
+//! ```
+
+use std::fmt;
+use std::net::Ipv4Addr;
+use std::time::SystemTime;
+
+use crate::dns::{DnsClass, QueryType, RData, ResourceRecord};
+use crate::idn::{SuspiciousLabel, check_homographs};
+use crate::lint::{LintOptions, lint_domain};
+use crate::output::{TtlFormat, expiry_timestamp, humanize_duration, json_string};
+use crate::resolver::{QueryOptions, query, resolve_many, resolve_with_options};
+
+/// How serious a [`Finding`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth knowing, but not a problem in itself.
+    Info,
+    /// Likely to cause trouble or weakens security.
+    Warning,
+    /// Broken or actively harmful configuration.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A single observation made while building a report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// How serious the observation is.
+    pub severity: Severity,
+    /// A one-line description of the observation.
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Finding {
+            severity,
+            message: message.into(),
+        }
+    }
+}
+
+/// The answers found for one record type.
+#[derive(Debug, Clone)]
+pub struct RecordSet {
+    /// The record type that was queried.
+    pub query_type: QueryType,
+    /// The records of that type, or the error that prevented the lookup.
+    pub records: Result<Vec<ResourceRecord>, String>,
+}
+
+/// Mail-related records of a domain.
+#[derive(Debug, Clone, Default)]
+pub struct MailPosture {
+    /// Mail exchanges as `(preference, exchange)` pairs, most preferred first.
+    pub mx: Vec<(u16, String)>,
+    /// TXT records at the domain that declare an SPF policy (`v=spf1`).
+    pub spf: Vec<String>,
+    /// TXT records at `_dmarc.<domain>` that declare a DMARC policy (`v=DMARC1`).
+    pub dmarc: Vec<String>,
+}
+
+/// The combined overview of a domain.
+#[derive(Debug, Clone)]
+pub struct DomainReport {
+    /// The domain the report describes.
+    pub domain: String,
+    /// The answers for each of [`QueryType::COMMON`].
+    pub records: Vec<RecordSet>,
+    /// Whether the upstream resolver set the Authentic Data bit on the domain's
+    /// answer, or `None` if that query failed.
+    pub authenticated: Option<bool>,
+    /// MX, SPF and DMARC records.
+    pub mail: MailPosture,
+    /// Problems and notable facts, most severe first.
+    pub findings: Vec<Finding>,
+    /// Labels of the domain that may be impersonating another name, such as a
+    /// Cyrillic look-alike of a Latin one.
+    pub suspicious_labels: Vec<SuspiciousLabel>,
+    /// Checks that belong in the report but cannot be performed yet.
+    pub not_checked: Vec<String>,
+    /// When the record lookups were made, from which record expiry is counted.
+    pub queried_at: SystemTime,
+    /// How TTLs are rendered in the text and JSON output.
+    pub ttl_format: TtlFormat,
+}
+
+/// Builds a report for `domain` by querying `dns_server_addr`.
+///
+/// Lookups that fail are recorded in the report instead of aborting it, so a
+/// report is always produced. A domain that does not exist at all is reported as an
+/// error finding.
+///
+/// # Arguments
+///
+/// * `domain` - The domain to report on
+/// * `dns_server_addr` - The recursive resolver to query; it should validate
+///   DNSSEC for the authentication status to be meaningful
+/// * `options` - Settings shared by every query
+pub fn build_report(
+    domain: &str,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> DomainReport {
+    let queried_at = SystemTime::now();
+    let records: Vec<RecordSet> = QueryType::COMMON
+        .iter()
+        .zip(resolve_many(
+            domain,
+            &QueryType::COMMON,
+            dns_server_addr,
+            options,
+        ))
+        .map(|(query_type, result)| RecordSet {
+            query_type: *query_type,
+            records: result
+                .map(|message| {
+                    message
+                        .answers
+                        .into_iter()
+                        .filter(|record| record.rtype == *query_type)
+                        .collect()
+                })
+                .map_err(|e| e.to_string()),
+        })
+        .collect();
+
+    // Ask for the AD bit explicitly so a validating upstream reports its verdict.
+    let ad_options = QueryOptions {
+        authentic_data: true,
+        ..options.clone()
+    };
+    let authenticated = query(domain, QueryType::A, dns_server_addr, &ad_options)
+        .ok()
+        .map(|result| result.message.header.authentic_data());
+
+    let dmarc_txt = resolve_with_options(
+        &format!("_dmarc.{}", domain),
+        QueryType::TXT,
+        dns_server_addr,
+        options,
+    )
+    .map(|message| txt_strings(&message.answers))
+    .unwrap_or_default();
+
+    let rrset = |query_type: QueryType| -> Vec<ResourceRecord> {
+        records
+            .iter()
+            .find(|set| set.query_type == query_type)
+            .and_then(|set| set.records.as_ref().ok())
+            .cloned()
+            .unwrap_or_default()
+    };
+    let mut mx: Vec<(u16, String)> = rrset(QueryType::MX)
+        .iter()
+        .filter_map(|record| match &record.data {
+            RData::MX {
+                preference,
+                exchange,
+            } => Some((*preference, exchange.clone())),
+            _ => None,
+        })
+        .collect();
+    mx.sort();
+    let mail = MailPosture {
+        mx,
+        spf: txt_strings(&rrset(QueryType::TXT))
+            .into_iter()
+            .filter(|txt| is_policy(txt, "v=spf1"))
+            .collect(),
+        dmarc: dmarc_txt
+            .into_iter()
+            .filter(|txt| is_policy(txt, "v=DMARC1"))
+            .collect(),
+    };
+
+    let mut findings = Vec::new();
+    let suspicious_labels = check_homographs(domain);
+    for label in &suspicious_labels {
+        findings.push(Finding::new(
+            Severity::Warning,
+            format!("Possible homograph: label {}", label),
+        ));
+    }
+    let exists = records.iter().any(|set| set.records.is_ok());
+    if !exists {
+        // Without any records there is no DNSSEC or mail posture worth grading.
+        findings.push(Finding::new(
+            Severity::Error,
+            format!("No record type could be resolved for {}", domain),
+        ));
+    } else {
+        match authenticated {
+            Some(true) => {}
+            Some(false) => findings.push(Finding::new(
+                Severity::Info,
+                "Answers are not DNSSEC-authenticated (AD=0): the zone is unsigned or the resolver does not validate",
+            )),
+            None => findings.push(Finding::new(
+                Severity::Warning,
+                "DNSSEC status could not be determined",
+            )),
+        }
+        findings.extend(assess_mail(&mail));
+
+        let gathered: Vec<ResourceRecord> = records
+            .iter()
+            .filter_map(|set| set.records.as_ref().ok())
+            .flatten()
+            .cloned()
+            .collect();
+        findings.extend(lint_domain(
+            domain,
+            &gathered,
+            dns_server_addr,
+            options,
+            &LintOptions::default(),
+        ));
+    }
+    findings.sort_by_key(|finding| std::cmp::Reverse(finding.severity));
+
+    DomainReport {
+        domain: domain.to_string(),
+        records,
+        authenticated,
+        mail,
+        findings,
+        suspicious_labels,
+        not_checked: vec![
+            "Nameserver and delegation audit (NS and SOA consistency checks are not implemented)"
+                .to_string(),
+            "CAA records (CAA is not a supported record type)".to_string(),
+        ],
+        queried_at,
+        ttl_format: TtlFormat::default(),
+    }
+}
+
+/// Evaluates the mail-related records of a domain.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::report::{MailPosture, Severity, assess_mail};
+///
+/// let findings = assess_mail(&MailPosture::default());
+/// assert!(findings.iter().any(|f| f.severity == Severity::Warning));
+/// ```
+pub fn assess_mail(mail: &MailPosture) -> Vec<Finding> {
+    let mut findings = Vec::new();
+
+    // RFC 7505: a single MX with preference 0 and the root as exchange (".") means
+    // the domain accepts no mail, which makes SPF and DMARC checks moot for inbound.
+    let null_mx = mail.mx.len() == 1 && mail.mx[0].1.trim_end_matches('.').is_empty();
+    if null_mx {
+        findings.push(Finding::new(
+            Severity::Info,
+            "Null MX: the domain declares that it accepts no mail",
+        ));
+    } else if mail.mx.is_empty() {
+        findings.push(Finding::new(
+            Severity::Info,
+            "No MX records: mail is delivered to the domain's address records, if any",
+        ));
+    }
+
+    match mail.spf.as_slice() {
+        [] => findings.push(Finding::new(
+            Severity::Warning,
+            "No SPF record: anyone can send mail claiming to be from this domain",
+        )),
+        [spf] => {
+            let last = spf.split_whitespace().last().unwrap_or_default();
+            if last == "+all" || last == "all" {
+                findings.push(Finding::new(
+                    Severity::Error,
+                    format!(
+                        "SPF record ends in '{}', which authorizes every sender",
+                        last
+                    ),
+                ));
+            } else if last == "?all" {
+                findings.push(Finding::new(
+                    Severity::Warning,
+                    "SPF record ends in '?all', which makes no assertion about other senders",
+                ));
+            }
+        }
+        _ => findings.push(Finding::new(
+            Severity::Error,
+            format!(
+                "{} SPF records published; receivers treat more than one as a permanent error",
+                mail.spf.len()
+            ),
+        )),
+    }
+
+    match mail.dmarc.as_slice() {
+        [] => findings.push(Finding::new(
+            Severity::Warning,
+            "No DMARC record at _dmarc: receivers get no policy for failed authentication",
+        )),
+        [dmarc] => {
+            let policy = dmarc
+                .split(';')
+                .map(str::trim)
+                .find_map(|tag| tag.strip_prefix("p="))
+                .map(str::trim);
+            match policy {
+                Some("none") => findings.push(Finding::new(
+                    Severity::Info,
+                    "DMARC policy is p=none: failures are reported but not acted on",
+                )),
+                Some("quarantine") | Some("reject") => {}
+                _ => findings.push(Finding::new(
+                    Severity::Error,
+                    "DMARC record has no valid p= policy tag",
+                )),
+            }
+        }
+        _ => findings.push(Finding::new(
+            Severity::Error,
+            format!(
+                "{} DMARC records published; receivers ignore DMARC when there is more than one",
+                mail.dmarc.len()
+            ),
+        )),
+    }
+
+    findings
+}
+
+/// Returns `true` if a TXT string starts with the given version tag, ignoring case.
+pub(crate) fn is_policy(txt: &str, version: &str) -> bool {
+    let txt = txt.trim_start();
+    txt.len() >= version.len()
+        && txt[..version.len()].eq_ignore_ascii_case(version)
+        && txt[version.len()..]
+            .chars()
+            .next()
+            .is_none_or(|c| c == ' ' || c == ';')
+}
+
+/// Collects the text of every TXT record in `records`.
+fn txt_strings(records: &[ResourceRecord]) -> Vec<String> {
+    records
+        .iter()
+        .filter_map(|record| record.get_txt_data())
+        .map(str::to_string)
+        .collect()
+}
+
+impl DomainReport {
+    /// Returns the extra JSON fields [`DomainReport::ttl_format`] asks for, each
+    /// with a leading comma. The numeric `ttl` field is always present.
+    fn ttl_json_fields(&self, ttl: u32) -> String {
+        let mut fields = String::new();
+        if self.ttl_format.human {
+            fields.push_str(&format!(
+                ",\"ttl_human\":{}",
+                json_string(&humanize_duration(ttl))
+            ));
+        }
+        if self.ttl_format.expiry {
+            fields.push_str(&format!(
+                ",\"expires\":{}",
+                json_string(&expiry_timestamp(ttl, self.queried_at))
+            ));
+        }
+        fields
+    }
+
+    /// Renders the report as a single JSON object.
+    pub fn to_json(&self) -> String {
+        let records: Vec<String> = self
+            .records
+            .iter()
+            .map(|set| {
+                let body = match &set.records {
+                    Ok(records) => format!(
+                        "\"records\":[{}]",
+                        records
+                            .iter()
+                            .map(|r| format!(
+                                "{{\"name\":{},\"ttl\":{}{},\"class\":{},\"data\":{}}}",
+                                json_string(&r.name),
+                                r.ttl,
+                                self.ttl_json_fields(r.ttl),
+                                json_string(&DnsClass::from(r.rclass).to_string()),
+                                json_string(&r.data.to_string())
+                            ))
+                            .collect::<Vec<_>>()
+                            .join(",")
+                    ),
+                    Err(e) => format!("\"error\":{}", json_string(e)),
+                };
+                format!(
+                    "{{\"type\":{},{}}}",
+                    json_string(&set.query_type.to_string()),
+                    body
+                )
+            })
+            .collect();
+        let mx: Vec<String> = self
+            .mail
+            .mx
+            .iter()
+            .map(|(preference, exchange)| {
+                format!(
+                    "{{\"preference\":{},\"exchange\":{}}}",
+                    preference,
+                    json_string(exchange)
+                )
+            })
+            .collect();
+        let strings = |values: &[String]| -> String {
+            values
+                .iter()
+                .map(|v| json_string(v))
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        let findings: Vec<String> = self
+            .findings
+            .iter()
+            .map(|f| {
+                format!(
+                    "{{\"severity\":{},\"message\":{}}}",
+                    json_string(&f.severity.to_string()),
+                    json_string(&f.message)
+                )
+            })
+            .collect();
+        let suspicious: Vec<String> = self
+            .suspicious_labels
+            .iter()
+            .map(|label| {
+                format!(
+                    "{{\"label\":{},\"reason\":{},\"lookalike\":{}}}",
+                    json_string(&label.label),
+                    json_string(&label.reason),
+                    label
+                        .lookalike
+                        .as_deref()
+                        .map_or_else(|| "null".to_string(), json_string)
+                )
+            })
+            .collect();
+        let authenticated = match self.authenticated {
+            Some(value) => value.to_string(),
+            None => "null".to_string(),
+        };
+
+        format!(
+            "{{\"domain\":{},\"records\":[{}],\"dnssec\":{{\"authenticated\":{}}},\
+             \"mail\":{{\"mx\":[{}],\"spf\":[{}],\"dmarc\":[{}]}},\"findings\":[{}],\"suspicious_labels\":[{}],\"not_checked\":[{}]}}",
+            json_string(&self.domain),
+            records.join(","),
+            authenticated,
+            mx.join(","),
+            strings(&self.mail.spf),
+            strings(&self.mail.dmarc),
+            findings.join(","),
+            suspicious.join(","),
+            strings(&self.not_checked)
+        )
+    }
+}
+
+impl fmt::Display for DomainReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Report for {}", self.domain)?;
+        writeln!(f, "====================================")?;
+        writeln!(f)?;
+
+        writeln!(f, "Records:")?;
+        for set in &self.records {
+            match &set.records {
+                Ok(records) if records.is_empty() => writeln!(f, "  {}: none", set.query_type)?,
+                Ok(records) => {
+                    writeln!(f, "  {}:", set.query_type)?;
+                    for record in records {
+                        writeln!(
+                            f,
+                            "    - {}",
+                            self.ttl_format.record(record, self.queried_at)
+                        )?;
+                    }
+                }
+                Err(e) => writeln!(f, "  {}: error: {}", set.query_type, e)?,
+            }
+        }
+        writeln!(f)?;
+
+        let dnssec = match self.authenticated {
+            Some(true) => "authenticated (AD=1)",
+            Some(false) => "not authenticated (AD=0)",
+            None => "unknown",
+        };
+        writeln!(f, "DNSSEC: {}", dnssec)?;
+        writeln!(f)?;
+
+        writeln!(f, "Mail:")?;
+        if self.mail.mx.is_empty() {
+            writeln!(f, "  MX: none")?;
+        }
+        for (preference, exchange) in &self.mail.mx {
+            writeln!(f, "  MX: {} {}", preference, exchange)?;
+        }
+        for spf in &self.mail.spf {
+            writeln!(f, "  SPF: {}", spf)?;
+        }
+        for dmarc in &self.mail.dmarc {
+            writeln!(f, "  DMARC: {}", dmarc)?;
+        }
+        writeln!(f)?;
+
+        writeln!(f, "Findings:")?;
+        if self.findings.is_empty() {
+            writeln!(f, "  none")?;
+        }
+        for finding in &self.findings {
+            writeln!(f, "  [{}] {}", finding.severity, finding.message)?;
+        }
+
+        if !self.not_checked.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "Not checked:")?;
+            for item in &self.not_checked {
+                writeln!(f, "  - {}", item)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::a_record;
+
+    fn posture(mx: &[(u16, &str)], spf: &[&str], dmarc: &[&str]) -> MailPosture {
+        MailPosture {
+            mx: mx.iter().map(|(p, e)| (*p, e.to_string())).collect(),
+            spf: spf.iter().map(|s| s.to_string()).collect(),
+            dmarc: dmarc.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_healthy_mail_has_no_findings() {
+        let mail = posture(
+            &[(10, "mx1.example.com")],
+            &["v=spf1 mx -all"],
+            &["v=DMARC1; p=reject; rua=mailto:d@example.com"],
+        );
+        assert!(assess_mail(&mail).is_empty());
+    }
+
+    #[test]
+    fn test_mail_problems_are_flagged() {
+        let mail = posture(&[], &["v=spf1 +all"], &["v=DMARC1; p=none"]);
+        let findings = assess_mail(&mail);
+        assert!(
+            findings
+                .iter()
+                .any(|f| f.severity == Severity::Error && f.message.contains("+all"))
+        );
+        assert!(findings.iter().any(|f| f.message.contains("p=none")));
+        assert!(findings.iter().any(|f| f.message.contains("No MX")));
+
+        let duplicated = posture(&[(0, ".")], &["v=spf1 -all", "v=spf1 mx -all"], &[]);
+        let findings = assess_mail(&duplicated);
+        assert!(findings.iter().any(|f| f.message.contains("Null MX")));
+        assert!(
+            findings
+                .iter()
+                .any(|f| f.message.starts_with("2 SPF records"))
+        );
+        assert!(findings.iter().any(|f| f.message.contains("No DMARC")));
+    }
+
+    #[test]
+    fn test_policy_version_matching() {
+        assert!(is_policy("v=spf1 -all", "v=spf1"));
+        assert!(is_policy("V=DMARC1;p=reject", "v=DMARC1"));
+        assert!(!is_policy("v=spf10 -all", "v=spf1"));
+        assert!(!is_policy("google-site-verification=abc", "v=spf1"));
+    }
+
+    #[test]
+    fn test_report_json_shape() {
+        let report = DomainReport {
+            domain: "example.com".to_string(),
+            records: vec![RecordSet {
+                query_type: QueryType::MX,
+                records: Err("timed out".to_string()),
+            }],
+            authenticated: None,
+            mail: posture(&[], &[], &[]),
+            findings: vec![Finding::new(Severity::Warning, "x")],
+            suspicious_labels: Vec::new(),
+            not_checked: Vec::new(),
+            queried_at: SystemTime::UNIX_EPOCH,
+            ttl_format: TtlFormat::default(),
+        };
+        assert_eq!(
+            report.to_json(),
+            "{\"domain\":\"example.com\",\"records\":[{\"type\":\"MX\",\"error\":\"timed out\"}],\
+             \"dnssec\":{\"authenticated\":null},\"mail\":{\"mx\":[],\"spf\":[],\"dmarc\":[]},\
+             \"findings\":[{\"severity\":\"warning\",\"message\":\"x\"}],\"suspicious_labels\":[],\"not_checked\":[]}"
+        );
+    }
+
+    #[test]
+    fn test_report_json_ttl_fields() {
+        let report = DomainReport {
+            domain: "example.com".to_string(),
+            records: vec![RecordSet {
+                query_type: QueryType::A,
+                records: Ok(vec![a_record("example.com", [192, 0, 2, 1], 3600)]),
+            }],
+            authenticated: None,
+            mail: posture(&[], &[], &[]),
+            findings: Vec::new(),
+            suspicious_labels: Vec::new(),
+            not_checked: Vec::new(),
+            queried_at: SystemTime::UNIX_EPOCH,
+            ttl_format: TtlFormat {
+                human: true,
+                expiry: true,
+            },
+        };
+        assert!(
+            report
+                .to_json()
+                .contains("\"ttl\":3600,\"ttl_human\":\"1h\",\"expires\":\"1970-01-01T01:00:00Z\"")
+        );
+        assert!(
+            report
+                .to_string()
+                .contains(" 1h (expires 1970-01-01T01:00:00Z) ")
+        );
+    }
+}
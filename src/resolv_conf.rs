@@ -0,0 +1,275 @@
+//! Reading the system's resolver configuration from `/etc/resolv.conf`.
+//!
+//! Hardcoding a public resolver is wrong on networks that answer internal names
+//! differently, such as corporate networks with split-horizon DNS or hosts behind a
+//! local caching daemon. [`ResolvConf`] reads the servers and the `options`
+//! settings the C library uses (see `resolv.conf(5)`), so queries go where every
+//! other program on the host sends them.
+//!
+//! Parsing is as lenient as the C library's: unknown keywords, unknown options and
+//! malformed values are ignored, and only the first three `nameserver` lines count.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolv_conf::ResolvConf;
+//!
+//! let resolver = ResolvConf::load()?.resolver_builder()?.build()?;
+//! let response = resolver.resolve("intranet.corp.example", QueryType::A)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::resolver::{QueryOptions, Resolver, ResolverBuilder};
+use crate::retry::RetryPolicy;
+
+/// Where the system resolver configuration lives.
+pub const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// The number of `nameserver` lines the C library uses (`MAXNS`); later ones are
+/// ignored.
+pub const MAX_NAMESERVERS: usize = 3;
+
+/// The server used when the configuration names none, as the C library does.
+pub const DEFAULT_NAMESERVER: Ipv4Addr = Ipv4Addr::LOCALHOST;
+
+/// The system resolver configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvConf {
+    /// The servers to query, in order, at most [`MAX_NAMESERVERS`].
+    pub nameservers: Vec<IpAddr>,
+    /// The search list from the last `search` or `domain` line.
+    pub search: Vec<String>,
+    /// Names with at least this many dots are tried as given before the search
+    /// list (`options ndots:n`, default 1, at most 15).
+    pub ndots: u32,
+    /// How long to wait for each server before trying the next
+    /// (`options timeout:n`, default 5 seconds, at most 30).
+    pub timeout: Duration,
+    /// How many times to go through the list of servers
+    /// (`options attempts:n`, default 2, at most 5).
+    pub attempts: u32,
+    /// Spread queries over the servers instead of always starting with the first
+    /// (`options rotate`).
+    pub rotate: bool,
+}
+
+impl Default for ResolvConf {
+    fn default() -> Self {
+        ResolvConf {
+            nameservers: Vec::new(),
+            search: Vec::new(),
+            ndots: 1,
+            timeout: Duration::from_secs(5),
+            attempts: 2,
+            rotate: false,
+        }
+    }
+}
+
+impl ResolvConf {
+    /// Reads [`RESOLV_CONF_PATH`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn load() -> Result<Self, String> {
+        Self::load_from(RESOLV_CONF_PATH)
+    }
+
+    /// Reads a configuration in `resolv.conf` format from `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    pub fn load_from(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        fs::read_to_string(path)
+            .map(|text| Self::parse(&text))
+            .map_err(|e| format!("cannot read {}: {}", path.display(), e))
+    }
+
+    /// Parses the text of a `resolv.conf` file.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::resolv_conf::ResolvConf;
+    /// use std::time::Duration;
+    ///
+    /// let conf = ResolvConf::parse(
+    ///     "nameserver 10.0.0.2\nsearch corp.example\noptions timeout:2 attempts:3 rotate\n",
+    /// );
+    /// assert_eq!(conf.nameservers, ["10.0.0.2".parse::<std::net::IpAddr>().unwrap()]);
+    /// assert_eq!(conf.search, ["corp.example"]);
+    /// assert_eq!((conf.timeout, conf.attempts, conf.rotate), (Duration::from_secs(2), 3, true));
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut conf = ResolvConf::default();
+        for line in text.lines() {
+            let mut fields = line.split_whitespace();
+            match fields.next() {
+                Some("nameserver") => {
+                    // Scoped IPv6 addresses carry their interface after a `%`.
+                    let server = fields
+                        .next()
+                        .and_then(|value| value.split('%').next())
+                        .and_then(|value| value.parse().ok());
+                    if let Some(server) = server
+                        && conf.nameservers.len() < MAX_NAMESERVERS
+                    {
+                        conf.nameservers.push(server);
+                    }
+                }
+                Some("search") | Some("domain") => {
+                    conf.search = fields.map(str::to_string).collect();
+                }
+                Some("options") => fields.for_each(|option| conf.set_option(option)),
+                _ => {}
+            }
+        }
+        conf
+    }
+
+    /// Applies one word of an `options` line.
+    fn set_option(&mut self, option: &str) {
+        let (name, value) = match option.split_once(':') {
+            Some((name, value)) => (name, value.parse::<u32>().ok()),
+            None => (option, None),
+        };
+        match (name, value) {
+            ("ndots", Some(n)) => self.ndots = n.min(15),
+            ("timeout", Some(n)) => self.timeout = Duration::from_secs(n.clamp(1, 30).into()),
+            ("attempts", Some(n)) => self.attempts = n.clamp(1, 5),
+            ("rotate", _) => self.rotate = true,
+            _ => {}
+        }
+    }
+
+    /// Returns the servers queries can be sent to: the IPv4 `nameserver` entries,
+    /// or [`DEFAULT_NAMESERVER`] if there are none at all.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if every server is an IPv6 address, since queries are only
+    /// sent over IPv4.
+    pub fn ipv4_nameservers(&self) -> Result<Vec<Ipv4Addr>, String> {
+        if self.nameservers.is_empty() {
+            return Ok(vec![DEFAULT_NAMESERVER]);
+        }
+        let servers: Vec<Ipv4Addr> = self
+            .nameservers
+            .iter()
+            .filter_map(|server| match server {
+                IpAddr::V4(server) => Some(*server),
+                IpAddr::V6(_) => None,
+            })
+            .collect();
+        if servers.is_empty() {
+            return Err("resolv.conf only lists IPv6 nameservers".to_string());
+        }
+        Ok(servers)
+    }
+
+    /// Returns the retry policy the C library follows: each server in turn, waiting
+    /// [`ResolvConf::timeout`] for each, for [`ResolvConf::attempts`] rounds.
+    pub fn retry_policy(&self, server_count: usize) -> RetryPolicy {
+        RetryPolicy {
+            initial_timeout: self.timeout,
+            multiplier: 1.0,
+            max_attempts: self.attempts * server_count.max(1) as u32,
+            switch_server_after: (server_count > 1).then_some(1),
+            ..RetryPolicy::default()
+        }
+    }
+
+    /// Points `options` at the configured servers and sets their retry policy and
+    /// rotation, returning the primary server to pass to
+    /// [`query`](crate::resolver::query) with them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no IPv4 server (see
+    /// [`ResolvConf::ipv4_nameservers`]).
+    pub fn apply(&self, options: &mut QueryOptions) -> Result<Ipv4Addr, String> {
+        let servers = self.ipv4_nameservers()?;
+        options.retry = self.retry_policy(servers.len());
+        options.rotate = self.rotate;
+        options.alternate_servers = servers[1..].to_vec();
+        Ok(servers[0])
+    }
+
+    /// Returns a [`Resolver`] builder with the configured servers, retry policy and
+    /// rotation.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there is no IPv4 server (see
+    /// [`ResolvConf::ipv4_nameservers`]).
+    pub fn resolver_builder(&self) -> Result<ResolverBuilder, String> {
+        let servers = self.ipv4_nameservers()?;
+        Ok(Resolver::builder()
+            .servers(servers.iter().copied())
+            .retry_policy(self.retry_policy(servers.len()))
+            .rotate(self.rotate))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_resolv_conf() {
+        let conf = ResolvConf::parse(
+            "# Generated by NetworkManager\n\
+             domain old.example\n\
+             search corp.example lab.corp.example\n\
+             nameserver 10.0.0.2\n\
+             nameserver fe80::1%eth0\n\
+             nameserver not-an-address\n\
+             nameserver 10.0.0.3\n\
+             nameserver 10.0.0.4\n\
+             options ndots:2 timeout:60 attempts:0 edns0 rotate\n",
+        );
+        assert_eq!(
+            conf.nameservers,
+            ["10.0.0.2", "fe80::1", "10.0.0.3"].map(|server| server.parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(conf.search, ["corp.example", "lab.corp.example"]);
+        assert_eq!(conf.ndots, 2);
+        // Out-of-range values are clamped as the C library does.
+        assert_eq!((conf.timeout, conf.attempts), (Duration::from_secs(30), 1));
+        assert!(conf.rotate);
+
+        assert_eq!(
+            conf.ipv4_nameservers().unwrap(),
+            [Ipv4Addr::new(10, 0, 0, 2), Ipv4Addr::new(10, 0, 0, 3)]
+        );
+        let policy = conf.retry_policy(2);
+        assert_eq!(
+            (policy.max_attempts, policy.switch_server_after),
+            (2, Some(1))
+        );
+    }
+
+    #[test]
+    fn test_servers_without_a_usable_nameserver() {
+        let conf = ResolvConf::parse("");
+        assert_eq!(conf, ResolvConf::default());
+        assert_eq!(conf.ipv4_nameservers().unwrap(), [DEFAULT_NAMESERVER]);
+
+        let mut options = QueryOptions::default();
+        assert_eq!(conf.apply(&mut options).unwrap(), DEFAULT_NAMESERVER);
+        assert!(options.alternate_servers.is_empty());
+        assert_eq!(options.retry.max_attempts, 2);
+
+        let conf = ResolvConf::parse("nameserver 2001:db8::53\n");
+        assert!(conf.ipv4_nameservers().is_err());
+    }
+}
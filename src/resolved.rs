@@ -0,0 +1,257 @@
+//! Comparing answers from systemd-resolved with its upstream servers.
+//!
+//! On most current Linux distributions `/etc/resolv.conf` points at the
+//! systemd-resolved stub listener on `127.0.0.53`, which forwards each query to the
+//! DNS servers of whichever network link it routes the name to. When a lookup
+//! misbehaves it is useful to see both what resolved answers and what the upstream
+//! server it would have used says directly, since resolved adds its own caching,
+//! DNSSEC handling and split-DNS routing on top.
+//!
+//! The per-link configuration is read with `resolvectl`, which asks resolved over
+//! D-Bus for the `DNS` and `Domains` properties of every link. When `resolvectl` is
+//! unavailable, the upstream list resolved writes to
+//! `/run/systemd/resolve/resolv.conf` is used instead, without per-link detail.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::resolved::ResolvedConfig;
+//!
+//! let config = ResolvedConfig::load()?;
+//! if let Some(link) = config.route("intranet.corp.example") {
+//!     println!("resolved would ask {:?} via {}", link.servers, link.name);
+//! }
+//! # Ok::<(), String>(())
+//! ```
+
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::process::Command;
+
+use crate::validation::names_equal;
+
+/// The address of the systemd-resolved stub listener.
+pub const STUB_ADDRESS: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 53);
+
+/// The upstream list systemd-resolved maintains for programs that bypass the stub.
+const UPSTREAM_RESOLV_CONF: &str = "/run/systemd/resolve/resolv.conf";
+
+/// The DNS settings of one link, or of the global scope.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkConfig {
+    /// The interface name (e.g. `eth0`), or `Global` for the global settings.
+    pub name: String,
+    /// The DNS servers configured on the link, in order.
+    pub servers: Vec<IpAddr>,
+    /// The search and routing domains of the link. Routing-only domains keep their
+    /// `~` prefix; `~.` makes the link a default route for all names.
+    pub domains: Vec<String>,
+}
+
+/// The DNS configuration of systemd-resolved across all links.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ResolvedConfig {
+    /// The global scope first, if present, followed by each link.
+    pub links: Vec<LinkConfig>,
+}
+
+impl ResolvedConfig {
+    /// Reads the current configuration from the running systemd-resolved.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if neither `resolvectl` nor resolved's upstream
+    /// `resolv.conf` is available, which usually means resolved is not running.
+    pub fn load() -> Result<Self, String> {
+        match (resolvectl(&["dns"]), resolvectl(&["domain"])) {
+            (Some(dns), Some(domains)) => Ok(parse_resolvectl(&dns, &domains)),
+            _ => fs::read_to_string(UPSTREAM_RESOLV_CONF)
+                .map(|text| parse_upstream_resolv_conf(&text))
+                .map_err(|e| {
+                    format!(
+                        "systemd-resolved does not appear to be running (resolvectl failed and {} is unreadable: {})",
+                        UPSTREAM_RESOLV_CONF, e
+                    )
+                }),
+        }
+    }
+
+    /// Returns the link systemd-resolved routes `name` to.
+    ///
+    /// This follows resolved's split-DNS rules in simplified form: the link with
+    /// the longest search or routing domain matching the name wins, `~.` matches
+    /// every name, and without any match the first scope with servers is used.
+    /// Links without servers are never chosen.
+    pub fn route(&self, name: &str) -> Option<&LinkConfig> {
+        let candidates = self.links.iter().filter(|link| !link.servers.is_empty());
+        candidates
+            .clone()
+            .filter_map(|link| {
+                link.domains
+                    .iter()
+                    .filter_map(|domain| domain_match_length(name, domain))
+                    .max()
+                    .map(|length| (length, link))
+            })
+            // Prefer the earliest link among equally specific matches.
+            .fold(
+                None,
+                |best: Option<(usize, &LinkConfig)>, (length, link)| match best {
+                    Some((best_length, _)) if best_length >= length => best,
+                    _ => Some((length, link)),
+                },
+            )
+            .map(|(_, link)| link)
+            .or_else(|| candidates.clone().next())
+    }
+}
+
+/// Returns how many labels of `domain` match the end of `name`, if it matches.
+///
+/// `~.` and `.` match everything with length zero.
+fn domain_match_length(name: &str, domain: &str) -> Option<usize> {
+    let domain = domain.trim_start_matches('~').trim_end_matches('.');
+    if domain.is_empty() {
+        return Some(0);
+    }
+    let name = name.trim_end_matches('.');
+    let suffix_start = name.len().checked_sub(domain.len())?;
+    let at_label_boundary = suffix_start == 0 || name[..suffix_start].ends_with('.');
+    (at_label_boundary && names_equal(&name[suffix_start..], domain))
+        .then(|| domain.split('.').count())
+}
+
+/// Runs `resolvectl` with `args`, returning its output if it succeeded.
+fn resolvectl(args: &[&str]) -> Option<String> {
+    let output = Command::new("resolvectl").args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses the output of `resolvectl dns` and `resolvectl domain`.
+///
+/// Both list one scope per line, as `Global: <values>` or
+/// `Link 2 (eth0): <values>`. Server entries may carry a port, an interface
+/// suffix (`%2`) or a TLS server name (`#dns.example`), which are dropped.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::resolved::parse_resolvectl;
+///
+/// let config = parse_resolvectl("Global:\nLink 2 (eth0): 192.168.1.1\n", "Link 2 (eth0): ~.\n");
+/// assert_eq!(config.links[1].name, "eth0");
+/// assert_eq!(config.links[1].domains, vec!["~.".to_string()]);
+/// ```
+pub fn parse_resolvectl(dns_output: &str, domain_output: &str) -> ResolvedConfig {
+    let mut config = ResolvedConfig::default();
+    for (name, values) in dns_output.lines().filter_map(parse_scope_line) {
+        config.links.push(LinkConfig {
+            name,
+            servers: values.split_whitespace().filter_map(parse_server).collect(),
+            domains: Vec::new(),
+        });
+    }
+    for (name, values) in domain_output.lines().filter_map(parse_scope_line) {
+        let domains = values.split_whitespace().map(str::to_string);
+        match config.links.iter_mut().find(|link| link.name == name) {
+            Some(link) => link.domains.extend(domains),
+            None => config.links.push(LinkConfig {
+                name,
+                servers: Vec::new(),
+                domains: domains.collect(),
+            }),
+        }
+    }
+    config
+}
+
+/// Splits a `resolvectl` line into the scope name and the rest of the line.
+fn parse_scope_line(line: &str) -> Option<(String, &str)> {
+    let (label, values) = line.split_once(':')?;
+    let label = label.trim();
+    let name = if label == "Global" {
+        label.to_string()
+    } else {
+        let (_, interface) = label.strip_prefix("Link ")?.split_once('(')?;
+        interface.strip_suffix(')')?.to_string()
+    };
+    Some((name, values.trim()))
+}
+
+/// Parses one server entry as printed by `resolvectl`.
+fn parse_server(entry: &str) -> Option<IpAddr> {
+    let entry = entry.split('#').next()?;
+    entry
+        .parse::<IpAddr>()
+        .or_else(|_| entry.parse::<SocketAddr>().map(|addr| addr.ip()))
+        .ok()
+        .or_else(|| entry.split('%').next()?.parse().ok())
+}
+
+/// Parses resolved's upstream `resolv.conf` into a single global scope.
+fn parse_upstream_resolv_conf(text: &str) -> ResolvedConfig {
+    let mut global = LinkConfig {
+        name: "Global".to_string(),
+        servers: Vec::new(),
+        domains: Vec::new(),
+    };
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("nameserver") => global.servers.extend(fields.next().and_then(parse_server)),
+            Some("search") | Some("domain") => global.domains.extend(fields.map(str::to_string)),
+            _ => {}
+        }
+    }
+    ResolvedConfig {
+        links: vec![global],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DNS_OUTPUT: &str = "Global: 1.1.1.1#cloudflare-dns.com\n\
+                              Link 2 (eth0): 192.168.1.1 fe80::1%2\n\
+                              Link 3 (wg0): 10.8.0.1:5353\n\
+                              Link 4 (docker0):\n";
+    const DOMAIN_OUTPUT: &str = "Global:\n\
+                                 Link 2 (eth0): lan ~.\n\
+                                 Link 3 (wg0): ~corp.example\n";
+
+    #[test]
+    fn test_parse_resolvectl() {
+        let config = parse_resolvectl(DNS_OUTPUT, DOMAIN_OUTPUT);
+        let names: Vec<&str> = config.links.iter().map(|l| l.name.as_str()).collect();
+        assert_eq!(names, vec!["Global", "eth0", "wg0", "docker0"]);
+        assert_eq!(
+            config.links[0].servers,
+            vec!["1.1.1.1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(config.links[1].servers.len(), 2);
+        assert_eq!(
+            config.links[2].servers,
+            vec!["10.8.0.1".parse::<IpAddr>().unwrap()]
+        );
+        assert_eq!(config.links[1].domains, vec!["lan", "~."]);
+        assert!(config.links[3].servers.is_empty());
+    }
+
+    #[test]
+    fn test_routing_prefers_most_specific_domain() {
+        let config = parse_resolvectl(DNS_OUTPUT, DOMAIN_OUTPUT);
+        assert_eq!(config.route("git.CORP.example.").unwrap().name, "wg0");
+        assert_eq!(config.route("printer.lan").unwrap().name, "eth0");
+        assert_eq!(config.route("example.com").unwrap().name, "eth0");
+        // "corp.example" must match on a label boundary.
+        assert_eq!(config.route("notcorp.example").unwrap().name, "eth0");
+
+        let fallback = parse_upstream_resolv_conf("# generated\nnameserver 9.9.9.9\nsearch home\n");
+        assert_eq!(fallback.route("example.com").unwrap().name, "Global");
+        assert_eq!(fallback.links[0].domains, vec!["home"]);
+    }
+}
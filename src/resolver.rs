@@ -5,15 +5,34 @@
 //! building queries, sending them over UDP, receiving responses, and parsing the
 //! returned DNS messages.
 //!
-//! The resolver supports standard DNS query types (A, AAAA, CNAME, MX, TXT) and
-//! provides comprehensive error handling for network issues, timeouts, and DNS
-//! protocol errors.
+//! The resolver supports standard DNS query types (A, AAAA, CNAME, MX, TXT, PTR, NS, SOA,
+//! HINFO, SRV, NAPTR, SSHFP, SVCB, HTTPS, DS, DNSKEY, RRSIG, NSEC, NSEC3, ANY) and provides
+//! comprehensive error handling for network issues, timeouts, and DNS protocol errors.
 //!
 //! # Examples
 //!
-//! Basic DNS resolution:
+//! A [`Resolver`] holds the servers and settings, so repeated queries do not have to
+//! repeat them, and shares its cache and server health between clones:
 //!
-//! ```rust
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::Resolver;
+//! use std::net::Ipv4Addr;
+//! use std::time::Duration;
+//!
+//! let resolver = Resolver::builder()
+//!     .servers([Ipv4Addr::new(8, 8, 8, 8), Ipv4Addr::new(1, 1, 1, 1)])
+//!     .timeout(Duration::from_secs(2))
+//!     .edns(1232)
+//!     .build()?;
+//! let response = resolver.resolve("google.com", QueryType::A)?;
+//! println!("Resolved successfully: {} answers", response.answers.len());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! One-off queries can use the free functions instead, such as [`resolve`]:
+//!
+//! ```rust,no_run
 //! use dns_resolver::resolver::resolve;
 //! use dns_resolver::dns::QueryType;
 //! use std::net::Ipv4Addr;
@@ -33,18 +52,382 @@
 //!
 //! # Network Configuration
 //!
-//! The resolver uses UDP on port 53 (the standard DNS port) and sets a 5-second
-//! timeout for queries. It binds to a random local port chosen by the operating system.
+//! The resolver uses UDP on port 53 (the standard DNS port) and by default sends a
+//! single attempt with a 5-second timeout; [`QueryOptions::retry`] configures
+//! retransmissions, backoff and failover to alternate servers. It binds to a random
+//! local port chosen by the operating system and connects the socket to the server, so
+//! an ICMP port unreachable from a host with no DNS service fails the attempt
+//! immediately instead of waiting for the timeout.
+//!
+//! UDP responses are limited to 512 bytes, or to the payload size advertised with
+//! [`QueryOptions::edns`]. When an answer does not fit, the server
+//! sets the TC bit and the resolver repeats the query over TCP on the same port, so
+//! large answer sets come back whole. [`QueryOptions::tcp`] sends every query over
+//! TCP from the start, for networks that block UDP. On Linux TCP connections are
+//! bound to the configured source address and interface like UDP sockets are.
+//! Elsewhere they cannot be, so with either configured a truncated UDP answer is
+//! returned as it is, with its TC bit set.
+//!
+//! # DNSSEC
+//!
+//! With [`QueryOptions::validate`] set, the response is checked against the root
+//! trust anchor, fetching the keys and delegation records of every zone on the way
+//! from the same server, and [`QueryResult::security`] reports whether it is secure,
+//! insecure or bogus. See [`crate::dnssec`].
+//!
+//! # TSIG
+//!
+//! With [`QueryOptions::tsig`] set, queries are signed with a key shared with the
+//! server, and responses are only accepted with a valid signature from the same key.
+//! See [`crate::tsig`].
 //!
 //! # Error Handling
 //!
 //! The resolver provides detailed error information through the [`DnsError`] enum,
 //! which covers I/O errors, timeouts, malformed responses, and DNS server errors.
+//!
+//! # Concurrency
+//!
+//! Every function here may be called from any number of threads at once. Each
+//! attempt of each query uses a socket of its own, and each query carries its own
+//! random transaction ID, so concurrent queries never share state on the wire. A
+//! [`Resolver`] bundles a server, its options and a host cache into one handle that
+//! can be cloned cheaply and shared between threads.
+
+use std::collections::HashSet;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{Cursor, Read, Write};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::addrs::{AddressOrder, resolve_socket_addrs};
+use crate::breaker::CircuitBreaker;
+use crate::cache::{DnsCache, ResponseCache};
+use crate::dns::{
+    DnsClass, DnsHeader, DnsMessage, DnsName, DnsQuestion, Edns, EdnsOption, QueryType,
+    ResourceRecord, ResponseCode, reverse_lookup_name,
+};
+use crate::dnssec::{DNSSEC_UDP_PAYLOAD_SIZE, SecurityStatus, validate};
+use crate::host_cache::{CacheStats, HostCache, TtlPolicy};
+use crate::hosts::HostsFile;
+use crate::idn::to_ascii;
+use crate::retry::RetryPolicy;
+use crate::stats::MessageStats;
+use crate::transcript::Transcript;
+use crate::transport::{MockTransport, Transport};
+use crate::tsig::{TsigKey, TsigSession};
+use crate::validation::validate_response;
+use crate::watch::{Watch, WatchOptions};
+
+/// Options controlling how a query is sent.
+///
+/// The default value reproduces the behavior of [`resolve`]: the operating system
+/// picks the outgoing interface and source address from its routing table.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::resolver::QueryOptions;
+/// use std::net::Ipv4Addr;
+///
+/// // Send queries from a specific address on a multi-homed host.
+/// let options = QueryOptions {
+///     source_address: Some(Ipv4Addr::new(192, 0, 2, 10)),
+///     ..QueryOptions::default()
+/// };
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions {
+    /// Local IPv4 address to bind the outgoing socket to.
+    ///
+    /// When `None`, the socket is bound to `0.0.0.0` and the operating system
+    /// chooses the source address.
+    pub source_address: Option<Ipv4Addr>,
+
+    /// Name of the network interface to send queries through (e.g. `eth1`).
+    ///
+    /// This binds the socket to the device with `SO_BINDTODEVICE`, which is what
+    /// VRF setups and hosts with overlapping routes need. It is only supported on
+    /// Linux and usually requires `CAP_NET_RAW`.
+    pub interface: Option<String>,
+
+    /// How long to keep listening after a response has been accepted.
+    ///
+    /// A legitimate server sends exactly one response per query, so any further
+    /// response carrying the same transaction ID is reported in
+    /// [`QueryResult::duplicates`]. A duplicate whose contents differ from the
+    /// accepted response is classic evidence of an off-path spoofing race.
+    /// When `None`, the query returns as soon as the first response arrives.
+    pub duplicate_window: Option<Duration>,
+
+    /// Reject responses with any protocol irregularity.
+    ///
+    /// When set, every response is checked with
+    /// [`validate_response`](crate::validation::validate_response) and any
+    /// violation fails the query with [`DnsError::ProtocolViolations`]. This is
+    /// meant for testing DNS middleboxes and suspicious networks, where the
+    /// lenient default parsing would hide tampering.
+    pub strict: bool,
+
+    /// Set the Checking Disabled (CD) bit on the query.
+    ///
+    /// A validating upstream then returns records even when DNSSEC validation
+    /// fails, which helps tell a validation failure apart from a genuine outage.
+    pub checking_disabled: bool,
+
+    /// Set the Authentic Data (AD) bit on the query.
+    ///
+    /// Per RFC 6840 this tells the upstream that the client understands the AD
+    /// bit, so a validating resolver reports validation status in its response
+    /// even without EDNS.
+    pub authentic_data: bool,
+
+    /// Set the DNSSEC OK (DO) bit in an EDNS(0) OPT record (RFC 3225).
+    ///
+    /// The server then includes the RRSIG, NSEC and NSEC3 records that DNSSEC
+    /// validation needs. The DO bit can only travel in an OPT record, so queries
+    /// without [`QueryOptions::edns`] advertise a payload size of 1232 bytes.
+    pub dnssec_ok: bool,
+
+    /// Validate the response with DNSSEC and report the outcome in
+    /// [`QueryResult::security`].
+    ///
+    /// This implies [`QueryOptions::dnssec_ok`] and
+    /// [`QueryOptions::checking_disabled`], so that the signatures arrive even
+    /// when an upstream validator would have rejected them. The chain of trust is
+    /// fetched from the same server with further queries; see [`crate::dnssec`].
+    pub validate: bool,
+
+    /// The DS records of the root keys to validate from, when
+    /// [`QueryOptions::validate`] is set.
+    ///
+    /// When `None`, the root keys IANA publishes are used
+    /// ([`root_trust_anchors`](crate::dnssec::root_trust_anchors)). Anchors read
+    /// with [`load_trust_anchors`](crate::dnssec::load_trust_anchors) replace them,
+    /// ahead of a root key rollover or for a private root.
+    pub trust_anchors: Option<Arc<Vec<ResourceRecord>>>,
+
+    /// When and where to retransmit a query that goes unanswered.
+    ///
+    /// The default sends a single attempt with a five second timeout.
+    pub retry: RetryPolicy,
+
+    /// Servers to fall back to, in order, when [`RetryPolicy::switch_server_after`]
+    /// moves an attempt away from the primary server.
+    pub alternate_servers: Vec<Ipv4Addr>,
+
+    /// Start each query at a randomly chosen server among the primary and alternate
+    /// servers, rather than always at the primary, to spread the load over them
+    /// (`options rotate` in `resolv.conf`).
+    pub rotate: bool,
+
+    /// The class to query in.
+    ///
+    /// Defaults to IN (Internet). Chaos class queries such as `version.bind` or
+    /// `id.server` ask a server to identify itself.
+    pub class: DnsClass,
+
+    /// Send queries over this Unix domain socket instead of UDP.
+    ///
+    /// Messages are framed as on TCP, each preceded by its length as a two-byte
+    /// big-endian integer (RFC 1035 section 4.2.2). This suits local daemons and
+    /// test harnesses that expose DNS on a socket file. The server address passed
+    /// to [`query`] is then only reported back in [`QueryResult::server`], and no
+    /// duplicate responses are collected, since a stream cannot be spoofed off-path.
+    pub unix_socket: Option<PathBuf>,
+
+    /// Send every query over TCP instead of UDP.
+    ///
+    /// This suits networks that block UDP port 53 outright. Without it, TCP is
+    /// only used to repeat a query whose UDP response came back truncated. TCP
+    /// connections are bound to [`QueryOptions::source_address`] and
+    /// [`QueryOptions::interface`] only on Linux, so elsewhere queries fail when
+    /// either is set.
+    pub tcp: bool,
+
+    /// Advertise this UDP payload size, in bytes, in an EDNS(0) OPT record.
+    ///
+    /// Without EDNS a server must fit its UDP response in 512 bytes, and sets the
+    /// TC bit when it cannot. Advertising a larger size, such as the widely used
+    /// 1232 or 4096, lets larger answers arrive in one datagram instead of costing a
+    /// second query over TCP. When `None`, queries carry no OPT record.
+    ///
+    /// Some old or broken servers answer FORMERR or NOTIMP to any query with an
+    /// OPT record. Such a query is sent once more without one, and the server is
+    /// remembered in [`QueryOptions::edns_downgrades`], if set.
+    pub edns: Option<u16>,
+
+    /// Send these options, as they are, in the OPT record of every query.
+    ///
+    /// This is meant for protocol experiments: options the crate knows nothing
+    /// about can be tried against a server, and its answer read back from
+    /// [`DnsMessage::edns`]. Like [`QueryOptions::dnssec_ok`], options can only
+    /// travel in an OPT record, so queries without [`QueryOptions::edns`] advertise
+    /// a payload size of 1232 bytes.
+    pub edns_options: Vec<EdnsOption>,
+
+    /// Servers that rejected a query for its OPT record, shared between queries.
+    ///
+    /// Queries whose first server is in the set are sent without an OPT record
+    /// from the start, instead of costing a rejected query each time. When `None`,
+    /// every query tries EDNS first. [`Resolver`] sets one up for its session.
+    pub edns_downgrades: Option<Arc<EdnsDowngrades>>,
+
+    /// The port servers listen on, for UDP and TCP and for every server queried.
+    ///
+    /// When `None`, the standard port 53 is used. Other ports suit DNS daemons run
+    /// unprivileged and test servers such as `testutil::StubServer`.
+    pub port: Option<u16>,
+
+    /// Failure tracking shared between queries, which steers attempts away from
+    /// servers that keep timing out or answering SERVFAIL.
+    ///
+    /// Share one breaker (through the `Arc`) between all queries to the same set of
+    /// servers. When `None`, every attempt goes where the retry policy says.
+    pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+
+    /// Record every exchange to a transcript, or answer queries from one.
+    ///
+    /// A recording transcript is written to after each attempt that got a
+    /// response or timed out. A replaying transcript answers every attempt, so
+    /// nothing is sent; queries it has no recording for fail with
+    /// [`DnsError::Io`] of kind [`std::io::ErrorKind::NotFound`].
+    pub transcript: Option<Arc<Transcript>>,
 
-use std::net::{Ipv4Addr, UdpSocket};
-use std::time::Duration;
+    /// Sign queries with this key and require responses signed with it (TSIG).
+    ///
+    /// A response that is unsigned, signed with another key or carries a MAC that
+    /// does not match fails with [`DnsError::InvalidResponse`]. A key whose name
+    /// cannot be encoded fails the query with [`DnsError::Io`] of kind
+    /// [`std::io::ErrorKind::InvalidInput`] before anything is sent. Signed queries
+    /// differ on every run, so they cannot be answered from a replaying
+    /// [`QueryOptions::transcript`]. See [`crate::tsig`].
+    pub tsig: Option<TsigKey>,
+
+    /// Answer queries in process with canned responses instead of sending them.
+    ///
+    /// Meant for testing code that depends on the resolver; every attempt is
+    /// answered by the mock, and no socket is opened. See [`MockTransport`].
+    pub mock_transport: Option<Arc<MockTransport>>,
+
+    /// Answer A, AAAA and PTR queries for the names and addresses a hosts file
+    /// lists from the file, without sending them.
+    ///
+    /// Queries the file has no answer for, and queries that are TSIG-signed or
+    /// validated, go to the server as usual. See [`crate::hosts`].
+    pub hosts: Option<Arc<HostsFile>>,
+}
+
+impl QueryOptions {
+    /// Returns the UDP payload size queries advertise in an OPT record, or `None`
+    /// when they carry none. The DO bit and raw options can only travel in an OPT
+    /// record, so asking for either implies one sized for signed answers.
+    fn udp_payload_size(&self) -> Option<u16> {
+        match self.edns {
+            Some(size) => Some(size),
+            None => (self.dnssec_ok || self.validate || !self.edns_options.is_empty())
+                .then_some(DNSSEC_UDP_PAYLOAD_SIZE),
+        }
+    }
+}
+
+/// The servers known to reject queries that carry an EDNS(0) OPT record.
+///
+/// RFC 6891 section 7 lets a client that gets FORMERR or NOTIMP for a query with
+/// an OPT record ask again without one. [`query`] does so, and adds the server to
+/// the set so later queries skip the rejected attempt.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::resolver::{EdnsDowngrades, QueryOptions};
+/// use std::net::Ipv4Addr;
+/// use std::sync::Arc;
+///
+/// let downgrades = Arc::new(EdnsDowngrades::default());
+/// let options = QueryOptions {
+///     edns: Some(1232),
+///     edns_downgrades: Some(Arc::clone(&downgrades)),
+///     ..QueryOptions::default()
+/// };
+/// assert!(!downgrades.contains(Ipv4Addr::new(192, 0, 2, 53)));
+/// ```
+#[derive(Debug, Default)]
+pub struct EdnsDowngrades {
+    servers: Mutex<HashSet<Ipv4Addr>>,
+}
+
+impl EdnsDowngrades {
+    /// Returns whether `server` is queried without an OPT record.
+    pub fn contains(&self, server: Ipv4Addr) -> bool {
+        self.servers.lock().unwrap().contains(&server)
+    }
+
+    /// Queries `server` without an OPT record from now on.
+    pub fn insert(&self, server: Ipv4Addr) {
+        self.servers.lock().unwrap().insert(server);
+    }
+}
+
+/// The outcome of a [`query`]: the accepted response plus metadata gathered
+/// while waiting for it.
+#[derive(Debug)]
+pub struct QueryResult {
+    /// The first well-formed response received for the query.
+    pub message: DnsMessage,
+
+    /// Further responses for the same transaction that arrived during
+    /// [`QueryOptions::duplicate_window`], in order of arrival.
+    pub duplicates: Vec<DuplicateResponse>,
+
+    /// Set when recursion was requested (RD) but the server reports that it is
+    /// not available (RA clear).
+    ///
+    /// This typically means the query was sent to an authoritative-only server,
+    /// and the message may hold a partial referral rather than the answer.
+    pub recursion_unavailable: bool,
+
+    /// The server that sent the accepted response, which differs from the one
+    /// passed to [`query`] when the retry policy moved on to an alternate server.
+    pub server: Ipv4Addr,
+
+    /// The size of the query as sent, in bytes.
+    pub request_size: usize,
 
-use crate::dns::{DnsMessage, DnsQuestion, QueryType, ResponseCode};
+    /// The size and composition of the accepted response as received, including
+    /// any disagreement between its header counts and its sections.
+    pub response_stats: MessageStats,
+
+    /// The DNSSEC status of the response, when [`QueryOptions::validate`] is set.
+    pub security: Option<SecurityStatus>,
+}
+
+/// A response that arrived after the accepted one for the same transaction.
+#[derive(Debug, Clone)]
+pub struct DuplicateResponse {
+    /// The address the duplicate was received from.
+    pub source: SocketAddr,
+
+    /// Time elapsed between the accepted response and this duplicate.
+    pub delay: Duration,
+
+    /// The response code carried by the duplicate.
+    pub response_code: ResponseCode,
+
+    /// Answer records present in the accepted response but not in the duplicate.
+    pub only_in_accepted: Vec<ResourceRecord>,
+
+    /// Answer records present in the duplicate but not in the accepted response.
+    pub only_in_duplicate: Vec<ResourceRecord>,
+
+    /// Whether the duplicate disagrees with the accepted response, either in its
+    /// response code or its answer records. An identical duplicate usually just
+    /// means the datagram was duplicated in transit.
+    pub conflicting: bool,
+}
 
 /// Errors that can occur during DNS resolution.
 ///
@@ -72,8 +455,8 @@ pub enum DnsError {
 
     /// The DNS query timed out.
     ///
-    /// This occurs when no response is received within the configured
-    /// timeout period (currently 5 seconds).
+    /// This occurs when no response is received to any attempt allowed by
+    /// the [`RetryPolicy`] (by default a single attempt of 5 seconds).
     Timeout,
 
     /// The DNS response was malformed or could not be parsed.
@@ -83,12 +466,26 @@ pub enum DnsError {
     /// went wrong during parsing.
     InvalidResponse(String),
 
+    /// The name to look up cannot be sent in a query.
+    ///
+    /// This occurs before anything is sent, when a label is empty, longer than 63
+    /// octets or not a valid IDNA2008 label, or the whole name is longer than 255
+    /// octets. The string describes what is wrong with the name.
+    InvalidName(String),
+
     /// The DNS server returned an error response code.
     ///
     /// This occurs when the DNS server successfully processed the query
     /// but returned an error condition such as NXDOMAIN (domain not found),
     /// SERVFAIL (server failure), or other DNS error codes.
     ServerReturnedError(ResponseCode),
+
+    /// The response failed strict validation.
+    ///
+    /// Only returned when [`QueryOptions::strict`] is enabled. Each string
+    /// describes one protocol irregularity found in the response, such as a
+    /// question mismatch, trailing bytes or out-of-bailiwick records.
+    ProtocolViolations(Vec<String>),
 }
 
 /// Enables `DnsError` to be used with the standard error handling infrastructure.
@@ -122,9 +519,15 @@ impl std::fmt::Display for DnsError {
             DnsError::Io(e) => write!(f, "I/O error: {}", e),
             DnsError::Timeout => write!(f, "Request timed out"),
             DnsError::InvalidResponse(msg) => write!(f, "Invalid DNS response: {}", msg),
+            DnsError::InvalidName(msg) => write!(f, "Invalid domain name: {}", msg),
             DnsError::ServerReturnedError(code) => {
                 write!(f, "DNS server returned an error: {:?}", code)
             }
+            DnsError::ProtocolViolations(violations) => write!(
+                f,
+                "Response failed strict validation: {}",
+                violations.join("; ")
+            ),
         }
     }
 }
@@ -152,6 +555,82 @@ impl From<std::io::Error> for DnsError {
     }
 }
 
+/// The broad category of a [`DnsError`], for deciding how to react to it.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::ResponseCode;
+/// use dns_resolver::resolver::{DnsError, DnsErrorKind};
+///
+/// let error = DnsError::ServerReturnedError(ResponseCode::ServerFailure);
+/// assert_eq!(error.kind(), DnsErrorKind::UpstreamFailure);
+/// assert!(error.is_retryable());
+/// assert!(!DnsError::ServerReturnedError(ResponseCode::NameError).is_retryable());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsErrorKind {
+    /// No response arrived, or the network failed in a way that may clear up:
+    /// timeouts, refused or reset connections, unreachable networks. Retrying,
+    /// possibly against another server, may succeed.
+    Transient,
+    /// The server answered but could not resolve the query (SERVFAIL, REFUSED,
+    /// NOTIMP). The same server will likely fail again, but another may not.
+    UpstreamFailure,
+    /// The answer is definitive, such as NXDOMAIN, or the server rejected the
+    /// query itself (FORMERR). Retrying will not change it.
+    Permanent,
+    /// The local setup is wrong: an unusable source address or interface,
+    /// missing permissions, a missing socket file or invalid input.
+    Configuration,
+    /// The response was malformed or failed strict validation, which points at a
+    /// broken server or a middlebox tampering with packets.
+    Protocol,
+}
+
+impl DnsError {
+    /// Classifies the error so callers can decide whether to retry, fail over to
+    /// another server, or give up.
+    pub fn kind(&self) -> DnsErrorKind {
+        use std::io::ErrorKind;
+
+        match self {
+            DnsError::Timeout => DnsErrorKind::Transient,
+            DnsError::Io(e) => match e.kind() {
+                ErrorKind::AddrNotAvailable
+                | ErrorKind::AddrInUse
+                | ErrorKind::PermissionDenied
+                | ErrorKind::InvalidInput
+                | ErrorKind::NotFound
+                | ErrorKind::Unsupported => DnsErrorKind::Configuration,
+                _ => DnsErrorKind::Transient,
+            },
+            DnsError::InvalidName(_) => DnsErrorKind::Configuration,
+            DnsError::ServerReturnedError(code) => match code {
+                ResponseCode::ServerFailure
+                | ResponseCode::Refused
+                | ResponseCode::NotImplemented => DnsErrorKind::UpstreamFailure,
+                ResponseCode::NoError | ResponseCode::FormatError | ResponseCode::NameError => {
+                    DnsErrorKind::Permanent
+                }
+            },
+            DnsError::InvalidResponse(_) | DnsError::ProtocolViolations(_) => {
+                DnsErrorKind::Protocol
+            }
+        }
+    }
+
+    /// Returns `true` if trying again, against the same or another server, may
+    /// succeed: that is, for [`DnsErrorKind::Transient`] and
+    /// [`DnsErrorKind::UpstreamFailure`] errors.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self.kind(),
+            DnsErrorKind::Transient | DnsErrorKind::UpstreamFailure
+        )
+    }
+}
+
 /// Performs a DNS query for the specified domain and record type.
 ///
 /// This function implements a complete DNS resolution process by:
@@ -161,8 +640,9 @@ impl From<std::io::Error> for DnsError {
 /// 4. Receiving and parsing the DNS response
 /// 5. Validating the response and extracting the results
 ///
-/// The function uses a 5-second timeout to prevent indefinite blocking and
-/// provides detailed error information for troubleshooting failed queries.
+/// Unanswered attempts are retransmitted according to [`QueryOptions::retry`],
+/// which by default allows a single 5-second attempt. The function provides
+/// detailed error information for troubleshooting failed queries.
 ///
 /// # Arguments
 ///
@@ -192,9 +672,12 @@ impl From<std::io::Error> for DnsError {
 /// This function can return several types of errors:
 ///
 /// - [`DnsError::Io`] - Network connectivity issues, permission problems, or
-///   other socket-related errors
-/// - [`DnsError::Timeout`] - No response received within 5 seconds
+///   other socket-related errors. When the server host reports that nothing is
+///   listening on port 53 (ICMP port unreachable), this is returned immediately
+///   with [`std::io::ErrorKind::ConnectionRefused`] rather than after the timeout
+/// - [`DnsError::Timeout`] - No response received to any attempt of the retry policy
 /// - [`DnsError::InvalidResponse`] - Malformed or unparseable DNS response
+/// - [`DnsError::InvalidName`] - The domain name cannot be encoded in a query
 /// - [`DnsError::ServerReturnedError`] - DNS server returned an error code such as:
 ///   - `NXDOMAIN` - Domain name does not exist
 ///   - `SERVFAIL` - Server failure or temporary error
@@ -204,7 +687,7 @@ impl From<std::io::Error> for DnsError {
 ///
 /// ## Basic A record lookup
 ///
-/// ```rust
+/// ```rust,no_run
 /// use dns_resolver::resolver::resolve;
 /// use dns_resolver::dns::QueryType;
 /// use std::net::Ipv4Addr;
@@ -226,7 +709,7 @@ impl From<std::io::Error> for DnsError {
 ///
 /// ## MX record lookup
 ///
-/// ```rust
+/// ```rust,no_run
 /// use dns_resolver::resolver::resolve;
 /// use dns_resolver::dns::QueryType;
 /// use std::net::Ipv4Addr;
@@ -247,7 +730,7 @@ impl From<std::io::Error> for DnsError {
 ///
 /// ## Error handling
 ///
-/// ```rust
+/// ```rust,no_run
 /// use dns_resolver::resolver::{resolve, DnsError};
 /// use dns_resolver::dns::QueryType;
 /// use std::net::Ipv4Addr;
@@ -283,79 +766,2212 @@ pub fn resolve(
     query_type: QueryType,
     dns_server_addr: Ipv4Addr,
 ) -> Result<DnsMessage, DnsError> {
-    // The DNS server port is standardized to 53 per RFC 1035.
-    let server_address = (dns_server_addr, 53);
+    resolve_with_options(
+        domain_name,
+        query_type,
+        dns_server_addr,
+        &QueryOptions::default(),
+    )
+}
 
-    // Bind a UDP socket to an available local port.
-    // Using "0.0.0.0:0" allows the OS to choose an appropriate interface and ephemeral port.
-    let socket = UdpSocket::bind("0.0.0.0:0")?;
+/// Performs a DNS query like [`resolve`], using the given [`QueryOptions`].
+///
+/// # Arguments
+///
+/// * `domain_name` - The fully qualified domain name to resolve
+/// * `query_type` - The type of DNS record to request
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+/// * `options` - Settings for the outgoing socket, such as its source address
+///
+/// # Errors
+///
+/// In addition to the errors described for [`resolve`], this returns
+/// [`DnsError::Io`] if the socket cannot be bound to the requested source
+/// address or interface (for example when the address is not assigned to this
+/// host, or the process lacks the privileges to bind to a device).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::resolver::{resolve_with_options, QueryOptions};
+/// use dns_resolver::dns::QueryType;
+/// use std::net::Ipv4Addr;
+///
+/// let options = QueryOptions {
+///     interface: Some("eth1".to_string()),
+///     ..QueryOptions::default()
+/// };
+///
+/// let response = resolve_with_options(
+///     "internal.example",
+///     QueryType::A,
+///     Ipv4Addr::new(10, 0, 0, 53),
+///     &options,
+/// )?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn resolve_with_options(
+    domain_name: &str,
+    query_type: QueryType,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<DnsMessage, DnsError> {
+    let result = query(domain_name, query_type, dns_server_addr, options)?;
+    check_response_code(result.message)
+}
 
-    // Set a read timeout to prevent indefinite blocking on unresponsive servers.
-    // 5 seconds provides a reasonable balance between responsiveness and reliability.
-    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+/// Passes a response on, or fails with its response code if it is not NOERROR.
+fn check_response_code(message: DnsMessage) -> Result<DnsMessage, DnsError> {
+    // --- Validate the Response ---
+    // Check if the DNS server encountered an error processing our query.
+    // Even if we receive a response, it might contain an error code like NXDOMAIN.
+    if message.header.get_response_code() != ResponseCode::NoError {
+        return Err(DnsError::ServerReturnedError(
+            message.header.get_response_code(),
+        ));
+    }
 
-    // --- Build the DNS Query Message ---
-    let mut message = DnsMessage::new();
+    // Return the successfully parsed and validated DNS response.
+    // The caller can now examine the answers, authority, and additional sections.
+    Ok(message)
+}
 
-    // Configure the header for a standard recursive query:
-    // - Use a fixed ID for request/response matching (production code should use random IDs)
-    // - Set flags to 0x0100 (standard query with Recursion Desired bit set)
-    // - Set question count to 1 since we're asking one question
-    message.header.id = 1234; // TODO: Use a random ID for production security
-    message.header.flags = 0x0100; // Standard query (RD=1, recursion desired)
-    message.header.question_count = 1;
+/// Looks up the host names an address maps back to, through its PTR records in
+/// `in-addr.arpa` or `ip6.arpa`.
+///
+/// An NXDOMAIN answer means the address simply has no reverse mapping, so it is
+/// returned as an empty list rather than an error.
+///
+/// # Arguments
+///
+/// * `addr` - The IPv4 or IPv6 address to look up
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+///
+/// # Errors
+///
+/// Returns any [`DnsError`] [`resolve`] can, other than NXDOMAIN.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::resolver::reverse_lookup;
+/// use std::net::Ipv4Addr;
+///
+/// let names = reverse_lookup("8.8.8.8".parse()?, Ipv4Addr::new(1, 1, 1, 1))?;
+/// assert!(names.iter().any(|name| name.starts_with("dns.google")));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn reverse_lookup(addr: IpAddr, dns_server_addr: Ipv4Addr) -> Result<Vec<String>, DnsError> {
+    reverse_lookup_with_options(addr, dns_server_addr, &QueryOptions::default())
+}
 
-    // Create the question section of the DNS message.
-    // This specifies what we're asking for: domain name, record type, and class (Internet).
-    message.questions.push(DnsQuestion {
-        name: domain_name.to_string(),
-        qtype: query_type,
-        qclass: 1, // IN (Internet) class - the most common DNS class
-    });
+/// Looks up the host names of an address like [`reverse_lookup`], using the given
+/// [`QueryOptions`].
+///
+/// # Arguments
+///
+/// * `addr` - The IPv4 or IPv6 address to look up
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+/// * `options` - Settings for the query
+///
+/// # Errors
+///
+/// Returns any [`DnsError`] [`resolve_with_options`] can, other than NXDOMAIN.
+pub fn reverse_lookup_with_options(
+    addr: IpAddr,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<Vec<String>, DnsError> {
+    match resolve_with_options(
+        &reverse_lookup_name(&addr),
+        QueryType::PTR,
+        dns_server_addr,
+        options,
+    ) {
+        Ok(response) => Ok(response
+            .answers
+            .iter()
+            .filter_map(|r| r.get_ptr())
+            .map(str::to_string)
+            .collect()),
+        Err(DnsError::ServerReturnedError(ResponseCode::NameError)) => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
 
-    // Serialize the DNS message into the wire format (binary representation).
-    // This converts our structured data into the byte format expected by DNS servers.
-    let mut query_buffer = Vec::new();
-    message
-        .pack(&mut query_buffer)
-        .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+/// Resolves several record types for the same name concurrently.
+///
+/// Each type is queried on its own thread with [`resolve_with_options`], so the
+/// whole lookup takes about as long as the slowest query rather than the sum of all
+/// of them.
+///
+/// # Arguments
+///
+/// * `domain_name` - The fully qualified domain name to resolve
+/// * `query_types` - The record types to request
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+/// * `options` - Settings shared by every query
+///
+/// # Returns
+///
+/// One result per entry of `query_types`, in the same order.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::resolver::{resolve_many, QueryOptions};
+/// use dns_resolver::dns::QueryType;
+/// use std::net::Ipv4Addr;
+///
+/// let types = [QueryType::A, QueryType::AAAA, QueryType::MX];
+/// let results = resolve_many("example.com", &types, Ipv4Addr::new(8, 8, 8, 8), &QueryOptions::default());
+/// for (query_type, result) in types.iter().zip(results) {
+///     println!("{}: {:?}", query_type, result.map(|m| m.answers.len()));
+/// }
+/// ```
+pub fn resolve_many(
+    domain_name: &str,
+    query_types: &[QueryType],
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Vec<Result<DnsMessage, DnsError>> {
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = query_types
+            .iter()
+            .map(|query_type| {
+                scope.spawn(move || {
+                    resolve_with_options(domain_name, *query_type, dns_server_addr, options)
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("query thread panicked"))
+            .collect()
+    })
+}
+
+/// A shareable handle for resolving names through one server.
+///
+/// A `Resolver` holds the server address, the [`QueryOptions`], a [`HostCache`] and
+/// a [`DnsCache`] behind an [`Arc`], so cloning it is cheap and every clone
+/// shares the same caches.
+/// Build one with [`Resolver::builder`], or with [`Resolver::new`] from options
+/// already at hand.
+/// It is `Send` and `Sync`, and all methods take `&self`: queries from different
+/// threads run in parallel, each on its own socket and with its own transaction ID,
+/// and only the cache is synchronized, by a mutex held for lookups and inserts but
+/// never across network I/O.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::resolver::Resolver;
+/// use std::net::Ipv4Addr;
+/// use std::thread;
+///
+/// let resolver = Resolver::builder().server(Ipv4Addr::new(8, 8, 8, 8)).build()?;
+/// let handles: Vec<_> = ["example.com", "example.org"]
+///     .into_iter()
+///     .map(|name| {
+///         let resolver = resolver.clone();
+///         thread::spawn(move || resolver.lookup_host(name))
+///     })
+///     .collect();
+/// for handle in handles {
+///     println!("{:?}", handle.join().unwrap());
+/// }
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    inner: Arc<ResolverInner>,
+}
+
+#[derive(Debug)]
+struct ResolverInner {
+    dns_server_addr: Ipv4Addr,
+    options: QueryOptions,
+    cache: HostCache,
+    responses: Option<Arc<dyn DnsCache>>,
+}
 
-    // --- Send the Query Over UDP ---
-    // --- Send the Query Over UDP ---
-    // Transmit the serialized DNS query to the target server.
-    socket.send_to(&query_buffer, server_address)?;
+impl Resolver {
+    /// Returns a [`ResolverBuilder`] for configuring a resolver setting by setting.
+    pub fn builder() -> ResolverBuilder {
+        ResolverBuilder::default()
+    }
 
-    // --- Receive the DNS Response ---
-    // DNS messages are typically limited to 512 bytes over UDP (RFC 1035).
-    // Larger responses use TCP or DNS extensions, but 512 bytes covers most use cases.
-    let mut response_buffer = [0; 512];
+    /// Creates a resolver that sends its queries to `dns_server_addr`.
+    ///
+    /// Unless `options` already has them, the resolver gets a [`CircuitBreaker`]
+    /// with the default thresholds and an empty set of [`EdnsDowngrades`], both
+    /// shared by all of its clones. Responses to
+    /// [`Resolver::resolve`] are cached with the default [`TtlPolicy`].
+    pub fn new(dns_server_addr: Ipv4Addr, options: QueryOptions) -> Self {
+        let responses: Arc<dyn DnsCache> = Arc::new(ResponseCache::new());
+        Self::with_response_cache(dns_server_addr, options, Some(responses))
+    }
 
-    // Wait for the server's response, handling timeout and other I/O errors appropriately.
-    let (size, _) = socket.recv_from(&mut response_buffer).map_err(|e| {
-        // Convert specific I/O error types to more descriptive DNS errors.
-        if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut {
-            DnsError::Timeout
-        } else {
-            DnsError::Io(e)
+    fn with_response_cache(
+        dns_server_addr: Ipv4Addr,
+        mut options: QueryOptions,
+        responses: Option<Arc<dyn DnsCache>>,
+    ) -> Self {
+        options
+            .circuit_breaker
+            .get_or_insert_with(|| Arc::new(CircuitBreaker::default()));
+        options.edns_downgrades.get_or_insert_default();
+        Resolver {
+            inner: Arc::new(ResolverInner {
+                dns_server_addr,
+                cache: HostCache::new(dns_server_addr, options.clone()),
+                options,
+                responses,
+            }),
         }
-    })?;
+    }
 
-    // --- Parse the DNS Response Message ---
-    // Deserialize the binary response back into a structured DnsMessage.
-    // Only parse the actual response data (not the full buffer).
-    let response_message = DnsMessage::from_bytes(&response_buffer[..size])
-        .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+    /// Returns the server this resolver queries.
+    pub fn server(&self) -> Ipv4Addr {
+        self.inner.dns_server_addr
+    }
 
-    // --- Validate the Response ---
-    // Check if the DNS server encountered an error processing our query.
-    // Even if we receive a response, it might contain an error code like NXDOMAIN.
-    if response_message.header.get_response_code() != ResponseCode::NoError {
-        return Err(DnsError::ServerReturnedError(
-            response_message.header.get_response_code(),
-        ));
+    /// Returns the options used for every query.
+    pub fn options(&self) -> &QueryOptions {
+        &self.inner.options
     }
 
-    // Return the successfully parsed and validated DNS response.
-    // The caller can now examine the answers, authority, and additional sections.
-    Ok(response_message)
+    /// Sends a query like [`query`], returning any well-formed response.
+    pub fn query(&self, domain_name: &str, query_type: QueryType) -> Result<QueryResult, DnsError> {
+        query(
+            domain_name,
+            query_type,
+            self.inner.dns_server_addr,
+            &self.inner.options,
+        )
+    }
+
+    /// Resolves a name like [`resolve_with_options`], failing on error response codes.
+    ///
+    /// Responses come from the resolver's [`DnsCache`] while their TTLs last, with the
+    /// TTLs counted down; only questions without a fresh cached response are sent.
+    /// When the server times out, cannot be reached or answers SERVFAIL, an expired
+    /// response is returned instead if the cache still serves it stale (see
+    /// [`ResolverBuilder::serve_stale`]).
+    pub fn resolve(
+        &self,
+        domain_name: &str,
+        query_type: QueryType,
+    ) -> Result<DnsMessage, DnsError> {
+        let Some(cache) = &self.inner.responses else {
+            return resolve_with_options(
+                domain_name,
+                query_type,
+                self.inner.dns_server_addr,
+                &self.inner.options,
+            );
+        };
+        let class = self.inner.options.class;
+        let message = match cache.get(domain_name, query_type, class) {
+            Some(message) => {
+                if cache.claim_prefetch(domain_name, query_type, class) {
+                    self.prefetch(domain_name, query_type);
+                }
+                message
+            }
+            None => {
+                let outcome = self.query(domain_name, query_type);
+                let unanswered = match &outcome {
+                    Ok(result) => {
+                        result.message.header.get_response_code() == ResponseCode::ServerFailure
+                    }
+                    Err(e) => e.kind() == DnsErrorKind::Transient,
+                };
+                if unanswered && let Some(stale) = cache.get_stale(domain_name, query_type, class) {
+                    return Ok(stale);
+                }
+                let message = outcome?.message;
+                cache.insert(domain_name, query_type, class, &message);
+                message
+            }
+        };
+        check_response_code(message)
+    }
+
+    /// Looks up the host names of an address like [`reverse_lookup`].
+    pub fn reverse_lookup(&self, addr: IpAddr) -> Result<Vec<String>, DnsError> {
+        reverse_lookup_with_options(addr, self.inner.dns_server_addr, &self.inner.options)
+    }
+
+    /// Returns the addresses of a name through the shared cache, like
+    /// [`HostCache::lookup_host`].
+    pub fn lookup_host(&self, name: &str) -> Result<Vec<IpAddr>, DnsError> {
+        self.inner.cache.lookup_host(name)
+    }
+
+    /// Resolves a `host:port` string like [`resolve_socket_addrs`].
+    pub fn resolve_socket_addrs(
+        &self,
+        target: &str,
+        order: AddressOrder,
+    ) -> Result<Vec<SocketAddr>, DnsError> {
+        resolve_socket_addrs(
+            target,
+            order,
+            self.inner.dns_server_addr,
+            &self.inner.options,
+        )
+    }
+
+    /// Returns the hit and miss counters of the shared cache.
+    pub fn cache_stats(&self) -> CacheStats {
+        self.inner.cache.stats()
+    }
+
+    /// Refreshes the cached response to a question on a background thread.
+    fn prefetch(&self, domain_name: &str, query_type: QueryType) {
+        let resolver = self.clone();
+        let domain_name = domain_name.to_string();
+        std::thread::spawn(move || {
+            let Some(cache) = &resolver.inner.responses else {
+                return;
+            };
+            // A failed refresh leaves the entry to expire; the next lookup after
+            // that goes to the server as usual.
+            if let Ok(result) = resolver.query(&domain_name, query_type) {
+                let class = resolver.inner.options.class;
+                cache.insert(&domain_name, query_type, class, &result.message);
+            }
+        });
+    }
+
+    /// Returns the cache of [`Resolver::resolve`], or `None` if the resolver was
+    /// built without one.
+    pub fn response_cache(&self) -> Option<&dyn DnsCache> {
+        self.inner.responses.as_deref()
+    }
+
+    /// Watches `domain_name` for changes to its `query_type` records, re-querying
+    /// as their TTL runs out. See [`Watch`].
+    pub fn watch(&self, domain_name: &str, query_type: QueryType) -> Watch {
+        self.watch_with_options(domain_name, query_type, WatchOptions::default())
+    }
+
+    /// Like [`Resolver::watch`], with control over how often to re-query.
+    pub fn watch_with_options(
+        &self,
+        domain_name: &str,
+        query_type: QueryType,
+        options: WatchOptions,
+    ) -> Watch {
+        Watch::new(self.clone(), domain_name, query_type, options)
+    }
+}
+
+/// Configures a [`Resolver`] step by step. Created with [`Resolver::builder`].
+///
+/// Every setting starts at the [`QueryOptions`] default, so only what differs needs
+/// to be set: the servers, how long to wait and how often to try, the transport and
+/// EDNS. The servers are tried in the order given; with more than one, an attempt
+/// that goes unanswered moves on to the next server, and each server gets at least
+/// one attempt unless [`ResolverBuilder::attempts`] says otherwise.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::resolver::Resolver;
+/// use std::net::Ipv4Addr;
+/// use std::time::Duration;
+///
+/// let resolver = Resolver::builder()
+///     .server(Ipv4Addr::new(1, 1, 1, 1))
+///     .server(Ipv4Addr::new(8, 8, 8, 8))
+///     .timeout(Duration::from_secs(2))
+///     .edns(1232)
+///     .build()?;
+/// assert_eq!(resolver.server(), Ipv4Addr::new(1, 1, 1, 1));
+/// assert_eq!(resolver.options().retry.max_attempts, 2);
+/// # Ok::<(), String>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ResolverBuilder {
+    servers: Vec<Ipv4Addr>,
+    attempts: Option<u32>,
+    options: QueryOptions,
+    without_response_cache: bool,
+    cache_ttl_policy: TtlPolicy,
+    cache_max_entries: Option<usize>,
+    cache_max_bytes: Option<usize>,
+    cache_max_stale: Option<Duration>,
+    cache_prefetch_hits: Option<u32>,
+    cache: Option<Arc<dyn DnsCache>>,
+}
+
+impl ResolverBuilder {
+    /// Starts from these options instead of the defaults. Settings made before this
+    /// call are replaced, apart from the servers.
+    pub fn options(mut self, options: QueryOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a server to query, after any added before.
+    pub fn server(mut self, server: Ipv4Addr) -> Self {
+        self.servers.push(server);
+        self
+    }
+
+    /// Adds several servers to query, in order, after any added before.
+    pub fn servers(mut self, servers: impl IntoIterator<Item = Ipv4Addr>) -> Self {
+        self.servers.extend(servers);
+        self
+    }
+
+    /// Queries servers on this port instead of 53, over every transport.
+    pub fn port(mut self, port: u16) -> Self {
+        self.options.port = Some(port);
+        self
+    }
+
+    /// Waits this long for a response to the first attempt. Later attempts wait
+    /// longer, as [`RetryPolicy::multiplier`] says.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.retry.initial_timeout = timeout;
+        self
+    }
+
+    /// Sends each query at most this many times in all, across all servers.
+    pub fn attempts(mut self, attempts: u32) -> Self {
+        self.attempts = Some(attempts);
+        self
+    }
+
+    /// Replaces the whole retransmission schedule. With several servers, a policy
+    /// that leaves [`RetryPolicy::switch_server_after`] unset still moves on to the
+    /// next server after each unanswered attempt.
+    pub fn retry_policy(mut self, retry: RetryPolicy) -> Self {
+        self.options.retry = retry;
+        self
+    }
+
+    /// Starts each query at a randomly chosen server (see [`QueryOptions::rotate`]).
+    pub fn rotate(mut self, rotate: bool) -> Self {
+        self.options.rotate = rotate;
+        self
+    }
+
+    /// Sends every query over TCP instead of UDP (see [`QueryOptions::tcp`]).
+    pub fn tcp(mut self, tcp: bool) -> Self {
+        self.options.tcp = tcp;
+        self
+    }
+
+    /// Sends every query over a Unix domain socket (see [`QueryOptions::unix_socket`]).
+    pub fn unix_socket(mut self, path: impl Into<PathBuf>) -> Self {
+        self.options.unix_socket = Some(path.into());
+        self
+    }
+
+    /// Advertises this UDP payload size in an EDNS(0) OPT record (see
+    /// [`QueryOptions::edns`]).
+    pub fn edns(mut self, udp_payload_size: u16) -> Self {
+        self.options.edns = Some(udp_payload_size);
+        self
+    }
+
+    /// Adds an option to the OPT record of every query (see
+    /// [`QueryOptions::edns_options`]).
+    pub fn edns_option(mut self, code: u16, data: impl Into<Vec<u8>>) -> Self {
+        let data = data.into();
+        self.options.edns_options.push(EdnsOption { code, data });
+        self
+    }
+
+    /// Asks for DNSSEC records with the DO bit (see [`QueryOptions::dnssec_ok`]).
+    pub fn dnssec_ok(mut self, dnssec_ok: bool) -> Self {
+        self.options.dnssec_ok = dnssec_ok;
+        self
+    }
+
+    /// Validates every response with DNSSEC (see [`QueryOptions::validate`]).
+    pub fn validate(mut self, validate: bool) -> Self {
+        self.options.validate = validate;
+        self
+    }
+
+    /// Validates from these DS records of the root keys instead of the built-in
+    /// ones (see [`QueryOptions::trust_anchors`]).
+    pub fn trust_anchors(mut self, trust_anchors: Vec<ResourceRecord>) -> Self {
+        self.options.trust_anchors = Some(Arc::new(trust_anchors));
+        self
+    }
+
+    /// Queries in this class instead of IN.
+    pub fn class(mut self, class: DnsClass) -> Self {
+        self.options.class = class;
+        self
+    }
+
+    /// Sends UDP queries from this local address.
+    pub fn source_address(mut self, source_address: Ipv4Addr) -> Self {
+        self.options.source_address = Some(source_address);
+        self
+    }
+
+    /// Sends UDP queries through this network interface (see
+    /// [`QueryOptions::interface`]).
+    pub fn interface(mut self, interface: impl Into<String>) -> Self {
+        self.options.interface = Some(interface.into());
+        self
+    }
+
+    /// Signs queries and verifies responses with this TSIG key.
+    pub fn tsig(mut self, key: TsigKey) -> Self {
+        self.options.tsig = Some(key);
+        self
+    }
+
+    /// Answers queries with canned responses instead of sending them (see
+    /// [`QueryOptions::mock_transport`]).
+    pub fn mock_transport(mut self, mock: Arc<MockTransport>) -> Self {
+        self.options.mock_transport = Some(mock);
+        self
+    }
+
+    /// Answers queries for the names and addresses a hosts file lists from the file
+    /// (see [`QueryOptions::hosts`]).
+    pub fn hosts(mut self, hosts: Arc<HostsFile>) -> Self {
+        self.options.hosts = Some(hosts);
+        self
+    }
+
+    /// Caches the responses to [`Resolver::resolve`], which is the default, or not.
+    pub fn response_cache(mut self, enabled: bool) -> Self {
+        self.without_response_cache = !enabled;
+        self
+    }
+
+    /// Bounds how long [`Resolver::resolve`] keeps responses in its cache.
+    ///
+    /// Only applies to the in-memory cache the resolver creates itself; a cache
+    /// given with [`ResolverBuilder::cache`] brings its own policy.
+    pub fn cache_ttl_policy(mut self, ttl_policy: TtlPolicy) -> Self {
+        self.cache_ttl_policy = ttl_policy;
+        self
+    }
+
+    /// Limits the cache of [`Resolver::resolve`] to `max_entries` responses,
+    /// evicting the least recently used ones (see [`ResponseCache::with_max_entries`]).
+    ///
+    /// Like [`ResolverBuilder::cache_ttl_policy`], only applies to the in-memory
+    /// cache the resolver creates itself.
+    pub fn cache_max_entries(mut self, max_entries: usize) -> Self {
+        self.cache_max_entries = Some(max_entries);
+        self
+    }
+
+    /// Limits the responses in the cache of [`Resolver::resolve`] to about
+    /// `max_bytes` (see [`ResponseCache::with_max_bytes`]).
+    pub fn cache_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.cache_max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Serves responses up to `max_stale` after they expired when the server
+    /// cannot be reached (RFC 8767; see [`ResponseCache::with_serve_stale`]).
+    ///
+    /// Such responses are marked with an Extended DNS Error "Stale Answer" option,
+    /// which [`is_stale_answer`](crate::cache::is_stale_answer) detects.
+    pub fn serve_stale(mut self, max_stale: Duration) -> Self {
+        self.cache_max_stale = Some(max_stale);
+        self
+    }
+
+    /// Refreshes cached responses that were used at least `min_hits` times in the
+    /// background shortly before they expire (see [`ResponseCache::with_prefetch`]),
+    /// so lookups of popular names do not wait for the server.
+    pub fn prefetch(mut self, min_hits: u32) -> Self {
+        self.cache_prefetch_hits = Some(min_hits);
+        self
+    }
+
+    /// Caches the responses to [`Resolver::resolve`] in `cache`, such as a
+    /// [`FileCache`](crate::cache::FileCache) or a cache shared with other resolvers,
+    /// instead of a new in-memory one.
+    pub fn cache(mut self, cache: Arc<dyn DnsCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Creates the resolver.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when no server was added, or when the transport cannot
+    /// honour the other settings: TCP and a Unix domain socket are exclusive, and
+    /// outside Linux TCP connections cannot be bound to a source address or
+    /// interface.
+    pub fn build(self) -> Result<Resolver, String> {
+        let Some((&server, alternates)) = self.servers.split_first() else {
+            return Err("a resolver needs at least one server".to_string());
+        };
+        let mut options = self.options;
+        if options.tcp && options.unix_socket.is_some() {
+            return Err("TCP cannot be combined with a Unix domain socket".to_string());
+        }
+        if cfg!(not(target_os = "linux"))
+            && options.tcp
+            && (options.source_address.is_some() || options.interface.is_some())
+        {
+            return Err("TCP cannot be combined with a source address or interface".to_string());
+        }
+
+        options.alternate_servers.extend_from_slice(alternates);
+        if !options.alternate_servers.is_empty() {
+            options.retry.switch_server_after.get_or_insert(1);
+            let servers = options.alternate_servers.len() as u32 + 1;
+            options.retry.max_attempts = options.retry.max_attempts.max(servers);
+        }
+        if let Some(attempts) = self.attempts {
+            options.retry.max_attempts = attempts;
+        }
+        let responses = (!self.without_response_cache).then(|| {
+            self.cache.unwrap_or_else(|| {
+                let mut cache = ResponseCache::with_ttl_policy(self.cache_ttl_policy);
+                if let Some(max_entries) = self.cache_max_entries {
+                    cache = cache.with_max_entries(max_entries);
+                }
+                if let Some(max_bytes) = self.cache_max_bytes {
+                    cache = cache.with_max_bytes(max_bytes);
+                }
+                if let Some(max_stale) = self.cache_max_stale {
+                    cache = cache.with_serve_stale(max_stale);
+                }
+                if let Some(min_hits) = self.cache_prefetch_hits {
+                    cache = cache.with_prefetch(min_hits);
+                }
+                Arc::new(cache)
+            })
+        });
+        Ok(Resolver::with_response_cache(server, options, responses))
+    }
+}
+
+/// Returns an unpredictable transaction ID for the next query.
+///
+/// An off-path attacker who can guess the ID of a query can forge a response to it
+/// (RFC 5452), so IDs come from the operating system's random number generator
+/// rather than a counter. Where that is unavailable, they come from the keyed
+/// SipHash behind [`RandomState`], whose keys are themselves drawn from it.
+pub(crate) fn random_query_id() -> u16 {
+    #[cfg(unix)]
+    {
+        use std::fs::File;
+
+        let mut bytes = [0; 2];
+        if File::open("/dev/urandom")
+            .and_then(|mut random| random.read_exact(&mut bytes))
+            .is_ok()
+        {
+            return u16::from_be_bytes(bytes);
+        }
+    }
+    RandomState::new().build_hasher().finish() as u16
+}
+
+/// Sends a DNS query and returns the response together with query metadata.
+///
+/// Unlike [`resolve_with_options`], this does not treat an error response code
+/// as a failure: any well-formed response is returned, so that callers can still
+/// inspect it (and any duplicates) when the server answers with NXDOMAIN or
+/// SERVFAIL. Check `result.message.header.get_response_code()` to tell them apart.
+///
+/// # Arguments
+///
+/// * `domain_name` - The fully qualified domain name to resolve
+/// * `query_type` - The type of DNS record to request
+/// * `dns_server_addr` - The IPv4 address of the DNS server to query
+/// * `options` - Settings for the outgoing socket and duplicate detection
+///
+/// # Errors
+///
+/// Returns [`DnsError::Io`], [`DnsError::Timeout`], [`DnsError::InvalidResponse`] or
+/// [`DnsError::InvalidName`] under the same conditions as [`resolve`], and [`DnsError::ProtocolViolations`]
+/// when [`QueryOptions::strict`] is set and the response is irregular. It never
+/// returns [`DnsError::ServerReturnedError`].
+///
+/// A FORMERR or NOTIMP response to a query with an OPT record is taken to mean the
+/// server does not speak EDNS, and the query is sent again without one (see
+/// [`QueryOptions::edns_downgrades`]).
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use dns_resolver::resolver::{query, QueryOptions};
+/// use dns_resolver::dns::QueryType;
+/// use std::net::Ipv4Addr;
+/// use std::time::Duration;
+///
+/// let options = QueryOptions {
+///     duplicate_window: Some(Duration::from_millis(500)),
+///     ..QueryOptions::default()
+/// };
+///
+/// let result = query("example.com", QueryType::A, Ipv4Addr::new(8, 8, 8, 8), &options)?;
+/// for duplicate in result.duplicates.iter().filter(|d| d.conflicting) {
+///     eprintln!("conflicting response from {} after {:?}", duplicate.source, duplicate.delay);
+/// }
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn query(
+    domain_name: &str,
+    query_type: QueryType,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+) -> Result<QueryResult, DnsError> {
+    let downgrades = options.edns_downgrades.as_deref();
+    let edns = options.udp_payload_size().is_some()
+        && !downgrades.is_some_and(|downgrades| downgrades.contains(dns_server_addr));
+    let result = query_once(domain_name, query_type, dns_server_addr, options, edns)?;
+    // A server that predates EDNS may reject the OPT record rather than ignore
+    // it (RFC 6891 section 7), so ask once more without one.
+    let code = result.message.header.get_response_code();
+    if edns
+        && matches!(
+            code,
+            ResponseCode::FormatError | ResponseCode::NotImplemented
+        )
+    {
+        if let Some(downgrades) = downgrades {
+            downgrades.insert(result.server);
+        }
+        return query_once(domain_name, query_type, dns_server_addr, options, false);
+    }
+    Ok(result)
+}
+
+/// Sends a DNS query like [`query`], with an OPT record only when `edns` is set.
+fn query_once(
+    domain_name: &str,
+    query_type: QueryType,
+    dns_server_addr: Ipv4Addr,
+    options: &QueryOptions,
+    edns: bool,
+) -> Result<QueryResult, DnsError> {
+    // --- Build the DNS Query Message ---
+    let mut message = DnsMessage::new();
+
+    // Configure the header for a standard recursive query:
+    // - Use a random ID for request/response matching, so responses cannot be forged blindly
+    // - Set the Recursion Desired bit on a standard query (opcode QUERY)
+    // - Set question count to 1 since we're asking one question
+    message.header.id = random_query_id();
+    message.header.set_recursion_desired(true);
+    message.header.question_count = 1;
+
+    // Add the DNSSEC-related bits the caller asked for: AD says the client
+    // understands authenticated data, and CD asks the server not to withhold data
+    // that fails validation.
+    message.header.set_authentic_data(options.authentic_data);
+    message
+        .header
+        .set_checking_disabled(options.checking_disabled || options.validate);
+
+    // Advertise a larger UDP payload size in an OPT record in the additional section,
+    // which is also where the DO bit asking for DNSSEC records goes.
+    if edns && let Some(udp_payload_size) = options.udp_payload_size() {
+        let mut edns = Edns::new(udp_payload_size);
+        edns.dnssec_ok = options.dnssec_ok || options.validate;
+        edns.options = options.edns_options.clone();
+        message.additionals.push(edns.to_record());
+        message.header.additional_count = 1;
+    }
+
+    // Create the question section of the DNS message.
+    // This specifies what we're asking for: domain name, record type, and class.
+    // Unicode names are asked for by their A-labels, which is also how the server
+    // echoes them in its response.
+    message.questions.push(DnsQuestion {
+        name: to_ascii(domain_name)
+            .and_then(DnsName::new)
+            .map_err(DnsError::InvalidName)?,
+        qtype: query_type,
+        qclass: options.class.into(), // IN (Internet) unless the caller asked otherwise
+    });
+
+    // Serialize the DNS message into the wire format (binary representation).
+    // This converts our structured data into the byte format expected by DNS servers.
+    let mut query_buffer = Vec::new();
+    message
+        .pack(&mut query_buffer)
+        .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+
+    // Names the hosts file lists never reach a server, as with the C library. Its
+    // answers cannot be signed or validated, so those queries always go out.
+    if let Some(hosts) = &options.hosts
+        && options.tsig.is_none()
+        && !options.validate
+        && let Some(response_buffer) = hosts.respond(&query_buffer)
+    {
+        let response_message = DnsMessage::from_bytes(&response_buffer)
+            .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+        return Ok(QueryResult {
+            message: response_message,
+            duplicates: Vec::new(),
+            recursion_unavailable: false,
+            server: dns_server_addr,
+            request_size: query_buffer.len(),
+            response_stats: MessageStats::from_bytes(&response_buffer),
+            security: None,
+        });
+    }
+
+    // Sign the query last, since the signature covers every byte before it. Every
+    // attempt sends the same signed bytes.
+    let mut tsig = options
+        .tsig
+        .as_ref()
+        .map(|key| TsigSession::sign_request(key, &mut query_buffer, SystemTime::now()))
+        .transpose()
+        .map_err(|e| DnsError::Io(std::io::Error::new(std::io::ErrorKind::InvalidInput, e)))?;
+
+    // --- Send the Query, Retransmitting as the Retry Policy Allows ---
+    // Each attempt uses a fresh socket so a late answer to an earlier attempt cannot
+    // be mistaken for the current one, and may go to a different server.
+    let mut servers: Vec<Ipv4Addr> = std::iter::once(dns_server_addr)
+        .chain(options.alternate_servers.iter().copied())
+        .collect();
+    if options.rotate && servers.len() > 1 {
+        let start = random_query_id() as usize % servers.len();
+        servers.rotate_left(start);
+    }
+    let mut attempt = 0;
+    let (socket, response_buffer, server) = loop {
+        let preferred = options.retry.server_index(attempt, servers.len());
+        // Steer around servers whose circuit is open, if there is a breaker.
+        let server = match &options.circuit_breaker {
+            Some(breaker) => breaker.choose(&servers, preferred),
+            None => servers[preferred],
+        };
+        let timeout = options.retry.timeout(attempt);
+        let record = |outcome: &Result<(Option<UdpSocket>, Vec<u8>), DnsError>, sent: Instant| {
+            if let Some(transcript) = &options.transcript {
+                match outcome {
+                    Ok((_, response)) => {
+                        transcript.save(server, &query_buffer, Some(response), sent.elapsed())?
+                    }
+                    Err(DnsError::Timeout) => {
+                        transcript.save(server, &query_buffer, None, sent.elapsed())?
+                    }
+                    Err(_) => {}
+                }
+            }
+            Ok::<(), DnsError>(())
+        };
+        let sent = Instant::now();
+        let mut outcome = match (&options.transcript, &options.unix_socket) {
+            _ if let Some(mock) = &options.mock_transport => {
+                let transport = if options.tcp {
+                    Transport::Tcp
+                } else {
+                    Transport::Udp
+                };
+                mock.exchange(&query_buffer, transport).map(|r| (None, r))
+            }
+            (Some(transcript), _) if transcript.is_replay() => {
+                replay_exchange(transcript, &query_buffer).map(|r| (None, r))
+            }
+            (_, Some(path)) => exchange_unix(&query_buffer, path, timeout).map(|r| (None, r)),
+            (_, None) if options.tcp => {
+                exchange_tcp(&query_buffer, server, timeout, options).map(|r| (None, r))
+            }
+            (_, None) => exchange(&query_buffer, server, timeout, options)
+                .map(|(socket, r)| (Some(socket), r)),
+        };
+        record(&outcome, sent)?;
+
+        // A truncated UDP response holds only part of the answer, so ask again over
+        // TCP, which has no size limit, and use that response instead (RFC 7766).
+        // A query already sent over TCP or a Unix domain socket went over a stream
+        // and has nothing to fall back to. Where TCP cannot leave from the source
+        // address or interface the UDP query used, the truncated response is the
+        // best answer there is, and its TC bit tells the caller so.
+        if !options.tcp
+            && options.unix_socket.is_none()
+            && let Ok((_, response)) = &outcome
+            && is_truncated(response)
+        {
+            let sent = Instant::now();
+            let fallback = match &options.transcript {
+                _ if let Some(mock) = &options.mock_transport => {
+                    mock.exchange(&query_buffer, Transport::Tcp)
+                }
+                Some(transcript) if transcript.is_replay() => {
+                    replay_exchange(transcript, &query_buffer)
+                }
+                _ => exchange_tcp(&query_buffer, server, timeout, options),
+            }
+            .map(|r| (None, r));
+            match fallback {
+                Err(DnsError::Io(e)) if e.kind() == std::io::ErrorKind::Unsupported => {}
+                fallback => {
+                    record(&fallback, sent)?;
+                    outcome = fallback;
+                }
+            }
+        }
+        if let (Err(e), Some(breaker)) = (&outcome, &options.circuit_breaker)
+            && e.kind() == DnsErrorKind::Transient
+        {
+            breaker.record_failure(server);
+        }
+        match outcome {
+            Ok((socket, response_buffer)) => break (socket, response_buffer, server),
+            // Only a missing response or a network hiccup is worth another attempt;
+            // anything else would fail the same way again.
+            Err(e)
+                if e.kind() == DnsErrorKind::Transient
+                    && attempt + 1 < options.retry.attempts() =>
+            {
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    // Check the signature before trusting anything in the response.
+    if let Some(session) = &mut tsig {
+        session
+            .verify(&response_buffer, SystemTime::now())
+            .map_err(|e| DnsError::InvalidResponse(format!("TSIG verification failed: {}", e)))?;
+    }
+
+    // --- Parse the DNS Response Message ---
+    // Deserialize the binary response back into a structured DnsMessage.
+    // Only parse the actual response data (not the full buffer).
+    let response_message = DnsMessage::from_bytes(&response_buffer)
+        .map_err(|e| DnsError::InvalidResponse(e.to_string()))?;
+
+    // A server that answers SERVFAIL counts against its circuit like one that
+    // does not answer at all.
+    if let Some(breaker) = &options.circuit_breaker {
+        if response_message.header.get_response_code() == ResponseCode::ServerFailure {
+            breaker.record_failure(server);
+        } else {
+            breaker.record_success(server);
+        }
+    }
+
+    // In strict mode, hold the response against the query that produced it and
+    // refuse it if anything about it is irregular.
+    if options.strict {
+        let violations = validate_response(&message, &response_buffer);
+        if !violations.is_empty() {
+            return Err(DnsError::ProtocolViolations(violations));
+        }
+    }
+
+    // --- Watch for Duplicate Responses ---
+    // Keep the socket open for the configured window and collect any further
+    // responses to the same transaction for the caller to inspect.
+    let duplicates = match (options.duplicate_window, &socket) {
+        (Some(window), Some(socket)) => {
+            collect_duplicates(socket, &response_message, window, udp_buffer_size(options))?
+        }
+        _ => Vec::new(),
+    };
+
+    // A server that ignores a request for recursion answers only from its own
+    // zones, so flag it for the caller rather than silently passing on a referral.
+    let recursion_unavailable =
+        message.header.recursion_desired() && !response_message.header.recursion_available();
+
+    // Validating fetches the chain of trust with further queries, so do it last.
+    let security = options
+        .validate
+        .then(|| validate(&response_message, server, options));
+
+    Ok(QueryResult {
+        message: response_message,
+        duplicates,
+        recursion_unavailable,
+        server,
+        request_size: query_buffer.len(),
+        response_stats: MessageStats::from_bytes(&response_buffer),
+        security,
+    })
+}
+
+/// Answers one attempt of a query from a replaying transcript.
+fn replay_exchange(transcript: &Transcript, query_buffer: &[u8]) -> Result<Vec<u8>, DnsError> {
+    match transcript.answer(query_buffer) {
+        Some(Some(response)) => Ok(response),
+        Some(None) => Err(DnsError::Timeout),
+        None => Err(DnsError::Io(std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            "the transcript has no recorded response to this query",
+        ))),
+    }
+}
+
+/// Sends one attempt of a query to `server` and waits up to `timeout` for the reply.
+///
+/// Returns the socket, which stays connected to `server` so that duplicate responses
+/// can still be collected from it, along with the received datagram.
+pub(crate) fn exchange(
+    query_buffer: &[u8],
+    server: Ipv4Addr,
+    timeout: Duration,
+    options: &QueryOptions,
+) -> Result<(UdpSocket, Vec<u8>), DnsError> {
+    // The DNS server port is standardized to 53 per RFC 1035, unless the caller
+    // points us elsewhere.
+    let server_address = (server, options.port.unwrap_or(53));
+
+    // Bind a UDP socket to an available local port.
+    // Without a configured source address, "0.0.0.0:0" allows the OS to choose an
+    // appropriate interface and ephemeral port.
+    let local_address =
+        SocketAddrV4::new(options.source_address.unwrap_or(Ipv4Addr::UNSPECIFIED), 0);
+    let socket = UdpSocket::bind(local_address)?;
+
+    // Pin the socket to a specific device when requested, so the query leaves
+    // through that interface regardless of the routing table.
+    if let Some(interface) = &options.interface {
+        bind_to_device(&socket, interface)?;
+    }
+
+    // Connect the socket to the server so the kernel associates ICMP errors with it.
+    // If nothing is listening on the server's port, the resulting ICMP port unreachable
+    // is reported as `ConnectionRefused` on the next receive instead of the query
+    // sitting through the full timeout. It also drops datagrams from any other source.
+    socket.connect(server_address)?;
+
+    // Set a read timeout to prevent indefinite blocking on unresponsive servers.
+    // The retry policy decides how long each attempt may take.
+    socket.set_read_timeout(Some(timeout))?;
+
+    // Transmit the serialized DNS query to the connected server.
+    socket.send(query_buffer)?;
+
+    let mut response_buffer = vec![0; udp_buffer_size(options)];
+
+    // Wait for the server's response, handling timeout and other I/O errors appropriately.
+    // A `ConnectionRefused` here means the server answered with ICMP port unreachable,
+    // and is passed through as `DnsError::Io` so callers can fail over right away.
+    // Datagrams whose ID does not match the query are not answers to it: they are
+    // discarded, and the wait goes on until the deadline, so a forged response that
+    // guesses wrong cannot cut the exchange short.
+    let deadline = Instant::now() + timeout;
+    loop {
+        let size = socket.recv(&mut response_buffer).map_err(timeout_error)?;
+        if same_id(query_buffer, &response_buffer[..size]) {
+            return Ok((socket, response_buffer[..size].to_vec()));
+        }
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(DnsError::Timeout);
+        }
+        socket.set_read_timeout(Some(remaining))?;
+    }
+}
+
+/// Returns the size of buffer a UDP response can need: 512 bytes (RFC 1035), unless
+/// the query advertised a larger payload size with EDNS (RFC 6891).
+fn udp_buffer_size(options: &QueryOptions) -> usize {
+    options.udp_payload_size().map_or(512, |size| size.max(512)) as usize
+}
+
+/// Returns whether `response` carries the transaction ID of `query`.
+fn same_id(query: &[u8], response: &[u8]) -> bool {
+    response.len() >= 2 && query.get(..2) == response.get(..2)
+}
+
+/// Sends one attempt of a query to `server` over TCP and waits up to `timeout` for
+/// the reply.
+///
+/// Used when [`QueryOptions::tcp`] is set or a UDP response comes back truncated.
+/// The connection leaves from [`QueryOptions::source_address`] and
+/// [`QueryOptions::interface`] like a UDP query does (see [`connect_tcp`]).
+pub(crate) fn exchange_tcp(
+    query_buffer: &[u8],
+    server: Ipv4Addr,
+    timeout: Duration,
+    options: &QueryOptions,
+) -> Result<Vec<u8>, DnsError> {
+    let server_address = SocketAddrV4::new(server, options.port.unwrap_or(53));
+    let stream = connect_tcp(server_address, timeout, options)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    exchange_stream(stream, query_buffer)
+}
+
+/// Opens a TCP connection to `server_address`, waiting up to `timeout` for it to be
+/// established.
+///
+/// With [`QueryOptions::source_address`] or [`QueryOptions::interface`] set, the
+/// socket is bound before it connects. The standard library cannot do that, so on
+/// Linux the socket is created through `socket(2)` directly.
+///
+/// # Errors
+///
+/// Returns [`DnsError::Timeout`] if the connection is not established in time, and
+/// an [`ErrorKind::Unsupported`](std::io::ErrorKind::Unsupported) I/O error when a
+/// source address or interface is set on a platform other than Linux, rather than
+/// let the query leave from somewhere the caller did not ask for.
+pub(crate) fn connect_tcp(
+    server_address: SocketAddrV4,
+    timeout: Duration,
+    options: &QueryOptions,
+) -> Result<TcpStream, DnsError> {
+    if options.source_address.is_none() && options.interface.is_none() {
+        return TcpStream::connect_timeout(&SocketAddr::V4(server_address), timeout)
+            .map_err(timeout_error);
+    }
+    connect_bound_tcp(server_address, timeout, options)
+}
+
+/// Creates a TCP socket, binds it to the configured source address and interface,
+/// and connects it to `server_address`.
+#[cfg(target_os = "linux")]
+fn connect_bound_tcp(
+    server_address: SocketAddrV4,
+    timeout: Duration,
+    options: &QueryOptions,
+) -> Result<TcpStream, DnsError> {
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use std::os::raw::{c_int, c_long, c_void};
+
+    const AF_INET: c_int = 2;
+    const SOCK_STREAM: c_int = 1;
+    const SOCK_CLOEXEC: c_int = 0o2000000;
+    const SO_SNDTIMEO: c_int = 21;
+    const EINPROGRESS: i32 = 115;
+
+    #[repr(C)]
+    struct SockaddrIn {
+        family: u16,
+        port: [u8; 2],
+        address: [u8; 4],
+        zero: [u8; 8],
+    }
+
+    #[repr(C)]
+    struct Timeval {
+        seconds: c_long,
+        microseconds: c_long,
+    }
+
+    unsafe extern "C" {
+        fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+        fn bind(socket: c_int, address: *const SockaddrIn, address_len: u32) -> c_int;
+        fn connect(socket: c_int, address: *const SockaddrIn, address_len: u32) -> c_int;
+    }
+
+    let sockaddr = |address: &SocketAddrV4| SockaddrIn {
+        family: AF_INET as u16,
+        port: address.port().to_be_bytes(),
+        address: address.ip().octets(),
+        zero: [0; 8],
+    };
+    let check = |result: c_int| {
+        if result < 0 {
+            Err(std::io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    };
+
+    // SAFETY: `socket` takes no pointers, and a non-negative result is a new file
+    // descriptor that nothing else owns, so `OwnedFd` can take it over.
+    let fd =
+        unsafe { OwnedFd::from_raw_fd(check(socket(AF_INET, SOCK_STREAM | SOCK_CLOEXEC, 0))?) };
+
+    if let Some(interface) = &options.interface {
+        bind_to_device(&fd, interface)?;
+    }
+    let local_address =
+        SocketAddrV4::new(options.source_address.unwrap_or(Ipv4Addr::UNSPECIFIED), 0);
+    // SAFETY: the descriptor stays open for the call, and the pointer and length
+    // describe a `sockaddr_in` that lives until it returns.
+    check(unsafe {
+        bind(
+            fd.as_raw_fd(),
+            &sockaddr(&local_address),
+            size_of::<SockaddrIn>() as u32,
+        )
+    })?;
+
+    // A blocking connect gives up after the send timeout, with EINPROGRESS.
+    let send_timeout = Timeval {
+        seconds: timeout.as_secs() as c_long,
+        microseconds: timeout.subsec_micros() as c_long,
+    };
+    // SAFETY: as above, for a `timeval` the kernel only reads.
+    check(unsafe {
+        setsockopt(
+            fd.as_raw_fd(),
+            SOL_SOCKET,
+            SO_SNDTIMEO,
+            &send_timeout as *const Timeval as *const c_void,
+            size_of::<Timeval>() as u32,
+        )
+    })?;
+    // SAFETY: as for `bind`.
+    let connected = check(unsafe {
+        connect(
+            fd.as_raw_fd(),
+            &sockaddr(&server_address),
+            size_of::<SockaddrIn>() as u32,
+        )
+    });
+    match connected {
+        Ok(_) => Ok(TcpStream::from(fd)),
+        Err(e) if e.raw_os_error() == Some(EINPROGRESS) => Err(DnsError::Timeout),
+        Err(e) => Err(timeout_error(e)),
+    }
+}
+
+/// Binding a TCP socket before it connects is only implemented on Linux.
+#[cfg(not(target_os = "linux"))]
+fn connect_bound_tcp(
+    _server_address: SocketAddrV4,
+    _timeout: Duration,
+    _options: &QueryOptions,
+) -> Result<TcpStream, DnsError> {
+    Err(DnsError::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "TCP queries cannot be bound to a source address or interface on this platform",
+    )))
+}
+
+/// Sends one attempt of a query over the Unix domain socket at `path` and waits up
+/// to `timeout` for the reply, using TCP-style length-prefixed framing.
+#[cfg(unix)]
+fn exchange_unix(query_buffer: &[u8], path: &Path, timeout: Duration) -> Result<Vec<u8>, DnsError> {
+    use std::os::unix::net::UnixStream;
+
+    let stream = UnixStream::connect(path)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    exchange_stream(stream, query_buffer)
+}
+
+/// Writes a query to a connected stream and reads one response, each prefixed with
+/// its length as two bytes in network order (RFC 1035, section 4.2.2).
+fn exchange_stream(
+    mut stream: impl Read + Write,
+    query_buffer: &[u8],
+) -> Result<Vec<u8>, DnsError> {
+    write_framed(&mut stream, query_buffer)?;
+    let response_buffer = read_framed(&mut stream)?;
+
+    // Nothing can be injected into a stream off-path, so a mismatched ID means the
+    // server is broken rather than under attack.
+    if !same_id(query_buffer, &response_buffer) {
+        return Err(DnsError::InvalidResponse(
+            "response ID does not match the query".to_string(),
+        ));
+    }
+    Ok(response_buffer)
+}
+
+/// Writes one message to a stream, prefixed with its length as two bytes in
+/// network order (RFC 1035, section 4.2.2).
+pub(crate) fn write_framed(stream: &mut impl Write, message: &[u8]) -> Result<(), DnsError> {
+    // Prefix the message with its length; a DNS message never exceeds 65535 bytes.
+    let length = u16::try_from(message.len())
+        .map_err(|_| DnsError::InvalidResponse("query too large for stream framing".into()))?;
+    let mut framed = Vec::with_capacity(message.len() + 2);
+    framed.extend_from_slice(&length.to_be_bytes());
+    framed.extend_from_slice(message);
+    stream.write_all(&framed).map_err(timeout_error)
+}
+
+/// Reads one length-prefixed message from a stream.
+pub(crate) fn read_framed(stream: &mut impl Read) -> Result<Vec<u8>, DnsError> {
+    // Read the two-byte length, then exactly that many bytes of message.
+    let mut length = [0; 2];
+    stream.read_exact(&mut length).map_err(timeout_error)?;
+    let mut buffer = vec![0; u16::from_be_bytes(length) as usize];
+    stream.read_exact(&mut buffer).map_err(timeout_error)?;
+    Ok(buffer)
+}
+
+/// Returns whether a raw response has the TC bit set. A response too short to hold
+/// a header is left for the parser to reject.
+fn is_truncated(response: &[u8]) -> bool {
+    DnsHeader::from_bytes(&mut Cursor::new(response)).is_ok_and(|header| header.truncated())
+}
+
+/// Unix domain sockets are only available on Unix platforms.
+#[cfg(not(unix))]
+fn exchange_unix(
+    _query_buffer: &[u8],
+    path: &Path,
+    _timeout: Duration,
+) -> Result<Vec<u8>, DnsError> {
+    Err(DnsError::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "Unix domain socket '{}' is not supported on this platform",
+            path.display()
+        ),
+    )))
+}
+
+/// Converts an I/O error from a socket read into a DNS error, reporting an expired
+/// read timeout as [`DnsError::Timeout`].
+pub(crate) fn timeout_error(e: std::io::Error) -> DnsError {
+    if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut {
+        DnsError::Timeout
+    } else {
+        DnsError::Io(e)
+    }
+}
+
+/// Listens on `socket` for `window` and reports every further response whose
+/// transaction ID matches the accepted `response`.
+///
+/// Datagrams with a different ID belong to another transaction and are
+/// ignored, as are datagrams that fail to parse.
+fn collect_duplicates(
+    socket: &UdpSocket,
+    response: &DnsMessage,
+    window: Duration,
+    buffer_size: usize,
+) -> Result<Vec<DuplicateResponse>, DnsError> {
+    let accepted_at = Instant::now();
+    let deadline = accepted_at + window;
+    let mut duplicates = Vec::new();
+    let mut buffer = vec![0; buffer_size];
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(remaining))?;
+
+        let (size, source) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e)
+                if e.kind() == std::io::ErrorKind::WouldBlock
+                    || e.kind() == std::io::ErrorKind::TimedOut =>
+            {
+                break;
+            }
+            // A late socket error (such as a stray ICMP message) must not discard
+            // the response that was already accepted, so just stop listening.
+            Err(_) => break,
+        };
+        let delay = accepted_at.elapsed();
+
+        let Ok(duplicate) = DnsMessage::from_bytes(&buffer[..size]) else {
+            continue;
+        };
+        if duplicate.header.id != response.header.id {
+            continue;
+        }
+
+        duplicates.push(compare_responses(response, &duplicate, source, delay));
+    }
+
+    Ok(duplicates)
+}
+
+/// Compares a duplicate response against the accepted one.
+///
+/// Answer records are compared by their presentation form, so records are
+/// considered equal when name, TTL, type and data all match.
+fn compare_responses(
+    accepted: &DnsMessage,
+    duplicate: &DnsMessage,
+    source: SocketAddr,
+    delay: Duration,
+) -> DuplicateResponse {
+    let accepted_set: HashSet<String> = accepted.answers.iter().map(|r| r.to_string()).collect();
+    let duplicate_set: HashSet<String> = duplicate.answers.iter().map(|r| r.to_string()).collect();
+
+    let only_in_accepted: Vec<ResourceRecord> = accepted
+        .answers
+        .iter()
+        .filter(|r| !duplicate_set.contains(&r.to_string()))
+        .cloned()
+        .collect();
+    let only_in_duplicate: Vec<ResourceRecord> = duplicate
+        .answers
+        .iter()
+        .filter(|r| !accepted_set.contains(&r.to_string()))
+        .cloned()
+        .collect();
+
+    let response_code = duplicate.header.get_response_code();
+    let conflicting = response_code != accepted.header.get_response_code()
+        || !only_in_accepted.is_empty()
+        || !only_in_duplicate.is_empty();
+
+    DuplicateResponse {
+        source,
+        delay,
+        response_code,
+        only_in_accepted,
+        only_in_duplicate,
+        conflicting,
+    }
+}
+
+#[cfg(target_os = "linux")]
+const SOL_SOCKET: std::os::raw::c_int = 1;
+
+#[cfg(target_os = "linux")]
+unsafe extern "C" {
+    fn setsockopt(
+        socket: std::os::raw::c_int,
+        level: std::os::raw::c_int,
+        name: std::os::raw::c_int,
+        value: *const std::os::raw::c_void,
+        option_len: u32,
+    ) -> std::os::raw::c_int;
+}
+
+/// Binds a socket to a network device using `SO_BINDTODEVICE`.
+///
+/// The standard library has no API for this, so the option is set directly
+/// through `setsockopt(2)`.
+#[cfg(target_os = "linux")]
+fn bind_to_device(socket: &impl std::os::fd::AsRawFd, interface: &str) -> Result<(), DnsError> {
+    use std::os::raw::{c_int, c_void};
+
+    const SO_BINDTODEVICE: c_int = 25;
+
+    // SAFETY: the file descriptor is owned by `socket` and stays open for the
+    // duration of the call, and the pointer/length pair describes the bytes of
+    // `interface`, which the kernel only reads.
+    let result = unsafe {
+        setsockopt(
+            socket.as_raw_fd(),
+            SOL_SOCKET,
+            SO_BINDTODEVICE,
+            interface.as_ptr() as *const c_void,
+            interface.len() as u32,
+        )
+    };
+
+    if result != 0 {
+        return Err(DnsError::Io(std::io::Error::last_os_error()));
+    }
+    Ok(())
+}
+
+/// Binding to a device is only implemented on Linux.
+#[cfg(not(target_os = "linux"))]
+fn bind_to_device<S>(_socket: &S, interface: &str) -> Result<(), DnsError> {
+    Err(DnsError::Io(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        format!(
+            "binding to interface '{}' is not supported on this platform",
+            interface
+        ),
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::RData;
+    use crate::testutil::{StubServer, a_record};
+    use crate::transport::{Reply, Transport};
+
+    fn response(rcode: u16, addresses: &[Ipv4Addr]) -> DnsMessage {
+        let mut message = DnsMessage::new();
+        message.header.id = 1234;
+        message.header.flags = 0x8180 | rcode;
+        for addr in addresses {
+            message.answers.push(a_record("example.com", *addr, 300));
+        }
+        message
+    }
+
+    /// Starts a server on a Unix socket that answers every query by echoing it
+    /// back with the response bits set, one thread per connection.
+    #[cfg(unix)]
+    fn start_echo_server(tag: &str) -> PathBuf {
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixListener;
+
+        let path =
+            std::env::temp_dir().join(format!("dns-resolver-{}-{}.sock", tag, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                std::thread::spawn(move || {
+                    let mut length = [0; 2];
+                    stream.read_exact(&mut length).unwrap();
+                    let mut message = vec![0; u16::from_be_bytes(length) as usize];
+                    stream.read_exact(&mut message).unwrap();
+                    message[2..4].copy_from_slice(&[0x81, 0x80]);
+                    stream.write_all(&length).unwrap();
+                    stream.write_all(&message).unwrap();
+                });
+            }
+        });
+        path
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_query_over_unix_socket() {
+        let options = QueryOptions {
+            unix_socket: Some(start_echo_server("query")),
+            duplicate_window: Some(Duration::from_millis(10)),
+            ..QueryOptions::default()
+        };
+        let result = query("example.com", QueryType::MX, Ipv4Addr::LOCALHOST, &options).unwrap();
+
+        assert_eq!(
+            result.message.header.get_response_code(),
+            ResponseCode::NoError
+        );
+        assert_eq!(result.message.questions[0].qtype, QueryType::MX);
+        assert!(result.duplicates.is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_raw_edns_options_are_sent() {
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::LOCALHOST)
+            .unix_socket(start_echo_server("edns-options"))
+            .edns_option(65001, [0xbe, 0xef])
+            .build()
+            .unwrap();
+        // The echo server sends the query back, OPT record and all.
+        let result = resolver.query("example.com", QueryType::A).unwrap();
+        let edns = result.message.edns().unwrap();
+        assert_eq!(edns.udp_payload_size, DNSSEC_UDP_PAYLOAD_SIZE);
+        assert_eq!(
+            edns.options,
+            [EdnsOption {
+                code: 65001,
+                data: vec![0xbe, 0xef],
+            }]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_recorded_transcript_replays_without_server() {
+        let path = std::env::temp_dir().join(format!(
+            "dns-resolver-replay-{}.transcript",
+            std::process::id()
+        ));
+        let recording = QueryOptions {
+            unix_socket: Some(start_echo_server("record")),
+            transcript: Some(Arc::new(Transcript::record(&path).unwrap())),
+            ..QueryOptions::default()
+        };
+        let recorded = query(
+            "example.com",
+            QueryType::TXT,
+            Ipv4Addr::LOCALHOST,
+            &recording,
+        );
+        assert!(recorded.is_ok());
+
+        // The socket path is never used when replaying.
+        let replaying = QueryOptions {
+            unix_socket: Some(PathBuf::from("/nonexistent/dns.sock")),
+            transcript: Some(Arc::new(Transcript::replay(&path).unwrap())),
+            ..QueryOptions::default()
+        };
+        std::fs::remove_file(&path).unwrap();
+        let replayed = query(
+            "example.com",
+            QueryType::TXT,
+            Ipv4Addr::LOCALHOST,
+            &replaying,
+        )
+        .unwrap();
+        assert_eq!(replayed.message.questions[0].qtype, QueryType::TXT);
+        assert_ne!(
+            replayed.message.header.id,
+            recorded.unwrap().message.header.id
+        );
+
+        let missing = query(
+            "example.com",
+            QueryType::MX,
+            Ipv4Addr::LOCALHOST,
+            &replaying,
+        );
+        assert_eq!(missing.unwrap_err().kind(), DnsErrorKind::Configuration);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolver_is_shareable_across_threads() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Resolver>();
+
+        let resolver = Resolver::new(
+            Ipv4Addr::LOCALHOST,
+            QueryOptions {
+                unix_socket: Some(start_echo_server("stress")),
+                ..QueryOptions::default()
+            },
+        );
+        let ids: Vec<u16> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..16)
+                .map(|thread| {
+                    let resolver = resolver.clone();
+                    scope.spawn(move || {
+                        (0..8)
+                            .map(|i| {
+                                let name = format!("host{}-{}.example.com", thread, i);
+                                let result = resolver.query(&name, QueryType::A).unwrap();
+                                // Each thread gets the answer to its own question.
+                                assert_eq!(result.message.questions[0].name, name);
+                                result.message.header.id
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+        // IDs are drawn at random rather than counted up: a repeat among 128 is
+        // possible, but many repeats or a run of consecutive IDs is not.
+        assert!(ids.iter().collect::<HashSet<_>>().len() >= 120);
+        let consecutive = ids
+            .windows(2)
+            .filter(|pair| pair[1] == pair[0].wrapping_add(1))
+            .count();
+        assert!(consecutive < 8, "{} consecutive IDs", consecutive);
+    }
+
+    #[test]
+    fn test_resolver_builder() {
+        let primary = StubServer::start(vec![Reply::Drop]).unwrap();
+        let alternate_address = Ipv4Addr::new(127, 0, 0, 3);
+        let alternate = StubServer::start_on(
+            SocketAddrV4::new(alternate_address, primary.address().port()),
+            vec![Reply::Answer(Vec::new())],
+        )
+        .unwrap();
+
+        // With two servers, the unanswered attempt moves on to the second.
+        let resolver = Resolver::builder()
+            .servers([Ipv4Addr::LOCALHOST, alternate_address])
+            .port(primary.address().port())
+            .timeout(Duration::from_millis(100))
+            .edns(1232)
+            .build()
+            .unwrap();
+        assert_eq!(
+            resolver.options().alternate_servers,
+            vec![alternate_address]
+        );
+        assert_eq!(resolver.options().retry.switch_server_after, Some(1));
+        let result = resolver.query("example.com", QueryType::A).unwrap();
+        assert_eq!(result.server, alternate_address);
+        assert_eq!((primary.queries().len(), alternate.queries().len()), (1, 1));
+
+        // An explicit number of attempts wins over one per server.
+        let resolver = Resolver::builder()
+            .servers([Ipv4Addr::LOCALHOST, alternate_address])
+            .attempts(5)
+            .build()
+            .unwrap();
+        assert_eq!(resolver.options().retry.max_attempts, 5);
+
+        assert!(Resolver::builder().build().is_err());
+        assert!(
+            Resolver::builder()
+                .server(Ipv4Addr::LOCALHOST)
+                .tcp(true)
+                .unix_socket("/run/dns.sock")
+                .build()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_error_classification() {
+        use std::io::{Error, ErrorKind};
+
+        assert_eq!(DnsError::Timeout.kind(), DnsErrorKind::Transient);
+        assert_eq!(
+            DnsError::Io(Error::from(ErrorKind::ConnectionRefused)).kind(),
+            DnsErrorKind::Transient
+        );
+        assert_eq!(
+            DnsError::Io(Error::from(ErrorKind::AddrNotAvailable)).kind(),
+            DnsErrorKind::Configuration
+        );
+        assert_eq!(
+            DnsError::ServerReturnedError(ResponseCode::Refused).kind(),
+            DnsErrorKind::UpstreamFailure
+        );
+        assert_eq!(
+            DnsError::ServerReturnedError(ResponseCode::NameError).kind(),
+            DnsErrorKind::Permanent
+        );
+        assert_eq!(
+            DnsError::InvalidResponse("truncated".into()).kind(),
+            DnsErrorKind::Protocol
+        );
+        assert_eq!(
+            DnsError::InvalidName("empty label".into()).kind(),
+            DnsErrorKind::Configuration
+        );
+        assert!(DnsError::ServerReturnedError(ResponseCode::ServerFailure).is_retryable());
+        assert!(!DnsError::ProtocolViolations(Vec::new()).is_retryable());
+    }
+
+    #[test]
+    fn test_identical_duplicate_is_not_conflicting() {
+        let accepted = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]);
+        let duplicate = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]);
+        let source = "192.0.2.53:53".parse().unwrap();
+
+        let report = compare_responses(&accepted, &duplicate, source, Duration::from_millis(3));
+        assert!(!report.conflicting);
+        assert!(report.only_in_accepted.is_empty());
+        assert!(report.only_in_duplicate.is_empty());
+    }
+
+    #[test]
+    fn test_differing_duplicate_reports_rdata() {
+        let accepted = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]);
+        let duplicate = response(0, &[Ipv4Addr::new(203, 0, 113, 66)]);
+        let source = "192.0.2.53:53".parse().unwrap();
+
+        let report = compare_responses(&accepted, &duplicate, source, Duration::from_millis(3));
+        assert!(report.conflicting);
+        assert_eq!(
+            report.only_in_accepted[0].get_ipv4_address(),
+            Some(Ipv4Addr::new(192, 0, 2, 1))
+        );
+        assert_eq!(
+            report.only_in_duplicate[0].get_ipv4_address(),
+            Some(Ipv4Addr::new(203, 0, 113, 66))
+        );
+    }
+
+    #[test]
+    fn test_differing_rcode_is_conflicting() {
+        let accepted = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]);
+        let duplicate = response(3, &[]);
+        let source = "192.0.2.53:53".parse().unwrap();
+
+        let report = compare_responses(&accepted, &duplicate, source, Duration::from_millis(3));
+        assert!(report.conflicting);
+        assert_eq!(report.response_code, ResponseCode::NameError);
+    }
+
+    /// Options for quick retries against a stub server: `attempts` attempts of
+    /// 100 ms each.
+    fn stub_options(server: &StubServer, attempts: u32) -> QueryOptions {
+        QueryOptions {
+            retry: RetryPolicy {
+                initial_timeout: Duration::from_millis(100),
+                multiplier: 1.0,
+                max_attempts: attempts,
+                ..RetryPolicy::default()
+            },
+            ..server.query_options()
+        }
+    }
+
+    #[test]
+    fn test_unanswered_queries_time_out() {
+        let server = StubServer::start(vec![Reply::Delayed(
+            Duration::from_millis(500),
+            Box::new(Reply::Answer(Vec::new())),
+        )])
+        .unwrap();
+        let result = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&server, 2),
+        );
+        assert!(matches!(result, Err(DnsError::Timeout)));
+        assert_eq!(server.queries(), vec![Transport::Udp; 2]);
+    }
+
+    #[test]
+    fn test_edns_rejection_falls_back_to_plain_dns() {
+        let answer = vec![a_record("example.com", [192, 0, 2, 1], 300)];
+        let plain_server = StubServer::start(vec![Reply::Answer(answer.clone())]).unwrap();
+        let plain = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&plain_server, 1),
+        )
+        .unwrap();
+        for code in [ResponseCode::FormatError, ResponseCode::NotImplemented] {
+            let server =
+                StubServer::start(vec![Reply::Code(code), Reply::Answer(answer.clone())]).unwrap();
+            let resolver = Resolver::new(
+                Ipv4Addr::LOCALHOST,
+                QueryOptions {
+                    edns: Some(1232),
+                    ..stub_options(&server, 1)
+                },
+            );
+
+            // The rejected query is sent again without its OPT record.
+            let result = resolver.query("example.com", QueryType::A).unwrap();
+            assert_eq!(result.message.answers.len(), 1);
+            assert_eq!(result.request_size, plain.request_size);
+            assert_eq!(server.queries().len(), 2);
+
+            // The server is remembered, so the next query goes without one at once.
+            let downgrades = resolver.options().edns_downgrades.as_ref().unwrap();
+            assert!(downgrades.contains(Ipv4Addr::LOCALHOST));
+            let result = resolver.query("example.net", QueryType::A).unwrap();
+            assert_eq!(result.request_size, plain.request_size);
+            assert_eq!(server.queries().len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_dropped_query_is_retransmitted() {
+        let answer = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers;
+        let server = StubServer::start(vec![Reply::Drop, Reply::Answer(answer)]).unwrap();
+        let result = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&server, 2),
+        )
+        .unwrap();
+        assert_eq!(result.message.answers.len(), 1);
+        assert_eq!(server.queries().len(), 2);
+    }
+
+    #[test]
+    fn test_failover_to_alternate_server() {
+        let primary = StubServer::start(vec![Reply::Drop]).unwrap();
+        let alternate_address = Ipv4Addr::new(127, 0, 0, 2);
+        let alternate = StubServer::start_on(
+            SocketAddrV4::new(alternate_address, primary.address().port()),
+            vec![Reply::Answer(Vec::new())],
+        )
+        .unwrap();
+        let mut options = stub_options(&primary, 2);
+        options.retry.switch_server_after = Some(1);
+        options.alternate_servers = vec![alternate_address];
+
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.server, alternate_address);
+        assert_eq!(primary.queries().len(), 1);
+        assert_eq!(alternate.queries().len(), 1);
+    }
+
+    #[test]
+    fn test_responses_with_another_id_are_ignored() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (size, peer) = server.recv_from(&mut buffer).unwrap();
+            let mut response = buffer[..size].to_vec();
+            response[2..4].copy_from_slice(&[0x81, 0x80]);
+            // A forged response that guessed the wrong ID, then the real one.
+            let mut forged = response.clone();
+            forged[0] ^= 0xFF;
+            server.send_to(&forged, peer).unwrap();
+            server.send_to(&response, peer).unwrap();
+        });
+
+        let options = QueryOptions {
+            port: Some(port),
+            ..QueryOptions::default()
+        };
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.questions[0].name, "example.com");
+
+        // A datagram too short to carry the ID is no answer either.
+        let server = StubServer::start(vec![Reply::Raw(vec![0x12])]).unwrap();
+        let result = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&server, 1),
+        );
+        assert!(matches!(result, Err(DnsError::Timeout)));
+    }
+
+    #[test]
+    fn test_reverse_lookup() {
+        let ptr = ResourceRecord {
+            name: "1.2.0.192.in-addr.arpa".parse().unwrap(),
+            rtype: QueryType::PTR,
+            rclass: 1,
+            ttl: 300,
+            data: RData::PTR("host.example.com".to_string()),
+        };
+        let server = StubServer::start(vec![
+            Reply::Answer(vec![ptr]),
+            Reply::Code(ResponseCode::NameError),
+        ])
+        .unwrap();
+        let options = stub_options(&server, 1);
+        let addr = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        assert_eq!(
+            reverse_lookup_with_options(addr, Ipv4Addr::LOCALHOST, &options).unwrap(),
+            vec!["host.example.com".to_string()]
+        );
+        // An address without a reverse mapping has no names, which is not an error.
+        assert!(
+            reverse_lookup_with_options(addr, Ipv4Addr::LOCALHOST, &options)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tcp_only_queries() {
+        let answer = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers;
+        let server = StubServer::start(vec![Reply::Answer(answer)]).unwrap();
+        let options = QueryOptions {
+            tcp: true,
+            ..stub_options(&server, 1)
+        };
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.answers.len(), 1);
+        assert_eq!(server.queries(), vec![Transport::Tcp]);
+    }
+
+    #[test]
+    fn test_unicode_names_are_asked_as_a_labels() {
+        let answer = ResourceRecord {
+            name: "xn--bcher-kva.example".parse().unwrap(),
+            ..response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers[0].clone()
+        };
+        let mock = Arc::new(MockTransport::new().answer(
+            "xn--bcher-kva.example",
+            QueryType::A,
+            vec![answer],
+        ));
+        let options = QueryOptions {
+            mock_transport: Some(Arc::clone(&mock)),
+            strict: true,
+            ..QueryOptions::default()
+        };
+        let result = query(
+            "Bücher.example",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(result.message.answers.len(), 1);
+        assert_eq!(mock.queries()[0].name, "xn--bcher-kva.example");
+        let shown = crate::idn::to_unicode_message(&result.message);
+        assert_eq!(shown.answers[0].name, "bücher.example");
+    }
+
+    #[test]
+    fn test_mock_transport_honours_tcp() {
+        let mock = Arc::new(MockTransport::new().reply(
+            "example.com",
+            QueryType::A,
+            Reply::Truncated(vec![a_record("example.com", [192, 0, 2, 1], 300)]),
+        ));
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::LOCALHOST)
+            .mock_transport(Arc::clone(&mock))
+            .tcp(true)
+            .build()
+            .unwrap();
+        let result = resolver.resolve("example.com", QueryType::A).unwrap();
+        assert_eq!(result.answers.len(), 1);
+        assert_eq!(mock.transports(), [Transport::Tcp]);
+    }
+
+    #[test]
+    fn test_unencodable_names_are_invalid_input() {
+        let mock = Arc::new(MockTransport::new());
+        let options = QueryOptions {
+            mock_transport: Some(Arc::clone(&mock)),
+            ..QueryOptions::default()
+        };
+        let long_label = format!("{}.example", "a".repeat(70));
+        for name in ["☃.example", "mail..example", long_label.as_str()] {
+            match query(name, QueryType::A, Ipv4Addr::LOCALHOST, &options) {
+                Err(e @ DnsError::InvalidName(_)) => {
+                    assert_eq!(e.kind(), DnsErrorKind::Configuration)
+                }
+                other => panic!("expected an invalid name error, got {:?}", other),
+            }
+        }
+        assert!(mock.queries().is_empty());
+    }
+
+    #[test]
+    fn test_duplicates_larger_than_512_bytes() {
+        let server = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let port = server.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let mut buffer = [0; 512];
+            let (_, client) = server.recv_from(&mut buffer).unwrap();
+            let addresses: Vec<Ipv4Addr> = (0..40).map(|i| Ipv4Addr::new(192, 0, 2, i)).collect();
+            let mut reply = response(0, &addresses);
+            reply.header.id = u16::from_be_bytes([buffer[0], buffer[1]]);
+            reply.header.answer_count = addresses.len() as u16;
+            let mut bytes = Vec::new();
+            reply.pack(&mut bytes).unwrap();
+            assert!(bytes.len() > 512);
+            reply.answers.pop();
+            reply.header.answer_count -= 1;
+            let mut duplicate = Vec::new();
+            reply.pack(&mut duplicate).unwrap();
+            server.send_to(&bytes, client).unwrap();
+            server.send_to(&duplicate, client).unwrap();
+        });
+        let options = QueryOptions {
+            port: Some(port),
+            edns: Some(1232),
+            duplicate_window: Some(Duration::from_millis(200)),
+            ..QueryOptions::default()
+        };
+
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.answers.len(), 40);
+        assert_eq!(result.duplicates.len(), 1);
+        assert_eq!(result.duplicates[0].only_in_accepted.len(), 1);
+    }
+
+    #[test]
+    fn test_tsig_signed_queries() {
+        let key: TsigKey = "transfer-key:c2VjcmV0LWtleQ==".parse().unwrap();
+        let answer = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers;
+        let server = StubServer::start(vec![
+            Reply::Signed(key.clone(), Box::new(Reply::Answer(answer.clone()))),
+            Reply::Answer(answer),
+        ])
+        .unwrap();
+        let options = QueryOptions {
+            tsig: Some(key),
+            ..stub_options(&server, 1)
+        };
+
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.answers.len(), 1);
+        assert_eq!(result.message.additionals[0].rtype, QueryType::TSIG);
+
+        // The same server answering without a signature is rejected.
+        match query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options) {
+            Err(DnsError::InvalidResponse(message)) => {
+                assert!(message.contains("not signed"), "{}", message)
+            }
+            other => panic!("expected an unsigned response error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_unusable_tsig_key_is_a_configuration_error() {
+        let server = StubServer::start(vec![Reply::Answer(Vec::new())]).unwrap();
+        let mut key: TsigKey = "transfer-key:c2VjcmV0LWtleQ==".parse().unwrap();
+        key.name = "a".repeat(64);
+        let options = QueryOptions {
+            tsig: Some(key),
+            ..stub_options(&server, 1)
+        };
+
+        let error = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap_err();
+        assert_eq!(error.kind(), DnsErrorKind::Configuration);
+        assert!(server.queries().is_empty());
+    }
+
+    #[test]
+    fn test_strict_accepts_tsig_signed_responses() {
+        let key: TsigKey = "transfer-key:c2VjcmV0LWtleQ==".parse().unwrap();
+        let answer = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers;
+        let server = StubServer::start(vec![Reply::Signed(
+            key.clone(),
+            Box::new(Reply::Answer(answer)),
+        )])
+        .unwrap();
+        let options = QueryOptions {
+            strict: true,
+            tsig: Some(key),
+            ..stub_options(&server, 1)
+        };
+
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.answers.len(), 1);
+    }
+
+    #[test]
+    fn test_dnssec_ok_advertises_larger_payload() {
+        // Signed answers rarely fit in 512 bytes, so asking for them must also
+        // make room for them.
+        let addresses: Vec<Ipv4Addr> = (0..40).map(|i| Ipv4Addr::new(192, 0, 2, i)).collect();
+        let answer = response(0, &addresses).answers;
+        let server = StubServer::start(vec![Reply::Answer(answer)]).unwrap();
+        let options = QueryOptions {
+            dnssec_ok: true,
+            ..stub_options(&server, 1)
+        };
+        assert_eq!(options.udp_payload_size(), Some(1232));
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        assert_eq!(result.message.answers.len(), 40);
+        assert_eq!(server.queries(), vec![Transport::Udp]);
+        assert!(result.security.is_none());
+    }
+
+    #[test]
+    fn test_truncated_and_malformed_responses() {
+        // A truncated answer is fetched again, whole, over TCP.
+        let answer = response(0, &[Ipv4Addr::new(192, 0, 2, 1)]).answers;
+        let server = StubServer::start(vec![Reply::Truncated(answer)]).unwrap();
+        let result = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&server, 1),
+        )
+        .unwrap();
+        assert!(!result.message.header.truncated());
+        assert_eq!(result.message.answers.len(), 1);
+        assert_eq!(server.queries(), vec![Transport::Udp, Transport::Tcp]);
+
+        // The fallback leaves from the same source address. Where TCP cannot be
+        // bound, the truncated response is returned as it is.
+        let options = QueryOptions {
+            source_address: Some(Ipv4Addr::LOCALHOST),
+            ..stub_options(&server, 1)
+        };
+        let result = query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options).unwrap();
+        if cfg!(target_os = "linux") {
+            assert!(!result.message.header.truncated());
+            assert_eq!(server.queries()[2..], [Transport::Udp, Transport::Tcp]);
+        } else {
+            assert!(result.message.header.truncated());
+            assert_eq!(server.queries()[2..], [Transport::Udp]);
+        }
+
+        let server = StubServer::start(vec![Reply::Raw(vec![0, 0, 0x81, 0x80, 0, 1])]).unwrap();
+        let result = query(
+            "example.com",
+            QueryType::A,
+            Ipv4Addr::LOCALHOST,
+            &stub_options(&server, 3),
+        );
+        // A malformed response is not retried: it would be malformed again.
+        assert!(matches!(result, Err(DnsError::InvalidResponse(_))));
+        assert_eq!(server.queries().len(), 1);
+    }
 }
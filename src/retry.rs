@@ -0,0 +1,201 @@
+//! Retransmission schedules for UDP queries.
+//!
+//! A UDP query that goes unanswered has to be sent again, and how long to wait before
+//! each retransmission and where to send it make a large difference to tail latency.
+//! [`RetryPolicy`] describes that schedule: an initial timeout that grows by a
+//! multiplier on each attempt, randomized by a jitter fraction so that many clients
+//! do not retry in lockstep, and an optional switch to the next configured server
+//! after a number of attempts.
+//!
+//! # Examples
+//!
+//! Retry once, quickly, against an alternate server before settling on longer waits:
+//!
+//! ```rust
+//! use dns_resolver::resolver::QueryOptions;
+//! use dns_resolver::retry::RetryPolicy;
+//! use std::net::Ipv4Addr;
+//! use std::time::Duration;
+//!
+//! let options = QueryOptions {
+//!     retry: RetryPolicy {
+//!         initial_timeout: Duration::from_millis(400),
+//!         multiplier: 2.0,
+//!         jitter: 0.1,
+//!         max_attempts: 4,
+//!         switch_server_after: Some(1),
+//!     },
+//!     alternate_servers: vec![Ipv4Addr::new(1, 1, 1, 1)],
+//!     ..QueryOptions::default()
+//! };
+//! ```
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// The schedule of attempts for a single query.
+///
+/// Attempts are numbered from zero. The default policy sends a single attempt with a
+/// five second timeout to the primary server, which is how queries behaved before
+/// retries were configurable.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetryPolicy {
+    /// How long to wait for a response to the first attempt.
+    pub initial_timeout: Duration,
+    /// The factor the timeout grows by on each further attempt (at least 1.0).
+    pub multiplier: f64,
+    /// The fraction by which each timeout is randomly lengthened or shortened,
+    /// between 0.0 (no jitter) and 1.0.
+    pub jitter: f64,
+    /// The total number of attempts, including the first (at least one).
+    pub max_attempts: u32,
+    /// Move on to the next server after this many attempts against one server,
+    /// cycling through the primary and alternate servers. `None` keeps every attempt
+    /// on the primary server.
+    pub switch_server_after: Option<u32>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            initial_timeout: Duration::from_secs(5),
+            multiplier: 2.0,
+            jitter: 0.0,
+            max_attempts: 1,
+            switch_server_after: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Returns the timeout for `attempt` before jitter is applied.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::retry::RetryPolicy;
+    /// use std::time::Duration;
+    ///
+    /// let policy = RetryPolicy {
+    ///     initial_timeout: Duration::from_secs(1),
+    ///     multiplier: 2.0,
+    ///     ..RetryPolicy::default()
+    /// };
+    /// assert_eq!(policy.base_timeout(2), Duration::from_secs(4));
+    /// ```
+    pub fn base_timeout(&self, attempt: u32) -> Duration {
+        self.scaled_timeout(attempt, 1.0)
+    }
+
+    /// Returns the timeout for `attempt`, randomized by the jitter fraction.
+    pub fn timeout(&self, attempt: u32) -> Duration {
+        self.jittered_timeout(attempt, random_unit())
+    }
+
+    /// Applies jitter to the base timeout of `attempt` using `random`, a value in
+    /// `[0, 1)`; 0.5 leaves the timeout unchanged.
+    fn jittered_timeout(&self, attempt: u32, random: f64) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // A zero timeout would make the socket block forever, so keep a floor.
+        self.scaled_timeout(attempt, 1.0 + jitter * (2.0 * random - 1.0))
+            .max(Duration::from_millis(1))
+    }
+
+    /// Grows the initial timeout for `attempt` and multiplies it by `scale`,
+    /// saturating instead of overflowing for very long schedules.
+    fn scaled_timeout(&self, attempt: u32, scale: f64) -> Duration {
+        let growth = self.multiplier.max(1.0).powi(attempt.min(64) as i32);
+        Duration::try_from_secs_f64(self.initial_timeout.as_secs_f64() * growth * scale)
+            .unwrap_or(Duration::MAX)
+    }
+
+    /// Returns the number of attempts to make, never less than one.
+    pub fn attempts(&self) -> u32 {
+        self.max_attempts.max(1)
+    }
+
+    /// Returns which of `server_count` servers (the primary first) `attempt` goes to.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::retry::RetryPolicy;
+    ///
+    /// let policy = RetryPolicy {
+    ///     switch_server_after: Some(1),
+    ///     ..RetryPolicy::default()
+    /// };
+    /// assert_eq!(policy.server_index(0, 2), 0);
+    /// assert_eq!(policy.server_index(1, 2), 1);
+    /// assert_eq!(policy.server_index(2, 2), 0);
+    /// ```
+    pub fn server_index(&self, attempt: u32, server_count: usize) -> usize {
+        match self.switch_server_after {
+            Some(per_server) if server_count > 1 => {
+                (attempt / per_server.max(1)) as usize % server_count
+            }
+            _ => 0,
+        }
+    }
+}
+
+/// Returns a random value in `[0, 1)`.
+///
+/// The standard library has no random number generator, but every [`RandomState`] is
+/// seeded with fresh random keys, which is plenty for spreading out retries.
+fn random_unit() -> f64 {
+    let bits = RandomState::new().build_hasher().finish();
+    (bits >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_and_jitter() {
+        let policy = RetryPolicy {
+            initial_timeout: Duration::from_millis(500),
+            multiplier: 3.0,
+            jitter: 0.2,
+            max_attempts: 3,
+            switch_server_after: None,
+        };
+        assert_eq!(policy.base_timeout(0), Duration::from_millis(500));
+        assert_eq!(policy.base_timeout(2), Duration::from_millis(4500));
+        assert_eq!(policy.jittered_timeout(1, 0.5), Duration::from_millis(1500));
+        assert_eq!(policy.jittered_timeout(1, 0.0), Duration::from_millis(1200));
+
+        for _ in 0..100 {
+            let timeout = policy.timeout(0);
+            assert!(timeout >= Duration::from_millis(400) && timeout <= Duration::from_millis(600));
+        }
+    }
+
+    #[test]
+    fn test_server_rotation() {
+        let policy = RetryPolicy {
+            max_attempts: 6,
+            switch_server_after: Some(2),
+            ..RetryPolicy::default()
+        };
+        let servers: Vec<usize> = (0..6).map(|a| policy.server_index(a, 3)).collect();
+        assert_eq!(servers, vec![0, 0, 1, 1, 2, 2]);
+        assert_eq!(policy.server_index(5, 1), 0);
+        assert_eq!(RetryPolicy::default().server_index(3, 3), 0);
+    }
+
+    #[test]
+    fn test_degenerate_settings_are_clamped() {
+        let policy = RetryPolicy {
+            initial_timeout: Duration::ZERO,
+            multiplier: 0.5,
+            max_attempts: 0,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.attempts(), 1);
+        assert_eq!(policy.base_timeout(3), Duration::ZERO);
+        assert!(policy.timeout(0) > Duration::ZERO);
+    }
+}
@@ -0,0 +1,298 @@
+//! Detection of open recursive resolvers.
+//!
+//! An open resolver answers recursive queries for anyone, which makes it a ready-made
+//! amplifier for reflection attacks. Operators usually end up with one by accident,
+//! for example a CPE router or a forgotten lab server, so this module probes a range
+//! of their own addresses with a recursive query for a third-party name and reports
+//! which hosts resolved it.
+//!
+//! To keep the scanner from being pointed at other people's networks, targets must
+//! be IPv4 addresses in private, shared (CGNAT), loopback or link-local space unless
+//! the caller explicitly allows public targets, and scans share the
+//! [`MAX_SWEEP_ADDRESSES`] cap and rate limiting of reverse-DNS sweeps.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::resolver::QueryOptions;
+//! use dns_resolver::scan::{ResolverStatus, ScanOptions, scan_open_resolvers};
+//!
+//! let targets = "192.168.1.0/24".parse().unwrap();
+//! scan_open_resolvers(&targets, &ScanOptions::default(), &QueryOptions::default(), |address, status| {
+//!     if status == ResolverStatus::Open {
+//!         println!("{} is an open resolver", address);
+//!     }
+//! })?;
+//! # Ok::<(), String>(())
+//! ```
+
+use std::fmt;
+use std::io::ErrorKind;
+use std::net::{IpAddr, Ipv4Addr};
+
+use crate::dns::{QueryType, ResponseCode};
+use crate::output::{OutputFormat, csv_field, json_string};
+use crate::resolver::{DnsError, QueryOptions, query};
+use crate::sweep::{Cidr, MAX_SWEEP_ADDRESSES, SweepOptions, for_each_address};
+
+/// The name probed by default: a third-party name no scanned host is authoritative for.
+pub const DEFAULT_PROBE_NAME: &str = "example.com";
+
+/// Controls what a scan sends and where it may send it.
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+    /// The name to ask each target to resolve recursively.
+    pub probe_name: String,
+    /// Allow targets outside private, shared, loopback and link-local address space.
+    ///
+    /// Only enable this for public networks you operate.
+    pub allow_public: bool,
+    /// Concurrency and rate limit for the probes.
+    pub sweep: SweepOptions,
+}
+
+impl Default for ScanOptions {
+    /// Probes [`DEFAULT_PROBE_NAME`] with private targets only, eight probes in flight
+    /// and at most ten probes per second.
+    fn default() -> Self {
+        ScanOptions {
+            probe_name: DEFAULT_PROBE_NAME.to_string(),
+            allow_public: false,
+            sweep: SweepOptions {
+                concurrency: 8,
+                rate_limit: Some(10),
+            },
+        }
+    }
+}
+
+/// How a target responded to the recursive probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolverStatus {
+    /// The target resolved the third-party name: it is an open resolver.
+    Open,
+    /// The target answered but refused the query (REFUSED).
+    Refused,
+    /// The target answered without recursing (RA clear and no answer), as an
+    /// authoritative-only server does.
+    NoRecursion,
+    /// The target answered with another response code, or recursed but returned no
+    /// answer for the probe name.
+    OtherResponse(ResponseCode),
+    /// Nothing answered on port 53 (timeout or ICMP port unreachable).
+    NoResponse,
+    /// The probe failed for another reason, such as a malformed response.
+    Error(String),
+}
+
+impl ResolverStatus {
+    /// Returns `true` if the target sent any DNS response at all.
+    pub fn responded(&self) -> bool {
+        !matches!(self, ResolverStatus::NoResponse)
+    }
+}
+
+impl fmt::Display for ResolverStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResolverStatus::Open => write!(f, "open"),
+            ResolverStatus::Refused => write!(f, "refused"),
+            ResolverStatus::NoRecursion => write!(f, "no-recursion"),
+            ResolverStatus::OtherResponse(code) => write!(f, "response:{:?}", code),
+            ResolverStatus::NoResponse => write!(f, "no-response"),
+            ResolverStatus::Error(e) => write!(f, "error:{}", e),
+        }
+    }
+}
+
+/// Formats one scan result as an output line.
+///
+/// CSV lines match the header `address,status`.
+pub fn format_line(address: Ipv4Addr, status: &ResolverStatus, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => format!("{:<15} {}", address, status),
+        OutputFormat::Csv => format!("{},{}", address, csv_field(&status.to_string())),
+        OutputFormat::Ndjson => format!(
+            "{{\"address\":{},\"status\":{}}}",
+            json_string(&address.to_string()),
+            json_string(&status.to_string())
+        ),
+    }
+}
+
+/// Returns `true` if `addr` may be scanned without `allow_public`.
+///
+/// This covers RFC 1918 private space, RFC 6598 shared address space, loopback
+/// and link-local addresses.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::scan::is_scannable_without_opt_in;
+/// use std::net::Ipv4Addr;
+///
+/// assert!(is_scannable_without_opt_in(Ipv4Addr::new(192, 168, 1, 1)));
+/// assert!(!is_scannable_without_opt_in(Ipv4Addr::new(8, 8, 8, 8)));
+/// ```
+pub fn is_scannable_without_opt_in(addr: Ipv4Addr) -> bool {
+    let octets = addr.octets();
+    let shared = octets[0] == 100 && (octets[1] & 0xC0) == 64;
+    addr.is_private() || addr.is_loopback() || addr.is_link_local() || shared
+}
+
+/// Probes one target and classifies its response.
+///
+/// # Arguments
+///
+/// * `target` - The address of the host to probe
+/// * `probe_name` - The third-party name to ask for
+/// * `options` - Socket options for the query
+pub fn probe_resolver(
+    target: Ipv4Addr,
+    probe_name: &str,
+    options: &QueryOptions,
+) -> ResolverStatus {
+    match query(probe_name, QueryType::A, target, options) {
+        Ok(result) => {
+            let header = &result.message.header;
+            match header.get_response_code() {
+                ResponseCode::Refused => ResolverStatus::Refused,
+                ResponseCode::NoError if !result.message.answers.is_empty() => ResolverStatus::Open,
+                _ if result.recursion_unavailable => ResolverStatus::NoRecursion,
+                code => ResolverStatus::OtherResponse(code),
+            }
+        }
+        Err(DnsError::Timeout) => ResolverStatus::NoResponse,
+        Err(DnsError::Io(e))
+            if matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused | ErrorKind::WouldBlock | ErrorKind::TimedOut
+            ) =>
+        {
+            ResolverStatus::NoResponse
+        }
+        Err(e) => ResolverStatus::Error(e.to_string()),
+    }
+}
+
+/// Probes every address in `targets` for an open resolver.
+///
+/// `on_result` is called on the calling thread once per address, in the order the
+/// probes complete.
+///
+/// # Arguments
+///
+/// * `targets` - The IPv4 prefix to scan
+/// * `scan_options` - Probe name, target restrictions, concurrency and rate limit
+/// * `query_options` - Socket options for each probe
+/// * `on_result` - Receives each address and its status
+///
+/// # Errors
+///
+/// Returns an error without sending any query if `targets` is not IPv4, covers more
+/// than [`MAX_SWEEP_ADDRESSES`] addresses, or includes public addresses while
+/// [`ScanOptions::allow_public`] is unset.
+pub fn scan_open_resolvers<F>(
+    targets: &Cidr,
+    scan_options: &ScanOptions,
+    query_options: &QueryOptions,
+    mut on_result: F,
+) -> Result<(), String>
+where
+    F: FnMut(Ipv4Addr, ResolverStatus),
+{
+    if !targets.network().is_ipv4() {
+        return Err("Open resolver scans only support IPv4 targets".to_string());
+    }
+    if targets.len() > MAX_SWEEP_ADDRESSES {
+        return Err(format!(
+            "{} covers {} addresses; scans are limited to {}",
+            targets,
+            targets.len(),
+            MAX_SWEEP_ADDRESSES
+        ));
+    }
+    if !scan_options.allow_public {
+        let public = (0..targets.len())
+            .map(|index| targets.address(index))
+            .find(|addr| matches!(addr, IpAddr::V4(v4) if !is_scannable_without_opt_in(*v4)));
+        if let Some(addr) = public {
+            return Err(format!(
+                "{} includes the public address {}; only scan networks you operate, \
+                 and confirm this with --allow-public",
+                targets, addr
+            ));
+        }
+    }
+
+    for_each_address(
+        targets,
+        &scan_options.sweep,
+        |address| match address {
+            IpAddr::V4(v4) => probe_resolver(v4, &scan_options.probe_name, query_options),
+            IpAddr::V6(_) => unreachable!("targets were checked to be IPv4"),
+        },
+        |address, status| {
+            if let IpAddr::V4(v4) = address {
+                on_result(v4, status);
+            }
+        },
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_target_restrictions() {
+        assert!(is_scannable_without_opt_in(Ipv4Addr::new(10, 1, 2, 3)));
+        assert!(is_scannable_without_opt_in(Ipv4Addr::new(172, 31, 0, 1)));
+        assert!(is_scannable_without_opt_in(Ipv4Addr::new(100, 127, 0, 1)));
+        assert!(is_scannable_without_opt_in(Ipv4Addr::new(169, 254, 1, 1)));
+        assert!(!is_scannable_without_opt_in(Ipv4Addr::new(100, 128, 0, 1)));
+        assert!(!is_scannable_without_opt_in(Ipv4Addr::new(172, 32, 0, 1)));
+    }
+
+    #[test]
+    fn test_scan_rejects_public_and_ipv6_targets() {
+        let reject = |targets: &str, options: &ScanOptions| {
+            scan_open_resolvers(
+                &targets.parse().unwrap(),
+                options,
+                &QueryOptions::default(),
+                |_, _| panic!("no probes expected"),
+            )
+            .is_err()
+        };
+        let defaults = ScanOptions::default();
+        assert!(reject("8.8.8.0/24", &defaults));
+        // A prefix that straddles the end of private space is rejected as a whole.
+        assert!(reject("172.16.0.0/11", &defaults));
+        assert!(reject("fd00::/120", &defaults));
+        let public = ScanOptions {
+            allow_public: true,
+            ..ScanOptions::default()
+        };
+        assert!(reject("0.0.0.0/8", &public));
+    }
+
+    #[test]
+    fn test_format_line() {
+        let addr = Ipv4Addr::new(192, 168, 1, 1);
+        assert_eq!(
+            format_line(addr, &ResolverStatus::Open, OutputFormat::Csv),
+            "192.168.1.1,open"
+        );
+        assert_eq!(
+            format_line(
+                addr,
+                &ResolverStatus::OtherResponse(ResponseCode::ServerFailure),
+                OutputFormat::Ndjson
+            ),
+            r#"{"address":"192.168.1.1","status":"response:ServerFailure"}"#
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Size and composition statistics of DNS messages on the wire.
+//!
+//! Firewalls, NAT boxes and "DNS helpers" sometimes rewrite responses in transit:
+//! they strip records without fixing the header counts, append padding, or
+//! decompress names and push a message past a size limit. [`MessageStats`] describes
+//! a raw message independently of the regular parser, walking the sections by their
+//! length fields alone, so that these symptoms can be seen even when the record data
+//! itself cannot be decoded.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::stats::MessageStats;
+//!
+//! // A header announcing one answer that is not there.
+//! let data = [0x12, 0x34, 0x81, 0x80, 0, 0, 0, 1, 0, 0, 0, 0];
+//!
+//! let stats = MessageStats::from_bytes(&data);
+//! assert_eq!(stats.size, 12);
+//! assert_eq!(stats.mismatches(), vec!["header announces 1 answer records but 0 are present"]);
+//! ```
+
+use std::fmt;
+use std::io::{Cursor, Read};
+
+use crate::dns::DnsHeader;
+
+/// The sections of a message, in wire order, as named in reports.
+const SECTION_NAMES: [&str; 4] = ["question", "answer", "authority", "additional"];
+
+/// What a message looks like on the wire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MessageStats {
+    /// The size of the message in bytes.
+    pub size: usize,
+    /// The question, answer, authority and additional counts from the header.
+    pub header_counts: [u16; 4],
+    /// How many entries of each section are actually present, in the same order.
+    /// A section stops being counted at the first entry that runs past the end of
+    /// the message.
+    pub section_counts: [u16; 4],
+    /// Bytes left over after the last entry the header announces.
+    pub trailing_bytes: usize,
+    /// Compression pointers found in question and record owner names. Names inside
+    /// record data are not inspected, since their position depends on the type.
+    pub compression_pointers: usize,
+}
+
+impl MessageStats {
+    /// Measures a raw message. Messages too short to hold a header are reported with
+    /// only their size.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let mut stats = MessageStats {
+            size: bytes.len(),
+            ..MessageStats::default()
+        };
+        let mut cursor = Cursor::new(bytes);
+        let Ok(header) = DnsHeader::from_bytes(&mut cursor) else {
+            return stats;
+        };
+        stats.header_counts = [
+            header.question_count,
+            header.answer_count,
+            header.authority_count,
+            header.additional_count,
+        ];
+
+        'sections: for section in 0..4 {
+            for _ in 0..stats.header_counts[section] {
+                let position = cursor.position();
+                let entry = if section == 0 {
+                    skip_question(&mut cursor)
+                } else {
+                    skip_record(&mut cursor)
+                };
+                match entry {
+                    Some(pointers) if cursor.position() <= bytes.len() as u64 => {
+                        stats.section_counts[section] += 1;
+                        stats.compression_pointers += pointers;
+                    }
+                    _ => {
+                        cursor.set_position(position);
+                        break 'sections;
+                    }
+                }
+            }
+        }
+        stats.trailing_bytes = bytes.len() - cursor.position() as usize;
+        stats
+    }
+
+    /// Returns whether any name in the message was compressed.
+    pub fn compressed(&self) -> bool {
+        self.compression_pointers > 0
+    }
+
+    /// Describes each way the message disagrees with its own header: sections with
+    /// fewer entries than announced, and data after the last announced entry.
+    pub fn mismatches(&self) -> Vec<String> {
+        let mut mismatches: Vec<String> = SECTION_NAMES
+            .iter()
+            .zip(self.header_counts.iter().zip(&self.section_counts))
+            .filter(|(_, (announced, present))| announced != present)
+            .map(|(name, (announced, present))| {
+                format!(
+                    "header announces {} {} records but {} are present",
+                    announced, name, present
+                )
+            })
+            .collect();
+        if self.trailing_bytes > 0 {
+            mismatches.push(format!(
+                "{} trailing bytes after the last announced record",
+                self.trailing_bytes
+            ));
+        }
+        mismatches
+    }
+}
+
+impl fmt::Display for MessageStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes, QD/AN/NS/AR {}/{}/{}/{}, compression {}",
+            self.size,
+            self.section_counts[0],
+            self.section_counts[1],
+            self.section_counts[2],
+            self.section_counts[3],
+            if self.compressed() {
+                "used"
+            } else {
+                "not used"
+            }
+        )
+    }
+}
+
+/// Skips a name, returning 1 if it ends in a compression pointer and 0 if not, or
+/// `None` if it runs past the end of the message.
+fn skip_name(cursor: &mut Cursor<&[u8]>) -> Option<usize> {
+    let mut len = [0u8; 1];
+    loop {
+        cursor.read_exact(&mut len).ok()?;
+        match len[0] {
+            0 => return Some(0),
+            // A pointer ends the name in place; where it points does not matter here.
+            len if len & 0xC0 == 0xC0 => {
+                cursor.read_exact(&mut [0u8; 1]).ok()?;
+                return Some(1);
+            }
+            len => cursor.set_position(cursor.position() + u64::from(len)),
+        }
+    }
+}
+
+/// Skips a question entry, returning the compression pointers in its name.
+fn skip_question(cursor: &mut Cursor<&[u8]>) -> Option<usize> {
+    let pointers = skip_name(cursor)?;
+    cursor.read_exact(&mut [0u8; 4]).ok()?;
+    Some(pointers)
+}
+
+/// Skips a resource record, returning the compression pointers in its owner name.
+fn skip_record(cursor: &mut Cursor<&[u8]>) -> Option<usize> {
+    let pointers = skip_name(cursor)?;
+    // TYPE, CLASS and TTL, then RDLENGTH.
+    cursor.read_exact(&mut [0u8; 8]).ok()?;
+    let mut rdlength = [0u8; 2];
+    cursor.read_exact(&mut rdlength).ok()?;
+    cursor.set_position(cursor.position() + u64::from(u16::from_be_bytes(rdlength)));
+    Some(pointers)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::{DnsMessage, DnsQuestion, QueryType};
+    use crate::testutil::a_record;
+
+    fn response() -> Vec<u8> {
+        let mut message = DnsMessage::new();
+        message.header.flags = 0x8180;
+        message.header.question_count = 1;
+        message.header.answer_count = 2;
+        message.questions.push(DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::A,
+            qclass: 1,
+        });
+        for last_octet in [1, 2] {
+            message
+                .answers
+                .push(a_record("example.com", [192, 0, 2, last_octet], 60));
+        }
+        let mut bytes = Vec::new();
+        message.pack(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_well_formed_response() {
+        let bytes = response();
+        let stats = MessageStats::from_bytes(&bytes);
+        assert_eq!(stats.size, bytes.len());
+        assert_eq!(stats.header_counts, [1, 2, 0, 0]);
+        assert_eq!(stats.section_counts, [1, 2, 0, 0]);
+        // Both answers point back at the question name.
+        assert_eq!(stats.compression_pointers, 2);
+        assert!(stats.mismatches().is_empty());
+    }
+
+    #[test]
+    fn test_stripped_and_padded_responses() {
+        let mut bytes = response();
+        // Drop the last answer (a 2-byte pointer and 14 bytes of fixed fields and
+        // address) without fixing the header.
+        bytes.truncate(bytes.len() - 16);
+        let stats = MessageStats::from_bytes(&bytes);
+        assert_eq!(stats.section_counts, [1, 1, 0, 0]);
+        assert_eq!(stats.trailing_bytes, 0);
+        assert_eq!(stats.mismatches().len(), 1);
+
+        let mut bytes = response();
+        bytes.extend_from_slice(&[0, 0, 0]);
+        let stats = MessageStats::from_bytes(&bytes);
+        assert_eq!(
+            stats.mismatches(),
+            vec!["3 trailing bytes after the last announced record"]
+        );
+    }
+}
@@ -0,0 +1,422 @@
+//! Reverse-DNS sweeps over an address range.
+//!
+//! A sweep issues a PTR lookup for every address in a CIDR prefix and reports the
+//! hostnames found, which is how network inventory is usually cross-checked against
+//! what the reverse zones claim. Lookups run on a bounded pool of worker threads and
+//! are paced by a shared rate limit so that a sweep of a large prefix does not flood
+//! the recursive resolver.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::resolver::QueryOptions;
+//! use dns_resolver::sweep::{Cidr, SweepOptions, sweep};
+//! use std::net::Ipv4Addr;
+//!
+//! let cidr: Cidr = "192.0.2.0/28".parse().unwrap();
+//! sweep(
+//!     &cidr,
+//!     Ipv4Addr::new(8, 8, 8, 8),
+//!     &QueryOptions::default(),
+//!     &SweepOptions::default(),
+//!     |result| {
+//!         if let Ok(hostnames) = &result.outcome {
+//!             for hostname in hostnames {
+//!                 println!("{} {}", result.address, hostname);
+//!             }
+//!         }
+//!     },
+//! )?;
+//! # Ok::<(), String>(())
+//! ```
+
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::output::{OutputFormat, csv_field, json_string};
+use crate::resolver::{DnsError, QueryOptions, reverse_lookup_with_options};
+
+/// The largest number of addresses a single sweep will cover (a /16 in IPv4).
+pub const MAX_SWEEP_ADDRESSES: u128 = 65536;
+
+/// An IPv4 or IPv6 prefix in CIDR notation, such as `192.0.2.0/24`.
+///
+/// Host bits are cleared when parsing, so `192.0.2.77/24` covers the same
+/// addresses as `192.0.2.0/24`. A bare address is a single-address prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl Cidr {
+    /// Returns the first address of the prefix.
+    pub fn network(&self) -> IpAddr {
+        self.network
+    }
+
+    /// Returns the prefix length in bits.
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns the number of addresses covered by the prefix.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::sweep::Cidr;
+    ///
+    /// let cidr: Cidr = "192.0.2.0/24".parse().unwrap();
+    /// assert_eq!(cidr.len(), 256);
+    /// ```
+    #[allow(clippy::len_without_is_empty)] // A prefix always covers at least one address
+    pub fn len(&self) -> u128 {
+        let host_bits = self.max_prefix_len() - self.prefix_len;
+        // A full-width IPv6 prefix (::/0) would overflow; saturate instead.
+        1u128.checked_shl(u32::from(host_bits)).unwrap_or(u128::MAX)
+    }
+
+    /// Returns the address at `index` within the prefix, counting from the network address.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not less than [`Cidr::len`].
+    pub fn address(&self, index: u128) -> IpAddr {
+        assert!(index < self.len(), "address index out of range");
+        match self.network {
+            IpAddr::V4(v4) => IpAddr::V4(Ipv4Addr::from(u32::from(v4) + index as u32)),
+            IpAddr::V6(v6) => IpAddr::V6(Ipv6Addr::from(u128::from(v6) + index)),
+        }
+    }
+
+    fn max_prefix_len(&self) -> u8 {
+        match self.network {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (s, None),
+        };
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| format!("Invalid address in prefix '{}'", s))?;
+        let max_prefix_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len
+                .parse::<u8>()
+                .ok()
+                .filter(|len| *len <= max_prefix_len)
+                .ok_or_else(|| format!("Invalid prefix length in '{}'", s))?,
+            None => max_prefix_len,
+        };
+
+        // Clear the host bits so the prefix starts at its network address.
+        let network = match address {
+            IpAddr::V4(v4) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(prefix_len))
+                    .unwrap_or(0);
+                IpAddr::V4(Ipv4Addr::from(u32::from(v4) & mask))
+            }
+            IpAddr::V6(v6) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(prefix_len))
+                    .unwrap_or(0);
+                IpAddr::V6(Ipv6Addr::from(u128::from(v6) & mask))
+            }
+        };
+
+        Ok(Cidr {
+            network,
+            prefix_len,
+        })
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+/// Controls how hard a sweep drives the resolver.
+#[derive(Debug, Clone)]
+pub struct SweepOptions {
+    /// The number of lookups allowed in flight at once (at least one).
+    pub concurrency: usize,
+    /// The maximum number of lookups started per second, or `None` for no limit.
+    pub rate_limit: Option<u32>,
+}
+
+impl Default for SweepOptions {
+    /// Eight concurrent lookups at no more than 20 queries per second, which public
+    /// resolvers tolerate comfortably.
+    fn default() -> Self {
+        SweepOptions {
+            concurrency: 8,
+            rate_limit: Some(20),
+        }
+    }
+}
+
+/// The result of the PTR lookup for one address in a sweep.
+#[derive(Debug)]
+pub struct SweepResult {
+    /// The address that was looked up.
+    pub address: IpAddr,
+    /// The hostnames found, empty if the address has no PTR record, or the
+    /// error that prevented the lookup.
+    pub outcome: Result<Vec<String>, DnsError>,
+}
+
+impl SweepResult {
+    /// Formats the hostnames found for this address, one output line per element.
+    ///
+    /// Addresses without hostnames and failed lookups produce no lines, so that the
+    /// output only lists what was found. CSV output has one row per hostname; the
+    /// header row is given by [`csv_header`].
+    pub fn format_lines(&self, format: OutputFormat) -> Vec<String> {
+        let hostnames = match &self.outcome {
+            Ok(hostnames) if !hostnames.is_empty() => hostnames,
+            _ => return Vec::new(),
+        };
+
+        match format {
+            OutputFormat::Text => vec![format!("{:<39} {}", self.address, hostnames.join(", "))],
+            OutputFormat::Csv => hostnames
+                .iter()
+                .map(|hostname| format!("{},{}", self.address, csv_field(hostname)))
+                .collect(),
+            OutputFormat::Ndjson => {
+                let hostnames: Vec<String> = hostnames.iter().map(|h| json_string(h)).collect();
+                vec![format!(
+                    "{{\"address\":{},\"hostnames\":[{}]}}",
+                    json_string(&self.address.to_string()),
+                    hostnames.join(",")
+                )]
+            }
+        }
+    }
+}
+
+/// Returns the CSV header row matching [`SweepResult::format_lines`].
+pub fn csv_header() -> &'static str {
+    "address,hostname"
+}
+
+/// Looks up the PTR records of every address in `cidr`.
+///
+/// `on_result` is called on the calling thread once per address, in the order the
+/// lookups complete, so results can be written out as the sweep progresses.
+///
+/// # Arguments
+///
+/// * `cidr` - The prefix to sweep
+/// * `dns_server_addr` - The recursive resolver to query
+/// * `query_options` - Socket options for each query
+/// * `sweep_options` - Concurrency and rate limit
+/// * `on_result` - Receives the result for each address
+///
+/// # Errors
+///
+/// Returns an error without sending any query if the prefix covers more than
+/// [`MAX_SWEEP_ADDRESSES`] addresses.
+pub fn sweep<F>(
+    cidr: &Cidr,
+    dns_server_addr: Ipv4Addr,
+    query_options: &QueryOptions,
+    sweep_options: &SweepOptions,
+    mut on_result: F,
+) -> Result<(), String>
+where
+    F: FnMut(SweepResult),
+{
+    if cidr.len() > MAX_SWEEP_ADDRESSES {
+        return Err(format!(
+            "{} covers {} addresses; sweeps are limited to {}",
+            cidr,
+            cidr.len(),
+            MAX_SWEEP_ADDRESSES
+        ));
+    }
+
+    for_each_address(
+        cidr,
+        sweep_options,
+        |address| SweepResult {
+            address,
+            outcome: reverse_lookup_with_options(address, dns_server_addr, query_options),
+        },
+        |_, result| on_result(result),
+    );
+
+    Ok(())
+}
+
+/// Runs `probe` for every address in `cidr` on a pool of worker threads.
+///
+/// The pool size and pacing come from `sweep_options`. `on_result` is called on the
+/// calling thread with each address and its probe result, in completion order. The
+/// caller is responsible for bounding the size of `cidr`.
+pub(crate) fn for_each_address<T, P, F>(
+    cidr: &Cidr,
+    sweep_options: &SweepOptions,
+    probe: P,
+    mut on_result: F,
+) where
+    T: Send,
+    P: Fn(IpAddr) -> T + Sync,
+    F: FnMut(IpAddr, T),
+{
+    let total = cidr.len().min(usize::MAX as u128) as usize;
+    let workers = sweep_options.concurrency.clamp(1, total);
+    let limiter = sweep_options.rate_limit.map(RateLimiter::new);
+    let next_index = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (limiter, next_index, probe) = (&limiter, &next_index, &probe);
+            scope.spawn(move || {
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::Relaxed);
+                    if index >= total {
+                        break;
+                    }
+                    if let Some(limiter) = limiter {
+                        limiter.wait();
+                    }
+                    let address = cidr.address(index as u128);
+                    if sender.send((address, probe(address))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        // Drop our sender so the loop below ends when the last worker finishes.
+        drop(sender);
+
+        for (address, result) in receiver {
+            on_result(address, result);
+        }
+    });
+}
+
+/// Spaces out events evenly so no more than a given number start per second.
+struct RateLimiter {
+    interval: Duration,
+    next_slot: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(per_second: u32) -> Self {
+        RateLimiter {
+            interval: Duration::from_secs(1) / per_second.max(1),
+            next_slot: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Blocks until the caller's turn, reserving the following slot for the next caller.
+    fn wait(&self) {
+        let slot = {
+            let mut next_slot = self.next_slot.lock().unwrap();
+            let slot = (*next_slot).max(Instant::now());
+            *next_slot = slot + self.interval;
+            slot
+        };
+        thread::sleep(slot.saturating_duration_since(Instant::now()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cidr_masks_host_bits() {
+        let cidr: Cidr = "192.0.2.77/24".parse().unwrap();
+        assert_eq!(cidr.to_string(), "192.0.2.0/24");
+        assert_eq!(cidr.len(), 256);
+        assert_eq!(cidr.address(255), "192.0.2.255".parse::<IpAddr>().unwrap());
+
+        let single: Cidr = "2001:db8::1".parse().unwrap();
+        assert_eq!(single.to_string(), "2001:db8::1/128");
+        assert_eq!(single.len(), 1);
+
+        let v6: Cidr = "2001:db8::/126".parse().unwrap();
+        assert_eq!(v6.address(3), "2001:db8::3".parse::<IpAddr>().unwrap());
+        assert_eq!("0.0.0.0/0".parse::<Cidr>().unwrap().len(), 1 << 32);
+    }
+
+    #[test]
+    fn test_parse_cidr_rejects_invalid() {
+        assert!("192.0.2.0/33".parse::<Cidr>().is_err());
+        assert!("192.0.2/24".parse::<Cidr>().is_err());
+        assert!("2001:db8::/abc".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn test_sweep_rejects_oversized_prefix() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        let result = sweep(
+            &cidr,
+            Ipv4Addr::LOCALHOST,
+            &QueryOptions::default(),
+            &SweepOptions::default(),
+            |_| panic!("no lookups expected"),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_format_lines() {
+        let found = SweepResult {
+            address: "192.0.2.1".parse().unwrap(),
+            outcome: Ok(vec![
+                "a.example.com".to_string(),
+                "b.example.com".to_string(),
+            ]),
+        };
+        assert_eq!(
+            found.format_lines(OutputFormat::Csv),
+            vec!["192.0.2.1,a.example.com", "192.0.2.1,b.example.com"]
+        );
+        assert_eq!(
+            found.format_lines(OutputFormat::Ndjson),
+            vec![r#"{"address":"192.0.2.1","hostnames":["a.example.com","b.example.com"]}"#]
+        );
+
+        let missing = SweepResult {
+            address: "192.0.2.2".parse().unwrap(),
+            outcome: Ok(Vec::new()),
+        };
+        assert!(missing.format_lines(OutputFormat::Text).is_empty());
+    }
+
+    #[test]
+    fn test_rate_limiter_spaces_calls() {
+        let limiter = RateLimiter::new(100);
+        let start = Instant::now();
+        for _ in 0..4 {
+            limiter.wait();
+        }
+        // The first call goes immediately; the next three wait 10ms each.
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+}
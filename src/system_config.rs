@@ -0,0 +1,339 @@
+//! Discovering the DNS configuration of the operating system.
+//!
+//! Every platform keeps its resolver settings somewhere else, and only Unix-like
+//! systems write them to `/etc/resolv.conf`. [`system_config`] reads them from
+//! where the OS resolver itself does, so the default server is the one every other
+//! program on the host would ask:
+//!
+//! * **Linux and other Unix-like systems**: `/etc/resolv.conf` (see
+//!   [`crate::resolv_conf`]). If it cannot be read, the servers and search domains
+//!   of systemd-resolved's links (see [`crate::resolved`]).
+//! * **macOS**: the default resolver of the SystemConfiguration framework, as
+//!   printed by `scutil --dns`, falling back to `/etc/resolv.conf`, which macOS
+//!   keeps as a copy for compatibility.
+//! * **Windows**: the TCP/IP parameters in the registry, as printed by
+//!   `reg query`: the statically configured servers of the host and of each
+//!   interface, or those learned over DHCP where none are configured.
+//!
+//! Whatever the source, the result is a [`ResolvConf`], so it is applied to queries
+//! in the same way. Settings a platform has no equivalent for keep their defaults.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::system_config::system_config;
+//!
+//! let resolver = system_config()?.resolver_builder()?.build()?;
+//! let response = resolver.resolve("intranet.corp.example", QueryType::A)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::net::IpAddr;
+#[cfg(any(windows, target_os = "macos"))]
+use std::process::Command;
+use std::time::Duration;
+
+use crate::resolv_conf::{MAX_NAMESERVERS, ResolvConf};
+use crate::resolved::ResolvedConfig;
+
+/// The registry key holding the TCP/IP parameters on Windows.
+#[cfg(windows)]
+const TCPIP_PARAMETERS_KEY: &str = r"HKLM\SYSTEM\CurrentControlSet\Services\Tcpip\Parameters";
+
+/// Reads the DNS configuration the operating system resolver uses.
+///
+/// # Returns
+///
+/// The configured servers, search list and, where the platform has them, resolver
+/// options. See the [module documentation](self) for where each platform keeps
+/// them.
+///
+/// # Errors
+///
+/// Returns an error if the configuration cannot be read from any of the sources
+/// of the platform.
+pub fn system_config() -> Result<ResolvConf, String> {
+    #[cfg(windows)]
+    return windows_config();
+    #[cfg(target_os = "macos")]
+    return macos_config();
+    #[cfg(not(any(windows, target_os = "macos")))]
+    unix_config()
+}
+
+/// Reads `/etc/resolv.conf`, or asks systemd-resolved if there is none.
+#[cfg(not(any(windows, target_os = "macos")))]
+fn unix_config() -> Result<ResolvConf, String> {
+    ResolvConf::load().or_else(|e| {
+        let resolved = ResolvedConfig::load().map_err(|_| e)?;
+        Ok(from_resolved(&resolved))
+    })
+}
+
+/// Reads the default resolver from `scutil`, or `/etc/resolv.conf` if that fails.
+#[cfg(target_os = "macos")]
+fn macos_config() -> Result<ResolvConf, String> {
+    match run("scutil", &["--dns"]).map(|output| parse_scutil(&output)) {
+        Some(conf) if !conf.nameservers.is_empty() => Ok(conf),
+        _ => ResolvConf::load(),
+    }
+}
+
+/// Reads the TCP/IP parameters of the host and its interfaces from the registry.
+#[cfg(windows)]
+fn windows_config() -> Result<ResolvConf, String> {
+    run("reg", &["query", TCPIP_PARAMETERS_KEY, "/s"])
+        .map(|output| parse_reg_query(&output))
+        .ok_or_else(|| format!("cannot read the registry key {}", TCPIP_PARAMETERS_KEY))
+}
+
+/// Runs `program` with `args`, returning its output if it succeeded.
+#[cfg(any(windows, target_os = "macos"))]
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Builds a configuration from the links of systemd-resolved.
+///
+/// Takes the servers of every scope, global first, as resolved does in the
+/// upstream `resolv.conf` it writes, and the search domains of every scope.
+/// Routing-only domains (`~domain`) are not searched and are left out.
+pub fn from_resolved(resolved: &ResolvedConfig) -> ResolvConf {
+    let mut conf = ResolvConf::default();
+    for link in &resolved.links {
+        link.servers
+            .iter()
+            .for_each(|server| add_nameserver(&mut conf, *server));
+        for domain in &link.domains {
+            if !domain.starts_with('~') && !conf.search.contains(domain) {
+                conf.search.push(domain.clone());
+            }
+        }
+    }
+    conf
+}
+
+/// Parses the output of `scutil --dns` on macOS.
+///
+/// The first section lists the resolvers of the system in order. The first one
+/// that is not limited to a `domain` is the default resolver, and its
+/// `nameserver[n]`, `search domain[n]` and `timeout` entries are used. The
+/// resolvers of the later section for scoped queries are ignored.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::system_config::parse_scutil;
+///
+/// let conf = parse_scutil(
+///     "DNS configuration\n\n\
+///      resolver #1\n  search domain[0] : corp.example\n  nameserver[0] : 10.0.0.2\n",
+/// );
+/// assert_eq!(conf.nameservers, ["10.0.0.2".parse::<std::net::IpAddr>().unwrap()]);
+/// assert_eq!(conf.search, ["corp.example"]);
+/// ```
+pub fn parse_scutil(output: &str) -> ResolvConf {
+    let mut resolvers: Vec<Vec<(&str, &str)>> = Vec::new();
+    for line in output.lines() {
+        let line = line.trim();
+        if line.starts_with("DNS configuration (") {
+            break;
+        }
+        if line.starts_with("resolver #") {
+            resolvers.push(Vec::new());
+        } else if let (Some(resolver), Some((key, value))) =
+            (resolvers.last_mut(), line.split_once(" : "))
+        {
+            resolver.push((key.trim(), value.trim()));
+        }
+    }
+
+    let mut conf = ResolvConf::default();
+    let Some(default) = resolvers
+        .iter()
+        .find(|resolver| !resolver.iter().any(|(key, _)| *key == "domain"))
+    else {
+        return conf;
+    };
+    for (key, value) in default {
+        match key.split('[').next() {
+            Some("nameserver") => {
+                // Scoped IPv6 addresses carry their interface after a `%`.
+                if let Some(server) = value.split('%').next().and_then(|v| v.parse().ok()) {
+                    add_nameserver(&mut conf, server);
+                }
+            }
+            Some("search domain") => conf.search.push(value.to_string()),
+            Some("timeout") => {
+                if let Ok(seconds) = value.parse::<u64>() {
+                    conf.timeout = Duration::from_secs(seconds.clamp(1, 30));
+                }
+            }
+            _ => {}
+        }
+    }
+    conf
+}
+
+/// Parses the output of `reg query` for the TCP/IP parameters key on Windows,
+/// queried with `/s` so it includes the key of every interface.
+///
+/// The servers are taken from the host's key first and then from each interface,
+/// using an interface's `NameServer` value where one is configured and its
+/// `DhcpNameServer` value otherwise. Both hold addresses separated by commas or
+/// spaces. The search list is the host's `SearchList`, or else its `Domain` or
+/// `DhcpDomain`.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::system_config::parse_reg_query;
+///
+/// let conf = parse_reg_query(
+///     "HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\{1}\n\
+///      \x20   NameServer    REG_SZ    \n\
+///      \x20   DhcpNameServer    REG_SZ    192.168.1.1 192.168.1.2\n",
+/// );
+/// assert_eq!(conf.nameservers.len(), 2);
+/// ```
+pub fn parse_reg_query(output: &str) -> ResolvConf {
+    // The values of each key, in the order the keys are listed.
+    let mut keys: Vec<Vec<(&str, &str)>> = Vec::new();
+    for line in output.lines() {
+        if line.starts_with("HKEY_") {
+            keys.push(Vec::new());
+        } else if let Some(values) = keys.last_mut() {
+            let mut fields = line.split_whitespace();
+            if let (Some(name), Some(kind)) = (fields.next(), fields.next())
+                && kind.starts_with("REG_")
+            {
+                // Whatever follows the type is the value, which may hold spaces.
+                let value = line.split_once(kind).map_or("", |(_, value)| value.trim());
+                values.push((name, value));
+            }
+        }
+    }
+
+    let mut conf = ResolvConf::default();
+    for values in &keys {
+        let servers = registry_value(values, "NameServer")
+            .or_else(|| registry_value(values, "DhcpNameServer"));
+        servers
+            .into_iter()
+            .flat_map(|servers| servers.split([',', ' ']))
+            .filter_map(|server| server.parse().ok())
+            .for_each(|server| add_nameserver(&mut conf, server));
+    }
+    if let Some(host) = keys.first() {
+        conf.search = match registry_value(host, "SearchList") {
+            Some(list) => list
+                .split([',', ' '])
+                .filter(|d| !d.is_empty())
+                .map(str::to_string)
+                .collect(),
+            None => registry_value(host, "Domain")
+                .or_else(|| registry_value(host, "DhcpDomain"))
+                .map(|domain| vec![domain.to_string()])
+                .unwrap_or_default(),
+        };
+    }
+    conf
+}
+
+/// Returns the value named `name` among the values of a registry key, unless it
+/// is empty. Value names are case-insensitive.
+fn registry_value<'a>(values: &[(&str, &'a str)], name: &str) -> Option<&'a str> {
+    values
+        .iter()
+        .find(|(value_name, _)| value_name.eq_ignore_ascii_case(name))
+        .map(|(_, value)| *value)
+        .filter(|value| !value.is_empty())
+}
+
+/// Adds `server` to the configuration unless it is already listed or the list is
+/// full, keeping the list as short as a `resolv.conf` one (see [`MAX_NAMESERVERS`]).
+fn add_nameserver(conf: &mut ResolvConf, server: IpAddr) {
+    if conf.nameservers.len() < MAX_NAMESERVERS && !conf.nameservers.contains(&server) {
+        conf.nameservers.push(server);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolved::parse_resolvectl;
+
+    const SCUTIL_OUTPUT: &str = "\nDNS configuration\n\n\
+                                 resolver #1\n\
+                                 \x20 search domain[0] : corp.example\n\
+                                 \x20 nameserver[0] : 10.0.0.2\n\
+                                 \x20 nameserver[1] : fe80::1%en0\n\
+                                 \x20 if_index : 6 (en0)\n\
+                                 \x20 timeout  : 2\n\
+                                 \x20 reach    : 0x00020002 (Reachable,Directly Reachable Address)\n\n\
+                                 resolver #2\n\
+                                 \x20 domain   : local\n\
+                                 \x20 options  : mdns\n\
+                                 \x20 timeout  : 5\n\n\
+                                 DNS configuration (for scoped queries)\n\n\
+                                 resolver #1\n\
+                                 \x20 nameserver[0] : 192.168.1.1\n";
+
+    const REG_OUTPUT: &str = "\r\nHKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\r\n\
+                              \x20   NameServer    REG_SZ    \r\n\
+                              \x20   SearchList    REG_SZ    \r\n\
+                              \x20   Domain    REG_SZ    \r\n\
+                              \x20   DhcpDomain    REG_SZ    home.example\r\n\r\n\
+                              HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\{4A1B}\r\n\
+                              \x20   EnableDHCP    REG_DWORD    0x1\r\n\
+                              \x20   NameServer    REG_SZ    10.0.0.2,10.0.0.3\r\n\
+                              \x20   DhcpNameServer    REG_SZ    192.168.1.1\r\n\r\n\
+                              HKEY_LOCAL_MACHINE\\SYSTEM\\CurrentControlSet\\Services\\Tcpip\\Parameters\\Interfaces\\{7C2D}\r\n\
+                              \x20   DhcpNameServer    REG_SZ    10.0.0.3 192.168.8.1\r\n";
+
+    #[test]
+    fn test_parse_scutil() {
+        let conf = parse_scutil(SCUTIL_OUTPUT);
+        assert_eq!(
+            conf.nameservers,
+            ["10.0.0.2", "fe80::1"].map(|server| server.parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(conf.search, ["corp.example"]);
+        assert_eq!(conf.timeout, Duration::from_secs(2));
+        assert_eq!(
+            parse_scutil("No DNS configuration available\n"),
+            ResolvConf::default()
+        );
+    }
+
+    #[test]
+    fn test_parse_reg_query() {
+        let conf = parse_reg_query(REG_OUTPUT);
+        // A configured server hides the one learned over DHCP, and duplicates are
+        // listed once.
+        assert_eq!(
+            conf.nameservers,
+            ["10.0.0.2", "10.0.0.3", "192.168.8.1"].map(|server| server.parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(conf.search, ["home.example"]);
+    }
+
+    #[test]
+    fn test_from_resolved() {
+        let resolved = parse_resolvectl(
+            "Global: 1.1.1.1\nLink 2 (eth0): 192.168.1.1 1.1.1.1\n",
+            "Link 2 (eth0): lan ~corp.example\n",
+        );
+        let conf = from_resolved(&resolved);
+        assert_eq!(
+            conf.nameservers,
+            ["1.1.1.1", "192.168.1.1"].map(|server| server.parse::<IpAddr>().unwrap())
+        );
+        assert_eq!(conf.search, ["lan"]);
+    }
+}
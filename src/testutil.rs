@@ -0,0 +1,321 @@
+//! An in-process DNS server for testing resolvers.
+//!
+//! Retry, timeout and failover logic only shows its worth when servers misbehave, and
+//! real servers cannot be made to misbehave on demand. [`StubServer`] listens on UDP
+//! and TCP on the loopback interface and answers each query according to a script
+//! of [`Reply`] steps: a normal answer, an error code, a truncated answer, a zone
+//! transfer spread over several messages, raw bytes that are not a valid message,
+//! silence, or any of these after a delay or signed with a TSIG key.
+//!
+//! Steps are used one per query, in order, whichever transport the query came in
+//! on. The last step keeps being used once the others are, so a single-step script
+//! describes a server that always behaves the same way. The server stops when it is
+//! dropped.
+//!
+//! Code that only needs canned answers can skip the sockets altogether with a
+//! [`MockTransport`](crate::transport::MockTransport), which answers each query in
+//! process with the [`Reply`] registered for its question.
+//!
+//! The module is only built for the crate's own tests, or for other crates' tests
+//! with the `testutil` feature enabled.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::query;
+//! use dns_resolver::retry::RetryPolicy;
+//! use dns_resolver::testutil::StubServer;
+//! use dns_resolver::transport::Reply;
+//! use std::net::Ipv4Addr;
+//! use std::time::Duration;
+//!
+//! // A server that ignores the first query and answers the second.
+//! let server = StubServer::start(vec![Reply::Drop, Reply::Answer(Vec::new())])?;
+//! let mut options = server.query_options();
+//! options.retry = RetryPolicy {
+//!     initial_timeout: Duration::from_millis(100),
+//!     max_attempts: 2,
+//!     ..RetryPolicy::default()
+//! };
+//!
+//! query("example.com", QueryType::A, Ipv4Addr::LOCALHOST, &options)?;
+//! assert_eq!(server.queries().len(), 2);
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::VecDeque;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::dns::{DnsClass, QueryType, RData, ResourceRecord, ResponseCode};
+use crate::resolver::QueryOptions;
+use crate::transport::{Reply, Transport, build_response, respond};
+
+/// How often the listening threads check whether the server was dropped.
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// A scriptable DNS server running on background threads.
+#[derive(Debug)]
+pub struct StubServer {
+    address: SocketAddrV4,
+    state: Arc<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    script: Mutex<VecDeque<Reply>>,
+    queries: Mutex<Vec<Transport>>,
+    stopped: AtomicBool,
+}
+
+impl State {
+    /// Records a query and returns the step that answers it.
+    fn next(&self, transport: Transport) -> Reply {
+        self.queries.lock().unwrap().push(transport);
+        let mut script = self.script.lock().unwrap();
+        match script.len() {
+            0 => Reply::Drop,
+            1 => script[0].clone(),
+            _ => script.pop_front().unwrap_or(Reply::Drop),
+        }
+    }
+}
+
+impl StubServer {
+    /// Starts a server on an ephemeral port of 127.0.0.1.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the sockets cannot be bound.
+    pub fn start(script: Vec<Reply>) -> io::Result<Self> {
+        Self::start_on(SocketAddrV4::new(Ipv4Addr::LOCALHOST, 0), script)
+    }
+
+    /// Starts a server on `address`, listening on both UDP and TCP.
+    ///
+    /// A port of 0 picks an ephemeral port for UDP, and TCP listens on the same
+    /// one. Servers for failover tests can share a port on different loopback
+    /// addresses (127.0.0.2 and up), since [`QueryOptions::port`] applies to every
+    /// server queried.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either socket cannot be bound.
+    pub fn start_on(address: SocketAddrV4, script: Vec<Reply>) -> io::Result<Self> {
+        let udp = UdpSocket::bind(address)?;
+        let address = match udp.local_addr()? {
+            SocketAddr::V4(address) => address,
+            SocketAddr::V6(_) => unreachable!("bound to an IPv4 address"),
+        };
+        let tcp = TcpListener::bind(address)?;
+        udp.set_read_timeout(Some(POLL_INTERVAL))?;
+        tcp.set_nonblocking(true)?;
+
+        let state = Arc::new(State {
+            script: Mutex::new(script.into()),
+            queries: Mutex::new(Vec::new()),
+            stopped: AtomicBool::new(false),
+        });
+        let udp_state = Arc::clone(&state);
+        thread::spawn(move || serve_udp(udp, udp_state));
+        let tcp_state = Arc::clone(&state);
+        thread::spawn(move || serve_tcp(tcp, tcp_state));
+
+        Ok(StubServer { address, state })
+    }
+
+    /// Returns the address the server listens on.
+    pub fn address(&self) -> SocketAddrV4 {
+        self.address
+    }
+
+    /// Returns query options that send queries to this server's port.
+    pub fn query_options(&self) -> QueryOptions {
+        QueryOptions {
+            port: Some(self.address.port()),
+            ..QueryOptions::default()
+        }
+    }
+
+    /// Returns the transport of every query received so far, in order of arrival.
+    pub fn queries(&self) -> Vec<Transport> {
+        self.state.queries.lock().unwrap().clone()
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        self.state.stopped.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Returns an A record of class IN, the most common record in a test answer.
+///
+/// # Panics
+///
+/// Panics if `name` is not a valid domain name.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::testutil::a_record;
+/// use dns_resolver::transport::Reply;
+///
+/// let reply = Reply::Answer(vec![a_record("example.com", [192, 0, 2, 1], 300)]);
+/// ```
+pub fn a_record(name: &str, address: impl Into<Ipv4Addr>, ttl: u32) -> ResourceRecord {
+    record(name, ttl, RData::A(address.into()))
+}
+
+/// Returns a record of class IN holding `data`, with the type that data belongs to.
+///
+/// # Panics
+///
+/// Panics if `name` is not a valid domain name.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{QueryType, RData};
+/// use dns_resolver::testutil::record;
+///
+/// let ns = record("example.com", 3600, RData::NS("ns1.example.com".to_string()));
+/// assert_eq!(ns.rtype, QueryType::NS);
+/// ```
+pub fn record(name: &str, ttl: u32, data: RData) -> ResourceRecord {
+    let rtype = match &data {
+        RData::A(_) => QueryType::A,
+        RData::AAAA(_) => QueryType::AAAA,
+        RData::CNAME(_) => QueryType::CNAME,
+        RData::MX { .. } => QueryType::MX,
+        RData::TXT(_) => QueryType::TXT,
+        RData::PTR(_) => QueryType::PTR,
+        RData::NS(_) => QueryType::NS,
+        RData::SOA { .. } => QueryType::SOA,
+        RData::HINFO { .. } => QueryType::HINFO,
+        RData::SRV { .. } => QueryType::SRV,
+        RData::NAPTR { .. } => QueryType::NAPTR,
+        RData::OPT(_) => QueryType::OPT,
+        RData::DS { .. } => QueryType::DS,
+        RData::DNSKEY { .. } => QueryType::DNSKEY,
+        RData::RRSIG { .. } => QueryType::RRSIG,
+        RData::NSEC { .. } => QueryType::NSEC,
+        RData::NSEC3 { .. } => QueryType::NSEC3,
+        RData::SSHFP { .. } => QueryType::SSHFP,
+        RData::SVCB { .. } => QueryType::SVCB,
+        RData::HTTPS { .. } => QueryType::HTTPS,
+        RData::TSIG { .. } => QueryType::TSIG,
+        RData::Other { rtype, .. } => QueryType::from(*rtype),
+    };
+    ResourceRecord {
+        name: name.parse().unwrap(),
+        rtype,
+        rclass: u16::from(DnsClass::IN),
+        ttl,
+        data,
+    }
+}
+
+fn serve_udp(socket: UdpSocket, state: Arc<State>) {
+    let mut buffer = [0; 65535];
+    while !state.stopped.load(Ordering::Relaxed) {
+        let Ok((size, peer)) = socket.recv_from(&mut buffer) else {
+            continue;
+        };
+        let reply = state.next(Transport::Udp);
+        let query = buffer[..size].to_vec();
+        let Ok(socket) = socket.try_clone() else {
+            continue;
+        };
+        // Answer on a thread of its own so a delayed reply does not hold up others.
+        thread::spawn(move || {
+            if let Some(response) = respond(&reply, &query, Transport::Udp) {
+                let _ = socket.send_to(&response, peer);
+            }
+        });
+    }
+}
+
+fn serve_tcp(listener: TcpListener, state: Arc<State>) {
+    while !state.stopped.load(Ordering::Relaxed) {
+        match listener.accept() {
+            Ok((stream, _)) => {
+                let state = Arc::clone(&state);
+                thread::spawn(move || serve_connection(stream, state));
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+            Err(_) => return,
+        }
+    }
+}
+
+/// Answers length-prefixed queries on one connection until the client closes it.
+fn serve_connection(mut stream: TcpStream, state: Arc<State>) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    loop {
+        let mut length = [0; 2];
+        stream.read_exact(&mut length)?;
+        let mut query = vec![0; u16::from_be_bytes(length) as usize];
+        stream.read_exact(&mut query)?;
+        let reply = state.next(Transport::Tcp);
+        let responses: Vec<Vec<u8>> = match &reply {
+            Reply::Transfer(messages) => messages
+                .iter()
+                .filter_map(|records| build_response(&query, ResponseCode::NoError, records, false))
+                .collect(),
+            reply => respond(reply, &query, Transport::Tcp).into_iter().collect(),
+        };
+        for response in responses {
+            stream.write_all(&(response.len() as u16).to_be_bytes())?;
+            stream.write_all(&response)?;
+        }
+        if matches!(reply, Reply::Transfer(_)) {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::{DnsMessage, DnsQuestion};
+
+    #[test]
+    fn test_truncated_reply_is_complete_over_tcp() {
+        let record = a_record("example.com", [192, 0, 2, 1], 60);
+        let server = StubServer::start(vec![Reply::Truncated(vec![record])]).unwrap();
+
+        let mut query = DnsMessage::new();
+        query.header.id = 0x4242;
+        query.header.flags = 0x0100;
+        query.header.question_count = 1;
+        query.questions.push(DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::A,
+            qclass: 1,
+        });
+        let mut bytes = Vec::new();
+        query.pack(&mut bytes).unwrap();
+
+        let mut stream = TcpStream::connect(server.address()).unwrap();
+        stream
+            .write_all(&(bytes.len() as u16).to_be_bytes())
+            .unwrap();
+        stream.write_all(&bytes).unwrap();
+        let mut length = [0; 2];
+        stream.read_exact(&mut length).unwrap();
+        let mut response = vec![0; u16::from_be_bytes(length) as usize];
+        stream.read_exact(&mut response).unwrap();
+
+        let response = DnsMessage::from_bytes(&response).unwrap();
+        assert_eq!(response.header.id, 0x4242);
+        assert!(!response.header.truncated());
+        assert_eq!(response.answers.len(), 1);
+        assert_eq!(server.queries(), vec![Transport::Tcp]);
+    }
+}
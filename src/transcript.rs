@@ -0,0 +1,272 @@
+//! Recording DNS exchanges and replaying them later.
+//!
+//! Resolution bugs are often hard to reproduce: the answer depends on the server,
+//! the network and the moment. A [`Transcript`] in recording mode writes every
+//! query and the response it got, as wire bytes with timing, to a file. A run with
+//! the same transcript in replay mode sends nothing over the network and answers
+//! each query from the file instead, so a bug report can carry everything needed to
+//! reproduce it offline.
+//!
+//! # File Format
+//!
+//! The transcript is a text file with one exchange per line. Lines starting with `#`
+//! are comments.
+//!
+//! ```text
+//! <offset_ms> <server> <rtt_ms> <query_hex> <response_hex|timeout>
+//! ```
+//!
+//! The offset counts from when recording started, and the round-trip time is that of
+//! the single attempt. Queries that went unanswered are recorded as `timeout`, and
+//! replay as timeouts.
+//!
+//! # Replay Matching
+//!
+//! Transaction IDs differ from run to run, so queries are matched on their bytes
+//! with the ID left out, and each replayed response gets the ID of the query it
+//! answers. Recorded answers to the same query are served in order, and the last
+//! one keeps being served once the others are used up.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::{QueryOptions, query};
+//! use dns_resolver::transcript::Transcript;
+//! use std::net::Ipv4Addr;
+//! use std::sync::Arc;
+//!
+//! let options = QueryOptions {
+//!     transcript: Some(Arc::new(Transcript::record("bug-1234.transcript")?)),
+//!     ..QueryOptions::default()
+//! };
+//! query("example.com", QueryType::A, Ipv4Addr::new(8, 8, 8, 8), &options)?;
+//!
+//! // Later, without network access:
+//! let options = QueryOptions {
+//!     transcript: Some(Arc::new(Transcript::replay("bug-1234.transcript")?)),
+//!     ..QueryOptions::default()
+//! };
+//! query("example.com", QueryType::A, Ipv4Addr::new(8, 8, 8, 8), &options)?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use std::collections::{HashMap, VecDeque};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::net::Ipv4Addr;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// A recorded response, or `None` for a query that timed out.
+type Recorded = Option<Vec<u8>>;
+
+/// A transcript being written or replayed.
+#[derive(Debug)]
+pub struct Transcript {
+    mode: Mode,
+}
+
+#[derive(Debug)]
+enum Mode {
+    /// Exchanges are appended to the file as they happen.
+    Record { file: Mutex<File>, started: Instant },
+    /// Queries are answered from recorded responses, keyed by query without its ID.
+    Replay(Mutex<HashMap<Vec<u8>, VecDeque<Recorded>>>),
+}
+
+impl Transcript {
+    /// Starts recording to `path`, replacing any existing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    pub fn record(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        writeln!(
+            file,
+            "# dns-resolver transcript: offset_ms server rtt_ms query response"
+        )?;
+        Ok(Transcript {
+            mode: Mode::Record {
+                file: Mutex::new(file),
+                started: Instant::now(),
+            },
+        })
+    }
+
+    /// Loads the transcript at `path` for replay.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read, or with
+    /// [`io::ErrorKind::InvalidData`] if a line is malformed.
+    pub fn replay(path: impl AsRef<Path>) -> io::Result<Self> {
+        let text = fs::read_to_string(path)?;
+        Self::parse(&text).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Builds a replay transcript from the text of a transcript file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message naming the first malformed line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut responses: HashMap<Vec<u8>, VecDeque<Recorded>> = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let malformed = |what: &str| format!("transcript line {}: {}", number + 1, what);
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let [_, _, _, query, response] = fields[..] else {
+                return Err(malformed("expected 5 fields"));
+            };
+            let query = decode_hex(query).ok_or_else(|| malformed("invalid query bytes"))?;
+            let response = match response {
+                "timeout" => None,
+                hex => Some(decode_hex(hex).ok_or_else(|| malformed("invalid response bytes"))?),
+            };
+            let key = match_key(&query).ok_or_else(|| malformed("query is too short"))?;
+            responses.entry(key).or_default().push_back(response);
+        }
+        Ok(Transcript {
+            mode: Mode::Replay(Mutex::new(responses)),
+        })
+    }
+
+    /// Returns `true` if queries should be answered from the transcript rather
+    /// than sent.
+    pub(crate) fn is_replay(&self) -> bool {
+        matches!(self.mode, Mode::Replay(_))
+    }
+
+    /// Appends an exchange with `server` to a recording transcript. Does nothing
+    /// when replaying.
+    pub(crate) fn save(
+        &self,
+        server: Ipv4Addr,
+        query: &[u8],
+        response: Option<&[u8]>,
+        rtt: Duration,
+    ) -> io::Result<()> {
+        let Mode::Record { file, started } = &self.mode else {
+            return Ok(());
+        };
+        let line = format!(
+            "{} {} {} {} {}\n",
+            started.elapsed().as_millis(),
+            server,
+            rtt.as_millis(),
+            encode_hex(query),
+            response.map_or_else(|| "timeout".to_string(), encode_hex)
+        );
+        file.lock().unwrap().write_all(line.as_bytes())
+    }
+
+    /// Returns the recorded outcome for `query`, with the response's ID set to
+    /// the query's: `Some(None)` for a recorded timeout, and `None` if the query was
+    /// never recorded.
+    pub(crate) fn answer(&self, query: &[u8]) -> Option<Recorded> {
+        let Mode::Replay(responses) = &self.mode else {
+            return None;
+        };
+        let mut responses = responses.lock().unwrap();
+        let queue = responses.get_mut(&match_key(query)?)?;
+        let recorded = match queue.len() {
+            0 => return None,
+            1 => queue[0].clone(),
+            _ => queue.pop_front()?,
+        };
+        Some(recorded.map(|mut response| {
+            if response.len() >= 2 {
+                response[..2].copy_from_slice(&query[..2]);
+            }
+            response
+        }))
+    }
+}
+
+/// Returns the query bytes without the transaction ID, or `None` if there is no ID.
+fn match_key(query: &[u8]) -> Option<Vec<u8>> {
+    query.get(2..).map(<[u8]>::to_vec)
+}
+
+pub(crate) fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn decode_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.bytes().all(|b| b.is_ascii_hexdigit()) || !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_then_replay() {
+        let path = std::env::temp_dir().join(format!(
+            "dns-resolver-transcript-{}.txt",
+            std::process::id()
+        ));
+        let recording = Transcript::record(&path).unwrap();
+        let server = Ipv4Addr::new(192, 0, 2, 53);
+        let query = [0x12, 0x34, 0x01, 0x00, 0xAB];
+        recording
+            .save(
+                server,
+                &query,
+                Some(&[0x12, 0x34, 0x81, 0x80]),
+                Duration::from_millis(7),
+            )
+            .unwrap();
+        recording
+            .save(server, &query, None, Duration::from_secs(5))
+            .unwrap();
+        let other_query = [0x00, 0x01, 0x01, 0x00, 0xCD];
+        recording
+            .save(
+                server,
+                &other_query,
+                Some(&[0x00, 0x01, 0x81, 0x83]),
+                Duration::ZERO,
+            )
+            .unwrap();
+        drop(recording);
+
+        let replay = Transcript::replay(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(replay.is_replay());
+        // A different ID still matches, and the response takes the new ID.
+        let query = [0x56, 0x78, 0x01, 0x00, 0xAB];
+        assert_eq!(
+            replay.answer(&query),
+            Some(Some(vec![0x56, 0x78, 0x81, 0x80]))
+        );
+        // The timeout was recorded second, and keeps being replayed once it is last.
+        assert_eq!(replay.answer(&query), Some(None));
+        assert_eq!(replay.answer(&query), Some(None));
+        assert_eq!(
+            replay.answer(&other_query),
+            Some(Some(vec![0x00, 0x01, 0x81, 0x83]))
+        );
+        assert_eq!(replay.answer(&[0, 0, 0xFF]), None);
+    }
+
+    #[test]
+    fn test_malformed_lines_are_rejected() {
+        assert!(Transcript::parse("# comment only\n\n").is_ok());
+        let error = Transcript::parse("0 192.0.2.1 3 zz 00").unwrap_err();
+        assert!(error.starts_with("transcript line 1"));
+        assert!(Transcript::parse("0 192.0.2.1 3 0001").is_err());
+    }
+}
@@ -0,0 +1,337 @@
+//! Answering queries in process, for testing code built on the resolver.
+//!
+//! A [`MockTransport`] set as [`QueryOptions::mock_transport`](crate::resolver::QueryOptions::mock_transport) answers every attempt
+//! of a query with the [`Reply`] registered for its question, and nothing is sent.
+//! [`QueryOptions`](crate::resolver::QueryOptions) refers to it, so unlike the stub server and record fixtures of
+//! `testutil`, which are only built for tests and with the `testutil` feature, this
+//! module is part of every build.
+
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::dns::{
+    DnsClass, DnsMessage, DnsQuestion, QueryType, RData, ResourceRecord, ResponseCode,
+    canonical_name,
+};
+use crate::resolver::DnsError;
+use crate::tsig::{TsigKey, sign_response};
+
+/// How a [`MockTransport`], or a stub server, treats one query.
+#[derive(Debug, Clone)]
+pub enum Reply {
+    /// Answer with these records and NOERROR.
+    Answer(Vec<ResourceRecord>),
+    /// Answer with no records and this response code.
+    Code(ResponseCode),
+    /// Over UDP, answer with the TC bit set and no records, as a server does when
+    /// the answer does not fit in a datagram. Over TCP, answer with the records.
+    Truncated(Vec<ResourceRecord>),
+    /// Over TCP, answer with one NOERROR message per set of records, one after the
+    /// other, as a server streams a zone transfer, then close the connection. Over
+    /// UDP, answer with the first set only.
+    Transfer(Vec<Vec<ResourceRecord>>),
+    /// Send these bytes as the response. The first two bytes are replaced with the
+    /// query's ID, if there are two, so the response is not discarded as unsolicited.
+    Raw(Vec<u8>),
+    /// Send nothing.
+    Drop,
+    /// Wait, then reply as the inner step. Other queries are served meanwhile.
+    Delayed(Duration, Box<Reply>),
+    /// Reply as the inner step, signing the response with this key as a TSIG server
+    /// does, over the MAC of the signed query. Unsigned queries get unsigned replies.
+    Signed(TsigKey, Box<Reply>),
+}
+
+/// The transport a query arrived on, which decides how a truncated [`Reply`] is
+/// answered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// A UDP datagram.
+    Udp,
+    /// A length-prefixed message on a TCP connection.
+    Tcp,
+}
+
+/// Canned responses keyed by question, for testing code that uses the resolver
+/// without a network.
+///
+/// Set it as [`QueryOptions::mock_transport`](crate::resolver::QueryOptions::mock_transport), or with
+/// [`ResolverBuilder::mock_transport`](crate::resolver::ResolverBuilder::mock_transport),
+/// and every attempt of every query is answered in process with the [`Reply`]
+/// registered for its name, type and class, exactly as a stub server would answer
+/// it, down to retrying truncated answers as if over TCP. [`Reply::Drop`] times the
+/// attempt out at once instead of waiting. A query for a question with no reply
+/// fails with [`DnsError::Io`] of kind [`std::io::ErrorKind::NotFound`], so a test
+/// cannot pass by accident on a question it did not expect.
+///
+/// Queries sent with [`query`](crate::resolver::query) and everything built on it
+/// are answered: [`resolve`](crate::resolver::resolve), [`Resolver`](crate::resolver::Resolver),
+/// the host cache and DNSSEC validation. Zone transfers and NOTIFY messages still go
+/// to the network.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{QueryType, ResponseCode};
+/// use dns_resolver::resolver::{DnsError, Resolver};
+/// use dns_resolver::testutil::a_record;
+/// use dns_resolver::transport::{MockTransport, Reply};
+/// use std::net::Ipv4Addr;
+/// use std::sync::Arc;
+///
+/// let record = a_record("example.com", [192, 0, 2, 1], 300);
+/// let mock = Arc::new(
+///     MockTransport::new()
+///         .answer("example.com", QueryType::A, vec![record])
+///         .reply("missing.example", QueryType::A, Reply::Code(ResponseCode::NameError)),
+/// );
+/// let resolver = Resolver::builder()
+///     .server(Ipv4Addr::new(192, 0, 2, 53))
+///     .mock_transport(Arc::clone(&mock))
+///     .build()?;
+///
+/// assert_eq!(resolver.resolve("example.com", QueryType::A)?.answers.len(), 1);
+/// assert!(matches!(
+///     resolver.resolve("missing.example", QueryType::A),
+///     Err(DnsError::ServerReturnedError(ResponseCode::NameError))
+/// ));
+/// assert_eq!(mock.queries().len(), 2);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Default)]
+pub struct MockTransport {
+    replies: HashMap<(String, QueryType, DnsClass), Reply>,
+    queries: Mutex<Vec<(DnsQuestion, Transport)>>,
+}
+
+impl MockTransport {
+    /// Creates a mock transport with no replies.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers queries for `name` and `query_type` in class IN with these records
+    /// and NOERROR.
+    pub fn answer(self, name: &str, query_type: QueryType, records: Vec<ResourceRecord>) -> Self {
+        self.reply(name, query_type, Reply::Answer(records))
+    }
+
+    /// Answers queries for `name` and `query_type` in class IN as `reply` says.
+    pub fn reply(self, name: &str, query_type: QueryType, reply: Reply) -> Self {
+        self.reply_in_class(name, query_type, DnsClass::IN, reply)
+    }
+
+    /// Answers queries for `name` and `query_type` in `class` as `reply` says.
+    /// Names are matched without regard to case or a trailing dot.
+    pub fn reply_in_class(
+        mut self,
+        name: &str,
+        query_type: QueryType,
+        class: DnsClass,
+        reply: Reply,
+    ) -> Self {
+        self.replies
+            .insert((canonical_name(name), query_type, class), reply);
+        self
+    }
+
+    /// Returns the question of every attempt answered or refused so far, in order.
+    pub fn queries(&self) -> Vec<DnsQuestion> {
+        let queries = self.queries.lock().unwrap();
+        queries
+            .iter()
+            .map(|(question, _)| question.clone())
+            .collect()
+    }
+
+    /// Returns the transport of every attempt answered or refused so far, in order.
+    pub fn transports(&self) -> Vec<Transport> {
+        let queries = self.queries.lock().unwrap();
+        queries.iter().map(|&(_, transport)| transport).collect()
+    }
+
+    /// Answers one attempt of a query, as the server would over `transport`.
+    pub(crate) fn exchange(&self, query: &[u8], transport: Transport) -> Result<Vec<u8>, DnsError> {
+        let question = DnsMessage::from_bytes(query)
+            .map_err(|e| DnsError::InvalidResponse(e.to_string()))?
+            .questions
+            .into_iter()
+            .next()
+            .ok_or_else(|| DnsError::InvalidResponse("query has no question".to_string()))?;
+        self.queries
+            .lock()
+            .unwrap()
+            .push((question.clone(), transport));
+        let key = (
+            canonical_name(&question.name),
+            question.qtype,
+            DnsClass::from(question.qclass),
+        );
+        let Some(reply) = self.replies.get(&key) else {
+            return Err(DnsError::Io(io::Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "the mock transport has no reply for {} {} {}",
+                    question.name, key.2, question.qtype
+                ),
+            )));
+        };
+        respond(reply, query, transport).ok_or(DnsError::Timeout)
+    }
+}
+
+/// Builds the response a step calls for, or `None` to send nothing.
+pub(crate) fn respond(reply: &Reply, query: &[u8], transport: Transport) -> Option<Vec<u8>> {
+    match reply {
+        Reply::Answer(records) => build_response(query, ResponseCode::NoError, records, false),
+        Reply::Code(code) => build_response(query, *code, &[], false),
+        Reply::Truncated(records) => match transport {
+            Transport::Udp => build_response(query, ResponseCode::NoError, &[], true),
+            Transport::Tcp => build_response(query, ResponseCode::NoError, records, false),
+        },
+        Reply::Transfer(messages) => build_response(
+            query,
+            ResponseCode::NoError,
+            messages.first().map_or(&[][..], Vec::as_slice),
+            false,
+        ),
+        Reply::Raw(bytes) => {
+            let mut bytes = bytes.clone();
+            if bytes.len() >= 2 && query.len() >= 2 {
+                bytes[..2].copy_from_slice(&query[..2]);
+            }
+            Some(bytes)
+        }
+        Reply::Drop => None,
+        Reply::Delayed(delay, reply) => {
+            thread::sleep(*delay);
+            respond(reply, query, transport)
+        }
+        Reply::Signed(key, reply) => {
+            let mut response = respond(reply, query, transport)?;
+            let request_mac = DnsMessage::from_bytes(query)
+                .ok()?
+                .additionals
+                .into_iter()
+                .find_map(|record| match record.data {
+                    RData::TSIG { mac, .. } => Some(mac),
+                    _ => None,
+                });
+            if let Some(request_mac) = request_mac {
+                sign_response(key, &mut response, &request_mac, SystemTime::now()).ok()?;
+            }
+            Some(response)
+        }
+    }
+}
+
+/// Builds a response to `query` that repeats its ID, opcode, question and RD bit.
+pub(crate) fn build_response(
+    query: &[u8],
+    code: ResponseCode,
+    answers: &[ResourceRecord],
+    truncated: bool,
+) -> Option<Vec<u8>> {
+    let query = DnsMessage::from_bytes(query).ok()?;
+    let mut message = DnsMessage::new();
+    message.header.id = query.header.id;
+    message.header.set_response(true);
+    message.header.set_opcode(query.header.opcode());
+    message.header.set_truncated(truncated);
+    message
+        .header
+        .set_recursion_desired(query.header.recursion_desired());
+    message.header.set_recursion_available(true);
+    message.header.set_response_code(code);
+    message.header.question_count = query.questions.len() as u16;
+    message.header.answer_count = answers.len() as u16;
+    message.questions = query.questions;
+    message.answers = answers.to_vec();
+    let mut bytes = Vec::new();
+    message.pack(&mut bytes).ok()?;
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::resolver::{Resolver, query};
+    use crate::testutil::a_record;
+    use std::net::Ipv4Addr;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_mock_transport_answers_by_question() {
+        let record = a_record("example.com", [192, 0, 2, 1], 60);
+        let mock = Arc::new(
+            MockTransport::new()
+                .answer("Example.COM.", QueryType::A, vec![record.clone()])
+                .reply(
+                    "big.example",
+                    QueryType::TXT,
+                    Reply::Truncated(vec![record]),
+                )
+                .reply("slow.example", QueryType::A, Reply::Drop)
+                .reply_in_class(
+                    "version.bind",
+                    QueryType::TXT,
+                    DnsClass::CH,
+                    Reply::Code(ResponseCode::Refused),
+                ),
+        );
+        let resolver = Resolver::builder()
+            .server(Ipv4Addr::new(192, 0, 2, 53))
+            .attempts(2)
+            .mock_transport(Arc::clone(&mock))
+            .build()
+            .unwrap();
+
+        // Names match regardless of case and trailing dot.
+        let response = resolver.resolve("example.com", QueryType::A).unwrap();
+        assert_eq!(response.answers.len(), 1);
+
+        // A truncated answer is asked for again, as over TCP, and comes back whole.
+        let response = resolver.resolve("big.example", QueryType::TXT).unwrap();
+        assert_eq!(response.answers.len(), 1);
+
+        // Dropped attempts time out right away, and are retried.
+        assert!(matches!(
+            resolver.query("slow.example", QueryType::A),
+            Err(DnsError::Timeout)
+        ));
+
+        // Classes are part of the question.
+        let mut options = resolver.options().clone();
+        options.class = DnsClass::CH;
+        let result = query("version.bind", QueryType::TXT, resolver.server(), &options).unwrap();
+        assert_eq!(
+            result.message.header.get_response_code(),
+            ResponseCode::Refused
+        );
+
+        // Questions without a reply are not answered by accident.
+        match resolver.query("other.example", QueryType::A) {
+            Err(DnsError::Io(e)) => assert_eq!(e.kind(), ErrorKind::NotFound),
+            other => panic!("expected NotFound, got {:?}", other),
+        }
+
+        // Every attempt is recorded: the truncated answer took two, the dropped
+        // query both of its attempts.
+        let asked: Vec<String> = mock.queries().iter().map(|q| q.name.to_string()).collect();
+        assert_eq!(
+            asked,
+            [
+                "example.com",
+                "big.example",
+                "big.example",
+                "slow.example",
+                "slow.example",
+                "version.bind",
+                "other.example"
+            ]
+        );
+    }
+}
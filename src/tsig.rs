@@ -0,0 +1,694 @@
+//! Transaction signatures (TSIG, RFC 8945).
+//!
+//! TSIG authenticates DNS messages with a secret key shared between a client and a
+//! server. The sender appends a TSIG pseudo-record to the additional section holding
+//! an HMAC of the message, the key's name and algorithm, and the time of signing;
+//! the receiver computes the same HMAC and compares. Servers commonly require it for
+//! zone transfers and dynamic updates, and some for queries too.
+//!
+//! A response's MAC also covers the MAC of the request it answers, and each message
+//! of a zone transfer covers the MAC of the one before, so a [`TsigSession`] carries
+//! that state from signing the request to verifying the last response.
+//!
+//! Keys are written as `[algorithm:]name:secret` with a base64 secret, as `dig -y`
+//! takes them, or read from a BIND key file such as `tsig-keygen` writes:
+//!
+//! ```text
+//! key "transfer-key" {
+//!     algorithm hmac-sha256;
+//!     secret "aGVsbG8gd29ybGQgdGhpcyBpcyBhIGtleQ==";
+//! };
+//! ```
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::tsig::{TsigKey, TsigSession};
+//! use std::time::SystemTime;
+//!
+//! let key: TsigKey = "hmac-sha256:transfer-key:aGVsbG8gd29ybGQgdGhpcyBpcyBhIGtleQ==".parse()?;
+//! # let mut query = vec![0; 12];
+//! let mut session = TsigSession::sign_request(&key, &mut query, SystemTime::now())?;
+//! // Send `query`, then check every response with `session.verify(&response, SystemTime::now())`.
+//! # Ok::<(), String>(())
+//! ```
+
+use std::fmt;
+use std::io::Cursor;
+use std::str::FromStr;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::crypto::{HashAlgorithm, hmac, macs_equal};
+use crate::dns::{
+    DnsHeader, DnsName, DnsQuestion, QueryType, RData, ResourceRecord, canonical_name,
+    decode_base64, pack_domain_name, tsig_error_name,
+};
+
+/// How many seconds the time a message was signed may differ from the receiver's
+/// clock. RFC 8945 section 10 recommends 300.
+pub const DEFAULT_FUDGE: u16 = 300;
+
+/// The most messages of a stream that may follow each other unsigned (RFC 8945
+/// section 5.3.1).
+const MAX_UNSIGNED_MESSAGES: usize = 99;
+
+/// The TSIG record's class and TTL, which are fixed (RFC 8945 section 4.2).
+const TSIG_CLASS: u16 = 255;
+const TSIG_TTL: u32 = 0;
+
+/// An HMAC algorithm TSIG can use (RFC 8945 section 6).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TsigAlgorithm {
+    /// HMAC-SHA1, still common with older keys.
+    HmacSha1,
+    /// HMAC-SHA256, which every implementation must support.
+    HmacSha256,
+    /// HMAC-SHA384.
+    HmacSha384,
+    /// HMAC-SHA512.
+    HmacSha512,
+}
+
+impl TsigAlgorithm {
+    /// Returns the algorithm's name, as the TSIG record and key files write it.
+    pub fn name(self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacSha1 => "hmac-sha1",
+            TsigAlgorithm::HmacSha256 => "hmac-sha256",
+            TsigAlgorithm::HmacSha384 => "hmac-sha384",
+            TsigAlgorithm::HmacSha512 => "hmac-sha512",
+        }
+    }
+
+    /// Returns the hash the HMAC is built on.
+    fn hash(self) -> HashAlgorithm {
+        match self {
+            TsigAlgorithm::HmacSha1 => HashAlgorithm::Sha1,
+            TsigAlgorithm::HmacSha256 => HashAlgorithm::Sha256,
+            TsigAlgorithm::HmacSha384 => HashAlgorithm::Sha384,
+            TsigAlgorithm::HmacSha512 => HashAlgorithm::Sha512,
+        }
+    }
+}
+
+impl FromStr for TsigAlgorithm {
+    type Err = String;
+
+    /// Parses an algorithm name such as `hmac-sha256`, ignoring case and a
+    /// trailing dot.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match canonical_name(s).as_str() {
+            "hmac-sha1" => Ok(TsigAlgorithm::HmacSha1),
+            "hmac-sha256" => Ok(TsigAlgorithm::HmacSha256),
+            "hmac-sha384" => Ok(TsigAlgorithm::HmacSha384),
+            "hmac-sha512" => Ok(TsigAlgorithm::HmacSha512),
+            _ => Err(format!(
+                "Unsupported TSIG algorithm '{}'. Supported algorithms are hmac-sha1, hmac-sha256, hmac-sha384 and hmac-sha512.",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TsigAlgorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A key shared with a server for signing messages.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TsigKey {
+    /// The key's name, which both sides must agree on, e.g. `transfer-key`.
+    pub name: String,
+    /// The HMAC algorithm the key is used with.
+    pub algorithm: TsigAlgorithm,
+    /// The shared secret.
+    pub secret: Vec<u8>,
+}
+
+/// Shows the key without its secret, so that it does not end up in logs.
+impl fmt::Debug for TsigKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TsigKey")
+            .field("name", &self.name)
+            .field("algorithm", &self.algorithm)
+            .field("secret", &"<redacted>")
+            .finish()
+    }
+}
+
+impl FromStr for TsigKey {
+    type Err = String;
+
+    /// Parses a key written as `[algorithm:]name:secret`, with the secret in base64.
+    /// The algorithm defaults to hmac-sha256.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::tsig::{TsigAlgorithm, TsigKey};
+    ///
+    /// let key: TsigKey = "hmac-sha1:transfer-key:c2VjcmV0".parse().unwrap();
+    /// assert_eq!(key.name, "transfer-key");
+    /// assert_eq!(key.algorithm, TsigAlgorithm::HmacSha1);
+    /// assert_eq!(key.secret, b"secret");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.split(':').collect();
+        let (algorithm, name, secret) = match parts[..] {
+            [name, secret] => (TsigAlgorithm::HmacSha256, name, secret),
+            [algorithm, name, secret] => (algorithm.parse()?, name, secret),
+            _ => {
+                return Err(format!(
+                    "Invalid TSIG key '{}' (expected [algorithm:]name:secret)",
+                    s
+                ));
+            }
+        };
+        TsigKey::new(name, algorithm, secret)
+    }
+}
+
+impl TsigKey {
+    /// Builds a key from its name, algorithm and base64 secret, checking each.
+    fn new(name: &str, algorithm: TsigAlgorithm, secret: &str) -> Result<Self, String> {
+        if name.is_empty() {
+            return Err("The TSIG key name is empty".to_string());
+        }
+        // The name goes on the wire, so it must be a valid domain name.
+        pack_domain_name(&mut Vec::new(), name)?;
+        let secret = decode_base64(secret)?;
+        if secret.is_empty() {
+            return Err(format!("The secret of TSIG key '{}' is empty", name));
+        }
+        Ok(TsigKey {
+            name: name.to_string(),
+            algorithm,
+            secret,
+        })
+    }
+
+    /// Reads the first key from a BIND key file, as written by `tsig-keygen` or
+    /// `rndc-confgen`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of the key file
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TsigKey)` - The first `key` clause in the file
+    /// * `Err(String)` - If there is none, or it lacks an algorithm or secret
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use dns_resolver::tsig::TsigKey;
+    ///
+    /// let key = TsigKey::from_key_file(
+    ///     "key \"transfer-key\" {\n\talgorithm hmac-sha256;\n\tsecret \"c2VjcmV0\";\n};\n",
+    /// )
+    /// .unwrap();
+    /// assert_eq!(key.name, "transfer-key");
+    /// ```
+    pub fn from_key_file(text: &str) -> Result<Self, String> {
+        let tokens = tokenize(text)?;
+        let start = tokens
+            .iter()
+            .position(|token| token == "key")
+            .ok_or("The key file has no key clause")?;
+        let (name, body) = match &tokens[start + 1..] {
+            [name, open, body @ ..] if open == "{" => (name, body),
+            _ => return Err("The key clause must be 'key <name> { ... };'".to_string()),
+        };
+
+        let mut algorithm = None;
+        let mut secret = None;
+        for statement in body.split(|token| token == ";") {
+            match statement {
+                [keyword, value] if keyword == "algorithm" => algorithm = Some(value.parse()?),
+                [keyword, value] if keyword == "secret" => secret = Some(value.as_str()),
+                [close, ..] if close == "}" => break,
+                _ => {}
+            }
+        }
+        match (algorithm, secret) {
+            (Some(algorithm), Some(secret)) => TsigKey::new(name, algorithm, secret),
+            _ => Err(format!(
+                "Key '{}' needs both an algorithm and a secret statement",
+                name
+            )),
+        }
+    }
+}
+
+/// Splits a BIND configuration file into words, quoted strings and the punctuation
+/// `{`, `}` and `;`, dropping comments.
+fn tokenize(text: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '#' => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '"' => {
+                let quoted: String = chars.by_ref().take_while(|c| *c != '"').collect();
+                tokens.push(quoted);
+            }
+            '{' | '}' | ';' => tokens.push(c.to_string()),
+            c if c.is_whitespace() => {}
+            c => {
+                let mut word = c.to_string();
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace() && !"{};\"".contains(*c)) {
+                    word.push(c);
+                }
+                tokens.push(word);
+            }
+        }
+    }
+    if text.matches('"').count() % 2 == 1 {
+        return Err("The key file has an unterminated string".to_string());
+    }
+    Ok(tokens)
+}
+
+/// The signing state of one exchange: a request and its response, or the stream of
+/// responses of a zone transfer.
+#[derive(Debug)]
+pub struct TsigSession {
+    key: TsigKey,
+    /// The MAC of the last signed message, which the next one's MAC covers.
+    previous_mac: Vec<u8>,
+    /// The responses received since the last signed one, which its MAC covers.
+    unsigned: Vec<u8>,
+    unsigned_count: usize,
+    /// How many responses have been verified.
+    verified: usize,
+}
+
+impl TsigSession {
+    /// Signs a request by appending a TSIG record to it, and returns the session
+    /// that verifies the responses.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The key to sign with
+    /// * `message` - The packed request, which gains the TSIG record
+    /// * `now` - The time to sign the request at
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the message is too short to be a DNS message.
+    pub fn sign_request(
+        key: &TsigKey,
+        message: &mut Vec<u8>,
+        now: SystemTime,
+    ) -> Result<Self, String> {
+        let mac = sign(key, message, None, now)?;
+        Ok(TsigSession {
+            key: key.clone(),
+            previous_mac: mac,
+            unsigned: Vec::new(),
+            unsigned_count: 0,
+            verified: 0,
+        })
+    }
+
+    /// Verifies the next response of the exchange.
+    ///
+    /// The first response must be signed. Later responses of a stream may go
+    /// unsigned, up to 99 in a row, and are then covered by the next signed one;
+    /// [`TsigSession::is_complete`] tells whether the last one so far was signed.
+    ///
+    /// # Errors
+    ///
+    /// Returns a description of the problem if the response is unsigned when it must
+    /// be signed, is signed with another key, reports a TSIG error from the server,
+    /// has a MAC that does not match, or was signed too far from `now`.
+    pub fn verify(&mut self, response: &[u8], now: SystemTime) -> Result<(), String> {
+        let Some((signed, record)) = split_tsig(response)? else {
+            if self.verified == 0 {
+                return Err("the response is not signed".to_string());
+            }
+            self.unsigned_count += 1;
+            if self.unsigned_count > MAX_UNSIGNED_MESSAGES {
+                return Err(format!(
+                    "more than {} messages in a row are unsigned",
+                    MAX_UNSIGNED_MESSAGES
+                ));
+            }
+            self.unsigned.extend_from_slice(response);
+            return Ok(());
+        };
+        let RData::TSIG {
+            algorithm,
+            time_signed,
+            fudge,
+            mac,
+            error,
+            other,
+            ..
+        } = &record.data
+        else {
+            unreachable!("split_tsig only returns TSIG records");
+        };
+
+        if canonical_name(&record.name) != canonical_name(&self.key.name)
+            || algorithm.parse::<TsigAlgorithm>() != Ok(self.key.algorithm)
+        {
+            return Err(format!(
+                "the response is signed with key '{}' ({}) instead of '{}' ({})",
+                record.name, algorithm, self.key.name, self.key.algorithm
+            ));
+        }
+        // A server that cannot verify the request answers with an error and, for
+        // BADSIG and BADKEY, no MAC at all.
+        if *error != 0 {
+            return Err(format!(
+                "the server rejected the request's signature ({})",
+                tsig_error_name(*error)
+            ));
+        }
+
+        let mut data = Vec::new();
+        push_mac(&mut data, &self.previous_mac);
+        if self.verified == 0 {
+            data.extend_from_slice(&signed);
+            push_variables(&mut data, &self.key, *time_signed, *fudge, *error, other)?;
+        } else {
+            // Later messages of a stream cover only the timers (RFC 8945 section 5.3.1).
+            data.extend_from_slice(&self.unsigned);
+            data.extend_from_slice(&signed);
+            push_timers(&mut data, *time_signed, *fudge);
+        }
+        let expected = hmac(self.key.algorithm.hash(), &self.key.secret, &data);
+        if !macs_equal(mac, &expected) {
+            return Err("the response's MAC does not match (BADSIG)".to_string());
+        }
+
+        // Only check the time once the MAC shows it is genuine.
+        let skew = unix_time(now).abs_diff(*time_signed);
+        if skew > u64::from(*fudge) {
+            return Err(format!(
+                "the response was signed {} seconds away from the local clock, more than the allowed {} (BADTIME)",
+                skew, fudge
+            ));
+        }
+
+        self.previous_mac = mac.clone();
+        self.unsigned.clear();
+        self.unsigned_count = 0;
+        self.verified += 1;
+        Ok(())
+    }
+
+    /// Returns whether at least one response was verified and none has arrived
+    /// unsigned since. A stream must end with a signed message.
+    pub fn is_complete(&self) -> bool {
+        self.verified > 0 && self.unsigned_count == 0
+    }
+}
+
+/// Signs a response to a request whose MAC was `request_mac`, as a server does, by
+/// appending a TSIG record to it.
+///
+/// # Errors
+///
+/// Returns an error if the message is too short to be a DNS message.
+pub fn sign_response(
+    key: &TsigKey,
+    message: &mut Vec<u8>,
+    request_mac: &[u8],
+    now: SystemTime,
+) -> Result<Vec<u8>, String> {
+    sign(key, message, Some(request_mac), now)
+}
+
+/// Appends a TSIG record to `message` with the MAC of the message, preceded by
+/// `prior_mac` for a response, and returns the MAC.
+fn sign(
+    key: &TsigKey,
+    message: &mut Vec<u8>,
+    prior_mac: Option<&[u8]>,
+    now: SystemTime,
+) -> Result<Vec<u8>, String> {
+    if message.len() < 12 {
+        return Err("Cannot sign a message shorter than a DNS header".to_string());
+    }
+    let time_signed = unix_time(now);
+    let mut data = Vec::new();
+    if let Some(prior_mac) = prior_mac {
+        push_mac(&mut data, prior_mac);
+    }
+    data.extend_from_slice(message);
+    push_variables(&mut data, key, time_signed, DEFAULT_FUDGE, 0, &[])?;
+    let mac = hmac(key.algorithm.hash(), &key.secret, &data);
+
+    let record = ResourceRecord {
+        name: DnsName::new_unchecked(key.name.clone()),
+        rtype: QueryType::TSIG,
+        rclass: TSIG_CLASS,
+        ttl: TSIG_TTL,
+        data: RData::TSIG {
+            algorithm: key.algorithm.name().to_string(),
+            time_signed,
+            fudge: DEFAULT_FUDGE,
+            mac: mac.clone(),
+            original_id: u16::from_be_bytes([message[0], message[1]]),
+            error: 0,
+            other: Vec::new(),
+        },
+    };
+    record.pack(message)?;
+    let additional_count = u16::from_be_bytes([message[10], message[11]]) + 1;
+    message[10..12].copy_from_slice(&additional_count.to_be_bytes());
+    Ok(mac)
+}
+
+/// Splits a message into its bytes before the TSIG record, with the ID and ARCOUNT
+/// as they were when it was signed, and the TSIG record itself. Returns `None` if
+/// the last record of the message is not a TSIG record.
+fn split_tsig(message: &[u8]) -> Result<Option<(Vec<u8>, ResourceRecord)>, String> {
+    let invalid = |e: std::io::Error| format!("cannot find the TSIG record: {}", e);
+    let mut cursor = Cursor::new(message);
+    let header = DnsHeader::from_bytes(&mut cursor).map_err(invalid)?;
+    if header.additional_count == 0 {
+        return Ok(None);
+    }
+    for _ in 0..header.question_count {
+        DnsQuestion::from_bytes(&mut cursor).map_err(invalid)?;
+    }
+    let preceding = u32::from(header.answer_count)
+        + u32::from(header.authority_count)
+        + u32::from(header.additional_count)
+        - 1;
+    for _ in 0..preceding {
+        ResourceRecord::from_bytes(&mut cursor).map_err(invalid)?;
+    }
+    let start = cursor.position() as usize;
+    let record = ResourceRecord::from_bytes(&mut cursor).map_err(invalid)?;
+    let RData::TSIG { original_id, .. } = record.data else {
+        return Ok(None);
+    };
+
+    let mut signed = message[..start].to_vec();
+    signed[..2].copy_from_slice(&original_id.to_be_bytes());
+    signed[10..12].copy_from_slice(&(header.additional_count - 1).to_be_bytes());
+    Ok(Some((signed, record)))
+}
+
+/// Appends a MAC with its two-byte length, as a response's digest starts with the
+/// request's MAC.
+fn push_mac(data: &mut Vec<u8>, mac: &[u8]) {
+    data.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+    data.extend_from_slice(mac);
+}
+
+/// Appends the TSIG variables the MAC covers after the message (RFC 8945 section
+/// 4.3.3), with the names in canonical form.
+fn push_variables(
+    data: &mut Vec<u8>,
+    key: &TsigKey,
+    time_signed: u64,
+    fudge: u16,
+    error: u16,
+    other: &[u8],
+) -> Result<(), String> {
+    pack_domain_name(data, &canonical_name(&key.name))?;
+    data.extend_from_slice(&TSIG_CLASS.to_be_bytes());
+    data.extend_from_slice(&TSIG_TTL.to_be_bytes());
+    pack_domain_name(data, key.algorithm.name())?;
+    push_timers(data, time_signed, fudge);
+    data.extend_from_slice(&error.to_be_bytes());
+    push_mac(data, other);
+    Ok(())
+}
+
+/// Appends the time signed, as 48 bits, and the fudge.
+fn push_timers(data: &mut Vec<u8>, time_signed: u64, fudge: u16) {
+    data.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+    data.extend_from_slice(&fudge.to_be_bytes());
+}
+
+/// Returns `time` in whole seconds since the Unix epoch.
+fn unix_time(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DnsMessage;
+    use std::time::Duration;
+
+    fn key() -> TsigKey {
+        "hmac-sha256:transfer-key:c2VjcmV0LWtleS1mb3ItdGVzdHM="
+            .parse()
+            .unwrap()
+    }
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    fn message(id: u16, flags: u16) -> Vec<u8> {
+        let mut message = DnsMessage::new();
+        message.header.id = id;
+        message.header.flags = flags;
+        message.header.question_count = 1;
+        message.questions.push(DnsQuestion {
+            name: "example.com".parse().unwrap(),
+            qtype: QueryType::AXFR,
+            qclass: 1,
+        });
+        let mut bytes = Vec::new();
+        message.pack(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_parse_keys() {
+        let key = key();
+        assert_eq!(key.secret, b"secret-key-for-tests");
+        assert!(!format!("{:?}", key).contains("secret-key"));
+        assert_eq!(
+            "transfer-key:c2VjcmV0"
+                .parse::<TsigKey>()
+                .unwrap()
+                .algorithm,
+            TsigAlgorithm::HmacSha256
+        );
+        for invalid in [
+            "c2VjcmV0",
+            "hmac-md5:transfer-key:c2VjcmV0",
+            "transfer-key:not base64",
+            "a:b:c:d",
+        ] {
+            assert!(invalid.parse::<TsigKey>().is_err(), "{}", invalid);
+        }
+
+        let file = "# generated by tsig-keygen\nkey \"transfer-key\" {\n\talgorithm hmac-sha256;\n\tsecret \"c2VjcmV0LWtleS1mb3ItdGVzdHM=\";\n};\n";
+        assert_eq!(TsigKey::from_key_file(file).unwrap(), key);
+        assert!(TsigKey::from_key_file("key \"k\" { algorithm hmac-sha256; };").is_err());
+        assert!(TsigKey::from_key_file("options { };").is_err());
+    }
+
+    #[test]
+    fn test_signed_request_matches_reference() {
+        // The MAC computed independently, with Python's hmac module, over the same
+        // request and TSIG variables.
+        let mut request = message(0x1234, 0);
+        TsigSession::sign_request(&key(), &mut request, at(1_700_000_000)).unwrap();
+
+        let parsed = DnsMessage::from_bytes(&request).unwrap();
+        assert_eq!(parsed.header.additional_count, 1);
+        let tsig = &parsed.additionals[0];
+        assert_eq!(
+            (tsig.name.as_str(), tsig.rclass, tsig.ttl),
+            ("transfer-key", 255, 0)
+        );
+        assert_eq!(
+            tsig.data.to_string(),
+            "TSIG hmac-sha256 1700000000 300 32 p+cCgaFpz6WtnDBbJZ5+Fv78n8AMOwQn0Y1n5aVcssM= 4660 NOERROR 0"
+        );
+        let (signed, _) = split_tsig(&request).unwrap().unwrap();
+        assert_eq!(signed, message(0x1234, 0));
+    }
+
+    #[test]
+    fn test_verify_response_stream() {
+        let key = key();
+        let mut request = message(0x1234, 0);
+        let mut session = TsigSession::sign_request(&key, &mut request, at(1_700_000_000)).unwrap();
+        let request_mac = session.previous_mac.clone();
+
+        // The first response covers the request's MAC, and the next signed one the
+        // first response's MAC and the unsigned message in between.
+        let mut first = message(0x1234, 0x8400);
+        let first_mac = sign_response(&key, &mut first, &request_mac, at(1_700_000_001)).unwrap();
+        let unsigned = message(0x1234, 0x8400);
+        let mut last = message(0x1234, 0x8400);
+        let mut data = Vec::new();
+        push_mac(&mut data, &first_mac);
+        data.extend_from_slice(&unsigned);
+        data.extend_from_slice(&last);
+        push_timers(&mut data, 1_700_000_002, DEFAULT_FUDGE);
+        let last_mac = hmac(HashAlgorithm::Sha256, &key.secret, &data);
+        ResourceRecord {
+            name: key.name.parse().unwrap(),
+            rtype: QueryType::TSIG,
+            rclass: TSIG_CLASS,
+            ttl: TSIG_TTL,
+            data: RData::TSIG {
+                algorithm: "hmac-sha256".to_string(),
+                time_signed: 1_700_000_002,
+                fudge: DEFAULT_FUDGE,
+                mac: last_mac,
+                original_id: 0x1234,
+                error: 0,
+                other: Vec::new(),
+            },
+        }
+        .pack(&mut last)
+        .unwrap();
+        last[11] = 1;
+
+        session.verify(&first, at(1_700_000_010)).unwrap();
+        assert!(session.is_complete());
+        session.verify(&unsigned, at(1_700_000_010)).unwrap();
+        assert!(!session.is_complete());
+        session.verify(&last, at(1_700_000_010)).unwrap();
+        assert!(session.is_complete());
+    }
+
+    #[test]
+    fn test_reject_bad_responses() {
+        let key = key();
+        let session =
+            || TsigSession::sign_request(&key, &mut message(0x1234, 0), at(1_700_000_000));
+        let request_mac = session().unwrap().previous_mac;
+
+        let unsigned = message(0x1234, 0x8400);
+        let error = session().unwrap().verify(&unsigned, at(1_700_000_000));
+        assert_eq!(error, Err("the response is not signed".to_string()));
+
+        let mut response = message(0x1234, 0x8400);
+        sign_response(&key, &mut response, &request_mac, at(1_700_000_000)).unwrap();
+        // A flipped bit in the message.
+        let mut tampered = response.clone();
+        tampered[3] ^= 0x01;
+        let error = session().unwrap().verify(&tampered, at(1_700_000_000));
+        assert!(error.unwrap_err().contains("BADSIG"));
+        // A clock too far off.
+        let error = session().unwrap().verify(&response, at(1_700_000_301));
+        assert!(error.unwrap_err().contains("BADTIME"));
+        // Another key.
+        let other: TsigKey = "other-key:c2VjcmV0".parse().unwrap();
+        let mut response = message(0x1234, 0x8400);
+        sign_response(&other, &mut response, &request_mac, at(1_700_000_000)).unwrap();
+        let error = session().unwrap().verify(&response, at(1_700_000_000));
+        assert!(error.unwrap_err().contains("other-key"));
+    }
+}
@@ -0,0 +1,447 @@
+//! Strict protocol validation of DNS responses.
+//!
+//! The regular parser is deliberately lenient: it accepts anything it can make sense
+//! of. That is the right default for everyday lookups, but it hides exactly the kind of
+//! damage that broken middleboxes and suspicious networks introduce. This module checks
+//! a response against the query that produced it and reports every protocol
+//! irregularity it finds, rather than stopping at the first one.
+//!
+//! # Checks
+//!
+//! - The transaction ID, QR bit and opcode match the query
+//! - The reserved Z bit is clear
+//! - The question section echoes the question that was sent
+//! - The header counts describe exactly the records present, with no trailing bytes
+//! - Every name respects the 63-octet label and 255-octet name limits (RFC 1035)
+//! - Every record is in bailiwick for the question (see [`validate_response`])
+//! - There is at most one OPT pseudo-record, owned by the root (RFC 6891)
+//!
+//! # Examples
+//!
+//! ```rust
+//! use dns_resolver::dns::DnsMessage;
+//! use dns_resolver::validation::validate_response;
+//!
+//! let query = DnsMessage::new();
+//! let response_bytes = [0u8; 12]; // QR bit not set
+//!
+//! let violations = validate_response(&query, &response_bytes);
+//! assert!(!violations.is_empty());
+//! ```
+
+use crate::dns::{DnsClass, DnsMessage, QueryType, RData, ResourceRecord};
+
+/// Maximum length of a single label in octets (RFC 1035 section 2.3.4).
+const MAX_LABEL_LENGTH: usize = 63;
+
+/// Maximum length of a complete name in wire format, in octets (RFC 1035 section 2.3.4).
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Checks a raw response against the query that was sent and lists every violation.
+///
+/// Records are considered in bailiwick when:
+/// - **Answers** are owned by the query name or by a name reached through the
+///   CNAME chain that starts at it
+/// - **Authority** records are owned by the query name or one of its ancestors
+/// - **Additional** records are owned by a name referenced from the answer or
+///   authority data, or by a name inside a zone named in the authority section
+///
+/// # Arguments
+///
+/// * `query` - The query message that was sent
+/// * `response_bytes` - The complete response datagram as received
+///
+/// # Returns
+///
+/// A human-readable description of each irregularity found. An empty vector means
+/// the response passed every check.
+///
+/// # Examples
+///
+/// ```rust
+/// use dns_resolver::dns::{DnsMessage, DnsQuestion, QueryType};
+/// use dns_resolver::validation::validate_response;
+///
+/// let mut query = DnsMessage::new();
+/// query.header.id = 0x1234;
+/// query.header.question_count = 1;
+/// query.questions.push(DnsQuestion {
+///     name: "example.com".parse().unwrap(),
+///     qtype: QueryType::A,
+///     qclass: 1,
+/// });
+///
+/// let mut response_bytes = Vec::new();
+/// query.pack(&mut response_bytes).unwrap();
+/// response_bytes[2] |= 0x80; // Set QR: this is now a valid empty response
+///
+/// assert!(validate_response(&query, &response_bytes).is_empty());
+/// ```
+pub fn validate_response(query: &DnsMessage, response_bytes: &[u8]) -> Vec<String> {
+    let (response, consumed) = match DnsMessage::parse_prefix(response_bytes) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            return vec![format!(
+                "message is truncated or its header counts exceed the records present: {}",
+                e
+            )];
+        }
+    };
+
+    let mut violations = Vec::new();
+    check_header(query, &response, &mut violations);
+    check_questions(query, &response, &mut violations);
+
+    if consumed < response_bytes.len() {
+        violations.push(format!(
+            "{} trailing bytes after the last record (header counts may understate the records present)",
+            response_bytes.len() - consumed
+        ));
+    } else if consumed > response_bytes.len() {
+        violations.push("record data extends past the end of the message".to_string());
+    }
+
+    check_name_limits(&response, &mut violations);
+    check_bailiwick(query, &response, &mut violations);
+    check_opt(&response, &mut violations);
+    violations
+}
+
+/// Checks the header fields that must mirror the query.
+fn check_header(query: &DnsMessage, response: &DnsMessage, violations: &mut Vec<String>) {
+    if response.header.id != query.header.id {
+        violations.push(format!(
+            "transaction ID {} does not match query ID {}",
+            response.header.id, query.header.id
+        ));
+    }
+    if !response.header.is_response() {
+        violations.push("QR bit is not set; the message is not a response".to_string());
+    }
+
+    let query_opcode = query.header.opcode();
+    let response_opcode = response.header.opcode();
+    if query_opcode != response_opcode {
+        violations.push(format!(
+            "opcode {} does not match query opcode {}",
+            response_opcode, query_opcode
+        ));
+    }
+
+    if response.header.z() {
+        violations.push("reserved Z bit is set".to_string());
+    }
+}
+
+/// Checks that the question section echoes the questions that were sent.
+fn check_questions(query: &DnsMessage, response: &DnsMessage, violations: &mut Vec<String>) {
+    if response.questions.len() != query.questions.len() {
+        violations.push(format!(
+            "response carries {} questions but {} were sent",
+            response.questions.len(),
+            query.questions.len()
+        ));
+        return;
+    }
+
+    for (sent, echoed) in query.questions.iter().zip(&response.questions) {
+        if sent.name != echoed.name || sent.qtype != echoed.qtype || sent.qclass != echoed.qclass {
+            violations.push(format!(
+                "question mismatch: sent {} {} {}, response echoes {} {} {}",
+                sent.name,
+                DnsClass::from(sent.qclass),
+                sent.qtype,
+                echoed.name,
+                DnsClass::from(echoed.qclass),
+                echoed.qtype
+            ));
+        }
+    }
+}
+
+/// Checks every name in the message against the RFC 1035 length limits.
+fn check_name_limits(response: &DnsMessage, violations: &mut Vec<String>) {
+    let question_names = response.questions.iter().map(|q| q.name.as_str());
+    let record_names = all_records(response)
+        .flat_map(|record| std::iter::once(record.name.as_str()).chain(rdata_name(record)));
+
+    for name in question_names.chain(record_names) {
+        for label in name.split('.').filter(|label| !label.is_empty()) {
+            if label.len() > MAX_LABEL_LENGTH {
+                violations.push(format!(
+                    "label '{}' in {} is {} octets, over the {}-octet limit",
+                    label,
+                    name,
+                    label.len(),
+                    MAX_LABEL_LENGTH
+                ));
+            }
+        }
+
+        let wire_length = name_wire_length(name);
+        if wire_length > MAX_NAME_LENGTH {
+            violations.push(format!(
+                "name {} is {} octets, over the {}-octet limit",
+                name, wire_length, MAX_NAME_LENGTH
+            ));
+        }
+    }
+}
+
+/// Checks that every record belongs to the part of the namespace the question asked about.
+fn check_bailiwick(query: &DnsMessage, response: &DnsMessage, violations: &mut Vec<String>) {
+    let Some(question) = query.questions.first() else {
+        return;
+    };
+
+    // Follow the CNAME chain from the query name to find every name an answer may own.
+    let mut answer_owners = vec![question.name.to_string()];
+    loop {
+        let next = response
+            .answers
+            .iter()
+            .find_map(|record| match &record.data {
+                RData::CNAME(target)
+                    if names_equal(&record.name, answer_owners.last().unwrap())
+                        && !answer_owners.iter().any(|seen| names_equal(seen, target)) =>
+                {
+                    Some(target.clone())
+                }
+                _ => None,
+            });
+        match next {
+            Some(target) => answer_owners.push(target),
+            None => break,
+        }
+    }
+
+    for record in &response.answers {
+        if !answer_owners
+            .iter()
+            .any(|owner| names_equal(owner, &record.name))
+        {
+            violations.push(format!(
+                "out-of-bailiwick answer for {} (expected {} or its CNAME chain)",
+                record.name, question.name
+            ));
+        }
+    }
+
+    for record in &response.authorities {
+        if !is_subdomain(&question.name, &record.name) {
+            violations.push(format!(
+                "out-of-bailiwick authority record for {} (not an ancestor of {})",
+                record.name, question.name
+            ));
+        }
+    }
+
+    let referenced: Vec<&str> = response
+        .answers
+        .iter()
+        .chain(&response.authorities)
+        .filter_map(rdata_name)
+        .collect();
+    // The OPT and TSIG pseudo-records are not data, so bailiwick does not apply to
+    // them. A TSIG record is owned by its key name, which nothing references.
+    for record in response
+        .additionals
+        .iter()
+        .filter(|record| !matches!(record.rtype, QueryType::OPT | QueryType::TSIG))
+    {
+        let is_referenced = referenced
+            .iter()
+            .any(|name| names_equal(name, &record.name));
+        let in_authority_zone = response
+            .authorities
+            .iter()
+            .any(|zone| is_subdomain(&record.name, &zone.name));
+        if !is_referenced && !in_authority_zone {
+            violations.push(format!(
+                "out-of-bailiwick additional record for {} (not referenced by the response)",
+                record.name
+            ));
+        }
+    }
+}
+
+/// Checks that an OPT pseudo-record appears at most once, in the additional section,
+/// owned by the root (RFC 6891 section 6.1.1).
+fn check_opt(response: &DnsMessage, violations: &mut Vec<String>) {
+    let opt_records: Vec<&ResourceRecord> = all_records(response)
+        .filter(|record| record.rtype == QueryType::OPT)
+        .collect();
+    if opt_records.len() > 1 {
+        violations.push(format!(
+            "{} OPT records (at most one is allowed)",
+            opt_records.len()
+        ));
+    }
+    if response
+        .answers
+        .iter()
+        .chain(&response.authorities)
+        .any(|record| record.rtype == QueryType::OPT)
+    {
+        violations.push("OPT record outside the additional section".to_string());
+    }
+    for record in opt_records {
+        if !record.name.is_empty() && record.name != "." {
+            violations.push(format!(
+                "OPT record owned by {} instead of the root",
+                record.name
+            ));
+        }
+    }
+}
+
+/// Iterates over the records of all three resource record sections.
+fn all_records(message: &DnsMessage) -> impl Iterator<Item = &ResourceRecord> {
+    message
+        .answers
+        .iter()
+        .chain(&message.authorities)
+        .chain(&message.additionals)
+}
+
+/// Returns the domain name embedded in a record's data, if its type carries one.
+fn rdata_name(record: &ResourceRecord) -> Option<&str> {
+    match &record.data {
+        RData::CNAME(name) | RData::PTR(name) | RData::NS(name) => Some(name),
+        RData::MX { exchange, .. } => Some(exchange),
+        RData::SRV { target, .. } | RData::SVCB { target, .. } | RData::HTTPS { target, .. } => {
+            Some(target)
+        }
+        _ => None,
+    }
+}
+
+/// Length of a presentation-format name once encoded as labels on the wire.
+fn name_wire_length(name: &str) -> usize {
+    name.split('.')
+        .filter(|label| !label.is_empty())
+        .map(|label| label.len() + 1)
+        .sum::<usize>()
+        + 1
+}
+
+/// Compares two names case-insensitively, ignoring a trailing root dot.
+pub(crate) fn names_equal(a: &str, b: &str) -> bool {
+    a.trim_end_matches('.')
+        .eq_ignore_ascii_case(b.trim_end_matches('.'))
+}
+
+/// Whether `name` equals `zone` or lies below it.
+pub(crate) fn is_subdomain(name: &str, zone: &str) -> bool {
+    let name = name.trim_end_matches('.').to_ascii_lowercase();
+    let zone = zone.trim_end_matches('.').to_ascii_lowercase();
+    zone.is_empty() || name == zone || name.ends_with(&format!(".{}", zone))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::{DnsQuestion, Edns, pack_domain_name};
+
+    fn query() -> DnsMessage {
+        let mut message = DnsMessage::new();
+        message.header.id = 0x1234;
+        message.header.flags = 0x0100;
+        message.header.question_count = 1;
+        message.questions.push(DnsQuestion {
+            name: "www.example.com".parse().unwrap(),
+            qtype: QueryType::A,
+            qclass: 1,
+        });
+        message
+    }
+
+    /// Builds a response to `query()` with one A record owned by `owner`.
+    fn response_with_answer(owner: &str) -> Vec<u8> {
+        let mut bytes = vec![0x12, 0x34, 0x81, 0x80, 0, 1, 0, 1, 0, 0, 0, 0];
+        pack_domain_name(&mut bytes, "www.example.com").unwrap();
+        bytes.extend_from_slice(&[0, 1, 0, 1]);
+        pack_domain_name(&mut bytes, owner).unwrap();
+        bytes.extend_from_slice(&[0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 192, 0, 2, 1]);
+        bytes
+    }
+
+    #[test]
+    fn test_clean_response_passes() {
+        let violations = validate_response(&query(), &response_with_answer("www.example.com"));
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_case_randomized_echo_passes() {
+        let violations = validate_response(&query(), &response_with_answer("WwW.ExAmPlE.cOm"));
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn test_header_mismatches_are_reported() {
+        let mut bytes = response_with_answer("www.example.com");
+        bytes[1] = 0x35; // Wrong ID
+        bytes[2] = 0x01; // QR cleared
+        bytes[3] |= 0x40; // Z bit set
+        let violations = validate_response(&query(), &bytes);
+        assert_eq!(violations.len(), 3, "{:?}", violations);
+    }
+
+    #[test]
+    fn test_trailing_bytes_are_reported() {
+        let mut bytes = response_with_answer("www.example.com");
+        bytes.extend_from_slice(&[0xDE, 0xAD]);
+        let violations = validate_response(&query(), &bytes);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("2 trailing bytes"));
+    }
+
+    #[test]
+    fn test_overstated_counts_are_reported() {
+        let mut bytes = response_with_answer("www.example.com");
+        bytes[7] = 2; // Claim two answers
+        let violations = validate_response(&query(), &bytes);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("header counts exceed"));
+    }
+
+    #[test]
+    fn test_out_of_bailiwick_answer_is_reported() {
+        let violations = validate_response(&query(), &response_with_answer("bank.example.net"));
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("out-of-bailiwick answer"));
+    }
+
+    #[test]
+    fn test_question_mismatch_is_reported() {
+        let mut other = query();
+        other.questions[0].name = "mail.example.com".parse().unwrap();
+        let violations = validate_response(&other, &response_with_answer("mail.example.com"));
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].starts_with("question mismatch"));
+    }
+
+    #[test]
+    fn test_opt_records_are_checked() {
+        let mut bytes = response_with_answer("www.example.com");
+        bytes[11] = 1;
+        Edns::new(1232).to_record().pack(&mut bytes).unwrap();
+        assert!(validate_response(&query(), &bytes).is_empty());
+
+        bytes[11] = 2;
+        let mut stray = Edns::new(1232).to_record();
+        stray.name = "example.com".parse().unwrap();
+        stray.pack(&mut bytes).unwrap();
+        let violations = validate_response(&query(), &bytes);
+        assert_eq!(violations.len(), 2, "{:?}", violations);
+    }
+
+    #[test]
+    fn test_name_wire_length() {
+        assert_eq!(name_wire_length("www.example.com"), 17);
+        assert_eq!(name_wire_length(""), 1);
+        assert!(is_subdomain("www.example.com", "example.com"));
+        assert!(!is_subdomain("www.badexample.com", "example.com"));
+    }
+}
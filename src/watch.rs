@@ -0,0 +1,258 @@
+//! Watching a record set for changes.
+//!
+//! Services that follow DNS-published endpoints (service records, load balancer
+//! addresses, failover aliases) need to notice when the answer changes, but should
+//! not hammer their resolver to find out. A [`Watch`] re-queries a name whenever the
+//! previous answer's TTL runs out and yields a [`RecordChange`] only when the set of
+//! records differs from the last one seen. TTL changes alone are not reported.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use dns_resolver::dns::QueryType;
+//! use dns_resolver::resolver::{QueryOptions, Resolver};
+//! use std::net::Ipv4Addr;
+//!
+//! let resolver = Resolver::new(Ipv4Addr::new(8, 8, 8, 8), QueryOptions::default());
+//! for change in resolver.watch("api.example.com", QueryType::A).take(3) {
+//!     match change {
+//!         Ok(change) => {
+//!             for record in &change.added {
+//!                 println!("+ {}", record);
+//!             }
+//!             for record in &change.removed {
+//!                 println!("- {}", record);
+//!             }
+//!         }
+//!         Err(e) => eprintln!("lookup failed, will retry: {}", e),
+//!     }
+//! }
+//! ```
+
+use std::thread;
+use std::time::Duration;
+
+use crate::dns::{QueryType, ResourceRecord, ResponseCode};
+use crate::resolver::{DnsError, Resolver};
+
+/// How often a [`Watch`] may re-query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    /// The shortest wait between queries, however small the TTL.
+    pub min_interval: Duration,
+    /// The longest wait between queries, however large the TTL.
+    pub max_interval: Duration,
+    /// How long to wait after a failed query before trying again.
+    pub error_interval: Duration,
+}
+
+impl Default for WatchOptions {
+    /// Follow the TTL, but re-query at least hourly and at most every second, and
+    /// retry failed queries after five seconds.
+    fn default() -> Self {
+        WatchOptions {
+            min_interval: Duration::from_secs(1),
+            max_interval: Duration::from_secs(60 * 60),
+            error_interval: Duration::from_secs(5),
+        }
+    }
+}
+
+/// A change in the answer to a watched query.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordChange {
+    /// Records present now that were not present before.
+    pub added: Vec<ResourceRecord>,
+    /// Records present before that are gone now.
+    pub removed: Vec<ResourceRecord>,
+    /// The complete current answer.
+    pub records: Vec<ResourceRecord>,
+}
+
+/// A blocking iterator over the changes to one name and record type.
+///
+/// The first item reports the initial answer, with every record as added. Each
+/// later call to [`Iterator::next`] sleeps until the current answer expires,
+/// re-queries, and repeats until the answer changes or a query fails. Failed queries
+/// are yielded as errors and retried after [`WatchOptions::error_interval`]; the
+/// iterator never ends on its own.
+#[derive(Debug)]
+pub struct Watch {
+    resolver: Resolver,
+    name: String,
+    query_type: QueryType,
+    options: WatchOptions,
+    /// The last answer seen, or `None` before the first successful query.
+    current: Option<Vec<ResourceRecord>>,
+    /// How long to wait before the next query.
+    wait: Duration,
+}
+
+impl Watch {
+    /// Creates a watch that queries through `resolver`.
+    pub fn new(
+        resolver: Resolver,
+        name: &str,
+        query_type: QueryType,
+        options: WatchOptions,
+    ) -> Self {
+        Watch {
+            resolver,
+            name: name.to_string(),
+            query_type,
+            options,
+            current: None,
+            wait: Duration::ZERO,
+        }
+    }
+}
+
+impl Iterator for Watch {
+    type Item = Result<RecordChange, DnsError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            thread::sleep(self.wait);
+            let records = match self.resolver.query(&self.name, self.query_type) {
+                // A name that stops existing is reported as all of its records removed.
+                Ok(result)
+                    if matches!(
+                        result.message.header.get_response_code(),
+                        ResponseCode::NoError | ResponseCode::NameError
+                    ) =>
+                {
+                    result.message.answers
+                }
+                Ok(result) => {
+                    self.wait = self.options.error_interval;
+                    return Some(Err(DnsError::ServerReturnedError(
+                        result.message.header.get_response_code(),
+                    )));
+                }
+                Err(e) => {
+                    self.wait = self.options.error_interval;
+                    return Some(Err(e));
+                }
+            };
+            self.wait = next_interval(&records, &self.options);
+
+            let first = self.current.is_none();
+            let previous = self.current.replace(records.clone()).unwrap_or_default();
+            let (added, removed) = diff_records(&previous, &records);
+            if first || !added.is_empty() || !removed.is_empty() {
+                return Some(Ok(RecordChange {
+                    added,
+                    removed,
+                    records,
+                }));
+            }
+        }
+    }
+}
+
+/// Returns how long to wait before re-querying an answer made of `records`.
+///
+/// This is the smallest TTL in the answer, kept within the watch's bounds. An empty
+/// answer has no TTL to go by, so it is checked again after the minimum interval.
+fn next_interval(records: &[ResourceRecord], options: &WatchOptions) -> Duration {
+    records
+        .iter()
+        .map(|record| Duration::from_secs(record.ttl.into()))
+        .min()
+        .unwrap_or(options.min_interval)
+        .clamp(
+            options.min_interval,
+            options.max_interval.max(options.min_interval),
+        )
+}
+
+/// Compares two answers, ignoring TTLs and the case of owner names.
+///
+/// Returns the records only in `new` and the records only in `old`.
+fn diff_records(
+    old: &[ResourceRecord],
+    new: &[ResourceRecord],
+) -> (Vec<ResourceRecord>, Vec<ResourceRecord>) {
+    let same = |a: &ResourceRecord, b: &ResourceRecord| {
+        a.name.eq_ignore_ascii_case(&b.name)
+            && a.rtype == b.rtype
+            && a.rclass == b.rclass
+            && a.data == b.data
+    };
+    let added = new
+        .iter()
+        .filter(|record| !old.iter().any(|other| same(record, other)))
+        .cloned()
+        .collect();
+    let removed = old
+        .iter()
+        .filter(|record| !new.iter().any(|other| same(record, other)))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::a_record;
+
+    #[test]
+    fn test_diff_ignores_ttl() {
+        let old = vec![
+            a_record("api.example.com", [192, 0, 2, 1], 300),
+            a_record("api.example.com", [192, 0, 2, 2], 300),
+        ];
+        let new = vec![
+            a_record("api.example.com", [192, 0, 2, 2], 120),
+            a_record("api.example.com", [192, 0, 2, 3], 300),
+        ];
+        let (added, removed) = diff_records(&old, &new);
+        assert_eq!(
+            added,
+            vec![a_record("api.example.com", [192, 0, 2, 3], 300)]
+        );
+        assert_eq!(
+            removed,
+            vec![a_record("api.example.com", [192, 0, 2, 1], 300)]
+        );
+
+        let (added, removed) = diff_records(
+            &old,
+            &[
+                a_record("api.example.com", [192, 0, 2, 2], 5),
+                a_record("api.example.com", [192, 0, 2, 1], 5),
+            ],
+        );
+        assert!(added.is_empty() && removed.is_empty());
+    }
+
+    #[test]
+    fn test_interval_follows_smallest_ttl_within_bounds() {
+        let options = WatchOptions {
+            min_interval: Duration::from_secs(10),
+            max_interval: Duration::from_secs(600),
+            ..WatchOptions::default()
+        };
+        let interval = next_interval(
+            &[
+                a_record("api.example.com", [192, 0, 2, 1], 300),
+                a_record("api.example.com", [192, 0, 2, 2], 60),
+            ],
+            &options,
+        );
+        assert_eq!(interval, Duration::from_secs(60));
+        assert_eq!(
+            next_interval(&[a_record("api.example.com", [192, 0, 2, 1], 0)], &options),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            next_interval(
+                &[a_record("api.example.com", [192, 0, 2, 1], 86400)],
+                &options
+            ),
+            Duration::from_secs(600)
+        );
+        assert_eq!(next_interval(&[], &options), Duration::from_secs(10));
+    }
+}